@@ -0,0 +1,73 @@
+//! Admin listener for operational endpoints.
+//!
+//! Profiling and other operator-only endpoints are served on a separate
+//! listener (e.g. `0.0.0.0:9090`) that is reachable from the cluster network
+//! but not exposed through the ingress, instead of being mixed with the
+//! public API routes. Configured with `EywaApp::admin_listener` and
+//! `EywaApp::admin_routes`, and started by `EywaApp::serve`.
+//!
+//! - `pprof` - CPU and heap profiling endpoints (`pprof` feature)
+
+use axum::Router;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+#[cfg(feature = "pprof")]
+pub mod pprof;
+
+/// Routes served on the admin listener.
+#[derive(Debug, Default)]
+pub struct AdminListener {
+    addr: Option<String>,
+    router: Router,
+}
+
+impl AdminListener {
+    /// Create a listener without address or routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the address the admin routes are served on.
+    pub fn bind(&mut self, addr: impl Into<String>) {
+        self.addr = Some(addr.into());
+    }
+
+    /// Add admin routes.
+    pub fn merge(&mut self, router: Router) {
+        self.router = std::mem::take(&mut self.router).merge(router);
+    }
+
+    /// The admin routes, e.g. for an in-process test client.
+    pub fn router(&self) -> Router {
+        self.router.clone()
+    }
+
+    /// Bind the listener and serve the admin routes in the background.
+    ///
+    /// Without an address the admin routes are not served.
+    pub(crate) async fn spawn(self) -> Result<()> {
+        let Some(addr) = self.addr else {
+            if self.router.has_routes() {
+                tracing::warn!("Admin routes registered without an admin listener are not served");
+            }
+            return Ok(());
+        };
+
+        let listener = TcpListener::bind(&addr).await.map_err(|e| {
+            AppError::InternalServerError(format!("Admin listener bind failed: {e}"))
+        })?;
+        info!("🛠️ Admin listener on http://{}", addr);
+
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, self.router.into_make_service()).await {
+                error!("❌ Admin listener stopped: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! CPU and heap profiling endpoints (`pprof` feature).
+//!
+//! - `GET /debug/pprof/profile?seconds=30&format=pprof|flamegraph` samples
+//!   the CPU for the given duration and returns a pprof protobuf (for
+//!   `go tool pprof` / Grafana Pyroscope) or an SVG flamegraph
+//! - `GET /debug/pprof/heap` returns a jemalloc heap profile in pprof format
+//!   (`pprof-heap` feature)
+//!
+//! Only one CPU profile runs at a time. The routes are meant for the admin
+//! listener (`EywaApp::pprof`), never for the public one.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use axum::{
+    extract::Query,
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use pprof::protos::Message;
+use serde::Deserialize;
+
+use eywa_errors::AppError;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::Result;
+
+/// Path of the CPU profile endpoint.
+pub const CPU_PROFILE_PATH: &str = "/debug/pprof/profile";
+
+/// Path of the heap profile endpoint.
+pub const HEAP_PROFILE_PATH: &str = "/debug/pprof/heap";
+
+/// Longest CPU profile, in seconds.
+const MAX_PROFILE_SECONDS: u64 = 300;
+
+/// Sampling frequency of CPU profiles, in Hz.
+const PROFILE_FREQUENCY: i32 = 99;
+
+static PROFILING: AtomicBool = AtomicBool::new(false);
+
+/// Output format of a CPU profile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileFormat {
+    /// pprof protobuf
+    #[default]
+    Pprof,
+    /// SVG flamegraph
+    Flamegraph,
+}
+
+/// Query parameters of the CPU profile endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileParams {
+    #[serde(default = "default_seconds")]
+    pub seconds: u64,
+    #[serde(default)]
+    pub format: ProfileFormat,
+}
+
+fn default_seconds() -> u64 {
+    30
+}
+
+/// Router serving the profiling endpoints.
+pub fn router() -> Router {
+    let router = Router::new().route(CPU_PROFILE_PATH, get(cpu_profile));
+    #[cfg(feature = "pprof-heap")]
+    let router = router.route(HEAP_PROFILE_PATH, get(heap_profile));
+    router
+}
+
+/// Resets the running-profile flag, even if the request is dropped.
+struct ProfilingGuard;
+
+impl Drop for ProfilingGuard {
+    fn drop(&mut self) {
+        PROFILING.store(false, Ordering::Release);
+    }
+}
+
+fn profile_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Profiling failed: {e}"))
+}
+
+/// Sample the CPU on a blocking thread (the profiler guard isn't `Send`).
+fn sample(params: ProfileParams) -> Result<Vec<u8>> {
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(PROFILE_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(profile_error)?;
+    std::thread::sleep(Duration::from_secs(params.seconds));
+    let report = guard.report().build().map_err(profile_error)?;
+
+    match params.format {
+        ProfileFormat::Flamegraph => {
+            let mut svg = Vec::new();
+            report.flamegraph(&mut svg).map_err(profile_error)?;
+            Ok(svg)
+        }
+        ProfileFormat::Pprof => Ok(report.pprof().map_err(profile_error)?.encode_to_vec()),
+    }
+}
+
+async fn cpu_profile(Query(params): Query<ProfileParams>) -> Result<Response> {
+    if !(1..=MAX_PROFILE_SECONDS).contains(&params.seconds) {
+        return Err(AppError::BadRequest(format!(
+            "seconds must be between 1 and {MAX_PROFILE_SECONDS}"
+        )));
+    }
+    if PROFILING.swap(true, Ordering::AcqRel) {
+        return Ok(
+            ErrorResponse::new(error_codes::CONFLICT, "A CPU profile is already running")
+                .into_response_with(StatusCode::CONFLICT),
+        );
+    }
+    let _guard = ProfilingGuard;
+
+    tracing::info!("📊 CPU profile started for {}s", params.seconds);
+    let format = params.format;
+    let body = tokio::task::spawn_blocking(move || sample(params))
+        .await
+        .map_err(profile_error)??;
+
+    Ok(match format {
+        ProfileFormat::Flamegraph => ([(CONTENT_TYPE, "image/svg+xml")], body).into_response(),
+        ProfileFormat::Pprof => (
+            [
+                (CONTENT_TYPE, "application/octet-stream"),
+                (CONTENT_DISPOSITION, "attachment; filename=\"profile.pb\""),
+            ],
+            body,
+        )
+            .into_response(),
+    })
+}
+
+/// Dump the jemalloc heap profile.
+///
+/// The service must use `tikv-jemallocator` as global allocator, with
+/// profiling enabled (`_RJEM_MALLOC_CONF=prof:true,prof_active:true`).
+#[cfg(feature = "pprof-heap")]
+async fn heap_profile() -> Result<Response> {
+    let Some(ctl) = jemalloc_pprof::PROF_CTL.as_ref() else {
+        return Err(AppError::BadRequest(
+            "jemalloc heap profiling is not enabled".to_string(),
+        ));
+    };
+    let mut ctl = ctl.lock().await;
+    if !ctl.activated() {
+        return Err(AppError::BadRequest(
+            "jemalloc heap profiling is not active".to_string(),
+        ));
+    }
+    let body = ctl.dump_pprof().map_err(profile_error)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/octet-stream"),
+            (CONTENT_DISPOSITION, "attachment; filename=\"heap.pb\""),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    #[tokio::test]
+    async fn test_invalid_duration_rejected() {
+        let client = TestClient::new(router());
+
+        let response = client.get("/debug/pprof/profile?seconds=0").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        let response = client.get("/debug/pprof/profile?format=svg").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
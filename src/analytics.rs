@@ -0,0 +1,482 @@
+//! Usage analytics export.
+//!
+//! The analytics middleware records one `AnalyticsRecord` per request (route,
+//! status, latency, tenant, API key, bytes) and hands it to a background task
+//! that ships batches to a sink, without waiting on the sink in the request
+//! path. Records are buffered in a bounded channel: when the sink can't keep
+//! up, new records are dropped and counted (`eywa_analytics_dropped_total`)
+//! rather than growing memory.
+//!
+//! Sinks:
+//! - `ClickHouseSink` - `INSERT ... FORMAT JSONEachRow` over the HTTP interface
+//! - `KafkaRestSink` - Produce to a topic through a Kafka REST proxy
+//!
+//! ```toml
+//! [analytics]
+//! batch_size = 500
+//! flush_interval_ms = 5000
+//! buffer = 10000
+//! sink = { type = "clickhouse", url = "http://clickhouse:8123", table = "usage.requests" }
+//! # sink = { type = "kafka_rest", url = "http://kafka-rest:8082", topic = "usage-requests" }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use eywa_errors::AppError;
+
+use crate::middleware::rate_limit::TENANT_HEADER;
+use crate::Result;
+
+/// Route recorded for requests that matched no route.
+pub const UNMATCHED_ROUTE: &str = "<unmatched>";
+
+/// One request, as exported to the analytics sink.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsRecord {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    /// Route template (`/api/v1/projects/{id}`), not the concrete path
+    pub route: String,
+    pub status: u16,
+    pub latency_ms: f64,
+    pub tenant: Option<String>,
+    /// Prefix of the API key (never the key itself)
+    pub api_key: Option<String>,
+    pub request_bytes: Option<u64>,
+    pub response_bytes: Option<u64>,
+}
+
+/// Destination of analytics batches.
+#[async_trait]
+pub trait AnalyticsSink: Send + Sync + 'static {
+    /// Ship a batch of records.
+    async fn send(&self, batch: &[AnalyticsRecord]) -> Result<()>;
+}
+
+fn sink_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Analytics sink failed: {e}"))
+}
+
+/// ClickHouse HTTP interface sink.
+#[derive(Debug, Clone)]
+pub struct ClickHouseSink {
+    client: reqwest::Client,
+    url: String,
+    table: String,
+    credentials: Option<(String, String)>,
+}
+
+impl ClickHouseSink {
+    /// Insert into `table` through the HTTP interface at `url`.
+    pub fn new(client: reqwest::Client, url: impl Into<String>, table: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            table: table.into(),
+            credentials: None,
+        }
+    }
+
+    /// Authenticate as `user`.
+    pub fn credentials(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for ClickHouseSink {
+    async fn send(&self, batch: &[AnalyticsRecord]) -> Result<()> {
+        let mut body = String::new();
+        for record in batch {
+            body.push_str(&serde_json::to_string(record).map_err(sink_error)?);
+            body.push('\n');
+        }
+
+        let query = format!("INSERT INTO {} FORMAT JSONEachRow", self.table);
+        let mut request = self
+            .client
+            .post(&self.url)
+            .query(&[
+                ("query", query.as_str()),
+                ("date_time_input_format", "best_effort"),
+            ])
+            .body(body);
+        if let Some((user, password)) = &self.credentials {
+            request = request.basic_auth(user, Some(password));
+        }
+        request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(sink_error)?;
+        Ok(())
+    }
+}
+
+/// Kafka sink producing through a Kafka REST proxy (v2 API).
+///
+/// Records are keyed by tenant, so a tenant's records stay in one partition.
+#[derive(Debug, Clone)]
+pub struct KafkaRestSink {
+    client: reqwest::Client,
+    url: String,
+    topic: String,
+}
+
+impl KafkaRestSink {
+    /// Produce to `topic` through the REST proxy at `url`.
+    pub fn new(client: reqwest::Client, url: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+            topic: topic.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for KafkaRestSink {
+    async fn send(&self, batch: &[AnalyticsRecord]) -> Result<()> {
+        let records: Vec<_> = batch
+            .iter()
+            .map(|record| serde_json::json!({ "key": record.tenant, "value": record }))
+            .collect();
+
+        self.client
+            .post(format!("{}/topics/{}", self.url.trim_end_matches('/'), self.topic))
+            .header("content-type", "application/vnd.kafka.json.v2+json")
+            .json(&serde_json::json!({ "records": records }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(sink_error)?;
+        Ok(())
+    }
+}
+
+/// Sink selected from config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkSettings {
+    Clickhouse {
+        url: String,
+        table: String,
+        #[serde(default)]
+        user: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    KafkaRest {
+        url: String,
+        topic: String,
+    },
+}
+
+impl SinkSettings {
+    /// Build the configured sink.
+    pub fn build(&self, client: reqwest::Client) -> Arc<dyn AnalyticsSink> {
+        match self {
+            Self::Clickhouse {
+                url,
+                table,
+                user,
+                password,
+            } => {
+                let sink = ClickHouseSink::new(client, url, table);
+                match user {
+                    Some(user) => {
+                        Arc::new(sink.credentials(user, password.clone().unwrap_or_default()))
+                    }
+                    None => Arc::new(sink),
+                }
+            }
+            Self::KafkaRest { url, topic } => Arc::new(KafkaRestSink::new(client, url, topic)),
+        }
+    }
+}
+
+/// Analytics pipeline settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsSettings {
+    pub sink: SinkSettings,
+    /// Records per batch
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest time a record waits for its batch to fill
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    /// Records buffered while the sink is busy; more are dropped
+    #[serde(default = "default_buffer")]
+    pub buffer: usize,
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval_ms() -> u64 {
+    5000
+}
+
+fn default_buffer() -> usize {
+    10_000
+}
+
+/// Handle to the analytics pipeline.
+///
+/// # Example
+///
+/// ```ignore
+/// let analytics = Analytics::from_settings(&config.analytics, reqwest::Client::new());
+///
+/// EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .analytics(analytics)
+/// ```
+#[derive(Debug, Clone)]
+pub struct Analytics {
+    sender: mpsc::Sender<AnalyticsRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Analytics {
+    /// Start the pipeline with the sink from the settings.
+    ///
+    /// Must be called from a tokio runtime.
+    pub fn from_settings(settings: &AnalyticsSettings, client: reqwest::Client) -> Self {
+        Self::spawn(settings, settings.sink.build(client))
+    }
+
+    /// Start the pipeline with a custom sink.
+    ///
+    /// The background task flushes the last batch once every handle is
+    /// dropped. Must be called from a tokio runtime.
+    pub fn spawn(settings: &AnalyticsSettings, sink: Arc<dyn AnalyticsSink>) -> Self {
+        let (sender, receiver) = mpsc::channel(settings.buffer.max(1));
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        tokio::spawn(export(
+            receiver,
+            sink,
+            settings.batch_size.max(1),
+            Duration::from_millis(settings.flush_interval_ms),
+            dropped.clone(),
+        ));
+        Self { sender, dropped }
+    }
+
+    /// Queue a record, dropping it if the buffer is full.
+    pub fn record(&self, record: AnalyticsRecord) {
+        if self.sender.try_send(record).is_err() {
+            count_dropped(&self.dropped, 1);
+        }
+    }
+
+    /// Records dropped so far (buffer full or sink failure).
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+fn count_dropped(dropped: &AtomicU64, count: u64) {
+    dropped.fetch_add(count, Ordering::Relaxed);
+    metrics::counter!("eywa_analytics_dropped_total").increment(count);
+}
+
+/// Ship batches when full or when the flush interval elapses.
+async fn export(
+    mut receiver: mpsc::Receiver<AnalyticsRecord>,
+    sink: Arc<dyn AnalyticsSink>,
+    batch_size: usize,
+    flush_interval: Duration,
+    dropped: Arc<AtomicU64>,
+) {
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut deadline = tokio::time::Instant::now() + flush_interval;
+
+    loop {
+        match tokio::time::timeout_at(deadline, receiver.recv()).await {
+            Ok(Some(record)) => {
+                batch.push(record);
+                if batch.len() < batch_size {
+                    continue;
+                }
+            }
+            Ok(None) => {
+                flush(sink.as_ref(), &mut batch, &dropped).await;
+                return;
+            }
+            Err(_) => {}
+        }
+        flush(sink.as_ref(), &mut batch, &dropped).await;
+        deadline = tokio::time::Instant::now() + flush_interval;
+    }
+}
+
+async fn flush(sink: &dyn AnalyticsSink, batch: &mut Vec<AnalyticsRecord>, dropped: &AtomicU64) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = sink.send(batch).await {
+        tracing::warn!("Dropping {} analytics records: {:?}", batch.len(), e);
+        count_dropped(dropped, batch.len() as u64);
+    }
+    batch.clear();
+}
+
+fn header(req: &Request, name: &str) -> Option<String> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Prefix of the request's API key, if it is an EYWA key.
+#[cfg(feature = "api-keys")]
+fn api_key_prefix(req: &Request) -> Option<String> {
+    let key = req
+        .headers()
+        .get(crate::middleware::rate_limit::API_KEY_HEADER)?
+        .to_str()
+        .ok()?;
+    crate::api_keys::parse_prefix(key).map(str::to_string)
+}
+
+#[cfg(not(feature = "api-keys"))]
+fn api_key_prefix(_req: &Request) -> Option<String> {
+    None
+}
+
+/// Middleware recording every request in the analytics pipeline.
+///
+/// Installed by `EywaApp::analytics()`.
+pub async fn analytics_middleware(
+    State(analytics): State<Analytics>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let timestamp = Utc::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+        .to_string();
+    let tenant = header(&req, TENANT_HEADER);
+    let api_key = api_key_prefix(&req);
+    let request_bytes = header(&req, CONTENT_LENGTH.as_str()).and_then(|value| value.parse().ok());
+
+    let response = next.run(req).await;
+
+    analytics.record(AnalyticsRecord {
+        timestamp,
+        method,
+        route,
+        status: response.status().as_u16(),
+        latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        tenant,
+        api_key,
+        request_bytes,
+        response_bytes: response.body().size_hint().exact(),
+    });
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<Vec<AnalyticsRecord>>>);
+
+    #[async_trait]
+    impl AnalyticsSink for MemorySink {
+        async fn send(&self, batch: &[AnalyticsRecord]) -> Result<()> {
+            self.0.lock().unwrap().push(batch.to_vec());
+            Ok(())
+        }
+    }
+
+    fn settings(batch_size: usize) -> AnalyticsSettings {
+        serde_json::from_value(serde_json::json!({
+            "sink": { "type": "kafka_rest", "url": "http://localhost:8082", "topic": "usage" },
+            "batch_size": batch_size,
+            "flush_interval_ms": 50,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_exported_in_batches() {
+        let sink = Arc::new(MemorySink::default());
+        let analytics = Analytics::spawn(&settings(2), sink.clone());
+        let client = TestClient::new(
+            Router::new()
+                .route("/projects/{id}", get(|| async { "project" }))
+                .layer(axum::middleware::from_fn_with_state(
+                    analytics,
+                    analytics_middleware,
+                )),
+        );
+
+        for id in 1..=3 {
+            client
+                .get(&format!("/projects/{id}"))
+                .header(TENANT_HEADER, "acme")
+                .send()
+                .await;
+        }
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        let batches = sink.0.lock().unwrap();
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+        let record = &batches[0][0];
+        assert_eq!(record.route, "/projects/{id}");
+        assert_eq!(record.status, 200);
+        assert_eq!(record.tenant.as_deref(), Some("acme"));
+        assert_eq!(record.response_bytes, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_drops_records() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let analytics = Analytics {
+            sender,
+            dropped: Arc::default(),
+        };
+        let record = AnalyticsRecord {
+            timestamp: Utc::now(),
+            method: "GET".to_string(),
+            route: UNMATCHED_ROUTE.to_string(),
+            status: 404,
+            latency_ms: 1.0,
+            tenant: None,
+            api_key: None,
+            request_bytes: None,
+            response_bytes: None,
+        };
+
+        analytics.record(record.clone());
+        analytics.record(record);
+        assert_eq!(analytics.dropped(), 1);
+    }
+}
@@ -0,0 +1,616 @@
+//! API key management (`api-keys` feature).
+//!
+//! Keys are stored in an `api_keys` table, hashed with SHA-256: the plain key
+//! is only returned when it is issued or rotated. Each key starts with a short
+//! random prefix (`eywa_3kf9a2xq_...`) stored in clear, which identifies it in
+//! listings and logs and is indexed for lookups.
+//!
+//! - `ApiKeyStore` - Issue, list, rotate, revoke and verify keys
+//! - `ApiKeyAuth` / `api_key_auth_middleware` - Authenticate requests by key
+//! - `ApiKeyController` - Admin endpoints, mounted with `EywaApp::api_key_endpoints`
+//!   and requiring the `api_keys:admin` scope:
+//!   - `POST /api-keys` - Issue a key
+//!   - `GET /api-keys?prefix=...` - List keys, optionally by prefix
+//!   - `POST /api-keys/{id}/rotate` - Replace the secret of a key
+//!   - `DELETE /api-keys/{id}` - Revoke a key
+//!
+//! `ApiKeyStore` is also a rate limiting `TierStore`, resolving the tier of
+//! a verified key from the `tier` column of its id.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    routing::{delete, post},
+    Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use rand::{distr::Alphanumeric, Rng};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, Schema, Set,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use eywa_errors::AppError;
+
+use crate::middleware::rate_limit::{TierStore, API_KEY_HEADER};
+use crate::Result;
+
+/// Scope required to call the API key admin endpoints.
+pub const API_KEYS_ADMIN_SCOPE: &str = "api_keys:admin";
+
+/// Leading segment of every key.
+pub const KEY_PREFIX: &str = "eywa";
+
+/// Length of the random prefix identifying a key.
+const PREFIX_LENGTH: usize = 8;
+
+/// Random bytes in the secret part of a key.
+const SECRET_BYTES: usize = 32;
+
+/// `last_used_at` is only updated when older than this, to avoid a write per request.
+const LAST_USED_RESOLUTION: chrono::TimeDelta = chrono::TimeDelta::minutes(1);
+
+/// sea_orm entity of the `api_keys` table.
+pub mod entity {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "api_keys")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub name: String,
+        #[sea_orm(indexed)]
+        pub prefix: String,
+        /// SHA-256 of the full key, hex encoded
+        pub key_hash: String,
+        /// Rate limiting tier
+        pub tier: Option<String>,
+        /// Space-separated scopes
+        pub scopes: String,
+        pub created_at: DateTimeUtc,
+        pub expires_at: Option<DateTimeUtc>,
+        pub revoked_at: Option<DateTimeUtc>,
+        pub last_used_at: Option<DateTimeUtc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// API key issuance request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssueApiKey {
+    pub name: String,
+    #[serde(default)]
+    pub tier: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// API key metadata (never includes the key itself)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyInfo {
+    pub id: Uuid,
+    pub name: String,
+    pub prefix: String,
+    pub tier: Option<String>,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyInfo {
+    /// Returns `true` if the key is neither revoked nor expired.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+impl From<entity::Model> for ApiKeyInfo {
+    fn from(model: entity::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            prefix: model.prefix,
+            tier: model.tier,
+            scopes: model.scopes.split_whitespace().map(str::to_string).collect(),
+            created_at: model.created_at,
+            expires_at: model.expires_at,
+            revoked_at: model.revoked_at,
+            last_used_at: model.last_used_at,
+        }
+    }
+}
+
+/// A newly issued or rotated key; the only time the key is returned
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IssuedApiKey {
+    pub key: String,
+    pub api_key: ApiKeyInfo,
+}
+
+/// Key listing filter
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct ApiKeyFilter {
+    /// Only keys whose prefix starts with this value
+    pub prefix: Option<String>,
+}
+
+/// Generate a key and its prefix.
+fn generate_key() -> (String, String) {
+    let mut rng = rand::rng();
+    let prefix: String = (&mut rng)
+        .sample_iter(&Alphanumeric)
+        .take(PREFIX_LENGTH)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    let mut secret = [0u8; SECRET_BYTES];
+    rng.fill(&mut secret);
+
+    let key = format!("{KEY_PREFIX}_{prefix}_{}", URL_SAFE_NO_PAD.encode(secret));
+    (key, prefix)
+}
+
+/// The prefix of a well-formed key.
+pub(crate) fn parse_prefix(key: &str) -> Option<&str> {
+    let mut parts = key.splitn(3, '_');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(KEY_PREFIX), Some(prefix), Some(secret))
+            if prefix.len() == PREFIX_LENGTH && !secret.is_empty() =>
+        {
+            Some(prefix)
+        }
+        _ => None,
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+/// Compare two hashes in constant time.
+fn hashes_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn store_error(e: DbErr) -> AppError {
+    AppError::InternalServerError(format!("API key store failed: {e}"))
+}
+
+fn not_found(id: Uuid) -> AppError {
+    AppError::BadRequest(format!("API key {id} not found"))
+}
+
+/// API keys persisted in the `api_keys` table.
+#[derive(Debug, Clone)]
+pub struct ApiKeyStore {
+    db: DatabaseConnection,
+}
+
+impl ApiKeyStore {
+    /// Create a store on the given connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Create the `api_keys` table and its prefix index, if missing.
+    ///
+    /// Services with their own migrations can create the table from
+    /// `entity::Entity` instead.
+    pub async fn create_table(&self) -> Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+
+        let mut table = schema.create_table_from_entity(entity::Entity);
+        table.if_not_exists();
+        self.db.execute(backend.build(&table)).await.map_err(store_error)?;
+        for mut index in schema.create_index_from_entity(entity::Entity) {
+            index.if_not_exists();
+            self.db.execute(backend.build(&index)).await.map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    /// Issue a new key.
+    pub async fn issue(&self, request: IssueApiKey) -> Result<IssuedApiKey> {
+        if request.name.trim().is_empty() {
+            return Err(AppError::BadRequest("API key name is required".to_string()));
+        }
+        let (key, prefix) = generate_key();
+
+        let model = entity::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set(request.name),
+            prefix: Set(prefix),
+            key_hash: Set(hash_key(&key)),
+            tier: Set(request.tier),
+            scopes: Set(request.scopes.join(" ")),
+            created_at: Set(Utc::now()),
+            expires_at: Set(request.expires_at),
+            revoked_at: Set(None),
+            last_used_at: Set(None),
+        }
+        .insert(&self.db)
+        .await
+        .map_err(store_error)?;
+
+        tracing::info!(prefix = model.prefix, "🔑 API key issued");
+        Ok(IssuedApiKey {
+            key,
+            api_key: model.into(),
+        })
+    }
+
+    /// List keys, most recent first, optionally those whose prefix starts with `prefix`.
+    pub async fn list(&self, prefix: Option<&str>) -> Result<Vec<ApiKeyInfo>> {
+        let mut query = entity::Entity::find().order_by_desc(entity::Column::CreatedAt);
+        if let Some(prefix) = prefix {
+            query = query.filter(entity::Column::Prefix.starts_with(prefix));
+        }
+        let models = query.all(&self.db).await.map_err(store_error)?;
+        Ok(models.into_iter().map(ApiKeyInfo::from).collect())
+    }
+
+    async fn find(&self, id: Uuid) -> Result<entity::Model> {
+        entity::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(store_error)?
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// Replace the secret (and prefix) of a key; the previous key stops working.
+    pub async fn rotate(&self, id: Uuid) -> Result<IssuedApiKey> {
+        let model = self.find(id).await?;
+        if model.revoked_at.is_some() {
+            return Err(AppError::BadRequest(format!("API key {id} is revoked")));
+        }
+        let (key, prefix) = generate_key();
+
+        let mut active: entity::ActiveModel = model.into();
+        active.prefix = Set(prefix);
+        active.key_hash = Set(hash_key(&key));
+        let model = active.update(&self.db).await.map_err(store_error)?;
+
+        tracing::info!(prefix = model.prefix, "🔑 API key rotated");
+        Ok(IssuedApiKey {
+            key,
+            api_key: model.into(),
+        })
+    }
+
+    /// Revoke a key.
+    pub async fn revoke(&self, id: Uuid) -> Result<()> {
+        let model = self.find(id).await?;
+        if model.revoked_at.is_some() {
+            return Ok(());
+        }
+
+        let mut active: entity::ActiveModel = model.into();
+        active.revoked_at = Set(Some(Utc::now()));
+        let model = active.update(&self.db).await.map_err(store_error)?;
+
+        tracing::info!(prefix = model.prefix, "🔑 API key revoked");
+        Ok(())
+    }
+
+    /// The active key matching `key`, if any.
+    pub async fn verify(&self, key: &str) -> Result<Option<ApiKeyInfo>> {
+        let Some(prefix) = parse_prefix(key) else {
+            return Ok(None);
+        };
+        let hash = hash_key(key);
+        let now = Utc::now();
+
+        let candidates = entity::Entity::find()
+            .filter(entity::Column::Prefix.eq(prefix))
+            .all(&self.db)
+            .await
+            .map_err(store_error)?;
+        let Some(model) = candidates
+            .into_iter()
+            .find(|model| hashes_match(&model.key_hash, &hash))
+        else {
+            return Ok(None);
+        };
+
+        let stale = model
+            .last_used_at
+            .is_none_or(|last_used_at| now - last_used_at > LAST_USED_RESOLUTION);
+        let model = if stale {
+            let mut active: entity::ActiveModel = model.into();
+            active.last_used_at = Set(Some(now));
+            active.update(&self.db).await.map_err(store_error)?
+        } else {
+            model
+        };
+
+        let info = ApiKeyInfo::from(model);
+        Ok(info.is_active(now).then_some(info))
+    }
+}
+
+/// Tiers of the keys verified by `api_key_auth_middleware`, by key id.
+#[async_trait]
+impl TierStore for ApiKeyStore {
+    async fn tier(&self, key: &str) -> Result<Option<String>> {
+        let Ok(id) = Uuid::parse_str(key) else {
+            return Ok(None);
+        };
+        let model = entity::Entity::find_by_id(id).one(&self.db).await.map_err(store_error)?;
+        Ok(model.and_then(|model| model.tier))
+    }
+}
+
+/// API key authentication settings.
+///
+/// # Example
+///
+/// ```ignore
+/// EywaApp::new(state)
+///     .mount::<PartnerController>()
+///     .api_key_auth(ApiKeyAuth::new(ApiKeyStore::new(db.clone())))
+/// ```
+#[derive(Debug, Clone)]
+pub struct ApiKeyAuth {
+    store: ApiKeyStore,
+    header: String,
+}
+
+impl ApiKeyAuth {
+    /// Authenticate with the `X-API-Key` header.
+    pub fn new(store: ApiKeyStore) -> Self {
+        Self {
+            store,
+            header: API_KEY_HEADER.to_string(),
+        }
+    }
+
+    /// Read the key from another header.
+    pub fn header(mut self, name: impl Into<String>) -> Self {
+        self.header = name.into();
+        self
+    }
+}
+
+/// Middleware rejecting requests without an active API key.
+///
+/// The key's `ApiKeyInfo` is added to the request extensions. Installed by
+/// `EywaApp::api_key_auth()`.
+pub async fn api_key_auth_middleware(
+    State(auth): State<Arc<ApiKeyAuth>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response> {
+    let key = req
+        .headers()
+        .get(auth.header.as_str())
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::Unauthorized("Missing API key".to_string()))?;
+
+    let info = auth
+        .store
+        .verify(key)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+    req.extensions_mut().insert(info);
+    Ok(next.run(req).await)
+}
+
+/// Issue an API key
+///
+/// Returns the key; it can't be retrieved afterwards.
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "API Keys",
+    request_body = IssueApiKey,
+    responses(
+        (status = 201, description = "Key issued", body = IssuedApiKey)
+    )
+)]
+pub async fn issue(
+    State(store): State<Arc<ApiKeyStore>>,
+    Json(request): Json<IssueApiKey>,
+) -> Result<(StatusCode, Json<IssuedApiKey>)> {
+    Ok((StatusCode::CREATED, Json(store.issue(request).await?)))
+}
+
+/// List API keys
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "API Keys",
+    params(ApiKeyFilter),
+    responses(
+        (status = 200, description = "Keys, most recent first", body = Vec<ApiKeyInfo>)
+    )
+)]
+pub async fn list(
+    State(store): State<Arc<ApiKeyStore>>,
+    Query(filter): Query<ApiKeyFilter>,
+) -> Result<Json<Vec<ApiKeyInfo>>> {
+    Ok(Json(store.list(filter.prefix.as_deref()).await?))
+}
+
+/// Rotate an API key
+///
+/// Replaces the key; the previous one stops working immediately.
+#[utoipa::path(
+    post,
+    path = "/api-keys/{id}/rotate",
+    tag = "API Keys",
+    params(("id" = Uuid, Path, description = "API key ID")),
+    responses(
+        (status = 200, description = "Key rotated", body = IssuedApiKey)
+    )
+)]
+pub async fn rotate(
+    State(store): State<Arc<ApiKeyStore>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IssuedApiKey>> {
+    Ok(Json(store.rotate(id).await?))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{id}",
+    tag = "API Keys",
+    params(("id" = Uuid, Path, description = "API key ID")),
+    responses(
+        (status = 204, description = "Key revoked")
+    )
+)]
+pub async fn revoke(
+    State(store): State<Arc<ApiKeyStore>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    store.revoke(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub struct ApiKeyController;
+
+impl ApiKeyController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 4] = [
+        ("POST", "/api-keys"),
+        ("GET", "/api-keys"),
+        ("POST", "/api-keys/{id}/rotate"),
+        ("DELETE", "/api-keys/{id}"),
+    ];
+
+    /// Build the API key admin router for the given store.
+    pub fn router<S>(store: ApiKeyStore) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/api-keys", post(issue).get(list))
+            .route("/api-keys/{id}/rotate", post(rotate))
+            .route("/api-keys/{id}", delete(revoke))
+            .with_state(Arc::new(store))
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_issue as Path>::path(),
+                <__path_issue as Path>::methods(),
+                <__path_issue as Path>::operation(),
+            ),
+            (
+                <__path_list as Path>::path(),
+                <__path_list as Path>::methods(),
+                <__path_list as Path>::operation(),
+            ),
+            (
+                <__path_rotate as Path>::path(),
+                <__path_rotate as Path>::methods(),
+                <__path_rotate as Path>::operation(),
+            ),
+            (
+                <__path_revoke as Path>::path(),
+                <__path_revoke as Path>::methods(),
+                <__path_revoke as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components
+            .schemas
+            .insert("IssueApiKey".to_string(), IssueApiKey::schema());
+        components
+            .schemas
+            .insert("ApiKeyInfo".to_string(), ApiKeyInfo::schema());
+        components
+            .schemas
+            .insert("IssuedApiKey".to_string(), IssuedApiKey::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_parse() {
+        let (key, prefix) = generate_key();
+
+        assert!(key.starts_with("eywa_"));
+        assert_eq!(parse_prefix(&key), Some(prefix.as_str()));
+        assert_eq!(parse_prefix("eywa_short_secret"), None);
+        assert_eq!(parse_prefix("other_3kf9a2xq_secret"), None);
+        assert_eq!(parse_prefix("eywa_3kf9a2xq_"), None);
+    }
+
+    #[test]
+    fn test_hash_comparison() {
+        let hash = hash_key("eywa_3kf9a2xq_secret");
+
+        assert_eq!(hash.len(), 64);
+        assert!(hashes_match(&hash, &hash_key("eywa_3kf9a2xq_secret")));
+        assert!(!hashes_match(&hash, &hash_key("eywa_3kf9a2xq_secreT")));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_key_lifecycle() {
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        let store = ApiKeyStore::new(db);
+        store.create_table().await.unwrap();
+
+        let issued = store
+            .issue(IssueApiKey {
+                name: "partner".to_string(),
+                tier: Some("enterprise".to_string()),
+                scopes: vec!["projects:read".to_string()],
+                expires_at: None,
+            })
+            .await
+            .unwrap();
+        let verified = store.verify(&issued.key).await.unwrap().unwrap();
+        assert_eq!(verified.scopes, ["projects:read"]);
+        assert!(verified.last_used_at.is_some());
+        let id = issued.api_key.id.to_string();
+        assert_eq!(store.tier(&id).await.unwrap().as_deref(), Some("enterprise"));
+        assert_eq!(store.tier(&issued.key).await.unwrap(), None);
+
+        let listed = store.list(Some(&issued.api_key.prefix[..4])).await.unwrap();
+        assert_eq!(listed.len(), 1);
+
+        let rotated = store.rotate(issued.api_key.id).await.unwrap();
+        assert!(store.verify(&issued.key).await.unwrap().is_none());
+        assert!(store.verify(&rotated.key).await.unwrap().is_some());
+
+        store.revoke(issued.api_key.id).await.unwrap();
+        assert!(store.verify(&rotated.key).await.unwrap().is_none());
+    }
+}
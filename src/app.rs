@@ -3,15 +3,108 @@
 //! This module provides the main application builder that automatically
 //! collects OpenAPI paths from controllers.
 
-use axum::{routing::get, Router};
+use std::time::Duration;
+
+use axum::{extract::FromRef, routing::get, Router};
 use tokio::net::TcpListener;
 use tracing::info;
 use utoipa::ToSchema;
-use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
-use utoipa::openapi::{Components, Info, OpenApi, Tag};
+use utoipa::openapi::OpenApi;
 use utoipa_scalar::{Scalar, Servable};
 
-use crate::traits::IntoRouter;
+use crate::admin::AdminListener;
+use crate::analytics::{analytics_middleware, Analytics};
+#[cfg(feature = "api-keys")]
+use crate::api_keys::{
+    api_key_auth_middleware, ApiKeyAuth, ApiKeyController, ApiKeyStore, API_KEYS_ADMIN_SCOPE,
+};
+use crate::assets::StaticAssets;
+use crate::audit::audit_context_middleware;
+use crate::authorization::{policy_middleware, PolicyEnforcement, PolicyEngine};
+use crate::capture::{capture_middleware, CaptureConfig};
+use crate::clock::{Clock, SharedClock};
+use crate::dead_letters::{
+    DeadLetterController, DeadLetterStore, Redriver, DEAD_LETTERS_ADMIN_SCOPE,
+};
+use crate::deprecations::{
+    deprecation_middleware, DeprecationController, Deprecations, DEPRECATIONS_ADMIN_SCOPE,
+};
+use crate::di::{Container, Dependency};
+use crate::envelope::{envelope_middleware, ResponseEnvelope};
+use crate::error_codes::{CodedError, ErrorCatalog, ErrorCodeInfo};
+use crate::error_responses::ErrorResponses;
+use crate::experiments::{experiments_middleware, Experiments};
+use crate::health::HealthChecks;
+use crate::kill_switches::{
+    kill_switch_middleware, KillSwitchController, KillSwitches, KILL_SWITCHES_ADMIN_SCOPE,
+};
+use crate::locale::locale_middleware;
+use crate::log_level::{LogLevelController, LOG_LEVEL_ADMIN_SCOPE};
+use crate::logging::{init_json, LogConfig};
+use crate::middleware::adaptive::{
+    adaptive_concurrency_middleware, AdaptiveConcurrency, AdaptiveLimiter,
+};
+use crate::middleware::auth::{user_context_middleware, JwtConfig, JwtVerifier, SharedVerifier};
+use crate::middleware::body_limit::body_limit_middleware;
+use crate::middleware::http_metrics::http_metrics_middleware;
+use crate::middleware::limits::{limits_middleware, Limits, LimitsConfig};
+use crate::middleware::propagation::{header_policy_middleware, HeaderPolicy};
+use crate::middleware::bulkhead::{bulkhead_middleware, Bulkheads};
+use crate::middleware::canary::{canary_middleware, Canaries, Canary};
+use crate::middleware::chaos::{chaos_middleware, ChaosConfig};
+use crate::middleware::compression::CompressionSettings;
+use crate::middleware::constraints::SchemaConstraints;
+use crate::middleware::content_types::{ContentTypePolicy, ContentTypes};
+use crate::middleware::deadline::{deadline_middleware, DeadlineConfig};
+use crate::middleware::decompression::{
+    limit_decompressed_middleware, mark_compressed_middleware, DecompressionSettings,
+};
+use crate::middleware::headers::static_headers_middleware;
+use crate::middleware::route_layers::{route_layers_middleware, RouteLayerRegistry};
+use crate::middleware::maintenance::{maintenance_middleware, MaintenanceMode};
+use crate::middleware::method_override::{method_override_middleware, MethodOverride};
+use crate::middleware::methods::{options_middleware, AutoMethods};
+#[cfg(feature = "otel")]
+use crate::middleware::otel::{otel_middleware, otel_route_middleware, OtelConfig};
+use crate::middleware::paths::{path_normalization_middleware, PathNormalizer, PathPolicy};
+use crate::middleware::queue::{admission_middleware, AdmissionQueue, AdmissionSettings};
+use crate::middleware::rate_limit::{rate_limit_middleware, RateLimiter};
+use crate::middleware::rejection::rejection_middleware;
+use crate::middleware::response_metrics::response_metrics_middleware;
+use crate::middleware::scopes::{scope_enforcement_middleware, ScopeEnforcement};
+use crate::middleware::slo::{slo_middleware, Slo, Slos};
+use crate::middleware::spec_checks::{Checks, SpecChecks};
+use crate::middleware::timing::{handler_timing_middleware, server_timing_middleware};
+use crate::observability::{observability_middleware, ObservabilitySettings};
+use crate::operation_ids::OperationIdStrategy;
+use crate::operations::{Operations, OperationsController};
+use crate::privacy::{DataSubjectHandler, PrivacyController, PrivacyRegistry, PRIVACY_ADMIN_SCOPE};
+use crate::reload::Watch;
+use crate::search::SearchClient;
+use crate::scalar::ScalarConfig;
+use crate::scheduler::{JobController, Jobs, Scheduler, JOBS_ADMIN_SCOPE};
+use crate::shutdown::{serve_until_drained, trigger_on_signal, Shutdown, DEFAULT_SHUTDOWN_GRACE};
+use crate::startup::{enabled_features, StartupSummary};
+use crate::spec::{docs_router, internal_docs_router, LazySpecs, SpecBuilder};
+use crate::state::Resources;
+use crate::telemetry::LogLevel;
+use crate::testing::TestClient;
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+#[cfg(feature = "jwe")]
+use crate::jwe::{jwe_middleware, JweKeys};
+use crate::typed_header::{CustomHeader, HeaderParameter};
+use crate::traits::{
+    AuthRequirement, IntoRouter, RouteDependencies, RouteErrors, RouteHeaders,
+    RouteRequestHeaders, RouteScopes, RouteValidation,
+};
+use crate::versioning::{prefix_path, prefix_paths, version_prefix, VersionRewriter};
+use crate::visibility::RouteSet;
+use crate::warmup::Warmup;
+use crate::webhooks::{WebhookController, WebhookSubscriptions, WEBHOOKS_SCOPE};
+
+/// Wraps the internal docs routes, typically with authentication.
+type DocsGuard<S> = Box<dyn FnOnce(Router<S>) -> Router<S> + Send + Sync>;
 
 /// Builder for creating EYWA applications with automatic OpenAPI support.
 ///
@@ -35,11 +128,45 @@ where
 {
     state: S,
     router: Router<S>,
-    info: Option<Info>,
-    tags: Vec<Tag>,
-    schema_fns: Vec<Box<dyn Fn(&mut utoipa::openapi::Components) + Send + Sync>>,
-    path_fns: Vec<Box<dyn Fn(&mut utoipa::openapi::OpenApi) + Send + Sync>>,
+    spec: SpecBuilder,
     has_health_checks: bool,
+    internal_docs: Option<DocsGuard<S>>,
+    policy_engine: Option<std::sync::Arc<dyn PolicyEngine>>,
+    jwt: SharedVerifier,
+    spec_checks: SpecChecks,
+    privacy: PrivacyRegistry,
+    has_privacy_endpoints: bool,
+    container: Container,
+    dependencies: Vec<RouteDependencies>,
+    bulkheads: Bulkheads,
+    slos: Slos,
+    kill_switches: KillSwitches,
+    canaries: Vec<(String, String, Canary, axum::routing::MethodRouter<S>)>,
+    operations: Option<Operations>,
+    admin: AdminListener,
+    has_server_timing: bool,
+    has_response_metrics: bool,
+    has_metrics: bool,
+    has_logging: bool,
+    #[cfg(feature = "otel")]
+    otel: Option<opentelemetry_sdk::trace::Tracer>,
+    observability: Option<ObservabilitySettings>,
+    warmup: Warmup,
+    database: Option<sea_orm::DatabaseConnection>,
+    search: Option<SearchClient>,
+    mock_mode: bool,
+    version_header: Option<axum::http::HeaderName>,
+    path_policy: Option<PathPolicy>,
+    method_override: Option<MethodOverride>,
+    middleware: Vec<&'static str>,
+    errors: Vec<String>,
+    route_count: usize,
+    deprecations: Option<Deprecations>,
+    version: Option<String>,
+    shutdown: Shutdown,
+    shutdown_grace: Duration,
+    scheduler: Option<Scheduler>,
+    jobs: Jobs,
 }
 
 impl<S> EywaApp<S>
@@ -51,11 +178,52 @@ where
         Self {
             state,
             router: Router::new(),
-            info: None,
-            tags: Vec::new(),
-            schema_fns: Vec::new(),
-            path_fns: Vec::new(),
+            spec: SpecBuilder::default(),
             has_health_checks: false,
+            internal_docs: None,
+            policy_engine: None,
+            jwt: SharedVerifier::default(),
+            spec_checks: SpecChecks::default(),
+            privacy: PrivacyRegistry::new(),
+            has_privacy_endpoints: false,
+            container: Container::new(),
+            dependencies: Vec::new(),
+            bulkheads: Bulkheads::new(),
+            slos: Slos::new(),
+            kill_switches: KillSwitches::new(),
+            canaries: Vec::new(),
+            operations: None,
+            admin: AdminListener::new(),
+            has_server_timing: false,
+            has_response_metrics: false,
+            has_metrics: false,
+            has_logging: false,
+            #[cfg(feature = "otel")]
+            otel: None,
+            observability: None,
+            warmup: Warmup::new(),
+            database: None,
+            search: None,
+            mock_mode: false,
+            version_header: None,
+            path_policy: None,
+            method_override: None,
+            middleware: Vec::new(),
+            errors: Vec::new(),
+            route_count: 0,
+            deprecations: None,
+            version: None,
+            shutdown: Shutdown::new(),
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
+            scheduler: None,
+            jobs: Jobs::new(),
+        }
+    }
+
+    /// Record an enabled middleware for the startup summary.
+    fn enable(&mut self, name: &'static str) {
+        if !self.middleware.contains(&name) {
+            self.middleware.push(name);
         }
     }
 
@@ -71,7 +239,7 @@ where
         version: impl Into<String>,
         description: impl Into<String>,
     ) -> Self {
-        self.info = Some(
+        self.spec.info = Some(
             utoipa::openapi::InfoBuilder::new()
                 .title(title.into())
                 .version(version.into())
@@ -81,19 +249,62 @@ where
         self
     }
 
+    /// List these tags first in the spec, in this order.
+    ///
+    /// Tags are otherwise listed in mount order; the remaining tags follow.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.tag_order(["Projects", "Users", "Health"])
+    /// ```
+    pub fn tag_order<I, T>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.spec.tag_layout.set_order(tags);
+        self
+    }
+
+    /// Group tags under a heading with the `x-tagGroups` extension.
+    ///
+    /// Scalar and Redoc use the groups to organize the sidebar by domain.
+    /// Tags outside every group are listed under `Other`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.tag_group("Core", ["Projects", "Users"])
+    ///    .tag_group("Billing", ["Invoices", "Payments"])
+    /// ```
+    pub fn tag_group<I, T>(mut self, name: impl Into<String>, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.spec.tag_layout.add_group(name, tags);
+        self
+    }
+
     /// Add a tag with description.
     ///
+    /// Replaces the description of an existing tag, including one declared
+    /// with `#[controller(tag_description = "...")]`.
+    ///
     /// # Example
     /// ```ignore
     /// app.tag("Timer", "Timer management endpoints")
     /// ```
     pub fn tag(mut self, name: impl Into<String>, description: impl Into<String>) -> Self {
-        self.tags.push(
-            utoipa::openapi::tag::TagBuilder::new()
-                .name(name.into())
-                .description(Some(description.into()))
-                .build(),
-        );
+        let (name, description) = (name.into(), description.into());
+        match self.spec.tags.iter_mut().find(|tag| tag.name == name) {
+            Some(tag) => tag.description = Some(description),
+            None => self.spec.tags.push(
+                utoipa::openapi::tag::TagBuilder::new()
+                    .name(name)
+                    .description(Some(description))
+                    .build(),
+            ),
+        }
         self
     }
 
@@ -105,7 +316,7 @@ where
     ///    .schema::<MyResponse>()
     /// ```
     pub fn schema<T: ToSchema + 'static>(mut self) -> Self {
-        self.schema_fns
+        self.spec.schema_fns
             .push(Box::new(|components: &mut utoipa::openapi::Components| {
                 let name = T::name().to_string();
                 let schema = T::schema();
@@ -114,305 +325,2899 @@ where
         self
     }
 
+    /// Serve the controllers mounted from now on under a version prefix.
+    ///
+    /// `version("v1")` nests their routes under `/v1` and prefixes their
+    /// spec paths and route metadata (auth, scopes, SLOs, ...) the same way,
+    /// so `/api/projects` is served and documented as `/v1/api/projects`.
+    /// Controllers mounted before, and routers added with `merge`, keep
+    /// their paths. Use `version_scope` to mount several versions side by side.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .health_checks()
+    ///     .version("v1")
+    ///     .mount::<ProjectsController>()  // /v1/api/projects
+    /// ```
+    pub fn version(mut self, version: impl AsRef<str>) -> Self {
+        self.version = Some(version_prefix(version.as_ref()));
+        self
+    }
+
+    /// Mount the controllers of `scope` under a version prefix.
+    ///
+    /// The prefix only applies inside `scope`; the controllers mounted
+    /// afterwards get the prefix of `version()`, if any. Each version gets
+    /// its own spec at `/api-docs/v{n}/openapi.json`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .version_scope("v1", |app| app.mount::<ProjectsV1>())
+    ///     .version_scope("v2", |app| app.mount::<ProjectsV2>().mount::<TasksV2>())
+    /// ```
+    pub fn version_scope<F>(mut self, version: impl AsRef<str>, scope: F) -> Self
+    where
+        F: FnOnce(Self) -> Self,
+    {
+        let outer = self.version.take();
+        let mut app = scope(self.version(version));
+        app.version = outer;
+        app
+    }
+
     /// Mount a controller to the application.
     ///
     /// This automatically:
     /// 1. Registers all routes from the controller
     /// 2. Collects OpenAPI paths from `__UTOIPA_PATHS__`
-    /// 3. Adds the controller's tag
+    /// 3. Applies the controller's own middleware to its routes
+    /// 4. Adds the controller's tag, with its description and position
+    /// 5. Registers the per-route authentication and OAuth scopes
+    /// 6. Registers its static response headers
+    /// 7. Registers the routes using validating extractors
+    /// 8. Registers its documented error statuses, injected services, hidden
+    ///    and internal routes
+    /// 9. Registers its GDPR data subject handlers
     ///
     /// # Example
     /// ```ignore
     /// app.mount::<TimerController>()
     ///    .mount::<UserController>()
     /// ```
-    pub fn mount<C>(mut self) -> Self
+    pub fn mount<C>(self) -> Self
     where
         C: IntoRouter<S>,
     {
-        let prefix = C::prefix();
-        let controller_tag = C::tag();
+        self.mount_with::<C, _>(|router| router)
+    }
+
+    /// Mount a controller, wrapping its routes with extra middleware.
+    ///
+    /// Use this for controllers whose middleware isn't declared through
+    /// `#[controller(middleware(...))]`. Use `route_layer` so unmatched
+    /// requests still return 404 instead of running the middleware.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.mount_with::<ProjectsController, _>(|router| {
+    ///     router.route_layer(axum::middleware::from_fn(tenant_middleware))
+    /// })
+    /// ```
+    pub fn mount_with<C, F>(mut self, wrap: F) -> Self
+    where
+        C: IntoRouter<S>,
+        F: FnOnce(Router<S>) -> Router<S>,
+    {
+        // Get the controller's router, wrapped with its own middleware only
+        let controller_router = self.spec_checks.wrap(C::into_router(self.state.clone()));
+        let controller_router = self.route_layers::<C, S>(controller_router, &self.state);
+        let controller_router = wrap(C::middleware(controller_router, &self.state));
 
-        // Get the controller's router
-        let controller_router = C::into_router(self.state.clone());
+        let state = self.state.clone();
+        self.register_controller::<C, S>(controller_router, &state);
+        self
+    }
+
+    /// Mount a controller that only needs a sub-state of the app state.
+    ///
+    /// Reusable controllers (shipped by other crates) should depend on the
+    /// smallest state they need rather than on the service's `AppState`. The
+    /// sub-state is extracted once with `FromRef` and provided to the
+    /// controller's router, so `C` implements `IntoRouter<T>` for its own
+    /// state type. Middleware and GDPR handlers also receive the sub-state.
+    ///
+    /// Controllers whose handlers extract `State<T>` from a generic `S` with
+    /// `T: FromRef<S>` can be mounted with `mount` instead.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[derive(Clone, FromRef)]
+    /// struct AppState {
+    ///     db: Database,
+    ///     audit: AuditState,
+    /// }
+    ///
+    /// // `impl IntoRouter<AuditState> for AuditController`
+    /// app.mount_substate::<AuditController, AuditState>()
+    /// ```
+    pub fn mount_substate<C, T>(mut self) -> Self
+    where
+        C: IntoRouter<T>,
+        T: FromRef<S> + Clone + Send + Sync + 'static,
+    {
+        let sub_state = T::from_ref(&self.state);
+        let controller_router = self.spec_checks.wrap(C::into_router(sub_state.clone()));
+        let controller_router = self.route_layers::<C, T>(controller_router, &sub_state);
+        let controller_router =
+            C::middleware(controller_router, &sub_state).with_state(sub_state.clone());
+
+        self.register_controller::<C, T>(controller_router, &sub_state);
+        self
+    }
+
+    /// Require a verified token on built-in endpoints, checking their
+    /// requests against the spec once authenticated.
+    fn protect(&self, router: Router<S>) -> Router<S> {
+        self.jwt.protect(self.spec_checks.wrap(router))
+    }
+
+    /// Wrap the routes declaring their own middleware with a `route_layer`.
+    ///
+    /// Applied before the controller's middleware, so route middleware runs
+    /// inside it (e.g. after the controller's authentication).
+    fn route_layers<C, T>(&self, controller_router: Router<T>, state: &T) -> Router<T>
+    where
+        C: IntoRouter<T>,
+        T: Clone + Send + Sync + 'static,
+    {
+        // Matched paths include the version prefix the router is nested under
+        let mut registry = RouteLayerRegistry::new();
+        let version = self.version.as_deref();
+        for route in versioned(version, C::route_middleware(state), |r| &mut r.path) {
+            registry.insert(route);
+        }
+        if registry.is_empty() {
+            return controller_router;
+        }
+        controller_router.route_layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(registry),
+            route_layers_middleware,
+        ))
+    }
+
+    /// Merge a controller's router and collect its metadata.
+    fn register_controller<C, T>(&mut self, controller_router: Router<S>, state: &T)
+    where
+        C: IntoRouter<T>,
+        T: Clone + Send + Sync + 'static,
+    {
+        let controller_tag = C::tag();
+        let version = self.version.clone();
+        let version = version.as_deref();
 
         // Get OpenAPI route metadata
         let openapi_routes = C::openapi_routes();
 
         // Log routes
         for route in &openapi_routes {
-            info!("📍 {} {} [{}]", route.method, route.path, route.tag);
+            let path = versioned_path(version, &route.path);
+            info!("📍 {} {} [{}]", route.method, path, route.tag);
         }
+        self.route_count += openapi_routes.len();
 
         // Merge the controller router (routes already have full path from macro)
         // We always merge because the controller macro bakes in the full path
-        // (e.g., "/api/v1/auth/login") so nesting would cause double-prefixing
+        // (e.g., "/api/v1/auth/login") so nesting would cause double-prefixing.
+        // Only the version prefix of `version()` is nested.
+        let controller_router = match version {
+            Some(version) => Router::new().nest(version, controller_router),
+            None => controller_router,
+        };
         self.router = self.router.merge(controller_router);
 
-        // Add controller tag if not already present
-        if !self.tags.iter().any(|t| t.name == controller_tag) {
-            self.tags.push(
+        // Add controller tag if not already present, with its description
+        let description = C::tag_description().map(str::to_string);
+        match self.spec.tags.iter_mut().find(|t| t.name == controller_tag) {
+            Some(tag) => {
+                if tag.description.is_none() {
+                    tag.description = description;
+                }
+            }
+            None => self.spec.tags.push(
                 utoipa::openapi::tag::TagBuilder::new()
                     .name(controller_tag)
+                    .description(description)
                     .build(),
-            );
+            ),
+        }
+        if let Some(position) = C::tag_order() {
+            self.spec.tag_layout.set_position(controller_tag, position);
+        }
+
+        // Collect controller's per-route authentication requirements
+        self.spec.route_auth.extend(versioned(version, C::route_auth(), |r| &mut r.path));
+
+        // Collect controller's required scopes
+        for route_scopes in versioned(version, C::route_scopes(), |r| &mut r.path) {
+            self.spec.scopes.insert(route_scopes);
+        }
+
+        // Collect controller's static response headers
+        for route_headers in versioned(version, C::route_headers(), |r| &mut r.path) {
+            self.spec.static_headers.insert(route_headers);
+        }
+
+        // Collect controller's routes using validating extractors
+        let validated = versioned(version, C::route_validation(), |r| &mut r.path);
+        self.spec.validated_routes.extend(validated);
+
+        // Collect controller's documented error statuses
+        let errors = versioned(version, C::route_errors(), |r| &mut r.path);
+        self.spec.route_errors.extend(errors);
+
+        // Collect controller's typed request headers
+        let request_headers = versioned(version, C::route_request_headers(), |r| &mut r.path);
+        self.spec.request_headers.extend(request_headers);
+
+        // Collect controller's injected services
+        let dependencies = versioned(version, C::route_dependencies(), |r| &mut r.path);
+        self.dependencies.extend(dependencies);
+
+        // Collect controller's bulkhead assignments
+        for route in versioned(version, C::route_bulkheads(), |r| &mut r.path) {
+            self.bulkheads.assign(&route.bulkhead, &route.method, route.path);
+        }
+
+        // Collect controller's SLOs
+        for route in versioned(version, C::route_slos(), |r| &mut r.path) {
+            self.slos.insert_route(route);
+        }
+
+        // Collect controller's killable routes
+        for route in versioned(version, C::killable_routes(), |r| &mut r.path) {
+            self.kill_switches.mark_killable(&route.method, &route.path);
+        }
+
+        // Collect controller's routes hidden from the spec
+        for route in versioned(version, C::hidden_routes(), |r| &mut r.path) {
+            self.spec.hidden.insert(&route.method, route.path);
+        }
+
+        // Collect controller's routes documented only in the internal spec
+        for route in versioned(version, C::internal_routes(), |r| &mut r.path) {
+            self.spec.internal.insert(&route.method, route.path);
+        }
+
+        // Collect controller's data subject handlers
+        for handler in C::data_subject_handlers(state) {
+            self.privacy.register(handler);
         }
 
         // Collect controller's schemas
-        self.schema_fns.push(Box::new(|components| {
+        self.spec.schema_fns.push(Box::new(|components| {
             C::register_schemas(components);
         }));
 
-        // Collect controller's paths
-        self.path_fns.push(Box::new(|openapi| {
-            C::register_paths(openapi);
+        // Collect controller's paths, under the version prefix if any
+        let version = version.map(str::to_string);
+        self.spec.path_fns.push(Box::new(move |openapi| match &version {
+            Some(version) => {
+                let mut versioned = OpenApi::default();
+                C::register_paths(&mut versioned);
+                prefix_paths(&mut versioned, version);
+                openapi.merge(versioned);
+            }
+            None => C::register_paths(openapi),
         }));
-
-        self
-    }
-
-    /// Merge another Router into this one.
-    pub fn merge(mut self, other: Router<S>) -> Self {
-        self.router = self.router.merge(other);
-        self
     }
 
-    /// Apply a middleware layer.
-    pub fn layer<L>(mut self, layer: L) -> Self
+    /// Register a service for the `Inject<T>` extractor.
+    ///
+    /// Services are keyed by type: provide trait objects as `Arc<dyn Trait>`
+    /// so handlers depend on the interface. Providing the same type twice
+    /// replaces the first service.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.provide::<Arc<dyn EmailSender>>(Arc::new(SmtpSender::new(&config)))
+    ///    .provide(reqwest::Client::new())
+    /// ```
+    pub fn provide<T>(mut self, service: T) -> Self
     where
-        L: tower::Layer<axum::routing::Route> + Clone + Send + Sync + 'static,
-        L::Service: tower::Service<axum::extract::Request> + Clone + Send + Sync + 'static,
-        <L::Service as tower::Service<axum::extract::Request>>::Future: Send + 'static,
-        <L::Service as tower::Service<axum::extract::Request>>::Response:
-            axum::response::IntoResponse + 'static,
-        <L::Service as tower::Service<axum::extract::Request>>::Error:
-            Into<std::convert::Infallible> + 'static,
+        T: Clone + Send + Sync + 'static,
     {
-        self.router = self.router.layer(layer);
+        self.container.provide(service);
         self
     }
 
-    /// Add health check endpoints for Kubernetes probes.
+    /// Register the resources built by `AppStateBuilder` for `Inject<T>`.
     ///
-    /// Adds three endpoints:
-    /// - `/health` - Basic health check (always returns 200 OK)
-    /// - `/health/ready` - Readiness probe (checks database connection)
-    /// - `/health/live` - Liveness probe (always returns 200 OK)
+    /// See `Container::provide_resources` for the types provided.
     ///
     /// # Example
     /// ```ignore
+    /// let resources = AppStateBuilder::new(config.resources.clone()).build().await?;
     /// EywaApp::new(state)
-    ///     .health_checks()
-    ///     .serve("0.0.0.0:3000")
-    ///     .await
+    ///     .provide_resources(&resources)
+    ///     .mount::<ProjectsController>()
     /// ```
-    pub fn health_checks(mut self) -> Self {
-        use crate::health::HealthController;
-
-        self.router = self.router
-            .route("/health", get(HealthController::health))
-            .route("/health/ready", get(HealthController::ready))
-            .route("/health/live", get(HealthController::live));
-
-        self.path_fns.push(Box::new(|openapi| {
-            HealthController::register_paths(openapi);
-        }));
-
-        self.schema_fns.push(Box::new(|components| {
-            HealthController::register_schemas(components);
-        }));
+    pub fn provide_resources(mut self, resources: &Resources) -> Self {
+        self.container.provide_resources(resources);
+        self
+    }
 
-        self.has_health_checks = true;
+    /// Read the time from `clock` instead of the system's.
+    ///
+    /// Provides it as `SharedClock` for `Inject<SharedClock>`; the rate
+    /// limit buckets, the scheduler's ticks and JWT expiry follow it too. Pass a
+    /// `TestClock` to test time-dependent behavior without sleeping.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let clock = TestClock::new();
+    /// let client = EywaApp::new(state)
+    ///     .clock(clock.clone())
+    ///     .mount::<ProjectsController>()
+    ///     .into_test_client();
+    /// clock.advance(Duration::from_secs(60));
+    /// ```
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        let clock: SharedClock = std::sync::Arc::new(clock);
+        self.scheduler = self.scheduler.map(|scheduler| scheduler.clock(clock.clone()));
+        self.container.provide(clock);
         self
     }
 
-    /// Enable response compression using gzip, deflate, and brotli.
+    /// Declare that a route injects `T`, so a missing `provide` fails at startup.
     ///
-    /// Automatically compresses responses based on Accept-Encoding header.
-    /// Typically reduces response size by 70-90% for JSON/text content.
+    /// Use this for routes that aren't declared through `#[route]`.
     ///
     /// # Example
     /// ```ignore
-    /// EywaApp::new(state)
-    ///     .compression()
-    ///     .serve("0.0.0.0:3000")
-    ///     .await
+    /// app.inject::<Arc<dyn EmailSender>>("POST", "/api/v1/invites")
     /// ```
-    pub fn compression(mut self) -> Self {
-        use tower_http::compression::CompressionLayer;
+    pub fn inject<T: 'static>(mut self, method: impl Into<String>, path: impl Into<String>) -> Self {
+        self.dependencies.push(RouteDependencies {
+            method: method.into(),
+            path: path.into(),
+            dependencies: vec![Dependency::of::<T>()],
+        });
+        self
+    }
 
-        self.router = self.router.layer(CompressionLayer::new());
+    /// Document a `TypedHeader<T>` read by a route as a header parameter.
+    ///
+    /// Use this for routes that aren't declared through `#[route]`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.typed_header::<TenantId>("GET", "/api/v1/projects")
+    /// ```
+    pub fn typed_header<T: CustomHeader>(
+        mut self,
+        method: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Self {
+        self.spec.request_headers.push(RouteRequestHeaders {
+            method: method.into(),
+            path: path.into(),
+            headers: vec![HeaderParameter::of::<T>()],
+        });
         self
     }
 
-    /// Enable structured request logging compatible with Loki/Grafana.
+    /// Require OAuth scopes for a route.
     ///
-    /// Logs HTTP method, path, correlation ID, status code, and latency.
-    /// Should be called after `.request_context()` to include correlation IDs.
+    /// The scopes are enforced against the `scope` claim of tokens verified
+    /// with the `JwtConfig` of `auth`, and emitted into the operation's
+    /// security requirement. Use this for routes that aren't declared through
+    /// `#[route(scopes(...))]`.
     ///
     /// # Example
     /// ```ignore
-    /// EywaApp::new(state)
-    ///     .request_context()
-    ///     .request_logging()
-    ///     .serve("0.0.0.0:3000")
-    ///     .await
+    /// app.require_scopes("DELETE", "/api/v1/projects/{id}", ["projects:admin"])
     /// ```
-    pub fn request_logging(mut self) -> Self {
-        use crate::middleware::request_logging_middleware;
+    pub fn require_scopes<I, T>(
+        mut self,
+        method: impl Into<String>,
+        path: impl Into<String>,
+        scopes: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.spec.scopes.insert(RouteScopes {
+            method: method.into(),
+            path: path.into(),
+            scopes: scopes.into_iter().map(Into::into).collect(),
+        });
+        self
+    }
 
-        self.router = self.router.layer(request_logging_middleware());
+    /// Document the `422` validation response on a route using `ValidatedJson`.
+    ///
+    /// Use this for routes that aren't declared through `#[route]`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.validated_route("POST", "/api/v1/projects")
+    /// ```
+    pub fn validated_route(mut self, method: impl Into<String>, path: impl Into<String>) -> Self {
+        self.spec.validated_routes.push(RouteValidation {
+            method: method.into(),
+            path: path.into(),
+            body: true,
+            ..Default::default()
+        });
         self
     }
 
-    /// Enable request context propagation (correlation ID, user ID, language).
+    /// Exclude every route matching a path glob from the OpenAPI spec.
     ///
-    /// Extracts request metadata from headers and makes it available to handlers
-    /// via `Extension<RequestContext>`. Should be called before `.request_logging()`.
+    /// The routes are still served, but don't appear in the spec, the docs
+    /// UIs or generated clients. `*` matches within a path segment, `**`
+    /// across segments. Single routes can use `#[route(hidden)]`.
     ///
     /// # Example
     /// ```ignore
-    /// use eywa_axum::middleware::RequestContext;
+    /// app.mount::<DebugController>()
+    ///    .hide_path("/internal/**")
+    /// ```
+    pub fn hide_path(mut self, pattern: impl Into<String>) -> Self {
+        self.spec.hidden.insert_pattern(pattern);
+        self
+    }
+
+    /// Document every route matching a path glob only in the internal spec.
     ///
-    /// async fn handler(
-    ///     Extension(ctx): Extension<RequestContext>,
-    /// ) -> Result<Json<Value>> {
-    ///     info!("Handling request {}", ctx.correlation_id);
-    ///     // ...
-    /// }
+    /// The routes are left out of the public spec served at `/scalar` (and
+    /// the per-version specs, Swagger UI and `openapi()`), but still served.
+    /// Single routes can use `#[route(internal)]`.
     ///
-    /// EywaApp::new(state)
-    ///     .request_context()
-    ///     .mount::<MyController>()
-    ///     .serve("0.0.0.0:3000")
-    ///     .await
+    /// # Example
+    /// ```ignore
+    /// app.internal_path("/api/v1/admin/**")
     /// ```
-    pub fn request_context(mut self) -> Self {
-        use crate::middleware::request_context_middleware_fn;
+    pub fn internal_path(mut self, pattern: impl Into<String>) -> Self {
+        self.spec.internal.insert_pattern(pattern);
+        self
+    }
 
-        use tower_http::normalize_path::NormalizePathLayer;
-        use tower::ServiceBuilder;
+    /// Serve the internal spec, including internal routes, behind a guard.
+    ///
+    /// The internal Scalar UI is served at `/scalar/internal` and its spec at
+    /// `/api-docs/internal/openapi.json`. Both routes are wrapped by `guard`,
+    /// which should authenticate the caller; without this call the internal
+    /// spec isn't served at all.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.internal_path("/api/v1/admin/**")
+    ///    .internal_docs(|docs| docs.route_layer(axum::middleware::from_fn(auth_middleware)))
+    /// ```
+    pub fn internal_docs<F>(mut self, guard: F) -> Self
+    where
+        F: FnOnce(Router<S>) -> Router<S> + Send + Sync + 'static,
+    {
+        self.internal_docs = Some(Box::new(guard));
+        self
+    }
 
-        self.router = self.router.layer(
-            ServiceBuilder::new()
-                .layer(NormalizePathLayer::trim_trailing_slash())
-                .layer(axum::middleware::from_fn(request_context_middleware_fn))
-        );
+    /// Pre-populate the Scalar "try it" client: servers per environment,
+    /// generated correlation IDs and header placeholders.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.scalar(
+    ///     ScalarConfig::new()
+    ///         .server("staging", "https://projects.staging.eywa.dev", "Staging")
+    ///         .environment(&config.env)
+    ///         .generate_correlation_id()
+    ///         .header("X-Tenant-ID", "00000000-0000-0000-0000-000000000000"),
+    /// )
+    /// ```
+    pub fn scalar(mut self, config: ScalarConfig) -> Self {
+        self.spec.scalar = config;
         self
     }
 
-    /// Serve the application with automatic Scalar UI.
+    /// Serve the raw spec documents under `path` instead of `/api-docs`.
     ///
-    /// This method:
-    /// 1. Builds the final OpenAPI spec
-    /// 2. Adds a `/scalar` endpoint for interactive API documentation
-    /// 3. Adds a `/swagger` endpoint if swagger-ui feature is enabled
-    /// 4. Starts the HTTP server
-    pub async fn serve(self, addr: &str) -> crate::Result<()> {
-        let (mut router, mut openapi) = (self.router, OpenApi::default());
-
-        // Apply custom info if provided
-        if let Some(info) = self.info {
-            openapi.info = info;
-        }
-
-        // Add tags
-        if !self.tags.is_empty() {
-            openapi.tags = Some(self.tags);
-        }
-
-        // Add schemas and security scheme to components
-        let mut components = openapi.components.unwrap_or_else(Components::new);
-
-        // Add bearer security scheme
-        components.add_security_scheme(
-            "bearer",
-            SecurityScheme::Http(
-                HttpBuilder::new()
-                    .scheme(HttpAuthScheme::Bearer)
-                    .bearer_format("JWT")
-                    .description(Some("JWT Bearer token"))
-                    .build(),
-            ),
-        );
+    /// The JSON and YAML specs are served at `{path}/openapi.json` and
+    /// `{path}/openapi.yaml` for CI and client generators, alongside the
+    /// per-version and internal specs. The Scalar and Swagger UIs follow.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.spec_path("/openapi")  // GET /openapi/openapi.yaml
+    /// ```
+    pub fn spec_path(mut self, path: impl Into<String>) -> Self {
+        let path = path.into();
+        self.spec.docs_path = Some(format!("/{}", path.trim_matches('/')));
+        self
+    }
 
-        // Add custom schemas
-        for schema_fn in self.schema_fns {
-            schema_fn(&mut components);
+    /// Register a reusable response under `components.responses`.
+    ///
+    /// Reference it from operations with `#[utoipa::path(responses((status = 429, response = ...)))]`,
+    /// or document it on every operation with `default_response`. A component
+    /// named after an error status (`Unauthorized`, `NotFound`, ...) replaces
+    /// the one generated by `error_responses`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use utoipa::openapi::ResponseBuilder;
+    ///
+    /// app.response_component(
+    ///     "RateLimited",
+    ///     ResponseBuilder::new().description("Rate limit exceeded").build(),
+    /// )
+    /// ```
+    pub fn response_component(
+        mut self,
+        name: impl Into<String>,
+        response: utoipa::openapi::Response,
+    ) -> Self {
+        self.spec.responses.insert(name, response);
+        self
+    }
+
+    /// Document `status` on every operation with a registered response component.
+    ///
+    /// Operations documenting the status themselves keep their own response,
+    /// and public routes don't get `401`/`403` defaults.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.response_component("RateLimited", rate_limited)
+    ///    .default_response("429", "RateLimited")
+    /// ```
+    pub fn default_response(mut self, status: impl Into<String>, name: impl Into<String>) -> Self {
+        self.spec.responses.set_default(status, name);
+        self
+    }
+
+    /// Document the standard `AppError` responses on every operation.
+    ///
+    /// Registers the `ErrorResponse` schema and adds the configured error
+    /// statuses (400, 401, 403, 404, 409, 422 and 500 by default) to each
+    /// operation that doesn't document them already. Public routes don't get
+    /// 401/403. Override the set per route with `#[route(errors(...))]` or
+    /// `route_errors`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::error_responses::ErrorResponses;
+    ///
+    /// app.error_responses(ErrorResponses::standard().without(409))
+    /// ```
+    pub fn error_responses(mut self, errors: ErrorResponses) -> Self {
+        self.spec.error_responses = Some(errors);
+        self
+    }
+
+    /// Replace the error statuses documented on a route.
+    ///
+    /// Use this for routes that aren't declared through `#[route(errors(...))]`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.route_errors("DELETE", "/api/v1/projects/{id}", [404, 409])
+    /// ```
+    pub fn route_errors(
+        mut self,
+        method: impl Into<String>,
+        path: impl Into<String>,
+        statuses: impl IntoIterator<Item = u16>,
+    ) -> Self {
+        self.spec.route_errors.push(RouteErrors {
+            method: method.into(),
+            path: path.into(),
+            statuses: statuses.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Serve the error code catalog at `GET /errors`.
+    ///
+    /// Documents every code of `AppError`, the framework middleware and the
+    /// codes registered with `error_code`/`coded_error` as the `ErrorCode`
+    /// enum referenced by `ErrorResponse.error`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .error_codes()
+    ///     .coded_error::<BillingError>()
+    ///     .error_code("PROJECT_ARCHIVED", 409, "Archived projects are read-only")
+    /// ```
+    pub fn error_codes(mut self) -> Self {
+        self.spec.error_codes.get_or_insert_with(ErrorCatalog::standard);
+        self
+    }
+
+    /// Register a service-defined error code in the catalog.
+    pub fn error_code(
+        mut self,
+        code: impl Into<String>,
+        status: u16,
+        description: impl Into<String>,
+    ) -> Self {
+        self.spec
+            .error_codes
+            .get_or_insert_with(ErrorCatalog::standard)
+            .register(ErrorCodeInfo::new(code, status, description));
+        self
+    }
+
+    /// Register every code of a `CodedError` in the catalog.
+    pub fn coded_error<E: CodedError>(mut self) -> Self {
+        self.spec
+            .error_codes
+            .get_or_insert_with(ErrorCatalog::standard)
+            .register_error::<E>();
+        self
+    }
+
+    /// Add a fixed response header to a route.
+    ///
+    /// The header is added to every response of the route (unless the handler
+    /// sets it) and documented on the operation's responses. Use this for
+    /// routes that aren't declared through `#[route(header(...))]`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.response_header("GET", "/api/v1/projects/{id}", "Cache-Control", "no-store")
+    /// ```
+    pub fn response_header(
+        mut self,
+        method: impl Into<String>,
+        path: impl Into<String>,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.spec.static_headers.insert(RouteHeaders {
+            method: method.into(),
+            path: path.into(),
+            headers: vec![(name.into(), value.into())],
+        });
+        self
+    }
+
+    /// Authorize every request with a policy engine (OPA, Cedar, or custom).
+    ///
+    /// The engine receives the subject (user ID, scopes), the action (HTTP
+    /// method) and the resource (route template, path parameters) and may
+    /// deny the request with 403. The subject is read from the bearer token
+    /// verified with the `JwtConfig` of `auth`, on every route; requests
+    /// without a token are anonymous.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::authorization::opa::OpaEngine;
+    ///
+    /// EywaApp::new(state)
+    ///     .request_context()
+    ///     .mount::<ProjectsController>()
+    ///     .authorize(OpaEngine::new("http://localhost:8181", "eywa/projects/allow"))
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn authorize(mut self, engine: impl PolicyEngine) -> Self {
+        self.enable("authorization");
+        self.policy_engine = Some(std::sync::Arc::new(engine));
+        self
+    }
+
+    /// Register a GDPR data subject handler.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.data_subject_handler(ProjectsPrivacy { db: db.clone() })
+    /// ```
+    pub fn data_subject_handler(mut self, handler: impl DataSubjectHandler) -> Self {
+        self.privacy.register(std::sync::Arc::new(handler));
+        self
+    }
+
+    /// Add GDPR data subject admin endpoints.
+    ///
+    /// Adds two endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `privacy:admin` scope:
+    /// - `/privacy/export` - Export a user's data from every registered handler
+    /// - `/privacy/erase` - Erase a user's data in every registered handler
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .data_subject_handler(BillingPrivacy::new(db))
+    ///     .auth(config.jwt.clone())
+    ///     .privacy_endpoints()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn privacy_endpoints(mut self) -> Self {
+        for path in ["/privacy/export", "/privacy/erase"] {
+            self.spec.scopes.insert(RouteScopes {
+                method: "POST".to_string(),
+                path: path.to_string(),
+                scopes: vec![PRIVACY_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            PrivacyController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            PrivacyController::register_schemas(components);
+        }));
+
+        self.has_privacy_endpoints = true;
+        self
+    }
+
+    /// Serve long-running operations at `GET /operations/{id}`.
+    ///
+    /// Handlers extract `Operations` to start background work and answer
+    /// `202 Accepted` with the operation's `Location`. An operation started
+    /// by an authenticated request is only served to a bearer token with the
+    /// same subject, verified with the `JwtConfig` of `auth`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ExportsController>()
+    ///     .operations(Operations::new().retention(Duration::from_secs(24 * 3600)))
+    ///     .layer(auth_middleware())
+    /// ```
+    pub fn operations(mut self, operations: Operations) -> Self {
+        self.spec.path_fns.push(Box::new(|openapi| {
+            OperationsController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            OperationsController::register_schemas(components);
+        }));
+
+        let endpoints = self.spec_checks.wrap(OperationsController::router(operations.clone()));
+        self.router = self.router.merge(self.jwt.identify(endpoints));
+        self.operations = Some(operations);
+        self
+    }
+
+    /// Add dead letter admin endpoints.
+    ///
+    /// Adds endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `dead_letters:admin` scope, to list, inspect, discard
+    /// and re-drive the dead letters of `store`, republishing them with
+    /// `redriver`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let dead_letters = DeadLetterStore::new(db.clone());
+    /// dead_letters.create_table().await?;
+    /// dead_letters.spawn_depth_metrics(Duration::from_secs(60));
+    ///
+    /// let producer = state.producer.clone();
+    /// EywaApp::new(state)
+    ///     .mount::<InvoiceController>()
+    ///     .dead_letter_endpoints(dead_letters, move |message: DeadLetter| {
+    ///         let producer = producer.clone();
+    ///         async move { producer.send(&message.topic, message.key, message.payload).await }
+    ///     })
+    /// ```
+    pub fn dead_letter_endpoints(
+        mut self,
+        store: DeadLetterStore,
+        redriver: impl Redriver,
+    ) -> Self {
+        for (method, path) in DeadLetterController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![DEAD_LETTERS_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            DeadLetterController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            DeadLetterController::register_schemas(components);
+        }));
+
+        let endpoints = DeadLetterController::router(store, redriver);
+        self.router = self.router.merge(self.protect(endpoints));
+        self
+    }
+
+    /// Add kill switch admin endpoints.
+    ///
+    /// Adds endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `kill_switches:admin` scope, to list the killable
+    /// routes and disable or enable them during an incident.
+    pub fn kill_switch_endpoints(mut self) -> Self {
+        for (method, path) in KillSwitchController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![KILL_SWITCHES_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            KillSwitchController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            KillSwitchController::register_schemas(components);
+        }));
+
+        let switches = self.kill_switches.clone();
+        self.router = self.router.merge(self.protect(KillSwitchController::router(switches)));
+        self
+    }
+
+    /// Add log level admin endpoints.
+    ///
+    /// Adds `GET` and `PUT /admin/log-level`, requiring a token verified with
+    /// the `JwtConfig` of `auth`, with the `log_level:admin` scope, to read
+    /// and change the log filter at runtime without a redeploy.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let log_level = eywa_axum::telemetry::init_tracing(&config.telemetry)?;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .auth(config.jwt.clone())
+    ///     .log_level_endpoints(log_level)
+    /// ```
+    pub fn log_level_endpoints(mut self, log_level: LogLevel) -> Self {
+        for (method, path) in LogLevelController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![LOG_LEVEL_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            LogLevelController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            LogLevelController::register_schemas(components);
+        }));
+
+        self.router = self.router.merge(self.protect(LogLevelController::router(log_level)));
+        self
+    }
+
+    /// Add job admin endpoints.
+    ///
+    /// Adds endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `jobs:admin` scope, to list the jobs of the
+    /// `scheduler`, run one now, pause or resume one and inspect its recent
+    /// failures, on the replica serving the request.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .scheduler(Scheduler::new().job(purge_sessions))
+    ///     .job_endpoints()
+    /// ```
+    pub fn job_endpoints(mut self) -> Self {
+        for (method, path) in JobController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![JOBS_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            JobController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            JobController::register_schemas(components);
+        }));
+
+        let endpoints = JobController::router(self.jobs.clone());
+        self.router = self.router.merge(self.protect(endpoints));
+        self
+    }
+
+    /// Count the calls of deprecated operations per client.
+    ///
+    /// Operations marked `deprecated` in the spec are counted by the
+    /// `eywa_deprecated_calls_total{method, route, client}` metric, and
+    /// `GET /admin/deprecations`, requiring a token verified with the
+    /// `JwtConfig` of `auth`, with the `deprecations:admin` scope, reports
+    /// this replica's calls since startup (see `deprecations`).
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .deprecation_analytics(Deprecations::new().client_header("x-app-name"))
+    /// ```
+    pub fn deprecation_analytics(mut self, deprecations: Deprecations) -> Self {
+        self.enable("deprecation_analytics");
+        for (method, path) in DeprecationController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![DEPRECATIONS_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            DeprecationController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            DeprecationController::register_schemas(components);
+        }));
+
+        let endpoints = DeprecationController::router(deprecations.clone());
+        self.router = self.router.merge(self.protect(endpoints));
+        self.deprecations = Some(deprecations);
+        self
+    }
+
+    /// Add webhook subscription endpoints.
+    ///
+    /// Adds endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `webhooks:manage` scope, for consumers to subscribe
+    /// URLs to events, list, verify and delete their subscriptions (see
+    /// `webhooks`). Subscriptions belong to the token's subject.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let webhooks = WebhookSubscriptions::new(db.clone())
+    ///     .events(["project.created", "project.deleted"]);
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .webhook_endpoints(webhooks.clone())
+    /// ```
+    pub fn webhook_endpoints(mut self, store: WebhookSubscriptions) -> Self {
+        for (method, path) in WebhookController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![WEBHOOKS_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            WebhookController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            WebhookController::register_schemas(components);
+        }));
+
+        self.router = self.router.merge(self.protect(WebhookController::router(store)));
+        self
+    }
+
+    /// Serve static assets under content-hashed names.
+    ///
+    /// Fingerprinted paths are cached for a year as immutable, the manifest
+    /// at `{prefix}/manifest.json` maps logical names to them, and with
+    /// `StaticAssets::spa_fallback` unmatched `GET` requests get the SPA entry
+    /// point. The routes are not documented in the OpenAPI spec.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .static_assets(StaticAssets::new().dir("web/dist").await?.spa_fallback("index.html"))
+    /// ```
+    pub fn static_assets(mut self, assets: StaticAssets) -> Self {
+        self.router = self.router.merge(assets.router());
+        self
+    }
+
+    /// Add API key admin endpoints.
+    ///
+    /// Adds endpoints requiring a token verified with the `JwtConfig` of
+    /// `auth`, with the `api_keys:admin` scope, to issue, list, rotate and
+    /// revoke the keys of `store`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let keys = ApiKeyStore::new(db.clone());
+    /// keys.create_table().await?;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<PartnerController>()
+    ///     .api_key_auth(ApiKeyAuth::new(keys.clone()))
+    ///     .auth(config.jwt.clone())
+    ///     .api_key_endpoints(keys)
+    /// ```
+    #[cfg(feature = "api-keys")]
+    pub fn api_key_endpoints(mut self, store: ApiKeyStore) -> Self {
+        for (method, path) in ApiKeyController::ROUTES {
+            self.spec.scopes.insert(RouteScopes {
+                method: method.to_string(),
+                path: path.to_string(),
+                scopes: vec![API_KEYS_ADMIN_SCOPE.to_string()],
+            });
+        }
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            ApiKeyController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            ApiKeyController::register_schemas(components);
+        }));
+
+        self.router = self.router.merge(self.protect(ApiKeyController::router(store)));
+        self
+    }
+
+    /// Require an active API key on the routes registered so far.
+    ///
+    /// Handlers can read the key's metadata with `Extension<ApiKeyInfo>`.
+    #[cfg(feature = "api-keys")]
+    pub fn api_key_auth(mut self, auth: ApiKeyAuth) -> Self {
+        self.enable("api_key_auth");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(auth),
+            api_key_auth_middleware,
+        ));
+        self
+    }
+
+    /// Decrypt `Encrypted<T>` requests and encrypt `Encrypted<T>` responses
+    /// with `keys`, on the routes registered so far.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<PartnerPaymentsController>()
+    ///     .jwe(JweKeys::new(&config.jwe)?)
+    /// ```
+    #[cfg(feature = "jwe")]
+    pub fn jwe(mut self, keys: JweKeys) -> Self {
+        self.enable("jwe");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(keys),
+            jwe_middleware,
+        ));
+        self
+    }
+
+    /// Require a valid JWT on the routes registered so far.
+    ///
+    /// Verifies the bearer token's signature (HS256, with the secret of
+    /// `config`) and expiry, rejecting the request with 401 otherwise. The
+    /// token's `sub` claim is the authenticated `UserId`, copied into
+    /// `RequestContext::user_id` and the request's log span, so handlers,
+    /// audit columns and logs see the user. Works with `request_context()`
+    /// added before or after. Handlers can still extract the `JwtService`.
+    ///
+    /// Routes declared `#[route(public)]` stay open, as their spec says.
+    ///
+    /// The first `config` also verifies the tokens of the routes requiring
+    /// scopes, wherever they are mounted; without it the app fails to build.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .auth(JwtConfig::new(config.jwt_secret.clone()))
+    ///     .request_context()
+    /// ```
+    pub fn auth(mut self, config: JwtConfig) -> Self {
+        self.enable("auth");
+        let verifier = JwtVerifier::new(&config);
+        self.jwt.set(verifier.clone());
+        let mut public = RouteSet::new();
+        for route in &self.spec.route_auth {
+            if route.requirement == AuthRequirement::Public {
+                public.insert(&route.method, route.path.clone());
+            }
+        }
+        let router = self.router.layer(axum::middleware::from_fn(user_context_middleware));
+        self.router = SharedVerifier::from(verifier)
+            .protect_except(router, public)
+            .layer(axum::Extension(config.service()));
+        self
+    }
+
+    /// Merge another Router into this one.
+    pub fn merge(mut self, other: Router<S>) -> Self {
+        self.router = self.router.merge(other);
+        self
+    }
+
+    /// Apply a middleware layer.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<axum::routing::Route> + Clone + Send + Sync + 'static,
+        L::Service: tower::Service<axum::extract::Request> + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Future: Send + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Response:
+            axum::response::IntoResponse + 'static,
+        <L::Service as tower::Service<axum::extract::Request>>::Error:
+            Into<std::convert::Infallible> + 'static,
+    {
+        self.router = self.router.layer(layer);
+        self
+    }
+
+    /// Register the application's database connection.
+    ///
+    /// Used by the readiness probe, so call it before `.health_checks()`.
+    /// Any `DatabaseMode` works, including in-memory SQLite and mock
+    /// connections for tests.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let db = DatabaseSettings::in_memory().connect().await?;
+    ///
+    /// EywaApp::new(AppState::new(db.clone()))
+    ///     .database(db)
+    ///     .health_checks()
+    /// ```
+    pub fn database(mut self, db: sea_orm::DatabaseConnection) -> Self {
+        self.database = Some(db);
+        self
+    }
+
+    /// Register the application's search cluster.
+    ///
+    /// Its health is part of the readiness probe, so call it before
+    /// `.health_checks()`: `/health/ready` answers `503` while the cluster
+    /// is unreachable or red.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let search = SearchClient::from_settings(&config.search, http.clone());
+    ///
+    /// EywaApp::new(AppState::new(search.clone()))
+    ///     .search(search)
+    ///     .health_checks()
+    /// ```
+    pub fn search(mut self, client: SearchClient) -> Self {
+        self.search = Some(client);
+        self
+    }
+
+    /// Run a task after the listener is bound, before the service reports ready.
+    ///
+    /// Until every warmup task finished, `/health/ready` and `/health/startup`
+    /// answer `503`, so the first real requests don't pay for cold caches or
+    /// connections. Tasks run concurrently; failures are logged.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let catalog = state.catalog.clone();
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<CatalogController>()
+    ///     .health_checks()
+    ///     .warmup("catalog cache", move || async move { catalog.prime().await })
+    /// ```
+    pub fn warmup<F, Fut>(mut self, name: impl Into<String>, task: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = crate::Result<()>> + Send + 'static,
+    {
+        self.warmup.add(name, task);
+        self
+    }
+
+    /// Add health check endpoints for Kubernetes probes.
+    ///
+    /// Adds four endpoints:
+    /// - `/health` - Basic health check (always returns 200 OK)
+    /// - `/health/ready` - Readiness probe (pings the database registered via
+    ///   `.database()` and the search cluster registered via `.search()`, 503
+    ///   until the `.warmup()` tasks completed)
+    /// - `/health/live` - Liveness probe (always returns 200 OK)
+    /// - `/health/startup` - Startup probe (503 until the `.warmup()` tasks completed)
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .health_checks()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn health_checks(self) -> Self {
+        self.health_checks_with(HealthChecks::new())
+    }
+
+    /// Add health check endpoints whose readiness probe also checks `checks`.
+    ///
+    /// The named checks (database, Redis, downstream services) run
+    /// concurrently on every `/health/ready` request, each bounded by its
+    /// timeout, and are reported under `checks.components`. One unhealthy
+    /// component answers 503 Service Unavailable.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .health_checks_with(
+    ///         HealthChecks::new()
+    ///             .add("redis", redis.clone())
+    ///             .add("billing", HttpCheck::new(http.clone(), "http://billing/health/live")),
+    ///     )
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn health_checks_with(mut self, checks: HealthChecks) -> Self {
+        use crate::health::HealthController;
+
+        let mut endpoints: Router<S> = Router::new()
+            .route("/health", get(HealthController::health))
+            .route("/health/live", get(HealthController::live));
+
+        let database = self.database.clone();
+        let search = self.search.clone();
+        let progress = self.warmup.progress();
+        endpoints = endpoints.route(
+            "/health/ready",
+            get(move || {
+                let (database, search, progress, checks) =
+                    (database.clone(), search.clone(), progress.clone(), checks.clone());
+                async move {
+                    let ready =
+                        HealthController::ready_with_warmup(database.as_ref(), &progress).await;
+                    let ready = match search {
+                        Some(search) => HealthController::with_search(ready, search.health().await),
+                        None => ready,
+                    };
+                    HealthController::with_components(ready, checks.run().await)
+                }
+            }),
+        );
+        let progress = self.warmup.progress();
+        endpoints = endpoints.route(
+            "/health/startup",
+            get(move || {
+                let progress = progress.clone();
+                async move { HealthController::startup_with_warmup(&progress) }
+            }),
+        );
+        self.router = self.router.merge(self.spec_checks.wrap(endpoints));
+
+        self.spec.path_fns.push(Box::new(|openapi| {
+            HealthController::register_paths(openapi);
+        }));
+
+        self.spec.schema_fns.push(Box::new(|components| {
+            HealthController::register_schemas(components);
+        }));
+
+        self.has_health_checks = true;
+        self
+    }
+
+    /// Enable response compression using gzip, deflate, and brotli.
+    ///
+    /// Automatically compresses responses based on Accept-Encoding header.
+    /// Typically reduces response size by 70-90% for JSON/text content.
+    /// Uses the default `CompressionSettings`; see `compression_with`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .compression()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn compression(self) -> Self {
+        self.compression_with(CompressionSettings::default())
+    }
+
+    /// Enable response compression with the given settings.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::compression::CompressionSettings;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .compression_with(CompressionSettings::default().min_size(1024).without_brotli())
+    /// ```
+    pub fn compression_with(mut self, settings: CompressionSettings) -> Self {
+        self.enable("compression");
+        self.router = self.router.layer(settings.layer());
+        self
+    }
+
+    /// Decompress gzip and deflate request bodies.
+    ///
+    /// Decompressed bodies larger than `settings.max_size` are rejected with
+    /// `413 Payload Too Large`, so a small compressed payload can't expand
+    /// without bound. Applies to the routes registered so far.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::decompression::DecompressionSettings;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<TelemetryController>()
+    ///     .request_decompression(DecompressionSettings::default().max_size(2 * 1024 * 1024))
+    /// ```
+    pub fn request_decompression(mut self, settings: DecompressionSettings) -> Self {
+        self.enable("request_decompression");
+        use tower::ServiceBuilder;
+        use tower_http::decompression::RequestDecompressionLayer;
+
+        self.router = self.router.layer(
+            ServiceBuilder::new()
+                .layer(axum::middleware::from_fn(mark_compressed_middleware))
+                .layer(
+                    RequestDecompressionLayer::new()
+                        .gzip(settings.gzip)
+                        .deflate(settings.deflate),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    settings.max_size,
+                    limit_decompressed_middleware,
+                )),
+        );
+        self
+    }
+
+    /// Cap request bodies at `limit` bytes, following reloads of the limit.
+    ///
+    /// Replaces axum's default 2 MB limit. Requests with a larger body are
+    /// rejected with `413 Payload Too Large` when the handler reads it.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let limit = Watch::named("body_limit", config.body_limit);
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<UploadsController>()
+    ///     .body_limit(limit.clone())
+    /// ```
+    pub fn body_limit(mut self, limit: impl Into<Watch<usize>>) -> Self {
+        self.enable("body_limit");
+        self.router = self
+            .router
+            .layer(axum::extract::DefaultBodyLimit::disable())
+            .layer(axum::middleware::from_fn_with_state(limit.into(), body_limit_middleware));
+        self
+    }
+
+    /// Limit the body size, header size, duration and concurrency of requests.
+    ///
+    /// Oversized requests get `413 Payload Too Large` or `431 Request Header
+    /// Fields Too Large`, requests running past the timeout `408 Request
+    /// Timeout`, and requests over the concurrency limit `503 Service
+    /// Unavailable`. `LimitsConfig::default()` fits most JSON APIs; the body
+    /// limit replaces axum's default 2 MB limit. Applies to the routes
+    /// registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<UploadsController>()
+    ///     .limits(LimitsConfig::default().max_body_bytes(10 * 1024 * 1024))
+    /// ```
+    pub fn limits(mut self, config: LimitsConfig) -> Self {
+        self.enable("limits");
+        self.router = self
+            .router
+            .layer(axum::extract::DefaultBodyLimit::disable())
+            .layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(Limits::new(config)),
+                limits_middleware,
+            ));
+        self.spec.retry_after_statuses.insert(503);
+        self
+    }
+
+    /// Enable structured request logging compatible with Loki/Grafana.
+    ///
+    /// Logs HTTP method, path, correlation ID, status code, and latency.
+    /// Should be called after `.request_context()` to include correlation IDs.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .request_context()
+    ///     .request_logging()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn request_logging(mut self) -> Self {
+        self.enable("request_logging");
+        use crate::middleware::request_logging_middleware;
+
+        self.router = self.router.layer(request_logging_middleware());
+        self
+    }
+
+    /// Fill the audit columns of entities saved during a request.
+    ///
+    /// Runs each request with an `AuditContext` from `RequestContext::user_id`
+    /// and the verified tenant (the `tenant_id` claim, or an `AuditTenant`),
+    /// used by `audit::stamp` in `before_save`. Applies to
+    /// the routes registered so far; call it before `.request_context()` and
+    /// the authentication layer, which must run first.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<DocumentsController>()
+    ///     .audit_columns()
+    ///     .layer(auth_middleware())
+    ///     .request_context()
+    /// ```
+    pub fn audit_columns(mut self) -> Self {
+        self.enable("audit_columns");
+        self.router = self.router.layer(axum::middleware::from_fn(audit_context_middleware));
+        self
+    }
+
+    /// Format `LocalizedDecimal`, `LocalizedDate` and `LocalizedDateTime` for
+    /// the request's locale.
+    ///
+    /// The locale is negotiated from `Accept-Language`, or set explicitly with
+    /// `?format=de-DE` (`?format=raw` for machine formats).
+    /// Applies to the routes registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<InvoicesController>()
+    ///     .localized_formats()
+    /// ```
+    pub fn localized_formats(mut self) -> Self {
+        self.enable("localized_formats");
+        self.router = self.router.layer(axum::middleware::from_fn(locale_middleware));
+        self
+    }
+
+    /// Enable request context propagation (correlation ID, user ID, language).
+    ///
+    /// Extracts request metadata from headers and makes it available to handlers
+    /// via `Extension<RequestContext>`. Should be called before `.request_logging()`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::RequestContext;
+    ///
+    /// async fn handler(
+    ///     Extension(ctx): Extension<RequestContext>,
+    /// ) -> Result<Json<Value>> {
+    ///     info!("Handling request {}", ctx.correlation_id);
+    ///     // ...
+    /// }
+    ///
+    /// EywaApp::new(state)
+    ///     .request_context()
+    ///     .mount::<MyController>()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn request_context(mut self) -> Self {
+        self.enable("request_context");
+        use crate::middleware::request_context_middleware_fn;
+
+        self.router = self
+            .router
+            .layer(axum::middleware::from_fn(request_context_middleware_fn));
+        self
+    }
+
+    /// Forward, reflect and strip headers as declared by `policy`.
+    ///
+    /// Stripped headers are removed from requests before the handlers run and
+    /// from responses. The propagated headers are set on `RequestContext` and
+    /// sent by `OutboundClient`; reflected ones are copied onto the response.
+    /// Applies to the routes registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .request_context()
+    ///     .header_policy(config.headers.clone())
+    /// ```
+    pub fn header_policy(mut self, policy: HeaderPolicy) -> Self {
+        self.enable("header_policy");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(policy),
+            header_policy_middleware,
+        ));
+        self
+    }
+
+    /// Normalize request paths before routing.
+    ///
+    /// Without a policy, paths are matched as sent: `/projects/` doesn't
+    /// match `/projects`. Case-insensitive matching applies to the static
+    /// segments of the documented routes.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::paths::{PathPolicy, TrailingSlash};
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .path_policy(PathPolicy::new().trailing_slash(TrailingSlash::Redirect))
+    /// ```
+    pub fn path_policy(mut self, policy: PathPolicy) -> Self {
+        self.enable("path_policy");
+        self.path_policy = Some(policy);
+        self
+    }
+
+    /// Answer `OPTIONS` with the allowed methods, and optionally document
+    /// `HEAD` and `OPTIONS` operations in the spec.
+    ///
+    /// `HEAD` is answered on every `GET` route regardless (axum runs the `GET`
+    /// handler and drops the body).
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::methods::AutoMethods;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .auto_methods(AutoMethods::new().document_head())
+    /// ```
+    pub fn auto_methods(mut self, methods: AutoMethods) -> Self {
+        self.enable("auto_methods");
+        self.spec.auto_methods = Some(methods);
+        self
+    }
+
+    /// Route `POST` requests with `X-HTTP-Method-Override` as the method it names.
+    ///
+    /// For clients behind proxies that only allow `GET` and `POST`. Overrides
+    /// are logged with the `eywa_axum::method_override` target for auditing.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::method_override::MethodOverride;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .method_override(MethodOverride::only([Method::PUT, Method::DELETE]))
+    /// ```
+    pub fn method_override(mut self, settings: MethodOverride) -> Self {
+        self.enable("method_override");
+        self.method_override = Some(settings);
+        self
+    }
+
+    /// Enforce request deadlines as handler timeouts.
+    ///
+    /// The deadline comes from the caller (`X-Request-Deadline` or
+    /// `grpc-timeout`), or from `config` for requests without one, and is
+    /// capped by its maximum budget. It is set on `RequestContext` and
+    /// forwarded by `OutboundClient`. Requests that run out of budget return
+    /// `504 Gateway Timeout`. Pass a `Watch` to reload the config.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::deadline::DeadlineConfig;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .request_context()
+    ///     .request_deadlines(DeadlineConfig::new().default_budget(Duration::from_secs(10)))
+    /// ```
+    pub fn request_deadlines(mut self, config: impl Into<Watch<DeadlineConfig>>) -> Self {
+        self.enable("request_deadlines");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            config.into(),
+            deadline_middleware,
+        ));
+        self
+    }
+
+    /// Declare a bulkhead: a pool of at most `max_concurrency` in-flight requests.
+    ///
+    /// Requests to the routes assigned to a full bulkhead are rejected with
+    /// `503 Service Unavailable`, so an expensive route group can't starve
+    /// the rest of the service. Assign routes with `#[route(bulkhead = "...")]`,
+    /// `bulkhead_path` or `route_bulkhead`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ReportsController>()
+    ///     .bulkhead("reports", 4)
+    ///     .bulkhead_path("reports", "/api/v1/reports/**")
+    /// ```
+    pub fn bulkhead(mut self, name: impl Into<String>, max_concurrency: usize) -> Self {
+        self.bulkheads.define(name, max_concurrency);
+        self.spec.retry_after_statuses.insert(503);
+        self
+    }
+
+    /// Assign every route whose path matches the glob to a bulkhead.
+    pub fn bulkhead_path(mut self, name: &str, pattern: impl Into<String>) -> Self {
+        self.bulkheads.assign_pattern(name, pattern);
+        self
+    }
+
+    /// Assign a single route to a bulkhead.
+    ///
+    /// Use this for routes that aren't declared through `#[route(bulkhead = "...")]`.
+    pub fn route_bulkhead(mut self, method: &str, path: impl Into<String>, name: &str) -> Self {
+        self.bulkheads.assign(name, method, path);
+        self
+    }
+
+    /// Declare the latency and availability objectives of a route.
+    ///
+    /// Use this for routes that aren't declared through `#[route(slo_latency_ms = ...)]`.
+    /// Requests to the route count good and bad `eywa_slo_events_total` events
+    /// for multi-window burn-rate alerts.
+    ///
+    /// ```rust,ignore
+    /// app.route_slo(
+    ///     "GET",
+    ///     "/api/v1/projects/{id}",
+    ///     Slo::latency(Duration::from_millis(300)).with_availability(99.9),
+    /// )
+    /// ```
+    pub fn route_slo(mut self, method: &str, path: impl Into<String>, slo: Slo) -> Self {
+        self.slos.insert(method, path, slo);
+        self
+    }
+
+    /// Allow a single route to be disabled by a kill switch.
+    ///
+    /// Use this for routes that aren't declared through `#[route(killable)]`.
+    pub fn killable_route(self, method: &str, path: &str) -> Self {
+        self.kill_switches.mark_killable(method, path);
+        self
+    }
+
+    /// Use `switches` as the kill switches of the killable routes.
+    ///
+    /// Keep a clone to disable routes from a config reload or a signal
+    /// handler. Without it, routes are only disabled through
+    /// `.kill_switch_endpoints()`, which must come after this call.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let switches = KillSwitches::new();
+    /// switches.apply(&config.kill_switches);
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ExportsController>()
+    ///     .kill_switches(switches.clone())
+    ///     .kill_switch_endpoints()
+    /// ```
+    pub fn kill_switches(mut self, switches: KillSwitches) -> Self {
+        switches.absorb(&self.kill_switches);
+        self.kill_switches = switches;
+        self
+    }
+
+    /// Serve part of a route's traffic with an alternate handler.
+    ///
+    /// Requests are assigned by the `X-Canary` header or `canary` cookie, then
+    /// by the canary's percentage. The assignment is stored in
+    /// `RequestContext::canary` and added to the request's log lines.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::canary::Canary;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<CheckoutController>()
+    ///     .request_context()
+    ///     .canary(
+    ///         "POST",
+    ///         "/api/v1/checkout",
+    ///         Canary::new("checkout-v2").percent(5.0).sticky_on("x-tenant-id"),
+    ///         post(checkout_v2),
+    ///     )
+    /// ```
+    pub fn canary(
+        mut self,
+        method: &str,
+        path: impl Into<String>,
+        canary: Canary,
+        handler: axum::routing::MethodRouter<S>,
+    ) -> Self {
+        self.canaries
+            .push((method.to_uppercase(), path.into(), canary, handler));
+        self
+    }
+
+    /// Assign the A/B experiment variants of every request.
+    ///
+    /// Variants are stored in `RequestContext::experiments`, extracted with
+    /// `Assignments` and returned in the `X-Experiments` header. Applies to
+    /// the routes registered so far; call it before `.request_context()` and
+    /// the authentication layer, which must run first.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<OnboardingController>()
+    ///     .experiments(Experiments::new(config.experiments.clone()))
+    ///     .layer(auth_middleware())
+    ///     .request_context()
+    /// ```
+    pub fn experiments(mut self, experiments: Experiments) -> Self {
+        self.enable("experiments");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(experiments),
+            experiments_middleware,
+        ));
+        self
+    }
+
+    /// Limit in-flight requests with a limit adjusted from observed latency.
+    ///
+    /// Requests above the current limit are shed with `503 Service Unavailable`
+    /// and a `Retry-After` of the average latency.
+    /// Applies to the routes registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::adaptive::AdaptiveConcurrency;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<SearchController>()
+    ///     .adaptive_concurrency(AdaptiveConcurrency::gradient().max_limit(500))
+    /// ```
+    pub fn adaptive_concurrency(mut self, config: AdaptiveConcurrency) -> Self {
+        self.enable("adaptive_concurrency");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(AdaptiveLimiter::new(config)),
+            adaptive_concurrency_middleware,
+        ));
+        self.spec.retry_after_statuses.insert(503);
+        self
+    }
+
+    /// Limit in-flight requests, queueing bursts briefly before shedding them.
+    ///
+    /// Up to `depth` requests wait at most `max_wait` for a slot; others get
+    /// `503 Service Unavailable`. Queue depth and wait are exported as
+    /// metrics. Applies to the routes registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::queue::AdmissionSettings;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<CheckoutController>()
+    ///     .admission_queue(AdmissionSettings::default().max_concurrency(32).depth(64))
+    /// ```
+    pub fn admission_queue(mut self, settings: AdmissionSettings) -> Self {
+        self.enable("admission_queue");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(AdmissionQueue::new(settings)),
+            admission_middleware,
+        ));
+        self.spec.retry_after_statuses.insert(503);
+        self
+    }
+
+    /// Limit requests per client IP, tenant or API key.
+    ///
+    /// Each key gets the quota of its tier, assigned from the settings or
+    /// a `TierStore`. Limited requests return `429 Too Many Requests` with
+    /// a `Retry-After` of the time until the next token.
+    /// Applies to the routes registered so far; add it after mounting.
+    ///
+    /// Tenants are read from the tokens verified with the `JwtConfig` of
+    /// `auth`; API keys must be verified by `api_key_auth`, added after the
+    /// rate limit so it runs first. Requests without either are limited by IP.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::rate_limit::RateLimiter;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .rate_limit(RateLimiter::new(config.rate_limit.clone()).store(keys.clone()))
+    ///     .api_key_auth(ApiKeyAuth::new(keys))
+    /// ```
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.enable("rate_limit");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(limiter.verifier(self.jwt.clone())),
+            rate_limit_middleware,
+        ));
+        self.spec.retry_after_statuses.insert(429);
+        self
+    }
+
+    /// Reject requests with `503 Service Unavailable` while `mode` is enabled.
+    ///
+    /// Applies to the routes registered so far: add it before
+    /// `.health_checks()` to keep the probes answering during maintenance.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::maintenance::MaintenanceMode;
+    ///
+    /// let maintenance = MaintenanceMode::new();
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .maintenance(maintenance.clone())
+    ///     .health_checks()
+    /// ```
+    pub fn maintenance(mut self, mode: MaintenanceMode) -> Self {
+        self.enable("maintenance");
+        self.router = self
+            .router
+            .layer(axum::middleware::from_fn_with_state(mode, maintenance_middleware));
+        self.spec.retry_after_statuses.insert(503);
+        self
+    }
+
+    /// Export per-request usage records to the analytics pipeline.
+    ///
+    /// Applies to the routes registered so far; add it after mounting.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::analytics::Analytics;
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .analytics(Analytics::from_settings(&config.analytics, http.clone()))
+    /// ```
+    pub fn analytics(mut self, analytics: Analytics) -> Self {
+        self.enable("analytics");
+        self.router = self
+            .router
+            .layer(axum::middleware::from_fn_with_state(analytics, analytics_middleware));
+        self
+    }
+
+    /// Add a `Server-Timing` header with middleware, handler and custom phases.
+    ///
+    /// Covers every route and the whole middleware stack, wherever it is
+    /// called. Handlers record their own phases with the `ServerTimings`
+    /// extractor.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .server_timing()
+    /// ```
+    pub fn server_timing(mut self) -> Self {
+        self.enable("server_timing");
+        self.has_server_timing = true;
+        self
+    }
+
+    /// Reject requests whose media types the spec doesn't document.
+    ///
+    /// Request bodies outside the operation's `requestBody` content get a
+    /// `415 Unsupported Media Type`, and `Accept` headers excluding every
+    /// media type of its successful responses a `406 Not Acceptable`. Both
+    /// responses are documented on the checked operations. Checked inside
+    /// the route's authentication and scopes, so unauthenticated requests
+    /// get their `401` first. See `middleware::content_types`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .strict_content_types(ContentTypePolicy::new().accept(false))
+    /// ```
+    pub fn strict_content_types(mut self, policy: ContentTypePolicy) -> Self {
+        self.enable("strict_content_types");
+        self.spec.content_types = Some(policy);
+        self
+    }
+
+    /// Check JSON bodies and query parameters against the constraints of
+    /// their schemas (`maxLength`, `pattern`, `minimum`, `required`, ...),
+    /// whatever extractor the handler uses.
+    ///
+    /// Violations get the `422` `ValidationErrorResponse` of `ValidatedJson`,
+    /// documented on the checked operations. Checked inside the route's
+    /// authentication and scopes, so unauthenticated requests get their
+    /// `401` first. See `middleware::constraints`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .enforce_schema_constraints()
+    /// ```
+    pub fn enforce_schema_constraints(mut self) -> Self {
+        self.enable("schema_constraints");
+        self.spec.enforces_constraints = true;
+        self
+    }
+
+    /// Record HTTP metrics per route and serve them at `GET /metrics`.
+    ///
+    /// Adds `http_requests_total`, the `http_request_duration_seconds`
+    /// histogram and the `http_requests_in_flight` gauge, labeled with the
+    /// method, the route template (never the raw path) and the status, in
+    /// the Prometheus text format of `eywa_metrics`. Applies to every route,
+    /// including the ones mounted afterwards, but not to the docs and
+    /// `/metrics` itself.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .metrics()
+    /// ```
+    pub fn metrics(mut self) -> Self {
+        self.enable("metrics");
+        self.has_metrics = true;
+        self
+    }
+
+    /// Record response size and serialization time histograms per route.
+    ///
+    /// Adds `eywa_http_response_size_bytes` (before compression) and
+    /// `eywa_http_response_serialization_seconds` to tell apart routes slow
+    /// to query from routes slow to encode. Serialization time is reported
+    /// by the crate's JSON responders (`TimedJson`, `BigJson`, `JsonStream`,
+    /// `JsonBytes::from_value`), not by `axum::Json`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .response_metrics()
+    /// ```
+    pub fn response_metrics(mut self) -> Self {
+        self.enable("response_metrics");
+        self.has_response_metrics = true;
+        self
+    }
+
+    /// Export a server span per request to an OpenTelemetry collector.
+    ///
+    /// Installs the OTLP exporter of `config` as the global tracer provider.
+    /// Spans continue the caller's W3C `traceparent`, are named after the
+    /// route template and cover the whole middleware stack, wherever it is
+    /// called. See `middleware::otel`.
+    ///
+    /// An exporter that can't be built is logged and tracing stays off.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .otel_tracing(OtelConfig::new("projects").endpoint("http://otel-collector:4317"))
+    /// ```
+    #[cfg(feature = "otel")]
+    pub fn otel_tracing(mut self, config: OtelConfig) -> Self {
+        match config.install() {
+            Ok(provider) => {
+                self.enable("otel_tracing");
+                self.otel = Some(crate::middleware::otel::server_tracer(&provider));
+            }
+            Err(e) => tracing::error!("OpenTelemetry tracing disabled: {}", e),
+        }
+        self
+    }
+
+    /// Wire RED metrics, request tracing and log correlation.
+    ///
+    /// Covers every route and the whole middleware stack, wherever it is
+    /// called, and includes `.request_context()` and `.request_logging()`:
+    /// don't add them as well. Requests are counted and timed in
+    /// `http_server_requests_total` and `http_server_request_duration_seconds`,
+    /// labeled by service, env, method, route template and status class, and
+    /// every log line carries `service` and `env` besides the request ids.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .observability(config.observability.clone())
+    /// ```
+    pub fn observability(mut self, settings: ObservabilitySettings) -> Self {
+        self.enable("observability");
+        self.observability = Some(settings);
+        self
+    }
+
+    /// Write JSON logs carrying the request's correlation fields.
+    ///
+    /// Installs the global subscriber with `logging::init_json`, and the
+    /// request context and access logs around the whole middleware stack,
+    /// so every event of a request, the access log included, carries its
+    /// `correlation_id`, `request_id` and `user_id` next to `service` and
+    /// `env`. Call it first, so the startup logs are JSON too, and don't
+    /// add `.request_context()` or `.request_logging()` as well.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .logging(config.logging.clone())
+    ///     .mount::<ProjectsController>()
+    /// ```
+    pub fn logging(mut self, config: LogConfig) -> Self {
+        self.enable("logging");
+        // Another subscriber may be installed already, e.g. by tests
+        if let Err(e) = init_json(&config) {
+            tracing::warn!("JSON logging not installed: {:?}", e);
+        }
+        self.has_logging = true;
+        self
+    }
+
+    /// Serve the admin routes on a separate listener.
+    ///
+    /// Bind it to a port that isn't exposed through the ingress: admin
+    /// routes (profiling, ...) have no authentication of their own.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .admin_listener("0.0.0.0:9090")
+    ///     .pprof()
+    ///     .serve("0.0.0.0:8080")
+    ///     .await
+    /// ```
+    pub fn admin_listener(mut self, addr: impl Into<String>) -> Self {
+        self.admin.bind(addr);
+        self
+    }
+
+    /// Add routes to the admin listener.
+    pub fn admin_routes(mut self, router: Router) -> Self {
+        self.admin.merge(router);
+        self
+    }
+
+    /// Serve CPU (and, with `pprof-heap`, heap) profiles on the admin listener.
+    ///
+    /// See `admin::pprof` for the endpoints.
+    #[cfg(feature = "pprof")]
+    pub fn pprof(self) -> Self {
+        self.admin_routes(crate::admin::pprof::router())
+    }
+
+    /// Return extractor rejections in the EYWA error envelope.
+    ///
+    /// axum's plain-text `JsonRejection`, `QueryRejection`, `PathRejection`
+    /// (etc.) responses are rewritten as `AppError::BadRequest`, and the `400`
+    /// response is documented on every operation. Layers wrap the routes added
+    /// before them, so call `.request_context()` afterwards to include the
+    /// request ID.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .map_rejections()
+    ///     .request_context()
+    /// ```
+    pub fn map_rejections(mut self) -> Self {
+        self.enable("map_rejections");
+        self.router = self.router.layer(axum::middleware::from_fn(rejection_middleware));
+        self.spec.maps_rejections = true;
+        self
+    }
+
+    /// Wrap successful JSON responses in a global envelope.
+    ///
+    /// The envelope is applied to every 2xx `application/json` body and to the
+    /// documented 2xx response schemas, so the spec (and mock mode) match what
+    /// clients receive. Error responses are left unchanged.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::envelope::DataEnvelope;
+    ///
+    /// // { "data": { ... }, "meta": { "correlation_id": "..." } }
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .response_envelope(DataEnvelope::new())
+    ///     .request_context()
+    /// ```
+    pub fn response_envelope(mut self, envelope: impl ResponseEnvelope) -> Self {
+        self.enable("response_envelope");
+        self.spec.envelope = Some(std::sync::Arc::new(envelope));
+        self
+    }
+
+    /// Record sanitized request/response pairs matching a filter.
+    ///
+    /// Intended for reproducing production-only bugs: captured exchanges can
+    /// be re-issued against a local build with `capture::replay`. Layers wrap
+    /// the routes added before them, so call `.request_context()` afterwards
+    /// to make correlation IDs available to the filter.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::capture::{CaptureConfig, CaptureFilter, FileCaptureStore};
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<MyController>()
+    ///     .capture(CaptureConfig::new(
+    ///         CaptureFilter::new().min_status(500),
+    ///         FileCaptureStore::new("/var/lib/eywa/captures"),
+    ///     ))
+    ///     .request_context()
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn capture(mut self, config: CaptureConfig) -> Self {
+        self.enable("capture");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(config),
+            capture_middleware,
+        ));
+        self
+    }
+
+    /// Inject faults (latency, error statuses, dropped connections) into a
+    /// percentage of matching requests.
+    ///
+    /// Only active when `RUN_MODE` is one of the configured environments
+    /// (development and staging by default); otherwise this is a no-op.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::middleware::chaos::{ChaosConfig, Fault, FaultRule};
+    ///
+    /// EywaApp::new(state)
+    ///     .mount::<MyController>()
+    ///     .chaos(ChaosConfig::new().rule(FaultRule::new(
+    ///         "/api/v1/projects*",
+    ///         0.1,
+    ///         Fault::Error(StatusCode::SERVICE_UNAVAILABLE),
+    ///     )))
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn chaos(mut self, config: ChaosConfig) -> Self {
+        let run_mode = std::env::var("RUN_MODE").unwrap_or_default();
+        if !config.is_allowed(&run_mode) {
+            tracing::warn!("🐒 Fault injection disabled in RUN_MODE={:?}", run_mode);
+            return self;
+        }
+
+        tracing::warn!("🐒 Fault injection enabled in RUN_MODE={}", run_mode);
+        self.enable("chaos");
+        self.router = self.router.layer(axum::middleware::from_fn_with_state(
+            std::sync::Arc::new(config),
+            chaos_middleware,
+        ));
+        self
+    }
+
+    /// Serve mock responses instead of calling the real handlers.
+    ///
+    /// Every documented operation answers with the example of its first 2xx
+    /// response, or a value derived from the response schema, so frontend
+    /// teams can develop against an unfinished backend. Routes that aren't
+    /// in the OpenAPI spec are not served.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .mock_mode(std::env::var("EYWA_MOCK").is_ok())
+    ///     .serve("0.0.0.0:3000")
+    ///     .await
+    /// ```
+    pub fn mock_mode(mut self, enabled: bool) -> Self {
+        self.mock_mode = enabled;
+        self
+    }
+
+    /// Select the API version from a request header.
+    ///
+    /// Requests carrying the header (e.g. `Api-Version: 2`) are routed to the
+    /// matching `v{n}` route without the version segment in the URL:
+    /// `GET /api/projects` is served by `/api/v2/projects`. Path-versioned
+    /// requests keep working unchanged.
+    ///
+    /// An invalid `header` name fails the build (see `into_router`).
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsV1>()
+    ///     .mount::<ProjectsV2>()
+    ///     .header_versioning(eywa_axum::versioning::DEFAULT_VERSION_HEADER)
+    /// ```
+    pub fn header_versioning(mut self, header: &str) -> Self {
+        self.enable("header_versioning");
+        match axum::http::HeaderName::try_from(header) {
+            Ok(header) => self.version_header = Some(header),
+            Err(_) => self.errors.push(format!("Invalid version header name '{header}'")),
+        }
+        self
+    }
+
+    /// Generate every `operationId` with a naming strategy.
+    ///
+    /// By default operations keep the handler name produced by utoipa, which
+    /// collides across controllers. The strategy sets the format and casing,
+    /// and colliding IDs get a stable numeric suffix.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use eywa_axum::operation_ids::{Casing, OperationIdStrategy};
+    ///
+    /// // `projects-get-api-v1-projects-by-id`
+    /// app.operation_ids(OperationIdStrategy::tag_method_path())
+    ///
+    /// // `getProject`
+    /// app.operation_ids(OperationIdStrategy::handler_name().casing(Casing::Camel))
+    /// ```
+    pub fn operation_ids(mut self, strategy: OperationIdStrategy) -> Self {
+        self.spec.operation_ids = Some(strategy);
+        self
+    }
+
+    /// Build the public OpenAPI specification of the application.
+    ///
+    /// This is the spec served at `/scalar`, without internal routes; use it
+    /// to export the document or generate clients (see `codegen`) without
+    /// starting the server.
+    pub fn openapi(&self) -> OpenApi {
+        self.spec.public()
+    }
+
+    /// Build the internal OpenAPI specification, including internal routes.
+    ///
+    /// This is the spec served at `/scalar/internal` (see `internal_docs`).
+    pub fn internal_openapi(&self) -> OpenApi {
+        self.spec.internal()
+    }
+
+    /// Build the final router without binding a listener.
+    ///
+    /// The OpenAPI specs are not assembled here: the docs endpoints assemble
+    /// them on first request and cache the serialized bytes (see `spec`).
+    /// Mock mode and header versioning need the spec to route requests, so
+    /// they assemble it eagerly.
+    ///
+    /// This method:
+    /// 1. Adds a `/scalar` endpoint for interactive API documentation, with
+    ///    the spec at `/api-docs/openapi.json` (and `.yaml`, see `spec_path`)
+    /// 2. Adds a `/swagger` endpoint if swagger-ui feature is enabled
+    /// 3. Adds the guarded `/scalar/internal` endpoint if configured
+    /// 4. Applies the application state
+    ///
+    /// # Errors
+    ///
+    /// Fails if a builder method was given an invalid setting, if routes
+    /// require scopes without `auth`, or if a service injected with
+    /// `Inject<T>` wasn't provided (except in mock mode, where handlers
+    /// don't run).
+    fn build(self) -> crate::Result<Router> {
+        if !self.errors.is_empty() {
+            return Err(eywa_errors::AppError::InternalServerError(format!(
+                "Invalid app configuration:\n  {}",
+                self.errors.join("\n  ")
+            )));
+        }
+        let mut router = self.router;
+
+        // Add privacy endpoints once every handler has been registered
+        if self.has_privacy_endpoints {
+            let endpoints = self.spec_checks.wrap(PrivacyController::router(self.privacy));
+            router = router.merge(self.jwt.protect(endpoints));
+        }
+
+        // Keep the registries enforced at runtime, then hand the rest to the docs
+        let envelope = self.spec.envelope.clone();
+        let static_headers = self.spec.static_headers.clone();
+        let scopes = self.spec.scopes.clone();
+        let error_codes = self.spec.error_codes.clone();
+        let auto_methods = self.spec.auto_methods;
+        let content_types = self.spec.content_types;
+        let enforces_constraints = self.spec.enforces_constraints;
+        let specs = std::sync::Arc::new(LazySpecs::new(self.spec));
+
+        // Replace the real handlers with spec-derived responses
+        if self.mock_mode {
+            info!("🎭 Mock mode: serving spec-derived responses");
+            router = self.spec_checks.wrap(crate::mock::router(specs.internal().openapi()));
+        }
+
+        // Send the requests assigned to a canary to its handler
+        if !self.canaries.is_empty() && !self.mock_mode {
+            let mut canaries = Canaries::new();
+            for (method, path, canary, handler) in self.canaries {
+                info!("🐤 Canary '{}' on {} {}", canary.name(), method, path);
+                let handler = Router::new().route(&path, handler).with_state(self.state.clone());
+                canaries.insert(&method, path, canary, handler);
+            }
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(canaries),
+                canary_middleware,
+            ));
+        }
+
+        // Time the matched route, inside the other build-time route layers
+        if self.has_server_timing {
+            router = router.route_layer(axum::middleware::from_fn(handler_timing_middleware));
+        }
+
+        // Run the routes assigned to a bulkhead within its concurrency limit
+        for name in self.bulkheads.undeclared() {
+            tracing::warn!("Routes assigned to undeclared bulkhead '{}' are not limited", name);
+        }
+        if !self.bulkheads.is_empty() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(self.bulkheads),
+                bulkhead_middleware,
+            ));
+        }
+
+        // Reject the requests to the routes disabled by a kill switch
+        for (method, path) in self.kill_switches.unknown() {
+            tracing::warn!("Kill switch disables {} {}, which isn't killable", method, path);
+        }
+        if !self.kill_switches.is_empty() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                self.kill_switches,
+                kill_switch_middleware,
+            ));
+        }
+
+        // Count SLO events outside the bulkhead, so rejected requests burn error budget
+        if !self.slos.is_empty() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(self.slos),
+                slo_middleware,
+            ));
+        }
+
+        // Wrap successful JSON bodies (mock responses already follow the enveloped spec)
+        if let Some(envelope) = envelope.filter(|_| !self.mock_mode) {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                envelope,
+                envelope_middleware,
+            ));
+        }
+
+        // Add static response headers to the routes that declare them
+        if !static_headers.is_empty() {
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(static_headers),
+                static_headers_middleware,
+            ));
+        }
+
+        // Evaluate the policy engine on every matched route
+        if let Some(engine) = self.policy_engine {
+            let mut enforcement = PolicyEnforcement::new(engine);
+            if let Some(verifier) = self.jwt.get().cloned() {
+                enforcement = enforcement.verifier(verifier);
+            }
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(enforcement),
+                policy_middleware,
+            ));
+        }
+
+        // Enforce required scopes on the routes that declare them, with verified tokens
+        if !scopes.is_empty() {
+            let Some(verifier) = self.jwt.get().cloned() else {
+                return Err(eywa_errors::AppError::InternalServerError(
+                    "Routes require OAuth scopes, but no token verification is configured: \
+                     call EywaApp::auth"
+                        .to_string(),
+                ));
+            };
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                std::sync::Arc::new(ScopeEnforcement::new(scopes, verifier)),
+                scope_enforcement_middleware,
+            ));
+        }
+
+        // Count the calls of the operations deprecated in the spec
+        if let Some(deprecations) = self.deprecations {
+            deprecations.track(specs.internal().openapi());
+            if deprecations.is_empty() {
+                tracing::warn!("Deprecation analytics enabled, but no operation is deprecated");
+            }
+            router = router.route_layer(axum::middleware::from_fn_with_state(
+                deprecations,
+                deprecation_middleware,
+            ));
+        }
+
+        // Measure the payload as sent, after the envelope and static headers
+        if self.has_response_metrics {
+            router = router.route_layer(axum::middleware::from_fn(response_metrics_middleware));
+        }
+
+        // Reject media types the spec doesn't document for the operation, then
+        // values violating its schema constraints, inside the authentication
+        // of each route
+        let mut checks = Checks::default();
+        if let Some(policy) = content_types {
+            checks.content_types = Some(ContentTypes::new(policy, specs.internal().openapi()));
+        }
+        if enforces_constraints {
+            checks.constraints = Some(SchemaConstraints::new(specs.internal().openapi()));
+        }
+        self.spec_checks.set(checks);
+
+        // Name the server span after the matched route
+        #[cfg(feature = "otel")]
+        if self.otel.is_some() {
+            router = router.route_layer(axum::middleware::from_fn(otel_route_middleware));
+        }
+
+        // Fail fast on missing services, then make them available to `Inject<T>`
+        if !self.mock_mode
+            && let Err(missing) = self.container.verify(&self.dependencies)
+        {
+            return Err(eywa_errors::AppError::InternalServerError(format!(
+                "Injected services were not provided:\n  {}",
+                missing.join("\n  ")
+            )));
+        }
+        if !self.container.is_empty() {
+            router = router.layer(axum::Extension(std::sync::Arc::new(self.container)));
+        }
+
+        // Make the operations registry available to every handler
+        if let Some(operations) = self.operations {
+            router = router.layer(axum::Extension(operations));
+        }
+
+        // Let streaming handlers close on shutdown
+        router = router.layer(axum::Extension(self.shutdown));
+
+        // Time the whole middleware stack
+        if self.has_server_timing {
+            router = router.layer(axum::middleware::from_fn(server_timing_middleware));
+        }
+
+        // Log and correlate every request, unless observability does
+        if self.has_logging && self.observability.is_none() {
+            router = router
+                .layer(crate::middleware::request_logging_middleware())
+                .layer(axum::middleware::from_fn(crate::middleware::request_context_middleware_fn));
         }
 
-        openapi.components = Some(components);
+        // Log, correlate and measure every request, outside the whole stack
+        if let Some(settings) = self.observability {
+            info!("📈 Observability: service '{}' in '{}'", settings.service, settings.env);
+            router = router
+                .layer(crate::middleware::request_logging_middleware())
+                .layer(axum::middleware::from_fn(crate::middleware::request_context_middleware_fn))
+                .layer(axum::middleware::from_fn_with_state(
+                    std::sync::Arc::new(settings),
+                    observability_middleware,
+                ));
+        }
 
-        // Add collected paths
-        for path_fn in self.path_fns {
-            path_fn(&mut openapi);
+        // Open the server span first, so request logs carry its trace ID
+        #[cfg(feature = "otel")]
+        if let Some(tracer) = self.otel {
+            router = router.layer(axum::middleware::from_fn_with_state(tracer, otel_middleware));
         }
 
-        // Log API info
-        info!("📚 API: {} v{}", openapi.info.title, openapi.info.version);
-        if let Some(ref desc) = openapi.info.description {
-            info!("   {}", desc);
+        // Count every request by route template, outside the whole stack
+        if self.has_metrics {
+            crate::state::init_metrics();
+            router = router
+                .layer(axum::middleware::from_fn(http_metrics_middleware))
+                .route("/metrics", get(eywa_metrics::metrics_handler));
         }
 
-        // Log discovered paths
-        for (path, item) in &openapi.paths.paths {
-            let methods: Vec<_> = [
-                item.get.as_ref().map(|_| "GET"),
-                item.post.as_ref().map(|_| "POST"),
-                item.put.as_ref().map(|_| "PUT"),
-                item.delete.as_ref().map(|_| "DELETE"),
-                item.patch.as_ref().map(|_| "PATCH"),
-            ]
-            .into_iter()
-            .flatten()
-            .collect();
-            info!("   {} [{}]", path, methods.join(", "));
+        // Serve the Scalar UI, the cached spec and one spec per API version
+        let mut router = router.merge(docs_router(specs.clone()));
+
+        // Serve the error code catalog
+        if let Some(catalog) = error_codes {
+            router = router.merge(catalog.router());
         }
 
-        // Create final router with Scalar UI
-        // Scalar::with_url returns a Router that serves the UI and JSON
-        // We merge it into our main router
-        let router = router
-            .merge(Scalar::with_url("/scalar", openapi.clone()));
+        // Serve the internal spec behind its guard
+        if let Some(guard) = self.internal_docs {
+            router = router.merge(guard(internal_docs_router(specs.clone())));
+        }
 
         // Add Swagger UI if feature is enabled
         #[cfg(feature = "swagger-ui")]
         let router = {
-            use utoipa_swagger_ui::SwaggerUi;
-            router.merge(SwaggerUi::new("/swagger")
-                .url("/api-docs/openapi.json", openapi.clone()))
+            use utoipa_swagger_ui::{Config, SwaggerUi};
+            let spec_url = format!("{}/openapi.json", specs.docs_path());
+            router.merge(SwaggerUi::new("/swagger").config(Config::from(spec_url)))
         };
 
         let router = router.with_state(self.state);
 
+        // Answer OPTIONS on paths without an OPTIONS handler
+        let router = match auto_methods {
+            Some(methods) if methods.options => {
+                router.layer(axum::middleware::from_fn(options_middleware))
+            }
+            _ => router,
+        };
+
+        // Route POST requests as their overridden method
+        let router = match self.method_override {
+            Some(settings) => {
+                let service = tower::ServiceBuilder::new()
+                    .layer(axum::middleware::from_fn_with_state(
+                        std::sync::Arc::new(settings),
+                        method_override_middleware,
+                    ))
+                    .service(router);
+                Router::new().fallback_service(service)
+            }
+            None => router,
+        };
+
+        // Normalize trailing slashes and letter case before routing
+        let router = match self.path_policy {
+            Some(policy) => {
+                let normalizer =
+                    std::sync::Arc::new(PathNormalizer::new(policy, specs.internal().openapi()));
+                let service = tower::ServiceBuilder::new()
+                    .layer(axum::middleware::from_fn_with_state(
+                        normalizer,
+                        path_normalization_middleware,
+                    ))
+                    .service(router);
+                Router::new().fallback_service(service)
+            }
+            None => router,
+        };
+
+        // Rewrite header-versioned requests before routing
+        let router = match self.version_header {
+            Some(header) => {
+                let rewriter =
+                    std::sync::Arc::new(VersionRewriter::new(header, specs.internal().openapi()));
+                let service = tower::ServiceBuilder::new()
+                    .map_request(move |mut req: axum::extract::Request| {
+                        rewriter.rewrite(&mut req);
+                        req
+                    })
+                    .service(router);
+                Router::new().fallback_service(service)
+            }
+            None => router,
+        };
+        Ok(router)
+    }
+
+    /// Summary of what `serve(addr)` starts: service, version, environment,
+    /// features, middleware, route count and docs URLs.
+    ///
+    /// `serve()` logs it once the listener is bound (see `startup`).
+    pub fn startup_summary(&self, addr: &str) -> StartupSummary {
+        self.summary(addr, false)
+    }
+
+    fn summary(&self, addr: &str, tls: bool) -> StartupSummary {
+        let scheme = if tls { "https" } else { "http" };
+        let info = self.spec.info.as_ref();
+        let service = match &self.observability {
+            Some(settings) => Some(settings.service.clone()),
+            None => info.map(|info| info.title.clone()),
+        };
+        let env = match &self.observability {
+            Some(settings) => settings.env.clone(),
+            None => std::env::var("RUN_MODE").unwrap_or_else(|_| "development".to_string()),
+        };
+
+        // Middleware installed at build time from the collected registries
+        let mut middleware = self.middleware.clone();
+        let registries = [
+            ("bulkheads", !self.bulkheads.is_empty()),
+            ("kill_switches", !self.kill_switches.is_empty()),
+            ("slos", !self.slos.is_empty()),
+            ("canaries", !self.canaries.is_empty()),
+            ("static_headers", !self.spec.static_headers.is_empty()),
+            ("scopes", !self.spec.scopes.is_empty()),
+            ("mock_mode", self.mock_mode),
+        ];
+        for (name, enabled) in registries {
+            if enabled && !middleware.contains(&name) {
+                middleware.push(name);
+            }
+        }
+
+        let mut docs = vec![
+            format!("{}://{}/scalar", scheme, addr),
+            format!("{}://{}{}/openapi.json", scheme, addr, self.spec.docs_path()),
+        ];
+        #[cfg(feature = "swagger-ui")]
+        docs.push(format!("{}://{}/swagger", scheme, addr));
+        if self.internal_docs.is_some() {
+            docs.push(format!("{}://{}/scalar/internal", scheme, addr));
+        }
+        if self.spec.error_codes.is_some() {
+            docs.push(format!("{}://{}{}", scheme, addr, crate::error_codes::ERROR_CATALOG_URL));
+        }
+        if self.has_health_checks {
+            docs.push(format!("{}://{}/health", scheme, addr));
+        }
+
+        StartupSummary {
+            service,
+            version: info.map(|info| info.version.clone()),
+            env,
+            address: addr.to_string(),
+            tls,
+            features: enabled_features(),
+            middleware,
+            routes: self.route_count,
+            docs,
+        }
+    }
+
+    /// Build the application into an in-process test client.
+    ///
+    /// The client drives the same router as `serve()` (middleware stack,
+    /// docs and health routes) through `tower::ServiceExt::oneshot`, without
+    /// binding a port. The Prometheus `/metrics` route is only added by `serve()`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let client = EywaApp::new(state)
+    ///     .health_checks()
+    ///     .mount::<ProjectsController>()
+    ///     .request_context()
+    ///     .into_test_client();
+    ///
+    /// let response = client.post("/api/v1/projects").json(&new_project).send().await;
+    /// response.assert_status(StatusCode::CREATED);
+    /// let project: Project = response.json();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the app fails to build (see `into_router`).
+    pub fn into_test_client(self) -> TestClient {
+        match self.into_router() {
+            Ok(router) => TestClient::new(router),
+            Err(e) => panic!("{e:?}"),
+        }
+    }
+
+    /// Build the application into its finished `Router` without binding a
+    /// listener.
+    ///
+    /// The router has the same docs and health routes, middleware stack and
+    /// state as `serve()`, and starts the warmup tasks. It doesn't get the
+    /// Prometheus `/metrics` route or the admin listener. Drive it with
+    /// `tower::ServiceExt::oneshot` or mount it into a larger router.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let router = EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .into_router()?;
+    ///
+    /// let response = router
+    ///     .oneshot(Request::get("/api/v1/projects").body(Body::empty())?)
+    ///     .await?;
+    ///
+    /// // Or embedded into another service
+    /// let gateway = Router::new().nest("/projects-service", router);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if a builder method was given an invalid setting, if routes
+    /// require scopes without `auth`, or if a service injected with
+    /// `Inject<T>` wasn't provided.
+    pub fn into_router(mut self) -> crate::Result<Router> {
+        std::mem::take(&mut self.warmup).spawn();
+        self.build()
+    }
+
+    /// Give open connections `grace` to close after a shutdown signal.
+    ///
+    /// On `SIGTERM` or Ctrl-C the server stops accepting connections, server-sent
+    /// event streams of `Shutdown::sse` end with a reconnect event, and WebSocket
+    /// handlers waiting on `Shutdown::triggered` close. Connections still open
+    /// after `grace` (30 seconds by default) are cut. See `shutdown`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.shutdown_grace(Duration::from_secs(20))  // below terminationGracePeriodSeconds
+    /// ```
+    pub fn shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// Run cron jobs once the server is listening, until it shuts down.
+    ///
+    /// Jobs marked `cluster_singleton` run once per tick across replicas,
+    /// coordinated through the scheduler's lease store. See `scheduler`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// app.scheduler(
+    ///     Scheduler::new()
+    ///         .leases(KubernetesLeases::in_cluster()?)
+    ///         .job(Job::new("purge-sessions", "0 0 * * * *", purge)?.cluster_singleton()),
+    /// )
+    /// ```
+    pub fn scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.jobs.register(scheduler.jobs());
+        self.scheduler = Some(match self.container.get::<SharedClock>() {
+            Some(clock) => scheduler.clock(clock),
+            None => scheduler,
+        });
+        self
+    }
+
+    /// Shutdown notification of the server, for background tasks to stop with it.
+    ///
+    /// Handlers extract `Shutdown` instead.
+    pub fn shutdown_handle(&self) -> Shutdown {
+        self.shutdown.clone()
+    }
+
+    /// Serve the application with automatic Scalar UI.
+    ///
+    /// Builds the router (see `into_test_client()` for in-process use),
+    /// adds the `/metrics` endpoint and starts the HTTP server. On `SIGTERM`
+    /// or Ctrl-C, connections are drained for the grace period of
+    /// `shutdown_grace`.
+    pub async fn serve(mut self, addr: &str) -> crate::Result<()> {
+        let summary = self.summary(addr, false);
+        let admin = std::mem::take(&mut self.admin);
+        let warmup = std::mem::take(&mut self.warmup);
+        let (shutdown, grace) = (self.shutdown.clone(), self.shutdown_grace);
+        let scheduler = self.scheduler.take();
+        let has_metrics = self.has_metrics;
+        let router = self.build()?;
+
         // Bind and serve
         let listener = TcpListener::bind(addr)
             .await
             .map_err(|e| eywa_errors::AppError::InternalServerError(e.to_string()))?;
 
-        info!("🚀 Server listening on http://{}", addr);
+        let router = Self::start(router, summary, admin, warmup, has_metrics).await?;
+        tokio::spawn(trigger_on_signal(shutdown.clone()));
+        if let Some(scheduler) = scheduler {
+            scheduler.spawn(shutdown.clone());
+        }
 
-        // Display available endpoints
-        info!("📚 Available endpoints:");
-        info!("   - Scalar: http://{}/scalar", addr);
-        #[cfg(feature = "swagger-ui")]
-        info!("   - Swagger UI: http://{}/swagger", addr);
-        if self.has_health_checks {
-            info!("   - Health Checks: http://{}/health", addr);
+        // Peer addresses are used by IP rate limiting
+        let draining = shutdown.clone();
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .with_graceful_shutdown(async move { draining.triggered().await });
+        serve_until_drained(std::future::IntoFuture::into_future(server), &shutdown, grace)
+            .await
+            .unwrap_or(Ok(()))
+            .map_err(|e: std::io::Error| eywa_errors::AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Serve the application over HTTPS, terminating TLS with rustls.
+    ///
+    /// Same as `serve()`, with the certificate of `tls` (see `tls`). HTTP/2
+    /// is negotiated through ALPN.
+    ///
+    /// # Example
+    /// ```ignore
+    /// EywaApp::new(state)
+    ///     .mount::<ProjectsController>()
+    ///     .serve_tls(
+    ///         "0.0.0.0:8443",
+    ///         TlsConfig::from_files(&config.tls.cert, &config.tls.key)
+    ///             .reload_on_change(Duration::from_secs(30)),
+    ///     )
+    ///     .await
+    /// ```
+    #[cfg(feature = "tls")]
+    pub async fn serve_tls(mut self, addr: &str, tls: TlsConfig) -> crate::Result<()> {
+        let summary = self.summary(addr, true);
+        let admin = std::mem::take(&mut self.admin);
+        let warmup = std::mem::take(&mut self.warmup);
+        let (shutdown, grace) = (self.shutdown.clone(), self.shutdown_grace);
+        let scheduler = self.scheduler.take();
+        let has_metrics = self.has_metrics;
+        let router = self.build()?;
+
+        // Load the certificate before binding, so a bad one fails the startup
+        let config = tls.load().await?;
+        let listener = TcpListener::bind(addr)
+            .await
+            .and_then(|listener| listener.into_std())
+            .map_err(|e| eywa_errors::AppError::InternalServerError(e.to_string()))?;
+        tls.spawn_reload(config.clone());
+
+        let router = Self::start(router, summary, admin, warmup, has_metrics).await?;
+        tokio::spawn(trigger_on_signal(shutdown.clone()));
+        if let Some(scheduler) = scheduler {
+            scheduler.spawn(shutdown.clone());
+        }
+
+        // Stop accepting connections on shutdown, cut them after the grace period
+        let handle = axum_server::Handle::new();
+        let draining = handle.clone();
+        tokio::spawn(async move {
+            shutdown.triggered().await;
+            draining.graceful_shutdown(Some(grace));
+        });
+
+        // Peer addresses are used by IP rate limiting
+        axum_server::from_tcp_rustls(listener, config)
+            .handle(handle)
+            .serve(router.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .await
+            .map_err(|e: std::io::Error| eywa_errors::AppError::InternalServerError(e.to_string()))
+    }
+
+    /// Start everything around the bound listener: log the startup summary,
+    /// start the warmup tasks and the admin listener, and add `/metrics`
+    /// unless `metrics()` already did.
+    async fn start(
+        router: Router,
+        summary: StartupSummary,
+        admin: AdminListener,
+        warmup: Warmup,
+        has_metrics: bool,
+    ) -> crate::Result<Router> {
+        summary.log();
+
+        // Warm up while the probes keep traffic away
+        warmup.spawn();
+
+        // Serve the admin routes on their own listener
+        admin.spawn().await?;
+
+        if has_metrics {
+            return Ok(router);
         }
 
         // Initialize metrics
-        eywa_metrics::init_metrics();
+        crate::state::init_metrics();
 
         // Add metrics route
-        let router = router
+        Ok(router
             .route("/metrics", get(eywa_metrics::metrics_handler))
-            .layer(axum::middleware::from_fn(eywa_metrics::track_metrics));
+            .layer(axum::middleware::from_fn(eywa_metrics::track_metrics)))
+    }
+}
 
-        axum::serve(listener, router.into_make_service())
-            .await
-            .map_err(|e: std::io::Error| eywa_errors::AppError::InternalServerError(e.to_string()))
+/// A controller's route path under the version prefix, if any.
+fn versioned_path(version: Option<&str>, path: &str) -> String {
+    match version {
+        Some(version) => prefix_path(version, path),
+        None => path.to_string(),
+    }
+}
+
+/// Route metadata of a controller, with its paths under the version prefix.
+fn versioned<T>(
+    version: Option<&str>,
+    mut routes: Vec<T>,
+    path: fn(&mut T) -> &mut String,
+) -> Vec<T> {
+    for route in &mut routes {
+        let path = path(route);
+        *path = versioned_path(version, path);
     }
+    routes
 }
 
 /// Legacy EywaApp for backward compatibility (uses manual OpenAPI).
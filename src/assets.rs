@@ -0,0 +1,375 @@
+//! Static assets with content-hashed file names.
+//!
+//! Frontends embedded in a service (admin UIs, SPAs) must be cache-busted on
+//! every deploy without giving up browser caching. `StaticAssets` serves
+//! each file under a fingerprinted name containing a hash of its content
+//! (`app.js` → `app.3f9a2c1d0b7e.js`) with a far-future, immutable
+//! `Cache-Control`, so a file is downloaded once per content change:
+//!
+//! ```ignore
+//! let assets = StaticAssets::new()
+//!     .file("app.js", include_bytes!("../web/dist/app.js").as_slice())
+//!     .file("app.css", include_bytes!("../web/dist/app.css").as_slice())
+//!     .file("index.html", include_bytes!("../web/dist/index.html").as_slice())
+//!     .spa_fallback("index.html");
+//!
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .static_assets(assets)
+//! ```
+//!
+//! Routes, under the prefix (`/assets` by default):
+//! - `GET /assets/app.3f9a2c1d0b7e.js` - `Cache-Control: public, max-age=31536000, immutable`
+//! - `GET /assets/app.js` - The current version, `Cache-Control: no-cache` with an `ETag`
+//! - `GET /assets/manifest.json` - Logical names mapped to fingerprinted paths,
+//!   `{"app.js": "/assets/app.3f9a2c1d0b7e.js"}`
+//!
+//! With `spa_fallback`, `GET` requests matching no route get the given file
+//! (never cached), so client-side routes survive a reload. Server-rendered
+//! pages link assets with `StaticAssets::url`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{Path as UrlPath, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, Method, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// Default prefix of the asset routes.
+pub const ASSETS_PREFIX: &str = "/assets";
+
+/// `Cache-Control` of fingerprinted paths, whose content never changes.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// `Cache-Control` of logical paths and the SPA fallback.
+pub const REVALIDATE: &str = "no-cache";
+
+/// Hex digits of the content hash in fingerprinted names.
+const HASH_LENGTH: usize = 12;
+
+/// An asset and its content hash.
+#[derive(Debug, Clone)]
+struct Asset {
+    content: Bytes,
+    content_type: &'static str,
+    hash: String,
+    fingerprinted: String,
+}
+
+/// Static files served with fingerprinted names.
+#[derive(Debug, Clone)]
+pub struct StaticAssets {
+    prefix: String,
+    /// Assets by logical name
+    assets: BTreeMap<String, Asset>,
+    /// Logical names by fingerprinted name
+    fingerprints: BTreeMap<String, String>,
+    spa_fallback: Option<String>,
+}
+
+impl Default for StaticAssets {
+    fn default() -> Self {
+        Self {
+            prefix: ASSETS_PREFIX.to_string(),
+            assets: BTreeMap::new(),
+            fingerprints: BTreeMap::new(),
+            spa_fallback: None,
+        }
+    }
+}
+
+/// FNV-1a, stable across builds and platforms.
+fn content_hash(content: &[u8]) -> String {
+    let hash = content.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")[..HASH_LENGTH].to_string()
+}
+
+/// `dir/app.min.js` → `dir/app.min.{hash}.js`
+fn fingerprint(name: &str, hash: &str) -> String {
+    let (dir, file) = name.rsplit_once('/').unwrap_or(("", name));
+    let file = match file.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem}.{hash}.{extension}"),
+        _ => format!("{file}.{hash}"),
+    };
+    if dir.is_empty() { file } else { format!("{dir}/{file}") }
+}
+
+fn content_type(name: &str) -> &'static str {
+    let extension = name.rsplit_once('.').map_or("", |(_, extension)| extension);
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+impl StaticAssets {
+    /// No assets, served under `/assets`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the assets under another prefix (e.g. `/static`).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Add a file under its logical name (`app.js`, `fonts/inter.woff2`).
+    pub fn file(mut self, name: impl Into<String>, content: impl Into<Bytes>) -> Self {
+        let name = name.into().trim_start_matches('/').to_string();
+        let content = content.into();
+        let hash = content_hash(&content);
+        let fingerprinted = fingerprint(&name, &hash);
+
+        if let Some(previous) = self.assets.get(&name) {
+            self.fingerprints.remove(&previous.fingerprinted);
+        }
+        self.fingerprints.insert(fingerprinted.clone(), name.clone());
+        self.assets.insert(
+            name.clone(),
+            Asset {
+                content_type: content_type(&name),
+                content,
+                hash,
+                fingerprinted,
+            },
+        );
+        self
+    }
+
+    /// Add every file under a directory, named by their path relative to it.
+    pub async fn dir(mut self, dir: impl AsRef<Path>) -> Result<Self> {
+        let root = dir.as_ref();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await.map_err(|e| {
+                AppError::InternalServerError(format!("Reading {} failed: {e}", dir.display()))
+            })?;
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let content = tokio::fs::read(&path).await.map_err(|e| {
+                    AppError::InternalServerError(format!("Reading {} failed: {e}", path.display()))
+                })?;
+                let name = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self = self.file(name, content);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Answer `GET` requests matching no route with this asset (e.g. `index.html`).
+    pub fn spa_fallback(mut self, name: impl Into<String>) -> Self {
+        self.spa_fallback = Some(name.into());
+        self
+    }
+
+    /// The fingerprinted URL of an asset, for server-rendered pages.
+    pub fn url(&self, name: &str) -> Option<String> {
+        let asset = self.assets.get(name.trim_start_matches('/'))?;
+        Some(format!("{}/{}", self.prefix, asset.fingerprinted))
+    }
+
+    /// Logical names mapped to fingerprinted URLs.
+    pub fn manifest(&self) -> BTreeMap<String, String> {
+        self.assets
+            .iter()
+            .map(|(name, asset)| (name.clone(), format!("{}/{}", self.prefix, asset.fingerprinted)))
+            .collect()
+    }
+
+    /// Build the router serving the assets, their manifest and the SPA fallback.
+    pub fn router<S>(self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        if let Some(name) = &self.spa_fallback
+            && !self.assets.contains_key(name)
+        {
+            tracing::warn!("SPA fallback '{}' is not a static asset", name);
+        }
+        let prefix = self.prefix.clone();
+        let assets = Arc::new(self);
+
+        let router = Router::new()
+            .route(&format!("{prefix}/manifest.json"), get(manifest))
+            .route(&format!("{prefix}/{{*path}}"), get(asset));
+        let router = if assets.spa_fallback.is_some() {
+            router.fallback(spa_fallback)
+        } else {
+            router
+        };
+        router.with_state(assets)
+    }
+
+    /// Serve an asset, or `304 Not Modified` if the client has it.
+    fn serve(asset: &Asset, cache_control: &'static str, headers: &HeaderMap) -> Response {
+        let etag = format!("\"{}\"", asset.hash);
+        let cached = headers
+            .get(IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+
+        let mut response = if cached {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let mut response = asset.content.clone().into_response();
+            response
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_static(asset.content_type));
+            response
+        };
+        let headers = response.headers_mut();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        if let Ok(etag) = HeaderValue::from_str(&etag) {
+            headers.insert(ETAG, etag);
+        }
+        response
+    }
+}
+
+async fn manifest(State(assets): State<Arc<StaticAssets>>) -> Response {
+    let mut response = Json(assets.manifest()).into_response();
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static(REVALIDATE));
+    response
+}
+
+async fn asset(
+    State(assets): State<Arc<StaticAssets>>,
+    UrlPath(path): UrlPath<String>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(asset) = assets.fingerprints.get(&path).and_then(|name| assets.assets.get(name)) {
+        return StaticAssets::serve(asset, IMMUTABLE, &headers);
+    }
+    match assets.assets.get(&path) {
+        Some(asset) => StaticAssets::serve(asset, REVALIDATE, &headers),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn spa_fallback(
+    State(assets): State<Arc<StaticAssets>>,
+    method: Method,
+    headers: HeaderMap,
+) -> Response {
+    let index = assets
+        .spa_fallback
+        .as_ref()
+        .and_then(|name| assets.assets.get(name));
+    match index {
+        Some(index) if method == Method::GET || method == Method::HEAD => {
+            StaticAssets::serve(index, REVALIDATE, &headers)
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    fn assets() -> StaticAssets {
+        StaticAssets::new()
+            .file("app.js", "console.log('v1')")
+            .file("fonts/inter.woff2", "font")
+            .file("index.html", "<html></html>")
+            .spa_fallback("index.html")
+    }
+
+    #[test]
+    fn test_fingerprinted_names() {
+        let hash = content_hash(b"console.log('v1')");
+        assert_eq!(hash.len(), HASH_LENGTH);
+        assert_eq!(fingerprint("app.min.js", &hash), format!("app.min.{hash}.js"));
+        assert_eq!(fingerprint("fonts/LICENSE", &hash), format!("fonts/LICENSE.{hash}"));
+
+        let assets = assets();
+        assert_eq!(assets.url("app.js"), Some(format!("/assets/app.{hash}.js")));
+        let updated = assets.file("app.js", "console.log('v2')");
+        assert_ne!(updated.url("app.js"), Some(format!("/assets/app.{hash}.js")));
+        assert_eq!(updated.fingerprints.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_headers() {
+        let assets = assets();
+        let url = assets.url("app.js").unwrap();
+        let client = TestClient::new(assets.router());
+
+        let response = client.get(&url).send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.header("cache-control"), Some(IMMUTABLE));
+        assert_eq!(response.header("content-type"), Some("text/javascript; charset=utf-8"));
+        assert_eq!(response.text(), "console.log('v1')");
+
+        let response = client.get("/assets/app.js").send().await;
+        assert_eq!(response.header("cache-control"), Some(REVALIDATE));
+        let etag = response.header("etag").unwrap().to_string();
+        let response = client.get("/assets/app.js").header("if-none-match", etag).send().await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+
+        let manifest: BTreeMap<String, String> =
+            client.get("/assets/manifest.json").send().await.json();
+        assert_eq!(manifest["app.js"], url);
+        assert!(manifest["fonts/inter.woff2"].starts_with("/assets/fonts/inter."));
+    }
+
+    #[tokio::test]
+    async fn test_spa_fallback() {
+        let client = TestClient::new(assets().router());
+
+        let response = client.get("/projects/42").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "<html></html>");
+        assert_eq!(response.header("cache-control"), Some(REVALIDATE));
+
+        client.get("/assets/missing.js").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.post("/projects/42").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,238 @@
+//! Audit columns filled from the request.
+//!
+//! Entities with `created_by`, `updated_by` or `tenant_id` columns get them
+//! set on every save by calling `audit::stamp` from their
+//! `ActiveModelBehavior::before_save`:
+//!
+//! ```ignore
+//! #[async_trait]
+//! impl ActiveModelBehavior for ActiveModel {
+//!     async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+//!     where
+//!         C: ConnectionTrait,
+//!     {
+//!         eywa_axum::audit::stamp(&mut self, insert);
+//!         Ok(self)
+//!     }
+//! }
+//! ```
+//!
+//! The values come from the `AuditContext` of the current task, set for each
+//! request by `EywaApp::audit_columns` from `RequestContext::user_id` and a
+//! verified tenant: the `tenant_id` claim of the verified bearer token, or an
+//! `AuditTenant` that authenticating middleware attached to the
+//! `RequestContext`. Headers such as `X-Tenant-ID` are never used, since any
+//! caller could write rows into another tenant with them. Background work
+//! runs with an explicit context through `AuditContext::scope`.
+//!
+//! - `created_by` and `tenant_id` are only set on insert, and only when the
+//!   model doesn't already set them
+//! - `updated_by` is set on every save
+//! - `uuid` columns get the parsed ID, string columns its string form; columns
+//!   of other types are left alone
+
+use std::future::Future;
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, ColumnType, EntityTrait, IdenStatic, Iterable,
+    Value,
+};
+use uuid::Uuid;
+
+use crate::middleware::auth::{VerifiedClaims, TENANT_CLAIM};
+use crate::middleware::RequestContext;
+
+/// Column of the user creating a row.
+pub const CREATED_BY: &str = "created_by";
+
+/// Column of the user last updating a row.
+pub const UPDATED_BY: &str = "updated_by";
+
+/// Column of the tenant owning a row.
+pub const TENANT_ID: &str = "tenant_id";
+
+tokio::task_local! {
+    static CURRENT_AUDIT: AuditContext;
+}
+
+/// Tenant of a request, attached to its `RequestContext` by the middleware
+/// that authenticated it (e.g. from an API key's owner).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditTenant(pub String);
+
+/// Who is writing, and for which tenant.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub user_id: Option<String>,
+    pub tenant_id: Option<String>,
+}
+
+impl AuditContext {
+    pub fn new(user_id: Option<String>, tenant_id: Option<String>) -> Self {
+        Self { user_id, tenant_id }
+    }
+
+    /// Context of the current task (empty outside a request or `scope`).
+    pub fn current() -> Self {
+        CURRENT_AUDIT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Run `future` with this context, e.g. in a background job.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_AUDIT.scope(self, future).await
+    }
+}
+
+/// The value of an audit column, typed after the column.
+fn column_value<C: ColumnTrait>(column: &C, id: &str) -> Option<Value> {
+    match column.def().get_column_type() {
+        ColumnType::Uuid => Uuid::parse_str(id).ok().map(Value::from),
+        ColumnType::String(_) | ColumnType::Text | ColumnType::Char(_) => {
+            Some(Value::from(id.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Fill the audit columns of `model` from the current `AuditContext`.
+pub fn stamp<A: ActiveModelTrait>(model: &mut A, insert: bool) {
+    stamp_with(model, insert, &AuditContext::current());
+}
+
+/// Fill the audit columns of `model` from `ctx`.
+pub fn stamp_with<A: ActiveModelTrait>(model: &mut A, insert: bool, ctx: &AuditContext) {
+    for column in <<A as ActiveModelTrait>::Entity as EntityTrait>::Column::iter() {
+        let (id, only_on_insert) = match column.as_str() {
+            CREATED_BY => (&ctx.user_id, true),
+            UPDATED_BY => (&ctx.user_id, false),
+            TENANT_ID => (&ctx.tenant_id, true),
+            _ => continue,
+        };
+        if only_on_insert && (!insert || !matches!(model.get(column), ActiveValue::NotSet)) {
+            continue;
+        }
+        if let Some(value) = id.as_deref().and_then(|id| column_value(&column, id)) {
+            model.set(column, value);
+        }
+    }
+}
+
+/// Middleware running the request with its `AuditContext`.
+///
+/// Installed by `EywaApp::audit_columns()`.
+pub async fn audit_context_middleware(req: Request, next: Next) -> Response {
+    let user_id = req
+        .extensions()
+        .get::<RequestContext>()
+        .and_then(|ctx| ctx.user_id.as_ref())
+        .map(|id| id.to_string());
+    let tenant_id = req
+        .extensions()
+        .get::<VerifiedClaims>()
+        .and_then(|claims| claims.string(TENANT_CLAIM))
+        .or_else(|| {
+            let ctx = req.extensions().get::<RequestContext>()?;
+            ctx.get::<AuditTenant>().map(|tenant| tenant.0.clone())
+        });
+
+    AuditContext::new(user_id, tenant_id).scope(next.run(req)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod document {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+        #[sea_orm(table_name = "documents")]
+        pub struct Model {
+            #[sea_orm(primary_key)]
+            pub id: i32,
+            pub title: String,
+            pub created_by: Option<String>,
+            pub updated_by: Option<String>,
+            pub tenant_id: Option<Uuid>,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    const TENANT: &str = "8f14e45f-ceea-467f-a8f0-9a1b2c3d4e5f";
+
+    fn ctx(user: &str) -> AuditContext {
+        AuditContext::new(Some(user.to_string()), Some(TENANT.to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_insert_sets_every_column() {
+        let mut model = document::ActiveModel {
+            title: ActiveValue::Set("Spec".to_string()),
+            ..Default::default()
+        };
+
+        ctx("alice").scope(async { stamp(&mut model, true) }).await;
+
+        assert_eq!(model.created_by, ActiveValue::Set(Some("alice".to_string())));
+        assert_eq!(model.updated_by, ActiveValue::Set(Some("alice".to_string())));
+        assert_eq!(model.tenant_id, ActiveValue::Set(Some(Uuid::parse_str(TENANT).unwrap())));
+    }
+
+    #[test]
+    fn test_update_only_sets_updated_by() {
+        let mut model = document::ActiveModel {
+            created_by: ActiveValue::Unchanged(Some("alice".to_string())),
+            ..Default::default()
+        };
+
+        stamp_with(&mut model, false, &ctx("bob"));
+
+        assert_eq!(model.created_by, ActiveValue::Unchanged(Some("alice".to_string())));
+        assert_eq!(model.updated_by, ActiveValue::Set(Some("bob".to_string())));
+        assert_eq!(model.tenant_id, ActiveValue::NotSet);
+    }
+
+    #[test]
+    fn test_explicit_values_and_missing_context_kept() {
+        let mut model = document::ActiveModel {
+            created_by: ActiveValue::Set(Some("importer".to_string())),
+            ..Default::default()
+        };
+
+        stamp(&mut model, true);
+
+        assert_eq!(model.created_by, ActiveValue::Set(Some("importer".to_string())));
+        assert_eq!(model.updated_by, ActiveValue::NotSet);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_from_verified_claims_only() {
+        use crate::JwtConfig;
+        use axum::routing::get;
+
+        let config = JwtConfig::new("s3cret");
+        let client = crate::EywaApp::new(())
+            .merge(axum::Router::new().route(
+                "/tenant",
+                get(|| async { format!("{:?}", AuditContext::current().tenant_id) }),
+            ))
+            .audit_columns()
+            .auth(config.clone())
+            .into_test_client();
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = |claims: serde_json::Value| config.sign(&claims).unwrap();
+
+        let plain = token(serde_json::json!({ "sub": "42", "exp": exp }));
+        let spoofed = client.get("/tenant").bearer(&plain).header("x-tenant-id", TENANT);
+        assert_eq!(spoofed.send().await.text(), "None");
+
+        let member = token(serde_json::json!({ "sub": "42", "tenant_id": TENANT, "exp": exp }));
+        let response = client.get("/tenant").bearer(&member).header("x-tenant-id", "other");
+        assert_eq!(response.send().await.text(), format!("Some({TENANT:?})"));
+    }
+}
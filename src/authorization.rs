@@ -0,0 +1,297 @@
+//! Policy-based authorization for access rules that are too rich for role checks.
+//!
+//! This module provides:
+//! - `PolicyEngine` - Trait implemented by policy backends
+//! - `PolicyRequest` - Subject/action/resource derived from the route and request context
+//! - `PolicyEnforcement` - The engine and the verifier of the subject's token
+//! - `policy_middleware` - Axum middleware evaluating the engine per request
+//! - `opa::OpaEngine` - Open Policy Agent adapter (REST data API)
+//! - `cedar::CedarEngine` - Cedar adapter (with `cedar` feature)
+//!
+//! The subject is read from the bearer token verified with the `JwtConfig` of
+//! `EywaApp::auth`, on every route: its `sub` claim and its scopes. Requests
+//! without a token are evaluated as anonymous; invalid tokens are rejected.
+//!
+//! OPA policies compiled to wasm are not evaluated in-process: run them in an
+//! OPA server (`opa run --server --bundle`) and use `OpaEngine`, or wrap a
+//! wasm runtime in a `PolicyEngine`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{MatchedPath, RawPathParams, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    RequestExt,
+};
+use serde::{Deserialize, Serialize};
+
+use eywa_errors::AppError;
+
+use crate::middleware::auth::{JwtVerifier, VerifiedClaims};
+use crate::middleware::RequestContext;
+use crate::Result;
+
+#[cfg(feature = "cedar")]
+pub mod cedar;
+pub mod opa;
+
+/// The caller performing the request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Subject {
+    /// Authenticated user ID, `None` for anonymous requests
+    pub id: Option<String>,
+
+    /// OAuth scopes granted to the caller's token
+    pub scopes: Vec<String>,
+}
+
+/// The resource targeted by the request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Resource {
+    /// Route template (e.g. `/api/v1/projects/{id}`)
+    pub route: String,
+
+    /// Concrete request path (e.g. `/api/v1/projects/42`)
+    pub path: String,
+
+    /// Path parameters extracted from the route template
+    pub params: HashMap<String, String>,
+}
+
+/// Input passed to a `PolicyEngine` for every request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyRequest {
+    pub subject: Subject,
+
+    /// HTTP method (GET, POST, etc.)
+    pub action: String,
+
+    pub resource: Resource,
+
+    /// Additional request attributes (correlation ID, language)
+    pub context: serde_json::Value,
+}
+
+/// Outcome of a policy evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    /// Denied, with an optional reason returned to the client
+    Deny(Option<String>),
+}
+
+/// A policy backend deciding whether a request is allowed.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::authorization::{PolicyDecision, PolicyEngine, PolicyRequest};
+///
+/// struct OwnerOnly;
+///
+/// #[async_trait]
+/// impl PolicyEngine for OwnerOnly {
+///     async fn evaluate(&self, request: &PolicyRequest) -> Result<PolicyDecision> {
+///         let owner = request.resource.params.get("user_id");
+///         Ok(if owner.is_some() && owner == request.subject.id.as_ref() {
+///             PolicyDecision::Allow
+///         } else {
+///             PolicyDecision::Deny(None)
+///         })
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait PolicyEngine: Send + Sync + 'static {
+    /// Evaluate the policy for a single request.
+    async fn evaluate(&self, request: &PolicyRequest) -> Result<PolicyDecision>;
+}
+
+/// A policy engine with the verifier of the callers' tokens.
+///
+/// Built by `EywaApp::authorize()` with the verifier of `EywaApp::auth()`.
+#[derive(Clone)]
+pub struct PolicyEnforcement {
+    engine: Arc<dyn PolicyEngine>,
+    verifier: Option<JwtVerifier>,
+}
+
+impl std::fmt::Debug for PolicyEnforcement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PolicyEnforcement")
+            .field("verifier", &self.verifier)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PolicyEnforcement {
+    /// Evaluate `engine`, with anonymous subjects until a verifier is set.
+    pub fn new(engine: Arc<dyn PolicyEngine>) -> Self {
+        Self {
+            engine,
+            verifier: None,
+        }
+    }
+
+    /// Read the subject from bearer tokens verified by `verifier`.
+    pub fn verifier(mut self, verifier: JwtVerifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+}
+
+/// Build the policy input from the matched route, the verified claims and
+/// the request extensions.
+async fn build_policy_request(
+    req: &mut Request,
+    claims: Option<&VerifiedClaims>,
+) -> PolicyRequest {
+    let params = req
+        .extract_parts::<RawPathParams>()
+        .await
+        .map(|params| {
+            params
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| req.uri().path().to_string());
+
+    let ctx = req.extensions().get::<RequestContext>();
+    let subject = claims
+        .map(|claims| {
+            let mut scopes: Vec<String> = claims.scopes().iter().map(str::to_string).collect();
+            scopes.sort();
+            Subject {
+                id: claims.subject().map(str::to_string),
+                scopes,
+            }
+        })
+        .unwrap_or_default();
+
+    PolicyRequest {
+        subject,
+        action: req.method().as_str().to_string(),
+        resource: Resource {
+            route,
+            path: req.uri().path().to_string(),
+            params,
+        },
+        context: serde_json::json!({
+            "correlation_id": ctx.map(|ctx| ctx.correlation_id),
+            "language": ctx.map(|ctx| ctx.language.clone()),
+        }),
+    }
+}
+
+/// Axum middleware evaluating the configured `PolicyEngine` for every matched route.
+///
+/// - **401 Unauthorized**: the bearer token is invalid or expired
+/// - **403 Forbidden**: the engine denied the request
+/// - **500 Internal Server Error**: the engine could not be evaluated
+///
+/// Installed by `EywaApp::authorize()`.
+pub async fn policy_middleware(
+    State(enforcement): State<Arc<PolicyEnforcement>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let claims = match &enforcement.verifier {
+        Some(verifier) => match verifier.try_authenticate(&mut req) {
+            Ok(claims) => claims,
+            Err(e) => return e.into_response(),
+        },
+        None => None,
+    };
+    let policy_request = build_policy_request(&mut req, claims.as_ref()).await;
+
+    match enforcement.engine.evaluate(&policy_request).await {
+        Ok(PolicyDecision::Allow) => next.run(req).await,
+        Ok(PolicyDecision::Deny(reason)) => {
+            tracing::info!(
+                action = %policy_request.action,
+                route = %policy_request.resource.route,
+                subject = ?policy_request.subject.id,
+                "request denied by policy"
+            );
+            AppError::Forbidden(reason.unwrap_or_else(|| "Access denied by policy".to_string()))
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("policy evaluation failed: {:?}", e);
+            e.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::JwtConfig;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Router};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Allows every request, recording its subject.
+    #[derive(Default)]
+    struct Recorder(Mutex<Option<Subject>>);
+
+    #[async_trait]
+    impl PolicyEngine for Arc<Recorder> {
+        async fn evaluate(&self, request: &PolicyRequest) -> Result<PolicyDecision> {
+            *self.0.lock().unwrap() = Some(request.subject.clone());
+            Ok(PolicyDecision::Allow)
+        }
+    }
+
+    fn token(secret: &str) -> String {
+        JwtConfig::new(secret)
+            .sign(&json!({
+                "sub": "u-17",
+                "scope": "projects:write projects:read",
+                "exp": 4_102_444_800_i64,
+            }))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subject_from_verified_claims() {
+        let recorder = Arc::new(Recorder::default());
+        let enforcement = PolicyEnforcement::new(Arc::new(recorder.clone()))
+            .verifier(JwtVerifier::new(&JwtConfig::new("s3cret")));
+        let client = TestClient::new(
+            Router::new()
+                .route("/projects", get(|| async { "projects" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(enforcement),
+                    policy_middleware,
+                )),
+        );
+        let subject = || recorder.0.lock().unwrap().take().unwrap();
+
+        // No scope enforcement on the route: scopes still come from the token
+        client.get("/projects").bearer(&token("s3cret")).send().await.assert_status(StatusCode::OK);
+        let verified = subject();
+        assert_eq!(verified.id.as_deref(), Some("u-17"));
+        assert_eq!(verified.scopes, ["projects:read", "projects:write"]);
+
+        client.get("/projects").send().await.assert_status(StatusCode::OK);
+        let anonymous = subject();
+        assert_eq!(anonymous.id, None);
+        assert!(anonymous.scopes.is_empty());
+
+        let forged = client.get("/projects").bearer(&token("guessed")).send().await;
+        forged.assert_status(StatusCode::UNAUTHORIZED);
+        assert!(recorder.0.lock().unwrap().is_none());
+    }
+}
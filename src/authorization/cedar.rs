@@ -0,0 +1,139 @@
+//! Cedar policy adapter.
+//!
+//! Evaluates requests in-process with the `cedar-policy` crate. The request is
+//! mapped to Cedar entities as follows:
+//! - principal: `User::"<user id>"` (or `User::"anonymous"`)
+//! - action: `Action::"<HTTP method>"`
+//! - resource: `Route::"<route template>"`
+//! - context: `{ "path": ..., "params": {...}, "scopes": [...] }`
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use cedar_policy::{Authorizer, Context, Decision, Entities, EntityUid, PolicySet, Request};
+
+use eywa_errors::AppError;
+
+use super::{PolicyDecision, PolicyEngine, PolicyRequest};
+use crate::Result;
+
+/// Principal used for unauthenticated requests.
+const ANONYMOUS: &str = "anonymous";
+
+/// Policy engine evaluating Cedar policies in-process.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::authorization::cedar::CedarEngine;
+///
+/// let engine = CedarEngine::from_policies(r#"
+///     permit(principal, action == Action::"GET", resource);
+/// "#)?;
+///
+/// EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .authorize(engine)
+///     .serve("0.0.0.0:3000")
+///     .await
+/// ```
+pub struct CedarEngine {
+    authorizer: Authorizer,
+    policies: PolicySet,
+    entities: Entities,
+}
+
+impl CedarEngine {
+    /// Create an engine from Cedar policy source text.
+    pub fn from_policies(source: &str) -> Result<Self> {
+        let policies = PolicySet::from_str(source)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid Cedar policies: {e}")))?;
+
+        Ok(Self {
+            authorizer: Authorizer::new(),
+            policies,
+            entities: Entities::empty(),
+        })
+    }
+
+    /// Provide the entity store (principal/resource attributes and hierarchy).
+    pub fn with_entities(mut self, entities: Entities) -> Self {
+        self.entities = entities;
+        self
+    }
+}
+
+/// Build a Cedar entity UID like `User::"42"`.
+fn entity_uid(entity_type: &str, id: &str) -> Result<EntityUid> {
+    EntityUid::from_str(&format!("{entity_type}::{id:?}"))
+        .map_err(|e| AppError::InternalServerError(format!("Invalid Cedar entity: {e}")))
+}
+
+#[async_trait]
+impl PolicyEngine for CedarEngine {
+    async fn evaluate(&self, request: &PolicyRequest) -> Result<PolicyDecision> {
+        let principal = entity_uid("User", request.subject.id.as_deref().unwrap_or(ANONYMOUS))?;
+        let action = entity_uid("Action", &request.action)?;
+        let resource = entity_uid("Route", &request.resource.route)?;
+        let context = Context::from_json_value(
+            serde_json::json!({
+                "path": request.resource.path,
+                "params": request.resource.params,
+                "scopes": request.subject.scopes,
+            }),
+            None,
+        )
+        .map_err(|e| AppError::InternalServerError(format!("Invalid Cedar context: {e}")))?;
+
+        let cedar_request = Request::new(principal, action, resource, context, None)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid Cedar request: {e}")))?;
+
+        let response = self
+            .authorizer
+            .is_authorized(&cedar_request, &self.policies, &self.entities);
+
+        Ok(match response.decision() {
+            Decision::Allow => PolicyDecision::Allow,
+            Decision::Deny => PolicyDecision::Deny(None),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorization::{Resource, Subject};
+
+    fn request(action: &str) -> PolicyRequest {
+        PolicyRequest {
+            subject: Subject {
+                id: Some("42".to_string()),
+                scopes: Vec::new(),
+            },
+            action: action.to_string(),
+            resource: Resource {
+                route: "/projects".to_string(),
+                path: "/projects".to_string(),
+                params: Default::default(),
+            },
+            context: serde_json::Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cedar_permit_and_default_deny() {
+        let engine = CedarEngine::from_policies(
+            r#"permit(principal == User::"42", action == Action::"GET", resource);"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            engine.evaluate(&request("GET")).await.unwrap(),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            engine.evaluate(&request("DELETE")).await.unwrap(),
+            PolicyDecision::Deny(None)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Open Policy Agent adapter.
+//!
+//! Queries OPA's REST data API (`POST /v1/data/<package>`) with the
+//! `PolicyRequest` as `input`. Works with an OPA sidecar or a central OPA
+//! server; policies compiled to wasm are evaluated by serving the bundle
+//! with OPA, not in-process.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use eywa_errors::AppError;
+
+use super::{PolicyDecision, PolicyEngine, PolicyRequest};
+use crate::Result;
+
+/// Policy engine backed by an OPA server.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::authorization::opa::OpaEngine;
+///
+/// EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .authorize(OpaEngine::new("http://localhost:8181", "eywa/projects/allow"))
+///     .serve("0.0.0.0:3000")
+///     .await
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpaEngine {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl OpaEngine {
+    /// Create an adapter querying `rule` (e.g. `eywa/projects/allow`) on the OPA server at `base_url`.
+    pub fn new(base_url: impl Into<String>, rule: impl AsRef<str>) -> Self {
+        Self::with_client(reqwest::Client::new(), base_url, rule)
+    }
+
+    /// Create an adapter using an existing HTTP client.
+    pub fn with_client(
+        client: reqwest::Client,
+        base_url: impl Into<String>,
+        rule: impl AsRef<str>,
+    ) -> Self {
+        let base_url = base_url.into();
+        let url = format!(
+            "{}/v1/data/{}",
+            base_url.trim_end_matches('/'),
+            rule.as_ref().trim_matches('/')
+        );
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl PolicyEngine for OpaEngine {
+    async fn evaluate(&self, request: &PolicyRequest) -> Result<PolicyDecision> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&json!({ "input": request }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| AppError::InternalServerError(format!("OPA request failed: {e}")))?;
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Invalid OPA response: {e}")))?;
+
+        Ok(parse_decision(&body))
+    }
+}
+
+/// Interpret an OPA data API response.
+///
+/// Accepts either a boolean rule (`{"result": true}`) or an object rule
+/// (`{"result": {"allow": false, "reason": "..."}}`). An undefined rule
+/// (no `result`) is a deny.
+fn parse_decision(body: &Value) -> PolicyDecision {
+    match body.get("result") {
+        Some(Value::Bool(true)) => PolicyDecision::Allow,
+        Some(Value::Object(result)) if result.get("allow") == Some(&Value::Bool(true)) => {
+            PolicyDecision::Allow
+        }
+        Some(Value::Object(result)) => PolicyDecision::Deny(
+            result
+                .get("reason")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        ),
+        _ => PolicyDecision::Deny(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_boolean_result() {
+        assert_eq!(parse_decision(&json!({ "result": true })), PolicyDecision::Allow);
+        assert_eq!(
+            parse_decision(&json!({ "result": false })),
+            PolicyDecision::Deny(None)
+        );
+    }
+
+    #[test]
+    fn test_parse_object_result() {
+        assert_eq!(
+            parse_decision(&json!({ "result": { "allow": true } })),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            parse_decision(&json!({ "result": { "allow": false, "reason": "not owner" } })),
+            PolicyDecision::Deny(Some("not owner".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_undefined_rule_denies() {
+        assert_eq!(parse_decision(&json!({})), PolicyDecision::Deny(None));
+    }
+
+    #[test]
+    fn test_url_joins_rule_path() {
+        let engine = OpaEngine::new("http://opa:8181/", "/eywa/projects/allow");
+        assert_eq!(engine.url, "http://opa:8181/v1/data/eywa/projects/allow");
+    }
+}
@@ -0,0 +1,88 @@
+//! Scaffold EYWA controllers and services.
+//!
+//! ```text
+//! eywa-scaffold controller <name> [--resource <Type>] [--version <v1>] [--dir <path>]
+//! eywa-scaffold service <name> [--controller <name>]... [--dir <path>]
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use eywa_axum::scaffold::{write_files, ControllerScaffold, ServiceScaffold};
+
+const USAGE: &str = "\
+usage: eywa-scaffold controller <name> [--resource <Type>] [--version <v1>] [--dir <path>]
+       eywa-scaffold service <name> [--controller <name>]... [--dir <path>]";
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1).collect()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let (command, name, options) = match args.as_slice() {
+        [command, name, options @ ..] => (command.as_str(), name.clone(), options),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let mut dir = PathBuf::from(".");
+    let mut resource = None;
+    let mut version = None;
+    let mut controllers = Vec::new();
+
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| format!("missing value for {option}\n{USAGE}"))?
+            .clone();
+        match option.as_str() {
+            "--dir" => dir = PathBuf::from(value),
+            "--resource" => resource = Some(value),
+            "--version" => version = Some(value),
+            "--controller" => controllers.push(value),
+            _ => return Err(format!("unknown option {option}\n{USAGE}")),
+        }
+    }
+
+    let (root, files, hint) = match command {
+        "controller" => {
+            let mut scaffold = ControllerScaffold::new(name);
+            if let Some(resource) = resource {
+                scaffold = scaffold.resource(resource);
+            }
+            if let Some(version) = version {
+                scaffold = scaffold.version(version);
+            }
+            let hint = format!(
+                "add `pub mod {};` to src/controllers/mod.rs and `.mount::<{}>()` to the app",
+                scaffold.module(),
+                scaffold.controller()
+            );
+            (dir, scaffold.files(), Some(hint))
+        }
+        "service" => {
+            let scaffold = controllers
+                .into_iter()
+                .fold(ServiceScaffold::new(name.clone()), |service, controller| {
+                    service.controller(ControllerScaffold::new(controller))
+                });
+            (dir.join(&name), scaffold.files(), None)
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let written = write_files(&root, &files).map_err(|e| e.to_string())?;
+    for path in written {
+        println!("created {}", path.display());
+    }
+    if let Some(hint) = hint {
+        println!("{hint}");
+    }
+    Ok(())
+}
@@ -0,0 +1,521 @@
+//! Request capture and replay for debugging production-only bugs.
+//!
+//! This module provides:
+//! - `capture_middleware` - Opt-in recorder persisting sanitized request/response pairs
+//! - `CaptureFilter` - Which exchanges to record (route, status, correlation ID)
+//! - `CaptureStore` - Storage backend (`MemoryCaptureStore`, `FileCaptureStore`)
+//! - `replay` - Re-issue a captured request against a local build
+
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_LENGTH, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use eywa_errors::AppError;
+
+use crate::middleware::RequestContext;
+use crate::Result;
+
+/// Placeholder written in place of sanitized values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A recorded request/response pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedExchange {
+    pub id: Uuid,
+    pub captured_at: DateTime<Utc>,
+    pub correlation_id: Option<Uuid>,
+    pub method: String,
+    /// Path and query of the original request
+    pub uri: String,
+    /// Matched route template, if any
+    pub route: Option<String>,
+    pub request_headers: Vec<(String, String)>,
+    /// UTF-8 request body, `None` if too large, streamed or binary
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    /// UTF-8 response body, `None` if too large, streamed or binary
+    pub response_body: Option<String>,
+}
+
+/// Selects which exchanges are recorded.
+///
+/// An exchange is recorded if it matches ANY of the configured criteria.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureFilter {
+    routes: HashSet<String>,
+    min_status: Option<u16>,
+    correlation_ids: HashSet<Uuid>,
+}
+
+impl CaptureFilter {
+    /// Create a filter matching nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record every request to the given route template.
+    pub fn route(mut self, route: impl Into<String>) -> Self {
+        self.routes.insert(route.into());
+        self
+    }
+
+    /// Record every response with a status of at least `status` (e.g. 500).
+    pub fn min_status(mut self, status: u16) -> Self {
+        self.min_status = Some(status);
+        self
+    }
+
+    /// Record every request carrying the given correlation ID.
+    pub fn correlation_id(mut self, correlation_id: Uuid) -> Self {
+        self.correlation_ids.insert(correlation_id);
+        self
+    }
+
+    /// Returns `true` if the exchange should be recorded.
+    pub fn matches(&self, route: Option<&str>, status: u16, correlation_id: Option<Uuid>) -> bool {
+        route.is_some_and(|route| self.routes.contains(route))
+            || self.min_status.is_some_and(|min| status >= min)
+            || correlation_id.is_some_and(|id| self.correlation_ids.contains(&id))
+    }
+}
+
+/// Storage backend for captured exchanges.
+#[async_trait]
+pub trait CaptureStore: Send + Sync + 'static {
+    /// Persist an exchange.
+    async fn save(&self, exchange: CapturedExchange) -> Result<()>;
+
+    /// Load an exchange by ID.
+    async fn get(&self, id: Uuid) -> Result<Option<CapturedExchange>>;
+
+    /// List all stored exchanges.
+    async fn list(&self) -> Result<Vec<CapturedExchange>>;
+}
+
+/// In-memory store keeping the most recent exchanges.
+#[derive(Debug)]
+pub struct MemoryCaptureStore {
+    capacity: usize,
+    exchanges: Mutex<VecDeque<CapturedExchange>>,
+}
+
+impl MemoryCaptureStore {
+    /// Create a store keeping at most `capacity` exchanges; 0 keeps none.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            exchanges: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+}
+
+#[async_trait]
+impl CaptureStore for MemoryCaptureStore {
+    async fn save(&self, exchange: CapturedExchange) -> Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let mut exchanges = self.exchanges.lock().unwrap();
+        if exchanges.len() == self.capacity {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<CapturedExchange>> {
+        let exchanges = self.exchanges.lock().unwrap();
+        Ok(exchanges.iter().find(|e| e.id == id).cloned())
+    }
+
+    async fn list(&self) -> Result<Vec<CapturedExchange>> {
+        Ok(self.exchanges.lock().unwrap().iter().cloned().collect())
+    }
+}
+
+/// Store writing each exchange as `<dir>/<id>.json`.
+#[derive(Debug, Clone)]
+pub struct FileCaptureStore {
+    dir: PathBuf,
+}
+
+impl FileCaptureStore {
+    /// Create a store writing into `dir` (created on first save).
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+fn io_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Capture store error: {e}"))
+}
+
+#[async_trait]
+impl CaptureStore for FileCaptureStore {
+    async fn save(&self, exchange: CapturedExchange) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await.map_err(io_error)?;
+        let json = serde_json::to_vec_pretty(&exchange).map_err(io_error)?;
+        tokio::fs::write(self.dir.join(format!("{}.json", exchange.id)), json)
+            .await
+            .map_err(io_error)
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<CapturedExchange>> {
+        match tokio::fs::read(self.dir.join(format!("{id}.json"))).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map(Some).map_err(io_error),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io_error(e)),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<CapturedExchange>> {
+        let mut exchanges = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(exchanges),
+            Err(e) => return Err(io_error(e)),
+        };
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            let bytes = tokio::fs::read(entry.path()).await.map_err(io_error)?;
+            exchanges.push(serde_json::from_slice(&bytes).map_err(io_error)?);
+        }
+        exchanges.sort_by_key(|e: &CapturedExchange| e.captured_at);
+        Ok(exchanges)
+    }
+}
+
+/// Capture configuration.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::capture::{CaptureConfig, CaptureFilter, FileCaptureStore};
+///
+/// let config = CaptureConfig::new(
+///     CaptureFilter::new().min_status(500).route("/api/v1/invoices/{id}"),
+///     FileCaptureStore::new("/var/lib/eywa/captures"),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct CaptureConfig {
+    filter: CaptureFilter,
+    store: Arc<dyn CaptureStore>,
+    redact_headers: HashSet<String>,
+    redact_fields: HashSet<String>,
+    max_body_bytes: usize,
+}
+
+impl CaptureConfig {
+    /// Create a configuration with the default sanitization rules.
+    ///
+    /// Redacts the `Authorization`, `Cookie`, `Set-Cookie` and `X-Api-Key`
+    /// headers, and the `password`, `token`, `secret` JSON fields. Bodies
+    /// larger than 64 KiB are not recorded.
+    pub fn new(filter: CaptureFilter, store: impl CaptureStore) -> Self {
+        Self {
+            filter,
+            store: Arc::new(store),
+            redact_headers: ["authorization", "cookie", "set-cookie", "x-api-key"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            redact_fields: ["password", "token", "secret"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            max_body_bytes: 64 * 1024,
+        }
+    }
+
+    /// Redact an additional header.
+    pub fn redact_header(mut self, name: impl AsRef<str>) -> Self {
+        self.redact_headers.insert(name.as_ref().to_lowercase());
+        self
+    }
+
+    /// Redact an additional JSON body field (at any depth).
+    pub fn redact_field(mut self, name: impl AsRef<str>) -> Self {
+        self.redact_fields.insert(name.as_ref().to_lowercase());
+        self
+    }
+
+    /// Maximum size of a recorded body.
+    pub fn max_body_bytes(mut self, max: usize) -> Self {
+        self.max_body_bytes = max;
+        self
+    }
+
+    fn sanitize_headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if self.redact_headers.contains(name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    fn sanitize_body(&self, body: Option<&Bytes>) -> Option<String> {
+        let body = std::str::from_utf8(body?).ok()?;
+        match serde_json::from_str::<serde_json::Value>(body) {
+            Ok(mut json) => {
+                redact_json(&mut json, &self.redact_fields);
+                Some(json.to_string())
+            }
+            Err(_) => Some(body.to_string()),
+        }
+    }
+}
+
+/// Replace the values of sensitive fields, recursively.
+fn redact_json(value: &mut serde_json::Value, fields: &HashSet<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if fields.contains(&key.to_lowercase()) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value, fields);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                redact_json(item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Buffer a body if its size is known and within `max` bytes.
+///
+/// Returns the (possibly rebuilt) body and the buffered bytes, or the error
+/// reading it: the body is consumed then, so it can't be passed on.
+async fn buffer_body(body: Body, max: usize) -> std::result::Result<(Body, Option<Bytes>), String> {
+    if body.size_hint().upper().is_none_or(|upper| upper > max as u64) {
+        return Ok((body, None));
+    }
+    let bytes = axum::body::to_bytes(body, max).await.map_err(|e| e.to_string())?;
+    Ok((Body::from(bytes.clone()), Some(bytes)))
+}
+
+/// Axum middleware recording exchanges matching the configured filter.
+///
+/// The exchange is written to the store in a background task, so a slow
+/// store never delays the response.
+///
+/// Installed by `EywaApp::capture()`.
+pub async fn capture_middleware(
+    State(config): State<Arc<CaptureConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let correlation_id = req
+        .extensions()
+        .get::<RequestContext>()
+        .map(|ctx| ctx.correlation_id);
+    let method = req.method().to_string();
+    let uri = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.to_string())
+        .unwrap_or_default();
+    let request_headers = config.sanitize_headers(req.headers());
+
+    let (parts, body) = req.into_parts();
+    let (body, request_body) = if parts.headers.contains_key(CONTENT_LENGTH) {
+        match buffer_body(body, config.max_body_bytes).await {
+            Ok(buffered) => buffered,
+            Err(e) => {
+                return AppError::BadRequest(format!("Failed to read the request body: {e}"))
+                    .into_response();
+            }
+        }
+    } else {
+        (body, None)
+    };
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    let status = response.status().as_u16();
+    if !config.filter.matches(route.as_deref(), status, correlation_id) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let (body, response_body) = match buffer_body(body, config.max_body_bytes).await {
+        Ok(buffered) => buffered,
+        Err(e) => {
+            tracing::error!("failed to read the response body: {}", e);
+            return AppError::InternalServerError(format!("Failed to read the response body: {e}"))
+                .into_response();
+        }
+    };
+
+    let exchange = CapturedExchange {
+        id: Uuid::new_v4(),
+        captured_at: Utc::now(),
+        correlation_id,
+        method,
+        uri,
+        route,
+        request_headers,
+        request_body: config.sanitize_body(request_body.as_ref()),
+        status,
+        response_headers: config.sanitize_headers(&parts.headers),
+        response_body: config.sanitize_body(response_body.as_ref()),
+    };
+
+    let store = config.store.clone();
+    tokio::spawn(async move {
+        let id = exchange.id;
+        match store.save(exchange).await {
+            Ok(()) => tracing::info!(capture_id = %id, "request captured"),
+            Err(e) => tracing::warn!("failed to store captured request: {:?}", e),
+        }
+    });
+
+    Response::from_parts(parts, body)
+}
+
+/// Headers never re-sent on replay.
+const SKIPPED_REPLAY_HEADERS: [&str; 3] = ["host", "content-length", "transfer-encoding"];
+
+/// Re-issue a captured request against another deployment (e.g. a local build).
+///
+/// Redacted headers are dropped and an `X-Replayed-From` header carrying the
+/// capture ID is added.
+///
+/// # Example
+///
+/// ```ignore
+/// let exchange = store.get(capture_id).await?.unwrap();
+/// let response = eywa_axum::capture::replay(&exchange, "http://localhost:3000").await?;
+/// assert_eq!(response.status().as_u16(), exchange.status);
+/// ```
+pub async fn replay(exchange: &CapturedExchange, base_url: &str) -> Result<reqwest::Response> {
+    let method = reqwest::Method::from_bytes(exchange.method.as_bytes())
+        .map_err(|e| AppError::InternalServerError(format!("Invalid method: {e}")))?;
+    let url = format!("{}{}", base_url.trim_end_matches('/'), exchange.uri);
+
+    let mut request = reqwest::Client::new()
+        .request(method, url)
+        .header("x-replayed-from", exchange.id.to_string());
+    for (name, value) in &exchange.request_headers {
+        if value != REDACTED && !SKIPPED_REPLAY_HEADERS.contains(&name.as_str()) {
+            request = request.header(name, value);
+        }
+    }
+    if let Some(body) = &exchange.request_body {
+        request = request.body(body.clone());
+    }
+
+    request
+        .send()
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Replay failed: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn config() -> CaptureConfig {
+        CaptureConfig::new(CaptureFilter::new(), MemoryCaptureStore::new(10))
+    }
+
+    #[test]
+    fn test_filter_matches_any_criterion() {
+        let correlation_id = Uuid::new_v4();
+        let filter = CaptureFilter::new()
+            .route("/projects/{id}")
+            .min_status(500)
+            .correlation_id(correlation_id);
+
+        assert!(filter.matches(Some("/projects/{id}"), 200, None));
+        assert!(filter.matches(Some("/users"), 503, None));
+        assert!(filter.matches(None, 200, Some(correlation_id)));
+        assert!(!filter.matches(Some("/users"), 404, Some(Uuid::new_v4())));
+    }
+
+    #[test]
+    fn test_sanitize_headers_redacts_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("accept", HeaderValue::from_static("application/json"));
+
+        let sanitized = config().sanitize_headers(&headers);
+        assert!(sanitized.contains(&("authorization".to_string(), REDACTED.to_string())));
+        assert!(sanitized.contains(&("accept".to_string(), "application/json".to_string())));
+    }
+
+    #[test]
+    fn test_sanitize_body_redacts_nested_fields() {
+        let body = Bytes::from(r#"{"user":{"email":"a@b.c","Password":"hunter2"}}"#);
+        let sanitized = config().sanitize_body(Some(&body)).unwrap();
+        assert_eq!(
+            sanitized,
+            r#"{"user":{"Password":"[REDACTED]","email":"a@b.c"}}"#
+        );
+    }
+
+    fn exchange(status: u16) -> CapturedExchange {
+        CapturedExchange {
+            id: Uuid::new_v4(),
+            captured_at: Utc::now(),
+            correlation_id: None,
+            method: "GET".to_string(),
+            uri: "/".to_string(),
+            route: None,
+            request_headers: Vec::new(),
+            request_body: None,
+            status,
+            response_headers: Vec::new(),
+            response_body: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_evicts_oldest() {
+        let store = MemoryCaptureStore::new(1);
+        store.save(exchange(500)).await.unwrap();
+        store.save(exchange(502)).await.unwrap();
+
+        let stored = store.list().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].status, 502);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_with_no_capacity_stores_nothing() {
+        let store = MemoryCaptureStore::new(0);
+        let captured = exchange(500);
+        let id = captured.id;
+        store.save(captured).await.unwrap();
+
+        assert!(store.list().await.unwrap().is_empty());
+        assert!(store.get(id).await.unwrap().is_none());
+    }
+}
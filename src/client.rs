@@ -0,0 +1,750 @@
+//! Context-aware outbound HTTP client.
+//!
+//! `OutboundClient` wraps a `reqwest::Client` and prepares each downstream
+//! request from the inbound `RequestContext`:
+//! - forwards `X-Correlation-ID`
+//! - forwards the inbound headers selected by the `HeaderPolicy` (see
+//!   `middleware::propagation`)
+//! - forwards the request deadline (`X-Request-Deadline` and `grpc-timeout`)
+//!   and uses the remaining budget as the request timeout
+//! - refuses to start calls once the deadline has passed, so expired work
+//!   doesn't fan out into downstream retries
+//!
+//! `OutboundClient::send` translates upstream failures into `UpstreamError`s
+//! (`502`, `504` or `424` with the upstream correlation ID), so gateway-style
+//! services answer with consistent error codes instead of raw reqwest messages.
+//!
+//! `OutboundClient::send_hedged` cuts the tail latency of critical internal
+//! calls: when the first attempt hasn't answered after a delay (by default
+//! the observed p99), a second attempt is sent to another replica and the
+//! first response wins, the other attempt being cancelled.
+
+use std::collections::{HashMap, VecDeque};
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use futures_util::future::{select, Either};
+use reqwest::{IntoUrl, Method, RequestBuilder, Url};
+
+use crate::error_codes::{self, CodedError, ErrorCodeInfo};
+use crate::error_responses::ErrorResponse;
+use crate::middleware::deadline::{
+    deadline_exceeded, format_grpc_timeout, DEADLINE_HEADER, GRPC_TIMEOUT_HEADER,
+};
+use crate::middleware::RequestContext;
+
+/// Error returned when the request deadline has already passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("request deadline exceeded")]
+pub struct DeadlineExceeded;
+
+impl IntoResponse for DeadlineExceeded {
+    fn into_response(self) -> Response {
+        deadline_exceeded()
+    }
+}
+
+/// How an upstream failure is reported to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamFailure {
+    /// `502 Bad Gateway`: the upstream is unreachable or failed
+    Unavailable,
+    /// `504 Gateway Timeout`: the upstream didn't answer in time
+    Timeout,
+    /// `424 Failed Dependency`: the upstream rejected the request
+    Rejected,
+}
+
+impl UpstreamFailure {
+    /// Status of the response sent to the caller.
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::Unavailable => StatusCode::BAD_GATEWAY,
+            Self::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Self::Rejected => StatusCode::FAILED_DEPENDENCY,
+        }
+    }
+
+    /// Error code of the response sent to the caller.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Unavailable => error_codes::UPSTREAM_UNAVAILABLE,
+            Self::Timeout => error_codes::UPSTREAM_TIMEOUT,
+            Self::Rejected => error_codes::UPSTREAM_REJECTED,
+        }
+    }
+}
+
+/// Maps upstream failures to `UpstreamFailure`s.
+///
+/// By default, timeouts (and upstream `408`/`504`) become `Timeout`, other
+/// `4xx` become `Rejected`, and everything else (`5xx`, connection errors)
+/// becomes `Unavailable`. Override single statuses with `status`.
+///
+/// # Example
+///
+/// ```ignore
+/// let billing = OutboundClient::new(resources.http.clone())
+///     .errors(UpstreamErrorMapper::new("billing").status(429, UpstreamFailure::Unavailable));
+/// ```
+#[derive(Debug, Clone)]
+pub struct UpstreamErrorMapper {
+    service: String,
+    statuses: HashMap<u16, UpstreamFailure>,
+}
+
+impl Default for UpstreamErrorMapper {
+    fn default() -> Self {
+        Self::new("upstream")
+    }
+}
+
+impl UpstreamErrorMapper {
+    /// Mapper for the named upstream service (used in error messages).
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            statuses: HashMap::new(),
+        }
+    }
+
+    /// Report upstream responses with `status` as `failure`.
+    pub fn status(mut self, status: u16, failure: UpstreamFailure) -> Self {
+        self.statuses.insert(status, failure);
+        self
+    }
+
+    /// The failure reported for an upstream response status.
+    pub fn map_status(&self, status: StatusCode) -> UpstreamFailure {
+        if let Some(failure) = self.statuses.get(&status.as_u16()) {
+            return *failure;
+        }
+        match status {
+            StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => UpstreamFailure::Timeout,
+            status if status.is_client_error() => UpstreamFailure::Rejected,
+            _ => UpstreamFailure::Unavailable,
+        }
+    }
+
+    /// Translate a failed upstream response.
+    pub fn map_response(&self, response: &reqwest::Response) -> UpstreamError {
+        let status = response.status();
+        UpstreamError {
+            failure: self.map_status(status),
+            service: self.service.clone(),
+            upstream_status: Some(status.as_u16()),
+            upstream_correlation_id: correlation_id(response.headers()),
+        }
+    }
+
+    /// Translate a transport error (connection, timeout, body).
+    pub fn map_error(&self, error: &reqwest::Error) -> UpstreamError {
+        let failure = match error.status() {
+            Some(status) => self.map_status(status),
+            None if error.is_timeout() => UpstreamFailure::Timeout,
+            None => UpstreamFailure::Unavailable,
+        };
+        tracing::warn!(service = %self.service, error = %error, "upstream call failed");
+        UpstreamError {
+            failure,
+            service: self.service.clone(),
+            upstream_status: error.status().map(|status| status.as_u16()),
+            upstream_correlation_id: None,
+        }
+    }
+}
+
+/// Correlation ID of an upstream response.
+fn correlation_id(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    ["x-correlation-id", "x-request-id"]
+        .into_iter()
+        .find_map(|name| headers.get(name)?.to_str().ok())
+        .map(str::to_string)
+}
+
+/// A failed upstream call, answered with `502`, `504` or `424`.
+///
+/// The message names the upstream service and status, never the raw
+/// transport error (which is logged instead).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamError {
+    pub failure: UpstreamFailure,
+    /// Name of the upstream service
+    pub service: String,
+    /// Status answered by the upstream, if it answered
+    pub upstream_status: Option<u16>,
+    /// Correlation ID reported by the upstream, if it answered
+    pub upstream_correlation_id: Option<String>,
+}
+
+impl std::fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.failure, self.upstream_status) {
+            (UpstreamFailure::Timeout, None) => write!(f, "Upstream '{}' timed out", self.service),
+            (_, Some(status)) => write!(f, "Upstream '{}' responded {status}", self.service),
+            (_, None) => write!(f, "Upstream '{}' is unreachable", self.service),
+        }
+    }
+}
+
+impl std::error::Error for UpstreamError {}
+
+impl CodedError for UpstreamError {
+    fn code(&self) -> &'static str {
+        self.failure.code()
+    }
+
+    fn status(&self) -> StatusCode {
+        self.failure.status()
+    }
+
+    fn catalog() -> Vec<ErrorCodeInfo> {
+        let standard = error_codes::ErrorCatalog::standard();
+        [
+            UpstreamFailure::Unavailable,
+            UpstreamFailure::Timeout,
+            UpstreamFailure::Rejected,
+        ]
+        .into_iter()
+        .filter_map(|failure| standard.get(failure.code()).cloned())
+        .collect()
+    }
+
+    fn error_response(&self) -> Response {
+        let mut body = ErrorResponse::new(self.code(), self.to_string());
+        if let Some(id) = &self.upstream_correlation_id {
+            body = body.upstream_correlation_id(id);
+        }
+        body.into_response_with(self.status())
+    }
+}
+
+impl IntoResponse for UpstreamError {
+    fn into_response(self) -> Response {
+        self.error_response()
+    }
+}
+
+/// Latencies kept to compute the hedging delay.
+const LATENCY_WINDOW: usize = 1000;
+
+/// Latencies observed before the hedging delay follows them.
+const MIN_LATENCY_SAMPLES: usize = 20;
+
+/// When the second attempt of a hedged request is sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HedgeDelay {
+    /// After a fixed delay
+    Fixed(Duration),
+    /// After the given quantile (e.g. `0.99`) of the recent latencies, or
+    /// `initial` until enough latencies are observed
+    Quantile { quantile: f64, initial: Duration },
+}
+
+/// Hedged request settings of an `OutboundClient`.
+///
+/// # Example
+///
+/// ```ignore
+/// let billing = OutboundClient::new(resources.http.clone()).hedging(
+///     Hedging::p99(Duration::from_millis(50))
+///         .replicas(["http://billing-0.billing:8080", "http://billing-1.billing:8080"])?,
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Hedging {
+    delay: HedgeDelay,
+    replicas: Vec<Url>,
+    next_replica: AtomicUsize,
+    latencies: Mutex<VecDeque<Duration>>,
+}
+
+impl Hedging {
+    pub fn new(delay: HedgeDelay) -> Self {
+        Self {
+            delay,
+            replicas: Vec::new(),
+            next_replica: AtomicUsize::new(0),
+            latencies: Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW)),
+        }
+    }
+
+    /// Hedge after a fixed delay.
+    pub fn fixed(delay: Duration) -> Self {
+        Self::new(HedgeDelay::Fixed(delay))
+    }
+
+    /// Hedge after the p99 of the recent latencies, `initial` until enough are observed.
+    pub fn p99(initial: Duration) -> Self {
+        Self::new(HedgeDelay::Quantile {
+            quantile: 0.99,
+            initial,
+        })
+    }
+
+    /// Send the second attempts to these replicas in turn, instead of the
+    /// request's own host.
+    ///
+    /// Only the scheme, host and port of the request's URL are replaced.
+    pub fn replicas<I>(mut self, replicas: I) -> Result<Self, url::ParseError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.replicas = replicas
+            .into_iter()
+            .map(|replica| Url::parse(replica.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// The current delay before the second attempt.
+    pub fn delay(&self) -> Duration {
+        match self.delay {
+            HedgeDelay::Fixed(delay) => delay,
+            HedgeDelay::Quantile { quantile, initial } => {
+                let latencies = self.latencies.lock().unwrap();
+                if latencies.len() < MIN_LATENCY_SAMPLES {
+                    return initial;
+                }
+                let mut sorted: Vec<_> = latencies.iter().copied().collect();
+                sorted.sort_unstable();
+                let rank = (quantile * sorted.len() as f64).ceil() as usize;
+                sorted[rank.clamp(1, sorted.len()) - 1]
+            }
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.len() == LATENCY_WINDOW {
+            latencies.pop_front();
+        }
+        latencies.push_back(latency);
+    }
+
+    /// Point the second attempt at the next replica.
+    fn retarget(&self, request: &mut reqwest::Request) {
+        if self.replicas.is_empty() {
+            return;
+        }
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+        let replica = &self.replicas[index];
+        let url = request.url_mut();
+        // Replicas are parsed absolute URLs, so these can't fail
+        let _ = url.set_scheme(replica.scheme());
+        let _ = url.set_host(replica.host_str());
+        let _ = url.set_port(replica.port());
+    }
+}
+
+/// Returns `true` for the methods safe to send twice.
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+    )
+}
+
+/// HTTP client propagating the request context to downstream services.
+///
+/// # Example
+///
+/// ```ignore
+/// async fn get_invoice(
+///     Extension(ctx): Extension<RequestContext>,
+///     State(state): State<AppState>,
+/// ) -> Result<Json<Invoice>> {
+///     let invoice = state
+///         .billing
+///         .get(&ctx, "http://billing/api/v1/invoices/42")
+///         .map_err(|e| AppError::InternalServerError(e.to_string()))?
+///         .send()
+///         .await?;
+///     // ...
+/// }
+///
+/// // Gateway-style: answer upstream failures with 502/504/424
+/// async fn proxy_invoice(
+///     Extension(ctx): Extension<RequestContext>,
+///     State(state): State<AppState>,
+/// ) -> std::result::Result<Json<Invoice>, Response> {
+///     let request = state
+///         .billing
+///         .get(&ctx, "http://billing/api/v1/invoices/42")
+///         .map_err(IntoResponse::into_response)?;
+///     let response = state.billing.send(request).await.map_err(IntoResponse::into_response)?;
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutboundClient {
+    client: reqwest::Client,
+    margin: Duration,
+    errors: UpstreamErrorMapper,
+    hedging: Option<Arc<Hedging>>,
+}
+
+impl OutboundClient {
+    /// Wrap a client (e.g. `Resources::http`).
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            margin: Duration::ZERO,
+            errors: UpstreamErrorMapper::default(),
+            hedging: None,
+        }
+    }
+
+    /// Translate upstream failures with `mapper`.
+    pub fn errors(mut self, mapper: UpstreamErrorMapper) -> Self {
+        self.errors = mapper;
+        self
+    }
+
+    /// Keep part of the remaining budget for this service to answer after
+    /// the downstream call returns.
+    pub fn margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Hedge the requests sent with `send_hedged`.
+    pub fn hedging(mut self, hedging: Hedging) -> Self {
+        self.hedging = Some(Arc::new(hedging));
+        self
+    }
+
+    /// The wrapped client.
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Send a request, translating transport errors and error statuses
+    /// (`4xx`/`5xx`) into `UpstreamError`s.
+    pub async fn send(&self, request: RequestBuilder) -> Result<reqwest::Response, UpstreamError> {
+        self.check(request.send().await)
+    }
+
+    /// Send a request like `send`, hedging it when the client has `Hedging`.
+    ///
+    /// If the first attempt hasn't answered after the hedging delay, a second
+    /// attempt is sent (to the next replica, if any) and the first response
+    /// wins; the other attempt is cancelled. A transport error of one attempt
+    /// waits for the other. Only idempotent requests with a clonable body are
+    /// hedged; others are sent once.
+    pub async fn send_hedged(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<reqwest::Response, UpstreamError> {
+        let Some(hedging) = self.hedging.as_deref() else {
+            return self.send(request).await;
+        };
+        let (client, request) = request.build_split();
+        let request = request.map_err(|e| self.errors.map_error(&e))?;
+        let Some(mut hedge) = request.try_clone().filter(|_| is_idempotent(request.method()))
+        else {
+            return self.check(client.execute(request).await);
+        };
+        hedging.retarget(&mut hedge);
+
+        let started = Instant::now();
+        let mut primary = pin!(client.execute(request));
+        let delay = pin!(tokio::time::sleep(hedging.delay()));
+        let response = match select(primary.as_mut(), delay).await {
+            Either::Left((response, _)) => {
+                hedging.record(started.elapsed());
+                response
+            }
+            Either::Right(((), primary)) => {
+                metrics::counter!(
+                    "eywa_outbound_hedged_requests_total",
+                    "service" => self.errors.service.clone()
+                )
+                .increment(1);
+                let hedged = Instant::now();
+                let secondary = pin!(client.execute(hedge));
+                match select(primary, secondary).await {
+                    Either::Left((Ok(response), _)) => {
+                        hedging.record(started.elapsed());
+                        Ok(response)
+                    }
+                    Either::Right((Ok(response), _)) => {
+                        hedging.record(hedged.elapsed());
+                        Ok(response)
+                    }
+                    Either::Left((Err(_), secondary)) => secondary.await,
+                    Either::Right((Err(_), primary)) => primary.await,
+                }
+            }
+        };
+        self.check(response)
+    }
+
+    /// Translate transport errors and error statuses into `UpstreamError`s.
+    fn check(
+        &self,
+        response: reqwest::Result<reqwest::Response>,
+    ) -> Result<reqwest::Response, UpstreamError> {
+        let response = response.map_err(|e| self.errors.map_error(&e))?;
+        if response.status().is_client_error() || response.status().is_server_error() {
+            return Err(self.errors.map_response(&response));
+        }
+        Ok(response)
+    }
+
+    /// Start a request carrying the context's correlation ID, deadline and
+    /// propagated headers.
+    pub fn request(
+        &self,
+        ctx: &RequestContext,
+        method: Method,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-correlation-id", ctx.correlation_id.to_string());
+        for (name, value) in &ctx.propagated_headers {
+            request = request.header(name, value);
+        }
+
+        if let Some(deadline) = ctx.deadline {
+            let budget = ctx
+                .remaining_budget()
+                .and_then(|remaining| remaining.checked_sub(self.margin))
+                .filter(|budget| !budget.is_zero())
+                .ok_or(DeadlineExceeded)?;
+            let downstream = deadline
+                - chrono::Duration::from_std(self.margin).unwrap_or(chrono::Duration::zero());
+            request = request
+                .header(DEADLINE_HEADER, downstream.timestamp_millis().to_string())
+                .header(GRPC_TIMEOUT_HEADER, format_grpc_timeout(budget))
+                .timeout(budget);
+        }
+        Ok(request)
+    }
+
+    /// Start a `GET` request.
+    pub fn get(
+        &self,
+        ctx: &RequestContext,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        self.request(ctx, Method::GET, url)
+    }
+
+    /// Start a `POST` request.
+    pub fn post(
+        &self,
+        ctx: &RequestContext,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        self.request(ctx, Method::POST, url)
+    }
+
+    /// Start a `PUT` request.
+    pub fn put(
+        &self,
+        ctx: &RequestContext,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        self.request(ctx, Method::PUT, url)
+    }
+
+    /// Start a `PATCH` request.
+    pub fn patch(
+        &self,
+        ctx: &RequestContext,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        self.request(ctx, Method::PATCH, url)
+    }
+
+    /// Start a `DELETE` request.
+    pub fn delete(
+        &self,
+        ctx: &RequestContext,
+        url: impl IntoUrl,
+    ) -> Result<RequestBuilder, DeadlineExceeded> {
+        self.request(ctx, Method::DELETE, url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_deadline_forwarded_with_remaining_budget() {
+        let ctx = RequestContext {
+            deadline: Some(Utc::now() + chrono::Duration::seconds(2)),
+            ..RequestContext::default()
+        };
+        let client =
+            OutboundClient::new(reqwest::Client::new()).margin(Duration::from_millis(500));
+
+        let request = client.get(&ctx, "http://billing/invoices").unwrap().build().unwrap();
+
+        let timeout = request.timeout().copied().unwrap();
+        assert!(timeout <= Duration::from_millis(1500));
+        assert!(timeout > Duration::from_millis(1000));
+        assert!(request.headers().contains_key(DEADLINE_HEADER));
+        assert!(request.headers()[GRPC_TIMEOUT_HEADER].to_str().unwrap().ends_with('m'));
+        assert_eq!(
+            request.headers()["x-correlation-id"],
+            ctx.correlation_id.to_string().as_str()
+        );
+    }
+
+    #[test]
+    fn test_propagated_headers_forwarded() {
+        let mut ctx = RequestContext::default();
+        ctx.propagated_headers.insert("x-tenant-id".to_string(), "acme".to_string());
+        let client = OutboundClient::new(reqwest::Client::new());
+
+        let request = client.get(&ctx, "http://billing/invoices").unwrap().build().unwrap();
+        assert_eq!(request.headers()["x-tenant-id"], "acme");
+    }
+
+    #[test]
+    fn test_expired_deadline_fails_fast() {
+        let ctx = RequestContext {
+            deadline: Some(Utc::now() - chrono::Duration::seconds(1)),
+            ..RequestContext::default()
+        };
+
+        let result = OutboundClient::default().get(&ctx, "http://billing/invoices");
+        assert_eq!(result.err(), Some(DeadlineExceeded));
+    }
+
+    #[test]
+    fn test_upstream_statuses_mapped() {
+        let mapper =
+            UpstreamErrorMapper::new("billing").status(429, UpstreamFailure::Unavailable);
+
+        let failure = |status| mapper.map_status(status);
+        assert_eq!(failure(StatusCode::SERVICE_UNAVAILABLE), UpstreamFailure::Unavailable);
+        assert_eq!(failure(StatusCode::GATEWAY_TIMEOUT), UpstreamFailure::Timeout);
+        assert_eq!(failure(StatusCode::NOT_FOUND), UpstreamFailure::Rejected);
+        assert_eq!(failure(StatusCode::TOO_MANY_REQUESTS), UpstreamFailure::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_error_response() {
+        let upstream = axum::Router::new().route(
+            "/invoices",
+            axum::routing::get(|| async {
+                (StatusCode::NOT_FOUND, [("x-correlation-id", "upstream-42")], "no such invoice")
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+        let client = OutboundClient::default().errors(UpstreamErrorMapper::new("billing"));
+        let request = client
+            .get(&RequestContext::default(), format!("http://{addr}/invoices"))
+            .unwrap();
+        let error = client.send(request).await.unwrap_err();
+
+        assert_eq!(error.failure, UpstreamFailure::Rejected);
+        assert_eq!(error.upstream_correlation_id.as_deref(), Some("upstream-42"));
+        assert_eq!(error.to_string(), "Upstream 'billing' responded 404");
+        assert_eq!(error.into_response().status(), StatusCode::FAILED_DEPENDENCY);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_upstream() {
+        let client = OutboundClient::default();
+        let request = client.get(&RequestContext::default(), "http://127.0.0.1:1/").unwrap();
+        let error = client.send(request).await.unwrap_err();
+
+        assert_eq!(error.failure, UpstreamFailure::Unavailable);
+        assert_eq!(error.to_string(), "Upstream 'upstream' is unreachable");
+    }
+
+    #[test]
+    fn test_hedge_delay_follows_p99() {
+        let hedging = Hedging::p99(Duration::from_millis(50));
+        for ms in 1..=10 {
+            hedging.record(Duration::from_millis(ms));
+        }
+        assert_eq!(hedging.delay(), Duration::from_millis(50));
+
+        for ms in 11..=100 {
+            hedging.record(Duration::from_millis(ms));
+        }
+        assert_eq!(hedging.delay(), Duration::from_millis(99));
+    }
+
+    /// Serves `/slow` after 2s on its first call and immediately after, counting calls.
+    async fn flaky_upstream() -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let upstream = axum::Router::new().route(
+            "/slow",
+            axum::routing::any(move || {
+                let call = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if call == 0 {
+                        tokio::time::sleep(Duration::from_secs(2)).await;
+                    }
+                    format!("attempt {call}")
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, upstream).await });
+        (format!("http://{addr}"), calls)
+    }
+
+    #[tokio::test]
+    async fn test_hedged_request_takes_first_response() {
+        let (primary, primary_calls) = flaky_upstream().await;
+        let (replica, replica_calls) = flaky_upstream().await;
+        // The replica answers immediately
+        replica_calls.store(1, Ordering::SeqCst);
+        let client = OutboundClient::default()
+            .hedging(Hedging::fixed(Duration::from_millis(50)).replicas([&replica]).unwrap());
+
+        let started = Instant::now();
+        let request = client.get(&RequestContext::default(), format!("{primary}/slow")).unwrap();
+        let response = client.send_hedged(request).await.unwrap();
+
+        assert_eq!(response.text().await.unwrap(), "attempt 1");
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(primary_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(replica_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_requests_not_hedged() {
+        let (upstream, calls) = flaky_upstream().await;
+        let client = OutboundClient::default().hedging(Hedging::fixed(Duration::from_millis(50)));
+
+        let request = client.post(&RequestContext::default(), format!("{upstream}/slow")).unwrap();
+        let response = client.send_hedged(request).await.unwrap();
+
+        assert_eq!(response.text().await.unwrap(), "attempt 0");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_no_deadline_no_timeout() {
+        let request = OutboundClient::default()
+            .get(&RequestContext::default(), "http://billing/invoices")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert!(request.timeout().is_none());
+        assert!(!request.headers().contains_key(DEADLINE_HEADER));
+    }
+}
@@ -0,0 +1,215 @@
+//! Time source shared by the time-dependent middleware and jobs.
+//!
+//! Rate limit buckets, inbox TTLs, scheduler ticks and JWT expiry read the
+//! time from a `Clock` rather than from `Utc::now()` and `Instant::now()`, so
+//! tests can drive them with a `TestClock` instead of sleeping:
+//!
+//! ```ignore
+//! let clock = TestClock::new();
+//! let client = EywaApp::new(state)
+//!     .clock(clock.clone())
+//!     .mount::<ProjectsController>()
+//!     .rate_limit(RateLimiter::new(settings))
+//!     .into_test_client();
+//!
+//! client.get("/api/v1/projects").send().await.assert_status(StatusCode::TOO_MANY_REQUESTS);
+//! clock.advance(Duration::from_secs(60));
+//! client.get("/api/v1/projects").send().await.assert_status(StatusCode::OK);
+//! ```
+//!
+//! `EywaApp::clock` provides the clock through the DI container: handlers
+//! extract it with `Inject<SharedClock>`, the rate limit and token
+//! verification middleware read it from the container, and the scheduler is
+//! given it when the app starts. Inbox stores are given it with their
+//! `clock` method. Without it, everything uses `SystemClock`.
+//!
+//! The scheduler waits for its ticks with `Clock::sleep_until`: advancing a
+//! `TestClock` past a tick runs the job right away.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::Extensions;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use tokio::sync::watch;
+
+use crate::di::Container;
+
+/// Source of the current time.
+pub trait Clock: Send + Sync + std::fmt::Debug + 'static {
+    /// The current wall-clock time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The current monotonic time, for measuring durations.
+    fn instant(&self) -> Instant;
+
+    /// Wait until the wall-clock time reaches `deadline`.
+    ///
+    /// By default, sleeps in real time for the time left by `now`.
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> BoxFuture<'static, ()> {
+        let wait = (deadline - self.now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).boxed()
+    }
+}
+
+/// A clock shared by the app, as provided to the DI container.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// The system's clock, shared.
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// The clock provided in the request's DI container, or the system's.
+pub fn from_extensions(extensions: &Extensions) -> SharedClock {
+    extensions
+        .get::<Arc<Container>>()
+        .and_then(|container| container.get::<SharedClock>())
+        .unwrap_or_else(system_clock)
+}
+
+#[derive(Debug)]
+struct Frozen {
+    now: DateTime<Utc>,
+    elapsed: Duration,
+}
+
+/// A clock that only moves when told to, for tests.
+///
+/// Clones share the same time. `sleep_until` returns once the clock is moved
+/// past the deadline, however little real time has passed.
+#[derive(Debug, Clone)]
+pub struct TestClock {
+    origin: Instant,
+    frozen: Arc<Mutex<Frozen>>,
+    moved: Arc<watch::Sender<DateTime<Utc>>>,
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        Self::at(Utc::now())
+    }
+}
+
+impl TestClock {
+    /// A clock stopped at the current time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A clock stopped at `now`.
+    pub fn at(now: DateTime<Utc>) -> Self {
+        Self {
+            origin: Instant::now(),
+            frozen: Arc::new(Mutex::new(Frozen {
+                now,
+                elapsed: Duration::ZERO,
+            })),
+            moved: Arc::new(watch::Sender::new(now)),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut frozen = self.frozen.lock().unwrap();
+        frozen.now += chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+        frozen.elapsed += duration;
+        self.moved.send_replace(frozen.now);
+    }
+
+    /// Set the wall-clock time, e.g. to just before a cron tick.
+    ///
+    /// The monotonic time only moves forward, by the time added if any.
+    pub fn set(&self, now: DateTime<Utc>) {
+        let mut frozen = self.frozen.lock().unwrap();
+        frozen.elapsed += (now - frozen.now).to_std().unwrap_or_default();
+        frozen.now = now;
+        self.moved.send_replace(now);
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.frozen.lock().unwrap().now
+    }
+
+    fn instant(&self) -> Instant {
+        self.origin + self.frozen.lock().unwrap().elapsed
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> BoxFuture<'static, ()> {
+        let mut moved = self.moved.subscribe();
+        async move {
+            // Only fails once every clone is dropped: the time can't move anymore
+            if moved.wait_for(|now| *now >= deadline).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_clock_moves_when_told() {
+        let start = DateTime::parse_from_rfc3339("2024-05-02T09:00:00Z").unwrap().to_utc();
+        let clock = TestClock::at(start);
+        let (now, instant) = (clock.now(), clock.instant());
+        assert_eq!(now, start);
+
+        clock.clone().advance(Duration::from_secs(90));
+        assert_eq!(clock.now(), start + chrono::Duration::seconds(90));
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+
+        // Going back in wall-clock time never moves the monotonic time back
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.instant() - instant, Duration::from_secs(90));
+    }
+
+    #[test]
+    fn test_clock_from_container() {
+        let clock = TestClock::at(DateTime::UNIX_EPOCH);
+        let mut container = Container::new();
+        container.provide::<SharedClock>(Arc::new(clock));
+        let mut extensions = Extensions::new();
+        extensions.insert(Arc::new(container));
+
+        assert_eq!(from_extensions(&extensions).now(), DateTime::UNIX_EPOCH);
+        assert!(from_extensions(&Extensions::new()).now() > DateTime::UNIX_EPOCH);
+    }
+
+    #[tokio::test]
+    async fn test_test_clock_sleeps_until_moved() {
+        let start = DateTime::parse_from_rfc3339("2024-05-02T09:00:00Z").unwrap().to_utc();
+        let clock = TestClock::at(start);
+        let mut sleep = clock.sleep_until(start + chrono::Duration::seconds(60));
+
+        clock.advance(Duration::from_secs(59));
+        assert!((&mut sleep).now_or_never().is_none());
+        clock.advance(Duration::from_secs(1));
+        tokio::time::timeout(Duration::from_secs(1), sleep).await.unwrap();
+
+        // Deadlines already passed return right away
+        assert!(clock.sleep_until(start).now_or_never().is_some());
+    }
+}
@@ -0,0 +1,602 @@
+//! Typed Rust client generation from the OpenAPI spec.
+//!
+//! Emits a self-contained module with a struct per component schema and a
+//! `reqwest` client with one async method per operation, so services calling
+//! each other use a client generated from the same spec the callee serves.
+//!
+//! The generated code depends on `reqwest` (`json` feature), `serde`,
+//! `serde_json` and `uuid` (`serde` feature). Run it from a test, an xtask or
+//! the provider's `build.rs`, and commit the output in the client crate:
+//!
+//! ```ignore
+//! let spec = app(state).openapi();
+//! ClientGenerator::new(&spec)
+//!     .client_name("ProjectsClient")
+//!     .write("../projects-client/src/lib.rs")?;
+//! ```
+
+use std::path::Path;
+
+use utoipa::openapi::path::{Operation, ParameterIn};
+use utoipa::openapi::request_body::RequestBody;
+use utoipa::openapi::schema::{
+    ArrayItems, KnownFormat, Object, Schema, SchemaFormat, SchemaType, Type,
+};
+use utoipa::openapi::{OpenApi, Ref, RefOr, Required};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// Rust keywords that must be escaped as raw identifiers.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use",
+    "where", "while",
+];
+
+/// Generator of a typed `reqwest` client from an OpenAPI document.
+pub struct ClientGenerator<'a> {
+    openapi: &'a OpenApi,
+    client_name: String,
+}
+
+impl<'a> ClientGenerator<'a> {
+    /// Create a generator for the given spec.
+    pub fn new(openapi: &'a OpenApi) -> Self {
+        Self {
+            openapi,
+            client_name: "Client".to_string(),
+        }
+    }
+
+    /// Name of the generated client struct (default `Client`).
+    pub fn client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    /// Generate the client source code.
+    pub fn generate(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "//! Typed client for {} v{}.\n//!\n//! @generated by eywa-axum. Do not edit.\n\n",
+            self.openapi.info.title, self.openapi.info.version
+        ));
+        out.push_str("#![allow(dead_code, clippy::all)]\n\n");
+        out.push_str("use serde::{Deserialize, Serialize};\n\n");
+
+        if let Some(components) = &self.openapi.components {
+            for (name, schema) in &components.schemas {
+                out.push_str(&model(name, schema));
+            }
+        }
+
+        out.push_str(ERROR_TYPE);
+        out.push_str(&client_struct(&self.client_name));
+
+        out.push_str(&format!("impl {} {{\n", self.client_name));
+        out.push_str(CLIENT_CONSTRUCTORS);
+        for (path, item) in &self.openapi.paths.paths {
+            let operations = [
+                ("get", &item.get),
+                ("post", &item.post),
+                ("put", &item.put),
+                ("delete", &item.delete),
+                ("patch", &item.patch),
+            ];
+            for (method, operation) in operations {
+                if let Some(operation) = operation {
+                    out.push_str(&method_for(method, path, operation));
+                }
+            }
+        }
+        out.push_str("}\n");
+
+        out
+    }
+
+    /// Generate the client and write it to `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.generate()).map_err(|e| {
+            AppError::InternalServerError(format!(
+                "Client generation failed: {}: {e}",
+                path.display()
+            ))
+        })
+    }
+}
+
+const ERROR_TYPE: &str = r#"/// Error returned by client calls.
+#[derive(Debug)]
+pub enum ClientError {
+    /// Transport or decoding failure
+    Http(reqwest::Error),
+    /// Non-success response from the service
+    Api { status: u16, body: serde_json::Value },
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "request failed: {e}"),
+            ClientError::Api { status, body } => write!(f, "service returned {status}: {body}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.json().await.unwrap_or(serde_json::Value::Null);
+    Err(ClientError::Api { status: status.as_u16(), body })
+}
+
+"#;
+
+const CLIENT_CONSTRUCTORS: &str = r#"    /// Create a client for the service at `base_url`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_client(base_url, reqwest::Client::new())
+    }
+
+    /// Create a client reusing an existing `reqwest::Client`.
+    pub fn with_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            http,
+            bearer: None,
+        }
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request.
+    pub fn bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer = Some(token.into());
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, url);
+        match &self.bearer {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+"#;
+
+fn client_struct(name: &str) -> String {
+    format!(
+        "/// Typed client, one method per operation.\n\
+         #[derive(Debug, Clone)]\n\
+         pub struct {name} {{\n    \
+         base_url: String,\n    \
+         http: reqwest::Client,\n    \
+         bearer: Option<String>,\n\
+         }}\n\n"
+    )
+}
+
+/// Generate the struct (or enum) for a component schema.
+fn model(name: &str, schema: &RefOr<Schema>) -> String {
+    let name = pascal_case(name);
+    let RefOr::T(Schema::Object(object)) = schema else {
+        return format!("pub type {name} = {};\n\n", rust_type(schema));
+    };
+
+    let mut out = doc_comment(object.description.as_deref(), "");
+
+    if let Some(values) = &object.enum_values {
+        let variants: Vec<&str> = values.iter().filter_map(|v| v.as_str()).collect();
+        if !variants.is_empty() && variants.len() == values.len() {
+            out.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]\n");
+            out.push_str(&format!("pub enum {name} {{\n"));
+            for variant in variants {
+                out.push_str(&format!(
+                    "    #[serde(rename = \"{variant}\")]\n    {},\n",
+                    pascal_case(variant)
+                ));
+            }
+            out.push_str("}\n\n");
+            return out;
+        }
+    }
+
+    if object.properties.is_empty() {
+        out.push_str(&format!("pub type {name} = {};\n\n", object_type(object)));
+        return out;
+    }
+
+    out.push_str("#[derive(Debug, Clone, Serialize, Deserialize)]\n");
+    out.push_str(&format!("pub struct {name} {{\n"));
+    for (property, schema) in &object.properties {
+        let field = snake_case(property);
+        let mut ty = rust_type(schema);
+        let required = object.required.contains(property);
+
+        if let RefOr::T(Schema::Object(inner)) = schema {
+            out.push_str(&doc_comment(inner.description.as_deref(), "    "));
+        }
+        if field != *property {
+            out.push_str(&format!("    #[serde(rename = \"{property}\")]\n"));
+        }
+        if !required {
+            if !ty.starts_with("Option<") {
+                ty = format!("Option<{ty}>");
+            }
+            out.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+        }
+        out.push_str(&format!("    pub {}: {ty},\n", ident(&field)));
+    }
+    out.push_str("}\n\n");
+    out
+}
+
+/// Rust type for a (possibly referenced) schema.
+fn rust_type(schema: &RefOr<Schema>) -> String {
+    match schema {
+        RefOr::Ref(reference) => ref_type(reference),
+        RefOr::T(Schema::Object(object)) => object_type(object),
+        RefOr::T(Schema::Array(array)) => match &array.items {
+            ArrayItems::RefOrSchema(items) => format!("Vec<{}>", rust_type(items)),
+            _ => "Vec<serde_json::Value>".to_string(),
+        },
+        // A single-item oneOf/allOf is how utoipa documents nullable references
+        RefOr::T(Schema::OneOf(one_of)) => nullable_wrapper(&one_of.items),
+        RefOr::T(Schema::AllOf(all_of)) => nullable_wrapper(&all_of.items),
+        _ => "serde_json::Value".to_string(),
+    }
+}
+
+fn nullable_wrapper(items: &[RefOr<Schema>]) -> String {
+    let non_null: Vec<&RefOr<Schema>> = items
+        .iter()
+        .filter(|item| {
+            !matches!(item, RefOr::T(Schema::Object(o)) if o.schema_type == SchemaType::Type(Type::Null))
+        })
+        .collect();
+    match non_null.as_slice() {
+        [single] if non_null.len() < items.len() => format!("Option<{}>", rust_type(single)),
+        [single] => rust_type(single),
+        _ => "serde_json::Value".to_string(),
+    }
+}
+
+fn ref_type(reference: &Ref) -> String {
+    let name = reference
+        .ref_location
+        .rsplit('/')
+        .next()
+        .unwrap_or(&reference.ref_location);
+    pascal_case(name)
+}
+
+fn object_type(object: &Object) -> String {
+    let (primitive, nullable) = match &object.schema_type {
+        SchemaType::Type(primitive) => (Some(primitive), false),
+        SchemaType::Array(types) => (
+            types.iter().find(|t| **t != Type::Null),
+            types.contains(&Type::Null),
+        ),
+        _ => (None, false),
+    };
+
+    let ty = match primitive {
+        Some(Type::String) => match &object.format {
+            Some(SchemaFormat::KnownFormat(KnownFormat::Uuid)) => "uuid::Uuid",
+            _ => "String",
+        },
+        Some(Type::Integer) => match &object.format {
+            Some(SchemaFormat::KnownFormat(KnownFormat::Int32)) => "i32",
+            _ => "i64",
+        },
+        Some(Type::Number) => "f64",
+        Some(Type::Boolean) => "bool",
+        _ => "serde_json::Value",
+    };
+
+    if nullable {
+        format!("Option<{ty}>")
+    } else {
+        ty.to_string()
+    }
+}
+
+/// Generate the client method for one operation.
+fn method_for(method: &str, path: &str, operation: &Operation) -> String {
+    let name = operation
+        .operation_id
+        .as_deref()
+        .map(snake_case)
+        .unwrap_or_else(|| fallback_name(method, path));
+
+    let mut args = Vec::new();
+    let mut query = Vec::new();
+    let mut url = path.to_string();
+    let mut url_args = Vec::new();
+
+    for parameter in operation.parameters.iter().flatten() {
+        let arg = ident(&snake_case(&parameter.name));
+        let ty = parameter
+            .schema
+            .as_ref()
+            .map(rust_type)
+            .unwrap_or_else(|| "String".to_string());
+        match parameter.parameter_in {
+            ParameterIn::Path => {
+                url = url.replace(&format!("{{{}}}", parameter.name), "{}");
+                url_args.push(arg.clone());
+                args.push(format!("{arg}: impl std::fmt::Display"));
+            }
+            ParameterIn::Query => {
+                let required = matches!(parameter.required, Required::True);
+                let ty = if required || ty.starts_with("Option<") {
+                    ty
+                } else {
+                    format!("Option<{ty}>")
+                };
+                query.push((parameter.name.clone(), arg.clone(), ty.starts_with("Option<")));
+                args.push(format!("{arg}: {ty}"));
+            }
+            _ => {}
+        }
+    }
+
+    let body_type = operation.request_body.as_ref().and_then(request_body_type);
+    if let Some(body_type) = &body_type {
+        args.push(format!("body: &{body_type}"));
+    }
+
+    let response_type = response_type(operation);
+
+    let mut out = doc_comment(operation.summary.as_deref(), "    ");
+    out.push_str(&format!("    /// `{} {path}`\n", method.to_uppercase()));
+    out.push_str(&format!(
+        "    pub async fn {name}(&self{}{}) -> Result<{}, ClientError> {{\n",
+        if args.is_empty() { "" } else { ", " },
+        args.join(", "),
+        response_type.as_deref().unwrap_or("()")
+    ));
+    let url_format: String = std::iter::once(format!("\"{{}}{url}\", self.base_url"))
+        .chain(url_args)
+        .collect::<Vec<_>>()
+        .join(", ");
+    out.push_str(&format!(
+        "        let request = self.request(reqwest::Method::{}, format!({url_format}));\n",
+        method.to_uppercase()
+    ));
+    if !query.is_empty() {
+        out.push_str("        let mut query: Vec<(&str, String)> = Vec::new();\n");
+        for (name, arg, optional) in &query {
+            if *optional {
+                out.push_str(&format!(
+                    "        if let Some(value) = &{arg} {{\n            query.push((\"{name}\", value.to_string()));\n        }}\n"
+                ));
+            } else {
+                out.push_str(&format!("        query.push((\"{name}\", {arg}.to_string()));\n"));
+            }
+        }
+        out.push_str("        let request = request.query(&query);\n");
+    }
+    if body_type.is_some() {
+        out.push_str("        let request = request.json(body);\n");
+    }
+    out.push_str("        let response = check(request.send().await?).await?;\n");
+    match response_type {
+        Some(_) => out.push_str("        Ok(response.json().await?)\n"),
+        None => out.push_str("        let _ = response;\n        Ok(())\n"),
+    }
+    out.push_str("    }\n\n");
+    out
+}
+
+fn request_body_type(body: &RequestBody) -> Option<String> {
+    let content = body
+        .content
+        .get("application/json")
+        .or_else(|| body.content.values().next())?;
+    Some(
+        content
+            .schema
+            .as_ref()
+            .map(rust_type)
+            .unwrap_or_else(|| "serde_json::Value".to_string()),
+    )
+}
+
+/// Type of the first 2xx JSON response, `None` if it has no body.
+fn response_type(operation: &Operation) -> Option<String> {
+    let (_, response) = operation
+        .responses
+        .responses
+        .iter()
+        .find(|(code, _)| code.starts_with('2'))?;
+    let RefOr::T(response) = response else {
+        return Some("serde_json::Value".to_string());
+    };
+    let content = response.content.get("application/json")?;
+    Some(
+        content
+            .schema
+            .as_ref()
+            .map(rust_type)
+            .unwrap_or_else(|| "serde_json::Value".to_string()),
+    )
+}
+
+/// Method name for operations without an `operationId`, e.g. `get_projects_id`.
+fn fallback_name(method: &str, path: &str) -> String {
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| snake_case(segment.trim_matches(|c| c == '{' || c == '}')))
+        .collect();
+    format!("{method}_{}", segments.join("_"))
+}
+
+fn doc_comment(text: Option<&str>, indent: &str) -> String {
+    text.map(|text| {
+        text.lines()
+            .map(|line| match line.trim() {
+                "" => format!("{indent}///\n"),
+                line => format!("{indent}/// {line}\n"),
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn ident(name: &str) -> String {
+    if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if previous_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            previous_lower = false;
+        } else if c.is_ascii_alphanumeric() {
+            out.push(c);
+            previous_lower = true;
+        } else {
+            if !out.ends_with('_') && !out.is_empty() {
+                out.push('_');
+            }
+            previous_lower = false;
+        }
+    }
+    out.trim_end_matches('_').to_string()
+}
+
+pub(crate) fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, ParameterBuilder, PathItem};
+    use utoipa::openapi::request_body::RequestBodyBuilder;
+    use utoipa::openapi::{ComponentsBuilder, ContentBuilder, ResponseBuilder};
+    use utoipa::{PartialSchema, ToSchema};
+
+    #[derive(ToSchema)]
+    #[allow(dead_code)]
+    struct Project {
+        id: uuid::Uuid,
+        name: String,
+        #[serde(rename = "ownerName")]
+        owner_name: Option<String>,
+    }
+
+    fn spec() -> OpenApi {
+        let operation = OperationBuilder::new()
+            .operation_id(Some("updateProject"))
+            .summary(Some("Update a project"))
+            .parameter(
+                ParameterBuilder::new()
+                    .name("id")
+                    .parameter_in(ParameterIn::Path)
+                    .required(Required::True)
+                    .schema(Some(uuid::Uuid::schema())),
+            )
+            .parameter(
+                ParameterBuilder::new()
+                    .name("dryRun")
+                    .parameter_in(ParameterIn::Query)
+                    .required(Required::False)
+                    .schema(Some(bool::schema())),
+            )
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .content(
+                        "application/json",
+                        ContentBuilder::new()
+                            .schema(Some(Ref::from_schema_name("Project")))
+                            .build(),
+                    )
+                    .build(),
+            ))
+            .response(
+                "200",
+                ResponseBuilder::new()
+                    .content(
+                        "application/json",
+                        ContentBuilder::new()
+                            .schema(Some(Ref::from_schema_name("Project")))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/projects/{id}".to_string(), PathItem::new(HttpMethod::Put, operation));
+        openapi.components = Some(
+            ComponentsBuilder::new()
+                .schema("Project", Project::schema())
+                .build(),
+        );
+        openapi
+    }
+
+    #[test]
+    fn test_generates_model_and_method() {
+        let source = ClientGenerator::new(&spec())
+            .client_name("ProjectsClient")
+            .generate();
+
+        assert!(source.contains("pub struct Project {"));
+        assert!(source.contains("pub id: uuid::Uuid,"));
+        assert!(source.contains("#[serde(rename = \"ownerName\")]"));
+        assert!(source.contains("pub owner_name: Option<String>,"));
+        assert!(source.contains("pub struct ProjectsClient {"));
+        assert!(source.contains(
+            "pub async fn update_project(&self, id: impl std::fmt::Display, \
+             dry_run: Option<bool>, body: &Project) -> Result<Project, ClientError>"
+        ));
+        assert!(source.contains("format!(\"{}/projects/{}\", self.base_url, id)"));
+        assert!(source.contains("query.push((\"dryRun\", value.to_string()));"));
+    }
+
+    #[test]
+    fn test_naming() {
+        assert_eq!(snake_case("listProjectTasks"), "list_project_tasks");
+        assert_eq!(snake_case("project-id"), "project_id");
+        assert_eq!(pascal_case("in_progress"), "InProgress");
+        assert_eq!(ident("type"), "r#type");
+        assert_eq!(fallback_name("get", "/projects/{id}"), "get_projects_id");
+    }
+}
@@ -0,0 +1,126 @@
+//! Database connection modes for fast tests.
+//!
+//! Besides Postgres, a connection can be backed by an in-memory SQLite
+//! database (`sqlite` feature) or a sea_orm `MockDatabase` (`mock-db` feature),
+//! selected from config, so controller tests can run without Docker.
+
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// URL of an in-memory SQLite database.
+pub const SQLITE_MEMORY_URL: &str = "sqlite::memory:";
+
+/// Backend used for the database connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseMode {
+    #[default]
+    Postgres,
+    /// SQLite, typically in memory (requires the `sqlite` feature)
+    Sqlite,
+    /// sea_orm `MockDatabase` with Postgres semantics (requires the `mock-db` feature)
+    Mock,
+}
+
+impl DatabaseMode {
+    /// Infer the mode from a connection URL.
+    pub fn from_url(url: &str) -> Self {
+        if url.starts_with("sqlite:") {
+            DatabaseMode::Sqlite
+        } else if url.starts_with("mock:") {
+            DatabaseMode::Mock
+        } else {
+            DatabaseMode::Postgres
+        }
+    }
+}
+
+/// Database connection settings, embeddable in a service's `EywaConfig`.
+///
+/// ```toml
+/// [database]
+/// url = "sqlite::memory:"   # or "postgres://...", or "mock://"
+/// mode = "sqlite"           # optional, inferred from the URL
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseSettings {
+    pub url: String,
+    #[serde(default)]
+    pub mode: Option<DatabaseMode>,
+}
+
+impl DatabaseSettings {
+    /// Settings for an in-memory SQLite database.
+    pub fn in_memory() -> Self {
+        Self {
+            url: SQLITE_MEMORY_URL.to_string(),
+            mode: Some(DatabaseMode::Sqlite),
+        }
+    }
+
+    /// Settings for a sea_orm `MockDatabase`.
+    pub fn mock() -> Self {
+        Self {
+            url: "mock://".to_string(),
+            mode: Some(DatabaseMode::Mock),
+        }
+    }
+
+    /// The effective mode (explicit or inferred from the URL).
+    pub fn mode(&self) -> DatabaseMode {
+        self.mode.unwrap_or_else(|| DatabaseMode::from_url(&self.url))
+    }
+
+    /// Open a connection in the configured mode.
+    ///
+    /// A mock connection has no queued results; tests needing canned query
+    /// results should build their own `MockDatabase` instead.
+    pub async fn connect(&self) -> Result<DatabaseConnection> {
+        match self.mode() {
+            DatabaseMode::Postgres => connect_url(&self.url).await,
+            #[cfg(feature = "sqlite")]
+            DatabaseMode::Sqlite => connect_url(&self.url).await,
+            #[cfg(not(feature = "sqlite"))]
+            DatabaseMode::Sqlite => Err(AppError::InternalServerError(
+                "SQLite database mode requires the `sqlite` feature".to_string(),
+            )),
+            #[cfg(feature = "mock-db")]
+            DatabaseMode::Mock => {
+                Ok(sea_orm::MockDatabase::new(sea_orm::DbBackend::Postgres).into_connection())
+            }
+            #[cfg(not(feature = "mock-db"))]
+            DatabaseMode::Mock => Err(AppError::InternalServerError(
+                "Mock database mode requires the `mock-db` feature".to_string(),
+            )),
+        }
+    }
+}
+
+async fn connect_url(url: &str) -> Result<DatabaseConnection> {
+    sea_orm::Database::connect(url)
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Database connection failed: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_url() {
+        assert_eq!(DatabaseMode::from_url("postgres://localhost/db"), DatabaseMode::Postgres);
+        assert_eq!(DatabaseMode::from_url(SQLITE_MEMORY_URL), DatabaseMode::Sqlite);
+        assert_eq!(DatabaseMode::from_url("mock://"), DatabaseMode::Mock);
+    }
+
+    #[test]
+    fn test_explicit_mode_overrides_url() {
+        let settings: DatabaseSettings =
+            serde_json::from_str(r#"{"url":"postgres://localhost/db","mode":"mock"}"#).unwrap();
+        assert_eq!(settings.mode(), DatabaseMode::Mock);
+    }
+}
@@ -0,0 +1,615 @@
+//! Dead-lettered messages and their redelivery.
+//!
+//! Messages a consumer gives up on (poison payloads, exhausted retries) are
+//! recorded in the `eywa_dead_letters` table instead of vanishing into a
+//! broker-specific DLQ. Operators list, inspect and re-drive them through
+//! admin endpoints, and the `eywa_dead_letters{consumer}` gauge reports the
+//! depth of each consumer's dead letters for alerting.
+//!
+//! ```ignore
+//! let dead_letters = DeadLetterStore::new(db.clone());
+//! dead_letters.create_table().await?;
+//! dead_letters.spawn_depth_metrics(Duration::from_secs(60));
+//!
+//! // In the consumer loop, once retries are exhausted
+//! dead_letters
+//!     .record(
+//!         NewDeadLetter::new("billing", &message.topic, &message.payload, e.to_string())
+//!             .key(&message.key)
+//!             .attempts(attempts),
+//!     )
+//!     .await?;
+//! ```
+//!
+//! - `DeadLetterStore` - Record, list, inspect and discard dead letters
+//! - `Redriver` - Republishes a dead letter to its topic
+//! - `DeadLetterController` - Admin endpoints, mounted with
+//!   `EywaApp::dead_letter_endpoints` and requiring the `dead_letters:admin` scope:
+//!   - `GET /dead-letters?consumer=...&topic=...` - List dead letters, oldest first
+//!   - `GET /dead-letters/{id}` - Inspect a dead letter
+//!   - `POST /dead-letters/{id}/redrive` - Republish a dead letter and remove it
+//!   - `DELETE /dead-letters/{id}` - Discard a dead letter
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Schema, Set,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// Scope required to call the dead letter admin endpoints.
+pub const DEAD_LETTERS_ADMIN_SCOPE: &str = "dead_letters:admin";
+
+/// Default number of dead letters returned by a listing.
+const DEFAULT_LIMIT: u64 = 100;
+
+/// sea_orm entity of the `eywa_dead_letters` table.
+pub mod entity {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "eywa_dead_letters")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        #[sea_orm(indexed)]
+        pub consumer: String,
+        pub topic: String,
+        pub message_key: Option<String>,
+        #[sea_orm(column_type = "Text")]
+        pub payload: String,
+        /// Message headers, as a JSON object of strings
+        pub headers: Json,
+        #[sea_orm(column_type = "Text")]
+        pub error: String,
+        pub attempts: i32,
+        pub failed_at: DateTimeUtc,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// A message a consumer gave up on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    /// Consumer that failed to process the message
+    pub consumer: String,
+    /// Topic (or subject) the message was consumed from
+    pub topic: String,
+    pub key: Option<String>,
+    /// Message payload; binary payloads are base64 encoded by the consumer
+    pub payload: String,
+    pub headers: BTreeMap<String, String>,
+    /// Error of the last attempt
+    pub error: String,
+    /// Delivery attempts before giving up
+    pub attempts: u32,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl From<entity::Model> for DeadLetter {
+    fn from(model: entity::Model) -> Self {
+        Self {
+            id: model.id,
+            consumer: model.consumer,
+            topic: model.topic,
+            key: model.message_key,
+            payload: model.payload,
+            headers: serde_json::from_value(model.headers).unwrap_or_default(),
+            error: model.error,
+            attempts: u32::try_from(model.attempts).unwrap_or_default(),
+            failed_at: model.failed_at,
+        }
+    }
+}
+
+/// A dead letter to record.
+#[derive(Debug, Clone)]
+pub struct NewDeadLetter {
+    consumer: String,
+    topic: String,
+    key: Option<String>,
+    payload: String,
+    headers: BTreeMap<String, String>,
+    error: String,
+    attempts: u32,
+}
+
+impl NewDeadLetter {
+    /// A message of `topic` that `consumer` failed to process with `error`.
+    pub fn new(
+        consumer: impl Into<String>,
+        topic: impl Into<String>,
+        payload: impl Into<String>,
+        error: impl Into<String>,
+    ) -> Self {
+        Self {
+            consumer: consumer.into(),
+            topic: topic.into(),
+            key: None,
+            payload: payload.into(),
+            headers: BTreeMap::new(),
+            error: error.into(),
+            attempts: 1,
+        }
+    }
+
+    /// Set the message key (partition key, NATS subject token...).
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Add a message header, kept for redelivery.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the number of delivery attempts (1 by default).
+    pub fn attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+}
+
+/// Dead letter listing filter
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct DeadLetterFilter {
+    /// Only dead letters of this consumer
+    pub consumer: Option<String>,
+    /// Only dead letters of this topic
+    pub topic: Option<String>,
+    /// Maximum number of dead letters returned (100 by default)
+    pub limit: Option<u64>,
+}
+
+/// Republishes dead letters to their topic.
+///
+/// Closures taking a `DeadLetter` implement it:
+///
+/// ```ignore
+/// let producer = state.producer.clone();
+/// let redriver = move |message: DeadLetter| {
+///     let producer = producer.clone();
+///     async move { producer.send(&message.topic, message.key, message.payload).await }
+/// };
+/// ```
+#[async_trait]
+pub trait Redriver: Send + Sync + 'static {
+    async fn redrive(&self, message: &DeadLetter) -> Result<()>;
+}
+
+#[async_trait]
+impl<F, Fut> Redriver for F
+where
+    F: Fn(DeadLetter) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    async fn redrive(&self, message: &DeadLetter) -> Result<()> {
+        self(message.clone()).await
+    }
+}
+
+fn store_error(e: DbErr) -> AppError {
+    AppError::InternalServerError(format!("Dead letter store failed: {e}"))
+}
+
+fn not_found(id: Uuid) -> AppError {
+    AppError::BadRequest(format!("Dead letter {id} not found"))
+}
+
+/// Dead letters persisted in the `eywa_dead_letters` table.
+#[derive(Debug, Clone)]
+pub struct DeadLetterStore {
+    db: DatabaseConnection,
+    /// Consumers with a reported depth, reset to 0 once they have no dead letters
+    consumers: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl DeadLetterStore {
+    /// Create a store on the given connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            consumers: Arc::default(),
+        }
+    }
+
+    /// Create the `eywa_dead_letters` table and its consumer index, if missing.
+    ///
+    /// Services with their own migrations can create the table from
+    /// `entity::Entity` instead.
+    pub async fn create_table(&self) -> Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+
+        let mut table = schema.create_table_from_entity(entity::Entity);
+        table.if_not_exists();
+        self.db.execute(backend.build(&table)).await.map_err(store_error)?;
+        for mut index in schema.create_index_from_entity(entity::Entity) {
+            index.if_not_exists();
+            self.db.execute(backend.build(&index)).await.map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    /// Record a dead letter.
+    pub async fn record(&self, message: NewDeadLetter) -> Result<DeadLetter> {
+        let headers = serde_json::to_value(&message.headers)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        let model = entity::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            consumer: Set(message.consumer),
+            topic: Set(message.topic),
+            message_key: Set(message.key),
+            payload: Set(message.payload),
+            headers: Set(headers),
+            error: Set(message.error),
+            attempts: Set(i32::try_from(message.attempts).unwrap_or(i32::MAX)),
+            failed_at: Set(Utc::now()),
+        }
+        .insert(&self.db)
+        .await
+        .map_err(store_error)?;
+
+        tracing::warn!(
+            consumer = %model.consumer,
+            topic = %model.topic,
+            id = %model.id,
+            error = %model.error,
+            "☠️ message dead-lettered"
+        );
+        self.report_depth().await?;
+        Ok(model.into())
+    }
+
+    /// List dead letters, oldest first.
+    pub async fn list(&self, filter: &DeadLetterFilter) -> Result<Vec<DeadLetter>> {
+        let mut query = entity::Entity::find().order_by_asc(entity::Column::FailedAt);
+        if let Some(consumer) = &filter.consumer {
+            query = query.filter(entity::Column::Consumer.eq(consumer));
+        }
+        if let Some(topic) = &filter.topic {
+            query = query.filter(entity::Column::Topic.eq(topic));
+        }
+        let models = query
+            .limit(filter.limit.unwrap_or(DEFAULT_LIMIT))
+            .all(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(models.into_iter().map(DeadLetter::from).collect())
+    }
+
+    /// Get a dead letter.
+    pub async fn get(&self, id: Uuid) -> Result<DeadLetter> {
+        entity::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(store_error)?
+            .map(DeadLetter::from)
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// Republish a dead letter with `redriver`, then remove it.
+    ///
+    /// The dead letter is kept if republishing fails.
+    pub async fn redrive(&self, id: Uuid, redriver: &dyn Redriver) -> Result<DeadLetter> {
+        let message = self.get(id).await?;
+        redriver.redrive(&message).await?;
+        self.discard(id).await?;
+
+        tracing::info!(
+            consumer = %message.consumer,
+            topic = %message.topic,
+            %id,
+            "dead letter re-driven"
+        );
+        Ok(message)
+    }
+
+    /// Remove a dead letter.
+    pub async fn discard(&self, id: Uuid) -> Result<()> {
+        let result = entity::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await
+            .map_err(store_error)?;
+        if result.rows_affected == 0 {
+            return Err(not_found(id));
+        }
+        self.report_depth().await
+    }
+
+    /// Number of dead letters per consumer.
+    pub async fn depth(&self) -> Result<BTreeMap<String, u64>> {
+        let counts: Vec<(String, i64)> = entity::Entity::find()
+            .select_only()
+            .column(entity::Column::Consumer)
+            .column_as(entity::Column::Id.count(), "count")
+            .group_by(entity::Column::Consumer)
+            .into_tuple()
+            .all(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(counts
+            .into_iter()
+            .map(|(consumer, count)| (consumer, count.max(0) as u64))
+            .collect())
+    }
+
+    /// Set the `eywa_dead_letters{consumer}` gauge from the table.
+    pub async fn report_depth(&self) -> Result<()> {
+        let depth = self.depth().await?;
+        let mut consumers = self.consumers.lock().unwrap();
+        consumers.extend(depth.keys().cloned());
+        for consumer in consumers.iter() {
+            let count = depth.get(consumer).copied().unwrap_or(0);
+            metrics::gauge!("eywa_dead_letters", "consumer" => consumer.clone()).set(count as f64);
+        }
+        Ok(())
+    }
+
+    /// Report the depth every `interval` in the background.
+    ///
+    /// Keeps the gauge right when other instances record or re-drive messages.
+    pub fn spawn_depth_metrics(&self, interval: Duration) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                if let Err(e) = store.report_depth().await {
+                    tracing::warn!(error = ?e, "dead letter depth report failed");
+                }
+            }
+        });
+    }
+}
+
+/// State of the admin endpoints.
+pub struct DeadLetterAdmin {
+    store: DeadLetterStore,
+    redriver: Box<dyn Redriver>,
+}
+
+/// List dead letters
+#[utoipa::path(
+    get,
+    path = "/dead-letters",
+    tag = "Dead Letters",
+    params(DeadLetterFilter),
+    responses(
+        (status = 200, description = "Dead letters, oldest first", body = Vec<DeadLetter>)
+    )
+)]
+pub async fn list(
+    State(admin): State<Arc<DeadLetterAdmin>>,
+    Query(filter): Query<DeadLetterFilter>,
+) -> Result<Json<Vec<DeadLetter>>> {
+    Ok(Json(admin.store.list(&filter).await?))
+}
+
+/// Get a dead letter
+#[utoipa::path(
+    get,
+    path = "/dead-letters/{id}",
+    tag = "Dead Letters",
+    params(("id" = Uuid, Path, description = "Dead letter ID")),
+    responses(
+        (status = 200, description = "Dead letter", body = DeadLetter)
+    )
+)]
+pub async fn inspect(
+    State(admin): State<Arc<DeadLetterAdmin>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DeadLetter>> {
+    Ok(Json(admin.store.get(id).await?))
+}
+
+/// Re-drive a dead letter
+///
+/// Republishes the message to its topic and removes it; it's kept if
+/// republishing fails.
+#[utoipa::path(
+    post,
+    path = "/dead-letters/{id}/redrive",
+    tag = "Dead Letters",
+    params(("id" = Uuid, Path, description = "Dead letter ID")),
+    responses(
+        (status = 200, description = "Message republished", body = DeadLetter)
+    )
+)]
+pub async fn redrive(
+    State(admin): State<Arc<DeadLetterAdmin>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DeadLetter>> {
+    Ok(Json(admin.store.redrive(id, admin.redriver.as_ref()).await?))
+}
+
+/// Discard a dead letter
+#[utoipa::path(
+    delete,
+    path = "/dead-letters/{id}",
+    tag = "Dead Letters",
+    params(("id" = Uuid, Path, description = "Dead letter ID")),
+    responses(
+        (status = 204, description = "Dead letter discarded")
+    )
+)]
+pub async fn discard(
+    State(admin): State<Arc<DeadLetterAdmin>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    admin.store.discard(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub struct DeadLetterController;
+
+impl DeadLetterController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 4] = [
+        ("GET", "/dead-letters"),
+        ("GET", "/dead-letters/{id}"),
+        ("POST", "/dead-letters/{id}/redrive"),
+        ("DELETE", "/dead-letters/{id}"),
+    ];
+
+    /// Build the dead letter admin router for the given store.
+    pub fn router<S>(store: DeadLetterStore, redriver: impl Redriver) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let admin = DeadLetterAdmin {
+            store,
+            redriver: Box::new(redriver),
+        };
+        Router::new()
+            .route("/dead-letters", get(list))
+            .route("/dead-letters/{id}", get(inspect).delete(discard))
+            .route("/dead-letters/{id}/redrive", post(redrive))
+            .with_state(Arc::new(admin))
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_list as Path>::path(),
+                <__path_list as Path>::methods(),
+                <__path_list as Path>::operation(),
+            ),
+            (
+                <__path_inspect as Path>::path(),
+                <__path_inspect as Path>::methods(),
+                <__path_inspect as Path>::operation(),
+            ),
+            (
+                <__path_redrive as Path>::path(),
+                <__path_redrive as Path>::methods(),
+                <__path_redrive as Path>::operation(),
+            ),
+            (
+                <__path_discard as Path>::path(),
+                <__path_discard as Path>::methods(),
+                <__path_discard as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components
+            .schemas
+            .insert("DeadLetter".to_string(), DeadLetter::schema());
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    async fn store() -> DeadLetterStore {
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        let store = DeadLetterStore::new(db);
+        store.create_table().await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn test_record_list_and_depth() {
+        let store = store().await;
+        let recorded = store
+            .record(
+                NewDeadLetter::new("billing", "invoices", r#"{"id":1}"#, "invalid amount")
+                    .key("invoice-1")
+                    .header("traceparent", "00-abc-def-01")
+                    .attempts(5),
+            )
+            .await
+            .unwrap();
+        store
+            .record(NewDeadLetter::new("notifications", "emails", "{}", "bounced"))
+            .await
+            .unwrap();
+
+        assert_eq!(store.get(recorded.id).await.unwrap(), recorded);
+        assert_eq!(recorded.attempts, 5);
+        assert_eq!(recorded.headers["traceparent"], "00-abc-def-01");
+
+        let filter = DeadLetterFilter {
+            consumer: Some("billing".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.list(&filter).await.unwrap(), vec![recorded.clone()]);
+        assert_eq!(store.list(&DeadLetterFilter::default()).await.unwrap().len(), 2);
+
+        store.discard(recorded.id).await.unwrap();
+        let depth = store.depth().await.unwrap();
+        assert_eq!(depth.get("billing"), None);
+        assert_eq!(depth["notifications"], 1);
+        assert!(store.discard(recorded.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redrive_endpoint() {
+        let store = store().await;
+        let message = store
+            .record(NewDeadLetter::new("billing", "invoices", "{}", "timeout"))
+            .await
+            .unwrap();
+        let poison = store
+            .record(NewDeadLetter::new("billing", "invoices", "poison", "invalid json"))
+            .await
+            .unwrap();
+
+        let redriver = |message: DeadLetter| async move {
+            if message.payload == "poison" {
+                return Err(AppError::InternalServerError("broker unavailable".to_string()));
+            }
+            Ok(())
+        };
+        let client = TestClient::new(DeadLetterController::router(store.clone(), redriver));
+
+        let response = client.post(format!("/dead-letters/{}/redrive", message.id)).send().await;
+        response.assert_status(StatusCode::OK);
+        client
+            .get(format!("/dead-letters/{}", message.id))
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let response = client.post(format!("/dead-letters/{}/redrive", poison.id)).send().await;
+        response.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        let remaining: Vec<DeadLetter> = client.get("/dead-letters").send().await.json();
+        assert_eq!(remaining, vec![poison]);
+    }
+}
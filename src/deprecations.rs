@@ -0,0 +1,389 @@
+//! Usage analytics of deprecated operations.
+//!
+//! An endpoint can only be removed once nobody calls it anymore. With
+//! `EywaApp::deprecation_analytics`, calls to the operations marked
+//! `deprecated` in the spec (`#[deprecated]` on the handler, or `deprecated`
+//! in `#[utoipa::path]`) are counted per client:
+//!
+//! - `eywa_deprecated_calls_total{method, route, client}` - For dashboards
+//!   across replicas
+//! - `GET /admin/deprecations` - This replica's report since startup, with the
+//!   last call of each client; requires a token verified by `EywaApp::auth`
+//!   with the `deprecations:admin` scope
+//!
+//! The client of a request is its `X-Client-ID` header (see
+//! `Deprecations::client_header`), then its API key prefix, then its tenant.
+//! Operations with no calls are listed too: those are safe to remove.
+//!
+//! ```ignore
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .deprecation_analytics(Deprecations::new())
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::{Deprecated, OpenApi};
+use utoipa::{PartialSchema, ToSchema};
+
+#[cfg(feature = "api-keys")]
+use crate::middleware::rate_limit::API_KEY_HEADER;
+use crate::middleware::rate_limit::TENANT_HEADER;
+
+/// Scope required to call the deprecation report endpoint.
+pub const DEPRECATIONS_ADMIN_SCOPE: &str = "deprecations:admin";
+
+/// Header identifying the calling client.
+pub const CLIENT_ID_HEADER: &str = "x-client-id";
+
+/// Client label of requests without a client identifier.
+pub const UNKNOWN_CLIENT: &str = "unknown";
+
+/// Client label of clients past `max_clients`.
+pub const OTHER_CLIENTS: &str = "other";
+
+type RouteKey = (String, String);
+
+#[derive(Debug, Default)]
+struct Usage {
+    clients: BTreeMap<String, ClientCalls>,
+}
+
+#[derive(Debug)]
+struct Registry {
+    since: DateTime<Utc>,
+    operations: BTreeMap<RouteKey, Usage>,
+}
+
+/// Calls of deprecated operations, per client.
+///
+/// Clones share the counts.
+#[derive(Debug, Clone)]
+pub struct Deprecations {
+    client_header: String,
+    max_clients: usize,
+    registry: Arc<RwLock<Registry>>,
+}
+
+impl Default for Deprecations {
+    fn default() -> Self {
+        Self {
+            client_header: CLIENT_ID_HEADER.to_string(),
+            max_clients: 100,
+            registry: Arc::new(RwLock::new(Registry {
+                since: Utc::now(),
+                operations: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl Deprecations {
+    /// Identify clients by `X-Client-ID`, up to 100 per operation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Identify clients by another header (e.g. `x-app-name`).
+    pub fn client_header(mut self, header: impl Into<String>) -> Self {
+        self.client_header = header.into().to_lowercase();
+        self
+    }
+
+    /// Count at most `max` distinct clients per operation; later clients
+    /// are counted as `other`, keeping the metric's cardinality bounded.
+    pub fn max_clients(mut self, max: usize) -> Self {
+        self.max_clients = max;
+        self
+    }
+
+    /// Track the deprecated operations of a spec.
+    pub fn track(&self, openapi: &OpenApi) {
+        let mut registry = self.registry.write().unwrap();
+        for (path, item) in &openapi.paths.paths {
+            let operations = [
+                ("GET", &item.get),
+                ("PUT", &item.put),
+                ("POST", &item.post),
+                ("DELETE", &item.delete),
+                ("PATCH", &item.patch),
+                ("HEAD", &item.head),
+                ("OPTIONS", &item.options),
+                ("TRACE", &item.trace),
+            ];
+            for (method, operation) in operations {
+                let deprecated = operation
+                    .as_ref()
+                    .is_some_and(|op| matches!(op.deprecated, Some(Deprecated::True)));
+                if deprecated {
+                    registry
+                        .operations
+                        .entry((method.to_string(), path.clone()))
+                        .or_default();
+                }
+            }
+        }
+    }
+
+    /// Whether any operation is tracked.
+    pub fn is_empty(&self) -> bool {
+        self.registry.read().unwrap().operations.is_empty()
+    }
+
+    fn is_tracked(&self, method: &str, route: &str) -> bool {
+        let key = (method.to_string(), route.to_string());
+        self.registry.read().unwrap().operations.contains_key(&key)
+    }
+
+    /// The client of a request.
+    fn client(&self, req: &Request) -> String {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        if let Some(client) = header(&self.client_header) {
+            return client.to_string();
+        }
+        #[cfg(feature = "api-keys")]
+        if let Some(prefix) = header(API_KEY_HEADER).and_then(crate::api_keys::parse_prefix) {
+            return format!("api_key:{prefix}");
+        }
+        match header(TENANT_HEADER) {
+            Some(tenant) => format!("tenant:{tenant}"),
+            None => UNKNOWN_CLIENT.to_string(),
+        }
+    }
+
+    /// Count a call, returning the client label it was counted under.
+    fn record(&self, method: &str, route: &str, client: String, now: DateTime<Utc>) -> String {
+        let mut registry = self.registry.write().unwrap();
+        let Some(usage) = registry
+            .operations
+            .get_mut(&(method.to_string(), route.to_string()))
+        else {
+            return client;
+        };
+        let known = usage.clients.contains_key(&client);
+        let client = if known || usage.clients.len() < self.max_clients {
+            client
+        } else {
+            OTHER_CLIENTS.to_string()
+        };
+        let calls = usage.clients.entry(client.clone()).or_insert_with(|| ClientCalls {
+            client: client.clone(),
+            calls: 0,
+            last_called: now,
+        });
+        calls.calls += 1;
+        calls.last_called = now;
+        client
+    }
+
+    /// Usage of every tracked operation since startup.
+    pub fn report(&self) -> DeprecationReport {
+        let registry = self.registry.read().unwrap();
+        let operations = registry
+            .operations
+            .iter()
+            .map(|((method, path), usage)| {
+                let mut clients: Vec<ClientCalls> = usage.clients.values().cloned().collect();
+                clients.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.client.cmp(&b.client)));
+                DeprecatedOperation {
+                    method: method.clone(),
+                    path: path.clone(),
+                    calls: clients.iter().map(|c| c.calls).sum(),
+                    last_called: clients.iter().map(|c| c.last_called).max(),
+                    clients,
+                }
+            })
+            .collect();
+        DeprecationReport {
+            since: registry.since,
+            operations,
+        }
+    }
+}
+
+/// Calls of a deprecated operation by one client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ClientCalls {
+    /// Client identifier, `api_key:{prefix}`, `tenant:{id}` or `unknown`
+    pub client: String,
+    pub calls: u64,
+    pub last_called: DateTime<Utc>,
+}
+
+/// Usage of a deprecated operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeprecatedOperation {
+    pub method: String,
+    /// Route template, e.g. `/api/v1/projects/{id}`
+    pub path: String,
+    /// Calls from every client
+    pub calls: u64,
+    /// Last call from any client, `null` if never called
+    pub last_called: Option<DateTime<Utc>>,
+    /// Clients, most calls first
+    pub clients: Vec<ClientCalls>,
+}
+
+/// Usage of the deprecated operations on this replica.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeprecationReport {
+    /// Start of the counts (process startup)
+    pub since: DateTime<Utc>,
+    pub operations: Vec<DeprecatedOperation>,
+}
+
+/// Middleware counting the calls of deprecated operations.
+///
+/// Installed by `EywaApp::deprecation_analytics()`.
+pub async fn deprecation_middleware(
+    State(deprecations): State<Deprecations>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>() else {
+        return next.run(req).await;
+    };
+    let method = req.method().as_str();
+    if !deprecations.is_tracked(method, route.as_str()) {
+        return next.run(req).await;
+    }
+
+    let client = deprecations.client(&req);
+    let client = deprecations.record(method, route.as_str(), client, Utc::now());
+    metrics::counter!(
+        "eywa_deprecated_calls_total",
+        "method" => method.to_string(),
+        "route" => route.as_str().to_string(),
+        "client" => client
+    )
+    .increment(1);
+    next.run(req).await
+}
+
+/// Report deprecated operation usage
+///
+/// Calls of each deprecated operation on this replica since startup, per
+/// client. Operations without calls are safe to remove.
+#[utoipa::path(
+    get,
+    path = "/admin/deprecations",
+    tag = "Deprecations",
+    responses(
+        (status = 200, description = "Deprecated operation usage", body = DeprecationReport)
+    )
+)]
+pub async fn report(State(deprecations): State<Deprecations>) -> Json<DeprecationReport> {
+    Json(deprecations.report())
+}
+
+pub struct DeprecationController;
+
+impl DeprecationController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 1] = [("GET", "/admin/deprecations")];
+
+    /// Build the deprecation report router.
+    pub fn router<S>(deprecations: Deprecations) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/admin/deprecations", get(report))
+            .with_state(deprecations)
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut OpenApi) {
+        use utoipa::Path;
+
+        openapi.paths.add_path_operation(
+            <__path_report as Path>::path(),
+            <__path_report as Path>::methods(),
+            <__path_report as Path>::operation(),
+        );
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        for (name, schema) in [
+            ("ClientCalls", ClientCalls::schema()),
+            ("DeprecatedOperation", DeprecatedOperation::schema()),
+            ("DeprecationReport", DeprecationReport::schema()),
+        ] {
+            components.schemas.insert(name.to_string(), schema);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn deprecations() -> Deprecations {
+        let mut openapi = OpenApi::default();
+        let deprecated = OperationBuilder::new().deprecated(Some(Deprecated::True)).build();
+        let mut item = PathItem::new(HttpMethod::Get, deprecated);
+        item.post = Some(OperationBuilder::new().build());
+        openapi.paths.paths.insert("/v1/projects/{id}".to_string(), item);
+
+        let deprecations = Deprecations::new().max_clients(2);
+        deprecations.track(&openapi);
+        deprecations
+    }
+
+    #[tokio::test]
+    async fn test_counts_deprecated_calls_per_client() {
+        let deprecations = deprecations();
+        let client = TestClient::new(
+            Router::new()
+                .route("/v1/projects/{id}", get(|| async { "old" }).post(|| async { "new" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    deprecations.clone(),
+                    deprecation_middleware,
+                ))
+                .merge(DeprecationController::router(deprecations.clone())),
+        );
+
+        for id in ["billing", "billing", "mobile", "web"] {
+            client.get("/v1/projects/1").header("x-client-id", id).send().await;
+        }
+        client.get("/v1/projects/2").send().await;
+        client.post("/v1/projects/1").header("x-client-id", "billing").send().await;
+
+        let report: DeprecationReport = client.get("/admin/deprecations").send().await.json();
+        assert_eq!(report.operations.len(), 1);
+        let operation = &report.operations[0];
+        assert_eq!((operation.method.as_str(), operation.calls), ("GET", 5));
+        let clients: Vec<(&str, u64)> = operation
+            .clients
+            .iter()
+            .map(|c| (c.client.as_str(), c.calls))
+            .collect();
+        assert_eq!(clients, [("billing", 2), ("other", 2), ("mobile", 1)]);
+    }
+
+    #[test]
+    fn test_unused_operations_reported() {
+        let report = deprecations().report();
+        assert_eq!(report.operations[0].calls, 0);
+        assert_eq!(report.operations[0].last_called, None);
+    }
+}
@@ -0,0 +1,291 @@
+//! Typed dependency injection.
+//!
+//! Services are registered by type with `EywaApp::provide` and extracted in
+//! handlers with `Inject<T>`, so handlers depend on the interfaces they use
+//! (`Arc<dyn EmailSender>`) instead of on the whole `AppState`:
+//!
+//! ```ignore
+//! EywaApp::new(state)
+//!     .provide::<Arc<dyn EmailSender>>(Arc::new(SmtpSender::new(&config)))
+//!     .mount::<InvitesController>()
+//!
+//! #[route(POST "/invites")]
+//! async fn invite(Inject(email): Inject<Arc<dyn EmailSender>>, ...) -> Result<...>
+//! ```
+//!
+//! The `#[route]` macro records the `Inject<T>` types used by each handler
+//! (see `RouteDependencies`), and the app refuses to start if one of them
+//! wasn't provided.
+//!
+//! The resources built by `AppStateBuilder` are provided in one call with
+//! `EywaApp::provide_resources`, so handlers inject `DatabaseConnection`,
+//! `JwtService` or `reqwest::Client` without a `FromRef` impl per field:
+//!
+//! ```ignore
+//! let resources = AppStateBuilder::new(config.resources.clone()).build().await?;
+//! EywaApp::new(state)
+//!     .provide_resources(&resources)
+//!     .mount::<ProjectsController>()
+//!
+//! #[route(GET "/")]
+//! async fn list(Inject(db): Inject<DatabaseConnection>) -> Result<Json<Vec<Project>>>
+//! ```
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use eywa_errors::AppError;
+
+use crate::state::Resources;
+use crate::traits::RouteDependencies;
+
+/// Types that can be provided to the container and injected.
+///
+/// Implemented for every `Clone + Send + Sync + 'static` type; it only
+/// exists to explain the compile error when a type isn't one.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be injected",
+    label = "`{Self}` must be `Clone + Send + Sync + 'static`",
+    note = "wrap it in an `Arc` (`Arc<{Self}>`, or `Arc<dyn Trait>` for trait objects)"
+)]
+pub trait Injectable: Clone + Send + Sync + 'static {}
+
+impl<T: Clone + Send + Sync + 'static> Injectable for T {}
+
+/// A type injected into handlers.
+#[derive(Clone, Copy)]
+pub struct Dependency {
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+}
+
+impl Dependency {
+    /// The dependency on `T`.
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
+        }
+    }
+}
+
+impl fmt::Debug for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.type_name)
+    }
+}
+
+impl PartialEq for Dependency {
+    fn eq(&self, other: &Self) -> bool {
+        self.type_id == other.type_id
+    }
+}
+
+impl Eq for Dependency {}
+
+/// Services available to `Inject<T>`, keyed by type.
+#[derive(Clone, Default)]
+pub struct Container {
+    services: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Container {
+    /// Create an empty container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register (or replace) the service of type `T`.
+    pub fn provide<T: Injectable>(&mut self, service: T) {
+        self.services.insert(TypeId::of::<T>(), Arc::new(service));
+    }
+
+    /// Register the resources that were configured.
+    ///
+    /// Provides `reqwest::Client`, and `DatabaseConnection`, `JwtService`
+    /// and (with the `redis` feature) `redis::aio::ConnectionManager` when
+    /// they have settings.
+    pub fn provide_resources(&mut self, resources: &Resources) {
+        self.provide(resources.http.clone());
+        if let Some(database) = &resources.database {
+            self.provide(database.clone());
+        }
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &resources.redis {
+            self.provide(redis.clone());
+        }
+        if let Some(jwt) = &resources.jwt {
+            self.provide(jwt.clone());
+        }
+    }
+
+    /// Returns the service of type `T`, if provided.
+    pub fn get<T: Injectable>(&self) -> Option<T> {
+        self.services
+            .get(&TypeId::of::<T>())
+            .and_then(|service| service.downcast_ref::<T>())
+            .cloned()
+    }
+
+    /// Returns `true` if the dependency was provided.
+    pub fn contains(&self, dependency: &Dependency) -> bool {
+        self.services.contains_key(&dependency.type_id)
+    }
+
+    /// Returns `true` if no service was provided.
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+
+    /// Check that every dependency of the routes was provided.
+    ///
+    /// Returns one line per missing dependency (`POST /invites: Arc<dyn EmailSender>`).
+    pub fn verify(&self, routes: &[RouteDependencies]) -> Result<(), Vec<String>> {
+        let missing: Vec<String> = routes
+            .iter()
+            .flat_map(|route| {
+                route
+                    .dependencies
+                    .iter()
+                    .filter(|dependency| !self.contains(dependency))
+                    .map(move |dependency| {
+                        format!("{} {}: {}", route.method, route.path, dependency.type_name)
+                    })
+            })
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+}
+
+impl fmt::Debug for Container {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Container")
+            .field("services", &self.services.len())
+            .finish()
+    }
+}
+
+/// Extractor for a service registered with `EywaApp::provide`.
+///
+/// Fails with `500 Internal Server Error` if the service wasn't provided,
+/// which the startup check prevents for routes declared through `#[route]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inject<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Inject<T>
+where
+    T: Injectable,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Arc<Container>>()
+            .and_then(|container| container.get::<T>())
+            .map(Self)
+            .ok_or_else(|| {
+                tracing::error!("Injected service {} was not provided", type_name::<T>());
+                AppError::InternalServerError(format!("{} was not provided", type_name::<T>()))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Greeter: Send + Sync {
+        fn greet(&self) -> String;
+    }
+
+    struct English;
+
+    impl Greeter for English {
+        fn greet(&self) -> String {
+            "hello".to_string()
+        }
+    }
+
+    #[test]
+    fn test_provide_trait_object() {
+        let mut container = Container::new();
+        container.provide::<Arc<dyn Greeter>>(Arc::new(English));
+
+        let greeter = container.get::<Arc<dyn Greeter>>().unwrap();
+        assert_eq!(greeter.greet(), "hello");
+        assert!(container.get::<String>().is_none());
+    }
+
+    #[test]
+    fn test_verify_lists_missing_dependencies() {
+        let mut container = Container::new();
+        container.provide(42u32);
+        let routes = vec![RouteDependencies {
+            method: "POST".to_string(),
+            path: "/invites".to_string(),
+            dependencies: vec![Dependency::of::<u32>(), Dependency::of::<Arc<dyn Greeter>>()],
+        }];
+
+        let missing = container.verify(&routes).unwrap_err();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].starts_with("POST /invites: "));
+        assert!(missing[0].contains("Greeter"));
+    }
+
+    #[tokio::test]
+    async fn test_provide_configured_resources() {
+        let resources = crate::AppStateBuilder::default()
+            .without_metrics()
+            .build()
+            .await
+            .unwrap();
+        let mut container = Container::new();
+        container.provide_resources(&resources);
+
+        assert!(container.get::<reqwest::Client>().is_some());
+        assert!(container.get::<sea_orm::DatabaseConnection>().is_none());
+        assert!(container.get::<eywa_authentication::JwtService>().is_none());
+    }
+
+    fn greeting_router() -> axum::Router {
+        axum::Router::new().route(
+            "/greeting",
+            axum::routing::get(|Inject(greeter): Inject<Arc<dyn Greeter>>| async move {
+                greeter.greet()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_inject_extractor() {
+        let client = crate::EywaApp::new(())
+            .provide::<Arc<dyn Greeter>>(Arc::new(English))
+            .merge(greeting_router())
+            .inject::<Arc<dyn Greeter>>("GET", "/greeting")
+            .into_test_client();
+
+        assert_eq!(client.get("/greeting").send().await.text(), "hello");
+    }
+
+    #[test]
+    fn test_missing_service_fails_at_startup() {
+        let result = crate::EywaApp::new(())
+            .merge(greeting_router())
+            .inject::<Arc<dyn Greeter>>("GET", "/greeting")
+            .into_router();
+
+        let Err(AppError::InternalServerError(message)) = result else {
+            panic!("expected the build to fail");
+        };
+        assert!(message.contains("GET /greeting"));
+    }
+}
@@ -0,0 +1,301 @@
+//! Configurable success envelope.
+//!
+//! A `ResponseEnvelope` strategy wraps the JSON body of every successful
+//! response and adjusts the documented 2xx response schemas accordingly, so
+//! services can follow their business unit's mandated shape (e.g.
+//! `{ "data": ..., "meta": ... }`) without changing handlers.
+//!
+//! Enabled with `EywaApp::response_envelope`. Without it, bodies are bare.
+//! Error responses (`AppError`, validation problems) are never wrapped.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::{json, Value};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::{OpenApi, RefOr};
+
+/// Request metadata available to envelopes.
+#[derive(Debug, Clone, Default)]
+pub struct EnvelopeMeta {
+    /// HTTP status of the response
+    pub status: u16,
+    /// Correlation ID of the request, if `.request_context()` is enabled
+    /// (always `None` for documented examples)
+    pub correlation_id: Option<String>,
+}
+
+/// Strategy wrapping successful JSON bodies and their documented schemas.
+///
+/// # Example
+///
+/// ```ignore
+/// struct ResultEnvelope;
+///
+/// impl ResponseEnvelope for ResultEnvelope {
+///     fn wrap(&self, body: Value, _meta: &EnvelopeMeta) -> Value {
+///         json!({ "success": true, "result": body })
+///     }
+///
+///     fn wrap_schema(&self, schema: RefOr<Schema>) -> RefOr<Schema> {
+///         ObjectBuilder::new()
+///             .property("success", ObjectBuilder::new().schema_type(Type::Boolean))
+///             .property("result", schema)
+///             .required("success")
+///             .required("result")
+///             .into()
+///     }
+/// }
+/// ```
+pub trait ResponseEnvelope: Send + Sync + 'static {
+    /// Wrap a successful response body.
+    fn wrap(&self, body: Value, meta: &EnvelopeMeta) -> Value;
+
+    /// Wrap the documented schema of a successful response body.
+    fn wrap_schema(&self, schema: RefOr<Schema>) -> RefOr<Schema>;
+}
+
+/// `{ "data": <body>, "meta": { "correlation_id": ... } }` envelope.
+#[derive(Debug, Clone)]
+pub struct DataEnvelope {
+    data_key: String,
+    meta_key: Option<String>,
+}
+
+impl Default for DataEnvelope {
+    fn default() -> Self {
+        Self {
+            data_key: "data".to_string(),
+            meta_key: Some("meta".to_string()),
+        }
+    }
+}
+
+impl DataEnvelope {
+    /// Create the default `data`/`meta` envelope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use another key for the body (default `data`).
+    pub fn data_key(mut self, key: impl Into<String>) -> Self {
+        self.data_key = key.into();
+        self
+    }
+
+    /// Use another key for the metadata (default `meta`).
+    pub fn meta_key(mut self, key: impl Into<String>) -> Self {
+        self.meta_key = Some(key.into());
+        self
+    }
+
+    /// Omit the metadata object.
+    pub fn without_meta(mut self) -> Self {
+        self.meta_key = None;
+        self
+    }
+}
+
+impl ResponseEnvelope for DataEnvelope {
+    fn wrap(&self, body: Value, meta: &EnvelopeMeta) -> Value {
+        let mut envelope = serde_json::Map::new();
+        envelope.insert(self.data_key.clone(), body);
+        if let Some(meta_key) = &self.meta_key {
+            envelope.insert(
+                meta_key.clone(),
+                json!({ "correlation_id": meta.correlation_id }),
+            );
+        }
+        Value::Object(envelope)
+    }
+
+    fn wrap_schema(&self, schema: RefOr<Schema>) -> RefOr<Schema> {
+        let mut envelope = ObjectBuilder::new()
+            .property(self.data_key.clone(), schema)
+            .required(self.data_key.clone());
+        if let Some(meta_key) = &self.meta_key {
+            envelope = envelope.property(
+                meta_key.clone(),
+                ObjectBuilder::new().property(
+                    "correlation_id",
+                    ObjectBuilder::new().schema_type(Type::String),
+                ),
+            );
+        }
+        envelope.into()
+    }
+}
+
+/// Returns `true` for `application/json` bodies (not problem details).
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+/// Axum middleware wrapping successful JSON bodies with the envelope.
+///
+/// The correlation ID is read from the `x-correlation-id` response header
+/// set by the request context layer.
+/// Installed by `EywaApp::response_envelope`.
+pub async fn envelope_middleware(
+    State(envelope): State<Arc<dyn ResponseEnvelope>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(req).await;
+    if !response.status().is_success() || !is_json(response.headers()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let Ok(body) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    let meta = EnvelopeMeta {
+        status: parts.status.as_u16(),
+        correlation_id: parts
+            .headers
+            .get("x-correlation-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string),
+    };
+    let wrapped = envelope.wrap(body, &meta);
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(wrapped.to_string()))
+}
+
+/// Wrap the documented JSON schemas and examples of every 2xx response.
+pub fn apply_to_openapi(openapi: &mut OpenApi, envelope: &dyn ResponseEnvelope) {
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            for (status, response) in operation.responses.responses.iter_mut() {
+                let RefOr::T(response) = response else {
+                    continue;
+                };
+                if !status.starts_with('2') {
+                    continue;
+                }
+                let Some(content) = response.content.get_mut("application/json") else {
+                    continue;
+                };
+                if let Some(schema) = content.schema.take() {
+                    content.schema = Some(envelope.wrap_schema(schema));
+                }
+                if let Some(example) = content.example.take() {
+                    let meta = EnvelopeMeta {
+                        status: status.parse().unwrap_or(200),
+                        correlation_id: None,
+                    };
+                    content.example = Some(envelope.wrap(example, &meta));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Json, Router};
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+    use utoipa::openapi::{ContentBuilder, ResponseBuilder};
+    use utoipa::PartialSchema;
+
+    fn client() -> TestClient {
+        let envelope: Arc<dyn ResponseEnvelope> = Arc::new(DataEnvelope::new());
+        TestClient::new(
+            Router::new()
+                .route("/project", get(|| async { Json(json!({ "name": "Apollo" })) }))
+                .route("/missing", get(|| async { StatusCode::NOT_FOUND }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    envelope,
+                    envelope_middleware,
+                )),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_success_body_is_wrapped() {
+        let response = client().get("/project").send().await;
+
+        response.assert_status(StatusCode::OK);
+        assert_eq!(
+            response.json::<Value>(),
+            json!({ "data": { "name": "Apollo" }, "meta": { "correlation_id": null } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_wrapped() {
+        client()
+            .get("/missing")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_custom_keys_without_meta() {
+        let envelope = DataEnvelope::new().data_key("result").without_meta();
+        assert_eq!(
+            envelope.wrap(json!([1, 2]), &EnvelopeMeta::default()),
+            json!({ "result": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn test_success_schemas_are_wrapped() {
+        let operation = OperationBuilder::new()
+            .response(
+                "200",
+                ResponseBuilder::new()
+                    .description("OK")
+                    .content(
+                        "application/json",
+                        ContentBuilder::new().schema(Some(String::schema())).build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/project".to_string(), PathItem::new(HttpMethod::Get, operation));
+
+        apply_to_openapi(&mut openapi, &DataEnvelope::new());
+
+        let operation = openapi.paths.paths["/project"].get.as_ref().unwrap();
+        let RefOr::T(response) = &operation.responses.responses["200"] else {
+            panic!("expected inline response");
+        };
+        let Some(RefOr::T(Schema::Object(envelope))) = &response.content["application/json"].schema
+        else {
+            panic!("expected inline envelope schema");
+        };
+        assert!(envelope.properties.contains_key("data"));
+        assert!(envelope.properties.contains_key("meta"));
+    }
+}
@@ -0,0 +1,360 @@
+//! Machine-readable error code catalog.
+//!
+//! Every error body carries a stable code in its `error` field (see
+//! `ErrorResponse`), so clients can branch on codes instead of parsing
+//! messages. `ErrorCatalog` lists the codes produced by `AppError` and the
+//! framework middleware, plus the codes a service defines for its own errors
+//! through `CodedError`.
+//!
+//! `EywaApp::error_codes` serves the catalog at `GET /errors` and documents
+//! every code as the `ErrorCode` enum, referenced by `ErrorResponse.error`.
+//!
+//! Codes are part of the API contract: once published, a code keeps its
+//! meaning and status. Add new codes instead of repurposing old ones.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::Response, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::{OpenApi, Ref, RefOr};
+use utoipa::{PartialSchema, ToSchema};
+
+use crate::error_responses::ErrorResponse;
+
+/// URL of the catalog endpoint.
+pub const ERROR_CATALOG_URL: &str = "/errors";
+
+/// `AppError::BadRequest`.
+pub const BAD_REQUEST: &str = "BAD_REQUEST";
+/// `AppError::Unauthorized`.
+pub const UNAUTHORIZED: &str = "UNAUTHORIZED";
+/// `AppError::Forbidden`.
+pub const FORBIDDEN: &str = "FORBIDDEN";
+/// `AppError::InternalServerError`.
+pub const INTERNAL_SERVER_ERROR: &str = "INTERNAL_SERVER_ERROR";
+/// A conflicting operation is already running.
+pub const CONFLICT: &str = "CONFLICT";
+/// Rejected by the rate limiter.
+pub const TOO_MANY_REQUESTS: &str = "TOO_MANY_REQUESTS";
+/// Shed by a bulkhead or the adaptive concurrency limit.
+pub const SERVICE_UNAVAILABLE: &str = "SERVICE_UNAVAILABLE";
+/// The service is down for maintenance.
+pub const MAINTENANCE: &str = "MAINTENANCE";
+/// The route was switched off by a kill switch.
+pub const ROUTE_DISABLED: &str = "ROUTE_DISABLED";
+/// The request deadline passed before the handler completed.
+pub const GATEWAY_TIMEOUT: &str = "GATEWAY_TIMEOUT";
+/// A long-running operation failed (reported by `GET /operations/{id}`).
+pub const OPERATION_FAILED: &str = "OPERATION_FAILED";
+/// The operation is unknown or expired (reported by `GET /operations/{id}`).
+pub const OPERATION_NOT_FOUND: &str = "OPERATION_NOT_FOUND";
+/// An upstream service is unreachable or failed.
+pub const UPSTREAM_UNAVAILABLE: &str = "UPSTREAM_UNAVAILABLE";
+/// An upstream service didn't answer in time.
+pub const UPSTREAM_TIMEOUT: &str = "UPSTREAM_TIMEOUT";
+/// An upstream service rejected the request made on the caller's behalf.
+pub const UPSTREAM_REJECTED: &str = "UPSTREAM_REJECTED";
+/// The request body media type isn't documented for the operation.
+pub const UNSUPPORTED_MEDIA_TYPE: &str = "UNSUPPORTED_MEDIA_TYPE";
+/// The `Accept` header excludes every documented response media type.
+pub const NOT_ACCEPTABLE: &str = "NOT_ACCEPTABLE";
+/// The request body exceeds the size limit.
+pub const PAYLOAD_TOO_LARGE: &str = "PAYLOAD_TOO_LARGE";
+/// The request headers exceed the size limit.
+pub const REQUEST_HEADERS_TOO_LARGE: &str = "REQUEST_HEADERS_TOO_LARGE";
+/// The request didn't complete within the request timeout.
+pub const REQUEST_TIMEOUT: &str = "REQUEST_TIMEOUT";
+
+/// A documented error code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ErrorCodeInfo {
+    /// Stable machine-readable code (e.g. `PROJECT_ARCHIVED`)
+    pub code: String,
+    /// HTTP status of responses carrying the code
+    pub status: u16,
+    /// What the error means and how clients should react
+    pub description: String,
+}
+
+impl ErrorCodeInfo {
+    pub fn new(code: impl Into<String>, status: u16, description: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            status,
+            description: description.into(),
+        }
+    }
+}
+
+/// A service-defined error with stable codes.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Debug, thiserror::Error)]
+/// enum BillingError {
+///     #[error("Invoice {0} is already paid")]
+///     AlreadyPaid(Uuid),
+///     #[error("Card declined")]
+///     CardDeclined,
+/// }
+///
+/// impl CodedError for BillingError {
+///     fn code(&self) -> &'static str {
+///         match self {
+///             Self::AlreadyPaid(_) => "INVOICE_ALREADY_PAID",
+///             Self::CardDeclined => "CARD_DECLINED",
+///         }
+///     }
+///
+///     fn status(&self) -> StatusCode {
+///         match self {
+///             Self::AlreadyPaid(_) => StatusCode::CONFLICT,
+///             Self::CardDeclined => StatusCode::PAYMENT_REQUIRED,
+///         }
+///     }
+///
+///     fn catalog() -> Vec<ErrorCodeInfo> {
+///         vec![
+///             ErrorCodeInfo::new("INVOICE_ALREADY_PAID", 409, "The invoice was already paid"),
+///             ErrorCodeInfo::new("CARD_DECLINED", 402, "The card was declined"),
+///         ]
+///     }
+/// }
+///
+/// impl IntoResponse for BillingError {
+///     fn into_response(self) -> Response {
+///         self.error_response()
+///     }
+/// }
+///
+/// EywaApp::new(state).error_codes().coded_error::<BillingError>()
+/// ```
+pub trait CodedError: std::fmt::Display {
+    /// Stable code of this error.
+    fn code(&self) -> &'static str;
+
+    /// HTTP status of this error.
+    fn status(&self) -> StatusCode;
+
+    /// Every code the type can produce, registered in the catalog.
+    fn catalog() -> Vec<ErrorCodeInfo>
+    where
+        Self: Sized;
+
+    /// Respond with the EYWA error envelope carrying the code.
+    fn error_response(&self) -> Response {
+        ErrorResponse::new(self.code(), self.to_string()).into_response_with(self.status())
+    }
+}
+
+/// Every error code a service can respond with.
+#[derive(Debug, Clone)]
+pub struct ErrorCatalog {
+    codes: BTreeMap<String, ErrorCodeInfo>,
+}
+
+impl Default for ErrorCatalog {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ErrorCatalog {
+    /// The codes of `AppError` and the framework middleware.
+    pub fn standard() -> Self {
+        let mut catalog = Self::empty();
+        for (code, status, description) in [
+            (BAD_REQUEST, 400, "The request is malformed or refers to a missing resource"),
+            (UNAUTHORIZED, 401, "Credentials are missing or invalid"),
+            (FORBIDDEN, 403, "The caller is not allowed to perform the operation"),
+            (INTERNAL_SERVER_ERROR, 500, "Unexpected server error"),
+            (CONFLICT, 409, "A conflicting operation is already running"),
+            (TOO_MANY_REQUESTS, 429, "Rate limit exceeded, retry later"),
+            (SERVICE_UNAVAILABLE, 503, "The server is overloaded, retry later"),
+            (MAINTENANCE, 503, "The service is down for maintenance, retry later"),
+            (ROUTE_DISABLED, 503, "The endpoint is temporarily disabled"),
+            (GATEWAY_TIMEOUT, 504, "The request deadline passed"),
+            (OPERATION_FAILED, 500, "A long-running operation failed"),
+            (OPERATION_NOT_FOUND, 404, "The operation is unknown or expired"),
+            (UPSTREAM_UNAVAILABLE, 502, "An upstream service is unreachable or failed"),
+            (UPSTREAM_TIMEOUT, 504, "An upstream service didn't answer in time"),
+            (UPSTREAM_REJECTED, 424, "An upstream service rejected the request"),
+            (UNSUPPORTED_MEDIA_TYPE, 415, "The request body media type is not supported"),
+            (NOT_ACCEPTABLE, 406, "None of the response media types is acceptable"),
+            (PAYLOAD_TOO_LARGE, 413, "The request body is too large"),
+            (REQUEST_HEADERS_TOO_LARGE, 431, "The request headers are too large"),
+            (REQUEST_TIMEOUT, 408, "The request took too long to complete"),
+        ] {
+            catalog.register(ErrorCodeInfo::new(code, status, description));
+        }
+        catalog
+    }
+
+    /// A catalog without any code.
+    pub fn empty() -> Self {
+        Self {
+            codes: BTreeMap::new(),
+        }
+    }
+
+    /// Register a code, replacing an existing entry with the same code.
+    pub fn register(&mut self, info: ErrorCodeInfo) {
+        self.codes.insert(info.code.clone(), info);
+    }
+
+    /// Register every code of a `CodedError`.
+    pub fn register_error<E: CodedError>(&mut self) {
+        for info in E::catalog() {
+            self.register(info);
+        }
+    }
+
+    /// Returns the entry of `code`.
+    pub fn get(&self, code: &str) -> Option<&ErrorCodeInfo> {
+        self.codes.get(code)
+    }
+
+    /// Every code, sorted.
+    pub fn codes(&self) -> impl Iterator<Item = &ErrorCodeInfo> {
+        self.codes.values()
+    }
+
+    /// Register the `ErrorCode` enum, reference it from `ErrorResponse.error`
+    /// and document the catalog endpoint.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        use utoipa::Path;
+
+        let components = openapi.components.get_or_insert_with(Default::default);
+        let codes: Vec<&str> = self.codes.keys().map(String::as_str).collect();
+        components.schemas.insert(
+            "ErrorCode".to_string(),
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .description(Some("Machine-readable error code, see `GET /errors`"))
+                .enum_values(Some(codes))
+                .into(),
+        );
+        components
+            .schemas
+            .insert(ErrorCodeInfo::name().into_owned(), ErrorCodeInfo::schema());
+
+        let error_response = components
+            .schemas
+            .entry(ErrorResponse::name().into_owned())
+            .or_insert_with(ErrorResponse::schema);
+        if let RefOr::T(Schema::Object(object)) = error_response {
+            object
+                .properties
+                .insert("error".to_string(), Ref::from_schema_name("ErrorCode").into());
+        }
+
+        let mut operation = <__path_list_error_codes as Path>::operation();
+        operation.security = Some(Vec::new());
+        openapi.paths.paths.insert(
+            ERROR_CATALOG_URL.to_string(),
+            utoipa::openapi::path::PathItem::new(utoipa::openapi::path::HttpMethod::Get, operation),
+        );
+    }
+
+    /// Router serving the catalog at `GET /errors`.
+    pub fn router<S>(self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route(ERROR_CATALOG_URL, get(list_error_codes))
+            .with_state(Arc::new(self))
+    }
+}
+
+/// List error codes
+///
+/// Every error code the service can respond with, with its status.
+#[utoipa::path(
+    get,
+    path = "/errors",
+    tag = "Errors",
+    responses(
+        (status = 200, description = "Error code catalog", body = [ErrorCodeInfo])
+    )
+)]
+pub async fn list_error_codes(
+    State(catalog): State<Arc<ErrorCatalog>>,
+) -> Json<Vec<ErrorCodeInfo>> {
+    Json(catalog.codes().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    struct QuotaExceeded;
+
+    impl std::fmt::Display for QuotaExceeded {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Project quota exceeded")
+        }
+    }
+
+    impl CodedError for QuotaExceeded {
+        fn code(&self) -> &'static str {
+            "PROJECT_QUOTA_EXCEEDED"
+        }
+
+        fn status(&self) -> StatusCode {
+            StatusCode::CONFLICT
+        }
+
+        fn catalog() -> Vec<ErrorCodeInfo> {
+            vec![ErrorCodeInfo::new(
+                "PROJECT_QUOTA_EXCEEDED",
+                409,
+                "The tenant has reached its project quota",
+            )]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catalog_endpoint() {
+        let mut catalog = ErrorCatalog::standard();
+        catalog.register_error::<QuotaExceeded>();
+        let client = TestClient::new(catalog.router::<()>());
+
+        let response = client.get(ERROR_CATALOG_URL).send().await;
+        response.assert_status(StatusCode::OK);
+        let codes: Vec<ErrorCodeInfo> = response.json();
+        assert!(codes.iter().any(|info| info.code == BAD_REQUEST && info.status == 400));
+        assert!(codes.iter().any(|info| info.code == "PROJECT_QUOTA_EXCEEDED"));
+    }
+
+    #[test]
+    fn test_coded_error_response() {
+        let response = QuotaExceeded.error_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_codes_documented_as_enum() {
+        let mut openapi = OpenApi::default();
+        ErrorCatalog::standard().apply_to_openapi(&mut openapi);
+
+        let schemas = &openapi.components.as_ref().unwrap().schemas;
+        let RefOr::T(Schema::Object(codes)) = &schemas["ErrorCode"] else {
+            panic!("expected inline enum");
+        };
+        assert!(codes
+            .enum_values
+            .as_ref()
+            .unwrap()
+            .contains(&serde_json::json!(TOO_MANY_REQUESTS)));
+        let RefOr::T(Schema::Object(response)) = &schemas["ErrorResponse"] else {
+            panic!("expected inline object");
+        };
+        assert!(matches!(response.properties["error"], RefOr::Ref(_)));
+        assert!(openapi.paths.paths.contains_key(ERROR_CATALOG_URL));
+    }
+}
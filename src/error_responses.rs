@@ -0,0 +1,411 @@
+//! Automatic error-response documentation.
+//!
+//! Handlers return `AppError`, but the error responses are rarely written into
+//! their `#[utoipa::path]` annotations. `ErrorResponses` registers the
+//! `ErrorResponse` schema, one reusable response component per status
+//! (`NotFound`, `Conflict`, ...) and references them from every
+//! operation, unless the route overrides the set (`#[route(errors(404, 409))]`
+//! or `EywaApp::route_errors`). Responses already documented by the handler,
+//! or by validation, are kept.
+
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+
+use serde::Serialize;
+use utoipa::openapi::header::HeaderBuilder;
+use utoipa::openapi::schema::{ObjectBuilder, Type};
+use utoipa::openapi::{ContentBuilder, OpenApi, Ref, RefOr, ResponseBuilder};
+use utoipa::{PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use crate::responses::{is_public, response_ref};
+use crate::traits::RouteErrors;
+
+/// Statuses documented by default on every operation.
+pub const STANDARD_ERROR_STATUSES: [u16; 7] = [400, 401, 403, 404, 409, 422, 500];
+
+/// JSON body of an `AppError` response.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Machine-readable error kind (e.g. `NOT_FOUND`)
+    pub error: String,
+    /// Human-readable description
+    pub message: String,
+    /// ID of the failed request, if request context is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Uuid>,
+    /// Correlation ID reported by the failed upstream service
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_correlation_id: Option<String>,
+    /// Seconds to wait before retrying, also sent as `Retry-After`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+}
+
+/// Statuses whose responses carry a `Retry-After` header.
+pub const RETRY_AFTER_STATUSES: [u16; 2] = [429, 503];
+
+/// `Retry-After` seconds for a wait, rounded up (at least one second).
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+impl ErrorResponse {
+    /// Error body for the current request (`request_id` is set when request
+    /// context is enabled).
+    pub fn new(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            message: message.into(),
+            request_id: eywa_errors::CURRENT_REQUEST_ID.try_with(|id| *id).ok(),
+            upstream_correlation_id: None,
+            retry_after: None,
+        }
+    }
+
+    /// Tell the client how long to wait before retrying.
+    pub fn retry_after(mut self, wait: Duration) -> Self {
+        self.retry_after = Some(retry_after_secs(wait));
+        self
+    }
+
+    /// Attach the correlation ID of the upstream call that failed.
+    pub fn upstream_correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.upstream_correlation_id = Some(id.into());
+        self
+    }
+
+    /// Respond with this body, for rejections produced outside `AppError`.
+    ///
+    /// Adds the `Retry-After` header when `retry_after` is set.
+    pub fn into_response_with(self, status: axum::http::StatusCode) -> axum::response::Response {
+        use axum::response::IntoResponse;
+
+        let retry_after = self.retry_after;
+        let mut response = (status, axum::Json(self)).into_response();
+        if let Some(seconds) = retry_after {
+            response
+                .headers_mut()
+                .insert(axum::http::header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+/// Default description of a documented error status.
+fn description(status: u16) -> &'static str {
+    match status {
+        400 => "Bad request",
+        401 => "Missing or invalid credentials",
+        403 => "Insufficient permissions",
+        404 => "Resource not found",
+        409 => "Conflict with the current state of the resource",
+        422 => "Validation failed",
+        429 => "Too many requests",
+        500 => "Internal server error",
+        503 => "Service unavailable",
+        _ => "Error",
+    }
+}
+
+/// Name of the response component documenting an error status.
+pub fn component_name(status: u16) -> String {
+    match status {
+        400 => "BadRequest".to_string(),
+        401 => "Unauthorized".to_string(),
+        403 => "Forbidden".to_string(),
+        404 => "NotFound".to_string(),
+        409 => "Conflict".to_string(),
+        422 => "UnprocessableEntity".to_string(),
+        429 => "TooManyRequests".to_string(),
+        500 => "InternalServerError".to_string(),
+        503 => "ServiceUnavailable".to_string(),
+        _ => format!("Error{status}"),
+    }
+}
+
+/// Error statuses documented on every operation.
+#[derive(Debug, Clone)]
+pub struct ErrorResponses {
+    statuses: BTreeSet<u16>,
+    routes: HashMap<(String, String), BTreeSet<u16>>,
+}
+
+impl Default for ErrorResponses {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ErrorResponses {
+    /// Document 400, 401, 403, 404, 409, 422 and 500.
+    pub fn standard() -> Self {
+        Self::only(STANDARD_ERROR_STATUSES)
+    }
+
+    /// Document exactly the given statuses.
+    pub fn only(statuses: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            statuses: statuses.into_iter().collect(),
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Also document `status` on every operation.
+    pub fn with(mut self, status: u16) -> Self {
+        self.statuses.insert(status);
+        self
+    }
+
+    /// Stop documenting `status` by default.
+    pub fn without(mut self, status: u16) -> Self {
+        self.statuses.remove(&status);
+        self
+    }
+
+    /// Replace the documented statuses of a single route.
+    pub fn insert(&mut self, route: RouteErrors) {
+        self.routes.insert(
+            (route.method.to_uppercase(), route.path),
+            route.statuses.into_iter().collect(),
+        );
+    }
+
+    /// Returns the statuses documented for `method` on the route template `path`.
+    pub fn statuses(&self, method: &str, path: &str) -> &BTreeSet<u16> {
+        self.routes
+            .get(&(method.to_uppercase(), path.to_string()))
+            .unwrap_or(&self.statuses)
+    }
+
+    /// Register the `ErrorResponse` schema and document the error responses.
+    ///
+    /// Response components already registered under the same name (see
+    /// `EywaApp::response_component`) are kept. Operations marked public
+    /// (empty security requirement) don't get the `401`/`403` responses.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components
+            .schemas
+            .insert(ErrorResponse::name().into_owned(), ErrorResponse::schema());
+
+        let mut used: BTreeSet<u16> = self.statuses.clone();
+        used.extend(self.routes.values().flatten());
+        for status in used {
+            components
+                .responses
+                .entry(component_name(status))
+                .or_insert_with(|| RefOr::T(error_response(status)));
+        }
+
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let operations = [
+                ("GET", &mut item.get),
+                ("POST", &mut item.post),
+                ("PUT", &mut item.put),
+                ("DELETE", &mut item.delete),
+                ("PATCH", &mut item.patch),
+            ];
+            for (method, operation) in operations {
+                let Some(operation) = operation.as_mut() else {
+                    continue;
+                };
+                let public = is_public(operation);
+
+                for &status in self.statuses(method, path) {
+                    if public && matches!(status, 401 | 403) {
+                        continue;
+                    }
+                    operation
+                        .responses
+                        .responses
+                        .entry(status.to_string())
+                        .or_insert_with(|| response_ref(&component_name(status)));
+                }
+            }
+        }
+    }
+}
+
+/// Build the response component of an error status.
+fn error_response(status: u16) -> utoipa::openapi::Response {
+    let response = ResponseBuilder::new()
+        .description(description(status))
+        .content(
+            "application/json",
+            ContentBuilder::new()
+                .schema(Some(Ref::from_schema_name(ErrorResponse::name())))
+                .build(),
+        );
+    if !RETRY_AFTER_STATUSES.contains(&status) {
+        return response.build();
+    }
+    response
+        .header(
+            "Retry-After",
+            HeaderBuilder::new()
+                .schema(ObjectBuilder::new().schema_type(Type::Integer).minimum(Some(1)))
+                .description(Some("Seconds to wait before retrying"))
+                .build(),
+        )
+        .build()
+}
+
+/// Document `status` (e.g. `429` from the rate limiter) with its
+/// `Retry-After` header on every operation that doesn't document it already.
+pub fn document_status(openapi: &mut OpenApi, status: u16) {
+    let components = openapi.components.get_or_insert_with(Default::default);
+    components
+        .schemas
+        .entry(ErrorResponse::name().into_owned())
+        .or_insert_with(ErrorResponse::schema);
+    components
+        .responses
+        .entry(component_name(status))
+        .or_insert_with(|| RefOr::T(error_response(status)));
+
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            operation
+                .responses
+                .responses
+                .entry(status.to_string())
+                .or_insert_with(|| response_ref(&component_name(status)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn spec() -> OpenApi {
+        let mut openapi = OpenApi::default();
+        let documented = OperationBuilder::new()
+            .response("404", ResponseBuilder::new().description("No such project").build())
+            .build();
+        let mut public = OperationBuilder::new().build();
+        public.security = Some(Vec::new());
+        openapi.paths.paths.insert(
+            "/projects/{id}".to_string(),
+            PathItem::new(HttpMethod::Get, documented),
+        );
+        openapi
+            .paths
+            .paths
+            .insert("/status".to_string(), PathItem::new(HttpMethod::Get, public));
+        openapi
+    }
+
+    fn statuses(openapi: &OpenApi, path: &str) -> Vec<String> {
+        let operation = openapi.paths.paths[path].get.as_ref().unwrap();
+        operation.responses.responses.keys().cloned().collect()
+    }
+
+    #[test]
+    fn test_standard_errors_documented() {
+        let mut openapi = spec();
+        ErrorResponses::standard().apply_to_openapi(&mut openapi);
+
+        assert_eq!(
+            statuses(&openapi, "/projects/{id}"),
+            ["400", "401", "403", "404", "409", "422", "500"]
+        );
+        assert!(openapi
+            .components
+            .as_ref()
+            .unwrap()
+            .schemas
+            .contains_key("ErrorResponse"));
+    }
+
+    #[test]
+    fn test_errors_reference_response_components() {
+        let mut openapi = spec();
+        ErrorResponses::standard().apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/projects/{id}"].get.as_ref().unwrap();
+        let RefOr::Ref(reference) = &operation.responses.responses["409"] else {
+            panic!("expected response reference");
+        };
+        assert_eq!(reference.ref_location, "#/components/responses/Conflict");
+        assert!(openapi
+            .components
+            .as_ref()
+            .unwrap()
+            .responses
+            .contains_key("Conflict"));
+    }
+
+    #[test]
+    fn test_existing_responses_are_kept() {
+        let mut openapi = spec();
+        ErrorResponses::standard().apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/projects/{id}"].get.as_ref().unwrap();
+        let RefOr::T(response) = &operation.responses.responses["404"] else {
+            panic!("expected inline response");
+        };
+        assert_eq!(response.description, "No such project");
+    }
+
+    #[test]
+    fn test_public_routes_skip_auth_errors() {
+        let mut openapi = spec();
+        ErrorResponses::standard().apply_to_openapi(&mut openapi);
+
+        assert_eq!(
+            statuses(&openapi, "/status"),
+            ["400", "404", "409", "422", "500"]
+        );
+    }
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(retry_after_secs(Duration::ZERO), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(200)), 1);
+        assert_eq!(retry_after_secs(Duration::from_secs(3)), 3);
+        assert_eq!(retry_after_secs(Duration::from_millis(3100)), 4);
+
+        let response = ErrorResponse::new("TOO_MANY_REQUESTS", "Slow down")
+            .retry_after(Duration::from_millis(1500))
+            .into_response_with(axum::http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "2");
+    }
+
+    #[test]
+    fn test_retry_after_documented() {
+        let mut openapi = spec();
+        document_status(&mut openapi, 429);
+
+        assert!(statuses(&openapi, "/status").contains(&"429".to_string()));
+        let components = openapi.components.as_ref().unwrap();
+        let RefOr::T(response) = &components.responses["TooManyRequests"] else {
+            panic!("expected response component");
+        };
+        assert!(response.headers.contains_key("Retry-After"));
+    }
+
+    #[test]
+    fn test_route_override() {
+        let mut errors = ErrorResponses::standard().without(409);
+        errors.insert(RouteErrors {
+            method: "get".to_string(),
+            path: "/status".to_string(),
+            statuses: vec![503],
+        });
+        let mut openapi = spec();
+        errors.apply_to_openapi(&mut openapi);
+
+        assert_eq!(statuses(&openapi, "/status"), ["503"]);
+        assert!(!statuses(&openapi, "/projects/{id}").contains(&"409".to_string()));
+    }
+}
@@ -0,0 +1,347 @@
+//! A/B experiment assignment.
+//!
+//! Experiments are declared in configuration and bucket requests
+//! deterministically by user or tenant: the same ID always gets the same
+//! variant of an experiment, on every replica and across restarts.
+//!
+//! ```toml
+//! [[experiments.experiments]]
+//! name = "new-onboarding"
+//! bucket_by = "user"            # or "tenant" (X-Tenant-ID)
+//! variants = [
+//!     { name = "control", weight = 50 },
+//!     { name = "guided", weight = 50 },
+//! ]
+//! ```
+//!
+//! `EywaApp::experiments` assigns the variants of every request with an ID to
+//! bucket by. The assignments are stored in `RequestContext::experiments`,
+//! extracted with `Assignments` and returned in the `X-Experiments` header
+//! (`new-onboarding=guided, checkout-copy=short`) so the frontend and the
+//! analytics pipeline see the same variants as the backend.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::rate_limit::TENANT_HEADER;
+use crate::middleware::RequestContext;
+
+/// Response header listing the assigned variants.
+pub const EXPERIMENTS_HEADER: &str = "x-experiments";
+
+/// Experiment settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExperimentSettings {
+    /// Running experiments
+    #[serde(default)]
+    pub experiments: Vec<Experiment>,
+}
+
+/// What requests are bucketed by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketBy {
+    /// `RequestContext::user_id`
+    #[default]
+    User,
+    /// The `X-Tenant-ID` header
+    Tenant,
+}
+
+/// A variant and its share of the traffic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    /// Relative weight of the variant
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// An experiment and its variants.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    /// Name of the experiment, also salting the bucketing
+    pub name: String,
+    #[serde(default)]
+    pub bucket_by: BucketBy,
+    pub variants: Vec<Variant>,
+    /// Disabled experiments assign no variant
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Experiment {
+    /// An enabled experiment bucketing by user, without variants.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            bucket_by: BucketBy::User,
+            variants: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    /// Bucket by tenant instead of user.
+    pub fn by_tenant(mut self) -> Self {
+        self.bucket_by = BucketBy::Tenant;
+        self
+    }
+
+    /// Add a variant.
+    pub fn variant(mut self, name: impl Into<String>, weight: u32) -> Self {
+        self.variants.push(Variant {
+            name: name.into(),
+            weight,
+        });
+        self
+    }
+
+    /// Variant of the user or tenant `id`.
+    pub fn assign(&self, id: &str) -> Option<&str> {
+        let total: u64 = self.variants.iter().map(|variant| u64::from(variant.weight)).sum();
+        if !self.enabled || total == 0 {
+            return None;
+        }
+
+        let mut bucket = fnv1a(&[self.name.as_bytes(), b":", id.as_bytes()]) % total;
+        for variant in &self.variants {
+            let weight = u64::from(variant.weight);
+            if bucket < weight {
+                return Some(&variant.name);
+            }
+            bucket -= weight;
+        }
+        None
+    }
+}
+
+/// FNV-1a, stable across Rust versions unlike `DefaultHasher`.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// The experiments of the application.
+#[derive(Debug, Clone, Default)]
+pub struct Experiments {
+    experiments: Vec<Experiment>,
+}
+
+impl Experiments {
+    /// Create the experiments from settings.
+    pub fn new(settings: ExperimentSettings) -> Self {
+        Self {
+            experiments: settings.experiments,
+        }
+    }
+
+    /// Add an experiment.
+    pub fn experiment(mut self, experiment: Experiment) -> Self {
+        self.experiments.push(experiment);
+        self
+    }
+
+    /// Variants of every experiment the request can be bucketed in.
+    pub fn assign(&self, user_id: Option<&str>, tenant: Option<&str>) -> BTreeMap<String, String> {
+        self.experiments
+            .iter()
+            .filter_map(|experiment| {
+                let id = match experiment.bucket_by {
+                    BucketBy::User => user_id?,
+                    BucketBy::Tenant => tenant?,
+                };
+                let variant = experiment.assign(id)?;
+                Some((experiment.name.clone(), variant.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Variants assigned to the request.
+///
+/// # Example
+///
+/// ```ignore
+/// async fn onboarding(assignments: Assignments) -> Json<Onboarding> {
+///     if assignments.is("new-onboarding", "guided") {
+///         Json(Onboarding::guided())
+///     } else {
+///         Json(Onboarding::classic())
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assignments(BTreeMap<String, String>);
+
+impl Assignments {
+    /// Variant of `experiment`, if the request was bucketed in it.
+    pub fn variant(&self, experiment: &str) -> Option<&str> {
+        self.0.get(experiment).map(String::as_str)
+    }
+
+    /// Returns `true` if the request got `variant` of `experiment`.
+    pub fn is(&self, experiment: &str, variant: &str) -> bool {
+        self.variant(experiment) == Some(variant)
+    }
+
+    /// Every assignment, by experiment.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(experiment, variant)| (experiment.as_str(), variant.as_str()))
+    }
+
+    fn header_value(&self) -> Option<HeaderValue> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let value = self
+            .iter()
+            .map(|(experiment, variant)| format!("{experiment}={variant}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+impl<S> FromRequestParts<S> for Assignments
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+fn tenant(headers: &HeaderMap) -> Option<&str> {
+    headers.get(TENANT_HEADER).and_then(|value| value.to_str().ok())
+}
+
+/// Middleware assigning the experiment variants of the request.
+///
+/// Installed by `EywaApp::experiments()`.
+pub async fn experiments_middleware(
+    State(experiments): State<Arc<Experiments>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let user_id = req
+        .extensions()
+        .get::<RequestContext>()
+        .and_then(|ctx| ctx.user_id.as_ref())
+        .map(|id| id.to_string());
+    let assignments = Assignments(experiments.assign(user_id.as_deref(), tenant(req.headers())));
+
+    if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+        ctx.experiments = assignments.0.clone();
+    }
+    req.extensions_mut().insert(assignments.clone());
+
+    let mut response = next.run(req).await;
+    if let Some(value) = assignments.header_value() {
+        response.headers_mut().insert(EXPERIMENTS_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    fn onboarding() -> Experiment {
+        Experiment::new("new-onboarding")
+            .variant("control", 50)
+            .variant("guided", 50)
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic() {
+        let experiment = onboarding();
+        let first: Vec<_> = (0..100).map(|id| experiment.assign(&id.to_string())).collect();
+        let second: Vec<_> = (0..100).map(|id| experiment.assign(&id.to_string())).collect();
+        assert_eq!(first, second);
+
+        let guided = first.iter().filter(|variant| **variant == Some("guided")).count();
+        assert!((30..70).contains(&guided), "{guided} guided out of 100");
+    }
+
+    #[test]
+    fn test_weights_and_disabled_experiments() {
+        let everyone = Experiment::new("copy").variant("short", 1).variant("long", 0);
+        assert!((0..20).all(|id| everyone.assign(&id.to_string()) == Some("short")));
+
+        let disabled = Experiment {
+            enabled: false,
+            ..onboarding()
+        };
+        assert_eq!(disabled.assign("user-1"), None);
+    }
+
+    #[test]
+    fn test_settings_from_config() {
+        let settings: ExperimentSettings = serde_json::from_value(serde_json::json!({
+            "experiments": [{
+                "name": "pricing-page",
+                "bucket_by": "tenant",
+                "variants": [{ "name": "control" }, { "name": "annual-first" }]
+            }]
+        }))
+        .unwrap();
+
+        let experiment = &settings.experiments[0];
+        assert_eq!(experiment.bucket_by, BucketBy::Tenant);
+        assert!(experiment.enabled);
+        assert_eq!(experiment.variants[1].weight, 1);
+    }
+
+    #[tokio::test]
+    async fn test_assignments_extracted_and_returned() {
+        let experiments = Experiments::default()
+            .experiment(Experiment::new("pricing-page").by_tenant().variant("annual-first", 1))
+            .experiment(onboarding());
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/",
+                    get(|assignments: Assignments| async move {
+                        assignments.variant("pricing-page").unwrap_or("none").to_string()
+                    }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(experiments),
+                    experiments_middleware,
+                )),
+        );
+
+        let response = client.get("/").header(TENANT_HEADER, "acme").send().await;
+        assert_eq!(response.header(EXPERIMENTS_HEADER), Some("pricing-page=annual-first"));
+        assert_eq!(response.text(), "annual-first");
+
+        let response = client.get("/").send().await;
+        assert_eq!(response.header(EXPERIMENTS_HEADER), None);
+        assert_eq!(response.text(), "none");
+    }
+}
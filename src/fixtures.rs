@@ -0,0 +1,308 @@
+//! Fixture and seed-data loading into sea_orm entities.
+//!
+//! Fixture files (YAML or JSON) map entity names to lists of rows:
+//!
+//! ```yaml
+//! users:
+//!   - { id: "5f0c...", email: "ada@example.com" }
+//! projects:
+//!   - { id: "9a41...", owner_id: "5f0c...", name: "Apollo" }
+//! ```
+//!
+//! Entities are registered with the entities they reference, so rows are
+//! inserted in foreign-key order and deleted in reverse order on teardown.
+//! Usable in tests (per-test `LoadedFixtures` scope) and for startup seeding
+//! with `RUN_MODE=development`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, IntoActiveModel};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+fn fixture_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Fixture error: {e}"))
+}
+
+/// Type-erased insert/delete operations for one entity.
+#[async_trait]
+trait EntityLoader: Send + Sync {
+    async fn insert(&self, db: &DatabaseConnection, row: Value) -> Result<()>;
+    async fn delete(&self, db: &DatabaseConnection, row: Value) -> Result<()>;
+}
+
+struct TypedLoader<E>(PhantomData<fn() -> E>);
+
+#[async_trait]
+impl<E> EntityLoader for TypedLoader<E>
+where
+    E: EntityTrait,
+    E::Model: DeserializeOwned + IntoActiveModel<E::ActiveModel>,
+    E::ActiveModel: ActiveModelTrait<Entity = E> + Send,
+{
+    async fn insert(&self, db: &DatabaseConnection, row: Value) -> Result<()> {
+        let model: E::Model = serde_json::from_value(row).map_err(fixture_error)?;
+        E::insert(model.into_active_model())
+            .exec_without_returning(db)
+            .await
+            .map_err(fixture_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, db: &DatabaseConnection, row: Value) -> Result<()> {
+        let model: E::Model = serde_json::from_value(row).map_err(fixture_error)?;
+        E::delete(model.into_active_model())
+            .exec(db)
+            .await
+            .map_err(fixture_error)?;
+        Ok(())
+    }
+}
+
+struct RegisteredEntity {
+    depends_on: Vec<String>,
+    loader: Arc<dyn EntityLoader>,
+}
+
+/// A set of fixture rows and the entities they are loaded into.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::fixtures::Fixtures;
+///
+/// let fixtures = Fixtures::new()
+///     .entity::<users::Entity>("users", &[])
+///     .entity::<projects::Entity>("projects", &["users"])
+///     .file("fixtures/projects.yaml")?;
+///
+/// let loaded = fixtures.load(&db).await?;
+/// // ... run the test ...
+/// loaded.teardown(&db).await?;
+/// ```
+#[derive(Default)]
+pub struct Fixtures {
+    entities: HashMap<String, RegisteredEntity>,
+    rows: BTreeMap<String, Vec<Value>>,
+}
+
+impl Fixtures {
+    /// Create an empty fixture set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an entity under `name`, listing the entities it references.
+    ///
+    /// Rows are deserialized as complete `Model`s, so fixtures must specify
+    /// primary keys (which other fixtures reference anyway).
+    pub fn entity<E>(mut self, name: impl Into<String>, depends_on: &[&str]) -> Self
+    where
+        E: EntityTrait,
+        E::Model: DeserializeOwned + IntoActiveModel<E::ActiveModel>,
+        E::ActiveModel: ActiveModelTrait<Entity = E> + Send,
+    {
+        self.entities.insert(
+            name.into(),
+            RegisteredEntity {
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                loader: Arc::new(TypedLoader::<E>(PhantomData)),
+            },
+        );
+        self
+    }
+
+    /// Add rows from a YAML or JSON document.
+    pub fn yaml(mut self, source: &str) -> Result<Self> {
+        let document: BTreeMap<String, Vec<Value>> =
+            serde_yaml::from_str(source).map_err(fixture_error)?;
+        self.extend(document);
+        Ok(self)
+    }
+
+    /// Add rows from a JSON document.
+    pub fn json(mut self, source: &str) -> Result<Self> {
+        let document: BTreeMap<String, Vec<Value>> =
+            serde_json::from_str(source).map_err(fixture_error)?;
+        self.extend(document);
+        Ok(self)
+    }
+
+    /// Add rows from a `.yaml`, `.yml` or `.json` file.
+    pub fn file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| fixture_error(format!("{}: {e}", path.display())))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => self.json(&source),
+            _ => self.yaml(&source),
+        }
+    }
+
+    fn extend(&mut self, document: BTreeMap<String, Vec<Value>>) {
+        for (entity, rows) in document {
+            self.rows.entry(entity).or_default().extend(rows);
+        }
+    }
+
+    /// Entity names in foreign-key order (referenced entities first).
+    fn load_order(&self) -> Result<Vec<String>> {
+        for entity in self.rows.keys() {
+            if !self.entities.contains_key(entity) {
+                return Err(fixture_error(format!("unregistered entity `{entity}`")));
+            }
+        }
+
+        let mut order: Vec<String> = Vec::with_capacity(self.entities.len());
+        let mut names: Vec<&String> = self.entities.keys().collect();
+        names.sort();
+
+        while order.len() < names.len() {
+            let next = names.iter().find(|name| {
+                !order.contains(*name)
+                    && self.entities[*name]
+                        .depends_on
+                        .iter()
+                        .all(|dep| order.contains(dep) || !self.entities.contains_key(dep))
+            });
+            match next {
+                Some(name) => order.push((*name).clone()),
+                None => return Err(fixture_error("circular entity dependencies")),
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Insert every row in foreign-key order.
+    pub async fn load(&self, db: &DatabaseConnection) -> Result<LoadedFixtures> {
+        let mut loaded = LoadedFixtures { rows: Vec::new() };
+
+        for entity in self.load_order()? {
+            let Some(rows) = self.rows.get(&entity) else {
+                continue;
+            };
+            let loader = self.entities[&entity].loader.clone();
+            for row in rows {
+                loader.insert(db, row.clone()).await?;
+                loaded.rows.push((loader.clone(), row.clone()));
+            }
+            tracing::debug!("🌱 Loaded {} {} fixtures", rows.len(), entity);
+        }
+
+        Ok(loaded)
+    }
+
+    /// Load the fixtures only when `RUN_MODE=development`.
+    ///
+    /// Intended for startup seeding; the rows are kept after shutdown.
+    pub async fn seed_if_development(&self, db: &DatabaseConnection) -> Result<bool> {
+        if std::env::var("RUN_MODE").as_deref() != Ok("development") {
+            return Ok(false);
+        }
+        let seeded = self.load(db).await?;
+        tracing::info!("🌱 Seeded {} development fixtures", seeded.len());
+        Ok(true)
+    }
+}
+
+/// Rows inserted by `Fixtures::load`, removed by `teardown`.
+#[must_use = "call teardown() to remove the fixture rows"]
+pub struct LoadedFixtures {
+    rows: Vec<(Arc<dyn EntityLoader>, Value)>,
+}
+
+impl LoadedFixtures {
+    /// Number of inserted rows.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns `true` if no row was inserted.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Delete the inserted rows in reverse foreign-key order.
+    pub async fn teardown(self, db: &DatabaseConnection) -> Result<()> {
+        for (loader, row) in self.rows.into_iter().rev() {
+            loader.delete(db, row).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopLoader;
+
+    #[async_trait]
+    impl EntityLoader for NoopLoader {
+        async fn insert(&self, _db: &DatabaseConnection, _row: Value) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete(&self, _db: &DatabaseConnection, _row: Value) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn register(mut fixtures: Fixtures, name: &str, depends_on: &[&str]) -> Fixtures {
+        fixtures.entities.insert(
+            name.to_string(),
+            RegisteredEntity {
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                loader: Arc::new(NoopLoader),
+            },
+        );
+        fixtures
+    }
+
+    #[test]
+    fn test_load_order_respects_dependencies() {
+        let fixtures = register(Fixtures::new(), "tasks", &["projects", "users"]);
+        let fixtures = register(fixtures, "projects", &["users"]);
+        let fixtures = register(fixtures, "users", &[]);
+
+        assert_eq!(
+            fixtures.load_order().unwrap(),
+            vec!["users", "projects", "tasks"]
+        );
+    }
+
+    #[test]
+    fn test_load_order_detects_cycles() {
+        let fixtures = register(Fixtures::new(), "a", &["b"]);
+        let fixtures = register(fixtures, "b", &["a"]);
+
+        assert!(fixtures.load_order().is_err());
+    }
+
+    #[test]
+    fn test_rows_for_unregistered_entities_are_rejected() {
+        let fixtures = Fixtures::new().json(r#"{"users":[{"id":1}]}"#).unwrap();
+
+        assert!(fixtures.load_order().is_err());
+    }
+
+    #[test]
+    fn test_yaml_documents_are_merged() {
+        let fixtures = Fixtures::new()
+            .yaml("users:\n  - { id: 1 }\n")
+            .unwrap()
+            .yaml("users:\n  - { id: 2 }\n")
+            .unwrap();
+
+        assert_eq!(fixtures.rows["users"].len(), 2);
+    }
+}
@@ -1,14 +1,43 @@
 //! Health check endpoints for Kubernetes readiness and liveness probes.
 //!
-//! This module provides three endpoints:
+//! This module provides four endpoints:
 //! - `/health` - Basic health check (always returns 200 OK)
-//! - `/health/ready` - Readiness probe (checks database connection)
+//! - `/health/ready` - Readiness probe (checks database connection, warmup
+//!   and the components registered as `HealthChecks`)
 //! - `/health/live` - Liveness probe (always returns 200 OK)
+//! - `/health/startup` - Startup probe (200 OK once warmup completed)
+//!
+//! Dependencies of a service are checked on readiness by implementing
+//! `HealthCheck` and registering it under a name:
+//!
+//! ```ignore
+//! let checks = HealthChecks::new()
+//!     .add("database", db.clone())
+//!     .add("redis", redis.clone())
+//!     .add_with_timeout(
+//!         "billing",
+//!         HttpCheck::new(http.clone(), "http://billing/health/live"),
+//!         Duration::from_millis(500),
+//!     );
+//!
+//! EywaApp::new(state).health_checks_with(checks)
+//! ```
+//!
+//! Checks run concurrently on every readiness probe, each bounded by its
+//! timeout (2 seconds by default); one unhealthy component fails the probe.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use axum::Json;
+use async_trait::async_trait;
+use axum::{http::StatusCode, Json};
+use futures_util::future::join_all;
+use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 use utoipa::{PartialSchema, ToSchema};
 
+use crate::warmup::WarmupProgress;
 use crate::Result;
 
 /// Health status enum
@@ -45,10 +74,266 @@ pub struct DetailedHealthResponse {
     pub checks: Checks,
 }
 
+/// Warmup tasks status
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum WarmupStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "completed")]
+    Completed,
+}
+
+impl From<&WarmupProgress> for WarmupStatus {
+    fn from(progress: &WarmupProgress) -> Self {
+        if progress.is_complete() {
+            Self::Completed
+        } else {
+            Self::Pending
+        }
+    }
+}
+
 /// Component health checks
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Checks {
-    pub database: DatabaseStatus,
+    /// Database registered with `EywaApp::database`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database: Option<DatabaseStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<WarmupStatus>,
+    /// Search cluster, when registered with `EywaApp::search`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<DatabaseStatus>,
+    /// Components registered with `EywaApp::health_checks_with`, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub components: BTreeMap<String, ComponentStatus>,
+}
+
+/// Status of a component checked by a `HealthCheck`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ComponentStatus {
+    pub status: HealthStatus,
+    /// Why the component is unhealthy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Time the check took, in milliseconds
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+impl ComponentStatus {
+    /// The component works.
+    pub fn healthy() -> Self {
+        Self {
+            status: HealthStatus::Healthy,
+            error: None,
+            duration_ms: 0,
+        }
+    }
+
+    /// The component doesn't work, for the given reason.
+    pub fn unhealthy(error: impl Into<String>) -> Self {
+        Self {
+            status: HealthStatus::Unhealthy,
+            error: Some(error.into()),
+            duration_ms: 0,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+}
+
+impl From<DatabaseStatus> for ComponentStatus {
+    fn from(status: DatabaseStatus) -> Self {
+        match status {
+            DatabaseStatus::Connected => Self::healthy(),
+            DatabaseStatus::Disconnected => Self::unhealthy("disconnected"),
+            DatabaseStatus::Error(e) => Self::unhealthy(e),
+        }
+    }
+}
+
+/// A dependency checked by the readiness probe.
+///
+/// # Example
+///
+/// ```ignore
+/// struct QueueCheck(QueueClient);
+///
+/// #[async_trait]
+/// impl HealthCheck for QueueCheck {
+///     async fn check(&self) -> ComponentStatus {
+///         match self.0.ping().await {
+///             Ok(()) => ComponentStatus::healthy(),
+///             Err(e) => ComponentStatus::unhealthy(e.to_string()),
+///         }
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait HealthCheck: Send + Sync + 'static {
+    /// Check the component. Timeouts are enforced by the caller.
+    async fn check(&self) -> ComponentStatus;
+}
+
+#[async_trait]
+impl HealthCheck for DatabaseConnection {
+    async fn check(&self) -> ComponentStatus {
+        check_database(self).await.into()
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl HealthCheck for redis::aio::ConnectionManager {
+    async fn check(&self) -> ComponentStatus {
+        match redis::cmd("PING").query_async::<String>(&mut self.clone()).await {
+            Ok(_) => ComponentStatus::healthy(),
+            Err(e) => ComponentStatus::unhealthy(e.to_string()),
+        }
+    }
+}
+
+/// Downstream HTTP service, healthy when `GET url` answers `2xx`.
+#[derive(Debug, Clone)]
+pub struct HttpCheck {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpCheck {
+    pub fn new(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for HttpCheck {
+    async fn check(&self) -> ComponentStatus {
+        match self.client.get(&self.url).send().await {
+            Ok(response) if response.status().is_success() => ComponentStatus::healthy(),
+            Ok(response) => {
+                ComponentStatus::unhealthy(format!("{} answered {}", self.url, response.status()))
+            }
+            Err(e) => ComponentStatus::unhealthy(e.to_string()),
+        }
+    }
+}
+
+/// Default timeout of a `HealthCheck`.
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+struct NamedCheck {
+    name: String,
+    check: Arc<dyn HealthCheck>,
+    timeout: Option<Duration>,
+}
+
+/// Named checks evaluated by the readiness probe.
+#[derive(Clone)]
+pub struct HealthChecks {
+    checks: Vec<NamedCheck>,
+    timeout: Duration,
+}
+
+impl Default for HealthChecks {
+    fn default() -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout: DEFAULT_CHECK_TIMEOUT,
+        }
+    }
+}
+
+impl std::fmt::Debug for HealthChecks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HealthChecks")
+            .field("checks", &self.names())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl HealthChecks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout of the checks added without their own (2 seconds by default).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Check a component under `name`, replacing a check with the same name.
+    pub fn add(self, name: impl Into<String>, check: impl HealthCheck) -> Self {
+        self.insert(name.into(), Arc::new(check), None)
+    }
+
+    /// Check a component under `name`, giving up after `timeout`.
+    pub fn add_with_timeout(
+        self,
+        name: impl Into<String>,
+        check: impl HealthCheck,
+        timeout: Duration,
+    ) -> Self {
+        self.insert(name.into(), Arc::new(check), Some(timeout))
+    }
+
+    fn insert(
+        mut self,
+        name: String,
+        check: Arc<dyn HealthCheck>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        self.checks.retain(|existing| existing.name != name);
+        self.checks.push(NamedCheck {
+            name,
+            check,
+            timeout,
+        });
+        self
+    }
+
+    /// Names of the checked components.
+    pub fn names(&self) -> Vec<&str> {
+        self.checks.iter().map(|check| check.name.as_str()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    /// Run every check concurrently, each bounded by its timeout.
+    pub async fn run(&self) -> BTreeMap<String, ComponentStatus> {
+        let checks = self.checks.iter().map(|named| async move {
+            let timeout = named.timeout.unwrap_or(self.timeout);
+            let started = Instant::now();
+            let mut status = match tokio::time::timeout(timeout, named.check.check()).await {
+                Ok(status) => status,
+                Err(_) => ComponentStatus::unhealthy(format!(
+                    "timed out after {}ms",
+                    timeout.as_millis()
+                )),
+            };
+            status.duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+            if !status.is_healthy() {
+                tracing::warn!(
+                    component = %named.name,
+                    error = status.error.as_deref().unwrap_or_default(),
+                    "Health check failed"
+                );
+            }
+            (named.name.clone(), status)
+        });
+        join_all(checks).await.into_iter().collect()
+    }
 }
 
 /// Basic health check endpoint
@@ -98,7 +383,10 @@ pub async fn ready() -> Result<Json<DetailedHealthResponse>> {
     Ok(Json(DetailedHealthResponse {
         status: HealthStatus::Healthy,
         checks: Checks {
-            database: DatabaseStatus::Connected,
+            database: None,
+            warmup: None,
+            search: None,
+            components: BTreeMap::new(),
         },
     }))
 }
@@ -123,6 +411,38 @@ pub async fn live() -> Result<Json<HealthResponse>> {
     }))
 }
 
+/// Startup probe endpoint
+///
+/// Returns 503 until the warmup tasks have completed, then 200 OK.
+///
+/// Kubernetes holds the liveness and readiness probes until it passes.
+#[utoipa::path(
+    get,
+    path = "/health/startup",
+    tag = "Health",
+    responses(
+        (status = 200, description = "Service has started", body = HealthResponse),
+        (status = 503, description = "Service is warming up", body = HealthResponse)
+    )
+)]
+#[allow(clippy::unused_async)]
+pub async fn startup() -> Result<Json<HealthResponse>> {
+    Ok(Json(HealthResponse {
+        status: HealthStatus::Healthy,
+    }))
+}
+
+/// Check database connectivity by pinging the connection.
+///
+/// Works for every `DatabaseMode`: in-memory SQLite and mock connections
+/// answer the ping without a server.
+pub async fn check_database(db: &DatabaseConnection) -> DatabaseStatus {
+    match db.ping().await {
+        Ok(()) => DatabaseStatus::Connected,
+        Err(e) => DatabaseStatus::Error(e.to_string()),
+    }
+}
+
 pub struct HealthController;
 
 impl HealthController {
@@ -136,6 +456,112 @@ impl HealthController {
         ready().await
     }
 
+    /// Readiness check against a live database connection
+    ///
+    /// Returns 503 Service Unavailable if the database doesn't answer.
+    pub async fn ready_with_database(
+        db: &DatabaseConnection,
+    ) -> (StatusCode, Json<DetailedHealthResponse>) {
+        let database = check_database(db).await;
+        let (code, status) = match database {
+            DatabaseStatus::Connected => (StatusCode::OK, HealthStatus::Healthy),
+            _ => (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Unhealthy),
+        };
+        (
+            code,
+            Json(DetailedHealthResponse {
+                status,
+                checks: Checks {
+                    database: Some(database),
+                    warmup: None,
+                    search: None,
+                    components: BTreeMap::new(),
+                },
+            }),
+        )
+    }
+
+    /// Readiness check of the database (if any) and the warmup tasks
+    ///
+    /// Returns 503 Service Unavailable until warmup completed.
+    pub async fn ready_with_warmup(
+        db: Option<&DatabaseConnection>,
+        warmup: &WarmupProgress,
+    ) -> (StatusCode, Json<DetailedHealthResponse>) {
+        let (code, Json(mut response)) = match db {
+            Some(db) => Self::ready_with_database(db).await,
+            None => (
+                StatusCode::OK,
+                Json(DetailedHealthResponse {
+                    status: HealthStatus::Healthy,
+                    checks: Checks {
+                        database: None,
+                        warmup: None,
+                        search: None,
+                        components: BTreeMap::new(),
+                    },
+                }),
+            ),
+        };
+        let warmup = WarmupStatus::from(warmup);
+        response.checks.warmup = Some(warmup.clone());
+        if warmup == WarmupStatus::Pending {
+            response.status = HealthStatus::Unhealthy;
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+        }
+        (code, Json(response))
+    }
+
+    /// Add the status of the search cluster to a readiness check
+    ///
+    /// Returns 503 Service Unavailable if the cluster is unreachable or red.
+    pub fn with_search(
+        (code, Json(mut response)): (StatusCode, Json<DetailedHealthResponse>),
+        search: DatabaseStatus,
+    ) -> (StatusCode, Json<DetailedHealthResponse>) {
+        let connected = matches!(search, DatabaseStatus::Connected);
+        response.checks.search = Some(search);
+        if !connected {
+            response.status = HealthStatus::Unhealthy;
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+        }
+        (code, Json(response))
+    }
+
+    /// Add the status of the registered components to a readiness check
+    ///
+    /// Returns 503 Service Unavailable if any component is unhealthy.
+    pub fn with_components(
+        (code, Json(mut response)): (StatusCode, Json<DetailedHealthResponse>),
+        components: BTreeMap<String, ComponentStatus>,
+    ) -> (StatusCode, Json<DetailedHealthResponse>) {
+        let healthy = components.values().all(ComponentStatus::is_healthy);
+        response.checks.components = components;
+        if !healthy {
+            response.status = HealthStatus::Unhealthy;
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(response));
+        }
+        (code, Json(response))
+    }
+
+    /// Startup check: 503 until the warmup tasks completed
+    pub fn startup_with_warmup(warmup: &WarmupProgress) -> (StatusCode, Json<HealthResponse>) {
+        match WarmupStatus::from(warmup) {
+            WarmupStatus::Completed => (
+                StatusCode::OK,
+                Json(HealthResponse {
+                    status: HealthStatus::Healthy,
+                }),
+            ),
+            WarmupStatus::Pending => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(HealthResponse {
+                    status: HealthStatus::Unhealthy,
+                }),
+            ),
+        }
+    }
+
     /// Wrapper for liveness check
     pub async fn live() -> Result<Json<HealthResponse>> {
         live().await
@@ -176,6 +602,10 @@ impl HealthController {
                 <__path_live as Path>::path().to_string(),
                 <__path_live as Path>::operation(),
             );
+            register(
+                <__path_startup as Path>::path().to_string(),
+                <__path_startup as Path>::operation(),
+            );
         }
     }
 
@@ -197,6 +627,12 @@ impl HealthController {
         components
             .schemas
             .insert("DatabaseStatus".to_string(), DatabaseStatus::schema());
+        components
+            .schemas
+            .insert("WarmupStatus".to_string(), WarmupStatus::schema());
+        components
+            .schemas
+            .insert("ComponentStatus".to_string(), ComponentStatus::schema());
     }
 }
 
@@ -218,7 +654,10 @@ mod tests {
         let response = DetailedHealthResponse {
             status: HealthStatus::Healthy,
             checks: Checks {
-                database: DatabaseStatus::Connected,
+                database: Some(DatabaseStatus::Connected),
+                warmup: None,
+                search: None,
+                components: BTreeMap::new(),
             },
         };
         let json = serde_json::to_string(&response).unwrap();
@@ -228,10 +667,117 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "mock-db")]
+    #[tokio::test]
+    async fn test_ready_with_mock_database() {
+        let db = sea_orm::MockDatabase::new(sea_orm::DbBackend::Postgres).into_connection();
+        let (code, Json(response)) = HealthController::ready_with_database(&db).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(response.status, HealthStatus::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_until_warmup_completes() {
+        let mut warmup = crate::warmup::Warmup::new();
+        warmup.add("cache", || async { Ok(()) });
+        let progress = warmup.progress();
+
+        let (code, Json(response)) = HealthController::ready_with_warmup(None, &progress).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.checks.warmup, Some(WarmupStatus::Pending));
+        let (code, _) = HealthController::startup_with_warmup(&progress);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+
+        warmup.run().await;
+        let (code, Json(response)) = HealthController::ready_with_warmup(None, &progress).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(response.checks.warmup, Some(WarmupStatus::Completed));
+        let (code, _) = HealthController::startup_with_warmup(&progress);
+        assert_eq!(code, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_search_cluster_down_not_ready() {
+        let ready = (
+            StatusCode::OK,
+            Json(DetailedHealthResponse {
+                status: HealthStatus::Healthy,
+                checks: Checks {
+                    database: Some(DatabaseStatus::Connected),
+                    warmup: None,
+                    search: None,
+                    components: BTreeMap::new(),
+                },
+            }),
+        );
+        let (code, Json(response)) = HealthController::with_search(
+            ready,
+            DatabaseStatus::Error("search cluster status is red".to_string()),
+        );
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.status, HealthStatus::Unhealthy);
+        assert!(response.checks.search.is_some());
+    }
+
     #[test]
     fn test_database_status_error_serialization() {
         let status = DatabaseStatus::Error("connection refused".to_string());
         let json = serde_json::to_string(&status).unwrap();
         assert_eq!(json, r#"{"status":"error","message":"connection refused"}"#);
     }
+
+    struct Fixed(ComponentStatus);
+
+    #[async_trait]
+    impl HealthCheck for Fixed {
+        async fn check(&self) -> ComponentStatus {
+            self.0.clone()
+        }
+    }
+
+    struct Hangs;
+
+    #[async_trait]
+    impl HealthCheck for Hangs {
+        async fn check(&self) -> ComponentStatus {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checks_run_with_timeouts() {
+        let checks = HealthChecks::new()
+            .timeout(Duration::from_secs(5))
+            .add("cache", Fixed(ComponentStatus::healthy()))
+            .add_with_timeout("billing", Hangs, Duration::from_millis(20))
+            .add("queue", Fixed(ComponentStatus::unhealthy("connection refused")));
+        assert_eq!(checks.names(), ["cache", "billing", "queue"]);
+
+        let started = Instant::now();
+        let statuses = checks.run().await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(statuses["cache"].is_healthy());
+        assert_eq!(statuses["billing"].error.as_deref(), Some("timed out after 20ms"));
+        assert_eq!(statuses["queue"].error.as_deref(), Some("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_component_not_ready() {
+        let warmup = crate::warmup::Warmup::new();
+        let progress = warmup.progress();
+        warmup.run().await;
+        let ready = HealthController::ready_with_warmup(None, &progress).await;
+        assert!(ready.1.checks.database.is_none());
+
+        let healthy = HealthChecks::new().add("cache", Fixed(ComponentStatus::healthy()));
+        let (code, _) = HealthController::with_components(ready.clone(), healthy.run().await);
+        assert_eq!(code, StatusCode::OK);
+
+        let down = healthy.add("cache", Fixed(ComponentStatus::unhealthy("down")));
+        let (code, Json(response)) = HealthController::with_components(ready, down.run().await);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.status, HealthStatus::Unhealthy);
+        let json = serde_json::to_value(&response.checks).unwrap();
+        assert_eq!(json["components"]["cache"]["error"], "down");
+    }
 }
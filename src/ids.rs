@@ -0,0 +1,281 @@
+//! Time-ordered identifiers.
+//!
+//! Random (v4) UUIDs scatter inserts across the primary key index and say
+//! nothing about when a row was created. Two sortable alternatives:
+//!
+//! - `uuid_v7()` - A UUIDv7: 48-bit Unix milliseconds then random bits. Fits
+//!   the existing `uuid` columns and sorts by creation time;
+//!   `created_at(&id)` reads the time back
+//! - `SnowflakeGenerator` - 64-bit `SnowflakeId`s (41-bit milliseconds since
+//!   `SNOWFLAKE_EPOCH`, 10-bit node ID, 12-bit sequence), for `BIGINT` keys.
+//!   The node ID comes from config and must be unique per replica
+//!
+//! ```toml
+//! [snowflake]
+//! node_id = 7
+//! ```
+//!
+//! ```ignore
+//! let ids = SnowflakeGenerator::new(config.snowflake.node_id)?;
+//!
+//! orders::ActiveModel {
+//!     id: Set(ids.next_id()),
+//!     reference: Set(ids::uuid_v7()),
+//!     ..Default::default()
+//! }
+//! ```
+//!
+//! `SnowflakeId` is a sea_orm value type stored as `BIGINT`, and is
+//! serialized as a string: JavaScript numbers lose precision past 2^53.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use eywa_errors::AppError;
+use sea_orm::DeriveValueType;
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::Result;
+
+/// Epoch of the Snowflake timestamps: 2024-01-01T00:00:00Z, in Unix milliseconds.
+pub const SNOWFLAKE_EPOCH: i64 = 1_704_067_200_000;
+
+/// Largest node ID (10 bits).
+pub const MAX_NODE_ID: u16 = (1 << NODE_BITS) - 1;
+
+const NODE_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+const MAX_SEQUENCE: u16 = (1 << SEQUENCE_BITS) - 1;
+
+/// A new UUIDv7 for the current time.
+pub fn uuid_v7() -> Uuid {
+    Uuid::now_v7()
+}
+
+/// A new UUIDv7 for `time`, e.g. to backfill rows in creation order.
+pub fn uuid_v7_at(time: DateTime<Utc>) -> Uuid {
+    let timestamp = uuid::Timestamp::from_unix(
+        uuid::NoContext,
+        time.timestamp().max(0) as u64,
+        time.timestamp_subsec_nanos(),
+    );
+    Uuid::new_v7(timestamp)
+}
+
+/// Creation time of a UUIDv7 (or v1/v6), to the millisecond.
+///
+/// `None` for UUIDs without a timestamp, such as v4.
+pub fn created_at(id: &Uuid) -> Option<DateTime<Utc>> {
+    let (seconds, nanos) = id.get_timestamp()?.to_unix();
+    DateTime::from_timestamp(seconds as i64, nanos)
+}
+
+/// A 64-bit time-ordered ID made by `SnowflakeGenerator`.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+    DeriveValueType,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct SnowflakeId(pub i64);
+
+impl SnowflakeId {
+    /// Creation time, to the millisecond.
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis((self.0 >> (NODE_BITS + SEQUENCE_BITS)) + SNOWFLAKE_EPOCH)
+    }
+
+    /// Node ID of the generator that made it.
+    pub fn node_id(&self) -> u16 {
+        ((self.0 >> SEQUENCE_BITS) & i64::from(MAX_NODE_ID)) as u16
+    }
+}
+
+impl fmt::Display for SnowflakeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for SnowflakeId {
+    type Err = AppError;
+
+    fn from_str(id: &str) -> std::result::Result<Self, Self::Err> {
+        id.parse()
+            .map(Self)
+            .map_err(|_| AppError::BadRequest(format!("Invalid ID '{id}'")))
+    }
+}
+
+impl TryFrom<String> for SnowflakeId {
+    type Error = AppError;
+
+    fn try_from(id: String) -> std::result::Result<Self, Self::Error> {
+        id.parse()
+    }
+}
+
+impl From<SnowflakeId> for String {
+    fn from(id: SnowflakeId) -> Self {
+        id.to_string()
+    }
+}
+
+impl PartialSchema for SnowflakeId {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("Time-ordered 64-bit ID, as a decimal string"))
+            .pattern(Some("^[0-9]{1,19}$"))
+            .examples(["1234567890123456789"])
+            .into()
+    }
+}
+
+impl ToSchema for SnowflakeId {}
+
+/// Snowflake settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnowflakeSettings {
+    /// Unique per replica, 0 to 1023, e.g. the StatefulSet pod ordinal
+    #[serde(default)]
+    pub node_id: u16,
+}
+
+#[derive(Debug)]
+struct Sequence {
+    millis: i64,
+    next: u16,
+}
+
+/// Generates `SnowflakeId`s for one node.
+///
+/// Up to 4096 IDs per millisecond; past that, IDs borrow the next
+/// millisecond instead of waiting for it. If the clock goes backwards, IDs
+/// keep the last timestamp until it catches up, so they never repeat or go
+/// down.
+#[derive(Debug, Clone)]
+pub struct SnowflakeGenerator {
+    node_id: u16,
+    clock: SharedClock,
+    sequence: Arc<Mutex<Sequence>>,
+}
+
+impl SnowflakeGenerator {
+    /// A generator for `node_id`, at most `MAX_NODE_ID`.
+    pub fn new(node_id: u16) -> Result<Self> {
+        if node_id > MAX_NODE_ID {
+            return Err(AppError::InternalServerError(format!(
+                "Snowflake node ID {node_id} is above {MAX_NODE_ID}"
+            )));
+        }
+        Ok(Self {
+            node_id,
+            clock: system_clock(),
+            sequence: Arc::new(Mutex::new(Sequence { millis: 0, next: 0 })),
+        })
+    }
+
+    /// A generator for the configured node.
+    pub fn from_settings(settings: &SnowflakeSettings) -> Result<Self> {
+        Self::new(settings.node_id)
+    }
+
+    /// Read the time from `clock` instead of the system's.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn millis(&self) -> i64 {
+        self.clock.now().timestamp_millis() - SNOWFLAKE_EPOCH
+    }
+
+    /// A new ID, greater than every ID made before by this generator.
+    pub fn next_id(&self) -> SnowflakeId {
+        let now = self.millis();
+        let mut sequence = self.sequence.lock().unwrap();
+        if now > sequence.millis {
+            sequence.millis = now;
+            sequence.next = 0;
+        } else if sequence.next > MAX_SEQUENCE {
+            // Sequence exhausted for this millisecond: never block the caller
+            sequence.millis += 1;
+            sequence.next = 0;
+        }
+        let id = (sequence.millis << (NODE_BITS + SEQUENCE_BITS))
+            | (i64::from(self.node_id) << SEQUENCE_BITS)
+            | i64::from(sequence.next);
+        sequence.next += 1;
+        SnowflakeId(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+
+    #[test]
+    fn test_uuid_v7_is_time_ordered() {
+        let time = DateTime::parse_from_rfc3339("2024-05-02T09:14:03.512Z").unwrap().to_utc();
+        let earlier = uuid_v7_at(time);
+        let later = uuid_v7_at(time + chrono::Duration::milliseconds(1));
+
+        assert_eq!(earlier.get_version_num(), 7);
+        assert!(earlier < later);
+        assert_eq!(created_at(&earlier), Some(time));
+        assert_eq!(created_at(&Uuid::new_v4()), None);
+    }
+
+    #[test]
+    fn test_snowflake_ids() {
+        let clock = TestClock::at(DateTime::from_timestamp_millis(SNOWFLAKE_EPOCH + 5).unwrap());
+        let ids = SnowflakeGenerator::new(7).unwrap().clock(Arc::new(clock.clone()));
+
+        let first = ids.next_id();
+        let second = ids.next_id();
+        assert_eq!(first.0, (5 << 22) | (7 << 12));
+        assert_eq!(second.0, first.0 + 1);
+        assert_eq!(first.node_id(), 7);
+        assert_eq!(first.created_at(), Some(clock.now()));
+
+        // Never goes down when the clock does
+        clock.set(clock.now() - chrono::Duration::seconds(1));
+        assert!(ids.next_id() > second);
+
+        assert!(SnowflakeGenerator::new(MAX_NODE_ID + 1).is_err());
+    }
+
+    #[test]
+    fn test_snowflake_exhausted_sequence_never_blocks() {
+        let clock = TestClock::at(DateTime::from_timestamp_millis(SNOWFLAKE_EPOCH + 5).unwrap());
+        let ids = SnowflakeGenerator::new(7).unwrap().clock(Arc::new(clock.clone()));
+
+        // The clock is frozen: the 4097th ID moves on to the next millisecond
+        let generated: Vec<SnowflakeId> = (0..4097).map(|_| ids.next_id()).collect();
+        assert!(generated.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(generated[4095].created_at(), Some(clock.now()));
+        let borrowed = clock.now() + chrono::Duration::milliseconds(1);
+        assert_eq!(generated[4096].created_at(), Some(borrowed));
+
+        // Until the clock catches up, IDs keep counting from there
+        assert!(ids.next_id() > generated[4096]);
+        clock.advance(std::time::Duration::from_millis(5));
+        assert_eq!(ids.next_id().created_at(), Some(clock.now()));
+    }
+
+    #[test]
+    fn test_snowflake_serialized_as_string() {
+        let id = SnowflakeId(1_234_567_890_123_456_789);
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, "\"1234567890123456789\"");
+        assert_eq!(serde_json::from_str::<SnowflakeId>(&json).unwrap(), id);
+        assert!(serde_json::from_str::<SnowflakeId>("\"12ab\"").is_err());
+    }
+}
@@ -0,0 +1,697 @@
+//! Idempotent message consumers.
+//!
+//! Kafka and NATS deliver at least once: after a consumer crash or a
+//! rebalance, messages that were already handled come again. `Inbox` records
+//! the IDs of processed messages per consumer and skips redeliveries, so side
+//! effects (emails, charges, counters) are applied once:
+//!
+//! ```ignore
+//! let inbox = Inbox::new("billing.invoice-paid", DatabaseInbox::new(db.clone()))
+//!     .ttl(Duration::from_secs(7 * 24 * 3600));
+//! inbox.spawn_cleanup(Duration::from_secs(3600));
+//!
+//! // In the consumer loop
+//! match inbox.process(&message.id, || handle_invoice_paid(&message)).await? {
+//!     Processed::Applied(()) => consumer.ack(&message).await?,
+//!     Processed::Duplicate => consumer.ack(&message).await?,
+//!     Processed::InProgress => {} // Left unacked, the broker redelivers it
+//! }
+//! ```
+//!
+//! A message is leased before its handler runs and only recorded as
+//! processed once the handler succeeds. A failing handler releases the
+//! lease, so the redelivery is processed again; if the consumer crashes or
+//! drops the future, the lease expires after `lease` and the next
+//! redelivery runs the handler. Redeliveries arriving while the lease is
+//! held answer `InProgress` and must not be acked. The lease must exceed
+//! the handler's run time, and `ttl` the broker's redelivery window.
+//!
+//! Stores: `DatabaseInbox` (the `eywa_inbox` table, Postgres or SQLite),
+//! `RedisInbox` (`redis` feature, keys expiring on their own) and
+//! `MemoryInbox` for tests. Each store has a `clock` method to expire
+//! records by a `TestClock` instead of the system's.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use eywa_errors::AppError;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Schema, Set,
+};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::Result;
+
+/// Default time processed message IDs are remembered.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Default time a message is held while its handler runs.
+const DEFAULT_LEASE: Duration = Duration::from_secs(5 * 60);
+
+/// Outcome of `InboxStore::claim`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// The message is leased to the caller
+    Claimed,
+    /// Another delivery of the message holds an unexpired lease
+    InProgress,
+    /// The message was already processed
+    Done,
+}
+
+/// Storage of processed message IDs.
+#[async_trait]
+pub trait InboxStore: Send + Sync + 'static {
+    /// Lease `message_id` for `consumer` until `lease` passes, unless it is
+    /// processed or leased already.
+    async fn claim(&self, consumer: &str, message_id: &str, lease: Duration) -> Result<Claim>;
+
+    /// Record a message as processed until `ttl` passes.
+    async fn complete(&self, consumer: &str, message_id: &str, ttl: Duration) -> Result<()>;
+
+    /// Forget a message, so its redelivery is processed again.
+    async fn release(&self, consumer: &str, message_id: &str) -> Result<()>;
+
+    /// Delete expired records, returning how many were deleted.
+    async fn purge_expired(&self) -> Result<u64>;
+}
+
+/// Outcome of `Inbox::process`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Processed<T> {
+    /// The handler ran
+    Applied(T),
+    /// The message was already processed, the handler didn't run
+    Duplicate,
+    /// Another delivery of the message is being processed, the handler
+    /// didn't run; the message must not be acked
+    InProgress,
+}
+
+/// Processed message IDs of one consumer.
+#[derive(Clone)]
+pub struct Inbox {
+    consumer: String,
+    store: Arc<dyn InboxStore>,
+    ttl: Duration,
+    lease: Duration,
+}
+
+impl std::fmt::Debug for Inbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Inbox")
+            .field("consumer", &self.consumer)
+            .field("ttl", &self.ttl)
+            .field("lease", &self.lease)
+            .finish()
+    }
+}
+
+impl Inbox {
+    /// Create the inbox of `consumer` (e.g. the consumer group and topic).
+    pub fn new(consumer: impl Into<String>, store: impl InboxStore) -> Self {
+        Self {
+            consumer: consumer.into(),
+            store: Arc::new(store),
+            ttl: DEFAULT_TTL,
+            lease: DEFAULT_LEASE,
+        }
+    }
+
+    /// Remember processed messages for `ttl` (one day by default).
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Hold a message for `lease` while its handler runs (five minutes by
+    /// default).
+    pub fn lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Run `handler` unless `message_id` was already processed or is being
+    /// processed.
+    pub async fn process<F, Fut, T>(&self, message_id: &str, handler: F) -> Result<Processed<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        match self.store.claim(&self.consumer, message_id, self.lease).await? {
+            Claim::Claimed => {}
+            Claim::InProgress => {
+                tracing::debug!(consumer = %self.consumer, message_id, "message in progress");
+                return Ok(Processed::InProgress);
+            }
+            Claim::Done => {
+                tracing::debug!(
+                    consumer = %self.consumer,
+                    message_id,
+                    "skipping duplicate message"
+                );
+                metrics::counter!(
+                    "eywa_inbox_duplicates_total",
+                    "consumer" => self.consumer.clone()
+                )
+                .increment(1);
+                return Ok(Processed::Duplicate);
+            }
+        }
+
+        match handler().await {
+            Ok(value) => {
+                if let Err(e) = self.store.complete(&self.consumer, message_id, self.ttl).await {
+                    tracing::error!(
+                        consumer = %self.consumer,
+                        message_id,
+                        error = ?e,
+                        "failed to record message, its redelivery will be processed again"
+                    );
+                }
+                Ok(Processed::Applied(value))
+            }
+            Err(e) => {
+                if let Err(release) = self.store.release(&self.consumer, message_id).await {
+                    tracing::error!(
+                        consumer = %self.consumer,
+                        message_id,
+                        error = ?release,
+                        "failed to release message, its redelivery waits for the lease to expire"
+                    );
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Purge expired records every `interval` in the background.
+    pub fn spawn_cleanup(&self, interval: Duration) {
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                match store.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::debug!(purged, "purged expired inbox records"),
+                    Err(e) => tracing::warn!(error = ?e, "inbox cleanup failed"),
+                }
+            }
+        });
+    }
+}
+
+/// In-memory store, for tests and single-instance consumers.
+#[derive(Debug)]
+pub struct MemoryInbox {
+    /// Expiry of each record, and whether the message was processed
+    messages: Mutex<HashMap<(String, String), (Instant, bool)>>,
+    clock: SharedClock,
+}
+
+impl Default for MemoryInbox {
+    fn default() -> Self {
+        Self {
+            messages: Mutex::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+}
+
+impl MemoryInbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expire records by `clock` instead of the system's.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+#[async_trait]
+impl InboxStore for MemoryInbox {
+    async fn claim(&self, consumer: &str, message_id: &str, lease: Duration) -> Result<Claim> {
+        let mut messages = self.messages.lock().unwrap();
+        let now = self.clock.instant();
+        let key = (consumer.to_string(), message_id.to_string());
+        match messages.get(&key) {
+            Some((expires, true)) if *expires > now => return Ok(Claim::Done),
+            Some((expires, false)) if *expires > now => return Ok(Claim::InProgress),
+            _ => {}
+        }
+        messages.insert(key, (now + lease, false));
+        Ok(Claim::Claimed)
+    }
+
+    async fn complete(&self, consumer: &str, message_id: &str, ttl: Duration) -> Result<()> {
+        let key = (consumer.to_string(), message_id.to_string());
+        let expires = self.clock.instant() + ttl;
+        self.messages.lock().unwrap().insert(key, (expires, true));
+        Ok(())
+    }
+
+    async fn release(&self, consumer: &str, message_id: &str) -> Result<()> {
+        let key = (consumer.to_string(), message_id.to_string());
+        self.messages.lock().unwrap().remove(&key);
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64> {
+        let mut messages = self.messages.lock().unwrap();
+        let before = messages.len();
+        let now = self.clock.instant();
+        messages.retain(|_, (expires, _)| *expires > now);
+        Ok((before - messages.len()) as u64)
+    }
+}
+
+/// sea_orm entity of the `eywa_inbox` table.
+pub mod entity {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "eywa_inbox")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub consumer: String,
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub message_id: String,
+        /// `None` while the message is leased
+        pub processed_at: Option<DateTimeUtc>,
+        #[sea_orm(indexed)]
+        pub expires_at: DateTimeUtc,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+fn store_error(e: DbErr) -> AppError {
+    AppError::InternalServerError(format!("Inbox store failed: {e}"))
+}
+
+/// Store in the `eywa_inbox` table.
+#[derive(Debug, Clone)]
+pub struct DatabaseInbox {
+    db: DatabaseConnection,
+    clock: SharedClock,
+}
+
+impl DatabaseInbox {
+    /// Create a store on the given connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            clock: system_clock(),
+        }
+    }
+
+    /// Expire records by `clock` instead of the system's.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Create the `eywa_inbox` table and its expiry index, if missing.
+    ///
+    /// Services with their own migrations can create the table from
+    /// `entity::Entity` instead.
+    pub async fn create_table(&self) -> Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+
+        let mut table = schema.create_table_from_entity(entity::Entity);
+        table.if_not_exists();
+        self.db.execute(backend.build(&table)).await.map_err(store_error)?;
+        for mut index in schema.create_index_from_entity(entity::Entity) {
+            index.if_not_exists();
+            self.db.execute(backend.build(&index)).await.map_err(store_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InboxStore for DatabaseInbox {
+    async fn claim(&self, consumer: &str, message_id: &str, lease: Duration) -> Result<Claim> {
+        let now = self.clock.now();
+        let lease = chrono::Duration::from_std(lease)
+            .map_err(|e| AppError::BadRequest(format!("Invalid inbox lease: {e}")))?;
+
+        // An expired record doesn't count as processed or leased
+        entity::Entity::delete_many()
+            .filter(entity::Column::Consumer.eq(consumer))
+            .filter(entity::Column::MessageId.eq(message_id))
+            .filter(entity::Column::ExpiresAt.lte(now))
+            .exec(&self.db)
+            .await
+            .map_err(store_error)?;
+
+        let inserted = entity::Entity::insert(entity::ActiveModel {
+            consumer: Set(consumer.to_string()),
+            message_id: Set(message_id.to_string()),
+            processed_at: Set(None),
+            expires_at: Set(now + lease),
+        })
+        .on_conflict(
+            OnConflict::columns([entity::Column::Consumer, entity::Column::MessageId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await
+        .map_err(store_error)?;
+        if inserted > 0 {
+            return Ok(Claim::Claimed);
+        }
+
+        // A record deleted in between is reported as leased, for a retry
+        let record = entity::Entity::find_by_id((consumer.to_string(), message_id.to_string()))
+            .one(&self.db)
+            .await
+            .map_err(store_error)?;
+        match record {
+            Some(record) if record.processed_at.is_some() => Ok(Claim::Done),
+            _ => Ok(Claim::InProgress),
+        }
+    }
+
+    async fn complete(&self, consumer: &str, message_id: &str, ttl: Duration) -> Result<()> {
+        let now = self.clock.now();
+        let ttl = chrono::Duration::from_std(ttl)
+            .map_err(|e| AppError::BadRequest(format!("Invalid inbox TTL: {e}")))?;
+
+        // Upserted, in case the lease expired and was purged meanwhile
+        entity::Entity::insert(entity::ActiveModel {
+            consumer: Set(consumer.to_string()),
+            message_id: Set(message_id.to_string()),
+            processed_at: Set(Some(now)),
+            expires_at: Set(now + ttl),
+        })
+        .on_conflict(
+            OnConflict::columns([entity::Column::Consumer, entity::Column::MessageId])
+                .update_columns([entity::Column::ProcessedAt, entity::Column::ExpiresAt])
+                .to_owned(),
+        )
+        .exec_without_returning(&self.db)
+        .await
+        .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn release(&self, consumer: &str, message_id: &str) -> Result<()> {
+        entity::Entity::delete_many()
+            .filter(entity::Column::Consumer.eq(consumer))
+            .filter(entity::Column::MessageId.eq(message_id))
+            .exec(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self) -> Result<u64> {
+        let result = entity::Entity::delete_many()
+            .filter(entity::Column::ExpiresAt.lte(self.clock.now()))
+            .exec(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(result.rows_affected)
+    }
+}
+
+/// Leases a key unless it holds an expiry time after `ARGV[1]`: returns 1
+/// if leased, 2 if leased already and 0 if processed.
+///
+/// Values are `leased:<expiry>` or `done:<expiry>`, in milliseconds. Keys
+/// also expire in Redis, by its own clock; the stored expiry lets a
+/// `TestClock` expire them sooner. Unreadable values count as processed.
+#[cfg(feature = "redis")]
+const REDIS_CLAIM: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current then
+    local state, expires = string.match(current, '^(%a+):(%d+)$')
+    expires = expires and tonumber(expires)
+    if not expires or expires > tonumber(ARGV[1]) then
+        if state == 'leased' then
+            return 2
+        end
+        return 0
+    end
+end
+redis.call('SET', KEYS[1], 'leased:' .. ARGV[2], 'PX', ARGV[3])
+return 1
+"#;
+
+/// Store in Redis, one expiring key per message.
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisInbox {
+    redis: redis::aio::ConnectionManager,
+    prefix: String,
+    clock: SharedClock,
+}
+
+#[cfg(feature = "redis")]
+impl RedisInbox {
+    /// Create a store with keys prefixed by `eywa:inbox:`.
+    pub fn new(redis: redis::aio::ConnectionManager) -> Self {
+        Self {
+            redis,
+            prefix: "eywa:inbox:".to_string(),
+            clock: system_clock(),
+        }
+    }
+
+    /// Expire records by `clock` instead of the system's.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn key(&self, consumer: &str, message_id: &str) -> String {
+        format!("{}{consumer}:{message_id}", self.prefix)
+    }
+}
+
+/// `duration` in milliseconds, at least one as Redis requires.
+#[cfg(feature = "redis")]
+fn redis_millis(duration: Duration) -> i64 {
+    duration.as_millis().clamp(1, i64::MAX as u128) as i64
+}
+
+#[cfg(feature = "redis")]
+fn redis_error(e: redis::RedisError) -> AppError {
+    AppError::InternalServerError(format!("Inbox store failed: {e}"))
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl InboxStore for RedisInbox {
+    async fn claim(&self, consumer: &str, message_id: &str, lease: Duration) -> Result<Claim> {
+        let now = self.clock.now().timestamp_millis();
+        let lease_millis = redis_millis(lease);
+        let claimed: i64 = redis::Script::new(REDIS_CLAIM)
+            .key(self.key(consumer, message_id))
+            .arg(now)
+            .arg(now.saturating_add(lease_millis))
+            .arg(lease_millis)
+            .invoke_async(&mut self.redis.clone())
+            .await
+            .map_err(redis_error)?;
+        Ok(match claimed {
+            1 => Claim::Claimed,
+            2 => Claim::InProgress,
+            _ => Claim::Done,
+        })
+    }
+
+    async fn complete(&self, consumer: &str, message_id: &str, ttl: Duration) -> Result<()> {
+        let now = self.clock.now().timestamp_millis();
+        let ttl_millis = redis_millis(ttl);
+        redis::cmd("SET")
+            .arg(self.key(consumer, message_id))
+            .arg(format!("done:{}", now.saturating_add(ttl_millis)))
+            .arg("PX")
+            .arg(ttl_millis)
+            .query_async::<()>(&mut self.redis.clone())
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn release(&self, consumer: &str, message_id: &str) -> Result<()> {
+        redis::cmd("DEL")
+            .arg(self.key(consumer, message_id))
+            .query_async::<()>(&mut self.redis.clone())
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn purge_expired(&self) -> Result<u64> {
+        // Keys expire on their own
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn apply(inbox: &Inbox, id: &str, applied: &AtomicUsize) -> Result<Processed<()>> {
+        inbox
+            .process(id, || async {
+                applied.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_redelivery_skipped() {
+        let inbox = Inbox::new("billing", MemoryInbox::new());
+        let applied = AtomicUsize::new(0);
+
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Applied(()));
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Duplicate);
+        assert_eq!(apply(&inbox, "m-2", &applied).await.unwrap(), Processed::Applied(()));
+        assert_eq!(applied.load(Ordering::SeqCst), 2);
+
+        // Consumers have separate inboxes
+        let other = Inbox {
+            consumer: "notifications".to_string(),
+            ..inbox.clone()
+        };
+        assert_eq!(apply(&other, "m-1", &applied).await.unwrap(), Processed::Applied(()));
+    }
+
+    #[tokio::test]
+    async fn test_failed_message_processed_again() {
+        let inbox = Inbox::new("billing", MemoryInbox::new());
+
+        let failed = inbox
+            .process("m-1", || async {
+                Err::<(), _>(AppError::InternalServerError("timeout".to_string()))
+            })
+            .await;
+        assert!(failed.is_err());
+
+        let applied = AtomicUsize::new(0);
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Applied(()));
+    }
+
+    #[tokio::test]
+    async fn test_redelivery_during_processing_not_acked() {
+        let inbox = Inbox::new("billing", MemoryInbox::new());
+        let applied = AtomicUsize::new(0);
+
+        let (started, running) = tokio::sync::oneshot::channel();
+        let (finish, finished) = tokio::sync::oneshot::channel::<()>();
+        let first = inbox.process("m-1", move || async move {
+            started.send(()).unwrap();
+            finished.await.unwrap();
+            Ok(())
+        });
+        let redelivery = async {
+            running.await.unwrap();
+            let redelivered = apply(&inbox, "m-1", &applied).await.unwrap();
+            finish.send(()).unwrap();
+            redelivered
+        };
+
+        let (first, redelivered) = tokio::join!(first, redelivery);
+        assert_eq!(first.unwrap(), Processed::Applied(()));
+        assert_eq!(redelivered, Processed::InProgress);
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Duplicate);
+        assert_eq!(applied.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_message_processed_after_lease() {
+        let clock = crate::clock::TestClock::new();
+        let store = MemoryInbox::new().clock(Arc::new(clock.clone()));
+        let inbox = Inbox::new("billing", store).lease(Duration::from_secs(60));
+        let applied = AtomicUsize::new(0);
+
+        // The consumer stops while the handler runs
+        let abandoned = inbox.process("m-1", || std::future::pending::<Result<()>>());
+        assert!(tokio::time::timeout(Duration::from_millis(10), abandoned).await.is_err());
+
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::InProgress);
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Applied(()));
+        assert_eq!(apply(&inbox, "m-1", &applied).await.unwrap(), Processed::Duplicate);
+    }
+
+    #[tokio::test]
+    async fn test_records_expire() {
+        let store = MemoryInbox::new();
+        assert_eq!(store.claim("billing", "m-1", Duration::ZERO).await.unwrap(), Claim::Claimed);
+        assert_eq!(store.claim("billing", "m-1", Duration::ZERO).await.unwrap(), Claim::Claimed);
+        store.complete("billing", "m-2", Duration::from_secs(60)).await.unwrap();
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_records_expire_by_clock() {
+        let clock = crate::clock::TestClock::new();
+        let store = MemoryInbox::new().clock(Arc::new(clock.clone()));
+        let ttl = Duration::from_secs(60);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+        store.complete("billing", "m-1", ttl).await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Done);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_database_records_expire_by_clock() {
+        let clock = crate::clock::TestClock::new();
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        let store = DatabaseInbox::new(db).clock(Arc::new(clock.clone()));
+        store.create_table().await.unwrap();
+
+        let ttl = Duration::from_secs(60);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+        store.complete("billing", "m-1", ttl).await.unwrap();
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Done);
+        assert_eq!(store.purge_expired().await.unwrap(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_database_inbox() {
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        let store = DatabaseInbox::new(db);
+        store.create_table().await.unwrap();
+
+        let ttl = Duration::from_secs(60);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::InProgress);
+        assert_eq!(store.claim("notifications", "m-1", ttl).await.unwrap(), Claim::Claimed);
+
+        store.release("billing", "m-1").await.unwrap();
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+        store.complete("billing", "m-1", ttl).await.unwrap();
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Done);
+
+        assert_eq!(store.claim("billing", "m-2", Duration::ZERO).await.unwrap(), Claim::Claimed);
+        assert_eq!(store.purge_expired().await.unwrap(), 1);
+    }
+}
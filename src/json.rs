@@ -0,0 +1,299 @@
+//! Responders for large JSON payloads.
+//!
+//! `axum::Json` serializes the whole value into one buffer on the async
+//! runtime before the first byte is sent. For multi-megabyte collections:
+//! - `BigJson<T>` serializes on a blocking thread, streaming 64 KiB chunks
+//!   into the response body as they are produced
+//! - `JsonStream<S>` writes a JSON array item by item from a `Stream`, so
+//!   the collection is never held in memory at once
+//! - `JsonBytes` returns pre-serialized JSON (e.g. a cached payload) without
+//!   re-serializing it
+//! - `TimedJson<T>` is `axum::Json` reporting its serialization time to
+//!   `EywaApp::response_metrics()`, like the responders above
+//!
+//! Once streaming has started the status is already sent: a serialization
+//! error aborts the body instead of returning an error response.
+
+use std::io;
+use std::time::Instant;
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header::CONTENT_TYPE, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use eywa_errors::AppError;
+
+use crate::middleware::response_metrics::{record_serialization, SerializationTimer};
+
+/// Size of the chunks written to the response body by `BigJson`.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of ready items serialized into one body chunk by `JsonStream`.
+const STREAM_BATCH: usize = 256;
+
+fn json_response(body: Body) -> Response {
+    let mut response = Response::new(body);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// `io::Write` sink sending fixed-size chunks to the response body.
+struct ChunkWriter {
+    buf: Vec<u8>,
+    tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl ChunkWriter {
+    fn send(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx
+            .blocking_send(Ok(Bytes::from(chunk)))
+            // The client went away: stop serializing
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+impl io::Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        if self.buf.len() >= CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send()
+    }
+}
+
+/// JSON responder for large values, serialized off the async runtime.
+///
+/// # Example
+///
+/// ```ignore
+/// #[route(GET "/export")]
+/// async fn export(State(state): State<AppState>) -> Result<BigJson<Vec<Project>>> {
+///     Ok(BigJson(state.projects.all().await?))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BigJson<T>(pub T);
+
+impl<T> IntoResponse for BigJson<T>
+where
+    T: Serialize + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let (tx, rx) = mpsc::channel(4);
+        let timer = SerializationTimer::current();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkWriter {
+                buf: Vec::with_capacity(CHUNK_SIZE),
+                tx,
+            };
+            // Includes the time blocked on a slow client
+            let started = Instant::now();
+            let result = serde_json::to_writer(&mut writer, &self.0)
+                .map_err(io::Error::from)
+                .and_then(|()| io::Write::flush(&mut writer));
+            if let Some(timer) = timer {
+                timer.add(started.elapsed());
+            }
+            if let Err(e) = result {
+                tracing::error!("BigJson serialization failed: {}", e);
+                let _ = writer.tx.blocking_send(Err(e));
+            }
+        });
+
+        let chunks = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        json_response(Body::from_stream(chunks))
+    }
+}
+
+/// Streams the items of a `Stream` as a JSON array.
+///
+/// # Example
+///
+/// ```ignore
+/// #[route(GET "/events")]
+/// async fn events(State(state): State<AppState>) -> JsonStream<impl Stream<Item = Event>> {
+///     JsonStream(state.events.stream_all())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonStream<S>(pub S);
+
+impl<S, T> IntoResponse for JsonStream<S>
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        let mut first = true;
+        let timer = SerializationTimer::current();
+        let items = self.0.ready_chunks(STREAM_BATCH).map(move |items| {
+            let started = Instant::now();
+            let mut buf = Vec::new();
+            for item in items {
+                if !first {
+                    buf.push(b',');
+                }
+                first = false;
+                serde_json::to_writer(&mut buf, &item)?;
+            }
+            if let Some(timer) = &timer {
+                timer.add(started.elapsed());
+            }
+            Ok::<_, serde_json::Error>(Bytes::from(buf))
+        });
+
+        let body = stream::once(async { Ok(Bytes::from_static(b"[")) })
+            .chain(items)
+            .chain(stream::once(async { Ok(Bytes::from_static(b"]")) }));
+        json_response(Body::from_stream(body))
+    }
+}
+
+/// Pre-serialized JSON returned as is.
+///
+/// Useful for payloads that are serialized once and served many times.
+///
+/// # Example
+///
+/// ```ignore
+/// let catalog = JsonBytes::from_value(&catalog)?;  // Cache this
+/// // ...
+/// async fn get_catalog(State(state): State<AppState>) -> JsonBytes {
+///     state.catalog.clone()  // Cheap: `Bytes` is reference counted
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonBytes(pub Bytes);
+
+impl JsonBytes {
+    /// Serialize a value once.
+    pub fn from_value<T: Serialize>(value: &T) -> serde_json::Result<Self> {
+        let started = Instant::now();
+        let json = serde_json::to_vec(value);
+        record_serialization(started.elapsed());
+        json.map(|json| Self(Bytes::from(json)))
+    }
+}
+
+impl IntoResponse for JsonBytes {
+    fn into_response(self) -> Response {
+        json_response(Body::from(self.0))
+    }
+}
+
+/// `axum::Json` responder reporting its serialization time.
+///
+/// Records into `eywa_http_response_serialization_seconds` when
+/// `EywaApp::response_metrics()` is enabled.
+///
+/// # Example
+///
+/// ```ignore
+/// #[route(GET "/projects")]
+/// async fn list_projects(State(state): State<AppState>) -> Result<TimedJson<Vec<Project>>> {
+///     Ok(TimedJson(state.projects.all().await?))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimedJson<T>(pub T);
+
+impl<T: Serialize> IntoResponse for TimedJson<T> {
+    fn into_response(self) -> Response {
+        match JsonBytes::from_value(&self.0) {
+            Ok(json) => json.into_response(),
+            Err(e) => {
+                AppError::InternalServerError(format!("JSON serialization failed: {e}"))
+                    .into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Router};
+    use serde_json::{json, Value};
+
+    fn projects(count: usize) -> Vec<Value> {
+        (0..count)
+            .map(|id| json!({ "id": id, "name": format!("Project {id}") }))
+            .collect()
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route("/big", get(|| async { BigJson(projects(5_000)) }))
+                .route(
+                    "/stream",
+                    get(|| async { JsonStream(stream::iter(projects(3))) }),
+                )
+                .route(
+                    "/empty",
+                    get(|| async { JsonStream(stream::iter(Vec::<Value>::new())) }),
+                )
+                .route(
+                    "/bytes",
+                    get(|| async { JsonBytes::from_value(&projects(1)).unwrap() }),
+                )
+                .route("/timed", get(|| async { TimedJson(projects(2)) })),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_big_json_spans_several_chunks() {
+        let response = client().get("/big").send().await;
+
+        response.assert_status(StatusCode::OK);
+        assert!(response.bytes().len() > CHUNK_SIZE);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.json::<Vec<Value>>(), projects(5_000));
+    }
+
+    #[tokio::test]
+    async fn test_json_stream_writes_an_array() {
+        let client = client();
+
+        assert_eq!(
+            client.get("/stream").send().await.json::<Vec<Value>>(),
+            projects(3)
+        );
+        assert_eq!(client.get("/empty").send().await.text(), "[]");
+    }
+
+    #[tokio::test]
+    async fn test_json_bytes() {
+        let response = client().get("/bytes").send().await;
+
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.json::<Vec<Value>>(), projects(1));
+    }
+
+    #[tokio::test]
+    async fn test_timed_json() {
+        let response = client().get("/timed").send().await;
+
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.json::<Vec<Value>>(), projects(2));
+    }
+}
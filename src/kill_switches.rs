@@ -0,0 +1,461 @@
+//! Kill switches: routes disabled at runtime.
+//!
+//! A misbehaving endpoint (a runaway export, a query melting the database)
+//! can be switched off during an incident without a deploy. Routes opt in
+//! with `#[route(killable)]` or `EywaApp::killable_route`; a disabled route
+//! answers `503 Service Unavailable` with the `ROUTE_DISABLED` error code
+//! while the rest of the service keeps serving. Routes that aren't killable
+//! can never be disabled.
+//!
+//! Routes are disabled from config (applied at startup and on reload):
+//!
+//! ```toml
+//! [[kill_switches.disabled]]
+//! method = "POST"
+//! path = "/api/v1/exports"
+//! reason = "Exports overload the replica, see INC-123"
+//! ```
+//!
+//! or through the admin endpoints, mounted with `EywaApp::kill_switch_endpoints`
+//! and requiring a token verified by `EywaApp::auth` with the
+//! `kill_switches:admin` scope:
+//!
+//! - `GET /admin/kill-switches` - The killable routes and their state
+//! - `POST /admin/kill-switches/disable` - Disable a route
+//! - `POST /admin/kill-switches/enable` - Serve a route again
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{PartialSchema, ToSchema};
+
+use eywa_errors::AppError;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::middleware::route_method;
+use crate::Result;
+
+/// Scope required to call the kill switch admin endpoints.
+pub const KILL_SWITCHES_ADMIN_SCOPE: &str = "kill_switches:admin";
+
+/// Message of disabled routes without a reason.
+const DEFAULT_REASON: &str = "This endpoint is temporarily disabled";
+
+type RouteKey = (String, String);
+
+/// Key of a route; `HEAD` requests are served by, and killed with, `GET`.
+fn key(method: &str, path: &str) -> RouteKey {
+    (route_method(method), path.to_string())
+}
+
+/// A route disabled from config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DisabledRoute {
+    pub method: String,
+    /// Route template, e.g. `/api/v1/exports/{id}`
+    pub path: String,
+    /// Returned to callers as the error message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Kill switch settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KillSwitchSettings {
+    /// Routes disabled at startup
+    #[serde(default)]
+    pub disabled: Vec<DisabledRoute>,
+}
+
+/// State of a killable route.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct KillSwitch {
+    pub method: String,
+    pub path: String,
+    pub disabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Switches {
+    killable: BTreeSet<RouteKey>,
+    /// Disabled routes and their reason
+    disabled: BTreeMap<RouteKey, Option<String>>,
+}
+
+/// Shared handle on the kill switches.
+///
+/// Keep a clone to disable routes from a config reload or a signal handler.
+///
+/// # Example
+///
+/// ```ignore
+/// let switches = KillSwitches::new();
+/// switches.apply(&config.kill_switches);
+///
+/// EywaApp::new(state)
+///     .mount::<ExportsController>()
+///     .kill_switches(switches.clone())
+///     .kill_switch_endpoints()
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitches(Arc<RwLock<Switches>>);
+
+impl KillSwitches {
+    /// Create a handle without killable routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a route to be disabled.
+    pub fn mark_killable(&self, method: &str, path: &str) {
+        self.0.write().unwrap().killable.insert(key(method, path));
+    }
+
+    /// Returns `true` if no route is killable.
+    pub fn is_empty(&self) -> bool {
+        self.0.read().unwrap().killable.is_empty()
+    }
+
+    /// Returns `true` if the route can be disabled.
+    pub fn is_killable(&self, method: &str, path: &str) -> bool {
+        self.0.read().unwrap().killable.contains(&key(method, path))
+    }
+
+    /// Reject the requests to a killable route until it's enabled again.
+    pub fn disable(&self, method: &str, path: &str, reason: Option<String>) -> Result<()> {
+        let mut switches = self.0.write().unwrap();
+        let route = key(method, path);
+        if !switches.killable.contains(&route) {
+            return Err(AppError::BadRequest(format!(
+                "Route {} {} is not killable",
+                route.0, route.1
+            )));
+        }
+        tracing::warn!(method = %route.0, path = %route.1, ?reason, "route disabled");
+        switches.disabled.insert(route, reason);
+        Ok(())
+    }
+
+    /// Serve a disabled route again.
+    pub fn enable(&self, method: &str, path: &str) {
+        let route = key(method, path);
+        if self.0.write().unwrap().disabled.remove(&route).is_some() {
+            tracing::info!(method = %route.0, path = %route.1, "route enabled");
+        }
+    }
+
+    /// Replace the disabled routes with the ones of `settings`.
+    ///
+    /// Routes marked killable later (e.g. by `EywaApp`) are disabled as
+    /// soon as they are marked.
+    pub fn apply(&self, settings: &KillSwitchSettings) {
+        self.0.write().unwrap().disabled = settings
+            .disabled
+            .iter()
+            .map(|route| (key(&route.method, &route.path), route.reason.clone()))
+            .collect();
+    }
+
+    /// Disabled routes that aren't killable, and so are still served.
+    pub fn unknown(&self) -> Vec<RouteKey> {
+        let switches = self.0.read().unwrap();
+        switches
+            .disabled
+            .keys()
+            .filter(|route| !switches.killable.contains(*route))
+            .cloned()
+            .collect()
+    }
+
+    /// The reason a route is disabled, if it is.
+    fn disabled(&self, method: &str, path: &str) -> Option<String> {
+        let switches = self.0.read().unwrap();
+        let route = key(method, path);
+        if !switches.killable.contains(&route) {
+            return None;
+        }
+        switches
+            .disabled
+            .get(&route)
+            .map(|reason| reason.clone().unwrap_or_else(|| DEFAULT_REASON.to_string()))
+    }
+
+    /// The killable routes and their state.
+    pub fn switches(&self) -> Vec<KillSwitch> {
+        let switches = self.0.read().unwrap();
+        switches
+            .killable
+            .iter()
+            .map(|route| {
+                let disabled = switches.disabled.get(route);
+                KillSwitch {
+                    method: route.0.clone(),
+                    path: route.1.clone(),
+                    disabled: disabled.is_some(),
+                    reason: disabled.cloned().flatten(),
+                }
+            })
+            .collect()
+    }
+
+    /// Copy the killable routes and disabled state of another handle.
+    pub(crate) fn absorb(&self, other: &KillSwitches) {
+        let other = other.0.read().unwrap();
+        let mut switches = self.0.write().unwrap();
+        switches.killable.extend(other.killable.iter().cloned());
+        for (route, reason) in &other.disabled {
+            switches.disabled.entry(route.clone()).or_insert(reason.clone());
+        }
+    }
+}
+
+/// Middleware rejecting the requests to disabled routes.
+///
+/// Installed by `EywaApp` when a route is killable.
+pub async fn kill_switch_middleware(
+    State(switches): State<KillSwitches>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>() else {
+        return next.run(req).await;
+    };
+    match switches.disabled(req.method().as_str(), route.as_str()) {
+        Some(reason) => ErrorResponse::new(error_codes::ROUTE_DISABLED, reason)
+            .into_response_with(StatusCode::SERVICE_UNAVAILABLE),
+        None => next.run(req).await,
+    }
+}
+
+/// Change of a route's kill switch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct KillSwitchChange {
+    pub method: String,
+    /// Route template, e.g. `/api/v1/exports/{id}`
+    pub path: String,
+    /// Returned to callers as the error message when disabling
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// List kill switches
+#[utoipa::path(
+    get,
+    path = "/admin/kill-switches",
+    tag = "Kill Switches",
+    responses(
+        (status = 200, description = "Killable routes and their state", body = Vec<KillSwitch>)
+    )
+)]
+pub async fn list(State(switches): State<KillSwitches>) -> Json<Vec<KillSwitch>> {
+    Json(switches.switches())
+}
+
+/// Disable a route
+///
+/// Requests to the route are rejected with `503` and the `ROUTE_DISABLED`
+/// error code until it's enabled again or the process restarts.
+#[utoipa::path(
+    post,
+    path = "/admin/kill-switches/disable",
+    tag = "Kill Switches",
+    request_body = KillSwitchChange,
+    responses(
+        (status = 200, description = "Route disabled", body = Vec<KillSwitch>)
+    )
+)]
+pub async fn disable(
+    State(switches): State<KillSwitches>,
+    Json(change): Json<KillSwitchChange>,
+) -> Result<Json<Vec<KillSwitch>>> {
+    switches.disable(&change.method, &change.path, change.reason)?;
+    Ok(Json(switches.switches()))
+}
+
+/// Enable a route
+#[utoipa::path(
+    post,
+    path = "/admin/kill-switches/enable",
+    tag = "Kill Switches",
+    request_body = KillSwitchChange,
+    responses(
+        (status = 200, description = "Route enabled", body = Vec<KillSwitch>)
+    )
+)]
+pub async fn enable(
+    State(switches): State<KillSwitches>,
+    Json(change): Json<KillSwitchChange>,
+) -> Json<Vec<KillSwitch>> {
+    switches.enable(&change.method, &change.path);
+    Json(switches.switches())
+}
+
+pub struct KillSwitchController;
+
+impl KillSwitchController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 3] = [
+        ("GET", "/admin/kill-switches"),
+        ("POST", "/admin/kill-switches/disable"),
+        ("POST", "/admin/kill-switches/enable"),
+    ];
+
+    /// Build the kill switch admin router for the given handle.
+    pub fn router<S>(switches: KillSwitches) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/admin/kill-switches", get(list))
+            .route("/admin/kill-switches/disable", post(disable))
+            .route("/admin/kill-switches/enable", post(enable))
+            .with_state(switches)
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_list as Path>::path(),
+                <__path_list as Path>::methods(),
+                <__path_list as Path>::operation(),
+            ),
+            (
+                <__path_disable as Path>::path(),
+                <__path_disable as Path>::methods(),
+                <__path_disable as Path>::operation(),
+            ),
+            (
+                <__path_enable as Path>::path(),
+                <__path_enable as Path>::methods(),
+                <__path_enable as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components
+            .schemas
+            .insert("KillSwitch".to_string(), KillSwitch::schema());
+        components
+            .schemas
+            .insert("KillSwitchChange".to_string(), KillSwitchChange::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    fn client(switches: KillSwitches) -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route("/exports", post(|| async { "export" }))
+                .route("/health", get(|| async { "ok" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    switches.clone(),
+                    kill_switch_middleware,
+                ))
+                .merge(KillSwitchController::router(switches)),
+        )
+    }
+
+    #[test]
+    fn test_only_killable_routes_are_disabled() {
+        let switches = KillSwitches::new();
+        switches.mark_killable("post", "/exports");
+
+        assert!(switches.disable("GET", "/health", None).is_err());
+        switches.disable("POST", "/exports", None).unwrap();
+        assert_eq!(
+            switches.disabled("POST", "/exports").as_deref(),
+            Some(DEFAULT_REASON)
+        );
+        assert_eq!(switches.disabled("GET", "/health"), None);
+
+        switches.enable("POST", "/exports");
+        assert_eq!(switches.disabled("POST", "/exports"), None);
+    }
+
+    #[tokio::test]
+    async fn test_killed_get_route_rejects_head() {
+        let switches = KillSwitches::new();
+        switches.mark_killable("GET", "/health");
+        switches.disable("GET", "/health", Some("INC-7".to_string())).unwrap();
+        let client = client(switches);
+
+        let head = client.request(axum::http::Method::HEAD, "/health").send().await;
+        head.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        client.get("/health").send().await.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn test_config_applies_to_routes_marked_later() {
+        let settings: KillSwitchSettings = serde_json::from_value(serde_json::json!({
+            "disabled": [
+                { "method": "POST", "path": "/exports", "reason": "INC-123" },
+                { "method": "GET", "path": "/health" }
+            ]
+        }))
+        .unwrap();
+        let switches = KillSwitches::new();
+        switches.apply(&settings);
+        switches.mark_killable("POST", "/exports");
+
+        assert_eq!(switches.disabled("POST", "/exports").as_deref(), Some("INC-123"));
+        assert_eq!(
+            switches.unknown(),
+            vec![("GET".to_string(), "/health".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_disable_through_admin_endpoints() {
+        let switches = KillSwitches::new();
+        switches.mark_killable("POST", "/exports");
+        let client = client(switches);
+
+        let change = KillSwitchChange {
+            method: "POST".to_string(),
+            path: "/exports".to_string(),
+            reason: Some("Exports overload the replica".to_string()),
+        };
+        let response = client.post("/admin/kill-switches/disable").json(&change).send().await;
+        let listed: Vec<KillSwitch> = response.assert_status(StatusCode::OK).json();
+        assert!(listed[0].disabled);
+
+        let response = client.post("/exports").send().await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], error_codes::ROUTE_DISABLED);
+        assert_eq!(body["message"], "Exports overload the replica");
+        client.get("/health").send().await.assert_status(StatusCode::OK);
+
+        client
+            .post("/admin/kill-switches/enable")
+            .json(&change)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        client.post("/exports").send().await.assert_status(StatusCode::OK);
+    }
+}
@@ -5,13 +5,79 @@
 //! ## Features
 //!
 //! - **Automatic OpenAPI**: Routes registered via `routes!()` are automatically documented
-//! - **Scalar UI**: Interactive API documentation at `/scalar`
+//! - **Scalar UI**: Interactive API documentation at `/scalar`, with a pre-populated "try it"
 //! - **Swagger UI**: Alternative OpenAPI documentation at `/swagger` (with `swagger-ui` feature)
-//! - **Health Checks**: Kubernetes-ready liveness and readiness probes
+//! - **Admin Listener**: Operator endpoints (e.g. pprof profiling) on a separate port
+//! - **Health Checks**: Kubernetes-ready liveness, readiness and startup probes
+//! - **Warmup**: Cache and connection priming before the service reports ready
+//! - **Scheduler**: Cron jobs run once per tick through Kubernetes Leases, with admin endpoints
+//! - **Graceful Shutdown**: Requests finished and SSE/WebSocket streams drained on `SIGTERM`
 //! - **Request Context**: Correlation ID, user ID, and language propagation
+//! - **JWT Authentication**: Verified bearer tokens, with the user set on `RequestContext`
+//! - **Header Policy**: Config-driven headers forwarded downstream, reflected or stripped
+//! - **Adaptive Concurrency**: AIMD/gradient in-flight limit shedding excess load
+//! - **API Keys**: Hashed key storage with admin endpoints and `ApiKeyAuth` (with `api-keys` feature)
+//! - **Usage Analytics**: Batched per-request records shipped to ClickHouse or Kafka
+//! - **Rate Limiting**: Token buckets keyed by IP, tenant or API key, with per-tier quotas
+//! - **Maintenance Mode**: Runtime switch answering `503` with `Retry-After`
+//! - **Kill Switches**: Killable routes disabled from config or an admin endpoint during incidents
+//! - **Bulkheads**: Bounded concurrency pools isolating expensive route groups
+//! - **Request Limits**: Body and header size, timeout and concurrency limits with sane defaults
+//! - **Admission Queue**: Short bounded queueing smoothing bursts before shedding
+//! - **Canary Routing**: Alternate handlers serving a share of a route's traffic or opted-in requests
+//! - **Experiments**: Deterministic A/B variant assignment by user or tenant
+//! - **Request Deadlines**: Caller deadlines enforced as timeouts and forwarded downstream
+//! - **Upstream Errors**: Outbound failures answered as consistent 502/504/424 errors
+//! - **Request Hedging**: p99-delayed second attempts to another replica for critical outbound calls
+//! - **Strict Media Types**: `415`/`406` for `Content-Type`/`Accept` outside the documented types
+//! - **Schema Constraints**: `maxLength`/`pattern`/`minimum`/... of the spec enforced on plain `Json<T>`
+//! - **OpenTelemetry Tracing**: OTLP server spans named by route, continuing W3C `traceparent`
+//! - **HTTP Metrics**: Prometheus request count, duration and in-flight gauge by route template
+//! - **Response Metrics**: Response size and serialization time histograms per route
+//! - **Server-Timing**: Middleware, handler and custom phase durations for frontend tooling
+//! - **Route SLOs**: Per-route latency/availability objectives with burn-rate event counters
+//! - **Observability**: One-call RED metrics, request tracing and log correlation with standard labels
 //! - **Request Logging**: Structured logging compatible with Loki/Grafana
-//! - **Response Compression**: Gzip, deflate, and brotli compression
+//! - **JSON Logs**: JSON event lines with `service`, `env` and the request's correlation fields
+//! - **Tracing Setup**: Config-driven subscriber with request ids on every log line and optional tokio-console
+//! - **Webhook Subscriptions**: Consumer-managed webhook URLs with ownership verification
+//! - **Deprecation Analytics**: Per-client calls of deprecated operations and an admin usage report
+//! - **Runtime Log Level**: Admin endpoints changing the log filter without a redeploy
+//! - **Response Compression**: Gzip, deflate, and brotli compression with configurable level and filters
+//! - **Request Decompression**: Gzip/deflate request bodies with a zip bomb size limit
 //! - **API Versioning**: Automatic version prefix support (e.g., `/v1/projects`)
+//! - **Path Normalization**: Explicit trailing-slash policy and case-insensitive matching
+//! - **HEAD and OPTIONS**: `OPTIONS` answered with the `Allow` header, optionally documented
+//! - **Method Override**: `X-HTTP-Method-Override` on `POST`, audited and limited to set methods
+//! - **Localized Formats**: Decimals and dates serialized for the request's negotiated locale
+//! - **Idempotent Consumers**: Processed message IDs recorded in Postgres or Redis to skip redeliveries
+//! - **Dead Letters**: Failed messages stored with admin endpoints to inspect and re-drive them
+//! - **Notifications**: FCM/APNs push and Twilio SMS providers behind a retrying background queue
+//! - **Search**: Elasticsearch/OpenSearch client, index helpers, filter/sort translation and readiness
+//! - **Long-Running Operations**: `202 Accepted` with a documented `/operations/{id}` status resource
+//! - **Audit Columns**: `created_by`/`updated_by`/`tenant_id` filled from the request on save
+//! - **Sortable IDs**: UUIDv7 helpers and a Snowflake generator with the node ID from config
+//! - **Money**: `Money` amounts with ISO 4217 currencies, checked arithmetic and OpenAPI schema
+//! - **Validated Extractors**: `ValidatedJson`/`ValidatedQuery`/`ValidatedPath` with structured errors
+//! - **Response Envelope**: Configurable success envelope applied to bodies and schemas
+//! - **Error Documentation**: Standard `AppError` responses documented on every operation
+//! - **Error Codes**: Stable error code catalog served at `/errors` and documented as an enum
+//! - **Reusable Responses**: Shared response components applied as defaults to every operation
+//! - **Operation IDs**: Configurable, collision-free `operationId` naming
+//! - **Tag Groups**: Explicit tag ordering and `x-tagGroups` for the docs sidebar
+//! - **State Builder**: Database, Redis, HTTP client, JWT and metrics wired from config
+//! - **Dependency Injection**: Typed services provided once and extracted with `Inject<T>`
+//! - **Clock**: Injectable time source with a `TestClock` for rate limits, TTLs and jobs
+//! - **Reloadable Settings**: Rate limits, body limits, CORS origins and deadlines behind `Watch<T>`
+//! - **Table Migrations**: `sea-orm-migration` definitions of the inbox, dead letter and key tables
+//! - **Typed Headers**: `TypedHeader<T>` parsing custom headers, documented as parameters
+//! - **Large JSON Responses**: Streaming `BigJson`/`JsonStream` and pre-serialized `JsonBytes`
+//! - **Static Assets**: Content-hashed file names, immutable caching, a manifest and SPA fallback
+//! - **Payload Encryption**: `Encrypted<T>` JWE request and response bodies (with `jwe` feature)
+//! - **TLS Termination**: `serve_tls` with rustls, PEM files or strings, hot reload and HTTP/2
+//! - **Startup Summary**: One structured event with the service, features, middleware and docs URLs
+//! - **Test Client**: In-process client running the full middleware stack
+//! - **Client Generation**: Typed `reqwest` clients generated from the OpenAPI spec
 //! - **Controller Pattern**: Optional `#[controller]` macro for grouping routes
 //! - **EYWA Ecosystem**: Integrated auth, errors, pagination, and more
 //!
@@ -38,21 +104,187 @@
 //! ```
 
 // Re-export specific modules
+pub mod admin;
+pub mod analytics;
+#[cfg(feature = "api-keys")]
+pub mod api_keys;
 mod app;
+pub mod assets;
+pub mod audit;
+pub mod authorization;
+pub mod capture;
+pub mod client;
+pub mod clock;
+pub mod codegen;
+pub mod database;
+pub mod dead_letters;
+pub mod deprecations;
+pub mod di;
+pub mod envelope;
+pub mod error_codes;
+pub mod error_responses;
+pub mod experiments;
+pub mod fixtures;
+pub mod ids;
+pub mod inbox;
+pub mod json;
+#[cfg(feature = "jwe")]
+pub mod jwe;
+pub mod kill_switches;
+pub mod locale;
+pub mod log_level;
+pub mod logging;
+#[cfg(feature = "migrations")]
+pub mod migrations;
 // pub mod config; // API change: config is now in eywa-config
 mod health;
 pub mod middleware;
+pub mod mock;
+pub mod money;
+pub mod observability;
+pub mod notifications;
+pub mod operation_ids;
+pub mod operations;
+pub mod privacy;
+pub mod reload;
+pub mod responses;
+pub mod search;
+#[cfg(feature = "scaffold")]
+pub mod scaffold;
+pub mod scalar;
+pub mod scheduler;
+pub mod shutdown;
+pub mod spec;
+pub mod startup;
+pub mod state;
+pub mod tags;
+pub mod telemetry;
+pub mod testing;
+#[cfg(feature = "tls")]
+pub mod tls;
 mod traits;
+pub mod typed_header;
+pub mod validation;
+pub mod versioning;
+pub mod visibility;
+pub mod warmup;
+pub mod webhooks;
 
 pub use app::legacy::LegacyEywaApp;
 pub use app::EywaApp;
 pub use traits::*;
 
+// Re-export authorization types
+pub use authorization::{PolicyDecision, PolicyEnforcement, PolicyEngine, PolicyRequest};
+
+// Re-export database modes
+pub use database::{DatabaseMode, DatabaseSettings};
+
 // Re-export health check types
-pub use health::{HealthController, HealthStatus};
+pub use health::{
+    ComponentStatus, DatabaseStatus, HealthCheck, HealthChecks, HealthController, HealthStatus,
+    HttpCheck,
+};
+
+// Re-export privacy types
+pub use privacy::{DataSubjectHandler, PrivacyRegistry};
+
+// Re-export testing types
+pub use testing::{TestClient, TestResponse};
+
+// Re-export validating extractors
+pub use validation::{
+    FieldError, ValidatedJson, ValidatedPath, ValidatedQuery, ValidationErrorResponse,
+};
+
+// Re-export the context-aware outbound client
+pub use client::{Hedging, OutboundClient, UpstreamError};
+
+// Re-export state builder types
+pub use state::{AppStateBuilder, ResourceSettings, Resources};
+
+// Re-export dependency injection types
+pub use di::{Container, Inject, Injectable};
+
+// Re-export clock types
+pub use clock::{Clock, SharedClock, SystemClock, TestClock};
+
+// Re-export reloadable setting handle
+pub use reload::Watch;
+
+// Re-export Scalar "try it" configuration
+pub use scalar::ScalarConfig;
+
+// Re-export typed header extractor
+pub use typed_header::{CustomHeader, TypedHeader};
+
+// Re-export experiment types
+pub use experiments::{Assignments, ExperimentSettings, Experiments};
+
+// Re-export locale-aware formatted values
+pub use locale::{LocalizedDate, LocalizedDateTime, LocalizedDecimal};
+
+// Re-export dead letter types
+pub use dead_letters::{DeadLetter, DeadLetterStore, NewDeadLetter};
+
+// Re-export deprecation analytics types
+pub use deprecations::{DeprecationReport, Deprecations};
+
+// Re-export webhook subscription types
+pub use webhooks::{WebhookSubscriber, WebhookSubscriptions};
+
+// Re-export static asset types
+pub use assets::StaticAssets;
+
+// Re-export kill switch types
+pub use kill_switches::{KillSwitchSettings, KillSwitches};
+
+// Re-export long-running operation types
+pub use operations::{Operation, OperationAccepted, Operations};
+
+// Re-export graceful shutdown types
+pub use shutdown::{close_for_shutdown, Shutdown};
+
+// Re-export cron scheduling types
+pub use scheduler::{Job, Jobs, KubernetesLeases, LeaseStore, MemoryLeases, Scheduler};
+
+// Re-export money types
+pub use money::{Currency, Money, MoneyError};
+
+// Re-export time-ordered ID types
+pub use ids::{SnowflakeGenerator, SnowflakeId, SnowflakeSettings};
+
+// Re-export search types
+pub use search::{SearchClient, SearchQuery, SearchSettings};
+
+// Re-export notification types
+pub use notifications::{Notification, Notifier, PushMessage};
+
+// Re-export large JSON responders
+pub use json::{BigJson, JsonBytes, JsonStream, TimedJson};
 
 // Re-export middleware types
 pub use middleware::{request_context_middleware_fn, RequestContext};
+pub use middleware::auth::{JwtConfig, JwtVerifier, VerifiedClaims};
+pub use middleware::method_override::MethodOverride;
+pub use middleware::content_types::ContentTypePolicy;
+pub use middleware::limits::LimitsConfig;
+pub use middleware::methods::AutoMethods;
+#[cfg(feature = "otel")]
+pub use middleware::otel::{inject_context, OtelConfig};
+pub use middleware::paths::{PathPolicy, TrailingSlash};
+pub use middleware::propagation::HeaderPolicy;
+pub use middleware::scopes::{GrantedScopes, ScopeEnforcement, ScopeRegistry};
+pub use middleware::slo::Slo;
+pub use middleware::timing::ServerTimings;
+
+// Re-export TLS configuration when feature is enabled
+#[cfg(feature = "tls")]
+pub use tls::TlsConfig;
+
+// Re-export payload encryption when feature is enabled
+#[cfg(feature = "jwe")]
+pub use jwe::{Encrypted, JweConfig, JweKeys};
 
 // Re-export Swagger UI when feature is enabled
 #[cfg(feature = "swagger-ui")]
@@ -67,7 +299,7 @@ pub use eywa_axum_macros::{controller, openapi_for, route};
 // Re-export common dependencies
 pub use axum::{
     self,
-    extract::{Extension, Json, Path, Query, Request, State},
+    extract::{Extension, FromRef, Json, Path, Query, Request, State},
     response::{IntoResponse, Response},
     routing::{delete, get, patch, post, put},
     Router,
@@ -150,18 +382,22 @@ pub mod prelude {
         ApiCollectionResult,
         ApiResult,
         AppError,
+        Assignments,
         CollectionResponse,
         Deserialize,
         Extension,
         EywaApp,
+        FromRef,
         HateoasResponse,
         HealthController,
         HealthStatus,
+        Inject,
         IntoParams,
         IntoResponse,
         Json,
         LegacyEywaApp,
         Link,
+        Money,
         // OpenAPI related
         OpenApi,
         // OpenApiRouter, <- Removed
@@ -174,9 +410,14 @@ pub mod prelude {
         Result,
         Router,
         Serialize,
+        ServerTimings,
         State,
         ToSchema,
+        TypedHeader,
         UserId,
+        ValidatedJson,
+        ValidatedPath,
+        ValidatedQuery,
     };
     pub use crate::traits::{IntoRouter, OpenApiPath};
     pub use eywa_config::EywaConfig;
@@ -0,0 +1,329 @@
+//! Locale-aware formatting of decimals and dates.
+//!
+//! Customer-facing endpoints returning amounts and dates for display can use
+//! `LocalizedDecimal`, `LocalizedDate` and `LocalizedDateTime` instead of the
+//! raw types. They serialize as strings formatted for the request's locale:
+//!
+//! | Locale  | Decimal      | Date         |
+//! |---------|--------------|--------------|
+//! | `en-US` | `1,234.56`   | `03/31/2025` |
+//! | `en-GB` | `1,234.56`   | `31/03/2025` |
+//! | `de`    | `1.234,56`   | `31.03.2025` |
+//! | `fr`    | `1 234,56`   | `31/03/2025` |
+//!
+//! The locale is negotiated from `Accept-Language` by
+//! `EywaApp::localized_formats`, or chosen explicitly with the `format` query
+//! parameter (`?format=de-DE`, or `?format=raw` for machine formats). Outside a
+//! request, or when no supported locale matches, values use machine formats:
+//! plain decimals, ISO 8601 dates and RFC 3339 timestamps.
+
+use axum::{
+    extract::Request,
+    http::{header::ACCEPT_LANGUAGE, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, SchemaFormat, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+
+/// Query parameter overriding the negotiated locale.
+pub const FORMAT_PARAM: &str = "format";
+
+tokio::task_local! {
+    /// Locale of the request being served.
+    pub static CURRENT_LOCALE: Locale;
+}
+
+/// Formatting conventions of a locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    /// Language tag, e.g. `de-DE`
+    pub tag: &'static str,
+    decimal_separator: char,
+    group_separator: Option<char>,
+    date: &'static str,
+    date_time: &'static str,
+}
+
+const fn locale(
+    tag: &'static str,
+    decimal_separator: char,
+    group_separator: Option<char>,
+    date: &'static str,
+    date_time: &'static str,
+) -> Locale {
+    Locale {
+        tag,
+        decimal_separator,
+        group_separator,
+        date,
+        date_time,
+    }
+}
+
+/// Supported locales, more specific tags first.
+const LOCALES: &[Locale] = &[
+    locale("en-US", '.', Some(','), "%m/%d/%Y", "%m/%d/%Y %I:%M %p UTC"),
+    locale("en-GB", '.', Some(','), "%d/%m/%Y", "%d/%m/%Y %H:%M UTC"),
+    locale("en", '.', Some(','), "%m/%d/%Y", "%m/%d/%Y %I:%M %p UTC"),
+    locale("de", ',', Some('.'), "%d.%m.%Y", "%d.%m.%Y %H:%M UTC"),
+    locale("fr", ',', Some('\u{202f}'), "%d/%m/%Y", "%d/%m/%Y %H:%M UTC"),
+    locale("es", ',', Some('.'), "%d/%m/%Y", "%d/%m/%Y %H:%M UTC"),
+    locale("it", ',', Some('.'), "%d/%m/%Y", "%d/%m/%Y %H:%M UTC"),
+    locale("pt", ',', Some('.'), "%d/%m/%Y", "%d/%m/%Y %H:%M UTC"),
+    locale("nl", ',', Some('.'), "%d-%m-%Y", "%d-%m-%Y %H:%M UTC"),
+    locale("ja", '.', Some(','), "%Y/%m/%d", "%Y/%m/%d %H:%M UTC"),
+    locale("zh", '.', Some(','), "%Y/%m/%d", "%Y/%m/%d %H:%M UTC"),
+];
+
+impl Locale {
+    /// Machine formats: plain decimals, ISO 8601 dates, RFC 3339 timestamps.
+    pub const RAW: Locale = locale("raw", '.', None, "%Y-%m-%d", "%+");
+
+    /// The supported locale of a language tag, by exact tag, then language.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let tag = tag.trim();
+        if tag.eq_ignore_ascii_case(Self::RAW.tag) {
+            return Some(Self::RAW);
+        }
+        let tag = tag.replace('_', "-");
+        let language = tag.split('-').next().unwrap_or(&tag);
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(&tag))
+            .or_else(|| LOCALES.iter().find(|locale| locale.tag.eq_ignore_ascii_case(language)))
+            .copied()
+    }
+
+    /// The preferred supported locale of an `Accept-Language` header.
+    pub fn negotiate(accept_language: &str) -> Option<Locale> {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1.0);
+                (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable: equal qualities keep the header order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| Self::from_tag(tag))
+    }
+
+    /// Locale of the request being served, `RAW` outside a request.
+    pub fn current() -> Locale {
+        CURRENT_LOCALE.try_with(|locale| *locale).unwrap_or(Self::RAW)
+    }
+
+    /// Format a decimal, keeping its scale.
+    pub fn format_decimal(&self, value: Decimal) -> String {
+        let plain = value.to_string();
+        let (sign, digits) = match plain.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", plain.as_str()),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        let mut formatted = String::from(sign);
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0
+                && (integer.len() - i) % 3 == 0
+                && let Some(separator) = self.group_separator
+            {
+                formatted.push(separator);
+            }
+            formatted.push(digit);
+        }
+        if !fraction.is_empty() {
+            formatted.push(self.decimal_separator);
+            formatted.push_str(fraction);
+        }
+        formatted
+    }
+
+    /// Format a date.
+    pub fn format_date(&self, value: NaiveDate) -> String {
+        value.format(self.date).to_string()
+    }
+
+    /// Format a UTC timestamp.
+    pub fn format_date_time(&self, value: DateTime<Utc>) -> String {
+        value.format(self.date_time).to_string()
+    }
+}
+
+fn string_schema(format: &str, description: &str, example: &str) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .format(Some(SchemaFormat::Custom(format.to_string())))
+        .description(Some(description))
+        .examples([example])
+        .into()
+}
+
+macro_rules! localized {
+    (
+        $(#[$doc:meta])*
+        $name:ident($inner:ty), $format:ident, $schema_format:literal, $example:literal
+    ) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub struct $name(pub $inner);
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> Self {
+                Self(value)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&Locale::current().$format(self.0))
+            }
+        }
+
+        impl PartialSchema for $name {
+            fn schema() -> RefOr<Schema> {
+                string_schema(
+                    $schema_format,
+                    "Formatted for the request locale (`Accept-Language` or `?format=`)",
+                    $example,
+                )
+            }
+        }
+
+        impl ToSchema for $name {}
+    };
+}
+
+localized!(
+    /// A decimal formatted for the request locale, e.g. `1.234,56`.
+    LocalizedDecimal(Decimal),
+    format_decimal,
+    "localized-decimal",
+    "1,234.56"
+);
+
+localized!(
+    /// A date formatted for the request locale, e.g. `31.03.2025`.
+    LocalizedDate(NaiveDate),
+    format_date,
+    "localized-date",
+    "03/31/2025"
+);
+
+localized!(
+    /// A UTC timestamp formatted for the request locale.
+    LocalizedDateTime(DateTime<Utc>),
+    format_date_time,
+    "localized-date-time",
+    "03/31/2025 02:30 PM UTC"
+);
+
+/// Locale of a request: `?format=`, then `Accept-Language`.
+fn request_locale(query: Option<&str>, headers: &HeaderMap) -> Locale {
+    let explicit = query.and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == FORMAT_PARAM)
+            .and_then(|(_, value)| Locale::from_tag(&value))
+    });
+    explicit
+        .or_else(|| {
+            headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Locale::negotiate)
+        })
+        .unwrap_or(Locale::RAW)
+}
+
+/// Middleware serving the request with its negotiated locale.
+///
+/// Installed by `EywaApp::localized_formats()`.
+pub async fn locale_middleware(req: Request, next: Next) -> Response {
+    let locale = request_locale(req.uri().query(), req.headers());
+    CURRENT_LOCALE.scope(locale, next.run(req)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Json, Router};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_negotiation() {
+        let tag = |header| Locale::negotiate(header).map(|locale| locale.tag);
+
+        assert_eq!(tag("de-DE,de;q=0.9,en;q=0.8"), Some("de"));
+        assert_eq!(tag("en-GB"), Some("en-GB"));
+        assert_eq!(tag("sv;q=0.9, fr;q=0.5"), Some("fr"));
+        assert_eq!(tag("en;q=0.2, nl"), Some("nl"));
+        assert_eq!(tag("sv, *"), None);
+    }
+
+    #[test]
+    fn test_decimal_formats() {
+        let value = Decimal::from_str("-1234567.50").unwrap();
+
+        assert_eq!(Locale::RAW.format_decimal(value), "-1234567.50");
+        assert_eq!(Locale::from_tag("en").unwrap().format_decimal(value), "-1,234,567.50");
+        assert_eq!(Locale::from_tag("de").unwrap().format_decimal(value), "-1.234.567,50");
+        assert_eq!(Locale::from_tag("de").unwrap().format_decimal(Decimal::from(999)), "999");
+    }
+
+    #[test]
+    fn test_date_formats() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+
+        assert_eq!(Locale::RAW.format_date(date), "2025-03-31");
+        assert_eq!(Locale::from_tag("en-US").unwrap().format_date(date), "03/31/2025");
+        assert_eq!(Locale::from_tag("de_AT").unwrap().format_date(date), "31.03.2025");
+    }
+
+    #[derive(Serialize)]
+    struct Invoice {
+        total: LocalizedDecimal,
+        due: LocalizedDate,
+    }
+
+    #[tokio::test]
+    async fn test_serialized_with_request_locale() {
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/invoice",
+                    get(|| async {
+                        Json(Invoice {
+                            total: Decimal::from_str("1234.5").unwrap().into(),
+                            due: NaiveDate::from_ymd_opt(2025, 3, 31).unwrap().into(),
+                        })
+                    }),
+                )
+                .layer(axum::middleware::from_fn(locale_middleware)),
+        );
+
+        let invoice: serde_json::Value =
+            client.get("/invoice").header("accept-language", "de-DE").send().await.json();
+        assert_eq!(invoice["total"], "1.234,5");
+        assert_eq!(invoice["due"], "31.03.2025");
+
+        let invoice: serde_json::Value = client
+            .get("/invoice?format=raw")
+            .header("accept-language", "de-DE")
+            .send()
+            .await
+            .json();
+        assert_eq!(invoice["total"], "1234.5");
+        assert_eq!(invoice["due"], "2025-03-31");
+    }
+}
@@ -0,0 +1,155 @@
+//! Runtime log-level changes.
+//!
+//! On-call turns on debug logging for a misbehaving service without a
+//! redeploy, and back off once done. The endpoints change the filter of the
+//! subscriber installed by `telemetry::init_tracing`, through the `LogLevel`
+//! handle it returns:
+//!
+//! ```ignore
+//! let log_level = eywa_axum::telemetry::init_tracing(&config.telemetry)?;
+//!
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .log_level_endpoints(log_level)
+//! ```
+//!
+//! - `LogLevelController` - Admin endpoints, mounted with
+//!   `EywaApp::log_level_endpoints` and requiring the `log_level:admin` scope:
+//!   - `GET /admin/log-level` - The current `EnvFilter` directives
+//!   - `PUT /admin/log-level` - Replace them, e.g. `{"filter":"debug,sqlx=warn"}`
+//!
+//! The change only lasts until the process restarts.
+
+use axum::{extract::State, routing::get, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{PartialSchema, ToSchema};
+
+use crate::telemetry::LogLevel;
+use crate::Result;
+
+/// Scope required to call the log level admin endpoints.
+pub const LOG_LEVEL_ADMIN_SCOPE: &str = "log_level:admin";
+
+/// `EnvFilter` directives of the log output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LogFilter {
+    #[schema(example = "debug,sqlx=warn")]
+    pub filter: String,
+}
+
+/// Get the log filter
+#[utoipa::path(
+    get,
+    path = "/admin/log-level",
+    tag = "Log Level",
+    responses(
+        (status = 200, description = "Current log filter", body = LogFilter)
+    )
+)]
+pub async fn current(State(log_level): State<LogLevel>) -> Json<LogFilter> {
+    Json(LogFilter {
+        filter: log_level.filter(),
+    })
+}
+
+/// Change the log filter
+///
+/// Applies immediately, until the process restarts. Invalid directives are
+/// rejected and the current filter is kept.
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    tag = "Log Level",
+    request_body = LogFilter,
+    responses(
+        (status = 200, description = "Log filter changed", body = LogFilter)
+    )
+)]
+pub async fn change(
+    State(log_level): State<LogLevel>,
+    Json(body): Json<LogFilter>,
+) -> Result<Json<LogFilter>> {
+    log_level.set(&body.filter)?;
+    Ok(Json(body))
+}
+
+pub struct LogLevelController;
+
+impl LogLevelController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 2] =
+        [("GET", "/admin/log-level"), ("PUT", "/admin/log-level")];
+
+    /// Build the log level admin router for the given handle.
+    pub fn router<S>(log_level: LogLevel) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/admin/log-level", get(current).put(change))
+            .with_state(log_level)
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_current as Path>::path(),
+                <__path_current as Path>::methods(),
+                <__path_current as Path>::operation(),
+            ),
+            (
+                <__path_change as Path>::path(),
+                <__path_change as Path>::methods(),
+                <__path_change as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components
+            .schemas
+            .insert("LogFilter".to_string(), LogFilter::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::http::StatusCode;
+    use tracing_subscriber::{reload, EnvFilter};
+
+    #[tokio::test]
+    async fn test_change_log_filter() {
+        let (_filter, handle) = reload::Layer::<_, tracing_subscriber::Registry>::new(
+            EnvFilter::new("info"),
+        );
+        let client = TestClient::new(LogLevelController::router(LogLevel::new(handle, "info")));
+
+        let current: LogFilter = client.get("/admin/log-level").send().await.json();
+        assert_eq!(current.filter, "info");
+
+        let changed = LogFilter {
+            filter: "debug,sqlx=warn".to_string(),
+        };
+        let response = client.put("/admin/log-level").json(&changed).send().await;
+        response.assert_status(StatusCode::OK);
+        let current: LogFilter = client.get("/admin/log-level").send().await.json();
+        assert_eq!(current, changed);
+
+        let invalid = LogFilter {
+            filter: "debug,sqlx=loud".to_string(),
+        };
+        let response = client.put("/admin/log-level").json(&invalid).send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let current: LogFilter = client.get("/admin/log-level").send().await.json();
+        assert_eq!(current, changed);
+    }
+}
@@ -0,0 +1,254 @@
+//! JSON logs with the request's correlation fields.
+//!
+//! Log aggregators index JSON fields without parsing rules, but the text
+//! format of `telemetry::init_tracing` appends the request ids as
+//! `key=value` pairs. `init_json` installs a subscriber writing one JSON
+//! object per event instead:
+//!
+//! ```json
+//! {"timestamp":"2024-05-02T09:14:03.512Z","level":"INFO","target":"projects::handlers",
+//!  "message":"project created","project_id":42,"service":"projects","env":"production",
+//!  "correlation_id":"a1b2...","request_id":"9f3c...","user_id":"u-17"}
+//! ```
+//!
+//! `service` and `env` come from `LogConfig` and are on every event, startup
+//! and background jobs included. `correlation_id`, `request_id`, `trace_id`,
+//! `user_id` and `canary` come from the request span opened by the context
+//! middleware, so they are on every event emitted while handling a request,
+//! access logs included. `EywaApp::logging` installs the subscriber and that
+//! middleware in one call:
+//!
+//! ```toml
+//! [logging]
+//! service = "projects"
+//! env = "production"
+//! filter = "info,sqlx=warn"   # overridden by RUST_LOG
+//! ```
+
+use std::fmt;
+
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+use crate::telemetry::{telemetry_error, CorrelationIds, CorrelationLayer, LogLevel};
+use crate::Result;
+
+/// JSON logging settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogConfig {
+    /// Service name, e.g. `projects`
+    pub service: String,
+    /// Deployment environment, e.g. `production`
+    #[serde(default = "default_env")]
+    pub env: String,
+    /// `EnvFilter` directives used when `RUST_LOG` isn't set
+    #[serde(default = "default_filter")]
+    pub filter: String,
+}
+
+fn default_env() -> String {
+    "development".to_string()
+}
+
+fn default_filter() -> String {
+    "info".to_string()
+}
+
+impl LogConfig {
+    /// Log `info` and above for `service` in `env`.
+    pub fn new(service: impl Into<String>, env: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            env: env.into(),
+            filter: default_filter(),
+        }
+    }
+
+    /// Use other `EnvFilter` directives, e.g. `debug,sqlx=warn`.
+    pub fn filter(mut self, filter: impl Into<String>) -> Self {
+        self.filter = filter.into();
+        self
+    }
+}
+
+/// Event fields as JSON values.
+#[derive(Default)]
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0.insert(field.name().to_string(), value.to_string().into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+/// Event format writing one JSON object per line.
+///
+/// Requires `CorrelationLayer` on the same subscriber for the request
+/// fields.
+#[derive(Debug, Clone)]
+pub struct JsonFormat {
+    service: String,
+    env: String,
+}
+
+impl JsonFormat {
+    /// Tag every event with `service` and `env`.
+    pub fn new(service: impl Into<String>, env: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            env: env.into(),
+        }
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let mut object = fields.0;
+
+        let metadata = event.metadata();
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        object.insert("timestamp".to_string(), timestamp.into());
+        object.insert("level".to_string(), metadata.level().as_str().into());
+        object.insert("target".to_string(), metadata.target().into());
+        object.insert("service".to_string(), self.service.as_str().into());
+        object.insert("env".to_string(), self.env.as_str().into());
+
+        // The service span of `EywaApp::observability` wins over the config
+        for (name, value) in CorrelationIds::of_event(ctx).0 {
+            object.insert(name.to_string(), value.into());
+        }
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Install the global tracing subscriber, writing JSON logs.
+///
+/// Fails if a global subscriber is already installed. Returns the handle
+/// changing the log filter at runtime.
+///
+/// # Example
+///
+/// ```ignore
+/// let config: MyAppConfig = EywaConfig::load()?;
+/// let log_level = eywa_axum::logging::init_json(&config.logging)?;
+/// ```
+pub fn init_json(config: &LogConfig) -> Result<LogLevel> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&config.filter).map_err(telemetry_error)?,
+    };
+    let directives = filter.to_string();
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let fmt = tracing_subscriber::fmt::layer()
+        .event_format(JsonFormat::new(&config.service, &config.env));
+    tracing_subscriber::registry()
+        .with(CorrelationLayer)
+        .with(fmt.with_filter(filter))
+        .try_init()
+        .map_err(telemetry_error)?;
+    Ok(LogLevel::new(handle, directives))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_events_carry_correlation_fields() {
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::registry().with(CorrelationLayer).with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(move || writer.clone())
+                .event_format(JsonFormat::new("projects", "staging")),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("starting");
+            let request = tracing::info_span!(
+                "request",
+                correlation_id = "c-1",
+                request_id = "r-1",
+                user_id = tracing::field::Empty,
+            );
+            request.record("user_id", "u-17");
+            let _request = request.enter();
+            tracing::warn!(project_id = 42, "project created");
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<Value> =
+            output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[0]["message"], "starting");
+        assert_eq!(lines[0]["service"], "projects");
+        assert!(lines[0].get("correlation_id").is_none());
+
+        assert_eq!(lines[1]["level"], "WARN");
+        assert_eq!(lines[1]["message"], "project created");
+        assert_eq!(lines[1]["project_id"], 42);
+        assert_eq!(lines[1]["env"], "staging");
+        assert_eq!(lines[1]["correlation_id"], "c-1");
+        assert_eq!(lines[1]["request_id"], "r-1");
+        assert_eq!(lines[1]["user_id"], "u-17");
+    }
+}
@@ -4,19 +4,81 @@
 //! - `RequestContext` - Request metadata propagation (correlation ID, user ID, language)
 //! - `request_context_middleware_fn` - Axum middleware for context extraction
 //! - `request_logging_middleware` - Tower-http TraceLayer for structured logging
+//! - `auth` - JWT authentication setting `RequestContext::user_id`
+//! - `scopes` - OAuth scope enforcement tied to OpenAPI security requirements
+//! - `chaos` - Fault injection for development and staging environments
+//! - `headers` - Static response headers declared on routes
+//! - `route_layers` - Middleware declared on individual routes
+//! - `rejection` - Extractor rejections mapped to the EYWA error envelope
+//! - `compression` - Configurable response compression
+//! - `body_limit` - Request body limit read from a reloadable setting
+//! - `limits` - Body size, header size, timeout and concurrency limits with defaults
+//! - `content_types` - Request and `Accept` media types enforced from the spec
+//! - `decompression` - Gzip/deflate request bodies with a decompressed-size limit
+//! - `deadline` - Request deadlines enforced as handler timeouts
+//! - `bulkhead` - Bounded concurrency pools per route group
+//! - `slo` - Per-route SLO good/bad event counters for burn-rate alerts
+//! - `adaptive` - Latency-driven concurrency limit shedding excess load
+//! - `queue` - Bounded admission queue smoothing bursts before shedding
+//! - `canary` - In-process canary routing to alternate handlers
+//! - `rate_limit` - Per-IP, per-tenant and per-API-key rate limiting
+//! - `maintenance` - Maintenance mode rejecting requests with `Retry-After`
+//! - `method_override` - `X-HTTP-Method-Override` for `POST`-only clients
+//! - `methods` - `OPTIONS` answered with the allowed methods, `HEAD`/`OPTIONS` docs
+//! - `paths` - Trailing-slash and case-insensitive path normalization before routing
+//! - `timing` - Server-Timing header with middleware, handler and custom phases
+//! - `http_metrics` - Prometheus request count, duration and in-flight metrics per route
+//! - `response_metrics` - Response size and serialization time histograms per route
+//! - `otel` - OpenTelemetry server spans with W3C trace context propagation
+//! - `propagation` - Headers forwarded downstream, reflected or stripped, from config
+
+use std::collections::BTreeMap;
 
 use axum::{
     extract::Request,
-    http::{HeaderMap, HeaderValue},
+    http::{Extensions, HeaderMap, HeaderValue},
     middleware::Next,
     response::Response,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use tracing::Instrument;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
 use eywa_user_id::UserId;
 
+pub mod adaptive;
+pub mod auth;
+pub mod body_limit;
+pub mod bulkhead;
+pub mod canary;
+pub mod chaos;
+pub mod compression;
+pub mod constraints;
+pub mod content_types;
+pub mod deadline;
+pub mod decompression;
+pub mod headers;
+pub mod http_metrics;
+pub mod limits;
+pub mod maintenance;
+pub mod method_override;
+pub mod methods;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod paths;
+pub mod propagation;
+pub mod queue;
+pub mod rate_limit;
+pub mod rejection;
+pub mod response_metrics;
+pub mod route_layers;
+pub mod scopes;
+pub mod slo;
+pub(crate) mod spec_checks;
+pub mod timing;
+
 /// Request context propagated through the entire request lifecycle.
 ///
 /// This struct contains metadata that's extracted from incoming request headers
@@ -29,6 +91,17 @@ use eywa_user_id::UserId;
 /// - `user_id` - Authenticated user ID, if present (extracted from JWT).
 /// - `language` - Content language from `Accept-Language` header (defaults to "en").
 /// - `request_id` - Unique identifier for this specific request (always generated).
+/// - `deadline` - When the caller stops waiting, from `X-Request-Deadline` or
+///   `grpc-timeout` (see `deadline`).
+/// - `canary` - Canary handler serving the request, if any (see `canary`).
+/// - `experiments` - Assigned experiment variants (see `crate::experiments`).
+/// - `propagated_headers` - Inbound headers forwarded to outbound calls (see
+///   `propagation`).
+///
+/// Middleware can also attach their own typed values with `insert`, read by
+/// the handlers with `get`, without defining an extension type and extractor
+/// each. Values are keyed by type, so wrap shared types in a newtype; they
+/// are neither serialized nor documented.
 ///
 /// # Example
 ///
@@ -57,6 +130,75 @@ pub struct RequestContext {
 
     /// Unique request ID (always generated)
     pub request_id: Uuid,
+
+    /// Deadline of the request (if the caller or `request_deadlines` set one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DateTime<Utc>>,
+
+    /// Canary serving the request (see `canary`), `None` for the stable handler
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canary: Option<String>,
+
+    /// Experiment variants assigned to the request (see `experiments`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub experiments: BTreeMap<String, String>,
+
+    /// Inbound headers forwarded to outbound calls (see `propagation`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub propagated_headers: BTreeMap<String, String>,
+
+    /// Values attached by middleware, keyed by type
+    #[serde(skip)]
+    #[schema(ignore)]
+    extensions: Extensions,
+}
+
+impl RequestContext {
+    /// Attach a value, replacing (and returning) the previous one of type `T`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[derive(Clone)]
+    /// struct Tenant(String);
+    ///
+    /// // In a middleware running after the context middleware
+    /// if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+    ///     ctx.insert(Tenant(tenant));
+    /// }
+    ///
+    /// // In the handler
+    /// let tenant = ctx.get::<Tenant>();
+    /// ```
+    pub fn insert<T: Clone + Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.extensions.insert(value)
+    }
+
+    /// The value of type `T`, if attached.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions.get::<T>()
+    }
+
+    /// The value of type `T`, mutably, if attached.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.extensions.get_mut::<T>()
+    }
+
+    /// Detach the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.extensions.remove::<T>()
+    }
+
+    /// Time left before the deadline (zero once it has passed).
+    pub fn remaining_budget(&self) -> Option<std::time::Duration> {
+        self.deadline
+            .map(|deadline| (deadline - Utc::now()).to_std().unwrap_or_default())
+    }
+
+    /// Returns `true` if the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining_budget().is_some_and(|remaining| remaining.is_zero())
+    }
 }
 
 impl Default for RequestContext {
@@ -66,6 +208,11 @@ impl Default for RequestContext {
             user_id: None,
             language: "en".to_string(),
             request_id: Uuid::new_v4(),
+            deadline: None,
+            canary: None,
+            experiments: BTreeMap::new(),
+            propagated_headers: BTreeMap::new(),
+            extensions: Extensions::new(),
         }
     }
 }
@@ -84,6 +231,22 @@ fn extract_correlation_id(headers: &HeaderMap) -> Uuid {
         .unwrap_or_else(Uuid::new_v4)
 }
 
+/// Trace ID of the request, for log correlation with traces.
+///
+/// # Priority
+///
+/// 1. Trace ID of the W3C `traceparent` header (if valid)
+/// 2. The correlation ID as 32 hex digits
+fn extract_trace_id(headers: &HeaderMap, correlation_id: Uuid) -> String {
+    headers
+        .get("traceparent")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|traceparent| traceparent.split('-').nth(1))
+        .filter(|id| id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_else(|| correlation_id.simple().to_string())
+}
+
 /// Extract language from Accept-Language header or default to "en".
 ///
 /// # Priority
@@ -104,8 +267,11 @@ fn extract_language(headers: &HeaderMap) -> String {
 /// 1. Extracts `X-Correlation-ID` header or generates a new UUID
 /// 2. Extracts `Accept-Language` header or defaults to "en"
 /// 3. Generates a unique `request_id`
+///    and reads the deadline (`X-Request-Deadline` / `grpc-timeout`)
 /// 4. Inserts `RequestContext` as an Axum Extension
-/// 5. Adds `X-Correlation-ID` to the response headers
+/// 5. Runs the request in a `request` span carrying `correlation_id`,
+///    `request_id` and `trace_id`, added to every log line by `telemetry`
+/// 6. Adds `X-Correlation-ID` to the response headers
 ///
 /// # Example
 ///
@@ -130,20 +296,51 @@ pub async fn request_context_middleware_fn(mut req: Request, next: Next) -> Resp
     // Generate request ID
     let request_id = Uuid::new_v4();
 
-    // Create request context (user_id will be set by auth middleware if present)
+    // Extract the caller's deadline
+    let deadline = deadline::inbound_deadline(&headers, Utc::now());
+
+    // Select the headers to forward, if the header policy already ran
+    let propagated_headers = req
+        .extensions()
+        .get::<std::sync::Arc<propagation::HeaderPolicy>>()
+        .map(|policy| policy.propagated(&headers))
+        .unwrap_or_default();
+
+    // Take the user if auth already ran, otherwise `EywaApp::auth` sets it
+    let user_id = req.extensions().get::<UserId>().cloned();
+
     let ctx = RequestContext {
         correlation_id,
-        user_id: None, // Will be set by auth middleware
+        user_id: user_id.clone(),
         language,
         request_id,
+        deadline,
+        canary: None, // Set by the canary middleware
+        experiments: BTreeMap::new(), // Set by the experiments middleware
+        propagated_headers,
+        extensions: Extensions::new(), // Filled by later middleware
     };
 
     // Insert context into request extensions so logging middleware can access it
     req.extensions_mut().insert(ctx.clone());
 
+    // Log every event of the request with its ids
+    let span = tracing::info_span!(
+        "request",
+        correlation_id = %correlation_id,
+        request_id = %request_id,
+        trace_id = %extract_trace_id(&headers, correlation_id),
+        canary = tracing::field::Empty,
+        user_id = tracing::field::Empty,
+    );
+    if let Some(user_id) = &user_id {
+        span.record("user_id", tracing::field::display(user_id));
+    }
+
     // Continue the request with request_id in task-local storage for error handling
     let mut response: Response = eywa_errors::CURRENT_REQUEST_ID
         .scope(request_id, next.run(req))
+        .instrument(span)
         .await;
 
     // Add correlation ID to response headers
@@ -197,6 +394,17 @@ pub fn request_logging_middleware() -> tower_http::trace::TraceLayer<
     )
 }
 
+/// The method route metadata is registered under for a request method.
+///
+/// Axum answers `HEAD` with the `GET` handler, so what a route declares for
+/// `GET` (scopes, middleware, kill switches) applies to `HEAD` as well.
+pub(crate) fn route_method(method: &str) -> String {
+    match method.to_uppercase() {
+        method if method == "HEAD" => "GET".to_string(),
+        method => method,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,6 +449,44 @@ mod tests {
         assert_eq!(result, "en");
     }
 
+    #[test]
+    fn test_extract_trace_id() {
+        let correlation_id = Uuid::new_v4();
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            extract_trace_id(&headers, correlation_id),
+            correlation_id.simple().to_string()
+        );
+
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01"),
+        );
+        let result = extract_trace_id(&headers, correlation_id);
+        assert_eq!(result, "4bf92f3577b34da6a3ce929d0e0e4736");
+    }
+
+    #[test]
+    fn test_context_extensions() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Tenant(&'static str);
+
+        let mut ctx = RequestContext::default();
+        assert_eq!(ctx.get::<Tenant>(), None);
+        assert_eq!(ctx.insert(Tenant("acme")), None);
+        assert_eq!(ctx.insert(Tenant("globex")), Some(Tenant("acme")));
+
+        // Handlers get a clone of the context, values included
+        let cloned = ctx.clone();
+        assert_eq!(cloned.get::<Tenant>(), Some(&Tenant("globex")));
+        assert_eq!(ctx.remove::<Tenant>(), Some(Tenant("globex")));
+        assert!(ctx.get::<Tenant>().is_none());
+
+        // Not serialized
+        let json = serde_json::to_value(&cloned).unwrap();
+        assert!(json.get("extensions").is_none());
+    }
+
     #[test]
     fn test_request_context_default() {
         let ctx = RequestContext::default();
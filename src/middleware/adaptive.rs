@@ -0,0 +1,384 @@
+//! Adaptive concurrency control.
+//!
+//! A fixed concurrency limit is either too low (wasted capacity) or too high
+//! (queues build up and every request gets slow) once traffic or downstream
+//! latency changes. The `AdaptiveLimiter` measures the latency of completed
+//! requests and adjusts the in-flight limit continuously; requests above the
+//! current limit are shed with `503 Service Unavailable`, with a
+//! `Retry-After` of the average request latency (the time a slot usually
+//! takes to free up), rounded up to whole seconds.
+//!
+//! Two strategies are available:
+//! - **AIMD**: additive increase while latency stays under a target,
+//!   multiplicative decrease when it exceeds it or the handler fails
+//! - **Gradient**: the limit follows the ratio between the long-term and the
+//!   current latency, growing while latency is stable and shrinking as soon
+//!   as requests start queueing; no target to tune
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+
+/// Weight of a new sample in the gradient's long-term latency average.
+const LONG_RTT_WEIGHT: f64 = 0.05;
+
+/// Weight of a new limit in the gradient's smoothed limit.
+const GRADIENT_SMOOTHING: f64 = 0.2;
+
+/// Weight of a new sample in the average latency reported as `Retry-After`.
+const LATENCY_WEIGHT: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy)]
+enum Strategy {
+    Aimd {
+        target: Duration,
+        backoff: f64,
+    },
+    Gradient,
+}
+
+/// Adaptive concurrency configuration.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::middleware::adaptive::AdaptiveConcurrency;
+///
+/// // Keep latency under 250ms
+/// app.adaptive_concurrency(AdaptiveConcurrency::aimd(Duration::from_millis(250)))
+///
+/// // Follow the latency gradient, between 10 and 500 in-flight requests
+/// app.adaptive_concurrency(AdaptiveConcurrency::gradient().min_limit(10).max_limit(500))
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveConcurrency {
+    strategy: Strategy,
+    initial_limit: usize,
+    min_limit: usize,
+    max_limit: usize,
+}
+
+impl AdaptiveConcurrency {
+    fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            initial_limit: 20,
+            min_limit: 1,
+            max_limit: 1000,
+        }
+    }
+
+    /// Additive increase / multiplicative decrease around a latency target.
+    pub fn aimd(target_latency: Duration) -> Self {
+        Self::new(Strategy::Aimd {
+            target: target_latency,
+            backoff: 0.9,
+        })
+    }
+
+    /// Latency-gradient based limit.
+    pub fn gradient() -> Self {
+        Self::new(Strategy::Gradient)
+    }
+
+    /// Factor applied to the limit when AIMD backs off (default 0.9).
+    pub fn backoff(mut self, ratio: f64) -> Self {
+        if let Strategy::Aimd { backoff, .. } = &mut self.strategy {
+            *backoff = ratio.clamp(0.1, 0.99);
+        }
+        self
+    }
+
+    /// Limit before any latency is measured (default 20).
+    pub fn initial_limit(mut self, limit: usize) -> Self {
+        self.initial_limit = limit;
+        self
+    }
+
+    /// Lowest limit (default 1).
+    pub fn min_limit(mut self, limit: usize) -> Self {
+        self.min_limit = limit.max(1);
+        self
+    }
+
+    /// Highest limit (default 1000).
+    pub fn max_limit(mut self, limit: usize) -> Self {
+        self.max_limit = limit;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Estimate {
+    limit: f64,
+    long_rtt: Option<f64>,
+    latency: Option<f64>,
+}
+
+/// In-flight request limiter adjusting its limit from observed latency.
+#[derive(Debug)]
+pub struct AdaptiveLimiter {
+    config: AdaptiveConcurrency,
+    in_flight: AtomicUsize,
+    estimate: Mutex<Estimate>,
+}
+
+impl AdaptiveLimiter {
+    /// Create a limiter starting at the configured initial limit.
+    pub fn new(config: AdaptiveConcurrency) -> Self {
+        let limit = config
+            .initial_limit
+            .clamp(config.min_limit, config.max_limit.max(config.min_limit));
+        Self {
+            config,
+            in_flight: AtomicUsize::new(0),
+            estimate: Mutex::new(Estimate {
+                limit: limit as f64,
+                long_rtt: None,
+                latency: None,
+            }),
+        }
+    }
+
+    /// The current in-flight limit.
+    pub fn limit(&self) -> usize {
+        self.estimate.lock().unwrap().limit as usize
+    }
+
+    /// Requests currently admitted.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    /// How long shed clients should wait: the average request latency.
+    pub fn retry_after(&self) -> Duration {
+        self.estimate
+            .lock()
+            .unwrap()
+            .latency
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default()
+    }
+
+    /// Admit a request, unless the limit is reached.
+    fn try_acquire(&self) -> Option<usize> {
+        let limit = self.limit();
+        self.in_flight
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |in_flight| {
+                (in_flight < limit).then_some(in_flight + 1)
+            })
+            .ok()
+            .map(|in_flight| in_flight + 1)
+    }
+
+    /// Update the limit from a completed request.
+    ///
+    /// `in_flight` is the number of requests running when it was admitted;
+    /// `failed` marks server errors, which count as overload.
+    pub fn record(&self, latency: Duration, in_flight: usize, failed: bool) {
+        let min = self.config.min_limit as f64;
+        let max = self.config.max_limit.max(self.config.min_limit) as f64;
+        let mut estimate = self.estimate.lock().unwrap();
+        let previous = estimate.limit;
+        estimate.latency = Some(match estimate.latency {
+            Some(average) => {
+                average * (1.0 - LATENCY_WEIGHT) + latency.as_secs_f64() * LATENCY_WEIGHT
+            }
+            None => latency.as_secs_f64(),
+        });
+
+        let limit = match self.config.strategy {
+            Strategy::Aimd { target, backoff } => {
+                if failed || latency > target {
+                    previous * backoff
+                } else if in_flight as f64 * 2.0 >= previous {
+                    // Only grow when the limit is actually being used
+                    previous + 1.0
+                } else {
+                    previous
+                }
+            }
+            Strategy::Gradient => {
+                let rtt = latency.as_secs_f64();
+                let long_rtt = match estimate.long_rtt {
+                    Some(long_rtt) => {
+                        long_rtt * (1.0 - LONG_RTT_WEIGHT) + rtt * LONG_RTT_WEIGHT
+                    }
+                    None => rtt,
+                };
+                estimate.long_rtt = Some(long_rtt);
+
+                let gradient = if failed || rtt <= 0.0 {
+                    0.5
+                } else {
+                    (long_rtt / rtt).clamp(0.5, 1.0)
+                };
+                // Headroom lets the limit grow while latency is stable
+                let queue = previous.sqrt();
+                let target = previous * gradient + queue;
+                previous * (1.0 - GRADIENT_SMOOTHING) + target * GRADIENT_SMOOTHING
+            }
+        };
+
+        estimate.limit = limit.clamp(min, max);
+        if estimate.limit as usize != previous as usize {
+            tracing::debug!(
+                "Adaptive concurrency limit {} -> {}",
+                previous as usize,
+                estimate.limit as usize
+            );
+        }
+    }
+}
+
+/// Releases an admitted request, even if its future is dropped.
+struct Admission<'a>(&'a AdaptiveLimiter);
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn overloaded(retry_after: Duration) -> Response {
+    ErrorResponse::new(error_codes::SERVICE_UNAVAILABLE, "Server is overloaded, retry later")
+        .retry_after(retry_after)
+        .into_response_with(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Middleware admitting requests under the adaptive limit.
+///
+/// Installed by `EywaApp::adaptive_concurrency()`.
+pub async fn adaptive_concurrency_middleware(
+    State(limiter): State<Arc<AdaptiveLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(in_flight) = limiter.try_acquire() else {
+        tracing::warn!(
+            limit = limiter.limit(),
+            "adaptive concurrency limit reached, shedding request"
+        );
+        return overloaded(limiter.retry_after());
+    };
+
+    let admission = Admission(&limiter);
+    let started = Instant::now();
+    let response = next.run(req).await;
+    drop(admission);
+    limiter.record(
+        started.elapsed(),
+        in_flight,
+        response.status().is_server_error(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    #[test]
+    fn test_aimd_backs_off_and_recovers() {
+        let limiter = AdaptiveLimiter::new(
+            AdaptiveConcurrency::aimd(Duration::from_millis(100)).initial_limit(10),
+        );
+
+        limiter.record(Duration::from_millis(300), 10, false);
+        assert_eq!(limiter.limit(), 9);
+        limiter.record(Duration::from_millis(10), 1, true);
+        assert_eq!(limiter.limit(), 8);
+
+        limiter.record(Duration::from_millis(10), 8, false);
+        assert_eq!(limiter.limit(), 9);
+        // Not growing while the limit is mostly unused
+        limiter.record(Duration::from_millis(10), 1, false);
+        assert_eq!(limiter.limit(), 9);
+    }
+
+    #[test]
+    fn test_gradient_follows_latency() {
+        let limiter = AdaptiveLimiter::new(AdaptiveConcurrency::gradient().initial_limit(50));
+
+        for _ in 0..20 {
+            limiter.record(Duration::from_millis(10), 50, false);
+        }
+        let stable = limiter.limit();
+        assert!(stable > 50);
+
+        for _ in 0..20 {
+            limiter.record(Duration::from_millis(200), stable, false);
+        }
+        assert!(limiter.limit() < stable);
+    }
+
+    #[test]
+    fn test_retry_after_follows_latency() {
+        let limiter = AdaptiveLimiter::new(AdaptiveConcurrency::gradient());
+        assert_eq!(limiter.retry_after(), Duration::ZERO);
+
+        limiter.record(Duration::from_secs(2), 1, false);
+        assert_eq!(limiter.retry_after(), Duration::from_secs(2));
+        limiter.record(Duration::from_secs(12), 1, false);
+        assert_eq!(limiter.retry_after(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_limit_bounds() {
+        let limiter = AdaptiveLimiter::new(
+            AdaptiveConcurrency::aimd(Duration::from_millis(1))
+                .initial_limit(3)
+                .min_limit(2)
+                .max_limit(4),
+        );
+        for _ in 0..10 {
+            limiter.record(Duration::from_secs(1), 3, true);
+        }
+        assert_eq!(limiter.limit(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_requests_over_the_limit_are_shed() {
+        let limiter = Arc::new(AdaptiveLimiter::new(
+            AdaptiveConcurrency::aimd(Duration::from_secs(1))
+                .initial_limit(1)
+                .max_limit(1),
+        ));
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/work",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        "done"
+                    }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    limiter.clone(),
+                    adaptive_concurrency_middleware,
+                )),
+        );
+
+        let (first, second) = tokio::join!(client.get("/work").send(), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            client.get("/work").send().await
+        });
+
+        first.assert_status(StatusCode::OK);
+        second.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(second.header("retry-after"), Some("1"));
+        assert_eq!(limiter.in_flight(), 0);
+    }
+}
@@ -0,0 +1,440 @@
+//! JWT authentication feeding `RequestContext::user_id`.
+//!
+//! Verifying the bearer token adds its `sub` claim as the authenticated
+//! `UserId` to the request extensions, but the context middleware builds
+//! `RequestContext` on its own, so without a bridge `ctx.user_id` stays `None`
+//! and audit columns, experiments and logs never see the user. `EywaApp::auth`
+//! installs both:
+//!
+//! ```ignore
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .auth(config.jwt.clone())
+//!     .request_context()
+//! ```
+//!
+//! Whichever of the two middleware runs first, the context ends up with the
+//! user: the context middleware reads a `UserId` already authenticated, and
+//! `user_context_middleware` fills a context already created. The user is
+//! also recorded on the request's log span.
+//!
+//! Layers deciding on the token's claims (scopes, admin endpoints) verify
+//! its signature and expiry with `JwtVerifier` and read the claims from
+//! `VerifiedClaims`, which only the verifier can create. The expiry is
+//! checked against the app's `Clock`.
+
+use std::sync::{Arc, OnceLock};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use eywa_authentication::JwtService;
+use eywa_errors::AppError;
+use eywa_user_id::UserId;
+
+use crate::clock;
+use crate::middleware::scopes::GrantedScopes;
+use crate::middleware::{route_method, RequestContext};
+use crate::state::JwtSettings;
+use crate::visibility::RouteSet;
+use crate::Result;
+
+/// Tolerated clock skew on `exp` and `nbf`, in seconds.
+const LEEWAY_SECS: i64 = 60;
+
+/// JWT verification settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwtConfig {
+    /// Secret the tokens are signed with
+    pub secret: String,
+}
+
+impl JwtConfig {
+    /// Verify tokens signed with `secret`.
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+        }
+    }
+
+    /// The service verifying the tokens.
+    pub fn service(&self) -> JwtService {
+        JwtService::new(&self.secret)
+    }
+
+    /// Sign `claims` with the secret (HS256), e.g. to call the service in tests.
+    pub fn sign(&self, claims: &impl Serialize) -> Result<String> {
+        let key = EncodingKey::from_secret(self.secret.as_bytes());
+        jsonwebtoken::encode(&Header::new(Algorithm::HS256), claims, &key)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to sign token: {e}")))
+    }
+}
+
+impl From<JwtSettings> for JwtConfig {
+    fn from(settings: JwtSettings) -> Self {
+        Self::new(settings.secret)
+    }
+}
+
+/// Default claim carrying the tenant of a token.
+pub const TENANT_CLAIM: &str = "tenant_id";
+
+/// Claims of a bearer token whose signature and expiry were verified.
+///
+/// Only `JwtVerifier` creates them, so a `VerifiedClaims` extension can't
+/// come from a layer that merely decoded the token. Handlers can read them
+/// with `Extension<VerifiedClaims>` on the routes that verify the token.
+#[derive(Debug, Clone)]
+pub struct VerifiedClaims(Value);
+
+impl VerifiedClaims {
+    /// All the claims.
+    pub fn claims(&self) -> &Value {
+        &self.0
+    }
+
+    /// The claim `name`, if present.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// The `sub` claim.
+    pub fn subject(&self) -> Option<&str> {
+        self.0.get("sub").and_then(Value::as_str)
+    }
+
+    /// The claim `name` as a string (numbers are formatted), if not empty.
+    pub fn string(&self, name: &str) -> Option<String> {
+        match self.0.get(name)? {
+            Value::String(value) if !value.is_empty() => Some(value.clone()),
+            Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    /// The scopes granted by the `scope` and `scp` claims.
+    pub fn scopes(&self) -> GrantedScopes {
+        GrantedScopes::from_claims(&self.0)
+    }
+}
+
+/// Verifies the signature (HS256) and expiry of bearer tokens.
+#[derive(Clone)]
+pub struct JwtVerifier {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl std::fmt::Debug for JwtVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret
+        f.debug_struct("JwtVerifier").finish_non_exhaustive()
+    }
+}
+
+fn unauthorized(message: &str) -> AppError {
+    AppError::Unauthorized(message.to_string())
+}
+
+impl JwtVerifier {
+    /// Verify tokens signed with the secret of `config`.
+    pub fn new(config: &JwtConfig) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        // Checked in `verify`, against the app's clock
+        validation.validate_exp = false;
+        validation.validate_nbf = false;
+        validation.validate_aud = false;
+        Self {
+            key: DecodingKey::from_secret(config.secret.as_bytes()),
+            validation,
+        }
+    }
+
+    /// Verify `token` at the time `now`.
+    ///
+    /// Fails with `401 Unauthorized` if the signature doesn't match, the
+    /// token has no `exp` claim, is expired, or isn't valid yet.
+    pub fn verify(&self, token: &str, now: DateTime<Utc>) -> Result<VerifiedClaims> {
+        let claims = jsonwebtoken::decode::<Value>(token, &self.key, &self.validation)
+            .map_err(|_| unauthorized("Invalid bearer token"))?
+            .claims;
+
+        let now = now.timestamp();
+        let exp = claims.get("exp").and_then(Value::as_i64);
+        if exp.is_none_or(|exp| exp + LEEWAY_SECS < now) {
+            return Err(unauthorized("Expired bearer token"));
+        }
+        if let Some(nbf) = claims.get("nbf").and_then(Value::as_i64)
+            && nbf - LEEWAY_SECS > now
+        {
+            return Err(unauthorized("Bearer token not valid yet"));
+        }
+        Ok(VerifiedClaims(claims))
+    }
+
+    /// Verify the bearer token of `req` and add its claims to the extensions,
+    /// with the `sub` claim as the `UserId`.
+    ///
+    /// Claims already verified by an outer layer are reused.
+    pub fn authenticate(&self, req: &mut Request) -> Result<VerifiedClaims> {
+        self.try_authenticate(req)?
+            .ok_or_else(|| unauthorized("Missing bearer token"))
+    }
+
+    /// Like `authenticate`, with `None` for requests without a bearer token.
+    pub fn try_authenticate(&self, req: &mut Request) -> Result<Option<VerifiedClaims>> {
+        if let Some(claims) = req.extensions().get::<VerifiedClaims>() {
+            return Ok(Some(claims.clone()));
+        }
+        let Some(token) = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+        else {
+            return Ok(None);
+        };
+        let now = clock::from_extensions(req.extensions()).now();
+        let claims = self.verify(token.trim(), now)?;
+        if let Some(user_id) = claims
+            .subject()
+            .and_then(|sub| serde_json::from_value::<UserId>(sub.into()).ok())
+        {
+            req.extensions_mut().insert(user_id);
+        }
+        req.extensions_mut().insert(claims.clone());
+        Ok(Some(claims))
+    }
+}
+
+/// The app's `JwtVerifier`, shared with the routes mounted before
+/// `EywaApp::auth` configures it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedVerifier(Arc<OnceLock<JwtVerifier>>);
+
+impl From<JwtVerifier> for SharedVerifier {
+    fn from(verifier: JwtVerifier) -> Self {
+        Self(Arc::new(OnceLock::from(verifier)))
+    }
+}
+
+impl SharedVerifier {
+    /// Configure the verifier; `false` if it already was.
+    pub(crate) fn set(&self, verifier: JwtVerifier) -> bool {
+        self.0.set(verifier).is_ok()
+    }
+
+    pub(crate) fn get(&self) -> Option<&JwtVerifier> {
+        self.0.get()
+    }
+
+    /// Reject the requests to the routes of `router` without a valid bearer token.
+    pub(crate) fn protect<S>(&self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        self.protect_except(router, RouteSet::new())
+    }
+
+    /// Like `protect`, letting the requests to the routes in `public` through.
+    pub(crate) fn protect_except<S>(&self, router: Router<S>, public: RouteSet) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router.route_layer(axum::middleware::from_fn_with_state(
+            (self.clone(), Arc::new(public)),
+            require_jwt_middleware,
+        ))
+    }
+
+    /// Verify the bearer token of the requests to the routes of `router`,
+    /// if any, letting anonymous requests through.
+    pub(crate) fn identify<S>(&self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router.route_layer(axum::middleware::from_fn_with_state(
+            self.clone(),
+            identify_jwt_middleware,
+        ))
+    }
+}
+
+/// Middleware rejecting requests without a valid bearer token with 401.
+///
+/// Fails closed while no verifier is configured. `HEAD` requests to a
+/// public `GET` route are public too.
+async fn require_jwt_middleware(
+    State((verifier, public)): State<(SharedVerifier, Arc<RouteSet>)>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(path) = req.extensions().get::<MatchedPath>()
+        && public.contains(&route_method(req.method().as_str()), path.as_str())
+    {
+        return next.run(req).await;
+    }
+    let Some(verifier) = verifier.get() else {
+        return unauthorized("Token verification is not configured").into_response();
+    };
+    match verifier.authenticate(&mut req) {
+        Ok(_) => next.run(req).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Middleware adding the claims of a valid bearer token to the request.
+///
+/// Requests without a token, or while no verifier is configured, pass
+/// through anonymous; an invalid token is rejected with 401.
+async fn identify_jwt_middleware(
+    State(verifier): State<SharedVerifier>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(verifier) = verifier.get()
+        && let Err(e) = verifier.try_authenticate(&mut req)
+    {
+        return e.into_response();
+    }
+    next.run(req).await
+}
+
+/// Middleware copying the authenticated `UserId` into `RequestContext`.
+///
+/// Runs after the token verification (or `auth_middleware`); requests
+/// without a user pass through untouched. Installed by `EywaApp::auth()`.
+pub async fn user_context_middleware(mut req: Request, next: Next) -> Response {
+    if let Some(user_id) = req.extensions().get::<UserId>().cloned() {
+        tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+        if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+            ctx.user_id = Some(user_id);
+        }
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Extension};
+
+    #[test]
+    fn test_config_from_settings() {
+        let settings = JwtSettings {
+            secret: "s3cret".to_string(),
+        };
+        assert_eq!(JwtConfig::from(settings), JwtConfig::new("s3cret"));
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_requests_pass_through() {
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/whoami",
+                    get(|Extension(ctx): Extension<RequestContext>| async move {
+                        format!("{:?}", ctx.user_id.map(|id| id.to_string()))
+                    }),
+                )
+                .layer(axum::middleware::from_fn(user_context_middleware))
+                .layer(axum::middleware::from_fn(
+                    crate::middleware::request_context_middleware_fn,
+                )),
+        );
+
+        assert_eq!(client.get("/whoami").send().await.text(), "None");
+    }
+
+    #[tokio::test]
+    async fn test_verified_user_in_request_context() {
+        let config = JwtConfig::new("s3cret");
+        let user = "0190f5b4-8a3e-7c41-b2d6-5e8f9a1c2d3e";
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = config.sign(&serde_json::json!({ "sub": user, "exp": exp })).unwrap();
+        let whoami = || {
+            Router::new().route(
+                "/whoami",
+                get(|Extension(ctx): Extension<RequestContext>| async move {
+                    format!("{:?}", ctx.user_id.map(|id| id.to_string()))
+                }),
+            )
+        };
+
+        // The context created before the authentication, then after it
+        let clients = [
+            crate::EywaApp::new(())
+                .merge(whoami())
+                .auth(config.clone())
+                .request_context()
+                .into_test_client(),
+            crate::EywaApp::new(())
+                .merge(whoami())
+                .request_context()
+                .auth(config.clone())
+                .into_test_client(),
+        ];
+        for client in clients {
+            let response = client.get("/whoami").bearer(&token).send().await;
+            assert_eq!(response.assert_status(StatusCode::OK).text(), format!("Some({user:?})"));
+            client.get("/whoami").send().await.assert_status(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protected_routes_fail_closed() {
+        let verifier = SharedVerifier::default();
+        let client = TestClient::new(
+            verifier.protect(Router::new().route("/admin", get(|| async { "admin" }))),
+        );
+        let config = JwtConfig::new("s3cret");
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = config.sign(&serde_json::json!({ "sub": "42", "exp": exp })).unwrap();
+
+        // No verifier configured yet
+        let response = client.get("/admin").bearer(&token).send().await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        verifier.set(JwtVerifier::new(&config));
+        let response = client.get("/admin").bearer(&token).send().await;
+        response.assert_status(StatusCode::OK);
+        let forged = JwtConfig::new("guessed").sign(&serde_json::json!({ "exp": exp })).unwrap();
+        let response = client.get("/admin").bearer(&forged).send().await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        client.get("/admin").send().await.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_token_expiry_follows_the_clock() {
+        use crate::clock::{SharedClock, TestClock};
+        use crate::di::Container;
+
+        let clock = TestClock::new();
+        let mut container = Container::new();
+        container.provide::<SharedClock>(Arc::new(clock.clone()));
+        let config = JwtConfig::new("s3cret");
+        let client = TestClient::new(
+            SharedVerifier::from(JwtVerifier::new(&config))
+                .protect(Router::new().route("/admin", get(|| async { "admin" })))
+                .layer(Extension(Arc::new(container))),
+        );
+        let exp = clock.now().timestamp() + 300;
+        let token = config.sign(&serde_json::json!({ "sub": "42", "exp": exp })).unwrap();
+
+        client.get("/admin").bearer(&token).send().await.assert_status(StatusCode::OK);
+        // Expired, past the leeway
+        clock.advance(std::time::Duration::from_secs(300 + LEEWAY_SECS as u64 + 1));
+        let response = client.get("/admin").bearer(&token).send().await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
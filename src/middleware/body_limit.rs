@@ -0,0 +1,61 @@
+//! Reloadable request body limit.
+//!
+//! axum's `DefaultBodyLimit` is fixed when the router is built. With
+//! `EywaApp::body_limit`, the limit is read from a `Watch<usize>` on every
+//! request, so a config reload applies to the next request. Reading past the
+//! limit fails and the extractors reject the request with
+//! `413 Payload Too Large`.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::Limited;
+
+use crate::reload::Watch;
+
+/// Middleware capping request bodies at the current limit, in bytes.
+///
+/// Installed with `DefaultBodyLimit::disable()` by `EywaApp::body_limit()`,
+/// so this limit replaces axum's default instead of stacking with it.
+pub async fn body_limit_middleware(
+    State(limit): State<Watch<usize>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let limit = limit.get();
+    let (parts, body) = req.into_parts();
+    let body = Body::new(Limited::new(body, limit));
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{extract::DefaultBodyLimit, http::StatusCode, routing::post, Router};
+
+    #[tokio::test]
+    async fn test_limit_follows_reloads() {
+        let limit = Watch::named("body_limit", 1024usize);
+        let client = TestClient::new(
+            Router::new()
+                .route("/ingest", post(|body: String| async move { body.len().to_string() }))
+                .layer(DefaultBodyLimit::disable())
+                .layer(axum::middleware::from_fn_with_state(
+                    limit.clone(),
+                    body_limit_middleware,
+                )),
+        );
+
+        let response = client.post("/ingest").body("0".repeat(4096)).send().await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        limit.set(8192);
+        let response = client.post("/ingest").body("0".repeat(4096)).send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "4096");
+    }
+}
@@ -0,0 +1,233 @@
+//! Bulkheads: bounded concurrency pools per route group.
+//!
+//! Each bulkhead caps the number of in-flight requests of the routes
+//! assigned to it, so an expensive endpoint (report generation, exports)
+//! can't take every worker and connection from cheap latency-sensitive
+//! endpoints served by the same process. A request arriving when its pool is
+//! full is rejected with `503 Service Unavailable` instead of queueing, with
+//! a `Retry-After` of the pool's average request latency.
+//!
+//! Routes are assigned with `#[route(bulkhead = "reports")]`,
+//! `EywaApp::bulkhead_path` or `EywaApp::route_bulkhead`. Routes without a
+//! bulkhead are not limited.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use tokio::sync::Semaphore;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::visibility::RouteSet;
+
+/// Weight of a new sample in a pool's average latency.
+const LATENCY_WEIGHT: f64 = 0.1;
+
+#[derive(Debug)]
+struct Pool {
+    max_concurrency: usize,
+    semaphore: Arc<Semaphore>,
+    /// Average request latency, in seconds
+    latency: Mutex<Option<f64>>,
+}
+
+impl Pool {
+    fn record(&self, latency: Duration) {
+        let mut average = self.latency.lock().unwrap();
+        *average = Some(match *average {
+            Some(average) => {
+                average * (1.0 - LATENCY_WEIGHT) + latency.as_secs_f64() * LATENCY_WEIGHT
+            }
+            None => latency.as_secs_f64(),
+        });
+    }
+
+    /// How long rejected clients should wait: the average request latency.
+    fn retry_after(&self) -> Duration {
+        self.latency
+            .lock()
+            .unwrap()
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default()
+    }
+}
+
+/// Bulkheads of the application and the routes assigned to them.
+#[derive(Debug, Default)]
+pub struct Bulkheads {
+    pools: BTreeMap<String, Pool>,
+    // Routes may be assigned before their bulkhead is declared
+    routes: BTreeMap<String, RouteSet>,
+}
+
+impl Bulkheads {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare (or resize) a bulkhead.
+    pub fn define(&mut self, name: impl Into<String>, max_concurrency: usize) {
+        self.pools.insert(
+            name.into(),
+            Pool {
+                max_concurrency,
+                semaphore: Arc::new(Semaphore::new(max_concurrency)),
+                latency: Mutex::new(None),
+            },
+        );
+    }
+
+    /// Assign a route to a bulkhead.
+    pub fn assign(&mut self, name: &str, method: &str, path: impl Into<String>) {
+        self.routes.entry(name.to_string()).or_default().insert(method, path);
+    }
+
+    /// Assign every route whose path matches the glob to a bulkhead.
+    pub fn assign_pattern(&mut self, name: &str, pattern: impl Into<String>) {
+        self.routes
+            .entry(name.to_string())
+            .or_default()
+            .insert_pattern(pattern);
+    }
+
+    /// Returns `true` if no bulkhead is declared.
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+
+    /// Names of the bulkheads routes were assigned to but never declared.
+    pub fn undeclared(&self) -> impl Iterator<Item = &str> {
+        self.routes
+            .keys()
+            .filter(|name| !self.pools.contains_key(*name))
+            .map(String::as_str)
+    }
+
+    /// The bulkhead of a route.
+    fn pool(&self, method: &str, path: &str) -> Option<(&str, &Pool)> {
+        self.routes
+            .iter()
+            .filter(|(_, routes)| routes.contains(method, path))
+            .find_map(|(name, _)| self.pools.get_key_value(name))
+            .map(|(name, pool)| (name.as_str(), pool))
+    }
+
+    /// Requests currently running in a bulkhead.
+    pub fn in_flight(&self, name: &str) -> Option<usize> {
+        self.pools
+            .get(name)
+            .map(|pool| pool.max_concurrency - pool.semaphore.available_permits())
+    }
+}
+
+fn bulkhead_full(name: &str, retry_after: Duration) -> Response {
+    ErrorResponse::new(
+        error_codes::SERVICE_UNAVAILABLE,
+        format!("Too many concurrent requests in bulkhead '{name}'"),
+    )
+    .retry_after(retry_after)
+    .into_response_with(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Middleware holding a permit of the route's bulkhead while the handler runs.
+///
+/// Installed by `EywaApp::bulkhead()`.
+pub async fn bulkhead_middleware(
+    State(bulkheads): State<Arc<Bulkheads>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>() else {
+        return next.run(req).await;
+    };
+    let Some((name, pool)) = bulkheads.pool(req.method().as_str(), route.as_str()) else {
+        return next.run(req).await;
+    };
+
+    match pool.semaphore.clone().try_acquire_owned() {
+        Ok(_permit) => {
+            let started = Instant::now();
+            let response = next.run(req).await;
+            pool.record(started.elapsed());
+            response
+        }
+        Err(_) => {
+            tracing::warn!(bulkhead = name, "bulkhead full, rejecting request");
+            bulkhead_full(name, pool.retry_after())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    fn client(bulkheads: Bulkheads) -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route(
+                    "/reports/{id}",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        "report"
+                    }),
+                )
+                .route("/health", get(|| async { "ok" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(bulkheads),
+                    bulkhead_middleware,
+                )),
+        )
+    }
+
+    #[test]
+    fn test_routes_assigned_before_declaration() {
+        let mut bulkheads = Bulkheads::new();
+        bulkheads.assign("reports", "GET", "/reports/{id}");
+        bulkheads.assign_pattern("exports", "/exports/**");
+        assert_eq!(bulkheads.undeclared().collect::<Vec<_>>(), ["exports", "reports"]);
+
+        bulkheads.define("reports", 2);
+        assert_eq!(bulkheads.undeclared().collect::<Vec<_>>(), ["exports"]);
+        assert_eq!(
+            bulkheads.pool("GET", "/reports/{id}").map(|(name, _)| name),
+            Some("reports")
+        );
+        assert_eq!(bulkheads.in_flight("reports"), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_full_bulkhead_rejects_only_its_routes() {
+        let mut bulkheads = Bulkheads::new();
+        bulkheads.define("reports", 1);
+        bulkheads.assign_pattern("reports", "/reports/**");
+        let client = client(bulkheads);
+
+        let (first, second, health) = tokio::join!(
+            client.get("/reports/1").send(),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                client.get("/reports/2").send().await
+            },
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                client.get("/health").send().await
+            },
+        );
+
+        first.assert_status(StatusCode::OK);
+        second.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(second.header("retry-after"), Some("1"));
+        health.assert_status(StatusCode::OK);
+    }
+}
@@ -0,0 +1,266 @@
+//! In-process canary routing.
+//!
+//! A risky change ships as an alternate handler registered next to the
+//! stable one with `EywaApp::canary`. The canary gets a share of the route's
+//! traffic, and every request opting in:
+//! - `X-Canary: <name>` header or `canary=<name>` cookie: served by the canary
+//! - any other value (e.g. `X-Canary: stable`): served by the stable handler
+//! - otherwise `percent` of the requests, either at random or sticky by the
+//!   value of a header (e.g. the tenant), so a client sees a single variant
+//!
+//! The assignment is stored in `RequestContext::canary` and recorded on the
+//! request span, so every log line of a canary request carries `canary=<name>`.
+//!
+//! The canary handler runs behind the application middleware (scopes,
+//! policies, bulkheads, ...) but not behind middleware a controller adds to
+//! its own routes: add those to the canary's `MethodRouter` as well.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::COOKIE, HeaderMap},
+    middleware::Next,
+    response::Response,
+    Router,
+};
+use tower::ServiceExt;
+
+use crate::middleware::RequestContext;
+
+/// Header selecting a variant.
+pub const CANARY_HEADER: &str = "x-canary";
+
+/// Cookie selecting a variant.
+pub const CANARY_COOKIE: &str = "canary";
+
+/// An alternate handler and the share of traffic it gets.
+///
+/// # Example
+///
+/// ```ignore
+/// // 5% of the checkouts, the same tenants every time
+/// Canary::new("checkout-v2").percent(5.0).sticky_on("x-tenant-id")
+/// ```
+#[derive(Debug, Clone)]
+pub struct Canary {
+    name: String,
+    percent: f64,
+    sticky_header: Option<String>,
+}
+
+impl Canary {
+    /// A canary only serving the requests opting in.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            percent: 0.0,
+            sticky_header: None,
+        }
+    }
+
+    /// Also serve `percent` (0-100) of the other requests.
+    pub fn percent(mut self, percent: f64) -> Self {
+        self.percent = percent.clamp(0.0, 100.0);
+        self
+    }
+
+    /// Assign requests by the value of `header` instead of at random.
+    pub fn sticky_on(mut self, header: impl Into<String>) -> Self {
+        self.sticky_header = Some(header.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Name of the canary.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns `true` if the request goes to the canary.
+    pub fn assign(&self, headers: &HeaderMap) -> bool {
+        if let Some(selected) = selected_variant(headers) {
+            return selected == self.name;
+        }
+        if self.percent <= 0.0 {
+            return false;
+        }
+
+        let sticky = self
+            .sticky_header
+            .as_deref()
+            .and_then(|header| headers.get(header))
+            .and_then(|value| value.to_str().ok());
+        let roll = match sticky {
+            Some(value) => {
+                let mut hasher = DefaultHasher::new();
+                (&self.name, value).hash(&mut hasher);
+                (hasher.finish() % 10_000) as f64 / 100.0
+            }
+            None => rand::random::<f64>() * 100.0,
+        };
+        roll < self.percent
+    }
+}
+
+/// Variant selected by the `X-Canary` header or the `canary` cookie.
+fn selected_variant(headers: &HeaderMap) -> Option<&str> {
+    if let Some(value) = headers.get(CANARY_HEADER).and_then(|v| v.to_str().ok()) {
+        return Some(value.trim());
+    }
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == CANARY_COOKIE)
+        .map(|(_, value)| value)
+}
+
+struct CanaryRoute {
+    canary: Canary,
+    router: Router,
+}
+
+/// Canaries of the application, by route.
+#[derive(Default)]
+pub struct Canaries {
+    routes: HashMap<(String, String), CanaryRoute>,
+}
+
+impl Canaries {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the canary of a route, served by `router` (which routes `path`).
+    pub fn insert(
+        &mut self,
+        method: &str,
+        path: impl Into<String>,
+        canary: Canary,
+        router: Router,
+    ) {
+        self.routes.insert(
+            (method.to_uppercase(), path.into()),
+            CanaryRoute { canary, router },
+        );
+    }
+
+    /// Returns `true` if no canary is registered.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// Route middleware sending assigned requests to the canary handler.
+///
+/// Installed by `EywaApp::canary()`.
+pub async fn canary_middleware(
+    State(canaries): State<Arc<Canaries>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>() else {
+        return next.run(req).await;
+    };
+    let key = (req.method().as_str().to_string(), route.as_str().to_string());
+    let Some(CanaryRoute { canary, router }) = canaries.routes.get(&key) else {
+        return next.run(req).await;
+    };
+    if !canary.assign(req.headers()) {
+        return next.run(req).await;
+    }
+
+    if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+        ctx.canary = Some(canary.name.clone());
+    }
+    tracing::Span::current().record("canary", canary.name.as_str());
+    tracing::debug!(canary = %canary.name, route = %key.1, "request assigned to canary");
+
+    match router.clone().oneshot(req).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::HeaderValue, routing::get};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_opt_in_and_out() {
+        let canary = Canary::new("v2").percent(100.0);
+
+        assert!(canary.assign(&headers(&[])));
+        assert!(canary.assign(&headers(&[("x-canary", "v2")])));
+        assert!(!canary.assign(&headers(&[("x-canary", "stable")])));
+        assert!(!canary.assign(&headers(&[("cookie", "theme=dark; canary=stable")])));
+        assert!(Canary::new("v2").assign(&headers(&[("cookie", "theme=dark; canary=v2")])));
+    }
+
+    #[test]
+    fn test_sticky_assignment() {
+        let canary = Canary::new("v2").percent(50.0).sticky_on("X-Tenant-ID");
+
+        let tenants = ["acme", "globex", "initech", "umbrella", "hooli", "stark"];
+        let assigned: Vec<bool> = tenants
+            .iter()
+            .map(|tenant| canary.assign(&headers(&[("x-tenant-id", tenant)])))
+            .collect();
+        for _ in 0..10 {
+            let again: Vec<bool> = tenants
+                .iter()
+                .map(|tenant| canary.assign(&headers(&[("x-tenant-id", tenant)])))
+                .collect();
+            assert_eq!(again, assigned);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_canary_handler_serves_assigned_requests() {
+        let mut canaries = Canaries::new();
+        canaries.insert(
+            "GET",
+            "/projects/{id}",
+            Canary::new("v2"),
+            Router::new().route(
+                "/projects/{id}",
+                get(|axum::extract::Path(id): axum::extract::Path<u32>| async move {
+                    format!("v2 {id}")
+                }),
+            ),
+        );
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/projects/{id}",
+                    get(|axum::extract::Path(id): axum::extract::Path<u32>| async move {
+                        format!("v1 {id}")
+                    }),
+                )
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(canaries),
+                    canary_middleware,
+                )),
+        );
+
+        assert_eq!(client.get("/projects/7").send().await.text(), "v1 7");
+        let response = client.get("/projects/7").header(CANARY_HEADER, "v2").send().await;
+        assert_eq!(response.text(), "v2 7");
+    }
+}
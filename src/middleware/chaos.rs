@@ -0,0 +1,221 @@
+//! Fault injection middleware for validating client retries and SLO alerts.
+//!
+//! Injects latency, error statuses or dropped connections into a percentage
+//! of requests matching route patterns. Only active in development and
+//! staging environments (see `ChaosConfig::is_allowed`).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Header added to every response affected by an injected fault.
+pub const CHAOS_HEADER: &str = "x-chaos-fault";
+
+/// Environments in which faults are injected by default.
+const DEFAULT_ENVIRONMENTS: [&str; 2] = ["development", "staging"];
+
+/// A fault to inject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Delay the request before handling it
+    Latency(Duration),
+    /// Reply with the given status without calling the handler
+    Error(StatusCode),
+    /// Abort the connection mid-response
+    Abort,
+}
+
+impl Fault {
+    fn name(&self) -> &'static str {
+        match self {
+            Fault::Latency(_) => "latency",
+            Fault::Error(_) => "error",
+            Fault::Abort => "abort",
+        }
+    }
+}
+
+/// Injects `fault` into `probability` (0.0 - 1.0) of the requests matching `pattern`.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    pattern: String,
+    probability: f64,
+    fault: Fault,
+}
+
+impl FaultRule {
+    /// Create a rule.
+    ///
+    /// `pattern` is a route template (`/projects/{id}`) or a path prefix
+    /// ending with `*` (`/api/v1/reports*`). `*` alone matches every route.
+    pub fn new(pattern: impl Into<String>, probability: f64, fault: Fault) -> Self {
+        Self {
+            pattern: pattern.into(),
+            probability: probability.clamp(0.0, 1.0),
+            fault,
+        }
+    }
+
+    /// Returns `true` if the rule applies to the request.
+    fn matches(&self, route: Option<&str>, path: &str) -> bool {
+        match self.pattern.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => route == Some(self.pattern.as_str()) || path == self.pattern,
+        }
+    }
+}
+
+/// Fault injection configuration.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::middleware::chaos::{ChaosConfig, Fault, FaultRule};
+///
+/// let chaos = ChaosConfig::new()
+///     .rule(FaultRule::new("/api/v1/projects*", 0.1, Fault::Latency(Duration::from_millis(800))))
+///     .rule(FaultRule::new("/api/v1/payments/{id}", 0.05, Fault::Error(StatusCode::SERVICE_UNAVAILABLE)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    rules: Vec<FaultRule>,
+    environments: Vec<String>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            environments: DEFAULT_ENVIRONMENTS.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+}
+
+impl ChaosConfig {
+    /// Create an empty configuration (active in development and staging).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a fault rule. Rules are evaluated in order; the first matching rule
+    /// whose dice roll succeeds is applied.
+    pub fn rule(mut self, rule: FaultRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Replace the environments (`RUN_MODE` values) in which faults are injected.
+    pub fn environments<I, T>(mut self, environments: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.environments = environments.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns `true` if faults may be injected in `run_mode`.
+    pub fn is_allowed(&self, run_mode: &str) -> bool {
+        self.environments.iter().any(|env| env == run_mode)
+    }
+
+    /// Pick the fault to inject for a request, if any.
+    fn select(&self, route: Option<&str>, path: &str) -> Option<&Fault> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(route, path))
+            .find(|rule| rand::random::<f64>() < rule.probability)
+            .map(|rule| &rule.fault)
+    }
+}
+
+/// Axum middleware injecting the configured faults.
+///
+/// Installed by `EywaApp::chaos()`.
+pub async fn chaos_middleware(
+    State(config): State<Arc<ChaosConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+
+    let Some(fault) = config.select(route.as_deref(), req.uri().path()).cloned() else {
+        return next.run(req).await;
+    };
+
+    tracing::warn!(
+        fault = fault.name(),
+        path = %req.uri().path(),
+        "injecting chaos fault"
+    );
+
+    let mut response = match fault {
+        Fault::Latency(delay) => {
+            tokio::time::sleep(delay).await;
+            next.run(req).await
+        }
+        Fault::Error(status) => status.into_response(),
+        Fault::Abort => {
+            // A body stream that fails makes hyper drop the connection
+            let stream = futures_util::stream::once(async {
+                Err::<axum::body::Bytes, _>(std::io::Error::new(
+                    std::io::ErrorKind::ConnectionAborted,
+                    "chaos: connection aborted",
+                ))
+            });
+            Response::new(Body::from_stream(stream))
+        }
+    };
+
+    response
+        .headers_mut()
+        .insert(CHAOS_HEADER, HeaderValue::from_static(fault.name()));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_matches_route_template() {
+        let rule = FaultRule::new("/projects/{id}", 1.0, Fault::Abort);
+        assert!(rule.matches(Some("/projects/{id}"), "/projects/42"));
+        assert!(!rule.matches(Some("/users/{id}"), "/users/42"));
+    }
+
+    #[test]
+    fn test_rule_matches_prefix() {
+        let rule = FaultRule::new("/api/v1/reports*", 1.0, Fault::Abort);
+        assert!(rule.matches(None, "/api/v1/reports/monthly"));
+        assert!(!rule.matches(None, "/api/v1/projects"));
+        assert!(FaultRule::new("*", 1.0, Fault::Abort).matches(None, "/anything"));
+    }
+
+    #[test]
+    fn test_probability_bounds() {
+        let always = ChaosConfig::new().rule(FaultRule::new("*", 2.0, Fault::Abort));
+        assert_eq!(always.select(None, "/"), Some(&Fault::Abort));
+
+        let never = ChaosConfig::new().rule(FaultRule::new("*", 0.0, Fault::Abort));
+        assert_eq!(never.select(None, "/"), None);
+    }
+
+    #[test]
+    fn test_only_allowed_in_configured_environments() {
+        let config = ChaosConfig::new();
+        assert!(config.is_allowed("development"));
+        assert!(config.is_allowed("staging"));
+        assert!(!config.is_allowed("production"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Configurable response compression.
+//!
+//! `EywaApp::compression()` uses the defaults below; `compression_with` takes
+//! settings usually loaded per environment:
+//!
+//! ```toml
+//! [compression]
+//! level = 4                  # algorithm-specific quality, default per algorithm
+//! min_size = 1024            # bytes
+//! brotli = false             # CPU-heavy, off where CPU matters more than bandwidth
+//! allow_content_types = ["application/json", "text/"]
+//! deny_content_types = ["application/pdf"]
+//! ```
+//!
+//! Already-encoded responses (with `Content-Encoding`) and server-sent event
+//! streams are never compressed.
+
+use axum::{
+    body::HttpBody,
+    http::{header::CONTENT_TYPE, Response},
+};
+use serde::{Deserialize, Serialize};
+use tower_http::compression::{
+    predicate::{Predicate, SizeAbove},
+    CompressionLayer, CompressionLevel,
+};
+
+/// Content types that are already compressed.
+const COMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "video/",
+    "audio/",
+    "font/woff",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/grpc",
+];
+
+/// Response compression settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionSettings {
+    /// Quality passed to the encoder (e.g. 1-11 for brotli, 1-9 for gzip)
+    #[serde(default)]
+    pub level: Option<i32>,
+    /// Smallest response body compressed, in bytes
+    #[serde(default = "default_min_size")]
+    pub min_size: u16,
+    #[serde(default = "enabled")]
+    pub gzip: bool,
+    #[serde(default = "enabled")]
+    pub deflate: bool,
+    #[serde(default = "enabled")]
+    pub brotli: bool,
+    /// Content type prefixes to compress (all if empty)
+    #[serde(default)]
+    pub allow_content_types: Vec<String>,
+    /// Content type prefixes never compressed
+    #[serde(default = "default_deny_content_types")]
+    pub deny_content_types: Vec<String>,
+}
+
+fn default_min_size() -> u16 {
+    32
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_deny_content_types() -> Vec<String> {
+    COMPRESSED_CONTENT_TYPES.iter().map(|t| t.to_string()).collect()
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            min_size: default_min_size(),
+            gzip: true,
+            deflate: true,
+            brotli: true,
+            allow_content_types: Vec::new(),
+            deny_content_types: default_deny_content_types(),
+        }
+    }
+}
+
+impl CompressionSettings {
+    /// Set the encoder quality.
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Only compress bodies of at least `bytes`.
+    pub fn min_size(mut self, bytes: u16) -> Self {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Disable brotli.
+    pub fn without_brotli(mut self) -> Self {
+        self.brotli = false;
+        self
+    }
+
+    /// Only compress content types starting with `prefix` (and other allowed ones).
+    pub fn allow_content_type(mut self, prefix: impl Into<String>) -> Self {
+        self.allow_content_types.push(prefix.into());
+        self
+    }
+
+    /// Never compress content types starting with `prefix`.
+    pub fn deny_content_type(mut self, prefix: impl Into<String>) -> Self {
+        self.deny_content_types.push(prefix.into());
+        self
+    }
+
+    /// Build the compression layer.
+    pub fn layer(&self) -> CompressionLayer<impl Predicate> {
+        let predicate = SizeAbove::new(self.min_size).and(ContentTypePredicate {
+            allow: self.allow_content_types.clone(),
+            deny: self.deny_content_types.clone(),
+        });
+        let layer = CompressionLayer::new()
+            .gzip(self.gzip)
+            .deflate(self.deflate)
+            .br(self.brotli)
+            .compress_when(predicate);
+        match self.level {
+            Some(level) => layer.quality(CompressionLevel::Precise(level)),
+            None => layer,
+        }
+    }
+}
+
+/// Compress allowed, non-denied content types, never event streams.
+#[derive(Debug, Clone)]
+struct ContentTypePredicate {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl Predicate for ContentTypePredicate {
+    fn should_compress<B>(&self, response: &Response<B>) -> bool
+    where
+        B: HttpBody,
+    {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let matches = |prefix: &String| content_type.starts_with(prefix.as_str());
+
+        !content_type.starts_with("text/event-stream")
+            && !self.deny.iter().any(matches)
+            && (self.allow.is_empty() || self.allow.iter().any(matches))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    fn client(settings: CompressionSettings) -> TestClient {
+        let body = "x".repeat(2048);
+        TestClient::new(
+            Router::new()
+                .route(
+                    "/json",
+                    get({
+                        let body = body.clone();
+                        || async move { ([(CONTENT_TYPE, "application/json")], body) }
+                    }),
+                )
+                .route(
+                    "/pdf",
+                    get(|| async move { ([(CONTENT_TYPE, "application/pdf")], body) }),
+                )
+                .route("/small", get(|| async { "small" }))
+                .layer(settings.layer()),
+        )
+    }
+
+    async fn encoding(client: &TestClient, path: &str, accept: &str) -> Option<String> {
+        let response = client.get(path).header("accept-encoding", accept).send().await;
+        response.header("content-encoding").map(str::to_string)
+    }
+
+    #[tokio::test]
+    async fn test_settings_applied() {
+        let client = client(
+            CompressionSettings::default()
+                .min_size(1024)
+                .without_brotli()
+                .deny_content_type("application/pdf"),
+        );
+
+        assert_eq!(encoding(&client, "/json", "br, gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(encoding(&client, "/json", "br").await, None);
+        assert_eq!(encoding(&client, "/pdf", "gzip").await, None);
+        assert_eq!(encoding(&client, "/small", "gzip").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_allow_list() {
+        let client = client(CompressionSettings::default().allow_content_type("text/"));
+
+        assert_eq!(encoding(&client, "/json", "gzip").await, None);
+    }
+}
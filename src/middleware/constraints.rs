@@ -0,0 +1,687 @@
+//! Schema constraints of the spec enforced on requests.
+//!
+//! `#[schema(max_length = 120, pattern = "^[a-z-]+$")]` and friends document
+//! constraints that `axum::Json<T>` never checks; only `ValidatedJson<T>`
+//! runs the `validator` rules. With `EywaApp::enforce_schema_constraints`,
+//! JSON request bodies and query parameters are checked against the schemas
+//! of the operation before the handler runs, whatever extractor it uses:
+//!
+//! - strings: `minLength`, `maxLength`, `pattern`, `enum`
+//! - numbers: `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+//!   `multipleOf`, `enum`
+//! - arrays: `minItems`, `maxItems` and their items
+//! - objects: `required` and their properties, following `$ref`s and
+//!   `allOf`/`oneOf`/`anyOf` compositions
+//!
+//! Invalid requests get the `422` `ValidationErrorResponse` of the
+//! validating extractors, with one `FieldError` per violation (`length`,
+//! `range`, `regex`, `enum`, `required`, `type`, ...). Syntax errors are left
+//! to the extractors, and the `422` response is documented on the checked
+//! operations.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use regex::Regex;
+use serde_json::Value;
+use utoipa::openapi::{
+    path::{Operation, ParameterIn},
+    schema::{ArrayItems, Number, Schema, SchemaType, Type},
+    Components, OpenApi, RefOr, Required,
+};
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::responses::response_ref;
+use crate::validation::{FieldError, ValidationErrorResponse, VALIDATION_FAILED_RESPONSE};
+
+/// Largest JSON body buffered for validation by default.
+pub const DEFAULT_MAX_VALIDATED_BODY: usize = 2 * 1024 * 1024;
+
+/// Deepest `$ref` chain followed, guarding against recursive schemas.
+const MAX_DEPTH: usize = 32;
+
+/// Constraints of one operation.
+#[derive(Debug, Clone, Default)]
+struct OperationSchemas {
+    /// Schema of the JSON request body
+    body: Option<RefOr<Schema>>,
+    /// Query parameters: name, schema and whether it is required
+    query: Vec<(String, RefOr<Schema>, bool)>,
+}
+
+impl OperationSchemas {
+    fn from_operation(operation: &Operation) -> Self {
+        let body = operation.request_body.as_ref().and_then(|body| {
+            body.content
+                .iter()
+                .find(|(media_type, _)| is_json(media_type))
+                .and_then(|(_, content)| content.schema.clone())
+        });
+        let query = operation
+            .parameters
+            .iter()
+            .flatten()
+            .filter(|parameter| matches!(parameter.parameter_in, ParameterIn::Query))
+            .filter_map(|parameter| {
+                let required = matches!(parameter.required, Required::True);
+                Some((parameter.name.clone(), parameter.schema.clone()?, required))
+            })
+            .collect();
+        Self { body, query }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.body.is_none() && self.query.is_empty()
+    }
+}
+
+fn is_json(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+fn number(value: &Number) -> f64 {
+    match value {
+        Number::Int(n) => *n as f64,
+        Number::UInt(n) => *n as f64,
+        Number::Float(n) => *n,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(schema_type: &Type, value: &Value) -> bool {
+    match schema_type {
+        Type::Object => value.is_object(),
+        Type::String => value.is_string(),
+        // `1.0` is a valid integer in JSON Schema
+        Type::Integer => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        Type::Number => value.is_number(),
+        Type::Boolean => value.is_boolean(),
+        Type::Array => value.is_array(),
+        Type::Null => value.is_null(),
+    }
+}
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+/// Schemas of every operation of a spec, checked against requests.
+#[derive(Debug)]
+pub struct SchemaConstraints {
+    schemas: BTreeMap<String, RefOr<Schema>>,
+    operations: HashMap<(String, String), OperationSchemas>,
+    patterns: Mutex<HashMap<String, Option<Regex>>>,
+    max_body: usize,
+}
+
+impl SchemaConstraints {
+    /// Read the schemas of every operation of `openapi`.
+    pub fn new(openapi: &OpenApi) -> Self {
+        let mut operations = HashMap::new();
+        for (path, item) in &openapi.paths.paths {
+            let methods = [
+                ("GET", &item.get),
+                ("PUT", &item.put),
+                ("POST", &item.post),
+                ("DELETE", &item.delete),
+                ("PATCH", &item.patch),
+            ];
+            for (method, operation) in methods {
+                let Some(schemas) = operation.as_ref().map(OperationSchemas::from_operation) else {
+                    continue;
+                };
+                if !schemas.is_empty() {
+                    operations.insert((method.to_string(), path.clone()), schemas);
+                }
+            }
+        }
+        Self {
+            schemas: openapi
+                .components
+                .as_ref()
+                .map(|components| components.schemas.clone())
+                .unwrap_or_default(),
+            operations,
+            patterns: Mutex::new(HashMap::new()),
+            max_body: DEFAULT_MAX_VALIDATED_BODY,
+        }
+    }
+
+    /// Buffer JSON bodies up to `max_body` bytes for validation; larger
+    /// bodies are rejected with `413 Payload Too Large`.
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// Whether `value` matches `pattern`. Invalid patterns never fail.
+    fn matches_pattern(&self, pattern: &str, value: &str) -> bool {
+        let mut patterns = self.patterns.lock().unwrap();
+        let regex = patterns.entry(pattern.to_string()).or_insert_with(|| {
+            Regex::new(pattern)
+                .inspect_err(|e| tracing::warn!("Ignoring invalid schema pattern {pattern}: {e}"))
+                .ok()
+        });
+        regex.as_ref().is_none_or(|regex| regex.is_match(value))
+    }
+
+    /// Check `value` against `schema`, collecting the violations under `path`.
+    pub fn validate(&self, schema: &RefOr<Schema>, value: &Value) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        self.check(schema, value, "", 0, &mut errors);
+        errors
+    }
+
+    fn check(
+        &self,
+        schema: &RefOr<Schema>,
+        value: &Value,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<FieldError>,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let schema = match schema {
+            RefOr::T(schema) => schema,
+            RefOr::Ref(reference) => {
+                let name = reference.ref_location.rsplit('/').next().unwrap_or_default();
+                if let Some(schema) = self.schemas.get(name) {
+                    self.check(schema, value, path, depth + 1, errors);
+                }
+                return;
+            }
+        };
+
+        match schema {
+            Schema::Object(object) => {
+                if !self.check_type(&object.schema_type, value, path, errors) {
+                    return;
+                }
+                if let Some(values) = &object.enum_values
+                    && !values.contains(value)
+                {
+                    errors.push(FieldError::new(path, "enum").param("values", values.clone()));
+                }
+                match value {
+                    Value::String(string) => {
+                        let length = string.chars().count();
+                        let too_short = object.min_length.is_some_and(|min| length < min);
+                        let too_long = object.max_length.is_some_and(|max| length > max);
+                        if too_short || too_long {
+                            let mut error = FieldError::new(path, "length");
+                            if let Some(min) = object.min_length {
+                                error = error.param("min", min);
+                            }
+                            if let Some(max) = object.max_length {
+                                error = error.param("max", max);
+                            }
+                            errors.push(error);
+                        }
+                        if let Some(pattern) = &object.pattern
+                            && !self.matches_pattern(pattern, string)
+                        {
+                            errors.push(
+                                FieldError::new(path, "regex").param("pattern", pattern.clone()),
+                            );
+                        }
+                    }
+                    Value::Number(n) => {
+                        let n = n.as_f64().unwrap_or_default();
+                        let bound = |bound: &Option<Number>| bound.as_ref().map(number);
+                        let out_of_range = bound(&object.minimum).is_some_and(|min| n < min)
+                            || bound(&object.maximum).is_some_and(|max| n > max)
+                            || bound(&object.exclusive_minimum).is_some_and(|min| n <= min)
+                            || bound(&object.exclusive_maximum).is_some_and(|max| n >= max);
+                        if out_of_range {
+                            let mut error = FieldError::new(path, "range");
+                            let bounds = [
+                                ("min", &object.minimum),
+                                ("max", &object.maximum),
+                                ("exclusive_min", &object.exclusive_minimum),
+                                ("exclusive_max", &object.exclusive_maximum),
+                            ];
+                            for (name, value) in bounds {
+                                if let Some(value) = bound(value) {
+                                    error = error.param(name, value);
+                                }
+                            }
+                            errors.push(error);
+                        }
+                        if let Some(multiple) = bound(&object.multiple_of)
+                            && multiple != 0.0
+                            && (n / multiple).fract().abs() > f64::EPSILON
+                        {
+                            errors.push(
+                                FieldError::new(path, "multiple_of").param("multiple_of", multiple),
+                            );
+                        }
+                    }
+                    Value::Object(fields) => {
+                        for name in &object.required {
+                            if !fields.contains_key(name) {
+                                errors.push(FieldError::new(join(path, name), "required"));
+                            }
+                        }
+                        for (name, field) in fields {
+                            if let Some(schema) = object.properties.get(name) {
+                                self.check(schema, field, &join(path, name), depth + 1, errors);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Schema::Array(array) => {
+                if !self.check_type(&array.schema_type, value, path, errors) {
+                    return;
+                }
+                let Value::Array(items) = value else {
+                    return;
+                };
+                let too_few = array.min_items.is_some_and(|min| items.len() < min);
+                let too_many = array.max_items.is_some_and(|max| items.len() > max);
+                if too_few || too_many {
+                    let mut error = FieldError::new(path, "length");
+                    if let Some(min) = array.min_items {
+                        error = error.param("min", min);
+                    }
+                    if let Some(max) = array.max_items {
+                        error = error.param("max", max);
+                    }
+                    errors.push(error);
+                }
+                if let ArrayItems::RefOrSchema(schema) = &array.items {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(schema, item, &format!("{path}[{index}]"), depth + 1, errors);
+                    }
+                }
+            }
+            Schema::AllOf(all_of) => {
+                for schema in &all_of.items {
+                    self.check(schema, value, path, depth + 1, errors);
+                }
+            }
+            Schema::OneOf(one_of) => self.check_any(&one_of.items, value, path, depth, errors),
+            Schema::AnyOf(any_of) => self.check_any(&any_of.items, value, path, depth, errors),
+            _ => {}
+        }
+    }
+
+    /// Whether `value` has one of the schema's types, reporting a `type` error if not.
+    fn check_type(
+        &self,
+        schema_type: &SchemaType,
+        value: &Value,
+        path: &str,
+        errors: &mut Vec<FieldError>,
+    ) -> bool {
+        let types = match schema_type {
+            SchemaType::Type(schema_type) => std::slice::from_ref(schema_type),
+            SchemaType::Array(types) => types.as_slice(),
+            SchemaType::AnyValue => return true,
+        };
+        if types.iter().any(|schema_type| type_matches(schema_type, value)) {
+            return true;
+        }
+        let expected = types
+            .iter()
+            .map(|schema_type| format!("{schema_type:?}").to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        errors.push(
+            FieldError::new(path, "type")
+                .param("expected", expected)
+                .param("actual", type_name(value)),
+        );
+        false
+    }
+
+    /// Valid if any of the schemas accepts the value; otherwise the
+    /// violations of the closest one are reported.
+    fn check_any(
+        &self,
+        schemas: &[RefOr<Schema>],
+        value: &Value,
+        path: &str,
+        depth: usize,
+        errors: &mut Vec<FieldError>,
+    ) {
+        let mut closest: Option<Vec<FieldError>> = None;
+        for schema in schemas {
+            let mut attempt = Vec::new();
+            self.check(schema, value, path, depth + 1, &mut attempt);
+            if attempt.is_empty() {
+                return;
+            }
+            if closest.as_ref().is_none_or(|closest| attempt.len() < closest.len()) {
+                closest = Some(attempt);
+            }
+        }
+        errors.extend(closest.unwrap_or_default());
+    }
+
+    /// Check the query parameters of a request.
+    fn check_query(&self, schemas: &OperationSchemas, query: &str) -> Vec<FieldError> {
+        let values: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let mut errors = Vec::new();
+        for (name, schema, required) in &schemas.query {
+            match values.get(name) {
+                Some(raw) => {
+                    let check = |value: &Value| {
+                        let mut violations = Vec::new();
+                        self.check(schema, value, name, 0, &mut violations);
+                        violations
+                    };
+                    // Query values are strings: retry numbers and booleans as such
+                    let mut violations = check(&Value::String(raw.clone()));
+                    if violations.iter().any(|error| error.code == "type")
+                        && let Some(value) = serde_json::from_str::<Value>(raw)
+                            .ok()
+                            .filter(|value| value.is_number() || value.is_boolean())
+                    {
+                        violations = check(&value);
+                    }
+                    // Mistyped values are reported by the extractor
+                    violations.retain(|error| error.code != "type");
+                    errors.extend(violations);
+                }
+                None if *required => errors.push(FieldError::new(name, "required")),
+                None => {}
+            }
+        }
+        errors
+    }
+
+    /// Check the query and JSON body of a request against the constraints
+    /// of its matched operation.
+    ///
+    /// Returns the request, with its body restored, or the response rejecting it.
+    pub(crate) async fn check_request(
+        &self,
+        req: Request,
+    ) -> std::result::Result<Request, Response> {
+        let schemas = req
+            .extensions()
+            .get::<MatchedPath>()
+            .and_then(|route| {
+                let key = (req.method().to_string(), route.as_str().to_string());
+                self.operations.get(&key)
+            });
+        let Some(schemas) = schemas else {
+            return Ok(req);
+        };
+
+        let mut errors = self.check_query(schemas, req.uri().query().unwrap_or_default());
+
+        let is_json_body = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(is_json);
+        let req = match &schemas.body {
+            Some(schema) if is_json_body => {
+                let (parts, body) = req.into_parts();
+                let Ok(bytes) = to_bytes(body, self.max_body).await else {
+                    return Err(ErrorResponse::new(
+                        error_codes::BAD_REQUEST,
+                        "Request body is too large",
+                    )
+                    .into_response_with(StatusCode::PAYLOAD_TOO_LARGE));
+                };
+                // Malformed JSON is reported by the extractor
+                if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+                    errors.extend(self.validate(schema, &value));
+                }
+                Request::from_parts(parts, Body::from(bytes))
+            }
+            _ => req,
+        };
+
+        if !errors.is_empty() {
+            return Err(ValidationErrorResponse::new(errors).into_response());
+        }
+        Ok(req)
+    }
+}
+
+/// Route middleware rejecting requests violating the documented constraints.
+///
+/// `EywaApp::enforce_schema_constraints()` runs the same check inside the
+/// authentication of each route (see `middleware::spec_checks`).
+pub async fn schema_constraints_middleware(
+    State(constraints): State<Arc<SchemaConstraints>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match constraints.check_request(req).await {
+        Ok(req) => next.run(req).await,
+        Err(rejection) => rejection,
+    }
+}
+
+/// Document the `422` validation response on every operation with a JSON
+/// body or query parameters.
+pub fn apply_to_openapi(openapi: &mut OpenApi) {
+    crate::validation::register_components(openapi.components.get_or_insert_with(Components::new));
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            if OperationSchemas::from_operation(operation).is_empty() {
+                continue;
+            }
+            operation
+                .responses
+                .responses
+                .entry("422".to_string())
+                .or_insert_with(|| response_ref(VALIDATION_FAILED_RESPONSE));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::post, Json, Router};
+    use serde::Deserialize;
+    use utoipa::openapi::{
+        content::ContentBuilder,
+        path::{HttpMethod, OperationBuilder, PathItem},
+        request_body::RequestBodyBuilder,
+        PathsBuilder,
+    };
+    use utoipa::{IntoParams, PartialSchema, ToSchema};
+
+    #[derive(Deserialize, ToSchema)]
+    #[allow(dead_code)]
+    struct Member {
+        #[schema(pattern = "^[^@]+@[^@]+$")]
+        email: String,
+    }
+
+    #[derive(Deserialize, ToSchema)]
+    #[allow(dead_code)]
+    struct CreateProject {
+        #[schema(min_length = 1, max_length = 10)]
+        name: String,
+        #[schema(minimum = 1, maximum = 5)]
+        priority: u8,
+        #[schema(max_items = 2)]
+        members: Vec<Member>,
+    }
+
+    #[derive(Deserialize, IntoParams)]
+    #[allow(dead_code)]
+    struct ListParams {
+        #[param(maximum = 100)]
+        limit: Option<u32>,
+    }
+
+    fn spec() -> OpenApi {
+        let operation = OperationBuilder::new()
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .content(
+                        "application/json",
+                        ContentBuilder::new()
+                            .schema(Some(utoipa::openapi::Ref::from_schema_name("CreateProject")))
+                            .build(),
+                    )
+                    .build(),
+            ))
+            .parameters(Some(ListParams::into_params(|| None)))
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi.paths = PathsBuilder::new()
+            .path("/projects", PathItem::new(HttpMethod::Post, operation))
+            .build();
+        let mut components = Components::new();
+        components.schemas.insert("CreateProject".to_string(), CreateProject::schema());
+        components.schemas.insert("Member".to_string(), Member::schema());
+        openapi.components = Some(components);
+        openapi
+    }
+
+    fn client() -> TestClient {
+        let constraints = Arc::new(SchemaConstraints::new(&spec()));
+        TestClient::new(
+            Router::new()
+                .route("/projects", post(|Json(body): Json<Value>| async move { Json(body) }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    constraints,
+                    schema_constraints_middleware,
+                )),
+        )
+    }
+
+    fn fields(response: &crate::testing::TestResponse) -> Vec<(String, String)> {
+        let body: ValidationErrorResponse = response.json();
+        body.errors.into_iter().map(|error| (error.field, error.code)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_reaches_handler() {
+        let body = serde_json::json!({
+            "name": "eywa",
+            "priority": 3,
+            "members": [{ "email": "ada@example.com" }]
+        });
+        let response = client().post("/projects?limit=50").json(&body).send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.json::<Value>(), body);
+    }
+
+    #[tokio::test]
+    async fn test_violations_reported() {
+        let body = serde_json::json!({
+            "name": "a very long project name",
+            "priority": 9,
+            "members": [{ "email": "nobody" }, { "email": "a@b" }, { "email": "c@d" }]
+        });
+        let response = client().post("/projects?limit=500").json(&body).send().await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+        let mut fields = fields(&response);
+        fields.sort();
+        let expected = [
+            ("limit", "range"),
+            ("members", "length"),
+            ("members[0].email", "regex"),
+            ("name", "length"),
+            ("priority", "range"),
+        ];
+        assert_eq!(fields, expected.map(|(field, code)| (field.into(), code.into())));
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_mistyped_fields() {
+        let body = serde_json::json!({ "name": 42, "members": [] });
+        let response = client().post("/projects").json(&body).send().await;
+
+        let mut fields = fields(&response);
+        fields.sort();
+        assert_eq!(
+            fields,
+            [("name".into(), "type".into()), ("priority".into(), "required".into())]
+        );
+    }
+
+    struct ProjectsController;
+
+    impl crate::IntoRouter<()> for ProjectsController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new()
+                .route("/projects", post(|Json(body): Json<Value>| async move { Json(body) }))
+        }
+
+        fn register_schemas(components: &mut Components) {
+            components.schemas.extend(spec().components.unwrap().schemas);
+        }
+
+        fn register_paths(openapi: &mut OpenApi) {
+            openapi.paths.paths.extend(spec().paths.paths);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checked_after_authentication() {
+        let config = crate::JwtConfig::new("s3cret");
+        let client = crate::EywaApp::new(())
+            .mount::<ProjectsController>()
+            .auth(config.clone())
+            .enforce_schema_constraints()
+            .into_test_client();
+        let body = serde_json::json!({ "name": "a very long project name" });
+
+        let response = client.post("/projects").json(&body).send().await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = config.sign(&serde_json::json!({ "sub": "42", "exp": exp })).unwrap();
+        let response = client.post("/projects").bearer(&token).json(&body).send().await;
+        response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn test_unprocessable_documented() {
+        let mut openapi = spec();
+        apply_to_openapi(&mut openapi);
+
+        let responses = &openapi.paths.paths["/projects"].post.as_ref().unwrap().responses;
+        assert!(responses.responses.contains_key("422"));
+    }
+}
@@ -0,0 +1,406 @@
+//! Requests checked against the media types documented in the spec.
+//!
+//! axum's extractors each check the `Content-Type` they expect, but nothing
+//! checks requests against the spec, so an operation documented as taking
+//! `application/json` may silently accept a form. With
+//! `EywaApp::strict_content_types` the documented media types are enforced
+//! before the handler runs:
+//!
+//! - `415 Unsupported Media Type` when a request with a body has a
+//!   `Content-Type` (or none) outside the operation's `requestBody` content
+//! - `406 Not Acceptable` when the `Accept` header excludes every media type
+//!   of the operation's successful responses
+//!
+//! Media type parameters (`charset`) are ignored, and `*/*` and `type/*`
+//! ranges match on both sides. Operations without a documented request body
+//! or response content aren't checked, and both responses are documented
+//! on the checked operations.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, Request, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use utoipa::openapi::{OpenApi, RefOr, ResponseBuilder};
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+
+/// Which media type checks are enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentTypePolicy {
+    /// Reject request bodies outside the documented media types with `415`
+    pub content_type: bool,
+    /// Reject `Accept` headers excluding the documented media types with `406`
+    pub accept: bool,
+}
+
+impl Default for ContentTypePolicy {
+    fn default() -> Self {
+        Self {
+            content_type: true,
+            accept: true,
+        }
+    }
+}
+
+impl ContentTypePolicy {
+    /// Enforce both `Content-Type` and `Accept`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether request bodies are checked.
+    pub fn content_type(mut self, enabled: bool) -> Self {
+        self.content_type = enabled;
+        self
+    }
+
+    /// Set whether `Accept` headers are checked.
+    pub fn accept(mut self, enabled: bool) -> Self {
+        self.accept = enabled;
+        self
+    }
+}
+
+/// Documented media types of an operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct OperationMediaTypes {
+    /// Content of the request body, empty without one
+    request: Vec<String>,
+    /// Content of the `2xx` responses, empty without one
+    responses: Vec<String>,
+}
+
+/// Media types of every documented operation, keyed by method and route.
+#[derive(Debug, Clone)]
+pub struct ContentTypes {
+    policy: ContentTypePolicy,
+    operations: HashMap<(String, String), OperationMediaTypes>,
+}
+
+/// Lowercase `type/subtype`, without parameters.
+fn essence(media_type: &str) -> String {
+    media_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Whether two media types or ranges overlap.
+fn matches(left: &str, right: &str) -> bool {
+    let split = |media_type: &str| {
+        let (kind, subtype) = media_type.split_once('/').unwrap_or((media_type, ""));
+        (kind.to_string(), subtype.to_string())
+    };
+    let ((left_type, left_subtype), (right_type, right_subtype)) = (split(left), split(right));
+    let overlap = |a: &str, b: &str| a == "*" || b == "*" || a == b;
+    overlap(&left_type, &right_type) && overlap(&left_subtype, &right_subtype)
+}
+
+/// Media ranges of an `Accept` header, without those refused with `q=0`.
+fn accepted(accept: &str) -> Vec<String> {
+    accept
+        .split(',')
+        .filter(|range| {
+            !range.split(';').skip(1).any(|param| {
+                let param = param.trim().replace(' ', "");
+                let zeros = |digits: &str| digits.bytes().all(|b| b == b'0');
+                param == "q=0" || param.strip_prefix("q=0.").is_some_and(zeros)
+            })
+        })
+        .map(essence)
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+impl ContentTypes {
+    /// Read the media types of every operation of `openapi`.
+    pub fn new(policy: ContentTypePolicy, openapi: &OpenApi) -> Self {
+        let components = openapi.components.as_ref();
+        let mut operations = HashMap::new();
+        for (path, item) in &openapi.paths.paths {
+            let methods = [
+                ("GET", &item.get),
+                ("PUT", &item.put),
+                ("POST", &item.post),
+                ("DELETE", &item.delete),
+                ("PATCH", &item.patch),
+            ];
+            for (method, operation) in methods {
+                let Some(operation) = operation else {
+                    continue;
+                };
+                let request = operation
+                    .request_body
+                    .as_ref()
+                    .map(|body| body.content.keys().map(|key| essence(key)).collect())
+                    .unwrap_or_default();
+                let responses = operation
+                    .responses
+                    .responses
+                    .iter()
+                    .filter(|(status, _)| status.starts_with('2'))
+                    .filter_map(|(_, response)| match response {
+                        RefOr::T(response) => Some(response.clone()),
+                        RefOr::Ref(reference) => {
+                            let name = reference.ref_location.rsplit('/').next()?;
+                            match components?.responses.get(name)? {
+                                RefOr::T(response) => Some(response.clone()),
+                                RefOr::Ref(_) => None,
+                            }
+                        }
+                    })
+                    .flat_map(|response| {
+                        response.content.keys().map(|key| essence(key)).collect::<Vec<_>>()
+                    })
+                    .collect();
+                operations.insert(
+                    (method.to_string(), path.clone()),
+                    OperationMediaTypes { request, responses },
+                );
+            }
+        }
+        Self { policy, operations }
+    }
+
+    /// The rejection of a request to `route`, if any.
+    pub(crate) fn check(&self, method: &str, route: &str, req: &Request) -> Option<Response> {
+        let media_types = self.operations.get(&(method.to_string(), route.to_string()))?;
+        let header = |name| req.headers().get(name).and_then(|value| value.to_str().ok());
+
+        // Bodyless requests aren't checked
+        if self.policy.content_type
+            && !media_types.request.is_empty()
+            && req.body().size_hint().exact() != Some(0)
+        {
+            let supported = header(CONTENT_TYPE).map(essence).is_some_and(|content_type| {
+                media_types.request.iter().any(|media| matches(&content_type, media))
+            });
+            if !supported {
+                let message =
+                    format!("Content-Type must be one of: {}", media_types.request.join(", "));
+                return Some(
+                    ErrorResponse::new(error_codes::UNSUPPORTED_MEDIA_TYPE, message)
+                        .into_response_with(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+                );
+            }
+        }
+
+        if self.policy.accept && !media_types.responses.is_empty() {
+            let ranges = header(ACCEPT).map(accepted).unwrap_or_default();
+            let acceptable = header(ACCEPT).is_none()
+                || ranges
+                    .iter()
+                    .any(|range| media_types.responses.iter().any(|media| matches(range, media)));
+            if !acceptable {
+                let message = format!(
+                    "Accept must allow one of: {}",
+                    media_types.responses.join(", ")
+                );
+                return Some(
+                    ErrorResponse::new(error_codes::NOT_ACCEPTABLE, message)
+                        .into_response_with(StatusCode::NOT_ACCEPTABLE),
+                );
+            }
+        }
+        None
+    }
+}
+
+/// Route middleware rejecting requests outside the documented media types.
+///
+/// `EywaApp::strict_content_types()` runs the same check inside the
+/// authentication of each route (see `middleware::spec_checks`).
+pub async fn content_type_middleware(
+    State(content_types): State<Arc<ContentTypes>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let rejection = req.extensions().get::<MatchedPath>().and_then(|route| {
+        content_types.check(req.method().as_str(), route.as_str(), &req)
+    });
+    match rejection {
+        Some(rejection) => rejection,
+        None => next.run(req).await,
+    }
+}
+
+/// Document `415` on operations with a request body and `406` on
+/// operations with response content, as enforced by `policy`.
+pub fn apply_to_openapi(openapi: &mut OpenApi, policy: &ContentTypePolicy) {
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            let has_body = operation
+                .request_body
+                .as_ref()
+                .is_some_and(|body| !body.content.is_empty());
+            let has_content = operation.responses.responses.iter().any(|(status, response)| {
+                status.starts_with('2')
+                    && match response {
+                        RefOr::T(response) => !response.content.is_empty(),
+                        RefOr::Ref(_) => true,
+                    }
+            });
+            let responses = &mut operation.responses.responses;
+            if policy.content_type && has_body {
+                responses.entry("415".to_string()).or_insert_with(|| {
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description("Request body media type not supported")
+                            .build(),
+                    )
+                });
+            }
+            if policy.accept && has_content {
+                responses.entry("406".to_string()).or_insert_with(|| {
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description("None of the response media types is acceptable")
+                            .build(),
+                    )
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::post, Router};
+    use utoipa::openapi::{
+        content::ContentBuilder,
+        path::{HttpMethod, OperationBuilder, PathItem},
+        request_body::RequestBodyBuilder,
+        PathsBuilder,
+    };
+
+    fn spec() -> OpenApi {
+        let operation = OperationBuilder::new()
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .content("application/json", ContentBuilder::new().build())
+                    .build(),
+            ))
+            .response(
+                "201",
+                ResponseBuilder::new()
+                    .description("Created")
+                    .content("application/json", ContentBuilder::new().build())
+                    .build(),
+            )
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi.paths = PathsBuilder::new()
+            .path("/projects", PathItem::new(HttpMethod::Post, operation))
+            .build();
+        openapi
+    }
+
+    fn client(policy: ContentTypePolicy) -> TestClient {
+        let content_types = Arc::new(ContentTypes::new(policy, &spec()));
+        TestClient::new(
+            Router::new()
+                .route("/projects", post(|| async { StatusCode::CREATED }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    content_types,
+                    content_type_middleware,
+                )),
+        )
+    }
+
+    #[test]
+    fn test_media_type_matching() {
+        assert_eq!(essence("Application/JSON; charset=utf-8"), "application/json");
+        assert!(matches("application/json", "application/*"));
+        assert!(matches("*/*", "text/csv"));
+        assert!(!matches("text/csv", "application/json"));
+        assert_eq!(accepted("text/html;q=0, application/json;q=0.5"), ["application/json"]);
+    }
+
+    #[tokio::test]
+    async fn test_content_type_enforced() {
+        let client = client(ContentTypePolicy::new());
+        let post = |content_type: &'static str| {
+            client.post("/projects").header("content-type", content_type).body("{}")
+        };
+
+        post("application/json; charset=utf-8").send().await.assert_status(StatusCode::CREATED);
+        let response = post("application/x-www-form-urlencoded").send().await;
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(response.json::<serde_json::Value>()["error"], "UNSUPPORTED_MEDIA_TYPE");
+
+        // Bodyless requests aren't checked
+        client.post("/projects").send().await.assert_status(StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_accept_enforced() {
+        let client = client(ContentTypePolicy::new().content_type(false));
+        let post = |accept: &'static str| client.post("/projects").header("accept", accept);
+
+        post("application/*").send().await.assert_status(StatusCode::CREATED);
+        post("text/html, */*;q=0.1").send().await.assert_status(StatusCode::CREATED);
+        post("text/csv").send().await.assert_status(StatusCode::NOT_ACCEPTABLE);
+        client.post("/projects").send().await.assert_status(StatusCode::CREATED);
+    }
+
+    struct ProjectsController;
+
+    impl crate::IntoRouter<()> for ProjectsController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new().route("/projects", post(|| async { StatusCode::CREATED }))
+        }
+
+        fn register_paths(openapi: &mut OpenApi) {
+            openapi.paths.paths.extend(spec().paths.paths);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checked_after_authentication() {
+        let config = crate::JwtConfig::new("s3cret");
+        let client = crate::EywaApp::new(())
+            .mount::<ProjectsController>()
+            .auth(config.clone())
+            .strict_content_types(ContentTypePolicy::new())
+            .into_test_client();
+        let post = || client.post("/projects").header("content-type", "text/csv").body("a,b");
+
+        post().send().await.assert_status(StatusCode::UNAUTHORIZED);
+
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = config.sign(&serde_json::json!({ "sub": "42", "exp": exp })).unwrap();
+        let response = post().bearer(&token).send().await;
+        response.assert_status(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_rejections_documented() {
+        let mut openapi = spec();
+        apply_to_openapi(&mut openapi, &ContentTypePolicy::new().accept(false));
+
+        let responses = &openapi.paths.paths["/projects"].post.as_ref().unwrap().responses;
+        assert!(responses.responses.contains_key("415"));
+        assert!(!responses.responses.contains_key("406"));
+    }
+}
@@ -0,0 +1,269 @@
+//! Request deadlines propagated from callers to downstream calls.
+//!
+//! A caller sets the deadline of a request with `X-Request-Deadline`
+//! (absolute, Unix epoch milliseconds) or `grpc-timeout` (relative, e.g.
+//! `250m`). The deadline is exposed on `RequestContext`, enforced as the
+//! handler timeout, and forwarded by `OutboundClient` with the remaining
+//! budget, so downstream services stop working on requests nobody waits for
+//! anymore instead of piling up retries.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::middleware::RequestContext;
+use crate::reload::Watch;
+
+/// Absolute deadline, in Unix epoch milliseconds.
+pub const DEADLINE_HEADER: &str = "x-request-deadline";
+
+/// Relative deadline in the gRPC format (`{value}{H|M|S|m|u|n}`).
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Parse a `grpc-timeout` value (at most 8 digits followed by a unit).
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let amount = &value[..value.len() - unit.len_utf8()];
+    if amount.is_empty() || amount.len() > 8 || !amount.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = amount.parse().ok()?;
+    match unit {
+        'H' => Some(Duration::from_secs(amount * 3600)),
+        'M' => Some(Duration::from_secs(amount * 60)),
+        'S' => Some(Duration::from_secs(amount)),
+        'm' => Some(Duration::from_millis(amount)),
+        'u' => Some(Duration::from_micros(amount)),
+        'n' => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Format a budget as a `grpc-timeout` value, in milliseconds when it fits.
+pub fn format_grpc_timeout(budget: Duration) -> String {
+    let millis = budget.as_millis();
+    if millis <= 99_999_999 {
+        format!("{millis}m")
+    } else {
+        format!("{}S", budget.as_secs().min(99_999_999))
+    }
+}
+
+/// The earliest deadline set by the request headers.
+pub fn inbound_deadline(headers: &HeaderMap, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let absolute = headers
+        .get(DEADLINE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .and_then(DateTime::from_timestamp_millis);
+    let relative = headers
+        .get(GRPC_TIMEOUT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_grpc_timeout)
+        .and_then(|budget| chrono::Duration::from_std(budget).ok())
+        .map(|budget| now + budget);
+
+    match (absolute, relative) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Budget applied to requests, on top of the deadline sent by the caller.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::middleware::deadline::DeadlineConfig;
+///
+/// // 10s for requests without a deadline, never more than 30s
+/// app.request_deadlines(
+///     DeadlineConfig::new()
+///         .default_budget(Duration::from_secs(10))
+///         .max_budget(Duration::from_secs(30)),
+/// )
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeadlineConfig {
+    default_budget: Option<Duration>,
+    max_budget: Option<Duration>,
+}
+
+impl DeadlineConfig {
+    /// Only enforce the deadlines sent by callers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Budget of the requests that don't carry a deadline.
+    pub fn default_budget(mut self, budget: Duration) -> Self {
+        self.default_budget = Some(budget);
+        self
+    }
+
+    /// Upper bound of every budget, whatever the caller asked for.
+    pub fn max_budget(mut self, budget: Duration) -> Self {
+        self.max_budget = Some(budget);
+        self
+    }
+
+    /// The deadline enforced for a request.
+    pub fn effective(
+        &self,
+        inbound: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let after = |budget: Duration| {
+            chrono::Duration::from_std(budget)
+                .ok()
+                .map(|budget| now + budget)
+        };
+        let deadline = inbound.or_else(|| self.default_budget.and_then(after));
+        let cap = self.max_budget.and_then(after);
+        match (deadline, cap) {
+            (Some(deadline), Some(cap)) => Some(deadline.min(cap)),
+            (deadline, cap) => deadline.or(cap),
+        }
+    }
+}
+
+/// `504 Gateway Timeout` in the `AppError` body format.
+pub(crate) fn deadline_exceeded() -> Response {
+    ErrorResponse::new(error_codes::GATEWAY_TIMEOUT, "Request deadline exceeded")
+        .into_response_with(StatusCode::GATEWAY_TIMEOUT)
+}
+
+/// Middleware enforcing the request deadline as the handler timeout.
+///
+/// The effective deadline replaces the inbound deadline headers and is set
+/// on `RequestContext`, whichever of this middleware and the context
+/// middleware runs first. Requests whose deadline has passed, or whose
+/// handler doesn't finish in time, return `504 Gateway Timeout`.
+pub async fn deadline_middleware(
+    State(config): State<Watch<DeadlineConfig>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let now = Utc::now();
+    let inbound = inbound_deadline(req.headers(), now);
+    let Some(deadline) = config.with(|config| config.effective(inbound, now)) else {
+        return next.run(req).await;
+    };
+
+    let headers = req.headers_mut();
+    headers.remove(GRPC_TIMEOUT_HEADER);
+    headers.insert(
+        DEADLINE_HEADER,
+        HeaderValue::from(deadline.timestamp_millis()),
+    );
+    if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+        ctx.deadline = Some(deadline);
+    }
+
+    let remaining = match (deadline - now).to_std() {
+        Ok(remaining) if !remaining.is_zero() => remaining,
+        _ => return deadline_exceeded(),
+    };
+    match tokio::time::timeout(remaining, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request deadline exceeded after {:?}", remaining);
+            deadline_exceeded()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    #[test]
+    fn test_parse_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("123456789m"), None);
+        assert_eq!(parse_grpc_timeout("m"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+        assert_eq!(format_grpc_timeout(Duration::from_millis(1500)), "1500m");
+    }
+
+    #[test]
+    fn test_earliest_deadline_wins() {
+        let now = Utc::now();
+        let mut headers = HeaderMap::new();
+        let absolute = now + chrono::Duration::seconds(5);
+        headers.insert(DEADLINE_HEADER, HeaderValue::from(absolute.timestamp_millis()));
+        headers.insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("1S"));
+
+        assert_eq!(
+            inbound_deadline(&headers, now),
+            Some(now + chrono::Duration::seconds(1))
+        );
+    }
+
+    #[test]
+    fn test_budget_is_capped() {
+        let now = Utc::now();
+        let config = DeadlineConfig::new()
+            .default_budget(Duration::from_secs(10))
+            .max_budget(Duration::from_secs(30));
+
+        assert_eq!(
+            config.effective(None, now),
+            Some(now + chrono::Duration::seconds(10))
+        );
+        assert_eq!(
+            config.effective(Some(now + chrono::Duration::minutes(5)), now),
+            Some(now + chrono::Duration::seconds(30))
+        );
+        assert_eq!(DeadlineConfig::new().effective(None, now), None);
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route("/fast", get(|| async { "done" }))
+                .route(
+                    "/slow",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                        "done"
+                    }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    Watch::new(DeadlineConfig::new()),
+                    deadline_middleware,
+                )),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_deadline_enforced_as_timeout() {
+        let client = client();
+
+        let fast = client.get("/fast").header(GRPC_TIMEOUT_HEADER, "1S").send().await;
+        fast.assert_status(StatusCode::OK);
+
+        let slow = client.get("/slow").header(GRPC_TIMEOUT_HEADER, "20m").send().await;
+        slow.assert_status(StatusCode::GATEWAY_TIMEOUT);
+
+        let expired = (Utc::now() - chrono::Duration::seconds(1)).timestamp_millis();
+        let late = client
+            .get("/fast")
+            .header(DEADLINE_HEADER, &expired.to_string())
+            .send()
+            .await;
+        late.assert_status(StatusCode::GATEWAY_TIMEOUT);
+    }
+}
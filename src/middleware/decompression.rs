@@ -0,0 +1,163 @@
+//! Request body decompression.
+//!
+//! Clients on constrained links (IoT devices, mobile) send gzip or deflate
+//! request bodies with a `Content-Encoding` header. `EywaApp::request_decompression`
+//! decodes them before the extractors run, so handlers see plain bodies.
+//!
+//! Decompressed bodies are capped at `max_size`: a small compressed payload
+//! can expand to gigabytes (zip bomb), so reading past the limit fails and the
+//! extractors reject the request with `413 Payload Too Large`. Bodies with an
+//! unsupported encoding are rejected with `415 Unsupported Media Type`.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::header::CONTENT_ENCODING,
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::Limited;
+use serde::{Deserialize, Serialize};
+
+/// Request decompression settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecompressionSettings {
+    /// Largest decompressed body, in bytes
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
+    #[serde(default = "enabled")]
+    pub gzip: bool,
+    #[serde(default = "enabled")]
+    pub deflate: bool,
+}
+
+fn default_max_size() -> usize {
+    10 * 1024 * 1024
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for DecompressionSettings {
+    fn default() -> Self {
+        Self {
+            max_size: default_max_size(),
+            gzip: true,
+            deflate: true,
+        }
+    }
+}
+
+impl DecompressionSettings {
+    /// Cap decompressed bodies at `bytes`.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = bytes;
+        self
+    }
+}
+
+/// Marks requests whose body is decompressed.
+#[derive(Debug, Clone, Copy)]
+struct CompressedBody;
+
+/// Middleware marking compressed requests, before the decoder strips
+/// their `Content-Encoding`.
+pub async fn mark_compressed_middleware(mut req: Request, next: Next) -> Response {
+    let compressed = req
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|encoding| !encoding.trim().eq_ignore_ascii_case("identity"));
+    if compressed {
+        req.extensions_mut().insert(CompressedBody);
+    }
+    next.run(req).await
+}
+
+/// Middleware capping the decompressed body of marked requests.
+pub async fn limit_decompressed_middleware(
+    State(max_size): State<usize>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if req.extensions().get::<CompressedBody>().is_none() {
+        return next.run(req).await;
+    }
+    let (parts, body) = req.into_parts();
+    let body = Body::new(Limited::new(body, max_size));
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{
+        http::StatusCode,
+        routing::{get, post},
+        Router,
+    };
+    use tower::ServiceBuilder;
+    use tower_http::{compression::CompressionLayer, decompression::RequestDecompressionLayer};
+
+    /// Gzip `payload` through the response compression layer.
+    async fn gzip(payload: String) -> Vec<u8> {
+        let client = TestClient::new(
+            Router::new()
+                .route("/", get(move || async move { payload }))
+                .layer(CompressionLayer::new()),
+        );
+        let response = client.get("/").header("accept-encoding", "gzip").send().await;
+        assert_eq!(response.header("content-encoding"), Some("gzip"));
+        response.bytes().to_vec()
+    }
+
+    fn client(max_size: usize) -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route("/ingest", post(|body: String| async move { body.len().to_string() }))
+                .layer(
+                    ServiceBuilder::new()
+                        .layer(axum::middleware::from_fn(mark_compressed_middleware))
+                        .layer(RequestDecompressionLayer::new())
+                        .layer(axum::middleware::from_fn_with_state(
+                            max_size,
+                            limit_decompressed_middleware,
+                        )),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_gzip_body_decompressed() {
+        let body = gzip("reading ".repeat(100)).await;
+
+        let response = client(1024)
+            .post("/ingest")
+            .header("content-encoding", "gzip")
+            .body(body)
+            .send()
+            .await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "800");
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_size_limited() {
+        let body = gzip("0".repeat(100_000)).await;
+        assert!(body.len() < 1024);
+
+        let response = client(1024)
+            .post("/ingest")
+            .header("content-encoding", "gzip")
+            .body(body)
+            .send()
+            .await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Uncompressed bodies are left to the regular body limit
+        let response = client(1024).post("/ingest").body("0".repeat(4096)).send().await;
+        response.assert_status(StatusCode::OK);
+    }
+}
@@ -0,0 +1,186 @@
+//! Static response headers declared on routes.
+//!
+//! Routes declare fixed response headers (via `#[route(header("Cache-Control", "no-store"))]`
+//! or `EywaApp::response_header`). The same `StaticHeaderRegistry` is used to:
+//! - add the headers to every response of the route
+//! - document them on each of the operation's responses
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use utoipa::openapi::header::HeaderBuilder;
+use utoipa::openapi::schema::{ObjectBuilder, Type};
+use utoipa::openapi::{OpenApi, RefOr};
+
+use crate::traits::RouteHeaders;
+
+/// Static response headers keyed by HTTP method and route template.
+#[derive(Debug, Clone, Default)]
+pub struct StaticHeaderRegistry {
+    routes: HashMap<(String, String), Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl StaticHeaderRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the headers of a route.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a header name or value is invalid, so typos surface at startup.
+    pub fn insert(&mut self, route: RouteHeaders) {
+        let headers = self
+            .routes
+            .entry((route.method.to_uppercase(), route.path))
+            .or_default();
+        for (name, value) in route.headers {
+            let name = HeaderName::try_from(name.as_str())
+                .unwrap_or_else(|_| panic!("invalid response header name `{name}`"));
+            let value = HeaderValue::try_from(value.as_str())
+                .unwrap_or_else(|_| panic!("invalid value for response header `{name}`"));
+            headers.retain(|(existing, _)| *existing != name);
+            headers.push((name, value));
+        }
+    }
+
+    /// Returns the headers declared for `method` on the route template `path`.
+    pub fn headers(&self, method: &str, path: &str) -> Option<&[(HeaderName, HeaderValue)]> {
+        self.routes
+            .get(&(method.to_uppercase(), path.to_string()))
+            .map(Vec::as_slice)
+    }
+
+    /// Returns `true` if no route declares a header.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Document the registered headers on every response of the matching operations.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let operations = [
+                ("GET", &mut item.get),
+                ("POST", &mut item.post),
+                ("PUT", &mut item.put),
+                ("DELETE", &mut item.delete),
+                ("PATCH", &mut item.patch),
+            ];
+            for (method, operation) in operations {
+                let (Some(operation), Some(headers)) =
+                    (operation.as_mut(), self.headers(method, path))
+                else {
+                    continue;
+                };
+                for response in operation.responses.responses.values_mut() {
+                    let RefOr::T(response) = response else {
+                        continue;
+                    };
+                    for (name, value) in headers {
+                        let value = value.to_str().unwrap_or_default();
+                        let header = HeaderBuilder::new()
+                            .schema(
+                                ObjectBuilder::new()
+                                    .schema_type(Type::String)
+                                    .enum_values(Some([value])),
+                            )
+                            .description(Some(format!("Always `{value}`")))
+                            .build();
+                        response.headers.insert(name.to_string(), header);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Axum middleware adding the static headers registered for the matched route.
+///
+/// Headers already set by the handler are left untouched.
+/// Installed automatically by `EywaApp` when any route declares a header.
+pub async fn static_headers_middleware(
+    State(registry): State<Arc<StaticHeaderRegistry>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let key = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| (req.method().to_string(), path.as_str().to_string()));
+
+    let mut response = next.run(req).await;
+
+    if let Some(headers) = key.and_then(|(method, path)| registry.headers(&method, &path)) {
+        for (name, value) in headers {
+            if !response.headers().contains_key(name) {
+                response.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+    use utoipa::openapi::ResponseBuilder;
+
+    fn registry() -> StaticHeaderRegistry {
+        let mut registry = StaticHeaderRegistry::new();
+        registry.insert(RouteHeaders {
+            method: "get".to_string(),
+            path: "/projects".to_string(),
+            headers: vec![("Cache-Control".to_string(), "no-store".to_string())],
+        });
+        registry
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let registry = registry();
+        let headers = registry.headers("GET", "/projects").unwrap();
+        assert_eq!(headers[0].0, "cache-control");
+        assert_eq!(headers[0].1, "no-store");
+        assert!(registry.headers("POST", "/projects").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid response header name")]
+    fn test_invalid_header_name_panics() {
+        StaticHeaderRegistry::new().insert(RouteHeaders {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            headers: vec![("bad header".to_string(), "x".to_string())],
+        });
+    }
+
+    #[test]
+    fn test_headers_documented_on_responses() {
+        let operation = OperationBuilder::new()
+            .response("200", ResponseBuilder::new().description("OK").build())
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/projects".to_string(), PathItem::new(HttpMethod::Get, operation));
+
+        registry().apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        let RefOr::T(response) = &operation.responses.responses["200"] else {
+            panic!("expected inline response");
+        };
+        assert!(response.headers.contains_key("cache-control"));
+    }
+}
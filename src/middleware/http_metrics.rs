@@ -0,0 +1,125 @@
+//! Prometheus HTTP metrics per route.
+//!
+//! `EywaApp::metrics()` records three metrics for every request and serves
+//! them at `GET /metrics` in the Prometheus text format (through
+//! `eywa_metrics`):
+//!
+//! - `http_requests_total{method, route, status}` - Requests handled
+//! - `http_request_duration_seconds{method, route, status}` - Duration histogram
+//! - `http_requests_in_flight{method, route}` - Requests being handled
+//!
+//! `route` is the route template (`/api/v1/projects/{id}`), never the raw
+//! path, so the number of series stays bounded by the number of routes;
+//! requests matching no route share the `<unmatched>` label. `status` is the
+//! numeric status code.
+
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::analytics::UNMATCHED_ROUTE;
+
+/// Counter of handled requests.
+pub const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+
+/// Histogram of request durations, in seconds.
+pub const HTTP_REQUEST_DURATION: &str = "http_request_duration_seconds";
+
+/// Gauge of the requests being handled.
+pub const HTTP_REQUESTS_IN_FLIGHT: &str = "http_requests_in_flight";
+
+/// Counts a request as in flight until dropped, including when the client
+/// goes away and the request is cancelled.
+struct InFlight {
+    method: String,
+    route: String,
+}
+
+impl InFlight {
+    fn start(method: String, route: String) -> Self {
+        metrics::gauge!(
+            HTTP_REQUESTS_IN_FLIGHT,
+            "method" => method.clone(),
+            "route" => route.clone()
+        )
+        .increment(1.0);
+        Self { method, route }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        metrics::gauge!(
+            HTTP_REQUESTS_IN_FLIGHT,
+            "method" => self.method.clone(),
+            "route" => self.route.clone()
+        )
+        .decrement(1.0);
+    }
+}
+
+/// Route label of a request: its route template, or `unmatched`.
+fn route_label(req: &Request) -> String {
+    req.extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+        .to_string()
+}
+
+/// Middleware recording the request count, duration and in-flight gauge.
+///
+/// Installed by `EywaApp::metrics()`.
+pub async fn http_metrics_middleware(req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = route_label(&req);
+    let in_flight = InFlight::start(method.clone(), route.clone());
+
+    let response = next.run(req).await;
+    drop(in_flight);
+
+    let status = response.status().as_u16().to_string();
+    metrics::counter!(
+        HTTP_REQUESTS_TOTAL,
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status.clone()
+    )
+    .increment(1);
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION,
+        "method" => method,
+        "route" => route,
+        "status" => status
+    )
+    .record(started.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Router};
+
+    #[tokio::test]
+    async fn test_route_template_labels() {
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/projects/{id}",
+                    get(|req: Request| async move { route_label(&req) }),
+                )
+                .layer(axum::middleware::from_fn(http_metrics_middleware)),
+        );
+
+        let response = client.get("/projects/42").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "/projects/{id}");
+        client.get("/missing").send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+}
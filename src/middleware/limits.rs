@@ -0,0 +1,280 @@
+//! Request size, time and concurrency limits.
+//!
+//! Without limits, one client can send a body as large as the process memory,
+//! hold a worker forever, or open more requests than the service can run.
+//! `EywaApp::limits` installs all four guards with defaults fit for a JSON
+//! API, each answering in the `AppError` body format:
+//!
+//! | Limit | Default | Rejection |
+//! |-------|---------|-----------|
+//! | `max_body_bytes` | 2 MiB | `413 Payload Too Large` |
+//! | `max_header_bytes` | 32 KiB | `431 Request Header Fields Too Large` |
+//! | `timeout_ms` | 30 s | `408 Request Timeout` |
+//! | `max_concurrency` | 1024 | `503 Service Unavailable` |
+//!
+//! ```toml
+//! [limits]
+//! max_body_bytes = 10485760   # 10 MiB for uploads
+//! timeout_ms = 5000
+//! ```
+//!
+//! Bodies announcing a larger `Content-Length` are rejected upfront; chunked
+//! bodies are rejected when the handler reads past the limit.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header::CONTENT_LENGTH, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::Limited;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+
+/// Delay suggested to clients shed by the concurrency limit.
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Request limits, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Largest request body, in bytes
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Largest total size of the request headers, in bytes
+    #[serde(default = "default_max_header_bytes")]
+    pub max_header_bytes: usize,
+    /// Longest time to produce a response, in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Requests running at the same time
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+fn default_max_body_bytes() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_max_header_bytes() -> usize {
+    32 * 1024
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_max_concurrency() -> usize {
+    1024
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: default_max_body_bytes(),
+            max_header_bytes: default_max_header_bytes(),
+            timeout_ms: default_timeout_ms(),
+            max_concurrency: default_max_concurrency(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// The default limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject request bodies larger than `bytes`.
+    pub fn max_body_bytes(mut self, bytes: usize) -> Self {
+        self.max_body_bytes = bytes;
+        self
+    }
+
+    /// Reject requests whose headers take more than `bytes`.
+    pub fn max_header_bytes(mut self, bytes: usize) -> Self {
+        self.max_header_bytes = bytes;
+        self
+    }
+
+    /// Give up on requests taking longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    /// Run at most `max_concurrency` requests at the same time.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    fn timeout_duration(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// Limits with the permits of the running requests.
+#[derive(Debug)]
+pub struct Limits {
+    config: LimitsConfig,
+    semaphore: Arc<Semaphore>,
+}
+
+impl Limits {
+    pub fn new(config: LimitsConfig) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(config.max_concurrency)),
+            config,
+        }
+    }
+
+    /// Requests currently running.
+    pub fn in_flight(&self) -> usize {
+        self.config.max_concurrency - self.semaphore.available_permits()
+    }
+}
+
+/// Size of the headers as sent, without the request line.
+fn header_bytes(headers: &HeaderMap) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len() + 4)
+        .sum()
+}
+
+fn rejection(status: StatusCode, code: &str, message: String) -> Response {
+    ErrorResponse::new(code, message).into_response_with(status)
+}
+
+/// Middleware enforcing the request limits.
+///
+/// Installed with `DefaultBodyLimit::disable()` by `EywaApp::limits()`, so
+/// the body limit replaces axum's default instead of stacking with it.
+pub async fn limits_middleware(
+    State(limits): State<Arc<Limits>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = &limits.config;
+
+    let headers = header_bytes(req.headers());
+    if headers > config.max_header_bytes {
+        return rejection(
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            error_codes::REQUEST_HEADERS_TOO_LARGE,
+            format!("Request headers exceed {} bytes", config.max_header_bytes),
+        );
+    }
+
+    let length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if length.is_some_and(|length| length > config.max_body_bytes) {
+        return rejection(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            error_codes::PAYLOAD_TOO_LARGE,
+            format!("Request body exceeds {} bytes", config.max_body_bytes),
+        );
+    }
+
+    let Ok(_permit) = limits.semaphore.clone().try_acquire_owned() else {
+        tracing::warn!(max_concurrency = config.max_concurrency, "request shed by the limits");
+        return ErrorResponse::new(error_codes::SERVICE_UNAVAILABLE, "Too many concurrent requests")
+            .retry_after(RETRY_AFTER)
+            .into_response_with(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    let (parts, body) = req.into_parts();
+    let body = Body::new(Limited::new(body, config.max_body_bytes));
+    let timeout = config.timeout_duration();
+    match tokio::time::timeout(timeout, next.run(Request::from_parts(parts, body))).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request timed out after {:?}", timeout);
+            rejection(
+                StatusCode::REQUEST_TIMEOUT,
+                error_codes::REQUEST_TIMEOUT,
+                format!("Request not completed within {timeout:?}"),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{
+        extract::DefaultBodyLimit,
+        routing::{get, post},
+        Router,
+    };
+
+    fn client(config: LimitsConfig) -> (TestClient, Arc<Limits>) {
+        let limits = Arc::new(Limits::new(config));
+        let client = TestClient::new(
+            Router::new()
+                .route("/ingest", post(|body: String| async move { body.len().to_string() }))
+                .route(
+                    "/slow",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        "done"
+                    }),
+                )
+                .layer(DefaultBodyLimit::disable())
+                .layer(axum::middleware::from_fn_with_state(limits.clone(), limits_middleware)),
+        );
+        (client, limits)
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: LimitsConfig = serde_json::from_str(r#"{"timeout_ms": 5000}"#).unwrap();
+        assert_eq!(config, LimitsConfig::new().timeout(Duration::from_secs(5)));
+        assert_eq!(config.max_body_bytes, 2 * 1024 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_size_limits() {
+        let (client, _) = client(LimitsConfig::new().max_body_bytes(1024).max_header_bytes(256));
+
+        let response = client.post("/ingest").body("0".repeat(512)).send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "512");
+
+        // Rejected upfront when announced, while reading otherwise
+        let oversized = || client.post("/ingest").body("0".repeat(4096));
+        let response = oversized().header("content-length", "4096").send().await;
+        response.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(response.json::<serde_json::Value>()["error"], "PAYLOAD_TOO_LARGE");
+        oversized().send().await.assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = client.post("/ingest").header("x-padding", &"0".repeat(512)).send().await;
+        response.assert_status(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_concurrency() {
+        let (client, limits) = client(
+            LimitsConfig::new().timeout(Duration::from_millis(20)).max_concurrency(1),
+        );
+        let response = client.get("/slow").send().await;
+        response.assert_status(StatusCode::REQUEST_TIMEOUT);
+        assert_eq!(limits.in_flight(), 0);
+
+        let _running = limits.semaphore.clone().try_acquire_owned().unwrap();
+        let response = client.post("/ingest").send().await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.header("retry-after"), Some("1"));
+    }
+}
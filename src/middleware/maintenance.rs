@@ -0,0 +1,160 @@
+//! Maintenance mode.
+//!
+//! While enabled, requests are rejected with `503 Service Unavailable` and a
+//! `Retry-After` pointing at the announced end of the maintenance window (one
+//! minute if the end isn't known or already passed).
+//!
+//! `MaintenanceMode` is a shared handle: keep a clone to switch it from an
+//! admin route, a config reload or a signal handler.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+
+/// `Retry-After` sent when the end of the maintenance window isn't known.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Window {
+    message: String,
+    until: Option<DateTime<Utc>>,
+}
+
+/// Switch rejecting requests during maintenance.
+///
+/// # Example
+///
+/// ```ignore
+/// let maintenance = MaintenanceMode::new();
+///
+/// EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .maintenance(maintenance.clone())
+///     .health_checks()
+///
+/// // Later, e.g. before a migration
+/// maintenance.enable("Database upgrade", Some(Utc::now() + chrono::Duration::minutes(15)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceMode(Arc<RwLock<Option<Window>>>);
+
+impl MaintenanceMode {
+    /// Create a disabled switch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start rejecting requests, until `disable` is called.
+    ///
+    /// `until` is the announced end of the window, used for `Retry-After`.
+    pub fn enable(&self, message: impl Into<String>, until: Option<DateTime<Utc>>) {
+        *self.0.write().unwrap() = Some(Window {
+            message: message.into(),
+            until,
+        });
+    }
+
+    /// Serve requests again.
+    pub fn disable(&self) {
+        *self.0.write().unwrap() = None;
+    }
+
+    /// Returns `true` while requests are rejected.
+    pub fn is_enabled(&self) -> bool {
+        self.0.read().unwrap().is_some()
+    }
+
+    /// How long clients should wait, if maintenance is enabled.
+    pub fn retry_after(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let window = self.0.read().unwrap();
+        let window = window.as_ref()?;
+        Some(
+            window
+                .until
+                .and_then(|until| (until - now).to_std().ok())
+                .filter(|remaining| !remaining.is_zero())
+                .unwrap_or(DEFAULT_RETRY_AFTER),
+        )
+    }
+
+    fn message(&self) -> Option<String> {
+        self.0
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|window| window.message.clone())
+    }
+}
+
+/// Middleware rejecting requests while maintenance is enabled.
+///
+/// Installed by `EywaApp::maintenance()`.
+pub async fn maintenance_middleware(
+    State(mode): State<MaintenanceMode>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let (Some(retry_after), Some(message)) = (mode.retry_after(Utc::now()), mode.message()) else {
+        return next.run(req).await;
+    };
+    ErrorResponse::new(error_codes::MAINTENANCE, message)
+        .retry_after(retry_after)
+        .into_response_with(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    #[test]
+    fn test_retry_after_window_end() {
+        let mode = MaintenanceMode::new();
+        let now = Utc::now();
+        assert_eq!(mode.retry_after(now), None);
+
+        mode.enable("Upgrade", Some(now + chrono::Duration::seconds(90)));
+        assert_eq!(mode.retry_after(now), Some(Duration::from_secs(90)));
+
+        // Overrunning windows fall back to the default
+        let later = now + chrono::Duration::minutes(5);
+        assert_eq!(mode.retry_after(later), Some(DEFAULT_RETRY_AFTER));
+    }
+
+    #[tokio::test]
+    async fn test_requests_rejected_while_enabled() {
+        let mode = MaintenanceMode::new();
+        let client = TestClient::new(
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(axum::middleware::from_fn_with_state(
+                    mode.clone(),
+                    maintenance_middleware,
+                )),
+        );
+
+        client.get("/").send().await.assert_status(StatusCode::OK);
+
+        mode.enable("Database upgrade", None);
+        let response = client.get("/").send().await;
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.header("retry-after"), Some("60"));
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["error"], error_codes::MAINTENANCE);
+        assert_eq!(body["retry_after"], 60);
+
+        mode.disable();
+        client.get("/").send().await.assert_status(StatusCode::OK);
+    }
+}
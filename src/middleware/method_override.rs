@@ -0,0 +1,186 @@
+//! `X-HTTP-Method-Override` for clients limited to `GET` and `POST`.
+//!
+//! Some proxies and legacy clients can only send `GET` and `POST`. With
+//! `EywaApp::method_override`, a `POST` carrying the header is routed as the
+//! method it names:
+//!
+//! ```text
+//! POST /api/v1/projects/Ab12
+//! X-HTTP-Method-Override: DELETE
+//! ```
+//!
+//! Only the configured target methods are honored (`PUT`, `PATCH` and
+//! `DELETE` by default); any other value is a `400`. The header is ignored on
+//! other methods, so a `GET` can never turn into a write.
+//!
+//! Every override is logged with the `eywa_axum::method_override` target, so
+//! audit pipelines can route or filter them apart from regular logs.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use eywa_errors::AppError;
+
+/// Header naming the method a `POST` request is routed as.
+pub const METHOD_OVERRIDE_HEADER: &str = "x-http-method-override";
+
+/// Log target of the override audit events.
+pub const METHOD_OVERRIDE_TARGET: &str = "eywa_axum::method_override";
+
+/// Methods a `POST` request may be overridden to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodOverride {
+    methods: Vec<Method>,
+}
+
+impl Default for MethodOverride {
+    fn default() -> Self {
+        Self {
+            methods: vec![Method::PUT, Method::PATCH, Method::DELETE],
+        }
+    }
+}
+
+impl MethodOverride {
+    /// Allow overriding to `PUT`, `PATCH` and `DELETE`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow overriding to exactly these methods.
+    ///
+    /// # Example
+    /// ```ignore
+    /// MethodOverride::only([Method::DELETE])
+    /// ```
+    pub fn only(methods: impl IntoIterator<Item = Method>) -> Self {
+        Self {
+            methods: methods.into_iter().collect(),
+        }
+    }
+
+    /// The allowed target methods.
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// The method the request should be routed as.
+    ///
+    /// `Ok(None)` when the request isn't overridden.
+    fn target(&self, req: &Request) -> Result<Option<Method>, AppError> {
+        if req.method() != Method::POST {
+            return Ok(None);
+        }
+        let Some(value) = req.headers().get(METHOD_OVERRIDE_HEADER) else {
+            return Ok(None);
+        };
+
+        let method = value
+            .to_str()
+            .ok()
+            .and_then(|value| Method::from_bytes(value.trim().to_uppercase().as_bytes()).ok());
+        match method {
+            Some(method) if self.methods.contains(&method) => Ok(Some(method)),
+            _ => Err(AppError::BadRequest(format!(
+                "{} must be one of: {}",
+                METHOD_OVERRIDE_HEADER,
+                self.methods
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
+
+/// Middleware routing `POST` requests as the method of their override header.
+///
+/// Installed before routing by `EywaApp::method_override()`.
+pub async fn method_override_middleware(
+    State(settings): State<Arc<MethodOverride>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    match settings.target(&req) {
+        Ok(Some(method)) => {
+            tracing::info!(
+                target: METHOD_OVERRIDE_TARGET,
+                original = %req.method(),
+                method = %method,
+                path = %req.uri().path(),
+                "HTTP method overridden"
+            );
+            *req.method_mut() = method;
+        }
+        Ok(None) => {}
+        Err(e) => return e.into_response(),
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{
+        http::StatusCode,
+        routing::{get, put},
+        Router,
+    };
+
+    fn client(settings: MethodOverride) -> TestClient {
+        let router = Router::new()
+            .route("/projects/{id}", put(|| async { "updated" }).delete(|| async { "deleted" }))
+            .route("/reports", get(|| async { "report" }).post(|| async { "created" }));
+        let service = tower::ServiceBuilder::new()
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(settings),
+                method_override_middleware,
+            ))
+            .service(router);
+        TestClient::new(Router::new().fallback_service(service))
+    }
+
+    #[tokio::test]
+    async fn test_post_is_routed_as_override() {
+        let client = client(MethodOverride::new());
+
+        let response = client
+            .post("/projects/1")
+            .header("X-HTTP-Method-Override", "delete")
+            .send()
+            .await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "deleted");
+
+        let response = client.post("/reports").send().await;
+        assert_eq!(response.text(), "created");
+    }
+
+    #[tokio::test]
+    async fn test_only_configured_methods_are_honored() {
+        let client = client(MethodOverride::only([Method::DELETE]));
+
+        let response = client
+            .post("/projects/1")
+            .header("X-HTTP-Method-Override", "PUT")
+            .send()
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+
+        // Only POST is overridden
+        let response = client
+            .get("/reports")
+            .header("X-HTTP-Method-Override", "DELETE")
+            .send()
+            .await;
+        assert_eq!(response.text(), "report");
+    }
+}
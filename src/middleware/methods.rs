@@ -0,0 +1,264 @@
+//! Automatic `HEAD` and `OPTIONS` responses.
+//!
+//! axum answers `HEAD` on every `GET` route with the headers of the `GET`
+//! response and an empty body. `OPTIONS` requests to a path without an
+//! `OPTIONS` handler get a `405`, which breaks clients probing a path for its
+//! methods; with `EywaApp::auto_methods` they get a `204` with the `Allow`
+//! header listing the path's methods instead:
+//!
+//! ```text
+//! OPTIONS /api/v1/projects/Ab12
+//!
+//! HTTP/1.1 204 No Content
+//! allow: GET,HEAD,PUT,DELETE,OPTIONS
+//! ```
+//!
+//! CORS preflight requests are answered by the CORS layer before reaching
+//! the router, so they are unaffected. Neither method is documented in the
+//! spec unless `AutoMethods::document_head` or `document_options` is set.
+
+use axum::{
+    extract::Request,
+    http::{header::ALLOW, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use utoipa::openapi::{
+    header::HeaderBuilder,
+    path::{Operation, OperationBuilder, PathItem},
+    Components, ObjectBuilder, OpenApi, RefOr, ResponseBuilder, Type,
+};
+
+/// Which methods are answered and documented automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoMethods {
+    /// Answer `OPTIONS` with the allowed methods
+    pub options: bool,
+    /// Document a `HEAD` operation for every `GET` operation
+    pub document_head: bool,
+    /// Document an `OPTIONS` operation on every path
+    pub document_options: bool,
+}
+
+impl Default for AutoMethods {
+    fn default() -> Self {
+        Self {
+            options: true,
+            document_head: false,
+            document_options: false,
+        }
+    }
+}
+
+impl AutoMethods {
+    /// Answer `OPTIONS`, without documenting `HEAD` or `OPTIONS`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether `OPTIONS` requests are answered with the allowed methods.
+    pub fn options(mut self, enabled: bool) -> Self {
+        self.options = enabled;
+        self
+    }
+
+    /// Document a `HEAD` operation for every `GET` operation.
+    pub fn document_head(mut self) -> Self {
+        self.document_head = true;
+        self
+    }
+
+    /// Document an `OPTIONS` operation on every path.
+    pub fn document_options(mut self) -> Self {
+        self.document_options = true;
+        self
+    }
+}
+
+/// Middleware answering `OPTIONS` with the methods allowed on the path.
+///
+/// Turns the router's `405` (which carries the `Allow` header) into a `204`,
+/// so paths with their own `OPTIONS` handler are left alone. Installed by
+/// `EywaApp::auto_methods()`.
+pub async fn options_middleware(req: Request, next: Next) -> Response {
+    if req.method() != Method::OPTIONS {
+        return next.run(req).await;
+    }
+    let response = next.run(req).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+    let Some(allow) = response.headers().get(ALLOW).and_then(|v| v.to_str().ok()) else {
+        return response;
+    };
+
+    let allow = format!("{allow},OPTIONS");
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    if let Ok(value) = HeaderValue::from_str(&allow) {
+        response.headers_mut().insert(ALLOW, value);
+    }
+    response
+}
+
+/// The `GET` operation with its response bodies removed.
+fn head_operation(get: &Operation, components: Option<&Components>) -> Operation {
+    let mut head = get.clone();
+    head.operation_id = None;
+    head.request_body = None;
+    for response in head.responses.responses.values_mut() {
+        if let RefOr::Ref(reference) = response {
+            let name = reference.ref_location.rsplit('/').next().unwrap_or_default();
+            let resolved = components.and_then(|components| components.responses.get(name));
+            if let Some(resolved) = resolved.cloned() {
+                *response = resolved;
+            }
+        }
+        if let RefOr::T(response) = response {
+            response.content.clear();
+        }
+    }
+    head
+}
+
+fn allowed_methods(item: &PathItem) -> String {
+    let operations = [
+        ("GET", &item.get),
+        ("HEAD", &item.get),
+        ("POST", &item.post),
+        ("PUT", &item.put),
+        ("DELETE", &item.delete),
+        ("PATCH", &item.patch),
+    ];
+    let mut methods: Vec<&str> = operations
+        .into_iter()
+        .filter(|(_, operation)| operation.is_some())
+        .map(|(method, _)| method)
+        .collect();
+    methods.push("OPTIONS");
+    methods.join(",")
+}
+
+fn options_operation(item: &PathItem) -> Operation {
+    let tags = [&item.get, &item.post, &item.put, &item.delete, &item.patch]
+        .into_iter()
+        .flatten()
+        .find_map(|operation| operation.tags.clone());
+    let allow = HeaderBuilder::new()
+        .schema(
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .examples([allowed_methods(item)]),
+        )
+        .description(Some("Methods allowed on the path"))
+        .build();
+
+    OperationBuilder::new()
+        .tags(tags)
+        .summary(Some("Allowed methods"))
+        .response(
+            "204",
+            ResponseBuilder::new()
+                .description("Methods allowed on the path, in the `Allow` header")
+                .header("Allow", allow)
+                .build(),
+        )
+        .build()
+}
+
+/// Document the `HEAD` and `OPTIONS` operations selected by `methods`.
+///
+/// Added operations have no `operationId`, so they can't collide with the
+/// `GET` operation's. Explicitly documented operations are kept.
+pub fn apply_to_openapi(openapi: &mut OpenApi, methods: &AutoMethods) {
+    let components = openapi.components.as_ref();
+    for item in openapi.paths.paths.values_mut() {
+        if methods.document_head
+            && item.head.is_none()
+            && let Some(get) = &item.get
+        {
+            item.head = Some(head_operation(get, components));
+        }
+        if methods.document_options && methods.options && item.options.is_none() {
+            item.options = Some(options_operation(item));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+    use utoipa::openapi::{path::HttpMethod, ContentBuilder};
+
+    fn router() -> Router {
+        Router::new()
+            .route("/projects", get(|| async { "projects" }).post(|| async { "created" }))
+            .route("/export", get(|| async { "export" }).options(|| async { "custom" }))
+            .layer(axum::middleware::from_fn(options_middleware))
+    }
+
+    #[tokio::test]
+    async fn test_options_reflects_allowed_methods() {
+        let client = TestClient::new(router());
+
+        let response = client.request(Method::OPTIONS, "/projects").send().await;
+        response.assert_status(StatusCode::NO_CONTENT);
+        assert_eq!(response.header("allow"), Some("GET,HEAD,POST,OPTIONS"));
+
+        // Explicit handlers and unknown paths are left to the router
+        let response = client.request(Method::OPTIONS, "/export").send().await;
+        assert_eq!(response.text(), "custom");
+        let response = client.request(Method::OPTIONS, "/unknown").send().await;
+        response.assert_status(StatusCode::NOT_FOUND);
+        let response = client.request(Method::DELETE, "/projects").send().await;
+        response.assert_status(StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_head_has_no_body() {
+        let client = TestClient::new(router());
+
+        let response = client.request(Method::HEAD, "/projects").send().await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.bytes().is_empty());
+    }
+
+    #[test]
+    fn test_documents_head_and_options() {
+        let get = OperationBuilder::new()
+            .operation_id(Some("list"))
+            .tag("Projects")
+            .response(
+                "200",
+                ResponseBuilder::new()
+                    .description("Projects")
+                    .content("application/json", ContentBuilder::new().build()),
+            )
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/projects".to_string(), PathItem::new(HttpMethod::Get, get));
+
+        let mut undocumented = openapi.clone();
+        apply_to_openapi(&mut undocumented, &AutoMethods::new());
+        assert!(undocumented.paths.paths["/projects"].head.is_none());
+
+        let methods = AutoMethods::new().document_head().document_options();
+        apply_to_openapi(&mut openapi, &methods);
+        let item = &openapi.paths.paths["/projects"];
+
+        let head = item.head.as_ref().unwrap();
+        assert_eq!(head.operation_id, None);
+        let RefOr::T(ok) = &head.responses.responses["200"] else {
+            panic!("expected an inline response");
+        };
+        assert!(ok.content.is_empty());
+
+        let options = item.options.as_ref().unwrap();
+        assert_eq!(options.tags, Some(vec!["Projects".to_string()]));
+        assert!(options.responses.responses.contains_key("204"));
+    }
+}
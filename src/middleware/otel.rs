@@ -0,0 +1,315 @@
+//! OpenTelemetry distributed tracing.
+//!
+//! `EywaApp::otel_tracing` exports one server span per request over OTLP
+//! (gRPC) to a collector, Tempo or Jaeger:
+//!
+//! ```ignore
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .observability(config.observability.clone())
+//!     .otel_tracing(
+//!         OtelConfig::new("projects")
+//!             .endpoint("http://otel-collector:4317")
+//!             .sample_ratio(0.1),
+//!     )
+//! ```
+//!
+//! - The W3C `traceparent`/`tracestate` headers of the request are the
+//!   parent of the server span; sampling follows the parent's decision
+//! - Spans are named after the route template (`GET /projects/{id}`), never
+//!   the raw path; unmatched requests are named after the method only
+//! - The request's `traceparent` is replaced by the server span's, so the
+//!   `trace_id` of the request logs is the exported trace, and handlers
+//!   forwarding it continue the trace
+//! - `inject_context` adds the headers to outgoing requests
+//!
+//! Requires the `otel` feature.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use opentelemetry::{
+    propagation::{Extractor, Injector, TextMapPropagator},
+    trace::{SpanKind, Status, TraceContextExt, Tracer, TracerProvider as _},
+    Context, KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{Sampler, TracerProvider},
+    Resource,
+};
+use serde::{Deserialize, Serialize};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// Default OTLP gRPC endpoint, a collector running next to the service.
+pub const DEFAULT_OTLP_ENDPOINT: &str = "http://localhost:4317";
+
+/// Instrumentation scope of the server spans.
+const TRACER_NAME: &str = "eywa-axum";
+
+tokio::task_local! {
+    static CONTEXT: Context;
+}
+
+fn default_endpoint() -> String {
+    DEFAULT_OTLP_ENDPOINT.to_string()
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+/// OTLP exporter settings, embeddable in a service's `EywaConfig`.
+///
+/// ```toml
+/// [otel]
+/// service_name = "projects"
+/// endpoint = "http://otel-collector:4317"
+/// sample_ratio = 0.1
+///
+/// [otel.resource]
+/// "deployment.environment" = "production"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OtelConfig {
+    /// `service.name` of the exported spans
+    pub service_name: String,
+    /// OTLP gRPC endpoint
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+    /// Share of new traces sampled, between 0 and 1
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+    /// Extra resource attributes
+    #[serde(default)]
+    pub resource: BTreeMap<String, String>,
+}
+
+impl OtelConfig {
+    /// Export every trace of `service_name` to a local collector.
+    pub fn new(service_name: impl Into<String>) -> Self {
+        Self {
+            service_name: service_name.into(),
+            endpoint: default_endpoint(),
+            sample_ratio: default_sample_ratio(),
+            resource: BTreeMap::new(),
+        }
+    }
+
+    /// Export to another OTLP gRPC endpoint.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+        self
+    }
+
+    /// Sample this share of new traces. Traces continued from a caller
+    /// follow the caller's decision.
+    pub fn sample_ratio(mut self, ratio: f64) -> Self {
+        self.sample_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Add a resource attribute (e.g. `deployment.environment`).
+    pub fn resource_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.resource.insert(key.into(), value.into());
+        self
+    }
+
+    /// Build the batching OTLP exporter and install it as the global provider.
+    pub fn install(&self) -> Result<TracerProvider> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(&self.endpoint)
+            .build()
+            .map_err(|e| AppError::InternalServerError(format!("Invalid OTLP exporter: {e}")))?;
+
+        let attributes = std::iter::once(KeyValue::new("service.name", self.service_name.clone()))
+            .chain(
+                self.resource
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+            );
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                self.sample_ratio,
+            ))))
+            .with_resource(Resource::new(attributes))
+            .build();
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        Ok(provider)
+    }
+}
+
+/// The tracer creating the server spans of a provider.
+pub fn server_tracer(provider: &TracerProvider) -> opentelemetry_sdk::trace::Tracer {
+    provider.tracer(TRACER_NAME)
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_bytes(key.as_bytes()), HeaderValue::from_str(&value))
+        {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+/// Context of the request being handled, if traced.
+pub fn current_context() -> Option<Context> {
+    CONTEXT.try_with(Clone::clone).ok()
+}
+
+/// Add the `traceparent`/`tracestate` of the current request to the headers
+/// of an outgoing request, so the callee continues the trace.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut headers = HeaderMap::new();
+/// inject_context(&mut headers);
+/// let response = http.get(url).headers(headers).send().await?;
+/// ```
+pub fn inject_context(headers: &mut HeaderMap) {
+    if let Some(cx) = current_context() {
+        TraceContextPropagator::new().inject_context(&cx, &mut HeaderInjector(headers));
+    }
+}
+
+/// Middleware running each request in a server span.
+///
+/// Installed by `EywaApp::otel_tracing()`, around the whole middleware stack.
+pub async fn otel_middleware(
+    State(tracer): State<opentelemetry_sdk::trace::Tracer>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let propagator = TraceContextPropagator::new();
+    let parent = propagator.extract(&HeaderExtractor(req.headers()));
+
+    let method = req.method().to_string();
+    let span = tracer
+        .span_builder(method.clone())
+        .with_kind(SpanKind::Server)
+        .with_attributes([
+            KeyValue::new("http.request.method", method),
+            KeyValue::new("url.path", req.uri().path().to_string()),
+        ])
+        .start_with_context(&tracer, &parent);
+    let cx = parent.with_span(span);
+
+    // Logs and forwarded headers continue the server span
+    propagator.inject_context(&cx, &mut HeaderInjector(req.headers_mut()));
+
+    let response = CONTEXT.scope(cx.clone(), next.run(req)).await;
+
+    let span = cx.span();
+    let status = response.status();
+    span.set_attribute(KeyValue::new(
+        "http.response.status_code",
+        i64::from(status.as_u16()),
+    ));
+    if status.is_server_error() {
+        span.set_status(Status::error(status.to_string()));
+    }
+    span.end();
+    response
+}
+
+/// Route middleware naming the server span after the route template.
+///
+/// Installed by `EywaApp::otel_tracing()`.
+pub async fn otel_route_middleware(req: Request, next: Next) -> Response {
+    if let Some(route) = req.extensions().get::<MatchedPath>() {
+        let name = format!("{} {}", req.method(), route.as_str());
+        let route = route.as_str().to_string();
+        let _ = CONTEXT.try_with(|cx| {
+            let span = cx.span();
+            span.update_name(name);
+            span.set_attribute(KeyValue::new("http.route", route));
+        });
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+    fn client() -> TestClient {
+        let tracer = server_tracer(&TracerProvider::builder().build());
+        let router = Router::new()
+            .route(
+                "/projects/{id}",
+                get(|headers: HeaderMap| async move {
+                    let mut outgoing = HeaderMap::new();
+                    inject_context(&mut outgoing);
+                    let incoming = headers["traceparent"].to_str().unwrap().to_string();
+                    assert_eq!(outgoing["traceparent"], incoming);
+                    incoming
+                }),
+            )
+            .route_layer(axum::middleware::from_fn(otel_route_middleware))
+            .layer(axum::middleware::from_fn_with_state(tracer, otel_middleware));
+        TestClient::new(router)
+    }
+
+    #[tokio::test]
+    async fn test_continues_incoming_trace() {
+        let parent = format!("00-{TRACE_ID}-00f067aa0ba902b7-01");
+        let response = client()
+            .get("/projects/42")
+            .header("traceparent", &parent)
+            .send()
+            .await;
+
+        let traceparent = response.text();
+        let parts: Vec<_> = traceparent.split('-').collect();
+        assert_eq!(parts[1], TRACE_ID);
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+        assert_eq!(parts[3], "01");
+    }
+
+    #[tokio::test]
+    async fn test_starts_trace_without_parent() {
+        let traceparent = client().get("/projects/42").send().await.text();
+        assert_eq!(traceparent.len(), 55);
+        assert!(!traceparent.contains(TRACE_ID));
+    }
+
+    #[test]
+    fn test_no_context_outside_requests() {
+        let mut headers = HeaderMap::new();
+        inject_context(&mut headers);
+        assert!(headers.is_empty());
+        assert!(current_context().is_none());
+    }
+}
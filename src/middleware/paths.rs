@@ -0,0 +1,294 @@
+//! Path normalization: trailing slashes and case-insensitive matching.
+//!
+//! Normalization runs before routing, so it decides which route a request
+//! reaches. Without `EywaApp::path_policy`, paths are matched as sent (axum's
+//! behavior: `/projects/` doesn't match `/projects`).
+//!
+//! Trailing slashes are handled by the `TrailingSlash` mode:
+//!
+//! - `Strict` - Matched as sent, a mismatched slash is a `404`
+//! - `Trim` - `/projects/` is routed as `/projects`
+//! - `Append` - `/projects` is routed as `/projects/`
+//! - `Redirect` - `/projects/` is answered with a `308` to `/projects`
+//!
+//! With case-insensitive matching, the static segments of a path are matched
+//! against the documented routes regardless of case (`/API/v1/Projects/Ab12`
+//! is routed as `/api/v1/projects/Ab12`); path parameters keep their case.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{uri::PathAndQuery, Uri},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use utoipa::openapi::OpenApi;
+
+/// How paths with or without a trailing slash are routed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Match paths as sent
+    #[default]
+    Strict,
+    /// Route `/projects/` as `/projects`
+    Trim,
+    /// Route `/projects` as `/projects/`
+    Append,
+    /// Answer `/projects/` with a `308 Permanent Redirect` to `/projects`
+    Redirect,
+}
+
+/// Path normalization applied before routing.
+///
+/// # Example
+///
+/// ```ignore
+/// EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .path_policy(
+///         PathPolicy::new()
+///             .trailing_slash(TrailingSlash::Redirect)
+///             .case_insensitive(),
+///     )
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathPolicy {
+    pub trailing_slash: TrailingSlash,
+    pub case_insensitive: bool,
+}
+
+impl PathPolicy {
+    /// Strict matching: paths are routed as sent.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how trailing slashes are handled.
+    pub fn trailing_slash(mut self, mode: TrailingSlash) -> Self {
+        self.trailing_slash = mode;
+        self
+    }
+
+    /// Match the static segments of documented routes regardless of case.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Normalized {
+    Rewrite(String),
+    Redirect(String),
+}
+
+/// Applies a `PathPolicy` to request paths, knowing the documented routes.
+#[derive(Debug, Clone)]
+pub struct PathNormalizer {
+    policy: PathPolicy,
+    /// Segments of the documented route templates
+    routes: Vec<Vec<String>>,
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+impl PathNormalizer {
+    /// Create a normalizer for the paths of the spec.
+    pub fn new(policy: PathPolicy, openapi: &OpenApi) -> Self {
+        let routes = openapi
+            .paths
+            .paths
+            .keys()
+            .map(|path| segments(path).map(str::to_string).collect())
+            .collect();
+        Self { policy, routes }
+    }
+
+    /// The path a request should be routed to, if it differs from `path`.
+    fn normalize(&self, path: &str) -> Option<Normalized> {
+        let mut target = path.to_string();
+        let mut redirect = false;
+        match self.policy.trailing_slash {
+            TrailingSlash::Strict => {}
+            TrailingSlash::Trim | TrailingSlash::Redirect if path.len() > 1 => {
+                target = format!("/{}", path.trim_matches('/'));
+                redirect = self.policy.trailing_slash == TrailingSlash::Redirect;
+            }
+            TrailingSlash::Append if !path.ends_with('/') => target.push('/'),
+            _ => {}
+        }
+        if self.policy.case_insensitive
+            && let Some(canonical) = self.canonical_case(&target)
+        {
+            target = canonical;
+        }
+
+        match (target == path, redirect) {
+            (true, _) => None,
+            (false, true) => Some(Normalized::Redirect(target)),
+            (false, false) => Some(Normalized::Rewrite(target)),
+        }
+    }
+
+    /// The path with the static segments spelled as in the matching route.
+    fn canonical_case(&self, path: &str) -> Option<String> {
+        let requested: Vec<&str> = segments(path).collect();
+        let matches = |route: &[String], same: fn(&str, &str) -> bool| {
+            let catch_all = route.last().is_some_and(|last| last.starts_with("{*"));
+            let len_matches = if catch_all {
+                requested.len() >= route.len() - 1
+            } else {
+                requested.len() == route.len()
+            };
+            len_matches
+                && route
+                    .iter()
+                    .zip(&requested)
+                    .all(|(template, segment)| {
+                        template.starts_with('{') || same(template, segment)
+                    })
+        };
+
+        if self.routes.iter().any(|route| matches(route, |a, b| a == b)) {
+            return None;
+        }
+        let route = self
+            .routes
+            .iter()
+            .find(|route| matches(route, |a, b| a.eq_ignore_ascii_case(b)))?;
+
+        let canonical: Vec<&str> = requested
+            .iter()
+            .enumerate()
+            .map(|(i, segment)| match route.get(i) {
+                Some(template) if !template.starts_with('{') => template.as_str(),
+                _ => *segment,
+            })
+            .collect();
+        let trailing = if path.len() > 1 && path.ends_with('/') { "/" } else { "" };
+        Some(format!("/{}{trailing}", canonical.join("/")))
+    }
+}
+
+fn with_path(uri: &Uri, path: &str) -> Option<Uri> {
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(PathAndQuery::try_from(path_and_query).ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+/// Middleware normalizing the request path before routing.
+///
+/// Installed around the router by `EywaApp::path_policy()`.
+pub async fn path_normalization_middleware(
+    State(normalizer): State<Arc<PathNormalizer>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    match normalizer.normalize(req.uri().path()) {
+        Some(Normalized::Redirect(path)) => {
+            let location = match req.uri().query() {
+                Some(query) => format!("{path}?{query}"),
+                None => path,
+            };
+            return Redirect::permanent(&location).into_response();
+        }
+        Some(Normalized::Rewrite(path)) => {
+            if let Some(uri) = with_path(req.uri(), &path) {
+                *req.uri_mut() = uri;
+            }
+        }
+        None => {}
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Router};
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn spec() -> OpenApi {
+        let mut openapi = OpenApi::default();
+        for path in ["/api/v1/projects", "/api/v1/projects/{code}", "/files/{*path}"] {
+            openapi.paths.paths.insert(
+                path.to_string(),
+                PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+            );
+        }
+        openapi
+    }
+
+    fn normalizer(policy: PathPolicy) -> PathNormalizer {
+        PathNormalizer::new(policy, &spec())
+    }
+
+    #[test]
+    fn test_trailing_slash_modes() {
+        let strict = normalizer(PathPolicy::new());
+        assert_eq!(strict.normalize("/api/v1/projects/"), None);
+
+        let trim = normalizer(PathPolicy::new().trailing_slash(TrailingSlash::Trim));
+        assert_eq!(
+            trim.normalize("/api/v1/projects//"),
+            Some(Normalized::Rewrite("/api/v1/projects".to_string()))
+        );
+        assert_eq!(trim.normalize("/"), None);
+
+        let append = normalizer(PathPolicy::new().trailing_slash(TrailingSlash::Append));
+        assert_eq!(
+            append.normalize("/api/v1/projects"),
+            Some(Normalized::Rewrite("/api/v1/projects/".to_string()))
+        );
+
+        let redirect = normalizer(PathPolicy::new().trailing_slash(TrailingSlash::Redirect));
+        assert_eq!(
+            redirect.normalize("/api/v1/projects/"),
+            Some(Normalized::Redirect("/api/v1/projects".to_string()))
+        );
+        assert_eq!(redirect.normalize("/api/v1/projects"), None);
+    }
+
+    #[test]
+    fn test_case_insensitive_keeps_parameter_case() {
+        let policy = PathPolicy::new().case_insensitive();
+        let normalizer = normalizer(policy);
+
+        assert_eq!(
+            normalizer.normalize("/API/V1/Projects/Ab12"),
+            Some(Normalized::Rewrite("/api/v1/projects/Ab12".to_string()))
+        );
+        assert_eq!(
+            normalizer.normalize("/FILES/Docs/Readme.md"),
+            Some(Normalized::Rewrite("/files/Docs/Readme.md".to_string()))
+        );
+        assert_eq!(normalizer.normalize("/api/v1/projects/Ab12"), None);
+        assert_eq!(normalizer.normalize("/unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_keeps_query() {
+        let normalizer = normalizer(PathPolicy::new().trailing_slash(TrailingSlash::Redirect));
+        let router = Router::new().route("/api/v1/projects", get(|| async { "projects" }));
+        let service = tower::ServiceBuilder::new()
+            .layer(axum::middleware::from_fn_with_state(
+                Arc::new(normalizer),
+                path_normalization_middleware,
+            ))
+            .service(router);
+        let client = TestClient::new(Router::new().fallback_service(service));
+
+        let response = client.get("/api/v1/projects/?page=2").send().await;
+        response.assert_status(StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.header("location"), Some("/api/v1/projects?page=2"));
+        client.get("/api/v1/projects").send().await.assert_status(StatusCode::OK);
+    }
+}
@@ -0,0 +1,237 @@
+//! Declarative header propagation.
+//!
+//! Which inbound headers reach downstream services, and which come back to
+//! the caller, is usually decided call site by call site, so a tenant header
+//! is forwarded by one client and forgotten by the next, and an internal
+//! auth header set by the mesh ends up forwarded to a third party. A
+//! `HeaderPolicy` decides once, from config:
+//!
+//! ```toml
+//! [headers]
+//! propagate = ["x-tenant-id", "baggage", "x-b3-*"]  # forwarded by OutboundClient
+//! reflect = ["x-request-source"]                    # copied onto the response
+//! strip = ["x-internal-auth"]                       # removed from requests and responses
+//! ```
+//!
+//! Names are case-insensitive; a trailing `*` matches a prefix. Stripped
+//! headers are removed before the handler runs and from the response, and
+//! are never propagated or reflected, even when another list names them.
+//! The propagated headers are carried by `RequestContext::propagated_headers`
+//! and sent by every `OutboundClient` request, next to the correlation ID and
+//! deadline.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::RequestContext;
+
+/// Headers `OutboundClient` sets itself, never taken from the inbound request.
+const RESERVED: [&str; 3] = ["x-correlation-id", "x-request-deadline", "grpc-timeout"];
+
+/// Header propagation policy, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderPolicy {
+    /// Inbound headers forwarded to outbound calls
+    #[serde(default)]
+    pub propagate: Vec<String>,
+    /// Inbound headers copied onto the response
+    #[serde(default)]
+    pub reflect: Vec<String>,
+    /// Headers removed from inbound requests and from responses
+    #[serde(default)]
+    pub strip: Vec<String>,
+}
+
+/// Whether `name` is listed, by exact name or `prefix*`.
+fn listed(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name
+            .get(..prefix.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(name),
+    })
+}
+
+impl HeaderPolicy {
+    /// A policy propagating, reflecting and stripping nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward `name` (or `prefix*`) to outbound calls.
+    pub fn propagate(mut self, name: impl Into<String>) -> Self {
+        self.propagate.push(name.into());
+        self
+    }
+
+    /// Copy `name` (or `prefix*`) from the request onto the response.
+    pub fn reflect(mut self, name: impl Into<String>) -> Self {
+        self.reflect.push(name.into());
+        self
+    }
+
+    /// Remove `name` (or `prefix*`) from requests and responses.
+    pub fn strip(mut self, name: impl Into<String>) -> Self {
+        self.strip.push(name.into());
+        self
+    }
+
+    /// Returns `true` if `name` is stripped.
+    pub fn strips(&self, name: &str) -> bool {
+        listed(&self.strip, name)
+    }
+
+    /// The inbound headers to forward, by lowercase name.
+    ///
+    /// Repeated headers are joined with `, `.
+    pub fn propagated(&self, headers: &HeaderMap) -> BTreeMap<String, String> {
+        let mut propagated = BTreeMap::<String, String>::new();
+        for (name, value) in headers {
+            let name = name.as_str();
+            if RESERVED.contains(&name) || self.strips(name) || !listed(&self.propagate, name) {
+                continue;
+            }
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            propagated
+                .entry(name.to_string())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+        propagated
+    }
+
+    /// The inbound headers to copy onto the response.
+    fn reflected(&self, headers: &HeaderMap) -> HeaderMap {
+        headers
+            .iter()
+            .filter(|(name, _)| {
+                listed(&self.reflect, name.as_str()) && !self.strips(name.as_str())
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Remove the stripped headers.
+    fn strip_from(&self, headers: &mut HeaderMap) {
+        let stripped: Vec<HeaderName> =
+            headers.keys().filter(|name| self.strips(name.as_str())).cloned().collect();
+        for name in stripped {
+            headers.remove(name);
+        }
+    }
+}
+
+/// Middleware applying the header policy.
+///
+/// Strips the request headers, makes the policy available to the context
+/// middleware (or fills `RequestContext::propagated_headers` itself when the
+/// context is already set), then reflects and strips the response headers.
+/// Installed by `EywaApp::header_policy()`.
+pub async fn header_policy_middleware(
+    State(policy): State<Arc<HeaderPolicy>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let reflected = policy.reflected(req.headers());
+    let propagated = policy.propagated(req.headers());
+    policy.strip_from(req.headers_mut());
+    if let Some(ctx) = req.extensions_mut().get_mut::<RequestContext>() {
+        ctx.propagated_headers = propagated;
+    }
+    req.extensions_mut().insert(policy.clone());
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    for (name, value) in &reflected {
+        if !headers.contains_key(name) {
+            headers.append(name, value.clone());
+        }
+    }
+    policy.strip_from(headers);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Extension, Router};
+
+    fn policy() -> HeaderPolicy {
+        HeaderPolicy::new()
+            .propagate("x-tenant-id")
+            .propagate("x-b3-*")
+            .propagate("x-internal-auth")
+            .propagate("x-correlation-id")
+            .reflect("x-request-source")
+            .strip("X-Internal-*")
+    }
+
+    #[test]
+    fn test_propagated_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", "acme".parse().unwrap());
+        headers.append("x-b3-flags", "1".parse().unwrap());
+        headers.append("x-b3-flags", "0".parse().unwrap());
+        headers.insert("x-internal-auth", "secret".parse().unwrap());
+        headers.insert("x-correlation-id", "abc".parse().unwrap());
+        headers.insert("authorization", "Bearer token".parse().unwrap());
+
+        let propagated = policy().propagated(&headers);
+        assert_eq!(
+            propagated.into_iter().collect::<Vec<_>>(),
+            [
+                ("x-b3-flags".to_string(), "1, 0".to_string()),
+                ("x-tenant-id".to_string(), "acme".to_string()),
+            ]
+        );
+    }
+
+    async fn whoami(
+        Extension(ctx): Extension<RequestContext>,
+        headers: HeaderMap,
+    ) -> impl axum::response::IntoResponse {
+        let tenant = ctx.propagated_headers.get("x-tenant-id").cloned();
+        let leaked = headers.contains_key("x-internal-auth");
+        ([("x-internal-trace", "debug")], format!("{tenant:?} {leaked}"))
+    }
+
+    #[tokio::test]
+    async fn test_policy_applied_to_requests_and_responses() {
+        let client = TestClient::new(
+            Router::new()
+                .route("/whoami", get(whoami))
+                .layer(axum::middleware::from_fn(
+                    crate::middleware::request_context_middleware_fn,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(policy()),
+                    header_policy_middleware,
+                )),
+        );
+
+        let response = client
+            .get("/whoami")
+            .header("x-tenant-id", "acme")
+            .header("x-internal-auth", "secret")
+            .header("x-request-source", "mobile")
+            .send()
+            .await;
+        assert_eq!(response.text(), "Some(\"acme\") false");
+        assert_eq!(response.header("x-request-source"), Some("mobile"));
+        assert_eq!(response.header("x-internal-trace"), None);
+    }
+}
@@ -0,0 +1,294 @@
+//! Bounded request queueing with backpressure.
+//!
+//! A bare concurrency limit rejects a request as soon as every slot is busy,
+//! so a burst of a few requests above the limit already produces errors. The
+//! admission queue lets up to `depth` requests wait (at most `max_wait`) for
+//! a slot before shedding them with `503 Service Unavailable`:
+//!
+//! ```toml
+//! [admission]
+//! max_concurrency = 64
+//! depth = 128           # requests allowed to wait
+//! max_wait_ms = 250     # shed requests waiting longer
+//! ```
+//!
+//! Metrics:
+//! - `eywa_admission_queue_depth` (gauge) - requests currently waiting
+//! - `eywa_admission_queue_wait_seconds` (histogram) - wait of admitted requests
+//! - `eywa_admission_rejected_total` (counter, `reason` = `full` / `timeout`)
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+
+/// Admission queue settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmissionSettings {
+    /// Requests running at the same time
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+    /// Requests allowed to wait for a slot
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+    /// Longest wait for a slot, in milliseconds
+    #[serde(default = "default_max_wait_ms")]
+    pub max_wait_ms: u64,
+}
+
+fn default_max_concurrency() -> usize {
+    64
+}
+
+fn default_depth() -> usize {
+    128
+}
+
+fn default_max_wait_ms() -> u64 {
+    250
+}
+
+impl Default for AdmissionSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrency: default_max_concurrency(),
+            depth: default_depth(),
+            max_wait_ms: default_max_wait_ms(),
+        }
+    }
+}
+
+impl AdmissionSettings {
+    /// Run at most `max_concurrency` requests at the same time.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// Let at most `depth` requests wait for a slot.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Shed requests that waited longer than `max_wait`.
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait_ms = max_wait.as_millis() as u64;
+        self
+    }
+
+    fn max_wait_duration(&self) -> Duration {
+        Duration::from_millis(self.max_wait_ms)
+    }
+}
+
+/// Concurrency limit with a bounded wait queue.
+#[derive(Debug)]
+pub struct AdmissionQueue {
+    settings: AdmissionSettings,
+    semaphore: Arc<Semaphore>,
+    waiting: AtomicUsize,
+}
+
+/// Why a request wasn't admitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    /// The queue was full on arrival
+    Full,
+    /// No slot freed up within `max_wait`
+    Timeout,
+}
+
+impl Rejection {
+    fn reason(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Timeout => "timeout",
+        }
+    }
+}
+
+/// Removes a waiting request from the queue, even if its future is dropped.
+struct Waiting<'a>(&'a AdmissionQueue);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let depth = self.0.waiting.fetch_sub(1, Ordering::AcqRel) - 1;
+        metrics::gauge!("eywa_admission_queue_depth").set(depth as f64);
+    }
+}
+
+impl AdmissionQueue {
+    /// Create an empty queue.
+    pub fn new(settings: AdmissionSettings) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(settings.max_concurrency)),
+            settings,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Requests currently waiting for a slot.
+    pub fn depth(&self) -> usize {
+        self.waiting.load(Ordering::Acquire)
+    }
+
+    /// Wait for a slot, unless the queue is full or the wait exceeds `max_wait`.
+    async fn admit(&self) -> Result<tokio::sync::OwnedSemaphorePermit, Rejection> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let depth = self.settings.depth;
+        self.waiting
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |waiting| {
+                (waiting < depth).then_some(waiting + 1)
+            })
+            .map_err(|_| Rejection::Full)?;
+        let _waiting = Waiting(self);
+        metrics::gauge!("eywa_admission_queue_depth").set(self.depth() as f64);
+
+        let started = Instant::now();
+        let permit = tokio::time::timeout(
+            self.settings.max_wait_duration(),
+            self.semaphore.clone().acquire_owned(),
+        )
+        .await
+        .map_err(|_| Rejection::Timeout)?
+        .map_err(|_| Rejection::Full)?;
+        metrics::histogram!("eywa_admission_queue_wait_seconds")
+            .record(started.elapsed().as_secs_f64());
+        Ok(permit)
+    }
+}
+
+fn shed(queue: &AdmissionQueue) -> Response {
+    ErrorResponse::new(error_codes::SERVICE_UNAVAILABLE, "Server is overloaded, retry later")
+        .retry_after(queue.settings.max_wait_duration())
+        .into_response_with(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+/// Middleware admitting requests through the queue.
+///
+/// Installed by `EywaApp::admission_queue()`.
+pub async fn admission_middleware(
+    State(queue): State<Arc<AdmissionQueue>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match queue.admit().await {
+        Ok(_permit) => next.run(req).await,
+        Err(rejection) => {
+            tracing::warn!(
+                reason = rejection.reason(),
+                depth = queue.depth(),
+                "admission queue rejected request"
+            );
+            metrics::counter!("eywa_admission_rejected_total", "reason" => rejection.reason())
+                .increment(1);
+            shed(&queue)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    fn client(settings: AdmissionSettings) -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route(
+                    "/work",
+                    get(|| async {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        "done"
+                    }),
+                )
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(AdmissionQueue::new(settings)),
+                    admission_middleware,
+                )),
+        )
+    }
+
+    #[test]
+    fn test_settings_defaults() {
+        let settings: AdmissionSettings = serde_json::from_str(r#"{ "depth": 8 }"#).unwrap();
+        assert_eq!(settings.max_concurrency, 64);
+        assert_eq!(settings.depth, 8);
+        assert_eq!(settings.max_wait_duration(), Duration::from_millis(250));
+    }
+
+    #[tokio::test]
+    async fn test_burst_queued_instead_of_rejected() {
+        let client = client(
+            AdmissionSettings::default()
+                .max_concurrency(1)
+                .depth(2)
+                .max_wait(Duration::from_secs(1)),
+        );
+
+        let (first, second, third) = tokio::join!(
+            client.get("/work").send(),
+            client.get("/work").send(),
+            client.get("/work").send(),
+        );
+        first.assert_status(StatusCode::OK);
+        second.assert_status(StatusCode::OK);
+        third.assert_status(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_and_long_waits_shed() {
+        let client = client(
+            AdmissionSettings::default()
+                .max_concurrency(1)
+                .depth(1)
+                .max_wait(Duration::from_millis(50)),
+        );
+
+        let (first, second, third) = tokio::join!(
+            client.get("/work").send(),
+            async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                client.get("/work").send().await
+            },
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                client.get("/work").send().await
+            },
+        );
+        first.assert_status(StatusCode::OK);
+        // Waited longer than 50ms
+        second.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        // Queue full on arrival
+        third.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(third.header("retry-after"), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth_released() {
+        let queue = AdmissionQueue::new(
+            AdmissionSettings::default()
+                .max_concurrency(0)
+                .max_wait(Duration::from_millis(10)),
+        );
+
+        assert_eq!(queue.admit().await.unwrap_err(), Rejection::Timeout);
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -0,0 +1,614 @@
+//! Rate limiting keyed by client IP, tenant or API key.
+//!
+//! Behind a shared NAT every customer of a network has the same IP, so limits
+//! can instead be keyed by tenant or API key, with a quota per tier. Only
+//! verified identities are used: the tenant claim of the bearer token
+//! verified with the `JwtConfig` of `EywaApp::auth`, or the id of the key
+//! verified by `EywaApp::api_key_auth` (add the rate limit before it, so it
+//! runs inside). Headers a client could make up, like `X-Tenant-ID`, never
+//! pick the bucket or the tier. Tier assignments come from config, and from
+//! a `TierStore` (e.g. a database table) for keys that aren't listed there.
+//! Requests without a verified identity are limited by IP.
+//!
+//! The IP is the peer address of the connection. Behind proxies, list them
+//! in `trusted_proxies`: `X-Forwarded-For` is then read from the right,
+//! skipping trusted proxies, and the first other address is the client.
+//! Entries left of it are set by the client and never used.
+//!
+//! ```toml
+//! [rate_limit]
+//! key = { by = "tenant", claim = "tenant_id" }
+//! default = { requests = 100, period_secs = 60 }
+//! trusted_proxies = ["10.0.0.5", "10.0.0.6"]
+//!
+//! [rate_limit.tiers]
+//! pro = { requests = 1000, period_secs = 60 }
+//! enterprise = { requests = 10000, period_secs = 60 }
+//!
+//! [rate_limit.assignments]
+//! acme = "enterprise"
+//! ```
+//!
+//! Each key has a token bucket refilled continuously: a quota of 100 requests
+//! per minute allows bursts of 100 requests, then one request every 600ms.
+//! Limited requests are rejected with `429 Too Many Requests` and a
+//! `Retry-After` header (also `retry_after` in the body) telling when the
+//! next token is available; responses carry `RateLimit-Limit` and
+//! `RateLimit-Remaining` headers.
+//!
+//! `RateLimiter::watch` follows a `Watch<RateLimitSettings>`: reloaded quotas
+//! and assignments apply from the next request, keeping the current buckets.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::clock;
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::middleware::auth::{SharedVerifier, TENANT_CLAIM};
+use crate::reload::Watch;
+use crate::Result;
+
+/// Default header carrying the tenant.
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Default header carrying the API key.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Header with the request quota of the key.
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "ratelimit-limit";
+
+/// Header with the requests left in the current window.
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "ratelimit-remaining";
+
+/// How long tiers resolved through the `TierStore` are cached.
+const TIER_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Number of buckets (or cached tiers) above which stale ones are evicted.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// What requests are counted by.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Client IP: the peer address, or the `X-Forwarded-For` entry added by
+    /// the outermost trusted proxy
+    #[default]
+    Ip,
+    /// Tenant claim of the bearer token verified by `EywaApp::auth`
+    Tenant {
+        #[serde(default = "default_tenant_claim")]
+        claim: String,
+    },
+    /// Id of the API key verified by `EywaApp::api_key_auth`
+    ApiKey,
+}
+
+fn default_tenant_claim() -> String {
+    TENANT_CLAIM.to_string()
+}
+
+impl RateLimitKey {
+    /// Key by the `tenant_id` claim of the verified bearer token.
+    pub fn tenant() -> Self {
+        Self::Tenant {
+            claim: default_tenant_claim(),
+        }
+    }
+
+    /// Key by the id of the verified API key.
+    pub fn api_key() -> Self {
+        Self::ApiKey
+    }
+
+    /// The verified identity of a request, if any.
+    fn identity(&self, req: &mut Request, verifier: &SharedVerifier) -> Option<String> {
+        match self {
+            Self::Ip => None,
+            Self::Tenant { claim } => verifier
+                .get()?
+                .try_authenticate(req)
+                .ok()
+                .flatten()
+                .and_then(|claims| claims.string(claim)),
+            Self::ApiKey => api_key_id(req),
+        }
+    }
+}
+
+#[cfg(feature = "api-keys")]
+fn api_key_id(req: &Request) -> Option<String> {
+    let info = req.extensions().get::<crate::api_keys::ApiKeyInfo>()?;
+    Some(info.id.to_string())
+}
+
+#[cfg(not(feature = "api-keys"))]
+fn api_key_id(_req: &Request) -> Option<String> {
+    None
+}
+
+/// Client IP of a request.
+///
+/// `X-Forwarded-For` is only read when the peer is a trusted proxy, from the
+/// right: each trusted proxy appends the address it was connected from, so
+/// the first untrusted one is the client. Unparsable entries end the walk
+/// at the last trusted hop.
+fn client_ip(req: &Request, trusted_proxies: &[IpAddr]) -> String {
+    let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>() else {
+        return "unknown".to_string();
+    };
+    let mut client = peer.ip();
+    let forwarded = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+    for entry in forwarded.iter().rev() {
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+        match entry.trim().parse::<IpAddr>() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+    }
+    client.to_string()
+}
+
+/// Requests allowed per period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quota {
+    pub requests: u32,
+    #[serde(default = "default_period_secs")]
+    pub period_secs: u64,
+}
+
+fn default_period_secs() -> u64 {
+    60
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self::per_minute(100)
+    }
+}
+
+impl Quota {
+    /// `requests` per second.
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            requests,
+            period_secs: 1,
+        }
+    }
+
+    /// `requests` per minute.
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            requests,
+            period_secs: 60,
+        }
+    }
+
+    fn period(&self) -> Duration {
+        Duration::from_secs(self.period_secs.max(1))
+    }
+
+    /// Tokens refilled per second.
+    fn rate(&self) -> f64 {
+        self.requests as f64 / self.period().as_secs_f64()
+    }
+}
+
+/// Rate limiting configuration, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitSettings {
+    #[serde(default)]
+    pub key: RateLimitKey,
+    /// Quota of keys without a tier
+    #[serde(default)]
+    pub default: Quota,
+    /// Quota of each tier
+    #[serde(default)]
+    pub tiers: HashMap<String, Quota>,
+    /// Tier of tenants or API key ids
+    #[serde(default)]
+    pub assignments: HashMap<String, String>,
+    /// Proxies whose `X-Forwarded-For` entries are trusted
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Source of tier assignments for keys missing from the config.
+///
+/// # Example
+///
+/// ```ignore
+/// struct ApiKeyTiers(DatabaseConnection);
+///
+/// #[async_trait]
+/// impl TierStore for ApiKeyTiers {
+///     async fn tier(&self, key: &str) -> Result<Option<String>> {
+///         let key = api_key::Entity::find_by_id(key).one(&self.0).await?;
+///         Ok(key.map(|key| key.tier))
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait TierStore: Send + Sync + 'static {
+    /// Tier of a tenant or API key id, `None` for the default quota.
+    ///
+    /// Only called with verified identities and client IPs.
+    async fn tier(&self, key: &str) -> Result<Option<String>>;
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    period: Duration,
+}
+
+/// Token buckets of every key.
+pub struct RateLimiter {
+    settings: Watch<RateLimitSettings>,
+    store: Option<Arc<dyn TierStore>>,
+    verifier: SharedVerifier,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    cached_tiers: Mutex<HashMap<String, (Option<String>, Instant)>>,
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("settings", &self.settings)
+            .field("store", &self.store.is_some())
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter with tiers assigned from the settings only.
+    pub fn new(settings: RateLimitSettings) -> Self {
+        Self::watch(Watch::named("rate_limit", settings))
+    }
+
+    /// Create a limiter following reloaded settings.
+    ///
+    /// Quotas and assignments apply to the next request of each key; tiers
+    /// resolved through the store stay cached for up to a minute.
+    pub fn watch(settings: Watch<RateLimitSettings>) -> Self {
+        Self {
+            settings,
+            store: None,
+            verifier: SharedVerifier::default(),
+            buckets: Mutex::new(HashMap::new()),
+            cached_tiers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve the tier of keys missing from the settings through a store.
+    ///
+    /// Store results are cached for a minute.
+    pub fn store(mut self, store: impl TierStore) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Verify the tenant claims with the app's verifier.
+    pub(crate) fn verifier(mut self, verifier: SharedVerifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    /// Tier of a key, `None` for the default quota.
+    pub async fn tier(&self, key: &str) -> Option<String> {
+        if let Some(tier) = self.settings.with(|s| s.assignments.get(key).cloned()) {
+            return Some(tier);
+        }
+        let store = self.store.as_ref()?;
+
+        if let Some((tier, resolved)) = self.cached_tiers.lock().unwrap().get(key)
+            && resolved.elapsed() < TIER_CACHE_TTL
+        {
+            return tier.clone();
+        }
+        let tier = store.tier(key).await.unwrap_or_else(|e| {
+            tracing::warn!("Rate limit tier lookup failed, using the default quota: {}", e);
+            None
+        });
+        let mut cached_tiers = self.cached_tiers.lock().unwrap();
+        if cached_tiers.len() >= SWEEP_THRESHOLD {
+            cached_tiers.retain(|_, (_, resolved)| resolved.elapsed() < TIER_CACHE_TTL);
+        }
+        cached_tiers.insert(key.to_string(), (tier.clone(), Instant::now()));
+        tier
+    }
+
+    /// Quota of a key.
+    pub async fn quota(&self, key: &str) -> Quota {
+        let tier = self.tier(key).await;
+        self.settings.with(|settings| match tier {
+            Some(tier) => match settings.tiers.get(&tier) {
+                Some(quota) => *quota,
+                None => {
+                    tracing::warn!(tier, "Unknown rate limit tier, using the default quota");
+                    settings.default
+                }
+            },
+            None => settings.default,
+        })
+    }
+
+    /// Take a token from the key's bucket.
+    ///
+    /// Returns the tokens left, or how long until the next one.
+    fn acquire(&self, key: &str, quota: Quota, now: Instant) -> std::result::Result<u32, Duration> {
+        let capacity = quota.requests as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= SWEEP_THRESHOLD {
+            // A bucket idle for a whole period is full again: dropping it is lossless
+            buckets.retain(|_, bucket| now.duration_since(bucket.updated) < bucket.period);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            period: quota.period(),
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * quota.rate()).min(capacity);
+        bucket.updated = now;
+        bucket.period = quota.period();
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(bucket.tokens as u32)
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / quota.rate()))
+        }
+    }
+}
+
+fn set_rate_limit_headers(response: &mut Response, quota: Quota, remaining: u32) {
+    let headers = response.headers_mut();
+    headers.insert(
+        HeaderName::from_static(RATE_LIMIT_LIMIT_HEADER),
+        HeaderValue::from(quota.requests),
+    );
+    headers.insert(
+        HeaderName::from_static(RATE_LIMIT_REMAINING_HEADER),
+        HeaderValue::from(remaining),
+    );
+}
+
+fn too_many_requests(quota: Quota, retry_after: Duration) -> Response {
+    let mut response =
+        ErrorResponse::new(error_codes::TOO_MANY_REQUESTS, "Rate limit exceeded, retry later")
+            .retry_after(retry_after)
+            .into_response_with(StatusCode::TOO_MANY_REQUESTS);
+    set_rate_limit_headers(&mut response, quota, 0);
+    response
+}
+
+/// Middleware counting requests against the quota of their key.
+///
+/// Installed by `EywaApp::rate_limit()`.
+pub async fn rate_limit_middleware(
+    State(limiter): State<Arc<RateLimiter>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let (by, trusted_proxies) = limiter
+        .settings
+        .with(|settings| (settings.key.clone(), settings.trusted_proxies.clone()));
+    let key = by
+        .identity(&mut req, &limiter.verifier)
+        .unwrap_or_else(|| client_ip(&req, &trusted_proxies));
+    let quota = limiter.quota(&key).await;
+
+    let now = clock::from_extensions(req.extensions()).instant();
+    match limiter.acquire(&key, quota, now) {
+        Ok(remaining) => {
+            let mut response = next.run(req).await;
+            set_rate_limit_headers(&mut response, quota, remaining);
+            response
+        }
+        Err(retry_after) => {
+            tracing::warn!(
+                retry_after_ms = retry_after.as_millis() as u64,
+                "rate limit exceeded, rejecting request"
+            );
+            too_many_requests(quota, retry_after)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{SharedClock, TestClock};
+    use crate::di::Container;
+    use crate::middleware::auth::{JwtConfig, JwtVerifier};
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    fn settings() -> RateLimitSettings {
+        serde_json::from_value(serde_json::json!({
+            "key": { "by": "tenant" },
+            "default": { "requests": 2 },
+            "tiers": { "enterprise": { "requests": 5, "period_secs": 1 } },
+            "assignments": { "acme": "enterprise" },
+        }))
+        .unwrap()
+    }
+
+    struct Tiers;
+
+    #[async_trait]
+    impl TierStore for Tiers {
+        async fn tier(&self, key: &str) -> Result<Option<String>> {
+            Ok((key == "globex").then(|| "enterprise".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quota_from_config_and_store() {
+        let limiter = RateLimiter::new(settings()).store(Tiers);
+
+        assert_eq!(limiter.settings.get().key, RateLimitKey::tenant());
+        assert_eq!(limiter.quota("acme").await, Quota::per_second(5));
+        assert_eq!(limiter.quota("globex").await, Quota::per_second(5));
+        assert_eq!(limiter.quota("initech").await, Quota::per_minute(2));
+    }
+
+    #[tokio::test]
+    async fn test_reloaded_assignments() {
+        let settings = Watch::named("rate_limit", settings());
+        let limiter = RateLimiter::watch(settings.clone());
+        assert_eq!(limiter.quota("initech").await, Quota::per_minute(2));
+
+        let mut reloaded = settings.get();
+        reloaded.assignments.insert("initech".to_string(), "enterprise".to_string());
+        settings.set(reloaded);
+        assert_eq!(limiter.quota("initech").await, Quota::per_second(5));
+    }
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = RateLimiter::new(RateLimitSettings::default());
+        let quota = Quota::per_second(2);
+        let now = Instant::now();
+
+        assert_eq!(limiter.acquire("acme", quota, now), Ok(1));
+        assert_eq!(limiter.acquire("acme", quota, now), Ok(0));
+        assert_eq!(
+            limiter.acquire("acme", quota, now),
+            Err(Duration::from_millis(500))
+        );
+        // Other keys have their own bucket
+        assert_eq!(limiter.acquire("globex", quota, now), Ok(1));
+
+        let later = now + Duration::from_millis(500);
+        assert_eq!(limiter.acquire("acme", quota, later), Ok(0));
+    }
+
+    fn tenant_client() -> TestClient {
+        let verifier = JwtVerifier::new(&JwtConfig::new("s3cret"));
+        let limiter = RateLimiter::new(settings()).verifier(SharedVerifier::from(verifier));
+        TestClient::new(Router::new().route("/", get(|| async { "ok" })).layer(
+            axum::middleware::from_fn_with_state(Arc::new(limiter), rate_limit_middleware),
+        ))
+    }
+
+    fn token(tenant: &str) -> String {
+        let exp = chrono::Utc::now().timestamp() + 300;
+        JwtConfig::new("s3cret")
+            .sign(&serde_json::json!({ "sub": "42", "tenant_id": tenant, "exp": exp }))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tenants_limited_separately() {
+        let client = tenant_client();
+        let initech = token("initech");
+
+        for remaining in ["1", "0"] {
+            let response = client.get("/").bearer(&initech).send().await;
+            response.assert_status(StatusCode::OK);
+            assert_eq!(response.header(RATE_LIMIT_REMAINING_HEADER), Some(remaining));
+        }
+        let response = client.get("/").bearer(&initech).send().await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        // One token every 30s
+        assert_eq!(response.header("retry-after"), Some("30"));
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["retry_after"], 30);
+
+        let response = client.get("/").bearer(&token("acme")).send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.header(RATE_LIMIT_LIMIT_HEADER), Some("5"));
+    }
+
+    #[tokio::test]
+    async fn test_tenant_headers_are_ignored() {
+        let client = tenant_client();
+
+        // Rotating the header doesn't reset the bucket of the client's IP
+        for tenant in ["a", "b"] {
+            let response = client.get("/").header(TENANT_HEADER, tenant).send().await;
+            response.assert_status(StatusCode::OK);
+        }
+        let response = client.get("/").header(TENANT_HEADER, "c").send().await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+
+        // Nor claims another tenant's quota
+        let response = client.get("/").header(TENANT_HEADER, "acme").send().await;
+        assert_eq!(response.header(RATE_LIMIT_LIMIT_HEADER), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn test_buckets_refill_by_provided_clock() {
+        let clock = TestClock::new();
+        let mut container = Container::new();
+        container.provide::<SharedClock>(Arc::new(clock.clone()));
+        let client = TestClient::new(
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(axum::middleware::from_fn_with_state(
+                    Arc::new(RateLimiter::new(settings())),
+                    rate_limit_middleware,
+                ))
+                .layer(axum::Extension(Arc::new(container))),
+        );
+        let send = || client.get("/").send();
+
+        send().await.assert_status(StatusCode::OK);
+        send().await.assert_status(StatusCode::OK);
+        send().await.assert_status(StatusCode::TOO_MANY_REQUESTS);
+        clock.advance(Duration::from_secs(30));
+        send().await.assert_status(StatusCode::OK);
+    }
+
+    #[test]
+    fn test_client_ip_through_trusted_proxies_only() {
+        let request = |peer: &str, forwarded: &str| {
+            let mut req = Request::builder()
+                .header("x-forwarded-for", forwarded)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            req.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+            req
+        };
+        let proxies = ["10.0.0.5".parse().unwrap(), "10.0.0.6".parse().unwrap()];
+
+        // Without trusted proxies the header is ignored
+        let spoofed = request("203.0.113.9:4000", "198.51.100.1");
+        assert_eq!(client_ip(&spoofed, &[]), "203.0.113.9");
+        assert_eq!(client_ip(&spoofed, &proxies), "203.0.113.9");
+
+        // Entries added by the client, left of the first untrusted one, are skipped
+        let proxied = request("10.0.0.5:4000", "198.51.100.1, 203.0.113.9, 10.0.0.6");
+        assert_eq!(client_ip(&proxied, &proxies), "203.0.113.9");
+        assert_eq!(client_ip(&proxied, &[]), "10.0.0.5");
+
+        let garbled = request("10.0.0.5:4000", "198.51.100.1, not-an-ip");
+        assert_eq!(client_ip(&garbled, &proxies), "10.0.0.5");
+
+        let no_peer = Request::builder().body(axum::body::Body::empty()).unwrap();
+        assert_eq!(client_ip(&no_peer, &proxies), "unknown");
+    }
+}
@@ -0,0 +1,133 @@
+//! Extractor rejections mapped to the EYWA error envelope.
+//!
+//! axum's built-in rejections (`JsonRejection`, `QueryRejection`,
+//! `PathRejection`, ...) answer with plain-text bodies. The rejection layer
+//! rewrites them as `AppError::BadRequest`, so clients get the same JSON
+//! envelope (with request ID) as for every other error, and documents the
+//! `400` response on every operation.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use utoipa::openapi::{OpenApi, RefOr, ResponseBuilder};
+
+use eywa_errors::AppError;
+
+/// Largest plain-text body considered a rejection message.
+const MAX_REJECTION_BYTES: usize = 16 * 1024;
+
+/// Statuses used by axum's extractor rejections.
+const REJECTION_STATUSES: [StatusCode; 3] = [
+    StatusCode::BAD_REQUEST,
+    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    StatusCode::UNPROCESSABLE_ENTITY,
+];
+
+/// Convert an extractor rejection into the EYWA error envelope.
+///
+/// Use in custom extractors: `.map_err(rejection_response)?`.
+pub fn rejection_response(rejection: impl std::fmt::Display) -> Response {
+    AppError::BadRequest(rejection.to_string()).into_response()
+}
+
+/// Returns `true` if the response looks like an axum extractor rejection.
+fn is_rejection(response: &Response) -> bool {
+    REJECTION_STATUSES.contains(&response.status())
+        && response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/plain"))
+}
+
+/// Axum middleware rewriting plain-text rejection responses as `AppError`s.
+///
+/// Installed by `EywaApp::map_rejections`.
+pub async fn rejection_middleware(req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    if !is_rejection(&response) {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_REJECTION_BYTES).await {
+        Ok(bytes) => rejection_response(String::from_utf8_lossy(&bytes)),
+        Err(_) => Response::from_parts(parts, Body::empty()),
+    }
+}
+
+/// Document the `400` rejection response on every operation that doesn't
+/// already document one.
+pub fn apply_to_openapi(openapi: &mut OpenApi) {
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.post,
+            &mut item.put,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            operation
+                .responses
+                .responses
+                .entry("400".to_string())
+                .or_insert_with(|| {
+                    RefOr::T(
+                        ResponseBuilder::new()
+                            .description("Malformed request body, query string or path parameters")
+                            .build(),
+                    )
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::post, Json, Router};
+    use serde_json::Value;
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route("/echo", post(|Json(body): Json<Value>| async move { Json(body) }))
+                .layer(axum::middleware::from_fn(rejection_middleware)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_json_rejection_is_mapped() {
+        let response = client()
+            .post("/echo")
+            .header("content-type", "application/json")
+            .body("{not json")
+            .send()
+            .await;
+
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let content_type = response.header("content-type").unwrap_or_default();
+        assert!(!content_type.starts_with("text/plain"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_content_type_is_mapped_to_400() {
+        let response = client().post("/echo").body("{}").send().await;
+
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_valid_requests_are_untouched() {
+        let response = client().post("/echo").json(&serde_json::json!({ "a": 1 })).send().await;
+
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.json::<Value>(), serde_json::json!({ "a": 1 }));
+    }
+}
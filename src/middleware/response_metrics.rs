@@ -0,0 +1,170 @@
+//! Response size and serialization time per route.
+//!
+//! Latency histograms don't tell whether a slow endpoint spends its time
+//! in the database or encoding a large payload. With
+//! `EywaApp::response_metrics()` every route records two more histograms:
+//!
+//! - `eywa_http_response_size_bytes{method, route}` - Body size before
+//!   compression, counted as streamed bodies are sent
+//! - `eywa_http_response_serialization_seconds{method, route}` - Time spent
+//!   serializing the body, reported by the crate's JSON responders
+//!   (`TimedJson`, `BigJson`, `JsonStream`, `JsonBytes::from_value`)
+//!
+//! `axum::Json` serializes inside the handler and can't be observed: switch
+//! the endpoints under investigation to `TimedJson`, a drop-in responder.
+//! Custom responders report their own time with `record_serialization`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::{Body, HttpBody},
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::StreamExt;
+
+tokio::task_local! {
+    static SERIALIZATION: SerializationTimer;
+}
+
+/// Serialization time of the current response, shared with the threads
+/// and body streams serializing it.
+#[derive(Debug, Clone, Default)]
+pub struct SerializationTimer(Arc<AtomicU64>);
+
+impl SerializationTimer {
+    /// The timer of the request being handled, if response metrics are on.
+    pub fn current() -> Option<Self> {
+        SERIALIZATION.try_with(Clone::clone).ok()
+    }
+
+    /// Add time spent serializing the response.
+    pub fn add(&self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.0.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn total(&self) -> Option<Duration> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+}
+
+/// Add time spent serializing the current response.
+///
+/// No-op outside of a request or without `EywaApp::response_metrics()`.
+pub fn record_serialization(duration: Duration) {
+    if let Some(timer) = SerializationTimer::current() {
+        timer.add(duration);
+    }
+}
+
+/// Records the histograms of one response when dropped.
+struct ResponseRecorder {
+    method: String,
+    route: String,
+    size: u64,
+    timer: SerializationTimer,
+}
+
+impl Drop for ResponseRecorder {
+    fn drop(&mut self) {
+        let (method, route) = (self.method.clone(), self.route.clone());
+        metrics::histogram!(
+            "eywa_http_response_size_bytes",
+            "method" => method.clone(),
+            "route" => route.clone()
+        )
+        .record(self.size as f64);
+        if let Some(serialization) = self.timer.total() {
+            metrics::histogram!(
+                "eywa_http_response_serialization_seconds",
+                "method" => method,
+                "route" => route
+            )
+            .record(serialization.as_secs_f64());
+        }
+    }
+}
+
+/// Route middleware recording response size and serialization time.
+///
+/// Installed by `EywaApp::response_metrics()`.
+pub async fn response_metrics_middleware(req: Request, next: Next) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>() else {
+        return next.run(req).await;
+    };
+    let mut recorder = ResponseRecorder {
+        method: req.method().to_string(),
+        route: route.as_str().to_string(),
+        size: 0,
+        timer: SerializationTimer::default(),
+    };
+
+    let response = SERIALIZATION.scope(recorder.timer.clone(), next.run(req)).await;
+
+    if let Some(size) = response.body().size_hint().exact() {
+        recorder.size = size;
+        return response;
+    }
+    // Streamed: count the bytes as they are sent, record once the body ends
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            recorder.size += chunk.len() as u64;
+        }
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+    use futures_util::stream;
+
+    #[tokio::test]
+    async fn test_serialization_time_scoped_to_request() {
+        record_serialization(Duration::from_millis(5));
+        assert!(SerializationTimer::current().is_none());
+
+        let timer = SerializationTimer::default();
+        SERIALIZATION
+            .scope(timer.clone(), async {
+                record_serialization(Duration::from_millis(2));
+                let handle = SerializationTimer::current().unwrap();
+                tokio::task::spawn_blocking(move || handle.add(Duration::from_millis(3)))
+                    .await
+                    .unwrap();
+            })
+            .await;
+        assert_eq!(timer.total(), Some(Duration::from_millis(5)));
+        assert_eq!(SerializationTimer::default().total(), None);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_bodies_pass_through() {
+        let client = TestClient::new(
+            Router::new()
+                .route("/sized", get(|| async { "projects" }))
+                .route(
+                    "/streamed",
+                    get(|| async {
+                        let chunks = ["[1,", "2]"].map(Ok::<_, std::io::Error>);
+                        Body::from_stream(stream::iter(chunks))
+                    }),
+                )
+                .route_layer(axum::middleware::from_fn(response_metrics_middleware)),
+        );
+
+        assert_eq!(client.get("/sized").send().await.text(), "projects");
+        assert_eq!(client.get("/streamed").send().await.text(), "[1,2]");
+    }
+}
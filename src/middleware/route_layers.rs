@@ -0,0 +1,98 @@
+//! Middleware declared on individual routes.
+//!
+//! Routes list their own middleware (via `#[route(GET "/{id}", middleware(admin_guard))]`,
+//! i.e. `IntoRouter::route_middleware`). When a controller is mounted, its
+//! routes are collected in a `RouteLayerRegistry` and a single `route_layer`
+//! runs the layers of the matched route, so other routes and unmatched
+//! requests never reach them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tower::ServiceExt;
+
+use crate::middleware::route_method;
+use crate::traits::{RouteMiddleware, RouteService};
+
+/// Route middleware keyed by HTTP method and route template.
+#[derive(Debug, Clone, Default)]
+pub struct RouteLayerRegistry {
+    routes: HashMap<(String, String), RouteMiddleware>,
+}
+
+impl RouteLayerRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the middleware of a route, replacing any registered before.
+    pub fn insert(&mut self, route: RouteMiddleware) {
+        if !route.is_empty() {
+            let key = (route_method(&route.method), route.path.clone());
+            self.routes.insert(key, route);
+        }
+    }
+
+    /// Returns the middleware declared for `method` on the route template `path`.
+    ///
+    /// `HEAD` runs the middleware of `GET`, like its handler.
+    pub fn get(&self, method: &str, path: &str) -> Option<&RouteMiddleware> {
+        self.routes.get(&(route_method(method), path.to_string()))
+    }
+
+    /// Returns `true` if no route declares middleware.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+/// Axum middleware running the layers registered for the matched route.
+///
+/// Installed by `EywaApp` on each controller declaring route middleware.
+pub async fn route_layers_middleware(
+    State(registry): State<Arc<RouteLayerRegistry>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|path| registry.get(req.method().as_str(), path.as_str()));
+
+    match route {
+        Some(route) => match route.wrap(RouteService::new(next)).oneshot(req).await {
+            Ok(response) => response,
+            Err(infallible) => match infallible {},
+        },
+        None => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::middleware::from_fn;
+
+    async fn pass(req: Request, next: Next) -> Response {
+        next.run(req).await
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let mut registry = RouteLayerRegistry::new();
+        registry.insert(RouteMiddleware::new("get", "/reports").layer(from_fn(pass)));
+        registry.insert(RouteMiddleware::new("POST", "/reports"));
+
+        assert!(registry.get("GET", "/reports").is_some());
+        assert!(registry.get("HEAD", "/reports").is_some());
+        // Routes without layers aren't registered
+        assert!(registry.get("POST", "/reports").is_none());
+        assert!(registry.get("GET", "/reports/{id}").is_none());
+    }
+}
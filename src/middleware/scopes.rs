@@ -0,0 +1,369 @@
+//! OAuth scope enforcement tied to the OpenAPI security requirements.
+//!
+//! Routes declare the scopes they need (via `#[route(scopes(...))]` or
+//! `EywaApp::require_scopes`). The same `ScopeRegistry` is used to:
+//! - reject requests whose token lacks a required scope
+//! - emit the scopes into each operation's `security` section
+//!
+//! so the documentation and the enforcement can't drift apart. The scopes
+//! are read from the token only once `JwtVerifier` has checked its signature
+//! and expiry.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use utoipa::openapi::security::SecurityRequirement;
+use utoipa::openapi::OpenApi;
+
+use eywa_errors::AppError;
+
+use crate::middleware::auth::JwtVerifier;
+use crate::middleware::route_method;
+use crate::traits::{AuthRequirement, RouteAuth, RouteScopes};
+
+/// Name of the security scheme registered by `EywaApp::serve`.
+pub const SECURITY_SCHEME: &str = "bearer";
+
+/// Required scopes keyed by HTTP method and route template.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeRegistry {
+    routes: HashMap<(String, String), Vec<String>>,
+}
+
+impl ScopeRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the scopes required by a route.
+    pub fn insert(&mut self, route: RouteScopes) {
+        let scopes = self
+            .routes
+            .entry((route_method(&route.method), route.path))
+            .or_default();
+        for scope in route.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+    }
+
+    /// Returns the scopes required by `method` on the route template `path`.
+    ///
+    /// `HEAD` requires the scopes of `GET`.
+    pub fn required(&self, method: &str, path: &str) -> Option<&[String]> {
+        self.routes
+            .get(&(route_method(method), path.to_string()))
+            .map(Vec::as_slice)
+    }
+
+    /// Returns `true` if no route requires any scope.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Write the registered scopes into the matching operations' security requirements.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let operations = [
+                ("GET", &mut item.get),
+                ("POST", &mut item.post),
+                ("PUT", &mut item.put),
+                ("DELETE", &mut item.delete),
+                ("PATCH", &mut item.patch),
+            ];
+            for (method, operation) in operations {
+                if let (Some(operation), Some(scopes)) =
+                    (operation.as_mut(), self.required(method, path))
+                {
+                    operation.security = Some(vec![SecurityRequirement::new(
+                        SECURITY_SCHEME,
+                        scopes.to_vec(),
+                    )]);
+                }
+            }
+        }
+    }
+}
+
+/// Document per-route authentication requirements on their operations.
+///
+/// Authenticated routes require the bearer scheme; public routes get an empty
+/// `security` list, overriding a controller-wide requirement. Scopes registered
+/// in a `ScopeRegistry` are applied afterwards and take precedence.
+pub fn apply_auth_requirements(openapi: &mut OpenApi, routes: &[RouteAuth]) {
+    for route in routes {
+        let Some(item) = openapi.paths.paths.get_mut(&route.path) else {
+            continue;
+        };
+        let operation = match route.method.to_uppercase().as_str() {
+            "GET" => &mut item.get,
+            "POST" => &mut item.post,
+            "PUT" => &mut item.put,
+            "DELETE" => &mut item.delete,
+            "PATCH" => &mut item.patch,
+            _ => continue,
+        };
+        if let Some(operation) = operation.as_mut() {
+            operation.security = Some(match route.requirement {
+                AuthRequirement::Required => {
+                    vec![SecurityRequirement::new(SECURITY_SCHEME, Vec::<String>::new())]
+                }
+                AuthRequirement::Public => Vec::new(),
+            });
+        }
+    }
+}
+
+/// Scopes granted to the current request's token.
+///
+/// Inserted as a request extension by `scope_enforcement_middleware`, so
+/// handlers can perform finer-grained checks via `Extension<GrantedScopes>`.
+/// The middleware itself never trusts this extension: it reads the scopes
+/// from the verified claims.
+#[derive(Debug, Clone, Default)]
+pub struct GrantedScopes(HashSet<String>);
+
+impl GrantedScopes {
+    /// Read scopes from token claims.
+    ///
+    /// Supports both the space-delimited `scope` claim (RFC 8693) and the
+    /// `scp` array used by some identity providers.
+    pub fn from_claims(claims: &serde_json::Value) -> Self {
+        let mut scopes = HashSet::new();
+        if let Some(scope) = claims.get("scope").and_then(|v| v.as_str()) {
+            scopes.extend(scope.split_whitespace().map(str::to_string));
+        }
+        if let Some(scp) = claims.get("scp").and_then(|v| v.as_array()) {
+            scopes.extend(scp.iter().filter_map(|v| v.as_str()).map(str::to_string));
+        }
+        Self(scopes)
+    }
+
+    /// Returns `true` if the scope was granted.
+    pub fn contains(&self, scope: &str) -> bool {
+        self.0.contains(scope)
+    }
+
+    /// Iterate over the granted scopes.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// Returns the required scopes that were not granted.
+    pub fn missing<'a>(&self, required: &'a [String]) -> Vec<&'a str> {
+        required
+            .iter()
+            .filter(|scope| !self.contains(scope))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// State of `scope_enforcement_middleware`.
+#[derive(Debug, Clone)]
+pub struct ScopeEnforcement {
+    registry: ScopeRegistry,
+    verifier: JwtVerifier,
+}
+
+impl ScopeEnforcement {
+    /// Enforce the scopes of `registry`, granted by the tokens `verifier` accepts.
+    pub fn new(registry: ScopeRegistry, verifier: JwtVerifier) -> Self {
+        Self { registry, verifier }
+    }
+}
+
+/// Axum middleware enforcing the scopes registered for the matched route.
+///
+/// Routes without registered scopes pass through untouched. Otherwise:
+/// - **401 Unauthorized**: no bearer token, or one whose signature or expiry
+///   doesn't verify
+/// - **403 Forbidden**: the token lacks at least one required scope
+///
+/// Installed automatically by `EywaApp::serve` when any route requires
+/// scopes, which requires `EywaApp::auth`.
+pub async fn scope_enforcement_middleware(
+    State(enforcement): State<Arc<ScopeEnforcement>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let required = req
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|path| enforcement.registry.required(req.method().as_str(), path.as_str()));
+
+    let Some(required) = required else {
+        return next.run(req).await;
+    };
+
+    let granted = match enforcement.verifier.authenticate(&mut req) {
+        Ok(claims) => claims.scopes(),
+        Err(e) => return e.into_response(),
+    };
+
+    let missing = granted.missing(required);
+    if !missing.is_empty() {
+        return AppError::Forbidden(format!("Missing required scopes: {}", missing.join(" ")))
+            .into_response();
+    }
+
+    req.extensions_mut().insert(granted);
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth::JwtConfig;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::post, Extension, Router};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use serde_json::json;
+
+    fn registry() -> ScopeRegistry {
+        let mut registry = ScopeRegistry::new();
+        registry.insert(RouteScopes {
+            method: "post".to_string(),
+            path: "/projects".to_string(),
+            scopes: vec!["projects:write".to_string()],
+        });
+        registry
+    }
+
+    #[test]
+    fn test_registry_lookup_is_method_case_insensitive() {
+        let registry = registry();
+        assert_eq!(
+            registry.required("POST", "/projects"),
+            Some(&["projects:write".to_string()][..])
+        );
+        assert!(registry.required("GET", "/projects").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_head_requires_the_scopes_of_get() {
+        let mut registry = ScopeRegistry::new();
+        registry.insert(RouteScopes {
+            method: "GET".to_string(),
+            path: "/reports".to_string(),
+            scopes: vec!["reports:read".to_string()],
+        });
+        let verifier = JwtVerifier::new(&JwtConfig::new("s3cret"));
+        let client = TestClient::new(
+            Router::new()
+                .route("/reports", axum::routing::get(|| async { "reports" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(ScopeEnforcement::new(registry, verifier)),
+                    scope_enforcement_middleware,
+                )),
+        );
+        let head = || client.request(axum::http::Method::HEAD, "/reports");
+
+        head().send().await.assert_status(StatusCode::UNAUTHORIZED);
+        let other = token("s3cret", "projects:write");
+        head().bearer(&other).send().await.assert_status(StatusCode::FORBIDDEN);
+        let reader = token("s3cret", "reports:read");
+        head().bearer(&reader).send().await.assert_status(StatusCode::OK);
+    }
+
+    #[test]
+    fn test_auth_requirements_in_openapi() {
+        use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+        let mut openapi = OpenApi::default();
+        for path in ["/projects", "/status"] {
+            let operation = OperationBuilder::new()
+                .security(SecurityRequirement::new(SECURITY_SCHEME, Vec::<String>::new()))
+                .build();
+            openapi
+                .paths
+                .paths
+                .insert(path.to_string(), PathItem::new(HttpMethod::Get, operation));
+        }
+
+        apply_auth_requirements(
+            &mut openapi,
+            &[RouteAuth {
+                method: "GET".to_string(),
+                path: "/status".to_string(),
+                requirement: AuthRequirement::Public,
+            }],
+        );
+
+        let security = |path: &str| {
+            openapi.paths.paths[path]
+                .get
+                .as_ref()
+                .and_then(|operation| operation.security.clone())
+                .map(|requirements| requirements.len())
+        };
+        assert_eq!(security("/projects"), Some(1));
+        assert_eq!(security("/status"), Some(0));
+    }
+
+    #[test]
+    fn test_granted_scopes_from_claims() {
+        let granted = GrantedScopes::from_claims(&json!({
+            "scope": "projects:read projects:write",
+            "scp": ["admin"]
+        }));
+        assert!(granted.contains("projects:read"));
+        assert!(granted.contains("projects:write"));
+        assert!(granted.contains("admin"));
+    }
+
+    #[test]
+    fn test_granted_scopes_missing() {
+        let granted = GrantedScopes::from_claims(&json!({ "scope": "projects:read" }));
+        let required = vec!["projects:read".to_string(), "projects:write".to_string()];
+        assert_eq!(granted.missing(&required), vec!["projects:write"]);
+    }
+
+    fn token(secret: &str, scope: &str) -> String {
+        let exp = chrono::Utc::now().timestamp() + 300;
+        JwtConfig::new(secret)
+            .sign(&json!({ "sub": "42", "scope": scope, "exp": exp }))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scopes_come_from_verified_tokens_only() {
+        let verifier = JwtVerifier::new(&JwtConfig::new("s3cret"));
+        let client = TestClient::new(
+            Router::new()
+                .route("/projects", post(|| async { "created" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(ScopeEnforcement::new(registry(), verifier)),
+                    scope_enforcement_middleware,
+                ))
+                // Scopes granted by an outer layer are ignored
+                .layer(Extension(GrantedScopes::from_claims(
+                    &json!({ "scope": "projects:write" }),
+                ))),
+        );
+        let create = |token: &str| client.post("/projects").bearer(token);
+
+        create(&token("s3cret", "projects:write")).send().await.assert_status(StatusCode::OK);
+        let read_only = token("s3cret", "projects:read");
+        create(&read_only).send().await.assert_status(StatusCode::FORBIDDEN);
+
+        // Signed with another secret
+        let forged = token("guessed", "projects:write");
+        create(&forged).send().await.assert_status(StatusCode::UNAUTHORIZED);
+
+        // Not signed at all
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none","typ":"JWT"}"#);
+        let claims = json!({ "scope": "projects:write", "exp": 4_102_444_800_i64 });
+        let unsigned = format!("{header}.{}.", URL_SAFE_NO_PAD.encode(claims.to_string()));
+        create(&unsigned).send().await.assert_status(StatusCode::UNAUTHORIZED);
+
+        client.post("/projects").send().await.assert_status(StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,269 @@
+//! Per-route SLOs: good/bad event counters for burn-rate alerts.
+//!
+//! A route declares its objectives with `#[route(slo_latency_ms = 300)]`
+//! (and optionally `slo_latency_objective = 99.0`, `slo_availability = 99.9`)
+//! or `EywaApp::route_slo`. Every request to the route then counts one event
+//! per objective:
+//!
+//! - `eywa_slo_events_total{method, route, sli, outcome}` - `sli` is
+//!   `latency` or `availability`, `outcome` is `good` or `bad`
+//! - `eywa_slo_objective{method, route, sli}` - The objective as a ratio
+//!   (`0.999`), so a single alert rule can be templated across services:
+//!
+//! ```promql
+//! (
+//!   sum by (route, sli) (rate(eywa_slo_events_total{outcome="bad"}[1h]))
+//!   / sum by (route, sli) (rate(eywa_slo_events_total[1h]))
+//! ) / on (route, sli) (1 - max by (route, sli) (eywa_slo_objective)) > 14.4
+//! ```
+//!
+//! A request is bad for availability when it ends with a 5xx status, and bad
+//! for latency when it takes longer than the threshold. Failed requests don't
+//! count toward the latency SLO, since a fast error isn't a good experience.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::traits::RouteSlo;
+
+/// Latency objective used when a route only declares a threshold, in percent.
+pub const DEFAULT_LATENCY_OBJECTIVE: f64 = 99.0;
+
+/// Objectives of a single route.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slo {
+    /// Requests slower than this are bad latency events
+    pub latency: Option<Duration>,
+    /// Percentage of requests expected under the latency threshold
+    pub latency_objective: f64,
+    /// Percentage of requests expected not to fail with a 5xx
+    pub availability: Option<f64>,
+}
+
+impl Slo {
+    /// An objective of [`DEFAULT_LATENCY_OBJECTIVE`] percent of requests under `threshold`.
+    pub fn latency(threshold: Duration) -> Self {
+        Self {
+            latency: Some(threshold),
+            latency_objective: DEFAULT_LATENCY_OBJECTIVE,
+            availability: None,
+        }
+    }
+
+    /// An objective of `objective` percent of requests not failing.
+    pub fn availability(objective: f64) -> Self {
+        Self {
+            latency: None,
+            latency_objective: DEFAULT_LATENCY_OBJECTIVE,
+            availability: Some(objective),
+        }
+    }
+
+    /// Set the percentage of requests expected under the latency threshold.
+    pub fn latency_objective(mut self, objective: f64) -> Self {
+        self.latency_objective = objective;
+        self
+    }
+
+    /// Also expect `objective` percent of requests not to fail.
+    pub fn with_availability(mut self, objective: f64) -> Self {
+        self.availability = Some(objective);
+        self
+    }
+
+    /// The latency and availability outcomes of a request, `true` when good.
+    ///
+    /// `None` when the request doesn't count toward that objective.
+    pub fn classify(&self, status: StatusCode, elapsed: Duration) -> (Option<bool>, Option<bool>) {
+        let failed = status.is_server_error();
+        let latency = self
+            .latency
+            .filter(|_| !failed)
+            .map(|threshold| elapsed <= threshold);
+        let availability = self.availability.map(|_| !failed);
+        (latency, availability)
+    }
+}
+
+/// Registry of the routes' SLOs, keyed by method and route template.
+#[derive(Clone, Debug, Default)]
+pub struct Slos {
+    routes: BTreeMap<(String, String), Slo>,
+}
+
+impl Slos {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare the objectives of a route.
+    pub fn insert(&mut self, method: &str, path: impl Into<String>, slo: Slo) {
+        self.routes.insert((method.to_uppercase(), path.into()), slo);
+    }
+
+    /// Declare the objectives collected from a `#[route(slo_...)]` attribute.
+    pub fn insert_route(&mut self, route: RouteSlo) {
+        let slo = Slo {
+            latency: route.latency_ms.map(Duration::from_millis),
+            latency_objective: route.latency_objective.unwrap_or(DEFAULT_LATENCY_OBJECTIVE),
+            availability: route.availability,
+        };
+        if slo.latency.is_some() || slo.availability.is_some() {
+            self.insert(&route.method, route.path, slo);
+        }
+    }
+
+    /// Returns `true` if no route declares an SLO.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// The objectives of a route.
+    pub fn get(&self, method: &str, path: &str) -> Option<&Slo> {
+        self.routes.get(&(method.to_string(), path.to_string()))
+    }
+}
+
+fn outcome(good: bool) -> &'static str {
+    if good { "good" } else { "bad" }
+}
+
+fn record(method: &str, route: &str, sli: &'static str, objective: f64, good: bool) {
+    metrics::gauge!(
+        "eywa_slo_objective",
+        "method" => method.to_string(),
+        "route" => route.to_string(),
+        "sli" => sli
+    )
+    .set(objective / 100.0);
+    metrics::counter!(
+        "eywa_slo_events_total",
+        "method" => method.to_string(),
+        "route" => route.to_string(),
+        "sli" => sli,
+        "outcome" => outcome(good)
+    )
+    .increment(1);
+}
+
+/// Middleware counting good and bad SLO events of the routes declaring objectives.
+///
+/// Installed by `EywaApp` when a route declares an SLO.
+pub async fn slo_middleware(
+    State(slos): State<Arc<Slos>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>().cloned() else {
+        return next.run(req).await;
+    };
+    let method = req.method().to_string();
+    let Some(slo) = slos.get(&method, route.as_str()).copied() else {
+        return next.run(req).await;
+    };
+
+    let started = Instant::now();
+    let response = next.run(req).await;
+    let (latency, availability) = slo.classify(response.status(), started.elapsed());
+
+    if let Some(good) = latency {
+        record(&method, route.as_str(), "latency", slo.latency_objective, good);
+    }
+    if let (Some(good), Some(objective)) = (availability, slo.availability) {
+        record(&method, route.as_str(), "availability", objective, good);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    #[test]
+    fn test_latency_outcomes() {
+        let slo = Slo::latency(Duration::from_millis(300));
+        assert_eq!(
+            slo.classify(StatusCode::OK, Duration::from_millis(120)),
+            (Some(true), None)
+        );
+        assert_eq!(
+            slo.classify(StatusCode::NOT_FOUND, Duration::from_millis(450)),
+            (Some(false), None)
+        );
+        // Failures only count toward availability
+        assert_eq!(
+            slo.classify(StatusCode::BAD_GATEWAY, Duration::from_millis(10)),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_availability_outcomes() {
+        let slo = Slo::latency(Duration::from_millis(300)).with_availability(99.9);
+        assert_eq!(
+            slo.classify(StatusCode::CONFLICT, Duration::from_millis(10)),
+            (Some(true), Some(true))
+        );
+        assert_eq!(
+            slo.classify(StatusCode::SERVICE_UNAVAILABLE, Duration::from_millis(10)),
+            (None, Some(false))
+        );
+    }
+
+    #[test]
+    fn test_routes_are_keyed_by_uppercase_method() {
+        let mut slos = Slos::new();
+        slos.insert("get", "/projects/{id}", Slo::availability(99.5));
+        assert_eq!(
+            slos.get("GET", "/projects/{id}"),
+            Some(&Slo::availability(99.5))
+        );
+        assert_eq!(slos.get("DELETE", "/projects/{id}"), None);
+    }
+
+    #[test]
+    fn test_route_attribute_defaults_latency_objective() {
+        let mut slos = Slos::new();
+        slos.insert_route(RouteSlo {
+            method: "GET".to_string(),
+            path: "/projects".to_string(),
+            latency_ms: Some(300),
+            latency_objective: None,
+            availability: None,
+        });
+        assert_eq!(
+            slos.get("GET", "/projects"),
+            Some(&Slo::latency(Duration::from_millis(300)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_middleware_passes_responses_through() {
+        let mut slos = Slos::new();
+        slos.insert("GET", "/projects/{id}", Slo::latency(Duration::from_millis(300)));
+        let client = TestClient::new(
+            Router::new()
+                .route("/projects/{id}", get(|| async { "project" }))
+                .route("/health", get(|| async { "ok" }))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    Arc::new(slos),
+                    slo_middleware,
+                )),
+        );
+
+        let response = client.get("/projects/1").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "project");
+        client.get("/health").send().await.assert_status(StatusCode::OK);
+    }
+}
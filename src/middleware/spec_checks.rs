@@ -0,0 +1,79 @@
+//! Request checks derived from the assembled spec.
+//!
+//! The checks read the whole spec, which is only final when the app is
+//! built, but they must run inside the authentication and scope layers: an
+//! unauthenticated request gets its `401` before its body is read, and never
+//! learns the documented constraints. `EywaApp` wraps each controller and
+//! built-in endpoint with `spec_checks_middleware` as it registers them,
+//! innermost, and configures the checks in `build()`.
+
+use std::sync::{Arc, OnceLock};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+    Router,
+};
+
+use crate::middleware::constraints::SchemaConstraints;
+use crate::middleware::content_types::ContentTypes;
+
+/// The checks enabled on the app.
+#[derive(Debug, Default)]
+pub(crate) struct Checks {
+    pub(crate) content_types: Option<ContentTypes>,
+    pub(crate) constraints: Option<SchemaConstraints>,
+}
+
+/// The app's spec checks, shared with the routes registered before they are
+/// configured.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpecChecks(Arc<OnceLock<Checks>>);
+
+impl SpecChecks {
+    /// Configure the checks; `false` if they already were.
+    pub(crate) fn set(&self, checks: Checks) -> bool {
+        self.0.set(checks).is_ok()
+    }
+
+    /// Run the checks on the requests to the routes of `router`.
+    pub(crate) fn wrap<S>(&self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        if !router.has_routes() {
+            return router;
+        }
+        router.route_layer(axum::middleware::from_fn_with_state(
+            self.clone(),
+            spec_checks_middleware,
+        ))
+    }
+}
+
+/// Middleware running the configured checks; requests pass through until
+/// `build()` configures them.
+async fn spec_checks_middleware(
+    State(checks): State<SpecChecks>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(checks) = checks.0.get() else {
+        return next.run(req).await;
+    };
+    if let Some(content_types) = &checks.content_types
+        && let Some(path) = req.extensions().get::<MatchedPath>()
+        && let Some(rejection) = content_types.check(req.method().as_str(), path.as_str(), &req)
+    {
+        return rejection;
+    }
+    let req = match &checks.constraints {
+        Some(constraints) => match constraints.check_request(req).await {
+            Ok(req) => req,
+            Err(rejection) => return rejection,
+        },
+        None => req,
+    };
+    next.run(req).await
+}
@@ -0,0 +1,238 @@
+//! Server-Timing response header.
+//!
+//! Reports where backend time goes to browser devtools and frontend
+//! performance tooling:
+//!
+//! ```text
+//! Server-Timing: db;dur=8.1;desc="Load projects", handler;dur=10.4, middleware;dur=1.2, total;dur=11.6
+//! ```
+//!
+//! `total` covers the whole middleware stack, `handler` the matched route
+//! (including its route middleware), and `middleware` the difference.
+//! Handlers add their own phases through the `ServerTimings` extractor.
+
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{request::Parts, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Name of the Server-Timing header.
+pub const SERVER_TIMING_HEADER: &str = "server-timing";
+
+#[derive(Debug, Clone)]
+struct Timing {
+    name: String,
+    duration: Duration,
+    description: Option<String>,
+}
+
+/// Phase durations of the current request.
+///
+/// Without the `server_timing` layer, recorded phases are discarded.
+///
+/// # Example
+///
+/// ```ignore
+/// async fn list_projects(timings: ServerTimings, State(db): State<Database>) -> Result<Json<Vec<Project>>> {
+///     let _db = timings.start("db");
+///     let projects = Project::find().all(&db).await?;
+///     Ok(Json(projects))
+/// }
+///
+/// // Or with an explicit duration
+/// timings.record("cache", started.elapsed());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ServerTimings(Arc<Mutex<Vec<Timing>>>);
+
+impl ServerTimings {
+    /// Record a phase.
+    pub fn record(&self, name: impl Into<String>, duration: Duration) {
+        self.push(name.into(), duration, None);
+    }
+
+    /// Record a phase with a description shown by devtools.
+    pub fn record_with_description(
+        &self,
+        name: impl Into<String>,
+        duration: Duration,
+        description: impl Into<String>,
+    ) {
+        self.push(name.into(), duration, Some(description.into()));
+    }
+
+    /// Start timing a phase, recorded when the returned guard is dropped.
+    pub fn start(&self, name: impl Into<String>) -> TimingGuard {
+        TimingGuard {
+            timings: self.clone(),
+            name: Some(name.into()),
+            started: Instant::now(),
+        }
+    }
+
+    fn push(&self, name: String, duration: Duration, description: Option<String>) {
+        self.0.lock().unwrap().push(Timing {
+            name,
+            duration,
+            description,
+        });
+    }
+
+    fn duration_of(&self, name: &str) -> Option<Duration> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|timing| timing.name == name)
+            .map(|timing| timing.duration)
+    }
+
+    /// The header value listing every recorded phase.
+    fn header_value(&self) -> Option<HeaderValue> {
+        let timings = self.0.lock().unwrap();
+        let value = timings
+            .iter()
+            .map(|timing| {
+                let mut metric = format!(
+                    "{};dur={:.1}",
+                    token(&timing.name),
+                    timing.duration.as_secs_f64() * 1000.0
+                );
+                if let Some(description) = &timing.description {
+                    let escaped = description.replace('\\', "\\\\").replace('"', "\\\"");
+                    metric.push_str(&format!(";desc=\"{escaped}\""));
+                }
+                metric
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+/// Metric names must be HTTP tokens.
+fn token(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl<S> FromRequestParts<S> for ServerTimings
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Self>().cloned().unwrap_or_default())
+    }
+}
+
+/// Records a phase when dropped.
+#[derive(Debug)]
+pub struct TimingGuard {
+    timings: ServerTimings,
+    name: Option<String>,
+    started: Instant,
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            self.timings.record(name, self.started.elapsed());
+        }
+    }
+}
+
+/// Middleware adding the `Server-Timing` header.
+///
+/// Installed by `EywaApp::server_timing()`, around the whole middleware stack.
+pub async fn server_timing_middleware(mut req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let timings = ServerTimings::default();
+    req.extensions_mut().insert(timings.clone());
+
+    let mut response = next.run(req).await;
+
+    let total = started.elapsed();
+    if let Some(handler) = timings.duration_of("handler") {
+        timings.record("middleware", total.saturating_sub(handler));
+    }
+    timings.record("total", total);
+    if let Some(value) = timings.header_value() {
+        response
+            .headers_mut()
+            .append(HeaderName::from_static(SERVER_TIMING_HEADER), value);
+    }
+    response
+}
+
+/// Route middleware timing the matched route.
+///
+/// Installed by `EywaApp::server_timing()`.
+pub async fn handler_timing_middleware(req: Request, next: Next) -> Response {
+    let Some(timings) = req.extensions().get::<ServerTimings>().cloned() else {
+        return next.run(req).await;
+    };
+    let _handler = timings.start("handler");
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{routing::get, Router};
+
+    #[test]
+    fn test_header_value() {
+        let timings = ServerTimings::default();
+        timings.record_with_description(
+            "db query",
+            Duration::from_micros(8200),
+            "Load \"projects\"",
+        );
+        timings.record("total", Duration::from_millis(12));
+
+        assert_eq!(
+            timings.header_value().unwrap(),
+            r#"db_query;dur=8.2;desc="Load \"projects\"", total;dur=12.0"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phases_reported() {
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/projects",
+                    get(|timings: ServerTimings| async move {
+                        timings.record("db", Duration::from_millis(5));
+                        "projects"
+                    }),
+                )
+                .route_layer(axum::middleware::from_fn(handler_timing_middleware))
+                .layer(axum::middleware::from_fn(server_timing_middleware)),
+        );
+
+        let response = client.get("/projects").send().await;
+        let header = response.header(SERVER_TIMING_HEADER).unwrap();
+        let names: Vec<_> = header
+            .split(", ")
+            .map(|metric| metric.split(';').next().unwrap())
+            .collect();
+        assert_eq!(names, ["db", "handler", "middleware", "total"]);
+    }
+}
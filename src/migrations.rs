@@ -0,0 +1,145 @@
+//! `sea-orm-migration` definitions of the framework's tables.
+//!
+//! Services using the inbox, the dead letters, the API keys or the webhook
+//! subscriptions add these migrations to their own migrator instead of
+//! hand-writing the tables (or calling `create_table()` at startup):
+//!
+//! ```ignore
+//! pub struct Migrator;
+//!
+//! impl MigratorTrait for Migrator {
+//!     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+//!         let mut migrations = eywa_axum::migrations::all();
+//!         migrations.push(Box::new(m20260101_000001_create_projects::Migration));
+//!         migrations
+//!     }
+//! }
+//! ```
+//!
+//! The tables are created from the subsystems' entities with their indexes,
+//! so they always match what the stores expect. Migration names are stable;
+//! new framework tables are appended as new migrations.
+//!
+//! Requires the `migrations` feature. The API key migration is only included
+//! with the `api-keys` feature.
+//!
+//! Only subsystems storing rows in the service's database have a migration.
+//! The framework has no outbox store, the scheduler keeps its job leases in
+//! a `LeaseStore` (Kubernetes Leases or memory), and audit columns are added
+//! to the service's own tables, so none of them ships a table here.
+
+use sea_orm::{EntityTrait, Schema};
+use sea_orm_migration::prelude::*;
+
+/// A migration creating the table of an entity, with its indexes.
+struct CreateTable<E> {
+    name: &'static str,
+    entity: E,
+}
+
+impl<E: EntityTrait> MigrationName for CreateTable<E> {
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+#[async_trait::async_trait]
+impl<E: EntityTrait> MigrationTrait for CreateTable<E> {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let schema = Schema::new(manager.get_database_backend());
+
+        let mut table = schema.create_table_from_entity(self.entity);
+        manager.create_table(table.if_not_exists().to_owned()).await?;
+        for mut index in schema.create_index_from_entity(self.entity) {
+            manager.create_index(index.if_not_exists().to_owned()).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(self.entity).if_exists().to_owned())
+            .await
+    }
+}
+
+/// Creates `eywa_inbox` (see `inbox::DatabaseInbox`).
+pub fn inbox() -> Box<dyn MigrationTrait> {
+    Box::new(CreateTable {
+        name: "m20261001_000001_create_eywa_inbox",
+        entity: crate::inbox::entity::Entity,
+    })
+}
+
+/// Creates `eywa_dead_letters` (see `dead_letters::DeadLetterStore`).
+pub fn dead_letters() -> Box<dyn MigrationTrait> {
+    Box::new(CreateTable {
+        name: "m20261001_000002_create_eywa_dead_letters",
+        entity: crate::dead_letters::entity::Entity,
+    })
+}
+
+/// Creates `api_keys` (see `api_keys::ApiKeyStore`).
+#[cfg(feature = "api-keys")]
+pub fn api_keys() -> Box<dyn MigrationTrait> {
+    Box::new(CreateTable {
+        name: "m20261001_000003_create_api_keys",
+        entity: crate::api_keys::entity::Entity,
+    })
+}
+
+/// Creates `eywa_webhook_subscriptions` (see `webhooks::WebhookSubscriptions`).
+pub fn webhook_subscriptions() -> Box<dyn MigrationTrait> {
+    Box::new(CreateTable {
+        name: "m20261015_000001_create_eywa_webhook_subscriptions",
+        entity: crate::webhooks::entity::Entity,
+    })
+}
+
+/// Every framework migration, in order.
+pub fn all() -> Vec<Box<dyn MigrationTrait>> {
+    let mut migrations = vec![inbox(), dead_letters()];
+    #[cfg(feature = "api-keys")]
+    migrations.push(api_keys());
+    migrations.push(webhook_subscriptions());
+    migrations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_are_unique_and_ordered() {
+        let names: Vec<String> = all().iter().map(|m| m.name().to_string()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_migrations_up_and_down() {
+        use crate::inbox::{Claim, DatabaseInbox, InboxStore};
+
+        struct Migrator;
+
+        impl MigratorTrait for Migrator {
+            fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+                all()
+            }
+        }
+
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let store = DatabaseInbox::new(db.clone());
+        let ttl = std::time::Duration::from_secs(60);
+        assert_eq!(store.claim("billing", "m-1", ttl).await.unwrap(), Claim::Claimed);
+
+        Migrator::down(&db, None).await.unwrap();
+        let manager = SchemaManager::new(&db);
+        assert!(!manager.has_table("eywa_inbox").await.unwrap());
+    }
+}
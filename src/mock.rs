@@ -0,0 +1,282 @@
+//! Mock server generated from the OpenAPI spec.
+//!
+//! Serves a response for every documented operation without calling the real
+//! handlers, so frontend teams can develop against a service whose backend
+//! isn't finished. The response body is, in order of preference:
+//! 1. The documented example of the first 2xx response
+//! 2. A value derived from the response schema
+//!
+//! Enabled with `EywaApp::mock_mode(true)`.
+
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{MethodFilter, MethodRouter},
+    Json, Router,
+};
+use serde_json::{json, Map, Value};
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::schema::{ArrayItems, KnownFormat, Schema, SchemaFormat, SchemaType, Type};
+use utoipa::openapi::{Components, OpenApi, RefOr};
+
+/// Header added to every mocked response.
+pub const MOCK_HEADER: &str = "x-eywa-mock";
+
+/// Maximum schema nesting followed when deriving examples (guards recursive schemas).
+const MAX_DEPTH: usize = 8;
+
+/// Build a router answering every documented operation with its mock response.
+pub fn router<S>(openapi: &OpenApi) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let components = openapi.components.as_ref();
+    let mut router = Router::new();
+
+    for (path, item) in &openapi.paths.paths {
+        let operations = [
+            (MethodFilter::GET, &item.get),
+            (MethodFilter::POST, &item.post),
+            (MethodFilter::PUT, &item.put),
+            (MethodFilter::DELETE, &item.delete),
+            (MethodFilter::PATCH, &item.patch),
+        ];
+
+        let mut method_router: Option<MethodRouter<S>> = None;
+        for (filter, operation) in operations {
+            let Some(operation) = operation else {
+                continue;
+            };
+            let (status, body) = mock_response(operation, components);
+            let handler = move || {
+                let body = body.clone();
+                async move { mocked(status, body) }
+            };
+            method_router = Some(match method_router {
+                Some(existing) => existing.on(filter, handler),
+                None => axum::routing::on(filter, handler),
+            });
+        }
+
+        if let Some(method_router) = method_router {
+            router = router.route(path, method_router);
+        }
+    }
+
+    router
+}
+
+fn mocked(status: StatusCode, body: Option<Value>) -> Response {
+    let mut response = match body {
+        Some(body) => (status, Json(body)).into_response(),
+        None => status.into_response(),
+    };
+    response
+        .headers_mut()
+        .insert(MOCK_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Pick the status and body of the first documented 2xx response.
+fn mock_response(
+    operation: &Operation,
+    components: Option<&Components>,
+) -> (StatusCode, Option<Value>) {
+    let success = operation
+        .responses
+        .responses
+        .iter()
+        .find(|(code, _)| code.starts_with('2'));
+
+    let Some((code, response)) = success else {
+        return (StatusCode::OK, None);
+    };
+    let status = code
+        .parse()
+        .ok()
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .unwrap_or(StatusCode::OK);
+
+    let RefOr::T(response) = response else {
+        return (status, None);
+    };
+    let Some(content) = response
+        .content
+        .get("application/json")
+        .or_else(|| response.content.values().next())
+    else {
+        return (status, None);
+    };
+
+    let documented_example = content.example.clone().or_else(|| {
+        content.examples.values().find_map(|example| match example {
+            RefOr::T(example) => example.value.clone(),
+            RefOr::Ref(_) => None,
+        })
+    });
+
+    let body = documented_example.or_else(|| {
+        content
+            .schema
+            .as_ref()
+            .map(|schema| example_for(schema, components, 0))
+    });
+    (status, body)
+}
+
+/// Derive an example value from a schema.
+pub fn example_for(
+    schema: &RefOr<Schema>,
+    components: Option<&Components>,
+    depth: usize,
+) -> Value {
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+
+    let schema = match schema {
+        RefOr::T(schema) => schema,
+        RefOr::Ref(reference) => {
+            let name = reference
+                .ref_location
+                .trim_start_matches("#/components/schemas/");
+            return components
+                .and_then(|components| components.schemas.get(name))
+                .map(|schema| example_for(schema, components, depth + 1))
+                .unwrap_or(Value::Null);
+        }
+    };
+
+    match schema {
+        Schema::Object(object) => {
+            if let Some(example) = object.examples.first().or(object.default.as_ref()) {
+                return example.clone();
+            }
+            if let Some(value) = object.enum_values.as_ref().and_then(|values| values.first()) {
+                return value.clone();
+            }
+            if !object.properties.is_empty() {
+                let properties: Map<String, Value> = object
+                    .properties
+                    .iter()
+                    .map(|(name, property)| {
+                        (name.clone(), example_for(property, components, depth + 1))
+                    })
+                    .collect();
+                return Value::Object(properties);
+            }
+            primitive_example(&object.schema_type, object.format.as_ref())
+        }
+        Schema::Array(array) => {
+            if let Some(example) = array.examples.first() {
+                return example.clone();
+            }
+            match &array.items {
+                ArrayItems::RefOrSchema(items) => {
+                    json!([example_for(items, components, depth + 1)])
+                }
+                _ => json!([]),
+            }
+        }
+        Schema::OneOf(one_of) => one_of
+            .items
+            .first()
+            .map(|item| example_for(item, components, depth + 1))
+            .unwrap_or(Value::Null),
+        Schema::AnyOf(any_of) => any_of
+            .items
+            .first()
+            .map(|item| example_for(item, components, depth + 1))
+            .unwrap_or(Value::Null),
+        Schema::AllOf(all_of) => {
+            let mut merged = Map::new();
+            for item in &all_of.items {
+                match example_for(item, components, depth + 1) {
+                    Value::Object(properties) => merged.extend(properties),
+                    other if all_of.items.len() == 1 => return other,
+                    _ => {}
+                }
+            }
+            Value::Object(merged)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Example for a primitive type, honoring common string formats.
+fn primitive_example(schema_type: &SchemaType, format: Option<&SchemaFormat>) -> Value {
+    let primitive = match schema_type {
+        SchemaType::Type(primitive) => Some(primitive),
+        SchemaType::Array(types) => types.iter().find(|t| **t != Type::Null),
+        _ => None,
+    };
+
+    match primitive {
+        Some(Type::String) => match format {
+            Some(SchemaFormat::KnownFormat(KnownFormat::Uuid)) => {
+                json!("00000000-0000-0000-0000-000000000000")
+            }
+            Some(SchemaFormat::KnownFormat(KnownFormat::DateTime)) => {
+                json!("2024-01-01T00:00:00Z")
+            }
+            Some(SchemaFormat::KnownFormat(KnownFormat::Date)) => json!("2024-01-01"),
+            _ => json!("string"),
+        },
+        Some(Type::Integer) => json!(0),
+        Some(Type::Number) => json!(0.0),
+        Some(Type::Boolean) => json!(true),
+        Some(Type::Array) => json!([]),
+        Some(Type::Object) => json!({}),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::{PartialSchema, ToSchema};
+
+    #[derive(ToSchema)]
+    #[allow(dead_code)]
+    struct Project {
+        id: uuid::Uuid,
+        name: String,
+        archived: bool,
+        tags: Vec<String>,
+        owner: Owner,
+    }
+
+    #[derive(ToSchema)]
+    #[allow(dead_code)]
+    struct Owner {
+        #[schema(examples("Ada"))]
+        name: String,
+        age: i32,
+    }
+
+    #[test]
+    fn test_example_from_schema() {
+        let mut components = Components::new();
+        components
+            .schemas
+            .insert("Owner".to_string(), Owner::schema());
+
+        let example = example_for(&Project::schema(), Some(&components), 0);
+        assert_eq!(
+            example,
+            json!({
+                "id": "00000000-0000-0000-0000-000000000000",
+                "name": "string",
+                "archived": true,
+                "tags": ["string"],
+                "owner": { "name": "Ada", "age": 0 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_unresolved_reference_is_null() {
+        let reference = RefOr::Ref(utoipa::openapi::Ref::from_schema_name("Missing"));
+        assert_eq!(example_for(&reference, None, 0), Value::Null);
+    }
+}
@@ -0,0 +1,378 @@
+//! Monetary amounts with their currency.
+//!
+//! `Money` pairs a `rust_decimal` amount with an ISO 4217 currency, so
+//! services exchange amounts in one shape:
+//!
+//! ```json
+//! { "amount": "12.50", "currency": "EUR" }
+//! ```
+//!
+//! Amounts are serialized as strings to survive JSON parsers using floats.
+//! Unknown currencies are rejected on deserialization, and `Validate` rejects
+//! amounts more precise than the currency's minor unit (`12.505 EUR`).
+//!
+//! Arithmetic is checked: mixing currencies or overflowing returns a
+//! `MoneyError` (a `400 Bad Request` when returned from a handler) instead of
+//! silently producing a wrong amount.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use eywa_errors::AppError;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{PartialSchema, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+/// Active ISO 4217 currencies and their minor units.
+#[rustfmt::skip]
+const CURRENCIES: &[(&str, u32)] = &[
+    ("AED", 2), ("AFN", 2), ("ALL", 2), ("AMD", 2), ("ANG", 2), ("AOA", 2), ("ARS", 2),
+    ("AUD", 2), ("AWG", 2), ("AZN", 2), ("BAM", 2), ("BBD", 2), ("BDT", 2), ("BGN", 2),
+    ("BHD", 3), ("BIF", 0), ("BMD", 2), ("BND", 2), ("BOB", 2), ("BRL", 2), ("BSD", 2),
+    ("BTN", 2), ("BWP", 2), ("BYN", 2), ("BZD", 2), ("CAD", 2), ("CDF", 2), ("CHF", 2),
+    ("CLP", 0), ("CNY", 2), ("COP", 2), ("CRC", 2), ("CUP", 2), ("CVE", 2), ("CZK", 2),
+    ("DJF", 0), ("DKK", 2), ("DOP", 2), ("DZD", 2), ("EGP", 2), ("ERN", 2), ("ETB", 2),
+    ("EUR", 2), ("FJD", 2), ("FKP", 2), ("GBP", 2), ("GEL", 2), ("GHS", 2), ("GIP", 2),
+    ("GMD", 2), ("GNF", 0), ("GTQ", 2), ("GYD", 2), ("HKD", 2), ("HNL", 2), ("HTG", 2),
+    ("HUF", 2), ("IDR", 2), ("ILS", 2), ("INR", 2), ("IQD", 3), ("IRR", 2), ("ISK", 0),
+    ("JMD", 2), ("JOD", 3), ("JPY", 0), ("KES", 2), ("KGS", 2), ("KHR", 2), ("KMF", 0),
+    ("KPW", 2), ("KRW", 0), ("KWD", 3), ("KYD", 2), ("KZT", 2), ("LAK", 2), ("LBP", 2),
+    ("LKR", 2), ("LRD", 2), ("LSL", 2), ("LYD", 3), ("MAD", 2), ("MDL", 2), ("MGA", 2),
+    ("MKD", 2), ("MMK", 2), ("MNT", 2), ("MOP", 2), ("MRU", 2), ("MUR", 2), ("MVR", 2),
+    ("MWK", 2), ("MXN", 2), ("MYR", 2), ("MZN", 2), ("NAD", 2), ("NGN", 2), ("NIO", 2),
+    ("NOK", 2), ("NPR", 2), ("NZD", 2), ("OMR", 3), ("PAB", 2), ("PEN", 2), ("PGK", 2),
+    ("PHP", 2), ("PKR", 2), ("PLN", 2), ("PYG", 0), ("QAR", 2), ("RON", 2), ("RSD", 2),
+    ("RUB", 2), ("RWF", 0), ("SAR", 2), ("SBD", 2), ("SCR", 2), ("SDG", 2), ("SEK", 2),
+    ("SGD", 2), ("SHP", 2), ("SLE", 2), ("SOS", 2), ("SRD", 2), ("SSP", 2), ("STN", 2),
+    ("SVC", 2), ("SYP", 2), ("SZL", 2), ("THB", 2), ("TJS", 2), ("TMT", 2), ("TND", 3),
+    ("TOP", 2), ("TRY", 2), ("TTD", 2), ("TWD", 2), ("TZS", 2), ("UAH", 2), ("UGX", 0),
+    ("USD", 2), ("UYU", 2), ("UZS", 2), ("VES", 2), ("VND", 0), ("VUV", 0), ("WST", 2),
+    ("XAF", 0), ("XCD", 2), ("XOF", 0), ("XPF", 0), ("YER", 2), ("ZAR", 2), ("ZMW", 2),
+    ("ZWL", 2),
+];
+
+/// Errors of money operations.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MoneyError {
+    #[error("Unknown currency '{0}'")]
+    UnknownCurrency(String),
+    #[error("Currency mismatch: {0} and {1}")]
+    CurrencyMismatch(Currency, Currency),
+    #[error("Amount out of range")]
+    Overflow,
+    #[error("Cannot allocate an amount without ratios")]
+    NoRatios,
+}
+
+impl From<MoneyError> for AppError {
+    fn from(error: MoneyError) -> Self {
+        AppError::BadRequest(error.to_string())
+    }
+}
+
+/// An ISO 4217 currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency {
+    code: &'static str,
+    minor_units: u32,
+}
+
+impl Currency {
+    pub const EUR: Currency = Currency { code: "EUR", minor_units: 2 };
+    pub const USD: Currency = Currency { code: "USD", minor_units: 2 };
+    pub const GBP: Currency = Currency { code: "GBP", minor_units: 2 };
+    pub const CHF: Currency = Currency { code: "CHF", minor_units: 2 };
+    pub const JPY: Currency = Currency { code: "JPY", minor_units: 0 };
+
+    /// The currency of an ISO 4217 code (case-insensitive).
+    pub fn new(code: &str) -> Result<Self, MoneyError> {
+        CURRENCIES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(code.trim()))
+            .map(|&(code, minor_units)| Self { code, minor_units })
+            .ok_or_else(|| MoneyError::UnknownCurrency(code.to_string()))
+    }
+
+    /// Three-letter code, e.g. `EUR`.
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    /// Digits after the decimal point, e.g. 2 for `EUR`, 0 for `JPY`.
+    pub fn minor_units(&self) -> u32 {
+        self.minor_units
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code)
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        Self::new(code)
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = MoneyError;
+
+    fn try_from(code: String) -> Result<Self, Self::Error> {
+        Self::new(&code)
+    }
+}
+
+impl From<Currency> for String {
+    fn from(currency: Currency) -> Self {
+        currency.code.to_string()
+    }
+}
+
+impl PartialSchema for Currency {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some("ISO 4217 currency code"))
+            .pattern(Some("^[A-Z]{3}$"))
+            .examples(["EUR"])
+            .into()
+    }
+}
+
+impl ToSchema for Currency {}
+
+/// An amount of a currency.
+///
+/// # Example
+///
+/// ```ignore
+/// let price = Money::new(dec!(19.99), Currency::EUR);
+/// let total = price.checked_mul(Decimal::from(3))?.checked_add(shipping)?;
+/// // Three installments, the first one carrying the leftover cents
+/// let installments = total.allocate(&[1, 1, 1])?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct Money {
+    /// Amount, as a decimal string
+    #[schema(value_type = String, example = "12.50")]
+    pub amount: Decimal,
+    #[schema(inline)]
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Decimal, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    /// Zero of `currency`.
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(Decimal::ZERO, currency)
+    }
+
+    /// An amount in minor units, e.g. cents.
+    pub fn from_minor(minor: i64, currency: Currency) -> Self {
+        Self::new(Decimal::new(minor, currency.minor_units), currency)
+    }
+
+    /// The amount in minor units, rounded to the currency's precision.
+    pub fn to_minor(&self) -> Result<i64, MoneyError> {
+        let scaled = self
+            .round()
+            .amount
+            .checked_mul(Decimal::from(10_i64.pow(self.currency.minor_units)))
+            .ok_or(MoneyError::Overflow)?;
+        scaled.to_i64().ok_or(MoneyError::Overflow)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.amount.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.amount.is_sign_negative() && !self.amount.is_zero()
+    }
+
+    /// Round to the currency's minor unit (half to even).
+    pub fn round(&self) -> Self {
+        Self::new(self.amount.round_dp(self.currency.minor_units), self.currency)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
+        }
+    }
+
+    pub fn checked_add(&self, other: Money) -> Result<Self, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self.amount.checked_add(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    pub fn checked_sub(&self, other: Money) -> Result<Self, MoneyError> {
+        self.same_currency(&other)?;
+        let amount = self.amount.checked_sub(other.amount).ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Multiply by a factor (quantity, tax rate, ...), without rounding.
+    pub fn checked_mul(&self, factor: Decimal) -> Result<Self, MoneyError> {
+        let amount = self.amount.checked_mul(factor).ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    /// Split by ratios without losing minor units: the remainder goes to the
+    /// first parts with a non-zero ratio, one minor unit each.
+    pub fn allocate(&self, ratios: &[u32]) -> Result<Vec<Self>, MoneyError> {
+        let total: u64 = ratios.iter().map(|&ratio| u64::from(ratio)).sum();
+        if total == 0 {
+            return Err(MoneyError::NoRatios);
+        }
+
+        let minor = self.to_minor()?;
+        let mut parts: Vec<i64> = ratios
+            .iter()
+            .map(|&ratio| {
+                let share = i128::from(minor) * i128::from(ratio) / i128::from(total);
+                share as i64
+            })
+            .collect();
+        let mut remainder = minor - parts.iter().sum::<i64>();
+        let step = remainder.signum();
+        // Less than one minor unit per part with a non-zero ratio is left
+        for (part, _) in parts.iter_mut().zip(ratios).filter(|(_, ratio)| **ratio > 0) {
+            if remainder == 0 {
+                break;
+            }
+            *part += step;
+            remainder -= step;
+        }
+
+        Ok(parts
+            .into_iter()
+            .map(|part| Self::from_minor(part, self.currency))
+            .collect())
+    }
+}
+
+impl PartialOrd for Money {
+    /// Amounts of different currencies are not comparable.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.amount.cmp(&other.amount))
+    }
+}
+
+impl std::ops::Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Self::new(-self.amount, self.currency)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+impl Validate for Money {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        if self.amount.normalize().scale() <= self.currency.minor_units {
+            return Ok(());
+        }
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "amount",
+            ValidationError::new("money_precision").with_message(
+                format!(
+                    "{} amounts have at most {} decimal places",
+                    self.currency, self.currency.minor_units
+                )
+                .into(),
+            ),
+        );
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn eur(amount: &str) -> Money {
+        Money::new(Decimal::from_str(amount).unwrap(), Currency::EUR)
+    }
+
+    #[test]
+    fn test_serde_shape() {
+        let json = serde_json::to_value(eur("12.50")).unwrap();
+        assert_eq!(json, serde_json::json!({ "amount": "12.50", "currency": "EUR" }));
+
+        let money: Money =
+            serde_json::from_value(serde_json::json!({ "amount": "3", "currency": "jpy" }))
+                .unwrap();
+        assert_eq!(money, Money::new(Decimal::from(3), Currency::JPY));
+
+        let unknown = serde_json::json!({ "amount": "1", "currency": "XYZ" });
+        assert!(serde_json::from_value::<Money>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(eur("10.25").checked_add(eur("0.75")), Ok(eur("11.00")));
+        assert_eq!(eur("10").checked_sub(eur("12.5")), Ok(eur("-2.5")));
+        assert_eq!(
+            eur("1").checked_add(Money::zero(Currency::USD)),
+            Err(MoneyError::CurrencyMismatch(Currency::EUR, Currency::USD))
+        );
+        assert!(eur("1").partial_cmp(&Money::zero(Currency::USD)).is_none());
+        assert!(eur("1") > eur("0.99"));
+        assert_eq!(
+            Money::new(Decimal::MAX, Currency::EUR).checked_add(eur("1")),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_minor_units() {
+        assert_eq!(Money::from_minor(1999, Currency::EUR), eur("19.99"));
+        assert_eq!(eur("19.995").to_minor(), Ok(2000));
+        assert_eq!(Money::from_minor(500, Currency::JPY).to_minor(), Ok(500));
+    }
+
+    #[test]
+    fn test_allocate_keeps_every_cent() {
+        let parts = eur("100.00").allocate(&[1, 1, 1]).unwrap();
+        assert_eq!(parts, vec![eur("33.34"), eur("33.33"), eur("33.33")]);
+
+        let parts = eur("-0.05").allocate(&[1, 1]).unwrap();
+        assert_eq!(parts, vec![eur("-0.03"), eur("-0.02")]);
+
+        // Parts with a zero ratio get nothing, remainder included
+        let parts = eur("0.01").allocate(&[0, 1, 1]).unwrap();
+        assert_eq!(parts, vec![eur("0.00"), eur("0.01"), eur("0.00")]);
+
+        assert_eq!(eur("1").allocate(&[]), Err(MoneyError::NoRatios));
+    }
+
+    #[test]
+    fn test_precision_validated() {
+        assert!(eur("12.50").validate().is_ok());
+        assert!(eur("12.505").validate().is_err());
+        assert!(Money::new(Decimal::from_str("1.5").unwrap(), Currency::JPY).validate().is_err());
+    }
+}
@@ -0,0 +1,708 @@
+//! Push notifications and SMS behind one interface.
+//!
+//! Services queue notifications on a `Notifier`; a background task delivers
+//! them with the configured providers, retrying transient failures with
+//! exponential backoff. Requests never wait on a provider.
+//!
+//! Providers:
+//! - `FcmProvider` - Firebase Cloud Messaging HTTP v1 API (Android, web, iOS via FCM)
+//! - `ApnsProvider` - Apple Push Notification service HTTP/2 API
+//! - `TwilioSms` - Twilio Messages API (or any Twilio-compatible SMS gateway)
+//!
+//! ```toml
+//! [notifications]
+//! max_attempts = 5
+//! push = { type = "fcm", project_id = "acme-prod", access_token = "..." }
+//! # push = { type = "apns", topic = "com.acme.app", provider_token = "...", sandbox = true }
+//! sms = { type = "twilio", account_sid = "AC...", auth_token = "...", from = "+15005550006" }
+//! ```
+//!
+//! FCM access tokens and APNs provider tokens expire: services refresh them
+//! with `FcmProvider::set_access_token` / `ApnsProvider::set_provider_token`.
+//!
+//! Providers answer `AppError::BadRequest` for rejected notifications
+//! (unregistered device, invalid number), which aren't retried. Delivered
+//! and failed notifications are counted in `eywa_notifications_sent_total`
+//! and `eywa_notifications_failed_total`, labeled by `channel`.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Semaphore};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+const FCM_URL: &str = "https://fcm.googleapis.com";
+const APNS_URL: &str = "https://api.push.apple.com";
+const APNS_SANDBOX_URL: &str = "https://api.sandbox.push.apple.com";
+const TWILIO_URL: &str = "https://api.twilio.com";
+
+/// A push notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushMessage {
+    pub title: String,
+    pub body: String,
+    /// Custom key-value data delivered to the app
+    #[serde(default)]
+    pub data: BTreeMap<String, String>,
+}
+
+impl PushMessage {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    /// Add custom data, e.g. the ID of the entity to open.
+    pub fn data(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.data.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// A notification to deliver.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+pub enum Notification {
+    Push {
+        device_token: String,
+        message: PushMessage,
+    },
+    Sms {
+        /// E.164 phone number
+        to: String,
+        text: String,
+    },
+}
+
+impl Notification {
+    /// A push notification to a device.
+    pub fn push(device_token: impl Into<String>, message: PushMessage) -> Self {
+        Self::Push {
+            device_token: device_token.into(),
+            message,
+        }
+    }
+
+    /// A text message to an E.164 phone number.
+    pub fn sms(to: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Sms {
+            to: to.into(),
+            text: text.into(),
+        }
+    }
+
+    /// Channel label of the notification (`push` or `sms`).
+    pub fn channel(&self) -> &'static str {
+        match self {
+            Self::Push { .. } => "push",
+            Self::Sms { .. } => "sms",
+        }
+    }
+}
+
+/// Delivers push notifications.
+#[async_trait]
+pub trait PushProvider: Send + Sync + 'static {
+    async fn send(&self, device_token: &str, message: &PushMessage) -> Result<()>;
+}
+
+/// Delivers text messages.
+#[async_trait]
+pub trait SmsProvider: Send + Sync + 'static {
+    async fn send(&self, to: &str, text: &str) -> Result<()>;
+}
+
+fn provider_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Notification provider failed: {e}"))
+}
+
+/// Map a provider response to a result: `4xx` rejects the notification,
+/// anything else unsuccessful is retried.
+async fn check_response(
+    response: std::result::Result<reqwest::Response, reqwest::Error>,
+) -> Result<()> {
+    let response = response.map_err(provider_error)?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        Err(AppError::BadRequest(format!("Notification rejected ({status}): {body}")))
+    } else {
+        Err(provider_error(format!("{status}: {body}")))
+    }
+}
+
+/// Firebase Cloud Messaging HTTP v1 provider.
+#[derive(Debug, Clone)]
+pub struct FcmProvider {
+    client: reqwest::Client,
+    url: String,
+    project_id: String,
+    access_token: Arc<RwLock<String>>,
+}
+
+impl FcmProvider {
+    /// Send for `project_id` with an OAuth2 access token of the
+    /// `firebase.messaging` scope.
+    pub fn new(
+        client: reqwest::Client,
+        project_id: impl Into<String>,
+        access_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            url: FCM_URL.to_string(),
+            project_id: project_id.into(),
+            access_token: Arc::new(RwLock::new(access_token.into())),
+        }
+    }
+
+    /// Send to another base URL (emulators, tests).
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Replace the access token, for every clone of the provider.
+    pub fn set_access_token(&self, access_token: impl Into<String>) {
+        *self.access_token.write().unwrap() = access_token.into();
+    }
+}
+
+#[async_trait]
+impl PushProvider for FcmProvider {
+    async fn send(&self, device_token: &str, message: &PushMessage) -> Result<()> {
+        let token = self.access_token.read().unwrap().clone();
+        let response = self
+            .client
+            .post(format!(
+                "{}/v1/projects/{}/messages:send",
+                self.url.trim_end_matches('/'),
+                self.project_id
+            ))
+            .bearer_auth(token)
+            .json(&serde_json::json!({
+                "message": {
+                    "token": device_token,
+                    "notification": { "title": message.title, "body": message.body },
+                    "data": message.data,
+                }
+            }))
+            .send()
+            .await;
+        check_response(response).await
+    }
+}
+
+/// Apple Push Notification service provider (token-based authentication).
+#[derive(Debug, Clone)]
+pub struct ApnsProvider {
+    client: reqwest::Client,
+    url: String,
+    topic: String,
+    provider_token: Arc<RwLock<String>>,
+}
+
+impl ApnsProvider {
+    /// Send to the app with bundle ID `topic`, authenticated by an ES256
+    /// provider token signed with the team's APNs key.
+    ///
+    /// `client` must support HTTP/2.
+    pub fn new(
+        client: reqwest::Client,
+        topic: impl Into<String>,
+        provider_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            url: APNS_URL.to_string(),
+            topic: topic.into(),
+            provider_token: Arc::new(RwLock::new(provider_token.into())),
+        }
+    }
+
+    /// Send through the development environment.
+    pub fn sandbox(self) -> Self {
+        self.url(APNS_SANDBOX_URL)
+    }
+
+    /// Send to another base URL (tests).
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// Replace the provider token, for every clone of the provider.
+    pub fn set_provider_token(&self, provider_token: impl Into<String>) {
+        *self.provider_token.write().unwrap() = provider_token.into();
+    }
+}
+
+#[async_trait]
+impl PushProvider for ApnsProvider {
+    async fn send(&self, device_token: &str, message: &PushMessage) -> Result<()> {
+        let token = self.provider_token.read().unwrap().clone();
+        let mut payload = serde_json::json!({
+            "aps": { "alert": { "title": message.title, "body": message.body } }
+        });
+        for (key, value) in &message.data {
+            payload[key] = value.clone().into();
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/3/device/{device_token}", self.url.trim_end_matches('/')))
+            .bearer_auth(token)
+            .header("apns-topic", &self.topic)
+            .header("apns-push-type", "alert")
+            .json(&payload)
+            .send()
+            .await;
+        check_response(response).await
+    }
+}
+
+/// Twilio Messages API provider.
+#[derive(Debug, Clone)]
+pub struct TwilioSms {
+    client: reqwest::Client,
+    url: String,
+    account_sid: String,
+    auth_token: String,
+    from: String,
+}
+
+impl TwilioSms {
+    /// Send from the number (or messaging service SID) `from`.
+    pub fn new(
+        client: reqwest::Client,
+        account_sid: impl Into<String>,
+        auth_token: impl Into<String>,
+        from: impl Into<String>,
+    ) -> Self {
+        Self {
+            client,
+            url: TWILIO_URL.to_string(),
+            account_sid: account_sid.into(),
+            auth_token: auth_token.into(),
+            from: from.into(),
+        }
+    }
+
+    /// Send to another base URL (Twilio-compatible gateways, tests).
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl SmsProvider for TwilioSms {
+    async fn send(&self, to: &str, text: &str) -> Result<()> {
+        let sender = if self.from.starts_with("MG") {
+            "MessagingServiceSid"
+        } else {
+            "From"
+        };
+        let response = self
+            .client
+            .post(format!(
+                "{}/2010-04-01/Accounts/{}/Messages.json",
+                self.url.trim_end_matches('/'),
+                self.account_sid
+            ))
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("To", to), (sender, self.from.as_str()), ("Body", text)])
+            .send()
+            .await;
+        check_response(response).await
+    }
+}
+
+/// Push provider selected from config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PushSettings {
+    Fcm {
+        project_id: String,
+        access_token: String,
+        #[serde(default)]
+        url: Option<String>,
+    },
+    Apns {
+        topic: String,
+        provider_token: String,
+        #[serde(default)]
+        sandbox: bool,
+        #[serde(default)]
+        url: Option<String>,
+    },
+}
+
+impl PushSettings {
+    /// Build the configured provider.
+    pub fn build(&self, client: reqwest::Client) -> Arc<dyn PushProvider> {
+        match self {
+            Self::Fcm {
+                project_id,
+                access_token,
+                url,
+            } => {
+                let provider = FcmProvider::new(client, project_id, access_token);
+                match url {
+                    Some(url) => Arc::new(provider.url(url)),
+                    None => Arc::new(provider),
+                }
+            }
+            Self::Apns {
+                topic,
+                provider_token,
+                sandbox,
+                url,
+            } => {
+                let mut provider = ApnsProvider::new(client, topic, provider_token);
+                if *sandbox {
+                    provider = provider.sandbox();
+                }
+                if let Some(url) = url {
+                    provider = provider.url(url);
+                }
+                Arc::new(provider)
+            }
+        }
+    }
+}
+
+/// SMS provider selected from config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SmsSettings {
+    Twilio {
+        account_sid: String,
+        auth_token: String,
+        from: String,
+        #[serde(default)]
+        url: Option<String>,
+    },
+}
+
+impl SmsSettings {
+    /// Build the configured provider.
+    pub fn build(&self, client: reqwest::Client) -> Arc<dyn SmsProvider> {
+        match self {
+            Self::Twilio {
+                account_sid,
+                auth_token,
+                from,
+                url,
+            } => {
+                let provider = TwilioSms::new(client, account_sid, auth_token, from);
+                match url {
+                    Some(url) => Arc::new(provider.url(url)),
+                    None => Arc::new(provider),
+                }
+            }
+        }
+    }
+}
+
+/// Notification settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    #[serde(default)]
+    pub push: Option<PushSettings>,
+    #[serde(default)]
+    pub sms: Option<SmsSettings>,
+    /// Delivery attempts per notification, including the first
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each further retry
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Notifications queued while providers are busy; more are rejected
+    #[serde(default = "default_buffer")]
+    pub buffer: usize,
+    /// Notifications delivered concurrently
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_retry_delay_ms() -> u64 {
+    500
+}
+
+fn default_buffer() -> usize {
+    10_000
+}
+
+fn default_concurrency() -> usize {
+    16
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            push: None,
+            sms: None,
+            max_attempts: default_max_attempts(),
+            retry_delay_ms: default_retry_delay_ms(),
+            buffer: default_buffer(),
+            concurrency: default_concurrency(),
+        }
+    }
+}
+
+/// The providers notifications are delivered with.
+#[derive(Clone, Default)]
+pub struct Providers {
+    pub push: Option<Arc<dyn PushProvider>>,
+    pub sms: Option<Arc<dyn SmsProvider>>,
+}
+
+impl Providers {
+    /// Deliver a notification once.
+    pub async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let not_configured = || {
+            AppError::InternalServerError(format!(
+                "No {} notification provider configured",
+                notification.channel()
+            ))
+        };
+        match notification {
+            Notification::Push {
+                device_token,
+                message,
+            } => {
+                let push = self.push.as_ref().ok_or_else(not_configured)?;
+                push.send(device_token, message).await
+            }
+            Notification::Sms { to, text } => {
+                let sms = self.sms.as_ref().ok_or_else(not_configured)?;
+                sms.send(to, text).await
+            }
+        }
+    }
+}
+
+/// Handle to the notification queue.
+///
+/// Cheap to clone; provide it to handlers with `EywaApp::provide` and
+/// extract it with `Inject<Notifier>`.
+///
+/// # Example
+///
+/// ```ignore
+/// let notifier = Notifier::from_settings(&config.notifications, http.clone());
+///
+/// notifier.notify(Notification::push(
+///     &device.token,
+///     PushMessage::new("Order shipped", "Your order is on its way")
+///         .data("order_id", order.id.to_string()),
+/// ))?;
+/// notifier.notify(Notification::sms(&user.phone, "Your code is 123456"))?;
+/// ```
+#[derive(Clone)]
+pub struct Notifier {
+    sender: mpsc::Sender<Notification>,
+    providers: Providers,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Notifier")
+            .field("push", &self.providers.push.is_some())
+            .field("sms", &self.providers.sms.is_some())
+            .finish()
+    }
+}
+
+impl Notifier {
+    /// Start the queue with the providers from the settings.
+    ///
+    /// Must be called from a tokio runtime.
+    pub fn from_settings(settings: &NotificationSettings, client: reqwest::Client) -> Self {
+        let providers = Providers {
+            push: settings.push.as_ref().map(|push| push.build(client.clone())),
+            sms: settings.sms.as_ref().map(|sms| sms.build(client)),
+        };
+        Self::spawn(settings, providers)
+    }
+
+    /// Start the queue with custom providers.
+    ///
+    /// The background task delivers the queued notifications once every
+    /// handle is dropped. Must be called from a tokio runtime.
+    pub fn spawn(settings: &NotificationSettings, providers: Providers) -> Self {
+        let (sender, receiver) = mpsc::channel(settings.buffer.max(1));
+        tokio::spawn(dispatch(
+            receiver,
+            providers.clone(),
+            settings.concurrency.max(1),
+            settings.max_attempts.max(1),
+            Duration::from_millis(settings.retry_delay_ms),
+        ));
+        Self { sender, providers }
+    }
+
+    /// Queue a notification for background delivery.
+    ///
+    /// Fails if the queue is full.
+    pub fn notify(&self, notification: Notification) -> Result<()> {
+        self.sender.try_send(notification).map_err(|e| {
+            let channel = e.into_inner().channel();
+            metrics::counter!("eywa_notifications_failed_total", "channel" => channel).increment(1);
+            AppError::InternalServerError("Notification queue is full".to_string())
+        })
+    }
+
+    /// Deliver a notification now, once, and return the provider's outcome.
+    pub async fn send_now(&self, notification: &Notification) -> Result<()> {
+        self.providers.deliver(notification).await
+    }
+}
+
+/// Deliver queued notifications, at most `concurrency` at a time.
+async fn dispatch(
+    mut receiver: mpsc::Receiver<Notification>,
+    providers: Providers,
+    concurrency: usize,
+    max_attempts: u32,
+    retry_delay: Duration,
+) {
+    let permits = Arc::new(Semaphore::new(concurrency));
+    while let Some(notification) = receiver.recv().await {
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            return;
+        };
+        let providers = providers.clone();
+        tokio::spawn(async move {
+            deliver_with_retries(&providers, &notification, max_attempts, retry_delay).await;
+            drop(permit);
+        });
+    }
+}
+
+async fn deliver_with_retries(
+    providers: &Providers,
+    notification: &Notification,
+    max_attempts: u32,
+    retry_delay: Duration,
+) {
+    let channel = notification.channel();
+    let mut delay = retry_delay;
+    for attempt in 1..=max_attempts {
+        match providers.deliver(notification).await {
+            Ok(()) => {
+                metrics::counter!("eywa_notifications_sent_total", "channel" => channel)
+                    .increment(1);
+                return;
+            }
+            Err(AppError::BadRequest(reason)) => {
+                tracing::warn!(channel, %reason, "notification rejected");
+                break;
+            }
+            Err(e) if attempt < max_attempts => {
+                tracing::debug!(channel, attempt, error = ?e, "notification failed, retrying");
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => {
+                tracing::warn!(channel, attempts = max_attempts, error = ?e, "notification failed");
+            }
+        }
+    }
+    metrics::counter!("eywa_notifications_failed_total", "channel" => channel).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// Fails the first `failures` sends, then records the notifications.
+    #[derive(Default)]
+    struct MemoryProvider {
+        failures: AtomicU32,
+        sent: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl PushProvider for MemoryProvider {
+        async fn send(&self, device_token: &str, message: &PushMessage) -> Result<()> {
+            if device_token == "unregistered" {
+                return Err(AppError::BadRequest("UNREGISTERED".to_string()));
+            }
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(AppError::InternalServerError("unavailable".to_string()));
+            }
+            self.sent.lock().unwrap().push(format!("{device_token}: {}", message.title));
+            Ok(())
+        }
+    }
+
+    fn settings() -> NotificationSettings {
+        serde_json::from_value(serde_json::json!({
+            "max_attempts": 3,
+            "retry_delay_ms": 1,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_retried() {
+        let provider = Arc::new(MemoryProvider::default());
+        provider.failures.store(2, Ordering::SeqCst);
+        let providers = Providers {
+            push: Some(provider.clone()),
+            sms: None,
+        };
+
+        let notification = Notification::push("device-1", PushMessage::new("Shipped", "Soon"));
+        deliver_with_retries(&providers, &notification, 3, Duration::from_millis(1)).await;
+        assert_eq!(*provider.sent.lock().unwrap(), ["device-1: Shipped"]);
+
+        let rejected = Notification::push("unregistered", PushMessage::new("Shipped", ""));
+        deliver_with_retries(&providers, &rejected, 3, Duration::from_millis(1)).await;
+        assert_eq!(provider.sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queued_notifications_delivered() {
+        let provider = Arc::new(MemoryProvider::default());
+        let providers = Providers {
+            push: Some(provider.clone()),
+            sms: None,
+        };
+        let notifier = Notifier::spawn(&settings(), providers);
+
+        for device in ["a", "b"] {
+            notifier
+                .notify(Notification::push(device, PushMessage::new("Hi", "").data("id", "1")))
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut sent = provider.sent.lock().unwrap().clone();
+        sent.sort();
+        assert_eq!(sent, ["a: Hi", "b: Hi"]);
+        let missing = notifier.send_now(&Notification::sms("+33600000000", "code")).await;
+        assert!(missing.is_err());
+    }
+}
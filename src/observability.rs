@@ -0,0 +1,136 @@
+//! RED metrics, request tracing and log correlation in one call.
+//!
+//! `EywaApp::observability` wires the standard stack every service needs
+//! for the shared Grafana dashboards:
+//!
+//! - Request context and access logs (`.request_context()` and
+//!   `.request_logging()`), so every log line carries the request's ids
+//! - A `service` span around each request, adding `service` and `env` to
+//!   every log line
+//! - RED metrics with the standard label set:
+//!   - `http_server_requests_total` - Requests (rate, and errors with
+//!     `status_class="5xx"`)
+//!   - `http_server_request_duration_seconds` - Duration histogram
+//!
+//! Both metrics are labeled `service`, `env`, `method`, `route` (the route
+//! template, e.g. `/api/v1/projects/{id}`, keeping cardinality bounded) and
+//! `status_class` (`2xx`, `4xx`, ...).
+//!
+//! ```toml
+//! [observability]
+//! service = "projects"
+//! env = "production"
+//! ```
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::analytics::UNMATCHED_ROUTE;
+
+/// Service identity attached to metrics and logs, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObservabilitySettings {
+    /// Service name, e.g. `projects`
+    pub service: String,
+    /// Deployment environment, e.g. `production`
+    #[serde(default = "default_env")]
+    pub env: String,
+}
+
+fn default_env() -> String {
+    "development".to_string()
+}
+
+impl ObservabilitySettings {
+    pub fn new(service: impl Into<String>, env: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            env: env.into(),
+        }
+    }
+}
+
+/// Status class label of a status code (`2xx`, `4xx`, ...).
+pub fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Middleware recording RED metrics and running the request in a `service` span.
+///
+/// Installed by `EywaApp::observability()`.
+pub async fn observability_middleware(
+    State(settings): State<Arc<ObservabilitySettings>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE, MatchedPath::as_str)
+        .to_string();
+
+    let span = tracing::info_span!(
+        "service",
+        service = %settings.service,
+        env = %settings.env,
+    );
+    let response = next.run(req).instrument(span).await;
+
+    let class = status_class(response.status());
+    metrics::counter!(
+        "http_server_requests_total",
+        "service" => settings.service.clone(),
+        "env" => settings.env.clone(),
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status_class" => class
+    )
+    .increment(1);
+    metrics::histogram!(
+        "http_server_request_duration_seconds",
+        "service" => settings.service.clone(),
+        "env" => settings.env.clone(),
+        "method" => method,
+        "route" => route,
+        "status_class" => class
+    )
+    .record(started.elapsed().as_secs_f64());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_classes() {
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::NOT_MODIFIED), "3xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::SERVICE_UNAVAILABLE), "5xx");
+    }
+
+    #[test]
+    fn test_env_defaults_to_development() {
+        let settings: ObservabilitySettings =
+            serde_json::from_value(serde_json::json!({ "service": "projects" })).unwrap();
+        assert_eq!(settings, ObservabilitySettings::new("projects", "development"));
+    }
+}
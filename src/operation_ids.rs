@@ -0,0 +1,288 @@
+//! Configurable `operationId` naming.
+//!
+//! utoipa uses the handler function name as `operationId`, which collides
+//! across controllers (`list`, `get`, ...) and doesn't follow any casing.
+//! An `OperationIdStrategy` rewrites every operation's ID from its handler
+//! name, tag, method and path, with a fixed casing, and disambiguates
+//! collisions with a numeric suffix so generated clients stay stable.
+//!
+//! Enabled with `EywaApp::operation_ids`.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use utoipa::openapi::OpenApi;
+
+use crate::codegen::snake_case;
+
+/// Information available when naming an operation.
+#[derive(Debug, Clone, Copy)]
+pub struct OperationContext<'a> {
+    /// Lowercase HTTP method (`get`, `post`, ...)
+    pub method: &'a str,
+    /// Route template (`/api/v1/projects/{id}`)
+    pub path: &'a str,
+    /// First tag of the operation (usually the controller tag)
+    pub tag: Option<&'a str>,
+    /// `operationId` produced by utoipa (the handler name)
+    pub handler: Option<&'a str>,
+}
+
+/// Casing applied to generated operation IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Casing {
+    /// `projects-get-api-v1-projects-by-id`
+    Kebab,
+    /// `projects_get_api_v1_projects_by_id`
+    Snake,
+    /// `projectsGetApiV1ProjectsById`
+    Camel,
+    /// `ProjectsGetApiV1ProjectsById`
+    Pascal,
+    /// Keep the name as produced
+    Preserve,
+}
+
+type NameFn = Arc<dyn Fn(&OperationContext<'_>) -> String + Send + Sync>;
+
+#[derive(Clone)]
+enum Format {
+    Handler,
+    TagMethodPath,
+    MethodPath,
+    Custom(NameFn),
+}
+
+/// Strategy generating the `operationId` of every operation.
+///
+/// # Example
+///
+/// ```ignore
+/// // `projects-get-api-v1-projects-by-id`
+/// OperationIdStrategy::tag_method_path()
+///
+/// // `getProject` (handler name, camelCase)
+/// OperationIdStrategy::handler_name().casing(Casing::Camel)
+///
+/// // Custom format
+/// OperationIdStrategy::custom(|op| format!("{}-{}", op.tag.unwrap_or("api"), op.handler.unwrap_or(op.method)))
+/// ```
+#[derive(Clone)]
+pub struct OperationIdStrategy {
+    format: Format,
+    casing: Casing,
+}
+
+impl OperationIdStrategy {
+    fn new(format: Format) -> Self {
+        Self {
+            format,
+            casing: Casing::Kebab,
+        }
+    }
+
+    /// Use the handler name (falls back to `{method}_{path}` if unknown).
+    pub fn handler_name() -> Self {
+        Self::new(Format::Handler)
+    }
+
+    /// Use `{tag}_{method}_{path}`.
+    pub fn tag_method_path() -> Self {
+        Self::new(Format::TagMethodPath)
+    }
+
+    /// Use `{method}_{path}`.
+    pub fn method_path() -> Self {
+        Self::new(Format::MethodPath)
+    }
+
+    /// Use a custom naming function; the casing is still applied.
+    pub fn custom<F>(name: F) -> Self
+    where
+        F: Fn(&OperationContext<'_>) -> String + Send + Sync + 'static,
+    {
+        Self::new(Format::Custom(Arc::new(name)))
+    }
+
+    /// Set the casing (default kebab-case).
+    pub fn casing(mut self, casing: Casing) -> Self {
+        self.casing = casing;
+        self
+    }
+
+    /// Returns the `operationId` of an operation, before disambiguation.
+    pub fn operation_id(&self, operation: &OperationContext<'_>) -> String {
+        let method_path = || format!("{} {}", operation.method, path_words(operation.path));
+        let name = match &self.format {
+            Format::Handler => operation
+                .handler
+                .map(str::to_string)
+                .unwrap_or_else(method_path),
+            Format::TagMethodPath => match operation.tag {
+                Some(tag) => format!("{tag} {}", method_path()),
+                None => method_path(),
+            },
+            Format::MethodPath => method_path(),
+            Format::Custom(name) => name(operation),
+        };
+        self.apply_casing(&name)
+    }
+
+    fn apply_casing(&self, name: &str) -> String {
+        if self.casing == Casing::Preserve {
+            return name.to_string();
+        }
+        let words = snake_case(name);
+        let words = words.split('_').filter(|word| !word.is_empty());
+        match self.casing {
+            Casing::Kebab => words.collect::<Vec<_>>().join("-"),
+            Casing::Snake => words.collect::<Vec<_>>().join("_"),
+            Casing::Camel => words
+                .enumerate()
+                .map(|(i, word)| if i == 0 { word.to_string() } else { capitalize(word) })
+                .collect(),
+            Casing::Pascal | Casing::Preserve => words.map(capitalize).collect(),
+        }
+    }
+
+    /// Separator used before the numeric suffix of colliding IDs.
+    fn separator(&self) -> &'static str {
+        match self.casing {
+            Casing::Kebab => "-",
+            Casing::Camel | Casing::Pascal => "",
+            Casing::Snake | Casing::Preserve => "_",
+        }
+    }
+
+    /// Rewrite the `operationId` of every operation in the spec.
+    ///
+    /// Paths and methods are visited in a fixed order, so colliding IDs get
+    /// the same suffix (`-2`, `-3`, ...) on every build.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        let mut seen = HashSet::new();
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            let operations = [
+                ("get", &mut item.get),
+                ("post", &mut item.post),
+                ("put", &mut item.put),
+                ("delete", &mut item.delete),
+                ("patch", &mut item.patch),
+            ];
+            for (method, operation) in operations {
+                let Some(operation) = operation.as_mut() else {
+                    continue;
+                };
+                let id = self.operation_id(&OperationContext {
+                    method,
+                    path,
+                    tag: operation
+                        .tags
+                        .as_ref()
+                        .and_then(|tags| tags.first())
+                        .map(String::as_str),
+                    handler: operation.operation_id.as_deref(),
+                });
+
+                let mut unique = id.clone();
+                let mut n = 2;
+                while !seen.insert(unique.clone()) {
+                    unique = format!("{id}{}{n}", self.separator());
+                    n += 1;
+                }
+                operation.operation_id = Some(unique);
+            }
+        }
+    }
+}
+
+/// `/api/v1/projects/{id}` -> `api v1 projects by id`
+fn path_words(path: &str) -> String {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(param) => format!("by {param}"),
+            None => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn context<'a>(method: &'a str, path: &'a str) -> OperationContext<'a> {
+        OperationContext {
+            method,
+            path,
+            tag: Some("Projects"),
+            handler: Some("getProject"),
+        }
+    }
+
+    #[test]
+    fn test_formats_and_casings() {
+        let op = context("get", "/api/v1/projects/{id}");
+
+        assert_eq!(
+            OperationIdStrategy::tag_method_path().operation_id(&op),
+            "projects-get-api-v1-projects-by-id"
+        );
+        assert_eq!(
+            OperationIdStrategy::method_path()
+                .casing(Casing::Snake)
+                .operation_id(&op),
+            "get_api_v1_projects_by_id"
+        );
+        assert_eq!(
+            OperationIdStrategy::handler_name().operation_id(&op),
+            "get-project"
+        );
+        assert_eq!(
+            OperationIdStrategy::handler_name()
+                .casing(Casing::Pascal)
+                .operation_id(&op),
+            "GetProject"
+        );
+        assert_eq!(
+            OperationIdStrategy::custom(|op| format!("{}Op", op.method))
+                .casing(Casing::Camel)
+                .operation_id(&op),
+            "getOp"
+        );
+    }
+
+    #[test]
+    fn test_collisions_get_stable_suffixes() {
+        let mut openapi = OpenApi::default();
+        for path in ["/api/v1/projects", "/api/v1/tasks"] {
+            openapi.paths.paths.insert(
+                path.to_string(),
+                PathItem::new(
+                    HttpMethod::Get,
+                    OperationBuilder::new().operation_id(Some("list")).build(),
+                ),
+            );
+        }
+
+        OperationIdStrategy::handler_name().apply_to_openapi(&mut openapi);
+
+        let id = |path: &str| {
+            openapi.paths.paths[path]
+                .get
+                .as_ref()
+                .and_then(|operation| operation.operation_id.clone())
+        };
+        assert_eq!(id("/api/v1/projects").as_deref(), Some("list"));
+        assert_eq!(id("/api/v1/tasks").as_deref(), Some("list-2"));
+    }
+}
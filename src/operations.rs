@@ -0,0 +1,490 @@
+//! Long-running operations: `202 Accepted` and a status resource.
+//!
+//! Slow endpoints (exports, imports, bulk updates) shouldn't hold the
+//! connection open until a proxy times out. With `Operations`, a handler
+//! starts the work in the background and answers right away:
+//!
+//! ```text
+//! POST /api/v1/exports          -> 202 Accepted
+//!                                  Location: /operations/7b0c...
+//!                                  { "id": "7b0c...", "status": "pending", ... }
+//! GET  /operations/7b0c...      -> 200 { "status": "running", "progress": 40, ... }
+//! GET  /operations/7b0c...      -> 200 { "status": "succeeded", "result": { ... } }
+//! ```
+//!
+//! Operations run as tasks of the service and are kept in memory for
+//! `retention` after they finish: a restart loses them, so the work itself
+//! must be safe to start again. A panic in the work fails the operation.
+//!
+//! An operation started by an authenticated request records the subject of
+//! its verified token, and only that subject can read it; the id alone
+//! gives no access. Internal errors are reported without their message.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::{
+    extract::{FromRequestParts, Path, State},
+    http::{header::LOCATION, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+use chrono::{DateTime, Utc};
+use eywa_errors::AppError;
+use futures_util::FutureExt;
+use serde::Serialize;
+use utoipa::{PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::middleware::auth::VerifiedClaims;
+
+/// URL of the status resource of an operation.
+pub fn operation_url(id: Uuid) -> String {
+    format!("/operations/{id}")
+}
+
+/// Default time finished operations stay available.
+const DEFAULT_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// State of an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl OperationStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+/// Status resource of a long-running operation.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Operation {
+    pub id: Uuid,
+    pub status: OperationStatus,
+    /// Completion percentage, if the operation reports it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<u8>,
+    /// Result of a succeeded operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// Error of a failed operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Subject of the token that started the operation
+    #[serde(skip)]
+    owner: Option<String>,
+}
+
+impl Operation {
+    fn new(owner: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            status: OperationStatus::Pending,
+            progress: None,
+            result: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            owner,
+        }
+    }
+
+    /// Returns `true` if `subject` may read the operation.
+    fn is_visible_to(&self, subject: Option<&str>) -> bool {
+        self.owner.is_none() || self.owner.as_deref() == subject
+    }
+}
+
+/// Registry of the service's operations.
+///
+/// Handlers extract it like a service; `EywaApp::operations` provides it and
+/// serves `GET /operations/{id}`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[utoipa::path(
+///     post,
+///     path = "/api/v1/exports",
+///     responses((status = 202, description = "Export started", body = Operation,
+///         headers(("Location" = String, description = "Status resource"))))
+/// )]
+/// async fn start_export(
+///     operations: Operations,
+///     State(state): State<AppState>,
+/// ) -> OperationAccepted {
+///     operations.start(move |operation| async move {
+///         let rows = state.reports.load().await?;
+///         operation.progress(50);
+///         state.exports.upload(rows).await
+///     })
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Operations {
+    operations: Arc<RwLock<HashMap<Uuid, Operation>>>,
+    retention: Duration,
+    /// Subject of the verified token of the request that extracted it
+    owner: Option<String>,
+}
+
+impl Default for Operations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Operations {
+    /// Create an empty registry keeping finished operations for an hour.
+    pub fn new() -> Self {
+        Self {
+            operations: Arc::default(),
+            retention: DEFAULT_RETENTION,
+            owner: None,
+        }
+    }
+
+    /// Keep finished operations for `retention`.
+    pub fn retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Run `work` in the background and answer `202 Accepted`.
+    ///
+    /// When extracted by an authenticated request, the operation is only
+    /// visible to the subject of its verified token.
+    pub fn start<F, Fut, T>(&self, work: F) -> OperationAccepted
+    where
+        F: FnOnce(OperationHandle) -> Fut + Send + 'static,
+        Fut: Future<Output = crate::Result<T>> + Send + 'static,
+        T: Serialize,
+    {
+        self.purge_expired();
+        let operation = Operation::new(self.owner.clone());
+        let id = operation.id;
+        self.operations.write().unwrap().insert(id, operation.clone());
+
+        let handle = OperationHandle {
+            id,
+            operations: self.clone(),
+        };
+        tokio::spawn(async move {
+            handle.update(|operation| operation.status = OperationStatus::Running);
+            let outcome = AssertUnwindSafe(work(handle.clone()))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|_| {
+                    Err(AppError::InternalServerError("Operation panicked".to_string()))
+                })
+                .and_then(|result| {
+                    serde_json::to_value(result)
+                        .map_err(|e| AppError::InternalServerError(e.to_string()))
+                });
+            match &outcome {
+                Ok(_) => tracing::info!(operation = %id, "operation succeeded"),
+                Err(e) => tracing::warn!(operation = %id, error = ?e, "operation failed"),
+            }
+            handle.update(|operation| match outcome {
+                Ok(result) => {
+                    operation.status = OperationStatus::Succeeded;
+                    operation.progress = Some(100);
+                    operation.result = Some(result);
+                }
+                Err(e) => {
+                    let message = match e {
+                        AppError::InternalServerError(_) => "The operation failed".to_string(),
+                        e => e.to_string(),
+                    };
+                    operation.status = OperationStatus::Failed;
+                    operation.error =
+                        Some(ErrorResponse::new(error_codes::OPERATION_FAILED, message));
+                }
+            });
+        });
+
+        OperationAccepted(operation)
+    }
+
+    /// Current state of an operation.
+    pub fn get(&self, id: Uuid) -> Option<Operation> {
+        self.operations.read().unwrap().get(&id).cloned()
+    }
+
+    fn purge_expired(&self) {
+        let Ok(retention) = chrono::Duration::from_std(self.retention) else {
+            return;
+        };
+        let cutoff = Utc::now() - retention;
+        self.operations
+            .write()
+            .unwrap()
+            .retain(|_, operation| {
+                !operation.status.is_finished() || operation.updated_at > cutoff
+            });
+    }
+}
+
+impl<S> FromRequestParts<S> for Operations
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let mut operations = parts.extensions.get::<Self>().cloned().ok_or_else(|| {
+            AppError::InternalServerError(
+                "Operations are not enabled, call EywaApp::operations()".to_string(),
+            )
+        })?;
+        operations.owner = parts
+            .extensions
+            .get::<VerifiedClaims>()
+            .and_then(|claims| claims.subject())
+            .map(str::to_string);
+        Ok(operations)
+    }
+}
+
+/// Reports the progress of a running operation.
+#[derive(Debug, Clone)]
+pub struct OperationHandle {
+    id: Uuid,
+    operations: Operations,
+}
+
+impl OperationHandle {
+    /// ID of the operation.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Report the completion percentage (capped at 100).
+    pub fn progress(&self, percent: u8) {
+        self.update(|operation| operation.progress = Some(percent.min(100)));
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Operation)) {
+        if let Some(operation) = self.operations.operations.write().unwrap().get_mut(&self.id) {
+            change(operation);
+            operation.updated_at = Utc::now();
+        }
+    }
+}
+
+/// `202 Accepted` with the operation and its `Location`.
+#[derive(Debug, Clone)]
+pub struct OperationAccepted(pub Operation);
+
+impl IntoResponse for OperationAccepted {
+    fn into_response(self) -> Response {
+        let location = HeaderValue::try_from(operation_url(self.0.id));
+        let mut response = (StatusCode::ACCEPTED, Json(self.0)).into_response();
+        if let Ok(location) = location {
+            response.headers_mut().insert(LOCATION, location);
+        }
+        response
+    }
+}
+
+/// Get an operation
+///
+/// Status, progress and outcome of a long-running operation, for the caller
+/// that started it.
+#[utoipa::path(
+    get,
+    path = "/operations/{id}",
+    tag = "Operations",
+    params(("id" = Uuid, Path, description = "Operation ID")),
+    responses(
+        (status = 200, description = "Operation status", body = Operation),
+        (status = 404, description = "Unknown or expired operation", body = ErrorResponse)
+    )
+)]
+pub async fn get_operation(
+    State(operations): State<Operations>,
+    claims: Option<Extension<VerifiedClaims>>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    let subject = claims.as_ref().and_then(|Extension(claims)| claims.subject());
+    operations
+        .get(id)
+        .filter(|operation| operation.is_visible_to(subject))
+        .map(|operation| Json(operation).into_response())
+        .unwrap_or_else(|| {
+            let message = format!("Operation {id} not found or expired");
+            ErrorResponse::new(error_codes::OPERATION_NOT_FOUND, message)
+                .into_response_with(StatusCode::NOT_FOUND)
+        })
+}
+
+/// Status endpoint of the operations.
+pub struct OperationsController;
+
+impl OperationsController {
+    /// Build the router serving `GET /operations/{id}`.
+    pub fn router<S>(operations: Operations) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/operations/{id}", get(get_operation))
+            .with_state(operations)
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        openapi.paths.paths.insert(
+            <__path_get_operation as Path>::path().to_string(),
+            utoipa::openapi::path::PathItem::new(
+                utoipa::openapi::path::HttpMethod::Get,
+                <__path_get_operation as Path>::operation(),
+            ),
+        );
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components
+            .schemas
+            .insert("Operation".to_string(), Operation::schema());
+        components
+            .schemas
+            .insert("OperationStatus".to_string(), OperationStatus::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+
+    async fn wait_for(client: &TestClient, location: &str, status: &str) -> serde_json::Value {
+        for _ in 0..50 {
+            let operation: serde_json::Value = client.get(location).send().await.json();
+            if operation["status"] == status {
+                return operation;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("operation never reached {status}");
+    }
+
+    #[tokio::test]
+    async fn test_accepted_then_succeeded() {
+        let operations = Operations::new();
+        let client = TestClient::new(
+            Router::new()
+                .route(
+                    "/exports",
+                    axum::routing::post(|operations: Operations| async move {
+                        operations.start(|operation| async move {
+                            operation.progress(50);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok(serde_json::json!({ "rows": 42 }))
+                        })
+                    }),
+                )
+                .merge(OperationsController::router(operations.clone()))
+                .layer(axum::Extension(operations)),
+        );
+
+        let response = client.post("/exports").send().await;
+        response.assert_status(StatusCode::ACCEPTED);
+        let location = response.header("location").unwrap().to_string();
+        let operation: serde_json::Value = response.json();
+        assert_eq!(location, format!("/operations/{}", operation["id"].as_str().unwrap()));
+
+        let operation = wait_for(&client, &location, "succeeded").await;
+        assert_eq!(operation["progress"], 100);
+        assert_eq!(operation["result"]["rows"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_failure_reported() {
+        let operations = Operations::new();
+        let accepted = operations.start(|_| async {
+            Err::<(), _>(AppError::InternalServerError("disk full".to_string()))
+        });
+        let client = TestClient::new(OperationsController::router(operations));
+
+        let operation = wait_for(&client, &operation_url(accepted.0.id), "failed").await;
+        assert_eq!(operation["error"]["error"], error_codes::OPERATION_FAILED);
+        assert!(!operation["error"]["message"].as_str().unwrap().contains("disk full"));
+        let unknown = client.get(&operation_url(Uuid::new_v4())).send().await;
+        unknown.assert_status(StatusCode::NOT_FOUND);
+        assert_eq!(unknown.json::<serde_json::Value>()["error"], error_codes::OPERATION_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_panic_fails_operation() {
+        let operations = Operations::new();
+        let accepted = operations.start(|_| async {
+            if true {
+                panic!("boom");
+            }
+            Ok(())
+        });
+        let client = TestClient::new(OperationsController::router(operations));
+
+        let operation = wait_for(&client, &operation_url(accepted.0.id), "failed").await;
+        assert_eq!(operation["error"]["error"], error_codes::OPERATION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn test_only_visible_to_owner() {
+        let config = crate::JwtConfig::new("s3cret");
+        let client = crate::EywaApp::new(())
+            .merge(Router::new().route(
+                "/exports",
+                axum::routing::post(|operations: Operations| async move {
+                    operations.start(|_| async { Ok(serde_json::json!({ "rows": 42 })) })
+                }),
+            ))
+            .auth(config.clone())
+            .operations(Operations::new())
+            .into_test_client();
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let alice = config.sign(&serde_json::json!({ "sub": "alice", "exp": exp })).unwrap();
+        let bob = config.sign(&serde_json::json!({ "sub": "bob", "exp": exp })).unwrap();
+
+        let response = client.post("/exports").bearer(&alice).send().await;
+        let location = response.header("location").unwrap().to_string();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let operation: serde_json::Value = client.get(&location).bearer(&alice).send().await.json();
+        assert_eq!(operation["result"]["rows"], 42);
+        let other = client.get(&location).bearer(&bob).send().await;
+        other.assert_status(StatusCode::NOT_FOUND);
+        client.get(&location).send().await.assert_status(StatusCode::NOT_FOUND);
+        let forged = client.get(&location).bearer("forged").send().await;
+        forged.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_finished_operations_expire() {
+        let operations = Operations::new().retention(Duration::ZERO);
+        let first = operations.start(|_| async { Ok(()) });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        operations.start(|_| async { Ok(()) });
+        assert!(operations.get(first.0.id).is_none());
+    }
+}
@@ -0,0 +1,329 @@
+//! GDPR data subject request hooks.
+//!
+//! Controllers register a `DataSubjectHandler` describing how to export and
+//! erase the data they own for a given user. The generated admin endpoints
+//! fan out to every registered handler and report per-handler progress:
+//! - `POST /privacy/export` - Collect all data held for a user
+//! - `POST /privacy/erase` - Erase all data held for a user
+//!
+//! Both endpoints require the `privacy:admin` scope.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use serde::{Deserialize, Serialize};
+use utoipa::{PartialSchema, ToSchema};
+
+use eywa_user_id::UserId;
+
+use crate::Result;
+
+/// Scope required to call the privacy admin endpoints.
+pub const PRIVACY_ADMIN_SCOPE: &str = "privacy:admin";
+
+/// Export and erasure hooks for the data owned by one part of the service.
+///
+/// # Example
+///
+/// ```ignore
+/// struct ProjectsPrivacy { db: Database }
+///
+/// #[async_trait]
+/// impl DataSubjectHandler for ProjectsPrivacy {
+///     fn name(&self) -> &str { "projects" }
+///
+///     async fn export(&self, user_id: &UserId) -> Result<serde_json::Value> {
+///         let projects = Project::find_by_owner(&self.db, user_id).await?;
+///         Ok(serde_json::to_value(projects)?)
+///     }
+///
+///     async fn erase(&self, user_id: &UserId) -> Result<()> {
+///         Project::delete_by_owner(&self.db, user_id).await
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait DataSubjectHandler: Send + Sync + 'static {
+    /// Name identifying this handler in progress reports.
+    fn name(&self) -> &str;
+
+    /// Export all data held for the user.
+    async fn export(&self, user_id: &UserId) -> Result<serde_json::Value>;
+
+    /// Erase all data held for the user.
+    async fn erase(&self, user_id: &UserId) -> Result<()>;
+}
+
+/// Registry of all data subject handlers of the application.
+#[derive(Clone, Default)]
+pub struct PrivacyRegistry {
+    handlers: Vec<Arc<dyn DataSubjectHandler>>,
+}
+
+impl PrivacyRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler.
+    pub fn register(&mut self, handler: Arc<dyn DataSubjectHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Returns `true` if no handler is registered.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    /// Export the user's data from every handler.
+    pub async fn export(&self, user_id: &UserId) -> DataSubjectReport {
+        let total = self.handlers.len();
+        let mut results = Vec::with_capacity(total);
+
+        for (index, handler) in self.handlers.iter().enumerate() {
+            let result = match handler.export(user_id).await {
+                Ok(data) => HandlerResult::completed(handler.name(), Some(data)),
+                Err(e) => HandlerResult::failed(handler.name(), format!("{e:?}")),
+            };
+            tracing::info!(
+                handler = handler.name(),
+                status = ?result.status,
+                "privacy export {}/{}",
+                index + 1,
+                total
+            );
+            results.push(result);
+        }
+
+        DataSubjectReport::new(results)
+    }
+
+    /// Erase the user's data from every handler.
+    pub async fn erase(&self, user_id: &UserId) -> DataSubjectReport {
+        let total = self.handlers.len();
+        let mut results = Vec::with_capacity(total);
+
+        for (index, handler) in self.handlers.iter().enumerate() {
+            let result = match handler.erase(user_id).await {
+                Ok(()) => HandlerResult::completed(handler.name(), None),
+                Err(e) => HandlerResult::failed(handler.name(), format!("{e:?}")),
+            };
+            tracing::info!(
+                handler = handler.name(),
+                status = ?result.status,
+                "privacy erasure {}/{}",
+                index + 1,
+                total
+            );
+            results.push(result);
+        }
+
+        DataSubjectReport::new(results)
+    }
+}
+
+/// Data subject request body
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DataSubjectRequest {
+    pub user_id: UserId,
+}
+
+/// Outcome of a single handler
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum HandlerStatus {
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+/// Result reported by a single handler
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HandlerResult {
+    pub handler: String,
+    pub status: HandlerStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HandlerResult {
+    fn completed(handler: &str, data: Option<serde_json::Value>) -> Self {
+        Self {
+            handler: handler.to_string(),
+            status: HandlerStatus::Completed,
+            data,
+            error: None,
+        }
+    }
+
+    fn failed(handler: &str, error: String) -> Self {
+        Self {
+            handler: handler.to_string(),
+            status: HandlerStatus::Failed,
+            data: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Progress report of a data subject request across all handlers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DataSubjectReport {
+    pub completed: usize,
+    pub total: usize,
+    pub results: Vec<HandlerResult>,
+}
+
+impl DataSubjectReport {
+    fn new(results: Vec<HandlerResult>) -> Self {
+        Self {
+            completed: results
+                .iter()
+                .filter(|r| r.status == HandlerStatus::Completed)
+                .count(),
+            total: results.len(),
+            results,
+        }
+    }
+
+    /// Returns `true` if every handler completed.
+    pub fn is_complete(&self) -> bool {
+        self.completed == self.total
+    }
+}
+
+impl IntoResponse for DataSubjectReport {
+    fn into_response(self) -> axum::response::Response {
+        let status = if self.is_complete() {
+            StatusCode::OK
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+/// Export a user's data
+///
+/// Collects the data held for the user from every registered handler.
+#[utoipa::path(
+    post,
+    path = "/privacy/export",
+    tag = "Privacy",
+    request_body = DataSubjectRequest,
+    responses(
+        (status = 200, description = "All handlers exported", body = DataSubjectReport),
+        (status = 500, description = "At least one handler failed", body = DataSubjectReport)
+    )
+)]
+pub async fn export(
+    State(registry): State<Arc<PrivacyRegistry>>,
+    Json(request): Json<DataSubjectRequest>,
+) -> DataSubjectReport {
+    registry.export(&request.user_id).await
+}
+
+/// Erase a user's data
+///
+/// Erases the data held for the user in every registered handler.
+#[utoipa::path(
+    post,
+    path = "/privacy/erase",
+    tag = "Privacy",
+    request_body = DataSubjectRequest,
+    responses(
+        (status = 200, description = "All handlers erased", body = DataSubjectReport),
+        (status = 500, description = "At least one handler failed", body = DataSubjectReport)
+    )
+)]
+pub async fn erase(
+    State(registry): State<Arc<PrivacyRegistry>>,
+    Json(request): Json<DataSubjectRequest>,
+) -> DataSubjectReport {
+    registry.erase(&request.user_id).await
+}
+
+pub struct PrivacyController;
+
+impl PrivacyController {
+    /// Build the privacy admin router for the given registry.
+    pub fn router<S>(registry: PrivacyRegistry) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/privacy/export", post(export))
+            .route("/privacy/erase", post(erase))
+            .with_state(Arc::new(registry))
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, operation) in [
+            (
+                <__path_export as Path>::path(),
+                <__path_export as Path>::operation(),
+            ),
+            (
+                <__path_erase as Path>::path(),
+                <__path_erase as Path>::operation(),
+            ),
+        ] {
+            paths.paths.insert(
+                path.to_string(),
+                utoipa::openapi::path::PathItem::new(
+                    utoipa::openapi::path::HttpMethod::Post,
+                    operation,
+                ),
+            );
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components.schemas.insert(
+            "DataSubjectRequest".to_string(),
+            DataSubjectRequest::schema(),
+        );
+        components.schemas.insert(
+            "DataSubjectReport".to_string(),
+            DataSubjectReport::schema(),
+        );
+        components
+            .schemas
+            .insert("HandlerResult".to_string(), HandlerResult::schema());
+        components
+            .schemas
+            .insert("HandlerStatus".to_string(), HandlerStatus::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_counts_completed_handlers() {
+        let report = DataSubjectReport::new(vec![
+            HandlerResult::completed("projects", None),
+            HandlerResult::failed("billing", "timeout".to_string()),
+        ]);
+        assert_eq!(report.completed, 1);
+        assert_eq!(report.total, 2);
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn test_handler_result_serialization() {
+        let result = HandlerResult::completed("projects", None);
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(json, r#"{"handler":"projects","status":"completed"}"#);
+    }
+}
@@ -0,0 +1,238 @@
+//! Middleware parameters reloaded without a restart.
+//!
+//! A `Watch<T>` holds the current value of a setting. The config reload
+//! loop of the service sets new values; the middleware reading the handle
+//! applies them to the next request:
+//!
+//! ```ignore
+//! let limits = Watch::new(config.rate_limit.clone());
+//! let deadlines = Watch::new(DeadlineConfig::new().default_budget(config.timeout()));
+//! let origins = Watch::named("cors_origins", config.cors_origins.clone());
+//!
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .rate_limit(RateLimiter::watch(limits.clone()))
+//!     .request_deadlines(deadlines.clone())
+//!     .body_limit(Watch::named("body_limit", config.body_limit))
+//!     .layer(CorsLayer::new().allow_origin(reload::cors_origins(origins.clone())))
+//!
+//! // In the config reload handler
+//! limits.set(new_config.rate_limit.clone());
+//! origins.set(new_config.cors_origins.clone());
+//! ```
+//!
+//! Every change is logged with the setting name and counted by
+//! `eywa_config_reloads_total{setting}`; setting an unchanged value is a
+//! no-op. Values derived from a larger config follow it with `Watch::map`.
+
+use std::any::type_name;
+use std::fmt;
+use std::sync::Arc;
+
+use axum::http::{request::Parts, HeaderValue};
+use tokio::sync::watch;
+use tower_http::cors::AllowOrigin;
+
+/// Shared handle on the current value of a reloadable setting.
+///
+/// Clones share the value: setting it through one handle is seen by all.
+pub struct Watch<T> {
+    name: Arc<str>,
+    sender: Arc<watch::Sender<T>>,
+}
+
+impl<T> Clone for Watch<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Watch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watch")
+            .field("name", &self.name)
+            .field("value", &*self.sender.borrow())
+            .finish()
+    }
+}
+
+/// Last segment of a type name (`DeadlineConfig`).
+fn short_type_name<T>() -> &'static str {
+    let name = type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+impl<T> Watch<T>
+where
+    T: PartialEq + Send + Sync + 'static,
+{
+    /// A setting named after its type.
+    pub fn new(value: T) -> Self {
+        Self::named(short_type_name::<T>(), value)
+    }
+
+    /// A setting with the name used in change logs and metrics.
+    pub fn named(name: impl Into<String>, value: T) -> Self {
+        let (sender, _) = watch::channel(value);
+        Self {
+            name: Arc::from(name.into()),
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Name of the setting.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Read the current value without cloning it.
+    ///
+    /// Don't hold on to the value across an `.await`: `f` runs under a read lock.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.sender.borrow())
+    }
+
+    /// Replace the value, logging the change.
+    ///
+    /// Returns `false` if the value was unchanged.
+    pub fn set(&self, value: T) -> bool {
+        let changed = self.sender.send_if_modified(|current| {
+            if *current == value {
+                return false;
+            }
+            *current = value;
+            true
+        });
+        if changed {
+            tracing::info!(setting = %self.name, "🔁 Setting reloaded");
+            metrics::counter!(
+                "eywa_config_reloads_total",
+                "setting" => self.name.to_string()
+            )
+            .increment(1);
+        }
+        changed
+    }
+
+    /// A receiver notified of every change, for tasks reacting to reloads.
+    pub fn subscribe(&self) -> watch::Receiver<T> {
+        self.sender.subscribe()
+    }
+
+    /// A setting derived from this one, updated whenever this one changes.
+    ///
+    /// Must be called within a Tokio runtime; the derived value stops
+    /// following once every handle on this setting is dropped.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let config = Watch::named("config", ServiceConfig::load()?);
+    /// let limits = config.map("rate_limit", |config| config.rate_limit.clone());
+    /// ```
+    pub fn map<U, F>(&self, name: impl Into<String>, f: F) -> Watch<U>
+    where
+        U: PartialEq + Send + Sync + 'static,
+        F: Fn(&T) -> U + Send + 'static,
+    {
+        let derived = Watch::named(name, self.with(&f));
+        let mut receiver = self.subscribe();
+        let target = derived.clone();
+        tokio::spawn(async move {
+            while receiver.changed().await.is_ok() {
+                let value = f(&receiver.borrow_and_update());
+                target.set(value);
+            }
+        });
+        derived
+    }
+}
+
+impl<T: Clone> Watch<T> {
+    /// A copy of the current value.
+    pub fn get(&self) -> T {
+        self.sender.borrow().clone()
+    }
+}
+
+impl<T> From<T> for Watch<T>
+where
+    T: PartialEq + Send + Sync + 'static,
+{
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+/// CORS origins allowed by the current value of `origins`.
+///
+/// `"*"` allows every origin.
+///
+/// # Example
+/// ```ignore
+/// let origins = Watch::named("cors_origins", vec!["https://app.eywa.dev".to_string()]);
+/// app.layer(CorsLayer::new().allow_origin(reload::cors_origins(origins.clone())))
+/// ```
+pub fn cors_origins(origins: Watch<Vec<String>>) -> AllowOrigin {
+    AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| {
+        origins.with(|allowed| {
+            allowed
+                .iter()
+                .any(|allowed| allowed == "*" || allowed.as_bytes() == origin.as_bytes())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Router};
+    use tower_http::cors::CorsLayer;
+
+    use crate::middleware::deadline::DeadlineConfig;
+    use crate::testing::TestClient;
+
+    #[test]
+    fn test_set_reports_changes() {
+        let limit = Watch::named("body_limit", 1024usize);
+        let clone = limit.clone();
+
+        assert!(clone.set(2048));
+        assert!(!clone.set(2048));
+        assert_eq!(limit.get(), 2048);
+        assert_eq!(Watch::new(DeadlineConfig::new()).name(), "DeadlineConfig");
+    }
+
+    #[tokio::test]
+    async fn test_map_follows_changes() {
+        let config = Watch::named("config", (10u32, "a"));
+        let derived = config.map("first", |config| config.0);
+        let mut changes = derived.subscribe();
+
+        config.set((20, "a"));
+        changes.changed().await.unwrap();
+        assert_eq!(derived.get(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_cors_origins_reload() {
+        let origins = Watch::named("cors_origins", vec!["https://a.example".to_string()]);
+        let client = TestClient::new(
+            Router::new()
+                .route("/", get(|| async { "ok" }))
+                .layer(CorsLayer::new().allow_origin(cors_origins(origins.clone()))),
+        );
+        let response = client.get("/").header("origin", "https://a.example").send().await;
+        response.assert_status(StatusCode::OK);
+        assert!(response.header("access-control-allow-origin").is_some());
+        let response = client.get("/").header("origin", "https://b.example").send().await;
+        assert!(response.header("access-control-allow-origin").is_none());
+
+        origins.set(vec!["https://b.example".to_string()]);
+        let response = client.get("/").header("origin", "https://b.example").send().await;
+        assert_eq!(response.header("access-control-allow-origin"), Some("https://b.example"));
+    }
+}
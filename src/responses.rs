@@ -0,0 +1,193 @@
+//! Reusable response components and global default responses.
+//!
+//! Responses registered here are emitted once under `components.responses`
+//! and referenced (`$ref`) from operations, instead of being repeated inline.
+//! Default responses are added to every operation that doesn't document the
+//! status itself.
+
+use std::collections::BTreeMap;
+
+use utoipa::openapi::{Components, OpenApi, Ref, RefOr, Response};
+
+/// Returns a `$ref` to the response component `name`.
+pub fn response_ref(name: &str) -> RefOr<Response> {
+    RefOr::Ref(Ref::new(format!("#/components/responses/{name}")))
+}
+
+/// Returns `true` if the operation is marked public (empty security requirement).
+pub(crate) fn is_public(operation: &utoipa::openapi::path::Operation) -> bool {
+    operation
+        .security
+        .as_ref()
+        .is_some_and(|requirements| requirements.is_empty())
+}
+
+/// Named response components and the statuses they document by default.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseComponents {
+    components: BTreeMap<String, Response>,
+    /// Status code -> component name
+    defaults: BTreeMap<String, String>,
+}
+
+impl ResponseComponents {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a reusable response under `components.responses.{name}`.
+    pub fn insert(&mut self, name: impl Into<String>, response: Response) {
+        self.components.insert(name.into(), response);
+    }
+
+    /// Document `status` on every operation with the component `name`.
+    pub fn set_default(&mut self, status: impl Into<String>, name: impl Into<String>) {
+        self.defaults.insert(status.into(), name.into());
+    }
+
+    /// Returns `true` if nothing is registered.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.defaults.is_empty()
+    }
+
+    /// Register the components and add the default responses.
+    ///
+    /// Responses already documented on an operation are kept, and public
+    /// operations don't get `401`/`403` defaults. Defaults referring to an
+    /// unregistered component are skipped with a warning.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        if self.is_empty() {
+            return;
+        }
+
+        let components = openapi.components.get_or_insert_with(Components::new);
+        for (name, response) in &self.components {
+            components
+                .responses
+                .insert(name.clone(), RefOr::T(response.clone()));
+        }
+
+        let mut defaults = Vec::new();
+        for (status, name) in &self.defaults {
+            if components.responses.contains_key(name) {
+                defaults.push((status, name));
+            } else {
+                tracing::warn!(
+                    "Default response {} refers to unknown response component `{}`",
+                    status,
+                    name
+                );
+            }
+        }
+
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.delete,
+                &mut item.patch,
+            ];
+            for operation in operations.into_iter().flatten() {
+                let public = is_public(operation);
+                for &(status, name) in &defaults {
+                    if public && matches!(status.as_str(), "401" | "403") {
+                        continue;
+                    }
+                    operation
+                        .responses
+                        .responses
+                        .entry(status.clone())
+                        .or_insert_with(|| response_ref(name));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+    use utoipa::openapi::ResponseBuilder;
+
+    fn spec() -> OpenApi {
+        let mut openapi = OpenApi::default();
+        let documented = OperationBuilder::new()
+            .response("429", ResponseBuilder::new().description("Quota exceeded").build())
+            .build();
+        let mut public = OperationBuilder::new().build();
+        public.security = Some(Vec::new());
+        openapi.paths.paths.insert(
+            "/projects".to_string(),
+            PathItem::new(HttpMethod::Get, documented),
+        );
+        openapi
+            .paths
+            .paths
+            .insert("/status".to_string(), PathItem::new(HttpMethod::Get, public));
+        openapi
+    }
+
+    fn registry() -> ResponseComponents {
+        let mut responses = ResponseComponents::new();
+        responses.insert(
+            "Unauthorized",
+            ResponseBuilder::new().description("Missing or invalid token").build(),
+        );
+        responses.insert(
+            "RateLimited",
+            ResponseBuilder::new().description("Too many requests").build(),
+        );
+        responses.set_default("401", "Unauthorized");
+        responses.set_default("429", "RateLimited");
+        responses
+    }
+
+    #[test]
+    fn test_components_registered_and_referenced() {
+        let mut openapi = spec();
+        registry().apply_to_openapi(&mut openapi);
+
+        let components = openapi.components.as_ref().unwrap();
+        assert!(components.responses.contains_key("Unauthorized"));
+        assert!(components.responses.contains_key("RateLimited"));
+
+        let operation = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        let RefOr::Ref(reference) = &operation.responses.responses["401"] else {
+            panic!("expected response reference");
+        };
+        assert_eq!(reference.ref_location, "#/components/responses/Unauthorized");
+    }
+
+    #[test]
+    fn test_documented_responses_are_kept() {
+        let mut openapi = spec();
+        registry().apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        assert!(matches!(operation.responses.responses["429"], RefOr::T(_)));
+    }
+
+    #[test]
+    fn test_public_routes_skip_auth_defaults() {
+        let mut openapi = spec();
+        registry().apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/status"].get.as_ref().unwrap();
+        assert!(!operation.responses.responses.contains_key("401"));
+        assert!(operation.responses.responses.contains_key("429"));
+    }
+
+    #[test]
+    fn test_unknown_component_is_skipped() {
+        let mut responses = ResponseComponents::new();
+        responses.set_default("503", "Unavailable");
+        let mut openapi = spec();
+        responses.apply_to_openapi(&mut openapi);
+
+        let operation = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        assert!(!operation.responses.responses.contains_key("503"));
+    }
+}
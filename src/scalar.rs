@@ -0,0 +1,214 @@
+//! Scalar "try it" configuration.
+//!
+//! By default the Scalar page at `/scalar` only loads the spec: every request
+//! sent from "try it" targets the docs' own origin, without the headers the
+//! service requires. `ScalarConfig` pre-populates it:
+//!
+//! - Servers per environment, the current environment's selected first
+//! - A fresh `X-Correlation-ID` on every request, unless one is set
+//! - Placeholder values for required custom headers (`X-Tenant-ID`), shown
+//!   as the example of the documented header parameter
+//!
+//! ```ignore
+//! EywaApp::new(state)
+//!     .mount::<ProjectsController>()
+//!     .scalar(
+//!         ScalarConfig::new()
+//!             .server("staging", "https://projects.staging.eywa.dev", "Staging")
+//!             .server("production", "https://projects.eywa.dev", "Production")
+//!             .environment(&config.env)
+//!             .generate_correlation_id()
+//!             .header("X-Tenant-ID", "00000000-0000-0000-0000-000000000000"),
+//!     )
+//! ```
+
+use serde_json::{json, Value};
+use utoipa::openapi::{path::ParameterIn, OpenApi};
+
+/// Header set on every "try it" request by `generate_correlation_id`.
+const CORRELATION_ID_HEADER: &str = "X-Correlation-ID";
+
+/// A server "try it" can send requests to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalarServer {
+    /// Environment the server belongs to (`staging`)
+    pub environment: String,
+    pub url: String,
+    pub description: String,
+}
+
+/// Pre-populated values of the Scalar "try it" client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScalarConfig {
+    servers: Vec<ScalarServer>,
+    environment: Option<String>,
+    correlation_id: bool,
+    headers: Vec<(String, String)>,
+}
+
+impl ScalarConfig {
+    /// Scalar's defaults: the docs' origin, no headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a server of an environment.
+    pub fn server(
+        mut self,
+        environment: impl Into<String>,
+        url: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        self.servers.push(ScalarServer {
+            environment: environment.into(),
+            url: url.into(),
+            description: description.into(),
+        });
+        self
+    }
+
+    /// Select the servers of an environment by default.
+    pub fn environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Send a fresh `X-Correlation-ID` with every request that doesn't set one.
+    pub fn generate_correlation_id(mut self) -> Self {
+        self.correlation_id = true;
+        self
+    }
+
+    /// Pre-fill a header parameter with a placeholder value.
+    ///
+    /// Applies to the operations documenting the header, e.g. through
+    /// `TypedHeader<T>`.
+    pub fn header(mut self, name: impl Into<String>, placeholder: impl Into<String>) -> Self {
+        self.headers.push((name.into(), placeholder.into()));
+        self
+    }
+
+    /// The servers, those of the selected environment first.
+    pub fn servers(&self) -> Vec<&ScalarServer> {
+        let selected = |server: &&ScalarServer| {
+            self.environment
+                .as_deref()
+                .is_some_and(|env| server.environment.eq_ignore_ascii_case(env))
+        };
+        let (mut servers, others): (Vec<_>, Vec<_>) = self.servers.iter().partition(selected);
+        servers.extend(others);
+        servers
+    }
+
+    /// Script creating the Scalar reference for the spec at `spec_url`.
+    pub(crate) fn script(&self, spec_url: &str) -> String {
+        let mut config = json!({ "url": spec_url });
+        if !self.servers.is_empty() {
+            let servers: Vec<Value> = self
+                .servers()
+                .into_iter()
+                .map(|server| json!({ "url": server.url, "description": server.description }))
+                .collect();
+            config["servers"] = Value::Array(servers);
+        }
+        // Keep the JSON from closing the surrounding script element
+        let config = config.to_string().replace("</", "<\\/");
+
+        let mut script = format!("const config = {config};\n");
+        if self.correlation_id {
+            script.push_str(&format!(
+                "config.onBeforeRequest = ({{ request }}) => {{\n  \
+                 if (!request.headers.has('{CORRELATION_ID_HEADER}')) \
+                 request.headers.set('{CORRELATION_ID_HEADER}', crypto.randomUUID());\n}};\n"
+            ));
+        }
+        script.push_str("Scalar.createApiReference('#api-reference', config);");
+        script
+    }
+
+    /// Set the placeholders as the example of the matching header parameters.
+    ///
+    /// Parameters with their own example keep it.
+    pub(crate) fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        if self.headers.is_empty() {
+            return;
+        }
+        let operations = openapi.paths.paths.values_mut().flat_map(|item| {
+            [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.delete,
+                &mut item.patch,
+            ]
+        });
+        for operation in operations.flatten() {
+            for parameter in operation.parameters.iter_mut().flatten() {
+                if parameter.parameter_in != ParameterIn::Header || parameter.example.is_some() {
+                    continue;
+                }
+                let placeholder = self
+                    .headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&parameter.name));
+                if let Some((_, placeholder)) = placeholder {
+                    parameter.example = Some(Value::String(placeholder.clone()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, ParameterBuilder, PathItem};
+
+    #[test]
+    fn test_selected_environment_first() {
+        let config = ScalarConfig::new()
+            .server("staging", "https://staging.example", "Staging")
+            .server("production", "https://example", "Production")
+            .environment("production");
+        let urls: Vec<&str> = config.servers().iter().map(|s| s.url.as_str()).collect();
+        assert_eq!(urls, ["https://example", "https://staging.example"]);
+
+        let script = config.script("/api-docs/openapi.json");
+        let production = script.find("https://example\"").unwrap();
+        assert!(production < script.find("https://staging.example").unwrap());
+        assert!(!script.contains("onBeforeRequest"));
+    }
+
+    #[test]
+    fn test_correlation_id_hook() {
+        let script = ScalarConfig::new()
+            .generate_correlation_id()
+            .script("/api-docs/openapi.json");
+        assert!(script.contains("request.headers.set('X-Correlation-ID', crypto.randomUUID())"));
+        assert!(!script.contains("servers"));
+    }
+
+    #[test]
+    fn test_header_placeholders() {
+        let operation = OperationBuilder::new()
+            .parameter(
+                ParameterBuilder::new()
+                    .name("X-Tenant-ID")
+                    .parameter_in(ParameterIn::Header),
+            )
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/projects".to_string(), PathItem::new(HttpMethod::Get, operation));
+
+        ScalarConfig::new()
+            .header("x-tenant-id", "tenant-placeholder")
+            .apply_to_openapi(&mut openapi);
+
+        let get = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        let parameter = &get.parameters.as_ref().unwrap()[0];
+        assert_eq!(parameter.example, Some(json!("tenant-placeholder")));
+    }
+}
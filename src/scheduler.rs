@@ -0,0 +1,1096 @@
+//! Cron jobs, optionally run once per tick across replicas.
+//!
+//! Every replica of a service runs the same scheduler, so a job purging
+//! expired sessions every hour would run once per replica. Jobs marked
+//! `cluster_singleton` claim each tick in a shared `LeaseStore` first: the
+//! replica winning the claim runs the job, the others skip the tick.
+//!
+//! ```ignore
+//! let purge = Job::new("purge-sessions", "0 0 * * * *", move || purge(db.clone()))?;
+//! let refresh = Job::new("refresh-cache", "0 */5 * * * *", move || refresh(cache.clone()))?;
+//! let scheduler = Scheduler::new()
+//!     .leases(KubernetesLeases::in_cluster()?)
+//!     .job(purge.cluster_singleton())  // once per hour across replicas
+//!     .job(refresh);                   // every 5 minutes on every replica
+//!
+//! EywaApp::new(state).scheduler(scheduler).serve(addr).await
+//! ```
+//!
+//! Expressions have a leading seconds field (`sec min hour day month weekday`)
+//! and are evaluated in UTC. Ticks falling due while a run is still going are
+//! skipped, not queued.
+//!
+//! Each tick is counted in `eywa_scheduler_ticks_total{job, outcome}`:
+//!
+//! | Outcome | Meaning |
+//! |---------|---------|
+//! | `ran` | The job ran and succeeded |
+//! | `failed` | The job ran and returned an error |
+//! | `skipped` | Another replica claimed the tick |
+//! | `paused` | The job is paused on this replica |
+//! | `missed` | A run was still going, the lease store failed or the tick was stale |
+//! | `duplicate` | The tick was claimed under this replica's holder identity already |
+//!
+//! `missed` and `duplicate` deserve an alert: the second means two replicas
+//! share a holder identity.
+//!
+//! Lease stores: `KubernetesLeases` (one `coordination.k8s.io/v1` Lease per
+//! job, claimed with optimistic concurrency) and `MemoryLeases` for tests and
+//! single-instance services.
+//!
+//! Operators manage the jobs through the admin endpoints mounted with
+//! `EywaApp::job_endpoints` and requiring the `jobs:admin` scope:
+//!
+//! - `GET /admin/jobs` - The jobs with their schedule, state and last run
+//! - `POST /admin/jobs/{name}/run` - Run a job now on the replica serving the request
+//! - `POST /admin/jobs/{name}/pause` - Skip the ticks of a job until resumed
+//! - `POST /admin/jobs/{name}/resume` - Run the ticks of a job again
+//! - `GET /admin/jobs/{name}/failures` - The last failed runs of a job
+//!
+//! Pausing applies to the replica serving the request: on the others, the
+//! ticks of a singleton job are claimed and run as usual.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::pin;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use eywa_errors::AppError;
+use futures_util::future::{self, BoxFuture, Either};
+use futures_util::FutureExt;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::{PartialSchema, ToSchema};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::error_codes;
+use crate::error_responses::ErrorResponse;
+use crate::shutdown::Shutdown;
+use crate::Result;
+
+/// Scope required to call the job admin endpoints.
+pub const JOBS_ADMIN_SCOPE: &str = "jobs:admin";
+
+/// Annotation of a Kubernetes Lease holding the last claimed tick.
+pub const TICK_ANNOTATION: &str = "eywa.dev/tick";
+
+/// Directory of the service account mounted in every pod.
+const SERVICE_ACCOUNT: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+
+/// Attempts to claim a Lease modified concurrently.
+const MAX_CLAIM_ATTEMPTS: usize = 3;
+
+/// Failed runs kept per job.
+const MAX_FAILURES: usize = 20;
+
+type JobFn = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// A run of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct JobRun {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// `ran` or `failed`
+    pub outcome: String,
+    /// Started through `POST /admin/jobs/{name}/run` instead of a tick
+    pub manual: bool,
+}
+
+/// A failed run of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct JobFailure {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub error: String,
+    pub manual: bool,
+}
+
+/// A job and its state on this replica.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct JobStatus {
+    pub name: String,
+    /// Cron expression, with seconds, in UTC
+    pub schedule: String,
+    pub cluster_singleton: bool,
+    pub paused: bool,
+    pub running: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<JobRun>,
+    /// Failed runs kept, see `GET /admin/jobs/{name}/failures`
+    pub recent_failures: usize,
+}
+
+/// Runtime state of a job, shared by its clones.
+#[derive(Debug, Default)]
+struct JobState {
+    paused: AtomicBool,
+    running: AtomicBool,
+    last_run: Mutex<Option<JobRun>>,
+    /// Newest first
+    failures: Mutex<VecDeque<JobFailure>>,
+}
+
+/// Marks a job as running until dropped.
+struct Running(Arc<JobState>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+/// A task run on a cron schedule.
+#[derive(Clone)]
+pub struct Job {
+    name: String,
+    expression: String,
+    schedule: cron::Schedule,
+    task: JobFn,
+    cluster_singleton: bool,
+    state: Arc<JobState>,
+}
+
+impl std::fmt::Debug for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Job")
+            .field("name", &self.name)
+            .field("expression", &self.expression)
+            .field("cluster_singleton", &self.cluster_singleton)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl Job {
+    /// Run `task` on the cron `expression` (with seconds, in UTC).
+    pub fn new<F, Fut>(name: impl Into<String>, expression: &str, task: F) -> Result<Self>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let schedule = cron::Schedule::from_str(expression).map_err(|e| {
+            AppError::InternalServerError(format!("Invalid cron expression {expression}: {e}"))
+        })?;
+        Ok(Self {
+            name: name.into(),
+            expression: expression.to_string(),
+            schedule,
+            task: Arc::new(move || task().boxed()),
+            cluster_singleton: false,
+            state: Arc::default(),
+        })
+    }
+
+    /// Run each tick on a single replica, coordinated by the scheduler's leases.
+    pub fn cluster_singleton(mut self) -> Self {
+        self.cluster_singleton = true;
+        self
+    }
+
+    /// Name of the job.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Cron expression of the job.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Whether each tick runs on a single replica.
+    pub fn is_cluster_singleton(&self) -> bool {
+        self.cluster_singleton
+    }
+
+    /// Next tick strictly after `after`.
+    pub fn next_tick(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.after(&after).next()
+    }
+
+    /// Skip the ticks of the job on this replica until resumed.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::Release);
+    }
+
+    /// Run the ticks of the job again.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::Release);
+    }
+
+    /// Returns `true` while the job is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` while a run is going on this replica.
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::Acquire)
+    }
+
+    /// The job and its state on this replica.
+    pub fn status(&self) -> JobStatus {
+        JobStatus {
+            name: self.name.clone(),
+            schedule: self.expression.clone(),
+            cluster_singleton: self.cluster_singleton,
+            paused: self.is_paused(),
+            running: self.is_running(),
+            next_run: self.next_tick(Utc::now()),
+            last_run: self.state.last_run.lock().unwrap().clone(),
+            recent_failures: self.state.failures.lock().unwrap().len(),
+        }
+    }
+
+    /// The last failed runs, newest first.
+    pub fn failures(&self) -> Vec<JobFailure> {
+        self.state.failures.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Mark the job as running, unless a run is already going.
+    fn start(&self) -> Option<Running> {
+        self.state
+            .running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| Running(self.state.clone()))
+    }
+
+    /// Run the task and record the outcome, `ran` or `failed`.
+    async fn execute(&self, _running: Running, manual: bool) -> &'static str {
+        let (started_at, started) = (Utc::now(), Instant::now());
+        let result = (self.task)().await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        metrics::histogram!("eywa_scheduler_job_duration_seconds", "job" => self.name.clone())
+            .record(started.elapsed().as_secs_f64());
+
+        let outcome = match result {
+            Ok(()) => {
+                tracing::info!(job = %self.name, elapsed_ms = duration_ms, manual, "job ran");
+                "ran"
+            }
+            Err(e) => {
+                tracing::error!(job = %self.name, error = ?e, manual, "job failed");
+                let mut failures = self.state.failures.lock().unwrap();
+                failures.push_front(JobFailure {
+                    started_at,
+                    duration_ms,
+                    error: format!("{e:?}"),
+                    manual,
+                });
+                failures.truncate(MAX_FAILURES);
+                "failed"
+            }
+        };
+        *self.state.last_run.lock().unwrap() = Some(JobRun {
+            started_at,
+            duration_ms,
+            outcome: outcome.to_string(),
+            manual,
+        });
+        outcome
+    }
+}
+
+/// Outcome of claiming a tick.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// This replica runs the tick
+    Acquired,
+    /// The tick was already claimed by `holder`
+    Held { holder: String },
+    /// A later tick was already claimed
+    Stale,
+}
+
+/// Shared record of the last tick claimed for each job.
+#[async_trait]
+pub trait LeaseStore: Send + Sync + 'static {
+    /// Claim the run of `job` at `tick` for `holder`.
+    async fn claim(&self, job: &str, tick: DateTime<Utc>, holder: &str) -> Result<Claim>;
+}
+
+/// In-memory leases, for tests and single-instance services.
+#[derive(Debug, Default)]
+pub struct MemoryLeases {
+    ticks: Mutex<HashMap<String, (DateTime<Utc>, String)>>,
+}
+
+impl MemoryLeases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl LeaseStore for MemoryLeases {
+    async fn claim(&self, job: &str, tick: DateTime<Utc>, holder: &str) -> Result<Claim> {
+        let mut ticks = self.ticks.lock().unwrap();
+        match ticks.get(job) {
+            Some((claimed, _)) if *claimed > tick => Ok(Claim::Stale),
+            Some((claimed, owner)) if *claimed == tick => Ok(Claim::Held {
+                holder: owner.clone(),
+            }),
+            _ => {
+                ticks.insert(job.to_string(), (tick, holder.to_string()));
+                Ok(Claim::Acquired)
+            }
+        }
+    }
+}
+
+/// Leases stored as `coordination.k8s.io/v1` Lease objects.
+///
+/// Each job has one Lease (`{prefix}-{job}`) whose `eywa.dev/tick`
+/// annotation holds the last claimed tick. A replica claims a tick by
+/// replacing the Lease at the `resourceVersion` it read, so concurrent
+/// claims of the same tick conflict and only one succeeds. The service
+/// account needs `get`, `create` and `update` on `leases`.
+#[derive(Debug, Clone)]
+pub struct KubernetesLeases {
+    client: reqwest::Client,
+    api_url: String,
+    namespace: String,
+    token: Option<String>,
+    prefix: String,
+}
+
+impl KubernetesLeases {
+    /// Leases in `namespace` of the API server at `api_url`.
+    pub fn new(api_url: impl Into<String>, namespace: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.into().trim_end_matches('/').to_string(),
+            namespace: namespace.into(),
+            token: None,
+            prefix: "eywa-cron".to_string(),
+        }
+    }
+
+    /// Leases in the pod's namespace, authenticated with its service account.
+    pub fn in_cluster() -> Result<Self> {
+        let read = |file: &str| {
+            std::fs::read(format!("{SERVICE_ACCOUNT}/{file}")).map_err(|e| {
+                AppError::InternalServerError(format!("Cannot read service account {file}: {e}"))
+            })
+        };
+        let host = std::env::var("KUBERNETES_SERVICE_HOST").map_err(|_| {
+            AppError::InternalServerError("KUBERNETES_SERVICE_HOST is not set".to_string())
+        })?;
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".to_string());
+        let namespace = String::from_utf8_lossy(&read("namespace")?).trim().to_string();
+        let token = String::from_utf8_lossy(&read("token")?).trim().to_string();
+
+        let ca = reqwest::Certificate::from_pem(&read("ca.crt")?)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid cluster CA: {e}")))?;
+        let client = reqwest::Client::builder()
+            .add_root_certificate(ca)
+            .build()
+            .map_err(|e| AppError::InternalServerError(format!("Invalid API client: {e}")))?;
+
+        Ok(Self::new(format!("https://{host}:{port}"), namespace)
+            .token(token)
+            .client(client))
+    }
+
+    /// Authenticate with a bearer token.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Use another HTTP client.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Prefix of the Lease names (`eywa-cron` by default).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Name of the Lease of `job`, a valid DNS subdomain.
+    fn lease_name(&self, job: &str) -> String {
+        let name: String = format!("{}-{job}", self.prefix)
+            .to_ascii_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' })
+            .collect();
+        name.trim_matches('-').to_string()
+    }
+
+    fn leases_url(&self) -> String {
+        format!(
+            "{}/apis/coordination.k8s.io/v1/namespaces/{}/leases",
+            self.api_url, self.namespace
+        )
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<(StatusCode, Value)> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Lease request failed: {e}")))?;
+        let status = response.status();
+        let body = response.json().await.unwrap_or(Value::Null);
+        let expected = matches!(status, StatusCode::NOT_FOUND | StatusCode::CONFLICT);
+        if status.is_success() || expected {
+            Ok((status, body))
+        } else {
+            Err(AppError::InternalServerError(format!(
+                "Lease request failed with {status}: {}",
+                body["message"].as_str().unwrap_or_default()
+            )))
+        }
+    }
+}
+
+/// Kubernetes `MicroTime` of a tick.
+fn micro_time(tick: DateTime<Utc>) -> String {
+    tick.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[async_trait]
+impl LeaseStore for KubernetesLeases {
+    async fn claim(&self, job: &str, tick: DateTime<Utc>, holder: &str) -> Result<Claim> {
+        let name = self.lease_name(job);
+        let url = format!("{}/{name}", self.leases_url());
+        let lease_spec = json!({
+            "holderIdentity": holder,
+            "acquireTime": micro_time(tick),
+            "renewTime": micro_time(tick),
+        });
+
+        for _ in 0..MAX_CLAIM_ATTEMPTS {
+            let (status, mut lease) = self.send(self.client.get(&url)).await?;
+            if status == StatusCode::NOT_FOUND {
+                let created = json!({
+                    "apiVersion": "coordination.k8s.io/v1",
+                    "kind": "Lease",
+                    "metadata": {
+                        "name": name,
+                        "annotations": { TICK_ANNOTATION: tick.to_rfc3339() },
+                    },
+                    "spec": lease_spec,
+                });
+                let create = self.client.post(self.leases_url()).json(&created);
+                let (status, _) = self.send(create).await?;
+                if status.is_success() {
+                    return Ok(Claim::Acquired);
+                }
+                // Created concurrently: read the winner
+                continue;
+            }
+
+            let claimed = lease["metadata"]["annotations"][TICK_ANNOTATION]
+                .as_str()
+                .and_then(|claimed| DateTime::parse_from_rfc3339(claimed).ok())
+                .map(|claimed| claimed.with_timezone(&Utc));
+            if let Some(claimed) = claimed {
+                if claimed > tick {
+                    return Ok(Claim::Stale);
+                }
+                if claimed == tick {
+                    let holder = lease["spec"]["holderIdentity"].as_str().unwrap_or_default();
+                    return Ok(Claim::Held {
+                        holder: holder.to_string(),
+                    });
+                }
+            }
+
+            // Replace at the read resourceVersion: a concurrent claim conflicts
+            lease["metadata"]["annotations"][TICK_ANNOTATION] = json!(tick.to_rfc3339());
+            for (key, value) in lease_spec.as_object().into_iter().flatten() {
+                lease["spec"][key] = value.clone();
+            }
+            let (status, _) = self.send(self.client.put(&url).json(&lease)).await?;
+            if status.is_success() {
+                return Ok(Claim::Acquired);
+            }
+        }
+        Err(AppError::InternalServerError(format!(
+            "Lease {name} kept changing while claiming it"
+        )))
+    }
+}
+
+/// Identity of this replica in the leases: the pod name, or a random ID.
+fn default_holder() -> String {
+    ["POD_NAME", "HOSTNAME"]
+        .into_iter()
+        .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Cron jobs of a service.
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    leases: Option<Arc<dyn LeaseStore>>,
+    holder: String,
+    clock: SharedClock,
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("jobs", &self.jobs)
+            .field("holder", &self.holder)
+            .finish()
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            jobs: Vec::new(),
+            leases: None,
+            holder: default_holder(),
+            clock: system_clock(),
+        }
+    }
+}
+
+impl Scheduler {
+    /// Create a scheduler without jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a job.
+    pub fn job(mut self, job: Job) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// Coordinate the `cluster_singleton` jobs through `leases`.
+    ///
+    /// Without a store, they are only deduplicated within this process.
+    pub fn leases(mut self, leases: impl LeaseStore) -> Self {
+        self.leases = Some(Arc::new(leases));
+        self
+    }
+
+    /// Identify this replica in the leases (`POD_NAME`, `HOSTNAME` or a
+    /// random ID by default). Must be unique per replica.
+    pub fn holder(mut self, holder: impl Into<String>) -> Self {
+        self.holder = holder.into();
+        self
+    }
+
+    /// Compute the ticks from `clock` instead of the system's.
+    ///
+    /// Set by `EywaApp::clock`.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The registered jobs.
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Run every job in the background until `shutdown` is triggered.
+    pub fn spawn(self, shutdown: Shutdown) {
+        let leases = match self.leases {
+            Some(leases) => leases,
+            None => {
+                if self.jobs.iter().any(Job::is_cluster_singleton) {
+                    tracing::warn!(
+                        "cluster singleton jobs have no lease store and may run on every replica"
+                    );
+                }
+                Arc::new(MemoryLeases::new())
+            }
+        };
+        for job in self.jobs {
+            tracing::info!(job = %job.name, schedule = %job.expression, "⏰ job scheduled");
+            let runner = JobRunner {
+                job,
+                leases: leases.clone(),
+                holder: self.holder.clone(),
+                clock: self.clock.clone(),
+            };
+            tokio::spawn(runner.run(shutdown.clone()));
+        }
+    }
+}
+
+/// Runs the ticks of one job.
+struct JobRunner {
+    job: Job,
+    leases: Arc<dyn LeaseStore>,
+    holder: String,
+    clock: SharedClock,
+}
+
+impl JobRunner {
+    fn count(&self, outcome: &'static str, ticks: u64) {
+        metrics::counter!(
+            "eywa_scheduler_ticks_total",
+            "job" => self.job.name.clone(),
+            "outcome" => outcome
+        )
+        .increment(ticks);
+    }
+
+    async fn run(self, shutdown: Shutdown) {
+        let mut last = self.clock.now();
+        loop {
+            // Ticks that fell due during the previous run are skipped
+            let now = self.clock.now();
+            let missed = self.job.schedule.after(&last).take_while(|tick| *tick <= now).count();
+            if missed > 0 {
+                tracing::warn!(job = %self.job.name, missed, "job ticks missed during a run");
+                self.count("missed", missed as u64);
+            }
+
+            let Some(tick) = self.job.next_tick(now.max(last)) else {
+                return;
+            };
+            // Woken by the clock, so a `TestClock` moved past the tick runs it
+            let sleep = self.clock.sleep_until(tick);
+            if let Either::Right(_) = future::select(sleep, pin!(shutdown.triggered())).await {
+                return;
+            }
+
+            self.tick(tick).await;
+            last = tick;
+        }
+    }
+
+    /// Claim the tick if needed, then run the job.
+    async fn tick(&self, tick: DateTime<Utc>) -> &'static str {
+        let job = &self.job.name;
+        if self.job.is_paused() {
+            tracing::debug!(job = %job, "job paused, skipping tick");
+            self.count("paused", 1);
+            return "paused";
+        }
+        if self.job.cluster_singleton {
+            let outcome = match self.leases.claim(job, tick, &self.holder).await {
+                Ok(Claim::Acquired) => None,
+                Ok(Claim::Held { holder }) if holder == self.holder => {
+                    tracing::error!(job = %job, %holder, "tick already claimed by this holder");
+                    Some("duplicate")
+                }
+                Ok(Claim::Held { holder }) => {
+                    tracing::debug!(job = %job, %holder, "tick claimed by another replica");
+                    Some("skipped")
+                }
+                Ok(Claim::Stale) => Some("missed"),
+                Err(e) => {
+                    tracing::warn!(job = %job, error = ?e, "cannot claim tick, skipping it");
+                    Some("missed")
+                }
+            };
+            if let Some(outcome) = outcome {
+                self.count(outcome, 1);
+                return outcome;
+            }
+        }
+
+        // A manual run may still be going
+        let Some(running) = self.job.start() else {
+            tracing::warn!(job = %job, "job still running, skipping tick");
+            self.count("missed", 1);
+            return "missed";
+        };
+        let outcome = self.job.execute(running, false).await;
+        self.count(outcome, 1);
+        outcome
+    }
+}
+
+/// Jobs of the service, shared with the admin endpoints.
+#[derive(Debug, Clone, Default)]
+pub struct Jobs(Arc<RwLock<Vec<Job>>>);
+
+impl Jobs {
+    /// Create a registry without jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the jobs of a scheduler.
+    pub(crate) fn register(&self, jobs: &[Job]) {
+        self.0.write().unwrap().extend(jobs.iter().cloned());
+    }
+
+    /// The job named `name`.
+    pub fn get(&self, name: &str) -> Result<Job> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .find(|job| job.name == name)
+            .cloned()
+            .ok_or_else(|| AppError::BadRequest(format!("Job {name} not found")))
+    }
+
+    /// Every job with its state on this replica.
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.0.read().unwrap().iter().map(Job::status).collect()
+    }
+}
+
+/// List jobs
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    tag = "Jobs",
+    responses(
+        (status = 200, description = "Jobs and their state on this replica", body = Vec<JobStatus>)
+    )
+)]
+pub async fn list(State(jobs): State<Jobs>) -> Json<Vec<JobStatus>> {
+    Json(jobs.statuses())
+}
+
+/// Run a job now
+///
+/// The job runs in the background on the replica serving the request, even
+/// if paused, without claiming a tick.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{name}/run",
+    tag = "Jobs",
+    params(("name" = String, Path, description = "Job name")),
+    responses(
+        (status = 202, description = "Run started", body = JobStatus),
+        (status = 409, description = "A run of the job is already going")
+    )
+)]
+pub async fn run(State(jobs): State<Jobs>, Path(name): Path<String>) -> Result<Response> {
+    let job = jobs.get(&name)?;
+    let Some(running) = job.start() else {
+        return Ok(
+            ErrorResponse::new(error_codes::CONFLICT, format!("Job {name} is already running"))
+                .into_response_with(StatusCode::CONFLICT),
+        );
+    };
+    tracing::info!(job = %name, "job run requested");
+    let status = job.status();
+    tokio::spawn(async move {
+        job.execute(running, true).await;
+    });
+    Ok((StatusCode::ACCEPTED, Json(status)).into_response())
+}
+
+/// Pause a job
+///
+/// Ticks are skipped on the replica serving the request until the job is
+/// resumed or the process restarts.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{name}/pause",
+    tag = "Jobs",
+    params(("name" = String, Path, description = "Job name")),
+    responses(
+        (status = 200, description = "Job paused", body = JobStatus)
+    )
+)]
+pub async fn pause(
+    State(jobs): State<Jobs>,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>> {
+    let job = jobs.get(&name)?;
+    job.pause();
+    tracing::info!(job = %name, "job paused");
+    Ok(Json(job.status()))
+}
+
+/// Resume a job
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{name}/resume",
+    tag = "Jobs",
+    params(("name" = String, Path, description = "Job name")),
+    responses(
+        (status = 200, description = "Job resumed", body = JobStatus)
+    )
+)]
+pub async fn resume(
+    State(jobs): State<Jobs>,
+    Path(name): Path<String>,
+) -> Result<Json<JobStatus>> {
+    let job = jobs.get(&name)?;
+    job.resume();
+    tracing::info!(job = %name, "job resumed");
+    Ok(Json(job.status()))
+}
+
+/// List the failed runs of a job
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{name}/failures",
+    tag = "Jobs",
+    params(("name" = String, Path, description = "Job name")),
+    responses(
+        (status = 200, description = "Last failed runs on this replica, newest first",
+            body = Vec<JobFailure>)
+    )
+)]
+pub async fn failures(
+    State(jobs): State<Jobs>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<JobFailure>>> {
+    Ok(Json(jobs.get(&name)?.failures()))
+}
+
+/// Job admin endpoints.
+pub struct JobController;
+
+impl JobController {
+    /// Methods and paths of the admin endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 5] = [
+        ("GET", "/admin/jobs"),
+        ("POST", "/admin/jobs/{name}/run"),
+        ("POST", "/admin/jobs/{name}/pause"),
+        ("POST", "/admin/jobs/{name}/resume"),
+        ("GET", "/admin/jobs/{name}/failures"),
+    ];
+
+    /// Build the job admin router for the given registry.
+    pub fn router<S>(jobs: Jobs) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/admin/jobs", get(list))
+            .route("/admin/jobs/{name}/run", post(run))
+            .route("/admin/jobs/{name}/pause", post(pause))
+            .route("/admin/jobs/{name}/resume", post(resume))
+            .route("/admin/jobs/{name}/failures", get(failures))
+            .with_state(jobs)
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_list as Path>::path(),
+                <__path_list as Path>::methods(),
+                <__path_list as Path>::operation(),
+            ),
+            (
+                <__path_run as Path>::path(),
+                <__path_run as Path>::methods(),
+                <__path_run as Path>::operation(),
+            ),
+            (
+                <__path_pause as Path>::path(),
+                <__path_pause as Path>::methods(),
+                <__path_pause as Path>::operation(),
+            ),
+            (
+                <__path_resume as Path>::path(),
+                <__path_resume as Path>::methods(),
+                <__path_resume as Path>::operation(),
+            ),
+            (
+                <__path_failures as Path>::path(),
+                <__path_failures as Path>::methods(),
+                <__path_failures as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        components.schemas.insert("JobStatus".to_string(), JobStatus::schema());
+        components.schemas.insert("JobRun".to_string(), JobRun::schema());
+        components.schemas.insert("JobFailure".to_string(), JobFailure::schema());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::TestClock;
+    use crate::testing::TestClient;
+    use std::sync::atomic::AtomicUsize;
+
+    fn counting_job(runs: Arc<AtomicUsize>) -> Job {
+        Job::new("purge", "0 0 * * * *", move || {
+            let runs = runs.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+        .unwrap()
+        .cluster_singleton()
+    }
+
+    #[test]
+    fn test_invalid_expression_rejected() {
+        assert!(Job::new("purge", "every hour", || async { Ok(()) }).is_err());
+
+        let job = Job::new("purge", "0 0 * * * *", || async { Ok(()) }).unwrap();
+        let noon = "2026-01-01T12:00:00Z".parse().unwrap();
+        assert_eq!(job.next_tick(noon), Some("2026-01-01T13:00:00Z".parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_singleton_tick_runs_once_across_replicas() {
+        let leases: Arc<dyn LeaseStore> = Arc::new(MemoryLeases::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let replica = |holder: &str| JobRunner {
+            job: counting_job(runs.clone()),
+            leases: leases.clone(),
+            holder: holder.to_string(),
+            clock: system_clock(),
+        };
+        let (first, second) = (replica("pod-a"), replica("pod-b"));
+        let tick = "2026-01-01T13:00:00Z".parse().unwrap();
+
+        assert_eq!(first.tick(tick).await, "ran");
+        assert_eq!(second.tick(tick).await, "skipped");
+        assert_eq!(replica("pod-a").tick(tick).await, "duplicate");
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let earlier = "2026-01-01T12:00:00Z".parse().unwrap();
+        assert_eq!(second.tick(earlier).await, "missed");
+        let next = "2026-01-01T14:00:00Z".parse().unwrap();
+        assert_eq!(second.tick(next).await, "ran");
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_lease_names() {
+        let leases = KubernetesLeases::new("https://kubernetes.default.svc/", "billing");
+        assert_eq!(leases.lease_name("Purge_Sessions"), "eywa-cron-purge-sessions");
+        assert_eq!(
+            leases.leases_url(),
+            "https://kubernetes.default.svc/apis/coordination.k8s.io/v1/namespaces/billing/leases"
+        );
+        assert_eq!(
+            micro_time("2026-01-01T13:00:00Z".parse().unwrap()),
+            "2026-01-01T13:00:00.000000Z"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawned_jobs_stop_on_shutdown() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        let every_second = Job::new("tick", "* * * * * *", move || {
+            let runs = counter.clone();
+            async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+        .unwrap();
+        let shutdown = Shutdown::new();
+        Scheduler::new().job(every_second).spawn(shutdown.clone());
+
+        shutdown.trigger();
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_ticks_follow_the_clock() {
+        let clock = TestClock::at("2026-01-01T12:59:59Z".parse().unwrap());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let shutdown = Shutdown::new();
+        Scheduler::new()
+            .clock(Arc::new(clock.clone()))
+            .job(counting_job(runs.clone()))
+            .spawn(shutdown.clone());
+        let ran = |expected: usize| {
+            let runs = runs.clone();
+            tokio::time::timeout(std::time::Duration::from_secs(1), async move {
+                while runs.load(Ordering::SeqCst) < expected {
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+
+        // Hourly job: nothing runs until the clock reaches 13:00
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        clock.advance(std::time::Duration::from_secs(1));
+        ran(1).await.unwrap();
+
+        clock.set("2026-01-01T14:00:00Z".parse().unwrap());
+        ran(2).await.unwrap();
+        shutdown.trigger();
+    }
+
+    #[tokio::test]
+    async fn test_paused_job_skips_ticks() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let runner = JobRunner {
+            job: counting_job(runs.clone()),
+            leases: Arc::new(MemoryLeases::new()),
+            holder: "pod-a".to_string(),
+            clock: system_clock(),
+        };
+        let tick = "2026-01-01T13:00:00Z".parse().unwrap();
+
+        runner.job.pause();
+        assert_eq!(runner.tick(tick).await, "paused");
+        runner.job.resume();
+        assert_eq!(runner.tick(tick).await, "ran");
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_admin_endpoints() {
+        let failing = Job::new("sync", "0 0 * * * *", || async {
+            Err(AppError::InternalServerError("upstream down".to_string()))
+        })
+        .unwrap();
+        let jobs = Jobs::new();
+        jobs.register(&[failing]);
+        let client = TestClient::new(JobController::router(jobs.clone()));
+
+        let listed: Vec<JobStatus> = client.get("/admin/jobs").send().await.json();
+        assert_eq!(listed[0].name, "sync");
+        assert!(listed[0].next_run.is_some());
+
+        let response = client.post("/admin/jobs/sync/pause").send().await;
+        assert!(response.assert_status(StatusCode::OK).json::<JobStatus>().paused);
+        client.post("/admin/jobs/sync/run").send().await.assert_status(StatusCode::ACCEPTED);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let failures: Vec<JobFailure> = client.get("/admin/jobs/sync/failures").send().await.json();
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].manual && failures[0].error.contains("upstream down"));
+        let status = jobs.get("sync").unwrap().status();
+        assert_eq!(status.last_run.unwrap().outcome, "failed");
+        assert!(!status.running);
+
+        // A run of the job is already going
+        let _running = jobs.get("sync").unwrap().start().unwrap();
+        client.post("/admin/jobs/sync/run").send().await.assert_status(StatusCode::CONFLICT);
+        client.post("/admin/jobs/nightly/run").send().await.assert_status(StatusCode::BAD_REQUEST);
+    }
+}
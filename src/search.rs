@@ -0,0 +1,509 @@
+//! Elasticsearch / OpenSearch integration.
+//!
+//! - `SearchClient` - REST client configured from `SearchSettings`, with
+//!   index management (create, delete, alias swaps), document indexing and
+//!   typed searches
+//! - `SearchQuery` - Query parameters of list endpoints (`q`, `filter`,
+//!   `sort`, `page`, `per_page`), translated to the query DSL against an
+//!   allow-list of `SearchFields`
+//! - `EywaApp::search` - Adds the cluster health to `/health/ready`
+//!
+//! ```toml
+//! [search]
+//! url = "http://opensearch:9200"
+//! index_prefix = "projects-"
+//! username = "projects"
+//! password = "..."
+//! ```
+//!
+//! Filter syntax: comma-separated conditions, `field:value` for an exact
+//! match, `field:a|b` for any of several values and `field>=value`,
+//! `field<=value`, `field>value`, `field<value` for ranges. Sort syntax:
+//! comma-separated fields, `-` prefixed for descending order.
+//!
+//! ```text
+//! GET /api/v1/projects?q=billing&filter=status:active|paused,created_at>=2025-01-01
+//!     &sort=-created_at
+//! ```
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use eywa_errors::AppError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::IntoParams;
+
+use crate::health::DatabaseStatus;
+use crate::Result;
+
+/// Default page size of a `SearchQuery`.
+const DEFAULT_PER_PAGE: u64 = 20;
+
+/// Largest page size of a `SearchQuery`.
+const MAX_PER_PAGE: u64 = 100;
+
+/// Search cluster settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSettings {
+    pub url: String,
+    /// Prefix of every index name, e.g. the service or environment
+    #[serde(default)]
+    pub index_prefix: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+fn search_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Search request failed: {e}"))
+}
+
+/// A search hit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hit<T> {
+    pub id: String,
+    pub score: Option<f64>,
+    pub source: T,
+}
+
+/// A page of search results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults<T> {
+    /// Number of matching documents
+    pub total: u64,
+    pub hits: Vec<Hit<T>>,
+}
+
+/// Client of an Elasticsearch or OpenSearch cluster.
+///
+/// # Example
+///
+/// ```ignore
+/// let search = SearchClient::from_settings(&config.search, http.clone());
+/// search.create_index("projects-v2", json!({ "mappings": { ... } })).await?;
+/// search.swap_alias("projects", "projects-v2").await?;
+///
+/// EywaApp::new(state)
+///     .search(search)
+///     .health_checks()
+/// ```
+#[derive(Debug, Clone)]
+pub struct SearchClient {
+    client: reqwest::Client,
+    url: String,
+    index_prefix: String,
+    credentials: Option<(String, String)>,
+    timeout: Duration,
+}
+
+impl SearchClient {
+    /// Client of the cluster at `url`.
+    pub fn new(client: reqwest::Client, url: impl Into<String>) -> Self {
+        Self {
+            client,
+            url: url.into().trim_end_matches('/').to_string(),
+            index_prefix: String::new(),
+            credentials: None,
+            timeout: Duration::from_secs(default_timeout_secs()),
+        }
+    }
+
+    /// Client configured from the settings.
+    pub fn from_settings(settings: &SearchSettings, client: reqwest::Client) -> Self {
+        let mut search = Self::new(client, &settings.url)
+            .index_prefix(&settings.index_prefix)
+            .timeout(Duration::from_secs(settings.timeout_secs));
+        if let Some(username) = &settings.username {
+            search = search.credentials(username, settings.password.clone().unwrap_or_default());
+        }
+        search
+    }
+
+    /// Prefix every index and alias name.
+    pub fn index_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.index_prefix = prefix.into();
+        self
+    }
+
+    /// Authenticate with basic authentication.
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Timeout of each request (10 seconds by default).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Full name of an index or alias.
+    pub fn index_name(&self, name: &str) -> String {
+        format!("{}{name}", self.index_prefix)
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .request(method, format!("{}/{path}", self.url))
+            .timeout(self.timeout);
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+        request
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value> {
+        let response = request.send().await.map_err(search_error)?;
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let reason = body["error"]["reason"].as_str().unwrap_or_default();
+            return Err(search_error(format!("{status} {reason}")));
+        }
+        Ok(body)
+    }
+
+    /// Returns `true` if the index (or alias) exists.
+    pub async fn index_exists(&self, index: &str) -> Result<bool> {
+        let response = self
+            .request(reqwest::Method::HEAD, &self.index_name(index))
+            .send()
+            .await
+            .map_err(search_error)?;
+        Ok(response.status().is_success())
+    }
+
+    /// Create an index with its settings and mappings, if missing.
+    pub async fn create_index(&self, index: &str, definition: Value) -> Result<()> {
+        if self.index_exists(index).await? {
+            return Ok(());
+        }
+        let request = self
+            .request(reqwest::Method::PUT, &self.index_name(index))
+            .json(&definition);
+        self.send(request).await?;
+        tracing::info!(index = %self.index_name(index), "🔎 search index created");
+        Ok(())
+    }
+
+    /// Delete an index.
+    pub async fn delete_index(&self, index: &str) -> Result<()> {
+        self.send(self.request(reqwest::Method::DELETE, &self.index_name(index)))
+            .await?;
+        Ok(())
+    }
+
+    /// Point `alias` to `index` only, atomically.
+    ///
+    /// Reindexing into a new index then swapping the alias read by the
+    /// service changes mappings without downtime.
+    pub async fn swap_alias(&self, alias: &str, index: &str) -> Result<()> {
+        let alias = self.index_name(alias);
+        let actions = json!({
+            "actions": [
+                { "remove": { "index": "*", "alias": alias, "must_exist": false } },
+                { "add": { "index": self.index_name(index), "alias": alias } },
+            ]
+        });
+        self.send(self.request(reqwest::Method::POST, "_aliases").json(&actions))
+            .await?;
+        Ok(())
+    }
+
+    /// Index (create or replace) a document.
+    pub async fn index_document<T>(&self, index: &str, id: &str, document: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let path = format!("{}/_doc/{id}", self.index_name(index));
+        self.send(self.request(reqwest::Method::PUT, &path).json(document))
+            .await?;
+        Ok(())
+    }
+
+    /// Index many documents in one bulk request.
+    pub async fn bulk_index<'a, T, I>(&self, index: &str, documents: I) -> Result<()>
+    where
+        T: Serialize + 'a,
+        I: IntoIterator<Item = (&'a str, &'a T)>,
+    {
+        let index = self.index_name(index);
+        let mut body = String::new();
+        for (id, document) in documents {
+            let action = json!({ "index": { "_index": index, "_id": id } });
+            body.push_str(&action.to_string());
+            body.push('\n');
+            body.push_str(&serde_json::to_string(document).map_err(search_error)?);
+            body.push('\n');
+        }
+        if body.is_empty() {
+            return Ok(());
+        }
+
+        let request = self
+            .request(reqwest::Method::POST, "_bulk")
+            .header("content-type", "application/x-ndjson")
+            .body(body);
+        let response = self.send(request).await?;
+        if response["errors"].as_bool().unwrap_or(false) {
+            return Err(search_error(format!("bulk indexing into {index} partially failed")));
+        }
+        Ok(())
+    }
+
+    /// Delete a document; deleting a missing document succeeds.
+    pub async fn delete_document(&self, index: &str, id: &str) -> Result<()> {
+        let path = format!("{}/_doc/{id}", self.index_name(index));
+        let response = self
+            .request(reqwest::Method::DELETE, &path)
+            .send()
+            .await
+            .map_err(search_error)?;
+        let status = response.status();
+        if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(search_error(status))
+        }
+    }
+
+    /// Make recent changes searchable now (tests, imports).
+    pub async fn refresh(&self, index: &str) -> Result<()> {
+        let path = format!("{}/_refresh", self.index_name(index));
+        self.send(self.request(reqwest::Method::POST, &path)).await?;
+        Ok(())
+    }
+
+    /// Run a query DSL request body against an index.
+    pub async fn search<T>(&self, index: &str, body: &Value) -> Result<SearchResults<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let path = format!("{}/_search", self.index_name(index));
+        let response = self
+            .send(self.request(reqwest::Method::POST, &path).json(body))
+            .await?;
+
+        let hits = response["hits"]["hits"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|hit| {
+                Ok(Hit {
+                    id: hit["_id"].as_str().unwrap_or_default().to_string(),
+                    score: hit["_score"].as_f64(),
+                    source: serde_json::from_value(hit["_source"].clone()).map_err(search_error)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(SearchResults {
+            total: response["hits"]["total"]["value"].as_u64().unwrap_or_default(),
+            hits,
+        })
+    }
+
+    /// Health of the cluster: `Connected` unless it is unreachable or red.
+    pub async fn health(&self) -> DatabaseStatus {
+        match self.send(self.request(reqwest::Method::GET, "_cluster/health")).await {
+            Ok(health) if health["status"] == "red" => {
+                DatabaseStatus::Error("search cluster status is red".to_string())
+            }
+            Ok(_) => DatabaseStatus::Connected,
+            Err(e) => DatabaseStatus::Error(e.to_string()),
+        }
+    }
+}
+
+/// Fields of an index exposed to `SearchQuery`.
+///
+/// Conditions and sorts on other fields are rejected, so clients can't
+/// query internal fields or sort on unindexed text.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFields {
+    text: Vec<String>,
+    filterable: BTreeSet<String>,
+    sortable: BTreeSet<String>,
+}
+
+impl SearchFields {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fields matched by the full-text `q` parameter (`title^2` boosts work).
+    pub fn text<I: IntoIterator<Item = impl Into<String>>>(mut self, fields: I) -> Self {
+        self.text.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Fields usable in `filter`.
+    pub fn filterable<I: IntoIterator<Item = impl Into<String>>>(mut self, fields: I) -> Self {
+        self.filterable.extend(fields.into_iter().map(Into::into));
+        self
+    }
+
+    /// Fields usable in `sort`.
+    pub fn sortable<I: IntoIterator<Item = impl Into<String>>>(mut self, fields: I) -> Self {
+        self.sortable.extend(fields.into_iter().map(Into::into));
+        self
+    }
+}
+
+/// Search, filter, sort and page parameters of a list endpoint
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Full-text search
+    pub q: Option<String>,
+    /// Conditions, e.g. `status:active|paused,created_at>=2025-01-01`
+    pub filter: Option<String>,
+    /// Sort fields, `-` prefixed for descending order, e.g. `-created_at,name`
+    pub sort: Option<String>,
+    /// Page number, starting at 1
+    pub page: Option<u64>,
+    /// Results per page (default 20, at most 100)
+    pub per_page: Option<u64>,
+}
+
+impl SearchQuery {
+    /// Translate to a query DSL request body.
+    ///
+    /// Returns `AppError::BadRequest` for unknown fields or malformed conditions.
+    pub fn to_dsl(&self, fields: &SearchFields) -> Result<Value> {
+        let mut must = Vec::new();
+        if let Some(q) = self.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            must.push(json!({ "multi_match": { "query": q, "fields": fields.text } }));
+        }
+
+        let mut filters = Vec::new();
+        for condition in self.filter.iter().flat_map(|filter| filter.split(',')) {
+            let condition = condition.trim();
+            if !condition.is_empty() {
+                filters.push(filter_clause(condition, fields)?);
+            }
+        }
+
+        let mut sort = Vec::new();
+        for field in self.sort.iter().flat_map(|sort| sort.split(',')) {
+            let field = field.trim();
+            let (field, order) = match field.strip_prefix('-') {
+                Some(field) => (field, "desc"),
+                None => (field, "asc"),
+            };
+            if field.is_empty() {
+                continue;
+            }
+            if !fields.sortable.contains(field) {
+                return Err(AppError::BadRequest(format!("Cannot sort by '{field}'")));
+            }
+            sort.push(json!({ field: { "order": order } }));
+        }
+
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        let page = self.page.unwrap_or(1).max(1);
+        Ok(json!({
+            "query": { "bool": { "must": must, "filter": filters } },
+            "sort": sort,
+            "from": (page - 1) * per_page,
+            "size": per_page,
+            "track_total_hits": true,
+        }))
+    }
+}
+
+/// Translate one `filter` condition.
+fn filter_clause(condition: &str, fields: &SearchFields) -> Result<Value> {
+    const RANGES: [(&str, &str); 4] = [(">=", "gte"), ("<=", "lte"), (">", "gt"), ("<", "lt")];
+
+    let operator = condition
+        .find([':', '>', '<'])
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid filter condition '{condition}'")))?;
+    let (field, rest) = condition.split_at(operator);
+    if !fields.filterable.contains(field) {
+        return Err(AppError::BadRequest(format!("Cannot filter by '{field}'")));
+    }
+
+    if let Some(values) = rest.strip_prefix(':') {
+        let values: Vec<&str> = values.split('|').collect();
+        return Ok(match values.as_slice() {
+            [value] => json!({ "term": { field: value } }),
+            values => json!({ "terms": { field: values } }),
+        });
+    }
+    let (range, value) = RANGES
+        .into_iter()
+        .find_map(|(operator, range)| Some((range, rest.strip_prefix(operator)?)))
+        .ok_or_else(|| AppError::BadRequest(format!("Invalid filter condition '{condition}'")))?;
+    Ok(json!({ "range": { field: { range: value } } }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> SearchFields {
+        SearchFields::new()
+            .text(["name^2", "description"])
+            .filterable(["status", "created_at"])
+            .sortable(["created_at", "name"])
+    }
+
+    fn query(filter: &str, sort: &str) -> SearchQuery {
+        SearchQuery {
+            filter: Some(filter.to_string()),
+            sort: Some(sort.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_translated() {
+        let dsl = SearchQuery {
+            q: Some("billing".to_string()),
+            page: Some(3),
+            per_page: Some(500),
+            ..query("status:active|paused,created_at>=2025-01-01", "-created_at,name")
+        }
+        .to_dsl(&fields())
+        .unwrap();
+
+        assert_eq!(
+            dsl["query"]["bool"]["must"][0]["multi_match"],
+            json!({ "query": "billing", "fields": ["name^2", "description"] })
+        );
+        assert_eq!(
+            dsl["query"]["bool"]["filter"],
+            json!([
+                { "terms": { "status": ["active", "paused"] } },
+                { "range": { "created_at": { "gte": "2025-01-01" } } },
+            ])
+        );
+        assert_eq!(
+            dsl["sort"],
+            json!([{ "created_at": { "order": "desc" } }, { "name": { "order": "asc" } }])
+        );
+        assert_eq!((dsl["from"].as_u64(), dsl["size"].as_u64()), (Some(200), Some(100)));
+    }
+
+    #[test]
+    fn test_unknown_fields_rejected() {
+        assert!(query("owner_id:1", "").to_dsl(&fields()).is_err());
+        assert!(query("status", "").to_dsl(&fields()).is_err());
+        assert!(query("", "-description").to_dsl(&fields()).is_err());
+
+        let dsl = query("status:active", "").to_dsl(&fields()).unwrap();
+        assert_eq!(dsl["query"]["bool"]["filter"][0], json!({ "term": { "status": "active" } }));
+        assert_eq!(dsl["from"], 0);
+    }
+}
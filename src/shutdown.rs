@@ -0,0 +1,304 @@
+//! Graceful shutdown and draining of long-lived connections.
+//!
+//! On `SIGTERM` or Ctrl-C, `EywaApp::serve` stops accepting connections and
+//! waits for in-flight requests to finish. Server-sent event streams and
+//! WebSockets never finish on their own, so they are told to close:
+//!
+//! - `Shutdown::sse` ends an event stream with a final `shutdown` event
+//!   carrying a `retry` hint, so `EventSource` clients reconnect to another
+//!   replica
+//! - WebSocket handlers wait on `Shutdown::triggered` and send a `1001 Going
+//!   Away` close frame with `close_for_shutdown`
+//!
+//! Streams still open after the grace period (`EywaApp::shutdown_grace`,
+//! 30 seconds by default) are cut.
+//!
+//! ```ignore
+//! async fn events(shutdown: Shutdown, State(state): State<AppState>) -> impl IntoResponse {
+//!     Sse::new(shutdown.sse(state.events.subscribe()))
+//! }
+//!
+//! async fn chat(upgrade: WebSocketUpgrade, shutdown: Shutdown) -> Response {
+//!     upgrade.on_upgrade(move |mut socket| async move {
+//!         let _stream = shutdown.track();
+//!         loop {
+//!             tokio::select! {
+//!                 message = socket.recv() => { /* ... */ }
+//!                 () = shutdown.triggered() => {
+//!                     let _ = close_for_shutdown(&mut socket).await;
+//!                     break;
+//!                 }
+//!             }
+//!         }
+//!     })
+//! }
+//! ```
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket},
+        FromRequestParts,
+    },
+    http::request::Parts,
+    response::sse::Event,
+};
+use futures_util::{future, stream, Stream, StreamExt};
+use tokio::sync::watch;
+
+use eywa_errors::AppError;
+
+/// Default time given to open connections to close after a shutdown signal.
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(30);
+
+/// Name of the final event of the streams closed by a shutdown.
+pub const SHUTDOWN_EVENT: &str = "shutdown";
+
+/// Reconnection delay suggested to `EventSource` clients by the final event.
+pub const RECONNECT_AFTER: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Inner {
+    triggered: watch::Sender<bool>,
+    streams: watch::Sender<usize>,
+}
+
+/// Shutdown notification shared by the server and the open streams.
+///
+/// Extract it in handlers, or get it from `EywaApp::shutdown_handle` for
+/// background tasks.
+#[derive(Debug, Clone)]
+pub struct Shutdown(Arc<Inner>);
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self(Arc::new(Inner {
+            triggered: watch::Sender::new(false),
+            streams: watch::Sender::new(0),
+        }))
+    }
+}
+
+impl Shutdown {
+    /// Create a handle that hasn't been triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the shutdown: open streams are told to close.
+    pub fn trigger(&self) {
+        self.0.triggered.send_replace(true);
+    }
+
+    /// Returns `true` once the shutdown has started.
+    pub fn is_triggered(&self) -> bool {
+        *self.0.triggered.borrow()
+    }
+
+    /// Wait for the shutdown to start.
+    pub async fn triggered(&self) {
+        let mut triggered = self.0.triggered.subscribe();
+        // The sender lives as long as `self`
+        let _ = triggered.wait_for(|triggered| *triggered).await;
+    }
+
+    /// Count a long-lived connection as open until the guard is dropped.
+    pub fn track(&self) -> StreamGuard {
+        self.0.streams.send_modify(|streams| *streams += 1);
+        StreamGuard(self.0.clone())
+    }
+
+    /// Number of tracked connections still open.
+    pub fn open_streams(&self) -> usize {
+        *self.0.streams.borrow()
+    }
+
+    /// Wait up to `grace` for the tracked connections to close.
+    ///
+    /// Returns `false` if some are still open.
+    pub async fn drain(&self, grace: Duration) -> bool {
+        let mut streams = self.0.streams.subscribe();
+        let drained = streams.wait_for(|streams| *streams == 0);
+        matches!(tokio::time::timeout(grace, drained).await, Ok(Ok(_)))
+    }
+
+    /// Wait for the shutdown to start, then for `grace` to elapse.
+    pub async fn grace_elapsed(&self, grace: Duration) {
+        self.triggered().await;
+        tokio::time::sleep(grace).await;
+    }
+
+    /// Forward an event stream until the shutdown starts, then end it with
+    /// a `shutdown` event telling the client to reconnect.
+    ///
+    /// The stream is tracked while open.
+    pub fn sse<S, E>(&self, events: S) -> impl Stream<Item = Result<Event, E>> + Send + 'static
+    where
+        S: Stream<Item = Result<Event, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let guard = self.track();
+        let (until, closing) = (self.clone(), self.clone());
+        let last = stream::once(async move {
+            closing.is_triggered().then(|| {
+                Ok(Event::default()
+                    .event(SHUTDOWN_EVENT)
+                    .retry(RECONNECT_AFTER)
+                    .data("reconnect"))
+            })
+        })
+        .filter_map(future::ready);
+
+        events
+            .take_until(async move { until.triggered().await })
+            .chain(last)
+            .map(move |event| {
+                // Closed with the stream
+                let _ = &guard;
+                event
+            })
+    }
+}
+
+impl<S> FromRequestParts<S> for Shutdown
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts.extensions.get::<Self>().cloned().ok_or_else(|| {
+            AppError::InternalServerError(
+                "Shutdown is only available in routers built by EywaApp".to_string(),
+            )
+        })
+    }
+}
+
+/// Counts a long-lived connection as open, see `Shutdown::track`.
+#[derive(Debug)]
+pub struct StreamGuard(Arc<Inner>);
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.0.streams.send_modify(|streams| *streams = streams.saturating_sub(1));
+    }
+}
+
+/// Close a WebSocket with `1001 Going Away`, telling the client to reconnect.
+pub async fn close_for_shutdown(socket: &mut WebSocket) -> Result<(), axum::Error> {
+    socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::AWAY,
+            reason: "Server shutting down".into(),
+        })))
+        .await
+}
+
+/// Wait for `SIGTERM` (on Unix) or Ctrl-C, then trigger `shutdown`.
+pub async fn trigger_on_signal(shutdown: Shutdown) {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    future::select(pin!(ctrl_c), pin!(terminate)).await;
+    tracing::info!("🛑 Shutdown signal received, draining connections");
+    shutdown.trigger();
+}
+
+/// Run `server` until it stops, or until the grace period after the
+/// shutdown started has elapsed.
+pub(crate) async fn serve_until_drained<F, T>(
+    server: F,
+    shutdown: &Shutdown,
+    grace: Duration,
+) -> Option<T>
+where
+    F: Future<Output = T>,
+{
+    match future::select(pin!(server), pin!(shutdown.grace_elapsed(grace))).await {
+        future::Either::Left((output, _)) => Some(output),
+        future::Either::Right(_) => {
+            tracing::warn!(
+                open_streams = shutdown.open_streams(),
+                grace_secs = grace.as_secs(),
+                "shutdown grace period elapsed, closing the remaining connections"
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{response::sse::Sse, routing::get, Router};
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_streams() {
+        let shutdown = Shutdown::new();
+        let guard = shutdown.track();
+        assert_eq!(shutdown.open_streams(), 1);
+        assert!(!shutdown.drain(Duration::from_millis(10)).await);
+
+        shutdown.trigger();
+        shutdown.triggered().await;
+        drop(guard);
+        assert!(shutdown.drain(Duration::from_millis(10)).await);
+    }
+
+    #[tokio::test]
+    async fn test_sse_ends_with_reconnect_event() {
+        let shutdown = Shutdown::new();
+        let handle = shutdown.clone();
+        let client = TestClient::new(Router::new().route(
+            "/events",
+            get(move || async move {
+                let tick = Ok::<_, Infallible>(Event::default().data("tick"));
+                let ticks = stream::once(async { tick }).chain(stream::pending());
+                Sse::new(handle.sse(ticks))
+            }),
+        ));
+
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            trigger.trigger();
+        });
+        let body = client.get("/events").send().await.text();
+
+        assert!(body.starts_with("data: tick\n\n"));
+        assert!(body.contains("event: shutdown\n"));
+        assert!(body.contains("retry: 1000\n"));
+        assert!(shutdown.drain(Duration::from_millis(10)).await);
+    }
+
+    #[tokio::test]
+    async fn test_grace_period_cuts_server() {
+        let shutdown = Shutdown::new();
+        shutdown.trigger();
+        let cut = serve_until_drained(future::pending::<()>(), &shutdown, Duration::ZERO).await;
+        assert!(cut.is_none());
+        let done = serve_until_drained(async { 42 }, &shutdown, DEFAULT_SHUTDOWN_GRACE).await;
+        assert_eq!(done, Some(42));
+    }
+}
@@ -0,0 +1,504 @@
+//! OpenAPI spec assembly and the lazily cached docs endpoints.
+//!
+//! `SpecBuilder` holds everything the specs are assembled from (info, tags,
+//! controller schemas and paths, route metadata). `EywaApp` fills it while
+//! mounting controllers; `build()` then hands it to `LazySpecs`, which
+//! assembles each document on its first request and caches the serialized
+//! JSON/YAML bytes with an ETag, shared by every docs endpoint.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock};
+
+use axum::{
+    body::Bytes,
+    extract::Path,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tracing::info;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Components, Info, OpenApi, Tag};
+
+use crate::envelope::ResponseEnvelope;
+use crate::error_codes::ErrorCatalog;
+use crate::error_responses::ErrorResponses;
+use crate::middleware::content_types::ContentTypePolicy;
+use crate::middleware::headers::StaticHeaderRegistry;
+use crate::middleware::methods::AutoMethods;
+use crate::middleware::scopes::{apply_auth_requirements, ScopeRegistry};
+use crate::operation_ids::OperationIdStrategy;
+use crate::responses::ResponseComponents;
+use crate::scalar::ScalarConfig;
+use crate::tags::TagLayout;
+use crate::traits::{RouteAuth, RouteErrors, RouteRequestHeaders, RouteValidation};
+use crate::visibility::RouteSet;
+
+/// Default path the spec documents are served under.
+pub const DOCS_PATH: &str = "/api-docs";
+/// URL of the public spec (JSON) under the default path.
+pub const SPEC_JSON_URL: &str = "/api-docs/openapi.json";
+/// URL of the public spec (YAML).
+pub const SPEC_YAML_URL: &str = "/api-docs/openapi.yaml";
+/// URL of the internal spec (JSON).
+pub const INTERNAL_SPEC_JSON_URL: &str = "/api-docs/internal/openapi.json";
+
+pub(crate) type SchemaFn = Box<dyn Fn(&mut Components) + Send + Sync>;
+pub(crate) type PathFn = Box<dyn Fn(&mut OpenApi) + Send + Sync>;
+
+/// Everything the OpenAPI specs are assembled from.
+#[derive(Default)]
+pub(crate) struct SpecBuilder {
+    pub(crate) info: Option<Info>,
+    pub(crate) tags: Vec<Tag>,
+    pub(crate) tag_layout: TagLayout,
+    pub(crate) schema_fns: Vec<SchemaFn>,
+    pub(crate) path_fns: Vec<PathFn>,
+    pub(crate) route_auth: Vec<RouteAuth>,
+    pub(crate) scopes: ScopeRegistry,
+    pub(crate) static_headers: StaticHeaderRegistry,
+    pub(crate) validated_routes: Vec<RouteValidation>,
+    pub(crate) responses: ResponseComponents,
+    pub(crate) error_responses: Option<ErrorResponses>,
+    pub(crate) route_errors: Vec<RouteErrors>,
+    pub(crate) error_codes: Option<ErrorCatalog>,
+    pub(crate) retry_after_statuses: BTreeSet<u16>,
+    pub(crate) hidden: RouteSet,
+    pub(crate) internal: RouteSet,
+    pub(crate) maps_rejections: bool,
+    pub(crate) envelope: Option<Arc<dyn ResponseEnvelope>>,
+    pub(crate) operation_ids: Option<OperationIdStrategy>,
+    pub(crate) auto_methods: Option<AutoMethods>,
+    pub(crate) content_types: Option<ContentTypePolicy>,
+    pub(crate) enforces_constraints: bool,
+    pub(crate) request_headers: Vec<RouteRequestHeaders>,
+    pub(crate) scalar: ScalarConfig,
+    pub(crate) docs_path: Option<String>,
+}
+
+impl SpecBuilder {
+    /// Path the spec documents are served under, `/api-docs` by default.
+    pub(crate) fn docs_path(&self) -> &str {
+        self.docs_path.as_deref().unwrap_or(DOCS_PATH)
+    }
+
+    /// Assemble the public spec (without internal routes).
+    pub(crate) fn public(&self) -> OpenApi {
+        let mut openapi = self.assemble();
+        self.internal.remove_from_openapi(&mut openapi);
+        self.apply_auto_methods(&mut openapi);
+        openapi
+    }
+
+    /// Assemble the internal spec (every route that isn't hidden).
+    pub(crate) fn internal(&self) -> OpenApi {
+        let mut openapi = self.assemble();
+        self.apply_auto_methods(&mut openapi);
+        openapi
+    }
+
+    /// Document the automatic `HEAD` and `OPTIONS` operations, once the
+    /// other operations of each path are final.
+    fn apply_auto_methods(&self, openapi: &mut OpenApi) {
+        if let Some(methods) = &self.auto_methods {
+            crate::middleware::methods::apply_to_openapi(openapi, methods);
+        }
+    }
+
+    fn assemble(&self) -> OpenApi {
+        let mut openapi = OpenApi::default();
+
+        // Apply custom info if provided
+        if let Some(info) = self.info.clone() {
+            openapi.info = info;
+        }
+
+        // Add tags, ordered and grouped
+        if !self.tags.is_empty() {
+            openapi.tags = Some(self.tags.clone());
+        }
+        self.tag_layout.apply_to_openapi(&mut openapi);
+
+        // Add schemas and security scheme to components
+        let mut components = openapi.components.unwrap_or_else(Components::new);
+
+        // Add bearer security scheme
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("JWT Bearer token"))
+                    .build(),
+            ),
+        );
+
+        // Add custom schemas
+        for schema_fn in &self.schema_fns {
+            schema_fn(&mut components);
+        }
+
+        openapi.components = Some(components);
+
+        // Add collected paths
+        for path_fn in &self.path_fns {
+            path_fn(&mut openapi);
+        }
+
+        // Drop hidden routes before anything else is derived from the paths
+        self.hidden.remove_from_openapi(&mut openapi);
+
+        // Rename operations with the configured strategy
+        if let Some(strategy) = &self.operation_ids {
+            strategy.apply_to_openapi(&mut openapi);
+        }
+
+        // Document per-route authentication, then required scopes
+        apply_auth_requirements(&mut openapi, &self.route_auth);
+        self.scopes.apply_to_openapi(&mut openapi);
+
+        // Document static response headers
+        self.static_headers.apply_to_openapi(&mut openapi);
+
+        // Document validation failures of routes using validating extractors
+        crate::validation::apply_to_openapi(&mut openapi, &self.validated_routes);
+
+        // Document the typed request headers
+        crate::typed_header::apply_to_openapi(&mut openapi, &self.request_headers);
+
+        // Pre-fill header placeholders for the Scalar "try it" client
+        self.scalar.apply_to_openapi(&mut openapi);
+
+        // Register reusable responses and apply the default responses
+        self.responses.apply_to_openapi(&mut openapi);
+
+        // Document the standard error responses
+        if let Some(errors) = &self.error_responses {
+            let mut errors = errors.clone();
+            for route in self.route_errors.iter().cloned() {
+                errors.insert(route);
+            }
+            errors.apply_to_openapi(&mut openapi);
+        }
+
+        // Document load shedding and maintenance responses with their Retry-After
+        for &status in &self.retry_after_statuses {
+            crate::error_responses::document_status(&mut openapi, status);
+        }
+
+        // Document the error code catalog and its endpoint
+        if let Some(catalog) = &self.error_codes {
+            catalog.apply_to_openapi(&mut openapi);
+        }
+
+        // Document mapped extractor rejections
+        if self.maps_rejections {
+            crate::middleware::rejection::apply_to_openapi(&mut openapi);
+        }
+
+        // Document the media type rejections
+        if let Some(policy) = &self.content_types {
+            crate::middleware::content_types::apply_to_openapi(&mut openapi, policy);
+        }
+
+        // Document the schema constraint violations
+        if self.enforces_constraints {
+            crate::middleware::constraints::apply_to_openapi(&mut openapi);
+        }
+
+        // Wrap successful response schemas in the configured envelope
+        if let Some(envelope) = &self.envelope {
+            crate::envelope::apply_to_openapi(&mut openapi, envelope.as_ref());
+        }
+
+        openapi
+    }
+}
+
+/// An assembled spec with its serialized forms.
+pub struct SpecDocument {
+    openapi: OpenApi,
+    json: Bytes,
+    yaml: Bytes,
+    etag: HeaderValue,
+}
+
+impl SpecDocument {
+    /// Serialize a spec and compute its ETag.
+    pub fn new(openapi: OpenApi) -> Self {
+        let json = serde_json::to_vec(&openapi).unwrap_or_default();
+        let yaml = serde_yaml::to_string(&openapi).unwrap_or_default();
+
+        let mut hasher = DefaultHasher::new();
+        json.hash(&mut hasher);
+        let etag = HeaderValue::try_from(format!("\"{:016x}\"", hasher.finish()))
+            .unwrap_or_else(|_| HeaderValue::from_static("\"0\""));
+
+        Self {
+            openapi,
+            json: Bytes::from(json),
+            yaml: Bytes::from(yaml),
+            etag,
+        }
+    }
+
+    /// The assembled spec.
+    pub fn openapi(&self) -> &OpenApi {
+        &self.openapi
+    }
+
+    /// The spec serialized as JSON.
+    pub fn json(&self) -> &Bytes {
+        &self.json
+    }
+
+    /// The spec serialized as YAML.
+    pub fn yaml(&self) -> &Bytes {
+        &self.yaml
+    }
+
+    /// Strong ETag of the serialized spec.
+    pub fn etag(&self) -> &HeaderValue {
+        &self.etag
+    }
+
+    /// Respond with the JSON or YAML bytes, or `304` if the client's copy is current.
+    fn respond(&self, request: &HeaderMap, yaml: bool) -> Response {
+        let not_modified = request
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|tag| tag.trim() == "*" || tag.trim().as_bytes() == self.etag.as_bytes())
+            });
+        if not_modified {
+            return (StatusCode::NOT_MODIFIED, [(header::ETAG, self.etag.clone())]).into_response();
+        }
+
+        let (content_type, body) = if yaml {
+            ("application/yaml", self.yaml.clone())
+        } else {
+            ("application/json", self.json.clone())
+        };
+        (
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+                (header::ETAG, self.etag.clone()),
+            ],
+            body,
+        )
+            .into_response()
+    }
+}
+
+/// Specs assembled on first use and cached for the lifetime of the app.
+pub(crate) struct LazySpecs {
+    builder: SpecBuilder,
+    public: OnceLock<SpecDocument>,
+    internal: OnceLock<SpecDocument>,
+    versions: OnceLock<BTreeMap<u32, SpecDocument>>,
+}
+
+impl LazySpecs {
+    pub(crate) fn new(builder: SpecBuilder) -> Self {
+        Self {
+            builder,
+            public: OnceLock::new(),
+            internal: OnceLock::new(),
+            versions: OnceLock::new(),
+        }
+    }
+
+    /// The public spec, served at `/scalar`.
+    pub(crate) fn public(&self) -> &SpecDocument {
+        self.public.get_or_init(|| {
+            let openapi = self.builder.public();
+            info!("📚 OpenAPI spec assembled ({} paths)", openapi.paths.paths.len());
+            SpecDocument::new(openapi)
+        })
+    }
+
+    /// Configuration of the Scalar pages.
+    pub(crate) fn scalar(&self) -> &ScalarConfig {
+        &self.builder.scalar
+    }
+
+    /// Path the spec documents are served under.
+    pub(crate) fn docs_path(&self) -> &str {
+        self.builder.docs_path()
+    }
+
+    /// The internal spec, including internal routes.
+    pub(crate) fn internal(&self) -> &SpecDocument {
+        self.internal
+            .get_or_init(|| SpecDocument::new(self.builder.internal()))
+    }
+
+    /// The public spec of a single API version.
+    pub(crate) fn version(&self, version: u32) -> Option<&SpecDocument> {
+        self.versions
+            .get_or_init(|| {
+                crate::versioning::split_by_version(self.public().openapi())
+                    .into_iter()
+                    .map(|(version, openapi)| (version, SpecDocument::new(openapi)))
+                    .collect()
+            })
+            .get(&version)
+    }
+}
+
+/// Scalar page loading the spec from `spec_url`, configured by `config`.
+pub(crate) fn scalar_page(spec_url: &str, config: &ScalarConfig) -> Html<String> {
+    let script = config.script(spec_url);
+    Html(format!(
+        r#"<!doctype html>
+<html>
+  <head>
+    <title>API Reference</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <div id="api-reference"></div>
+    <script src="https://cdn.jsdelivr.net/npm/@scalar/api-reference"></script>
+    <script>
+{script}
+    </script>
+  </body>
+</html>"#
+    ))
+}
+
+/// Public docs routes: `/scalar`, the JSON/YAML spec and the per-version specs.
+pub(crate) fn docs_router<S>(specs: Arc<LazySpecs>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let base = specs.docs_path().to_string();
+    let json_url = format!("{base}/openapi.json");
+    let page = scalar_page(&json_url, specs.scalar());
+    let json = specs.clone();
+    let yaml = specs.clone();
+    let versions = specs;
+    Router::new()
+        .route("/scalar", get(move || async move { page }))
+        .route(
+            &json_url,
+            get(move |headers: HeaderMap| async move { json.public().respond(&headers, false) }),
+        )
+        .route(
+            &format!("{base}/openapi.yaml"),
+            get(move |headers: HeaderMap| async move { yaml.public().respond(&headers, true) }),
+        )
+        .route(
+            &format!("{base}/{{version}}/openapi.json"),
+            get(move |Path(version): Path<String>, headers: HeaderMap| async move {
+                version
+                    .strip_prefix('v')
+                    .and_then(|n| n.parse().ok())
+                    .and_then(|n| versions.version(n))
+                    .map(|document| document.respond(&headers, false))
+                    .unwrap_or_else(|| StatusCode::NOT_FOUND.into_response())
+            }),
+        )
+}
+
+/// Internal docs routes: `/scalar/internal` and the internal JSON spec.
+pub(crate) fn internal_docs_router<S>(specs: Arc<LazySpecs>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let json_url = format!("{}/internal/openapi.json", specs.docs_path());
+    let page = scalar_page(&json_url, specs.scalar());
+    Router::new()
+        .route("/scalar/internal", get(move || async move { page }))
+        .route(
+            &json_url,
+            get(move |headers: HeaderMap| async move {
+                specs.internal().respond(&headers, false)
+            }),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn specs() -> Arc<LazySpecs> {
+        let mut builder = SpecBuilder::default();
+        builder.path_fns.push(Box::new(|openapi: &mut OpenApi| {
+            for path in ["/api/v1/projects", "/api/v1/admin/users"] {
+                openapi.paths.paths.insert(
+                    path.to_string(),
+                    PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+                );
+            }
+        }));
+        builder.internal.insert_pattern("/api/v1/admin/**");
+        Arc::new(LazySpecs::new(builder))
+    }
+
+    #[test]
+    fn test_specs_are_assembled_lazily() {
+        let specs = specs();
+        assert!(specs.public.get().is_none());
+
+        let public = specs.public();
+        assert_eq!(public.openapi().paths.paths.len(), 1);
+        assert_eq!(specs.internal().openapi().paths.paths.len(), 2);
+        assert!(specs.version(1).is_some());
+        assert!(specs.version(2).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_spec_endpoint_supports_etag() {
+        let client = TestClient::new(docs_router::<()>(specs()));
+
+        let response = client.get(SPEC_JSON_URL).send().await;
+        response.assert_status(StatusCode::OK);
+        let etag = response.header("etag").expect("etag header").to_string();
+
+        client
+            .get(SPEC_JSON_URL)
+            .header("if-none-match", &etag)
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED);
+
+        client
+            .get(SPEC_YAML_URL)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        client
+            .get("/api-docs/v1/openapi.json")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_docs_path_configurable() {
+        let mut builder = SpecBuilder::default();
+        builder.docs_path = Some("/openapi".to_string());
+        let client = TestClient::new(docs_router::<()>(Arc::new(LazySpecs::new(builder))));
+
+        let response = client.get("/openapi/openapi.json").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        let response = client.get("/openapi/openapi.yaml").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.header("content-type"), Some("application/yaml"));
+        assert!(response.text().starts_with("openapi:"));
+
+        assert!(client.get("/scalar").send().await.text().contains("/openapi/openapi.json"));
+        client.get(SPEC_JSON_URL).send().await.assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,121 @@
+//! Startup summary of what a service actually started with.
+//!
+//! `EywaApp::serve` logs one structured event once the listener is bound,
+//! instead of a series of free-form lines:
+//!
+//! ```json
+//! {"target":"eywa_axum::startup","message":"Service started","service":"projects",
+//!  "version":"1.4.0","env":"production","address":"0.0.0.0:3000","tls":false,
+//!  "features":"scalar,api-keys","middleware":"request_context,rate_limit",
+//!  "routes":12,"docs":"http://0.0.0.0:3000/scalar,http://0.0.0.0:3000/api-docs/openapi.json"}
+//! ```
+//!
+//! With JSON log output every field is a top-level key, so deployment tooling
+//! can assert on it (e.g. that `rate_limit` is in `middleware` in production).
+//! Lists are comma-separated. `EywaApp::startup_summary` returns the same
+//! summary for tests.
+
+use serde::Serialize;
+
+/// Log target of the startup summary event.
+pub const STARTUP_TARGET: &str = "eywa_axum::startup";
+
+/// What a service started with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StartupSummary {
+    /// Service name, from the observability settings or the API title
+    pub service: Option<String>,
+    /// API version set with `EywaApp::info`
+    pub version: Option<String>,
+    /// Deployment environment, from the observability settings or `RUN_MODE`
+    pub env: String,
+    /// Address the listener is bound to
+    pub address: String,
+    /// Whether TLS is terminated by the service (`serve_tls`)
+    pub tls: bool,
+    /// Cargo features of `eywa-axum` compiled in
+    pub features: Vec<&'static str>,
+    /// Middleware enabled on the app, in the order they were added
+    pub middleware: Vec<&'static str>,
+    /// Number of routes mounted from controllers
+    pub routes: usize,
+    /// URLs of the documentation endpoints
+    pub docs: Vec<String>,
+}
+
+impl StartupSummary {
+    /// Log the summary as one event with the `eywa_axum::startup` target.
+    pub fn log(&self) {
+        tracing::info!(
+            target: STARTUP_TARGET,
+            service = self.service.as_deref().unwrap_or_default(),
+            version = self.version.as_deref().unwrap_or_default(),
+            env = %self.env,
+            address = %self.address,
+            tls = self.tls,
+            features = %self.features.join(","),
+            middleware = %self.middleware.join(","),
+            routes = self.routes,
+            docs = %self.docs.join(","),
+            "Service started"
+        );
+    }
+}
+
+/// Cargo features of `eywa-axum` compiled in.
+pub fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("scalar", cfg!(feature = "scalar")),
+        ("swagger-ui", cfg!(feature = "swagger-ui")),
+        ("cedar", cfg!(feature = "cedar")),
+        ("sqlite", cfg!(feature = "sqlite")),
+        ("mock-db", cfg!(feature = "mock-db")),
+        ("redis", cfg!(feature = "redis")),
+        ("pprof", cfg!(feature = "pprof")),
+        ("pprof-heap", cfg!(feature = "pprof-heap")),
+        ("console", cfg!(feature = "console")),
+        ("api-keys", cfg!(feature = "api-keys")),
+        ("migrations", cfg!(feature = "migrations")),
+        ("testcontainers", cfg!(feature = "testcontainers")),
+        ("tls", cfg!(feature = "tls")),
+        ("otel", cfg!(feature = "otel")),
+        ("scaffold", cfg!(feature = "scaffold")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::rate_limit::{RateLimitSettings, RateLimiter};
+    use crate::EywaApp;
+
+    #[test]
+    fn test_enabled_features() {
+        let features = enabled_features();
+        assert_eq!(features.contains(&"scalar"), cfg!(feature = "scalar"));
+        assert_eq!(features.contains(&"api-keys"), cfg!(feature = "api-keys"));
+    }
+
+    #[test]
+    fn test_app_summary() {
+        let summary = EywaApp::new(())
+            .info("projects", "1.4.0", "Projects API")
+            .request_context()
+            .rate_limit(RateLimiter::new(RateLimitSettings::default()))
+            .request_context()
+            .health_checks()
+            .startup_summary("0.0.0.0:3000");
+
+        assert_eq!(summary.service.as_deref(), Some("projects"));
+        assert_eq!(summary.version.as_deref(), Some("1.4.0"));
+        assert_eq!(summary.middleware, ["request_context", "rate_limit"]);
+        assert_eq!(summary.routes, 0);
+        assert!(summary.docs.contains(&"http://0.0.0.0:3000/scalar".to_string()));
+        assert!(summary.docs.contains(&"http://0.0.0.0:3000/health".to_string()));
+    }
+}
@@ -0,0 +1,323 @@
+//! Standard wiring of the resources every service's state is built from.
+//!
+//! `AppStateBuilder` creates the metrics registry, the outbound HTTP client,
+//! the database connection, the Redis connection (`redis` feature) and the
+//! `JwtService` from config, always in that order. The first failure is
+//! logged and returned as `<resource> initialization failed: <cause>`.
+//!
+//! ```toml
+//! [resources.database]
+//! url = "postgres://localhost/projects"
+//!
+//! [resources.redis]
+//! url = "redis://localhost:6379"
+//!
+//! [resources.http]
+//! timeout_secs = 10
+//!
+//! [resources.jwt]
+//! secret = "..."
+//! ```
+
+use std::sync::Once;
+use std::time::Duration;
+
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use eywa_authentication::JwtService;
+use eywa_errors::AppError;
+
+use crate::database::DatabaseSettings;
+use crate::Result;
+
+static METRICS: Once = Once::new();
+
+/// Install the metrics registry once per process.
+pub(crate) fn init_metrics() {
+    METRICS.call_once(eywa_metrics::init_metrics);
+}
+
+/// Redis connection settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisSettings {
+    pub url: String,
+}
+
+/// Outbound HTTP client settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpClientSettings {
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    5
+}
+
+impl Default for HttpClientSettings {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_timeout_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            user_agent: None,
+        }
+    }
+}
+
+/// JWT signing settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtSettings {
+    pub secret: String,
+}
+
+/// Resource settings, embeddable in a service's `EywaConfig`.
+///
+/// Resources without settings are skipped; the HTTP client is always built.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceSettings {
+    #[serde(default)]
+    pub database: Option<DatabaseSettings>,
+    #[serde(default)]
+    pub redis: Option<RedisSettings>,
+    #[serde(default)]
+    pub http: HttpClientSettings,
+    #[serde(default)]
+    pub jwt: Option<JwtSettings>,
+    /// Install the Prometheus metrics registry (default `true`)
+    #[serde(default = "default_metrics")]
+    pub metrics: bool,
+}
+
+fn default_metrics() -> bool {
+    true
+}
+
+impl Default for ResourceSettings {
+    fn default() -> Self {
+        Self {
+            database: None,
+            redis: None,
+            http: HttpClientSettings::default(),
+            jwt: None,
+            metrics: default_metrics(),
+        }
+    }
+}
+
+/// Resources created by `AppStateBuilder`.
+#[derive(Clone)]
+pub struct Resources {
+    pub database: Option<DatabaseConnection>,
+    #[cfg(feature = "redis")]
+    pub redis: Option<redis::aio::ConnectionManager>,
+    pub http: reqwest::Client,
+    pub jwt: Option<JwtService>,
+}
+
+impl Resources {
+    /// The database connection, or an error if it isn't configured.
+    pub fn require_database(&self) -> Result<DatabaseConnection> {
+        self.database
+            .clone()
+            .ok_or_else(|| not_configured("database"))
+    }
+
+    /// The Redis connection, or an error if it isn't configured.
+    #[cfg(feature = "redis")]
+    pub fn require_redis(&self) -> Result<redis::aio::ConnectionManager> {
+        self.redis.clone().ok_or_else(|| not_configured("redis"))
+    }
+
+    /// The JWT service, or an error if it isn't configured.
+    pub fn require_jwt(&self) -> Result<JwtService> {
+        self.jwt.clone().ok_or_else(|| not_configured("jwt"))
+    }
+}
+
+fn not_configured(resource: &str) -> AppError {
+    AppError::InternalServerError(format!("{resource} is not configured"))
+}
+
+fn init_error(resource: &str, e: impl std::fmt::Display) -> AppError {
+    error!("❌ {} initialization failed: {}", resource, e);
+    AppError::InternalServerError(format!("{resource} initialization failed: {e}"))
+}
+
+/// Builds the common resources of an application state from config.
+///
+/// # Example
+///
+/// ```ignore
+/// let config: MyAppConfig = EywaConfig::load()?;
+/// let resources = AppStateBuilder::new(config.resources.clone()).build().await?;
+///
+/// let state = AppState {
+///     db: resources.require_database()?,
+///     jwt: resources.require_jwt()?,
+///     http: resources.http,
+///     config,
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AppStateBuilder {
+    settings: ResourceSettings,
+}
+
+impl AppStateBuilder {
+    /// Create a builder from the resource settings.
+    pub fn new(settings: ResourceSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Override the database settings (e.g. `DatabaseSettings::in_memory()` in tests).
+    pub fn database(mut self, settings: DatabaseSettings) -> Self {
+        self.settings.database = Some(settings);
+        self
+    }
+
+    /// Override the Redis settings.
+    pub fn redis(mut self, settings: RedisSettings) -> Self {
+        self.settings.redis = Some(settings);
+        self
+    }
+
+    /// Override the HTTP client settings.
+    pub fn http(mut self, settings: HttpClientSettings) -> Self {
+        self.settings.http = settings;
+        self
+    }
+
+    /// Override the JWT settings.
+    pub fn jwt(mut self, settings: JwtSettings) -> Self {
+        self.settings.jwt = Some(settings);
+        self
+    }
+
+    /// Skip installing the metrics registry.
+    pub fn without_metrics(mut self) -> Self {
+        self.settings.metrics = false;
+        self
+    }
+
+    /// Create the resources, stopping at the first failure.
+    pub async fn build(self) -> Result<Resources> {
+        let settings = self.settings;
+
+        if settings.metrics {
+            init_metrics();
+            info!("📈 Metrics registry installed");
+        }
+
+        let http = build_http_client(&settings.http).map_err(|e| init_error("http client", e))?;
+        info!("🌐 HTTP client ready");
+
+        let database = match &settings.database {
+            Some(database) => {
+                let connection = database
+                    .connect()
+                    .await
+                    .map_err(|e| init_error("database", e))?;
+                info!("🗄️ Database connected ({:?})", database.mode());
+                Some(connection)
+            }
+            None => None,
+        };
+
+        #[cfg(feature = "redis")]
+        let redis = match &settings.redis {
+            Some(redis) => {
+                let client = redis::Client::open(redis.url.as_str())
+                    .map_err(|e| init_error("redis", e))?;
+                let connection = client
+                    .get_connection_manager()
+                    .await
+                    .map_err(|e| init_error("redis", e))?;
+                info!("🧠 Redis connected");
+                Some(connection)
+            }
+            None => None,
+        };
+        #[cfg(not(feature = "redis"))]
+        if settings.redis.is_some() {
+            return Err(init_error("redis", "requires the `redis` feature"));
+        }
+
+        let jwt = settings.jwt.as_ref().map(|jwt| JwtService::new(&jwt.secret));
+        if jwt.is_some() {
+            info!("🔑 JWT service ready");
+        }
+
+        Ok(Resources {
+            database,
+            #[cfg(feature = "redis")]
+            redis,
+            http,
+            jwt,
+        })
+    }
+}
+
+fn build_http_client(settings: &HttpClientSettings) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_secs))
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs));
+    if let Some(user_agent) = &settings.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_defaults() {
+        let settings: ResourceSettings =
+            serde_json::from_str(r#"{"database":{"url":"postgres://localhost/db"}}"#).unwrap();
+
+        assert!(settings.metrics);
+        assert!(settings.redis.is_none());
+        assert_eq!(settings.http.timeout_secs, 30);
+        assert_eq!(settings.http.connect_timeout_secs, 5);
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_resources_are_skipped() {
+        let resources = AppStateBuilder::default()
+            .without_metrics()
+            .build()
+            .await
+            .unwrap();
+
+        assert!(resources.database.is_none());
+        assert!(resources.require_jwt().is_err());
+    }
+
+    #[cfg(not(feature = "redis"))]
+    #[tokio::test]
+    async fn test_failures_name_the_resource() {
+        let result = AppStateBuilder::default()
+            .without_metrics()
+            .redis(RedisSettings {
+                url: "redis://localhost:6379".to_string(),
+            })
+            .build()
+            .await;
+
+        let Err(AppError::InternalServerError(message)) = result else {
+            panic!("expected an initialization error");
+        };
+        assert_eq!(message, "redis initialization failed: requires the `redis` feature");
+    }
+}
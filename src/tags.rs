@@ -0,0 +1,203 @@
+//! Tag ordering and tag groups.
+//!
+//! Tags are listed in mount order by default. `TagLayout` reorders them,
+//! after the explicit order then the controllers' `tag_order` hints, and
+//! emits the `x-tagGroups` vendor extension, which Scalar and Redoc use to
+//! group the sidebar by domain.
+
+use serde_json::{json, Value};
+use utoipa::openapi::extensions::ExtensionsBuilder;
+use utoipa::openapi::tag::TagBuilder;
+use utoipa::openapi::OpenApi;
+
+/// Vendor extension holding the tag groups.
+pub const TAG_GROUPS_EXTENSION: &str = "x-tagGroups";
+
+/// Group collecting the tags that aren't part of any declared group.
+pub const UNGROUPED_TAGS: &str = "Other";
+
+/// Explicit tag order and tag groups of the spec.
+#[derive(Debug, Clone, Default)]
+pub struct TagLayout {
+    order: Vec<String>,
+    positions: Vec<(String, u32)>,
+    groups: Vec<(String, Vec<String>)>,
+}
+
+impl TagLayout {
+    /// Create an empty layout (mount order, no groups).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List these tags first, in this order.
+    pub fn set_order(&mut self, tags: impl IntoIterator<Item = impl Into<String>>) {
+        self.order = tags.into_iter().map(Into::into).collect();
+    }
+
+    /// Rank a tag by an ordering hint, lowest first, after the explicit order.
+    pub fn set_position(&mut self, tag: impl Into<String>, position: u32) {
+        let tag = tag.into();
+        self.positions.retain(|(existing, _)| *existing != tag);
+        self.positions.push((tag, position));
+    }
+
+    /// Add (or replace) a named group of tags.
+    pub fn add_group(
+        &mut self,
+        name: impl Into<String>,
+        tags: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        let name = name.into();
+        let tags = tags.into_iter().map(Into::into).collect();
+        match self.groups.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = tags,
+            None => self.groups.push((name, tags)),
+        }
+    }
+
+    /// Returns `true` if neither an order nor groups are configured.
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty() && self.positions.is_empty() && self.groups.is_empty()
+    }
+
+    fn is_grouped(&self, tag: &str) -> bool {
+        self.groups.iter().any(|(_, tags)| tags.iter().any(|name| name == tag))
+    }
+
+    /// Position of a tag: explicit order first, then ordering hints, then
+    /// group order, then mount order.
+    fn rank(&self, tag: &str) -> (usize, usize) {
+        if let Some(index) = self.order.iter().position(|name| name == tag) {
+            return (0, index);
+        }
+        if let Some((_, position)) = self.positions.iter().find(|(name, _)| name == tag) {
+            return (1, *position as usize);
+        }
+        let grouped = self.groups.iter().flat_map(|(_, tags)| tags);
+        match grouped.enumerate().find(|(_, name)| *name == tag) {
+            Some((index, _)) => (2, index),
+            None => (3, 0),
+        }
+    }
+
+    /// Reorder the spec's tags and emit `x-tagGroups`.
+    ///
+    /// Tags referenced by a group but not yet declared are added. When groups
+    /// are configured, the remaining tags are collected into an `Other` group
+    /// so viewers honoring `x-tagGroups` still show them.
+    pub fn apply_to_openapi(&self, openapi: &mut OpenApi) {
+        if self.is_empty() {
+            return;
+        }
+
+        let mut tags = openapi.tags.take().unwrap_or_default();
+        for (_, group) in &self.groups {
+            for name in group {
+                if !tags.iter().any(|tag| tag.name == *name) {
+                    tags.push(TagBuilder::new().name(name.clone()).build());
+                }
+            }
+        }
+        // Stable sort keeps mount order among unranked tags
+        tags.sort_by_key(|tag| self.rank(&tag.name));
+
+        if !self.groups.is_empty() {
+            let mut groups: Vec<Value> = self
+                .groups
+                .iter()
+                .map(|(name, tags)| json!({ "name": name, "tags": tags }))
+                .collect();
+            let ungrouped: Vec<&str> = tags
+                .iter()
+                .map(|tag| tag.name.as_str())
+                .filter(|name| !self.is_grouped(name))
+                .collect();
+            if !ungrouped.is_empty() {
+                groups.push(json!({ "name": UNGROUPED_TAGS, "tags": ungrouped }));
+            }
+
+            let extension = ExtensionsBuilder::new()
+                .add(TAG_GROUPS_EXTENSION, Value::Array(groups))
+                .build();
+            match openapi.extensions.as_mut() {
+                Some(extensions) => extensions.merge(extension),
+                None => openapi.extensions = Some(extension),
+            }
+        }
+
+        openapi.tags = Some(tags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> OpenApi {
+        let mut openapi = OpenApi::default();
+        openapi.tags = Some(
+            ["Health", "Users", "Billing", "Projects"]
+                .into_iter()
+                .map(|name| TagBuilder::new().name(name).build())
+                .collect(),
+        );
+        openapi
+    }
+
+    fn names(openapi: &OpenApi) -> Vec<String> {
+        openapi
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| tag.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_explicit_order() {
+        let mut layout = TagLayout::new();
+        layout.set_order(["Projects", "Users"]);
+        let mut openapi = spec();
+        layout.apply_to_openapi(&mut openapi);
+
+        assert_eq!(names(&openapi), ["Projects", "Users", "Health", "Billing"]);
+        assert!(openapi.extensions.is_none());
+    }
+
+    #[test]
+    fn test_positions_follow_explicit_order() {
+        let mut layout = TagLayout::new();
+        layout.set_order(["Health"]);
+        layout.set_position("Projects", 2);
+        layout.set_position("Billing", 1);
+        layout.set_position("Health", 0);
+        let mut openapi = spec();
+        layout.apply_to_openapi(&mut openapi);
+
+        assert_eq!(names(&openapi), ["Health", "Billing", "Projects", "Users"]);
+    }
+
+    #[test]
+    fn test_tag_groups() {
+        let mut layout = TagLayout::new();
+        layout.add_group("Core", ["Projects", "Users"]);
+        layout.add_group("Finance", ["Billing", "Invoices"]);
+        let mut openapi = spec();
+        layout.apply_to_openapi(&mut openapi);
+
+        assert_eq!(
+            names(&openapi),
+            ["Projects", "Users", "Billing", "Invoices", "Health"]
+        );
+        let value = serde_json::to_value(&openapi).unwrap();
+        assert_eq!(
+            value[TAG_GROUPS_EXTENSION],
+            json!([
+                { "name": "Core", "tags": ["Projects", "Users"] },
+                { "name": "Finance", "tags": ["Billing", "Invoices"] },
+                { "name": "Other", "tags": ["Health"] },
+            ])
+        );
+    }
+}
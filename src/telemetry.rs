@@ -0,0 +1,448 @@
+//! Tracing subscriber setup.
+//!
+//! `init_tracing` installs the standard stack (env filter and fmt layer)
+//! from config, so services don't hand-assemble it. The tokio-console layer
+//! (`console` feature) is toggled from the same config:
+//!
+//! ```toml
+//! [telemetry]
+//! filter = "info,sqlx=warn"   # overridden by RUST_LOG
+//!
+//! [telemetry.console]
+//! enabled = true
+//! bind = "0.0.0.0:6669"
+//! ```
+//!
+//! tokio only emits the runtime instrumentation read by the console when the
+//! service is built with `RUSTFLAGS="--cfg tokio_unstable"`.
+//!
+//! Every log line emitted within a request (access log, handlers, services)
+//! ends with the request's `correlation_id`, `request_id` and `trace_id`
+//! (plus `user_id` with `EywaApp::auth`, `canary` when a canary serves it,
+//! and `service` and `env` with `EywaApp::observability`), so a Loki query
+//! by correlation ID returns the handler's own logs too. The ids come from
+//! the request span opened by `request_context_middleware_fn`;
+//! `CorrelationLayer` and `CorrelatedFormat` add them to subscribers that
+//! aren't installed with `init_tracing`.
+//!
+//! The filter installed by `init_tracing` can be changed at runtime through
+//! the returned `LogLevel` handle, e.g. from `EywaApp::log_level_endpoints`.
+
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+use eywa_errors::AppError;
+
+use crate::Result;
+
+/// Default address of the tokio-console server.
+pub const DEFAULT_CONSOLE_BIND: &str = "127.0.0.1:6669";
+
+/// tokio-console settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsoleSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_console_bind")]
+    pub bind: String,
+}
+
+fn default_console_bind() -> String {
+    DEFAULT_CONSOLE_BIND.to_string()
+}
+
+impl Default for ConsoleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: default_console_bind(),
+        }
+    }
+}
+
+/// Tracing settings, embeddable in a service's `EywaConfig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetrySettings {
+    /// `EnvFilter` directives used when `RUST_LOG` isn't set
+    #[serde(default = "default_filter")]
+    pub filter: String,
+    #[serde(default)]
+    pub console: ConsoleSettings,
+}
+
+fn default_filter() -> String {
+    "info".to_string()
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            filter: default_filter(),
+            console: ConsoleSettings::default(),
+        }
+    }
+}
+
+pub(crate) fn telemetry_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Tracing initialization failed: {e}"))
+}
+
+type Reload = dyn Fn(EnvFilter) -> std::result::Result<(), reload::Error> + Send + Sync;
+
+/// Handle changing the log filter at runtime, returned by `init_tracing`.
+#[derive(Clone)]
+pub struct LogLevel {
+    filter: Arc<Mutex<String>>,
+    reload: Arc<Reload>,
+}
+
+impl LogLevel {
+    /// Wrap the handle of a reloadable `EnvFilter`, for subscribers not
+    /// installed with `init_tracing`.
+    pub fn new<S: 'static>(
+        handle: reload::Handle<EnvFilter, S>,
+        filter: impl Into<String>,
+    ) -> Self {
+        Self {
+            filter: Arc::new(Mutex::new(filter.into())),
+            reload: Arc::new(move |filter| handle.reload(filter)),
+        }
+    }
+
+    /// The current `EnvFilter` directives.
+    pub fn filter(&self) -> String {
+        self.filter.lock().unwrap().clone()
+    }
+
+    /// Replace the filter with new `EnvFilter` directives, e.g. `debug,sqlx=warn`.
+    ///
+    /// Invalid directives are rejected and the current filter is kept.
+    pub fn set(&self, filter: &str) -> Result<()> {
+        let parsed = EnvFilter::try_new(filter)
+            .map_err(|e| AppError::BadRequest(format!("Invalid log filter '{filter}': {e}")))?;
+        (self.reload)(parsed).map_err(|e| {
+            AppError::InternalServerError(format!("Log filter change failed: {e}"))
+        })?;
+
+        let previous = std::mem::replace(&mut *self.filter.lock().unwrap(), filter.to_string());
+        tracing::warn!(from = %previous, to = %filter, "log filter changed");
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogLevel")
+            .field("filter", &self.filter())
+            .finish()
+    }
+}
+
+/// Install the global tracing subscriber.
+///
+/// Fails if a global subscriber is already installed. Returns the handle
+/// changing the log filter at runtime.
+///
+/// # Example
+///
+/// ```ignore
+/// let config: MyAppConfig = EywaConfig::load()?;
+/// let log_level = eywa_axum::telemetry::init_tracing(&config.telemetry)?;
+/// ```
+pub fn init_tracing(settings: &TelemetrySettings) -> Result<LogLevel> {
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&settings.filter).map_err(telemetry_error)?,
+    };
+    let directives = filter.to_string();
+    let (filter, handle) = reload::Layer::new(filter);
+
+    // The filter only applies to the log output: the console needs the
+    // runtime's trace-level events whatever the log level
+    let fmt = tracing_subscriber::fmt::layer()
+        .event_format(CorrelatedFormat::new(tracing_subscriber::fmt::format()));
+    tracing_subscriber::registry()
+        .with(CorrelationLayer)
+        .with(fmt.with_filter(filter))
+        .with(console_layer(&settings.console)?)
+        .try_init()
+        .map_err(telemetry_error)?;
+
+    // Warn through the subscriber now that it's installed
+    if settings.console.enabled {
+        if !cfg!(feature = "console") {
+            tracing::warn!(
+                "tokio-console enabled, but eywa-axum was built without the `console` feature"
+            );
+        } else if !cfg!(tokio_unstable) {
+            tracing::warn!(
+                "tokio-console enabled, but tokio_unstable isn't set: no runtime data is recorded"
+            );
+        } else {
+            tracing::info!("🔍 tokio-console listening on {}", settings.console.bind);
+        }
+    }
+    Ok(LogLevel::new(handle, directives))
+}
+
+#[cfg(feature = "console")]
+fn console_layer<S>(settings: &ConsoleSettings) -> Result<Option<impl Layer<S>>>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use tracing_subscriber::filter::{LevelFilter, Targets};
+
+    if !settings.enabled {
+        return Ok(None);
+    }
+    let addr: std::net::SocketAddr = settings.bind.parse().map_err(telemetry_error)?;
+    let targets = Targets::new()
+        .with_target("tokio", LevelFilter::TRACE)
+        .with_target("runtime", LevelFilter::TRACE);
+    Ok(Some(
+        console_subscriber::ConsoleLayer::builder()
+            .server_addr(addr)
+            .spawn()
+            .with_filter(targets),
+    ))
+}
+
+#[cfg(not(feature = "console"))]
+fn console_layer<S>(
+    _settings: &ConsoleSettings,
+) -> Result<Option<tracing_subscriber::layer::Identity>>
+where
+    S: tracing::Subscriber,
+{
+    Ok(None)
+}
+
+/// Span fields copied onto every event emitted within the span.
+pub const CORRELATION_FIELDS: [&str; 7] =
+    ["service", "env", "correlation_id", "request_id", "trace_id", "user_id", "canary"];
+
+/// Correlation fields recorded on a span.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CorrelationIds(pub(crate) Vec<(&'static str, String)>);
+
+impl Visit for CorrelationIds {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.set(field, format!("{value:?}"));
+    }
+}
+
+impl CorrelationIds {
+    fn set(&mut self, field: &Field, value: String) {
+        if let Some(name) = CORRELATION_FIELDS.into_iter().find(|name| *name == field.name()) {
+            self.insert(name, value);
+        }
+    }
+
+    pub(crate) fn insert(&mut self, name: &'static str, value: String) {
+        match self.0.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.0.push((name, value)),
+        }
+    }
+
+    /// The fields of the spans enclosing the event being formatted.
+    pub(crate) fn of_event<S, N>(ctx: &FmtContext<'_, S, N>) -> Self
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+        N: for<'a> FormatFields<'a> + 'static,
+    {
+        // Outer spans first: the service span, then the request span
+        let mut ids = Self::default();
+        for span in ctx.event_scope().into_iter().flat_map(|scope| scope.from_root()) {
+            if let Some(span_ids) = span.extensions().get::<Self>() {
+                for (name, value) in &span_ids.0 {
+                    ids.insert(name, value.clone());
+                }
+            }
+        }
+        ids
+    }
+}
+
+/// Layer keeping the correlation fields of spans for `CorrelatedFormat`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CorrelationLayer;
+
+impl<S> Layer<S> for CorrelationLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut ids = CorrelationIds::default();
+        attrs.record(&mut ids);
+        if let Some(span) = ctx.span(id)
+            && !ids.0.is_empty()
+        {
+            span.extensions_mut().insert(ids);
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        match extensions.get_mut::<CorrelationIds>() {
+            Some(ids) => values.record(ids),
+            None => {
+                let mut ids = CorrelationIds::default();
+                values.record(&mut ids);
+                if !ids.0.is_empty() {
+                    extensions.insert(ids);
+                }
+            }
+        }
+    }
+}
+
+/// Event format appending the correlation fields of the enclosing spans.
+///
+/// Requires `CorrelationLayer` on the same subscriber.
+///
+/// # Example
+///
+/// ```ignore
+/// tracing_subscriber::registry()
+///     .with(CorrelationLayer)
+///     .with(fmt::layer().event_format(CorrelatedFormat::new(fmt::format())))
+///     .init();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CorrelatedFormat<F> {
+    inner: F,
+}
+
+impl<F> CorrelatedFormat<F> {
+    /// Wrap an event format.
+    pub fn new(inner: F) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for CorrelatedFormat<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let ids = CorrelationIds::of_event(ctx);
+        if ids.0.is_empty() {
+            return self.inner.format_event(ctx, writer, event);
+        }
+
+        let mut line = String::new();
+        self.inner.format_event(ctx, Writer::new(&mut line), event)?;
+        let mut line = line.trim_end_matches('\n').to_string();
+        for (name, value) in &ids.0 {
+            write!(line, " {name}={value}")?;
+        }
+        writeln!(writer, "{line}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_defaults() {
+        let settings: TelemetrySettings = serde_json::from_str(r#"{"console":{"enabled":true}}"#).unwrap();
+
+        assert_eq!(settings.filter, "info");
+        assert!(settings.console.enabled);
+        assert_eq!(settings.console.bind, DEFAULT_CONSOLE_BIND);
+    }
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events_carry_request_ids() {
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::registry().with(CorrelationLayer).with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .event_format(CorrelatedFormat::new(tracing_subscriber::fmt::format())),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("outside");
+            let _service = tracing::info_span!("service", service = "projects").entered();
+            let request = tracing::info_span!("request", correlation_id = "c-1", request_id = "r-1");
+            let _request = request.enter();
+            let _handler = tracing::info_span!("handler").entered();
+            tracing::info!("inside");
+        });
+
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert!(!lines[0].contains("correlation_id"));
+        assert!(lines[1].ends_with("inside service=projects correlation_id=c-1 request_id=r-1"));
+    }
+
+    #[test]
+    fn test_log_level_reloads_filter() {
+        let output = Output::default();
+        let writer = output.clone();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .with_filter(filter),
+        );
+        let log_level = LogLevel::new(handle, "info");
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("hidden");
+            log_level.set("debug").unwrap();
+            tracing::debug!("shown");
+            assert!(log_level.set("debug,sqlx=loud").is_err());
+        });
+
+        assert_eq!(log_level.filter(), "debug");
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(!output.contains("hidden"));
+        assert!(output.contains("shown"));
+    }
+}
@@ -0,0 +1,562 @@
+//! Testing utilities for EYWA applications.
+//!
+//! This module provides:
+//! - `TestClient` - In-process client driving the full application router
+//! - `TestRequest` - Request builder with typed JSON helpers
+//! - `TestResponse` - Buffered response with typed JSON helpers and assertions
+//! - `TestHarness` - Postgres/Redis containers and migrations (with `testcontainers` feature)
+//! - `ContractVerifier` - Pact provider verification with provider-state setup
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode},
+    Router,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod contract;
+#[cfg(feature = "testcontainers")]
+mod harness;
+
+pub use contract::{ContractVerifier, InteractionResult, VerificationReport};
+#[cfg(feature = "testcontainers")]
+pub use harness::{TestHarness, TestHarnessBuilder};
+
+/// Header used for correlation ID propagation.
+const CORRELATION_HEADER: &str = "x-correlation-id";
+
+/// In-process client for integration tests.
+///
+/// Every request gets a fresh `X-Correlation-ID` header (unless one is set
+/// explicitly), so failures can be matched with the application's logs.
+///
+/// # Example
+///
+/// ```ignore
+/// let client = EywaApp::new(state)
+///     .mount::<ProjectsController>()
+///     .into_test_client();
+///
+/// let response = client.get("/api/v1/projects").send().await;
+/// response.assert_status(StatusCode::OK);
+/// let projects: Vec<Project> = response.json();
+/// ```
+#[derive(Clone)]
+pub struct TestClient {
+    router: Router,
+}
+
+impl TestClient {
+    /// Wrap a finished router. Prefer `EywaApp::into_test_client()`.
+    pub fn new(router: Router) -> Self {
+        Self { router }
+    }
+
+    /// Start a request with an arbitrary method.
+    pub fn request(&self, method: Method, path: impl AsRef<str>) -> TestRequest {
+        TestRequest {
+            router: self.router.clone(),
+            method,
+            path: path.as_ref().to_string(),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        }
+    }
+
+    /// Start a GET request.
+    pub fn get(&self, path: impl AsRef<str>) -> TestRequest {
+        self.request(Method::GET, path)
+    }
+
+    /// Start a POST request.
+    pub fn post(&self, path: impl AsRef<str>) -> TestRequest {
+        self.request(Method::POST, path)
+    }
+
+    /// Start a PUT request.
+    pub fn put(&self, path: impl AsRef<str>) -> TestRequest {
+        self.request(Method::PUT, path)
+    }
+
+    /// Start a PATCH request.
+    pub fn patch(&self, path: impl AsRef<str>) -> TestRequest {
+        self.request(Method::PATCH, path)
+    }
+
+    /// Start a DELETE request.
+    pub fn delete(&self, path: impl AsRef<str>) -> TestRequest {
+        self.request(Method::DELETE, path)
+    }
+}
+
+/// A request being built by a `TestClient`.
+pub struct TestRequest {
+    router: Router,
+    method: Method,
+    path: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestRequest {
+    /// Set a header.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or value is not a valid header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(
+            HeaderName::from_bytes(name.as_bytes()).expect("invalid header name"),
+            HeaderValue::from_str(value).expect("invalid header value"),
+        );
+        self
+    }
+
+    /// Set the `Authorization: Bearer <token>` header.
+    pub fn bearer(self, token: &str) -> Self {
+        self.header(header::AUTHORIZATION.as_str(), &format!("Bearer {token}"))
+    }
+
+    /// Set an explicit correlation ID instead of a generated one.
+    pub fn correlation_id(self, correlation_id: Uuid) -> Self {
+        self.header(CORRELATION_HEADER, &correlation_id.to_string())
+    }
+
+    /// Send a JSON body.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value cannot be serialized.
+    pub fn json<T: Serialize>(mut self, value: &T) -> Self {
+        self.body = serde_json::to_vec(value)
+            .expect("failed to serialize request body")
+            .into();
+        self.header(header::CONTENT_TYPE.as_str(), "application/json")
+    }
+
+    /// Send a raw body.
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send the request through the application and buffer the response.
+    pub async fn send(mut self) -> TestResponse {
+        let correlation_id = self
+            .headers
+            .get(CORRELATION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| Uuid::parse_str(v).ok())
+            .unwrap_or_else(Uuid::new_v4);
+        self.headers.insert(
+            CORRELATION_HEADER,
+            HeaderValue::from_str(&correlation_id.to_string()).unwrap(),
+        );
+
+        let mut request = Request::builder()
+            .method(self.method)
+            .uri(self.path)
+            .body(Body::from(self.body))
+            .expect("invalid test request");
+        *request.headers_mut() = self.headers;
+
+        let response = self
+            .router
+            .oneshot(request)
+            .await
+            .unwrap_or_else(|e| match e {});
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .expect("failed to read response body");
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+            correlation_id,
+        }
+    }
+}
+
+/// A buffered response returned by `TestRequest::send()`.
+#[derive(Debug)]
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+    correlation_id: Uuid,
+}
+
+impl TestResponse {
+    /// Response status.
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// A response header as a string, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// Correlation ID sent with the request.
+    pub fn correlation_id(&self) -> Uuid {
+        self.correlation_id
+    }
+
+    /// Raw response body.
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Response body as text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserialize the JSON response body.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the raw body if it cannot be deserialized into `T`.
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| {
+            panic!(
+                "failed to deserialize response body ({e}) [correlation_id={}]: {}",
+                self.correlation_id,
+                self.text()
+            )
+        })
+    }
+
+    /// Assert the response status.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the body and correlation ID if the status differs.
+    #[track_caller]
+    pub fn assert_status(&self, expected: StatusCode) -> &Self {
+        assert_eq!(
+            self.status,
+            expected,
+            "unexpected status [correlation_id={}]: {}",
+            self.correlation_id,
+            self.text()
+        );
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AuthRequirement, EywaApp, IntoRouter, RouteAuth, RouteMiddleware};
+    use axum::{
+        extract::{MatchedPath, State},
+        middleware::{from_fn, Next},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+        Json,
+    };
+    use serde_json::{json, Value};
+
+    fn client() -> TestClient {
+        EywaApp::new(())
+            .merge(Router::new().route(
+                "/echo",
+                post(|Json(body): Json<Value>| async move { Json(body) }),
+            ))
+            .health_checks()
+            .request_context()
+            .into_test_client()
+    }
+
+    #[tokio::test]
+    async fn test_json_roundtrip() {
+        let response = client().post("/echo").json(&json!({ "name": "eywa" })).send().await;
+
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.json::<Value>(), json!({ "name": "eywa" }));
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_is_injected_and_echoed() {
+        let response = client().get("/health").send().await;
+
+        response.assert_status(StatusCode::OK);
+        assert_eq!(
+            response.header(CORRELATION_HEADER),
+            Some(response.correlation_id().to_string().as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_docs_routes_are_mounted() {
+        client().get("/scalar").send().await.assert_status(StatusCode::OK);
+    }
+
+    struct TaggedController;
+
+    async fn tag_response(request: Request<Body>, next: Next) -> Response {
+        let mut response = next.run(request).await;
+        response
+            .headers_mut()
+            .insert("x-controller", HeaderValue::from_static("tagged"));
+        response
+    }
+
+    impl IntoRouter<()> for TaggedController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new().route("/tagged", get(|| async { "tagged" }))
+        }
+
+        fn middleware(router: Router<()>, _state: &()) -> Router<()> {
+            router.route_layer(from_fn(tag_response))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_controller_middleware_only_wraps_its_routes() {
+        let client = EywaApp::new(())
+            .health_checks()
+            .mount::<TaggedController>()
+            .into_test_client();
+
+        let tagged = client.get("/tagged").send().await;
+        assert_eq!(tagged.header("x-controller"), Some("tagged"));
+
+        let health = client.get("/health").send().await;
+        assert_eq!(health.header("x-controller"), None);
+    }
+
+    struct ReportsController;
+
+    #[derive(Clone)]
+    struct Admin;
+
+    async fn mark_admin(mut request: Request<Body>, next: Next) -> Response {
+        if request.headers().contains_key("x-admin") {
+            request.extensions_mut().insert(Admin);
+        }
+        next.run(request).await
+    }
+
+    async fn admin_guard(request: Request<Body>, next: Next) -> Response {
+        if request.extensions().get::<Admin>().is_none() {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        next.run(request).await
+    }
+
+    impl IntoRouter<()> for ReportsController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new()
+                .route("/reports", get(|| async { "reports" }).post(|| async { "created" }))
+                .route("/reports/summary", get(|| async { "summary" }))
+        }
+
+        fn middleware(router: Router<()>, _state: &()) -> Router<()> {
+            router.route_layer(from_fn(mark_admin))
+        }
+
+        fn route_middleware(_state: &()) -> Vec<RouteMiddleware> {
+            vec![RouteMiddleware::new("GET", "/reports").layer(from_fn(admin_guard))]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_middleware_only_wraps_its_route() {
+        let client = EywaApp::new(())
+            .mount::<ReportsController>()
+            .version_scope("v1", |app| app.mount::<ReportsController>())
+            .into_test_client();
+
+        for prefix in ["", "/v1"] {
+            let reports = format!("{prefix}/reports");
+            client.get(&reports).send().await.assert_status(StatusCode::FORBIDDEN);
+            let head = client.request(axum::http::Method::HEAD, &reports).send().await;
+            head.assert_status(StatusCode::FORBIDDEN);
+            // Runs inside the controller's middleware
+            let admin = client.get(&reports).header("x-admin", "1").send().await;
+            admin.assert_status(StatusCode::OK);
+
+            client.post(&reports).send().await.assert_status(StatusCode::OK);
+            let summary = client.get(&format!("{prefix}/reports/summary")).send().await;
+            summary.assert_status(StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_into_router_drives_full_stack() {
+        let router = EywaApp::new(()).health_checks().into_router().unwrap();
+        let response = router
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let embedded = Router::new().nest("/projects-service", router);
+        let response = embedded
+            .oneshot(Request::get("/projects-service/scalar").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    struct ProjectsController;
+
+    impl IntoRouter<()> for ProjectsController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new().route(
+                "/projects",
+                get(|route: MatchedPath| async move { route.as_str().to_string() }),
+            )
+        }
+
+        fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+            use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+            openapi.paths.paths.insert(
+                "/projects".to_string(),
+                PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_version_scopes() {
+        let client = EywaApp::new(())
+            .version_scope("v1", |app| app.mount::<ProjectsController>())
+            .version_scope("/v2/", |app| app.mount::<ProjectsController>())
+            .mount::<TaggedController>()
+            .into_test_client();
+
+        assert_eq!(client.get("/v1/projects").send().await.text(), "/v1/projects");
+        assert_eq!(client.get("/v2/projects").send().await.text(), "/v2/projects");
+        client.get("/projects").send().await.assert_status(StatusCode::NOT_FOUND);
+        client.get("/tagged").send().await.assert_status(StatusCode::OK);
+
+        let spec: Value = client.get("/api-docs/openapi.json").send().await.json();
+        let paths = spec["paths"].as_object().unwrap();
+        assert!(paths.contains_key("/v1/projects") && paths.contains_key("/v2/projects"));
+        assert!(!paths.contains_key("/projects"));
+        client.get("/api-docs/v2/openapi.json").send().await.assert_status(StatusCode::OK);
+    }
+
+    struct DescribedController;
+
+    impl IntoRouter<()> for DescribedController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new()
+        }
+
+        fn tag() -> &'static str {
+            "Projects"
+        }
+
+        fn tag_description() -> Option<&'static str> {
+            Some("Project management")
+        }
+
+        fn tag_order() -> Option<u32> {
+            Some(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_controller_tag_description_and_order() {
+        let client = EywaApp::new(())
+            .mount::<TaggedController>()
+            .mount::<DescribedController>()
+            .into_test_client();
+
+        let spec: serde_json::Value = client.get("/api-docs/openapi.json").send().await.json();
+        assert_eq!(spec["tags"][0]["name"], "Projects");
+        assert_eq!(spec["tags"][0]["description"], "Project management");
+        assert_eq!(spec["tags"][1]["name"], "API");
+    }
+
+    #[derive(Clone)]
+    struct GreetingState {
+        greeting: &'static str,
+    }
+
+    #[derive(Clone)]
+    struct CompositeState {
+        greeting: GreetingState,
+    }
+
+    impl axum::extract::FromRef<CompositeState> for GreetingState {
+        fn from_ref(state: &CompositeState) -> Self {
+            state.greeting.clone()
+        }
+    }
+
+    struct GreetingController;
+
+    impl IntoRouter<GreetingState> for GreetingController {
+        fn into_router(_state: GreetingState) -> Router<GreetingState> {
+            Router::new().route(
+                "/greeting",
+                get(|State(state): State<GreetingState>| async move { state.greeting }),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_substate_controller() {
+        let state = CompositeState {
+            greeting: GreetingState { greeting: "hello" },
+        };
+        let client = EywaApp::new(state)
+            .mount_substate::<GreetingController, GreetingState>()
+            .into_test_client();
+
+        assert_eq!(client.get("/greeting").send().await.text(), "hello");
+    }
+
+    struct StatsController;
+
+    impl IntoRouter<()> for StatsController {
+        fn into_router(_state: ()) -> Router<()> {
+            Router::new()
+                .route("/stats", get(|| async { "stats" }))
+                .route("/stats/details", get(|| async { "details" }))
+        }
+
+        fn route_auth() -> Vec<RouteAuth> {
+            vec![RouteAuth {
+                method: "GET".to_string(),
+                path: "/stats".to_string(),
+                requirement: AuthRequirement::Public,
+            }]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_routes_skip_app_authentication() {
+        let config = crate::JwtConfig::new("s3cret");
+        let client = EywaApp::new(())
+            .mount::<StatsController>()
+            .auth(config.clone())
+            .into_test_client();
+
+        client.get("/stats").send().await.assert_status(StatusCode::OK);
+        let head = client.request(axum::http::Method::HEAD, "/stats").send().await;
+        head.assert_status(StatusCode::OK);
+        let details = client.get("/stats/details").send().await;
+        details.assert_status(StatusCode::UNAUTHORIZED);
+
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let token = config.sign(&json!({ "sub": "42", "exp": exp })).unwrap();
+        let details = client.get("/stats/details").bearer(&token).send().await;
+        details.assert_status(StatusCode::OK);
+    }
+}
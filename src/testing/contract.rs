@@ -0,0 +1,471 @@
+//! Consumer-driven contract (Pact) verification against the application.
+//!
+//! Replays every interaction of a Pact file (v2-v4 JSON) through a
+//! `TestClient` built from the same `EywaApp` the service deploys. Provider
+//! states are mapped to setup functions (typically loading fixtures).
+//!
+//! Body matching follows Pact semantics: objects may contain extra keys,
+//! arrays must match element-wise, and `"match": "type"` matching rules only
+//! require the same JSON type.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use axum::http::Method;
+use serde::Deserialize;
+use serde_json::Value;
+
+use eywa_errors::AppError;
+
+use super::{TestClient, TestResponse};
+use crate::Result;
+
+type StateFn =
+    Box<dyn Fn(Value) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+fn contract_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Contract error: {e}"))
+}
+
+#[derive(Debug, Deserialize)]
+struct Pact {
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Interaction {
+    description: String,
+    /// Pact v2 single provider state
+    #[serde(default)]
+    provider_state: Option<String>,
+    /// Pact v3+ provider states with parameters
+    #[serde(default)]
+    provider_states: Vec<ProviderState>,
+    request: PactRequest,
+    response: PactResponse,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderState {
+    name: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct PactRequest {
+    method: String,
+    path: String,
+    #[serde(default)]
+    query: Option<Value>,
+    #[serde(default)]
+    headers: HashMap<String, Value>,
+    #[serde(default)]
+    body: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PactResponse {
+    status: u16,
+    #[serde(default)]
+    headers: HashMap<String, Value>,
+    #[serde(default)]
+    body: Option<Value>,
+    #[serde(default)]
+    matching_rules: Option<Value>,
+}
+
+/// Outcome of a single interaction.
+#[derive(Debug, Clone)]
+pub struct InteractionResult {
+    pub description: String,
+    /// Mismatches found; empty if the interaction was verified
+    pub mismatches: Vec<String>,
+}
+
+/// Outcome of a contract verification.
+#[derive(Debug, Clone, Default)]
+pub struct VerificationReport {
+    pub interactions: Vec<InteractionResult>,
+}
+
+impl VerificationReport {
+    /// Returns `true` if every interaction was verified.
+    pub fn is_success(&self) -> bool {
+        self.interactions.iter().all(|i| i.mismatches.is_empty())
+    }
+
+    /// Assert that every interaction was verified.
+    ///
+    /// # Panics
+    ///
+    /// Panics listing every mismatch.
+    #[track_caller]
+    pub fn assert_success(&self) {
+        let failures: Vec<String> = self
+            .interactions
+            .iter()
+            .filter(|i| !i.mismatches.is_empty())
+            .map(|i| format!("- {}:\n    {}", i.description, i.mismatches.join("\n    ")))
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "contract verification failed:\n{}",
+            failures.join("\n")
+        );
+    }
+}
+
+/// Verifies Pact contracts against an application.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::testing::ContractVerifier;
+///
+/// #[tokio::test]
+/// async fn honours_frontend_contract() -> Result<()> {
+///     let db = DatabaseSettings::in_memory().connect().await?;
+///     let client = app(db.clone()).into_test_client();
+///
+///     let fixtures = Arc::new(project_fixtures());
+///     ContractVerifier::new(client)
+///         .state("a project exists", move |_params| {
+///             let (fixtures, db) = (fixtures.clone(), db.clone());
+///             async move { fixtures.load(&db).await.map(|_| ()) }
+///         })
+///         .verify_file("pacts/frontend-projects.json")
+///         .await?
+///         .assert_success();
+///     Ok(())
+/// }
+/// ```
+pub struct ContractVerifier {
+    client: TestClient,
+    states: HashMap<String, StateFn>,
+}
+
+impl ContractVerifier {
+    /// Create a verifier driving the given client.
+    pub fn new(client: TestClient) -> Self {
+        Self {
+            client,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Map a provider state to a setup function receiving the state's parameters.
+    pub fn state<F, Fut>(mut self, name: impl Into<String>, setup: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.states
+            .insert(name.into(), Box::new(move |params| Box::pin(setup(params))));
+        self
+    }
+
+    /// Verify a Pact file.
+    pub async fn verify_file(&self, path: impl AsRef<Path>) -> Result<VerificationReport> {
+        let source = tokio::fs::read_to_string(path.as_ref())
+            .await
+            .map_err(contract_error)?;
+        self.verify_json(&source).await
+    }
+
+    /// Verify a Pact fetched from a URL (e.g. a Pact Broker).
+    pub async fn verify_url(&self, url: &str) -> Result<VerificationReport> {
+        let source = reqwest::get(url)
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(contract_error)?
+            .text()
+            .await
+            .map_err(contract_error)?;
+        self.verify_json(&source).await
+    }
+
+    /// Verify a Pact document.
+    pub async fn verify_json(&self, source: &str) -> Result<VerificationReport> {
+        let pact: Pact = serde_json::from_str(source).map_err(contract_error)?;
+        let mut report = VerificationReport::default();
+
+        for interaction in pact.interactions {
+            let mismatches = self.verify_interaction(&interaction).await?;
+            report.interactions.push(InteractionResult {
+                description: interaction.description,
+                mismatches,
+            });
+        }
+
+        Ok(report)
+    }
+
+    async fn verify_interaction(&self, interaction: &Interaction) -> Result<Vec<String>> {
+        let states = interaction
+            .provider_state
+            .iter()
+            .map(|name| (name.as_str(), Value::Null))
+            .chain(
+                interaction
+                    .provider_states
+                    .iter()
+                    .map(|state| (state.name.as_str(), state.params.clone())),
+            );
+        for (name, params) in states {
+            let setup = self
+                .states
+                .get(name)
+                .ok_or_else(|| contract_error(format!("unknown provider state `{name}`")))?;
+            setup(params).await?;
+        }
+
+        let response = self.send(&interaction.request).await?;
+        Ok(compare_response(&interaction.response, &response))
+    }
+
+    async fn send(&self, request: &PactRequest) -> Result<TestResponse> {
+        let method =
+            Method::from_bytes(request.method.to_uppercase().as_bytes()).map_err(contract_error)?;
+
+        let mut path = request.path.clone();
+        if let Some(query) = query_string(request.query.as_ref()) {
+            path = format!("{path}?{query}");
+        }
+
+        let mut test_request = self.client.request(method, path);
+        for (name, value) in &request.headers {
+            test_request = test_request.header(name, &header_value(value));
+        }
+        test_request = match &request.body {
+            Some(Value::String(body)) => test_request.body(body.clone()),
+            Some(body) => test_request.json(body),
+            None => test_request,
+        };
+
+        Ok(test_request.send().await)
+    }
+}
+
+/// Build a query string from a v2 string or v3 `{name: [values]}` query.
+fn query_string(query: Option<&Value>) -> Option<String> {
+    match query? {
+        Value::String(query) if !query.is_empty() => Some(query.clone()),
+        Value::Object(params) => {
+            let pairs: Vec<String> = params
+                .iter()
+                .flat_map(|(name, values)| match values {
+                    Value::Array(values) => values
+                        .iter()
+                        .map(|value| format!("{name}={}", header_value(value)))
+                        .collect::<Vec<_>>(),
+                    value => vec![format!("{name}={}", header_value(value))],
+                })
+                .collect();
+            (!pairs.is_empty()).then(|| pairs.join("&"))
+        }
+        _ => None,
+    }
+}
+
+fn header_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Array(values) => values.iter().map(header_value).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
+fn compare_response(expected: &PactResponse, actual: &TestResponse) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    if actual.status().as_u16() != expected.status {
+        mismatches.push(format!(
+            "status: expected {}, got {}",
+            expected.status,
+            actual.status().as_u16()
+        ));
+    }
+
+    for (name, value) in &expected.headers {
+        let expected_value = header_value(value);
+        match actual.header(name) {
+            Some(actual_value) if actual_value == expected_value => {}
+            actual_value => mismatches.push(format!(
+                "header {name}: expected {expected_value:?}, got {actual_value:?}"
+            )),
+        }
+    }
+
+    if let Some(expected_body) = &expected.body {
+        match serde_json::from_slice::<Value>(actual.bytes()) {
+            Ok(actual_body) => {
+                let rules = type_rules(expected.matching_rules.as_ref());
+                compare_body("$", expected_body, &actual_body, &rules, &mut mismatches);
+            }
+            Err(_) if *expected_body == Value::String(actual.text()) => {}
+            Err(_) => mismatches.push(format!("body: expected JSON, got {:?}", actual.text())),
+        }
+    }
+
+    mismatches
+}
+
+/// Collect the body paths with a `"match": "type"` rule (v2 and v3+ formats).
+fn type_rules(matching_rules: Option<&Value>) -> Vec<String> {
+    let Some(Value::Object(rules)) = matching_rules else {
+        return Vec::new();
+    };
+
+    // v3+: {"body": {"$.id": {"matchers": [{"match": "type"}]}}}
+    if let Some(Value::Object(body_rules)) = rules.get("body") {
+        return body_rules
+            .iter()
+            .filter(|(_, rule)| {
+                rule.get("matchers")
+                    .and_then(Value::as_array)
+                    .is_some_and(|m| m.iter().any(is_type_matcher))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+    }
+
+    // v2: {"$.body.id": {"match": "type"}}
+    rules
+        .iter()
+        .filter(|(_, rule)| is_type_matcher(rule))
+        .filter_map(|(path, _)| path.strip_prefix("$.body").map(|rest| format!("${rest}")))
+        .collect()
+}
+
+fn is_type_matcher(matcher: &Value) -> bool {
+    matcher.get("match").and_then(Value::as_str) == Some("type")
+}
+
+/// Returns `true` if a type rule applies to `path` (indices may be written as `[*]`).
+fn has_type_rule(path: &str, rules: &[String]) -> bool {
+    rules.iter().any(|rule| {
+        rule == path || {
+            let mut wildcard = String::with_capacity(path.len());
+            let mut in_index = false;
+            for c in path.chars() {
+                match c {
+                    '[' => {
+                        in_index = true;
+                        wildcard.push_str("[*");
+                    }
+                    ']' => {
+                        in_index = false;
+                        wildcard.push(']');
+                    }
+                    _ if in_index => {}
+                    _ => wildcard.push(c),
+                }
+            }
+            *rule == wildcard
+        }
+    })
+}
+
+fn same_type(a: &Value, b: &Value) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn compare_body(
+    path: &str,
+    expected: &Value,
+    actual: &Value,
+    rules: &[String],
+    mismatches: &mut Vec<String>,
+) {
+    let type_only = has_type_rule(path, rules);
+
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual_value) => {
+                        compare_body(&child, expected_value, actual_value, rules, mismatches)
+                    }
+                    None => mismatches.push(format!("{child}: missing")),
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if !type_only && expected.len() != actual.len() {
+                mismatches.push(format!(
+                    "{path}: expected {} items, got {}",
+                    expected.len(),
+                    actual.len()
+                ));
+            }
+            for (index, (expected_item, actual_item)) in expected.iter().zip(actual).enumerate() {
+                let child = format!("{path}[{index}]");
+                compare_body(&child, expected_item, actual_item, rules, mismatches);
+            }
+        }
+        _ if type_only && same_type(expected, actual) => {}
+        _ if expected == actual => {}
+        _ => mismatches.push(format!("{path}: expected {expected}, got {actual}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn compare(expected: Value, actual: Value, rules: &[&str]) -> Vec<String> {
+        let rules: Vec<String> = rules.iter().map(|r| r.to_string()).collect();
+        let mut mismatches = Vec::new();
+        compare_body("$", &expected, &actual, &rules, &mut mismatches);
+        mismatches
+    }
+
+    #[test]
+    fn test_extra_keys_are_allowed() {
+        let mismatches = compare(json!({ "id": 1 }), json!({ "id": 1, "name": "x" }), &[]);
+        assert!(mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_value_mismatch_and_missing_key() {
+        let mismatches = compare(json!({ "id": 1, "name": "x" }), json!({ "id": 2 }), &[]);
+        assert_eq!(mismatches, vec!["$.id: expected 1, got 2", "$.name: missing"]);
+    }
+
+    #[test]
+    fn test_type_rule_with_wildcard_index() {
+        let mismatches = compare(
+            json!({ "items": [{ "id": 1 }] }),
+            json!({ "items": [{ "id": 42 }] }),
+            &["$.items[*].id"],
+        );
+        assert!(mismatches.is_empty());
+    }
+
+    #[test]
+    fn test_v3_and_v2_type_rules() {
+        let v3 = json!({ "body": { "$.id": { "matchers": [{ "match": "type" }] } } });
+        assert_eq!(type_rules(Some(&v3)), vec!["$.id"]);
+
+        let v2 = json!({ "$.body.id": { "match": "type" } });
+        assert_eq!(type_rules(Some(&v2)), vec!["$.id"]);
+    }
+
+    #[test]
+    fn test_query_string_formats() {
+        assert_eq!(query_string(Some(&json!("page=2"))), Some("page=2".to_string()));
+        assert_eq!(
+            query_string(Some(&json!({ "tag": ["a", "b"] }))),
+            Some("tag=a&tag=b".to_string())
+        );
+        assert_eq!(query_string(None), None);
+    }
+}
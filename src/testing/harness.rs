@@ -0,0 +1,205 @@
+//! Testcontainers-based integration test harness.
+//!
+//! Spins up Postgres/Redis containers, runs migrations and builds the
+//! `EywaApp` under test. Containers are removed when the harness is dropped.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use sea_orm::{DatabaseConnection, DbErr};
+use sea_orm_migration::MigratorTrait;
+use testcontainers::{runners::AsyncRunner, ContainerAsync};
+use testcontainers_modules::{postgres::Postgres, redis::Redis};
+
+use eywa_errors::AppError;
+
+use super::TestClient;
+use crate::{EywaApp, Result};
+
+type MigrateFn = Box<
+    dyn for<'a> FnOnce(
+            &'a DatabaseConnection,
+        ) -> Pin<Box<dyn Future<Output = std::result::Result<(), DbErr>> + Send + 'a>>
+        + Send,
+>;
+
+fn run_migrations<M: MigratorTrait>(
+    db: &DatabaseConnection,
+) -> Pin<Box<dyn Future<Output = std::result::Result<(), DbErr>> + Send + '_>> {
+    Box::pin(M::up(db, None))
+}
+
+fn harness_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(format!("Test harness error: {e}"))
+}
+
+/// Builder for a `TestHarness`.
+#[derive(Default)]
+pub struct TestHarnessBuilder {
+    postgres: bool,
+    redis: bool,
+    migrate: Option<MigrateFn>,
+}
+
+impl TestHarnessBuilder {
+    /// Start a Postgres container.
+    pub fn postgres(mut self) -> Self {
+        self.postgres = true;
+        self
+    }
+
+    /// Start a Redis container.
+    pub fn redis(mut self) -> Self {
+        self.redis = true;
+        self
+    }
+
+    /// Run the migrator's pending migrations once Postgres is up.
+    pub fn migrations<M: MigratorTrait>(mut self) -> Self {
+        self.postgres = true;
+        self.migrate = Some(Box::new(run_migrations::<M>));
+        self
+    }
+
+    /// Start the containers and run migrations.
+    pub async fn start(self) -> Result<TestHarness> {
+        let mut harness = TestHarness {
+            postgres: None,
+            redis: None,
+            db: None,
+            database_url: None,
+            redis_url: None,
+        };
+
+        if self.postgres {
+            let container = Postgres::default().start().await.map_err(harness_error)?;
+            let host = container.get_host().await.map_err(harness_error)?;
+            let port = container
+                .get_host_port_ipv4(5432)
+                .await
+                .map_err(harness_error)?;
+            let url = format!("postgres://postgres:postgres@{host}:{port}/postgres");
+
+            let db = sea_orm::Database::connect(&url)
+                .await
+                .map_err(harness_error)?;
+            if let Some(migrate) = self.migrate {
+                migrate(&db).await.map_err(harness_error)?;
+            }
+
+            tracing::info!("🧪 Postgres test container ready at {}", url);
+            harness.postgres = Some(container);
+            harness.db = Some(db);
+            harness.database_url = Some(url);
+        }
+
+        if self.redis {
+            let container = Redis::default().start().await.map_err(harness_error)?;
+            let host = container.get_host().await.map_err(harness_error)?;
+            let port = container
+                .get_host_port_ipv4(6379)
+                .await
+                .map_err(harness_error)?;
+            let url = format!("redis://{host}:{port}");
+
+            tracing::info!("🧪 Redis test container ready at {}", url);
+            harness.redis = Some(container);
+            harness.redis_url = Some(url);
+        }
+
+        Ok(harness)
+    }
+}
+
+/// Running test dependencies plus the application under test.
+///
+/// # Example
+///
+/// ```ignore
+/// use eywa_axum::testing::TestHarness;
+///
+/// #[tokio::test]
+/// async fn lists_projects() -> Result<()> {
+///     let harness = TestHarness::builder()
+///         .migrations::<Migrator>()
+///         .redis()
+///         .start()
+///         .await?;
+///
+///     let client = harness.app(|h| {
+///         EywaApp::new(AppState::new(h.db().clone(), h.redis_url()))
+///             .mount::<ProjectsController>()
+///     });
+///
+///     client.get("/api/v1/projects").send().await.assert_status(StatusCode::OK);
+///     harness.teardown().await
+/// }
+/// ```
+pub struct TestHarness {
+    postgres: Option<ContainerAsync<Postgres>>,
+    redis: Option<ContainerAsync<Redis>>,
+    db: Option<DatabaseConnection>,
+    database_url: Option<String>,
+    redis_url: Option<String>,
+}
+
+impl TestHarness {
+    /// Create a harness builder.
+    pub fn builder() -> TestHarnessBuilder {
+        TestHarnessBuilder::default()
+    }
+
+    /// Database connection to the Postgres container.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the harness was built without Postgres.
+    pub fn db(&self) -> &DatabaseConnection {
+        self.db.as_ref().expect("TestHarness built without postgres()")
+    }
+
+    /// Connection URL of the Postgres container.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the harness was built without Postgres.
+    pub fn database_url(&self) -> &str {
+        self.database_url
+            .as_deref()
+            .expect("TestHarness built without postgres()")
+    }
+
+    /// Connection URL of the Redis container.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the harness was built without Redis.
+    pub fn redis_url(&self) -> &str {
+        self.redis_url
+            .as_deref()
+            .expect("TestHarness built without redis()")
+    }
+
+    /// Build the application under test and return a client for it.
+    pub fn app<S, F>(&self, build: F) -> TestClient
+    where
+        S: Clone + Send + Sync + 'static,
+        F: FnOnce(&Self) -> EywaApp<S>,
+    {
+        build(self).into_test_client()
+    }
+
+    /// Close the database connection and remove the containers.
+    pub async fn teardown(mut self) -> Result<()> {
+        if let Some(db) = self.db.take() {
+            db.close().await.map_err(harness_error)?;
+        }
+        if let Some(container) = self.postgres.take() {
+            container.rm().await.map_err(harness_error)?;
+        }
+        if let Some(container) = self.redis.take() {
+            container.rm().await.map_err(harness_error)?;
+        }
+        Ok(())
+    }
+}
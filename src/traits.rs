@@ -1,6 +1,15 @@
 //! Common traits for the eywa-axum-controller framework.
 
-use axum::Router;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use axum::{extract::Request, response::Response, Router};
+use tower::{util::BoxCloneService, Layer, Service};
+
+use crate::di::Dependency;
+use crate::privacy::DataSubjectHandler;
+use crate::typed_header::HeaderParameter;
+use crate::validation::ParameterConstraint;
 
 /// OpenAPI path information
 #[derive(Clone, Debug)]
@@ -12,6 +21,210 @@ pub struct OpenApiPath {
     pub tag: String,
 }
 
+/// OAuth scopes required by a single route.
+///
+/// Emitted by the `#[route]` macro and used both to enforce the token's
+/// `scope` claim and to document the operation's security requirement.
+#[derive(Clone, Debug)]
+pub struct RouteScopes {
+    pub method: String,
+    pub path: String,
+    pub scopes: Vec<String>,
+}
+
+/// Static response headers declared on a single route.
+///
+/// Emitted by `#[route(header("Cache-Control", "no-store"))]`; the headers are
+/// added to every response of the route and documented on its responses.
+#[derive(Clone, Debug)]
+pub struct RouteHeaders {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// A route whose handler uses validating extractors (`ValidatedJson`, ...).
+///
+/// Emitted by the `#[route]` macro so the validation responses and the
+/// parameter constraints taken from `#[validate(...)]` are documented on
+/// the operation.
+#[derive(Clone, Debug, Default)]
+pub struct RouteValidation {
+    pub method: String,
+    pub path: String,
+    /// Uses `ValidatedJson` (documents `422`)
+    pub body: bool,
+    /// Uses `ValidatedQuery` (documents `422`)
+    pub query: bool,
+    /// Uses `ValidatedPath` (documents `400`)
+    pub path_params: bool,
+    pub constraints: Vec<ParameterConstraint>,
+}
+
+/// Error statuses documented on a single route.
+///
+/// Emitted by `#[route(errors(404, 409))]`; replaces the default set of
+/// `ErrorResponses` for the operation.
+#[derive(Clone, Debug)]
+pub struct RouteErrors {
+    pub method: String,
+    pub path: String,
+    pub statuses: Vec<u16>,
+}
+
+/// The bulkhead a single route runs in.
+///
+/// Emitted by `#[route(bulkhead = "reports")]`.
+#[derive(Clone, Debug)]
+pub struct RouteBulkhead {
+    pub method: String,
+    pub path: String,
+    pub bulkhead: String,
+}
+
+/// The service wrapped by a route's own middleware: the handler, with the
+/// layers added after it.
+pub type RouteService = BoxCloneService<Request, Response, Infallible>;
+
+type RouteLayer = Arc<dyn Fn(RouteService) -> RouteService + Send + Sync>;
+
+/// Middleware declared on a single route.
+///
+/// Emitted by `#[route(GET "/{id}", middleware(admin_guard))]`, with each
+/// listed middleware as a `from_fn_with_state` layer on the controller's
+/// state. The first layer added runs first.
+#[derive(Clone)]
+pub struct RouteMiddleware {
+    pub method: String,
+    pub path: String,
+    layers: Vec<RouteLayer>,
+}
+
+impl std::fmt::Debug for RouteMiddleware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteMiddleware")
+            .field("method", &self.method)
+            .field("path", &self.path)
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl RouteMiddleware {
+    /// Middleware of the route `path` for `method`, without layers yet.
+    pub fn new(method: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            path: path.into(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a layer, e.g. `axum::middleware::from_fn(admin_guard)`.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<RouteService> + Send + Sync + 'static,
+        L::Service:
+            Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Arc::new(move |inner| BoxCloneService::new(layer.layer(inner))));
+        self
+    }
+
+    /// Returns `true` if no layer was added.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Wrap `inner` with the layers, the first added outermost.
+    pub(crate) fn wrap(&self, inner: RouteService) -> RouteService {
+        self.layers.iter().rev().fold(inner, |service, layer| layer(service))
+    }
+}
+
+/// The SLO a single route declares.
+///
+/// Emitted by `#[route(slo_latency_ms = 300)]`, with the optional
+/// `slo_latency_objective = 99.0` and `slo_availability = 99.9`.
+#[derive(Clone, Debug)]
+pub struct RouteSlo {
+    pub method: String,
+    pub path: String,
+    pub latency_ms: Option<u64>,
+    pub latency_objective: Option<f64>,
+    pub availability: Option<f64>,
+}
+
+/// Services injected into a single route's handler.
+///
+/// Emitted by the `#[route]` macro for every `Inject<T>` parameter, so a
+/// missing `EywaApp::provide` is reported at startup.
+#[derive(Clone, Debug)]
+pub struct RouteDependencies {
+    pub method: String,
+    pub path: String,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Typed request headers read by a single route's handler.
+///
+/// Emitted by the `#[route]` macro for every `TypedHeader<T>` parameter, so
+/// the headers are documented as parameters of the operation.
+#[derive(Clone, Debug)]
+pub struct RouteRequestHeaders {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<HeaderParameter>,
+}
+
+/// A route excluded from the OpenAPI spec.
+///
+/// Emitted by `#[route(hidden)]`; the route is still served.
+#[derive(Clone, Debug)]
+pub struct HiddenRoute {
+    pub method: String,
+    pub path: String,
+}
+
+/// A route that can be disabled at runtime by a kill switch.
+///
+/// Emitted by `#[route(killable)]`.
+#[derive(Clone, Debug)]
+pub struct KillableRoute {
+    pub method: String,
+    pub path: String,
+}
+
+/// A route documented only in the internal spec.
+///
+/// Emitted by `#[route(internal)]`; the route is left out of the public spec.
+#[derive(Clone, Debug)]
+pub struct InternalRoute {
+    pub method: String,
+    pub path: String,
+}
+
+/// Authentication requirement declared on a route.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthRequirement {
+    /// `#[route(auth)]`: the handler is wrapped with `auth_middleware`
+    Required,
+    /// `#[route(public)]`: no authentication, even in a `security` controller
+    Public,
+}
+
+/// Authentication requirement of a single route.
+///
+/// Emitted by the `#[route(auth)]` / `#[route(public)]` attributes, which also
+/// wrap the handler itself, and used to document the operation's security.
+#[derive(Clone, Debug)]
+pub struct RouteAuth {
+    pub method: String,
+    pub path: String,
+    pub requirement: AuthRequirement,
+}
+
 /// Trait for controllers that can be converted into an axum Router.
 ///
 /// This trait is automatically implemented by the `#[controller]` macro.
@@ -32,11 +245,113 @@ where
         "API"
     }
 
+    /// Returns the description of the controller's tag.
+    ///
+    /// Generated from `#[controller(tag = "Projects", tag_description = "...")]`.
+    /// A description set with `EywaApp::tag` takes precedence.
+    fn tag_description() -> Option<&'static str> {
+        None
+    }
+
+    /// Returns the position of the controller's tag in the spec, lowest first.
+    ///
+    /// Generated from `#[controller(tag_order = 1)]`. Tags listed with
+    /// `EywaApp::tag_order` come first; tags without a position keep mount order.
+    fn tag_order() -> Option<u32> {
+        None
+    }
+
     /// Returns route metadata for OpenAPI generation.
     fn openapi_routes() -> Vec<OpenApiPath> {
         Vec::new()
     }
 
+    /// Applies the controller's own middleware to its routes.
+    ///
+    /// Generated from `#[controller(middleware(auth_middleware, tenant_middleware))]`
+    /// as `route_layer`s, so the layers only wrap this controller's routes.
+    /// The first listed middleware runs first.
+    fn middleware(router: Router<S>, state: &S) -> Router<S> {
+        let _ = state;
+        router
+    }
+
+    /// Returns the middleware declared on individual routes.
+    ///
+    /// Generated from `#[route(GET "/{id}", middleware(admin_guard))]`. Applied
+    /// with a `route_layer` when the controller is mounted, inside the
+    /// controller's own middleware, so only the declaring route runs it.
+    fn route_middleware(state: &S) -> Vec<RouteMiddleware> {
+        let _ = state;
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(auth)]` or `#[route(public)]`.
+    fn route_auth() -> Vec<RouteAuth> {
+        Vec::new()
+    }
+
+    /// Returns the OAuth scopes required by each route.
+    fn route_scopes() -> Vec<RouteScopes> {
+        Vec::new()
+    }
+
+    /// Returns the static response headers declared on each route.
+    fn route_headers() -> Vec<RouteHeaders> {
+        Vec::new()
+    }
+
+    /// Returns the routes whose handlers use a validating extractor.
+    fn route_validation() -> Vec<RouteValidation> {
+        Vec::new()
+    }
+
+    /// Returns the routes overriding the documented error statuses.
+    fn route_errors() -> Vec<RouteErrors> {
+        Vec::new()
+    }
+
+    /// Returns the services injected into each route.
+    fn route_dependencies() -> Vec<RouteDependencies> {
+        Vec::new()
+    }
+
+    /// Returns the typed request headers read by each route.
+    fn route_request_headers() -> Vec<RouteRequestHeaders> {
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(bulkhead = "...")]`.
+    fn route_bulkheads() -> Vec<RouteBulkhead> {
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(slo_latency_ms = ...)]` or `slo_availability`.
+    fn route_slos() -> Vec<RouteSlo> {
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(killable)]`.
+    fn killable_routes() -> Vec<KillableRoute> {
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(hidden)]`.
+    fn hidden_routes() -> Vec<HiddenRoute> {
+        Vec::new()
+    }
+
+    /// Returns the routes declaring `#[route(internal)]`.
+    fn internal_routes() -> Vec<InternalRoute> {
+        Vec::new()
+    }
+
+    /// Returns the GDPR export/erase hooks for the data owned by this controller.
+    fn data_subject_handlers(state: &S) -> Vec<Arc<dyn DataSubjectHandler>> {
+        let _ = state;
+        Vec::new()
+    }
+
     /// Register schemas used by this controller.
     /// Called by EywaApp::mount() to collect schemas.
     fn register_schemas(components: &mut utoipa::openapi::Components) {
@@ -0,0 +1,285 @@
+//! Typed request headers, validated and documented like other parameters.
+//!
+//! A header type implements `CustomHeader` (name, parsing, schema) and is
+//! extracted with `TypedHeader<T>`, so every route requiring it rejects a
+//! missing or malformed value with the same `400`:
+//!
+//! ```ignore
+//! pub struct TenantId(pub Uuid);
+//!
+//! impl CustomHeader for TenantId {
+//!     const NAME: &'static str = "X-Tenant-ID";
+//!     const DESCRIPTION: &'static str = "Tenant the request acts on";
+//!
+//!     fn parse(value: &str) -> Result<Self, String> {
+//!         value.parse().map(Self).map_err(|e| format!("{e}"))
+//!     }
+//!
+//!     fn schema() -> RefOr<Schema> {
+//!         ObjectBuilder::new()
+//!             .schema_type(Type::String)
+//!             .format(Some(KnownFormat::Uuid.into()))
+//!             .into()
+//!     }
+//! }
+//!
+//! #[route(GET "/projects")]
+//! async fn list(TypedHeader(tenant): TypedHeader<TenantId>) -> Result<...>
+//! ```
+//!
+//! `Option<TypedHeader<T>>` accepts a missing header but still rejects a
+//! malformed one. The `#[route]` macro records the typed headers of each
+//! handler (see `RouteRequestHeaders`) and they are documented as header
+//! parameters of the operation.
+
+use std::fmt;
+
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::request::Parts,
+};
+use eywa_errors::AppError;
+use utoipa::openapi::{
+    path::{Operation, ParameterBuilder, ParameterIn},
+    schema::{ObjectBuilder, Schema, Type},
+    OpenApi, RefOr, Required,
+};
+
+use crate::traits::RouteRequestHeaders;
+
+/// A request header with a name, a parser and a schema.
+pub trait CustomHeader: Sized + Send {
+    /// Header name, as documented (`X-Tenant-ID`)
+    const NAME: &'static str;
+
+    /// Description of the documented parameter
+    const DESCRIPTION: &'static str = "";
+
+    /// Parse the header value, or describe why it's invalid.
+    fn parse(value: &str) -> Result<Self, String>;
+
+    /// Schema of the documented parameter (a string by default).
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new().schema_type(Type::String).into()
+    }
+}
+
+/// A header read by a route's handler.
+#[derive(Clone, Copy)]
+pub struct HeaderParameter {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+    pub schema: fn() -> RefOr<Schema>,
+}
+
+impl HeaderParameter {
+    /// The header extracted with `TypedHeader<T>`.
+    pub fn of<T: CustomHeader>() -> Self {
+        Self {
+            name: T::NAME,
+            description: T::DESCRIPTION,
+            required: true,
+            schema: T::schema,
+        }
+    }
+
+    /// The header extracted with `Option<TypedHeader<T>>`.
+    pub fn optional<T: CustomHeader>() -> Self {
+        Self {
+            required: false,
+            ..Self::of::<T>()
+        }
+    }
+}
+
+impl fmt::Debug for HeaderParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderParameter")
+            .field("name", &self.name)
+            .field("required", &self.required)
+            .finish()
+    }
+}
+
+/// Extractor for a `CustomHeader`.
+///
+/// Fails with `400 Bad Request` when the header is missing or doesn't parse.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypedHeader<T>(pub T);
+
+fn parse<T: CustomHeader>(parts: &Parts) -> Result<Option<T>, AppError> {
+    let Some(value) = parts.headers.get(T::NAME) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} header: not ASCII", T::NAME)))?;
+    T::parse(value)
+        .map(Some)
+        .map_err(|e| AppError::BadRequest(format!("Invalid {} header: {}", T::NAME, e)))
+}
+
+impl<T, S> FromRequestParts<S> for TypedHeader<T>
+where
+    T: CustomHeader,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parse::<T>(parts)?
+            .map(Self)
+            .ok_or_else(|| AppError::BadRequest(format!("Missing {} header", T::NAME)))
+    }
+}
+
+impl<T, S> OptionalFromRequestParts<S> for TypedHeader<T>
+where
+    T: CustomHeader,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        Ok(parse::<T>(parts)?.map(Self))
+    }
+}
+
+fn document_headers(operation: &mut Operation, headers: &[HeaderParameter]) {
+    let parameters = operation.parameters.get_or_insert_with(Vec::new);
+    for header in headers {
+        let documented = parameters.iter().any(|parameter| {
+            parameter.parameter_in == ParameterIn::Header
+                && parameter.name.eq_ignore_ascii_case(header.name)
+        });
+        if documented {
+            continue;
+        }
+        let required = if header.required { Required::True } else { Required::False };
+        let description = Some(header.description).filter(|d| !d.is_empty());
+        parameters.push(
+            ParameterBuilder::new()
+                .name(header.name)
+                .parameter_in(ParameterIn::Header)
+                .required(required)
+                .description(description)
+                .schema(Some((header.schema)()))
+                .build(),
+        );
+    }
+}
+
+/// Document the typed headers of each route as header parameters.
+///
+/// Headers already documented on the operation (e.g. through
+/// `#[utoipa::path(params(...))]`) are left as they are.
+pub fn apply_to_openapi(openapi: &mut OpenApi, routes: &[RouteRequestHeaders]) {
+    for route in routes {
+        let Some(item) = openapi.paths.paths.get_mut(&route.path) else {
+            continue;
+        };
+        let operation = match route.method.to_uppercase().as_str() {
+            "GET" => &mut item.get,
+            "POST" => &mut item.post,
+            "PUT" => &mut item.put,
+            "DELETE" => &mut item.delete,
+            "PATCH" => &mut item.patch,
+            _ => continue,
+        };
+        if let Some(operation) = operation.as_mut() {
+            document_headers(operation, &route.headers);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{http::StatusCode, routing::get, Router};
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    #[derive(Debug)]
+    struct ApiVersion(u32);
+
+    impl CustomHeader for ApiVersion {
+        const NAME: &'static str = "X-Api-Version";
+        const DESCRIPTION: &'static str = "Requested API version";
+
+        fn parse(value: &str) -> Result<Self, String> {
+            value.parse().map(Self).map_err(|_| format!("'{value}' is not a version"))
+        }
+
+        fn schema() -> RefOr<Schema> {
+            ObjectBuilder::new().schema_type(Type::Integer).into()
+        }
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route(
+                    "/required",
+                    get(|TypedHeader(version): TypedHeader<ApiVersion>| async move {
+                        version.0.to_string()
+                    }),
+                )
+                .route(
+                    "/optional",
+                    get(|version: Option<TypedHeader<ApiVersion>>| async move {
+                        version.map_or("none".to_string(), |v| v.0.0.to_string())
+                    }),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_required_header() {
+        let client = client();
+
+        let response = client.get("/required").header("x-api-version", "2").send().await;
+        response.assert_status(StatusCode::OK);
+        assert_eq!(response.text(), "2");
+
+        client.get("/required").send().await.assert_status(StatusCode::BAD_REQUEST);
+        let response = client.get("/required").header("x-api-version", "two").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_optional_header_still_validated() {
+        let client = client();
+
+        assert_eq!(client.get("/optional").send().await.text(), "none");
+        let response = client.get("/optional").header("x-api-version", "two").send().await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_documents_header_parameters() {
+        let mut openapi = OpenApi::default();
+        openapi.paths.paths.insert(
+            "/projects".to_string(),
+            PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+        );
+        let routes = [RouteRequestHeaders {
+            method: "GET".to_string(),
+            path: "/projects".to_string(),
+            headers: vec![HeaderParameter::of::<ApiVersion>()],
+        }];
+
+        apply_to_openapi(&mut openapi, &routes);
+        apply_to_openapi(&mut openapi, &routes);
+
+        let get = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        let parameters = get.parameters.as_ref().unwrap();
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0].name, "X-Api-Version");
+        assert!(parameters[0].parameter_in == ParameterIn::Header);
+        assert!(matches!(parameters[0].required, Required::True));
+    }
+}
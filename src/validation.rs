@@ -0,0 +1,643 @@
+//! Validating extractors with structured 422/400 responses.
+//!
+//! `ValidatedJson<T>`, `ValidatedQuery<T>` and `ValidatedPath<T>` extract like
+//! their axum counterparts, then run `validator::Validate`. Invalid bodies and
+//! query strings return `422 Unprocessable Entity`, invalid path parameters
+//! `400 Bad Request`, with a problem-details body listing every invalid field:
+//!
+//! ```json
+//! {
+//!   "title": "Validation failed",
+//!   "status": 422,
+//!   "detail": "2 invalid fields",
+//!   "request_id": "6f1c...",
+//!   "errors": [
+//!     {
+//!       "field": "name",
+//!       "code": "length",
+//!       "message": "must not be empty",
+//!       "params": { "min": 1 }
+//!     },
+//!     { "field": "members[0].email", "code": "email" }
+//!   ]
+//! }
+//! ```
+//!
+//! `ValidationErrorResponse` is the one validation error body of the
+//! framework: the three extractors return it, and handlers validating
+//! further (uniqueness, cross-field rules) should return it too, so clients
+//! render every validation failure with a single component.
+//!
+//! Routes using a validating extractor get this response and their parameter
+//! constraints (min/max, length, pattern) documented automatically (see
+//! `apply_to_openapi`). The schemas and the `ValidationFailed` and
+//! `InvalidPathParameters` responses are registered once in `components`
+//! and referenced from the operations.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{FromRequest, FromRequestParts, Path, Query, Request},
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::schema::{Number, Schema};
+use serde_json::Value;
+use utoipa::openapi::{ContentBuilder, Components, OpenApi, Ref, RefOr, ResponseBuilder};
+use utoipa::{PartialSchema, ToSchema};
+use uuid::Uuid;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::middleware::rejection::rejection_response;
+use crate::responses::response_ref;
+use crate::traits::RouteValidation;
+
+/// Content type of validation error responses.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Response component documenting `422` validation failures.
+pub const VALIDATION_FAILED_RESPONSE: &str = "ValidationFailed";
+
+/// Response component documenting `400` invalid path parameters.
+pub const INVALID_PATH_PARAMETERS_RESPONSE: &str = "InvalidPathParameters";
+
+/// A single invalid field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    /// Path of the field, e.g. `address.city` or `members[0].email`
+    pub field: String,
+    /// Validator code, e.g. `length`, `email`, `range`
+    pub code: String,
+    /// Human-readable message, if the validator defines one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Parameters of the failed rule, e.g. `{"min": 1, "max": 120}`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schema(value_type = Object)]
+    pub params: BTreeMap<String, Value>,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: None,
+            params: BTreeMap::new(),
+        }
+    }
+
+    /// Set the human-readable message.
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Add a parameter of the failed rule.
+    pub fn param(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+}
+
+/// Problem details body of a validation failure (`422`, or `400` for path parameters).
+///
+/// # Example
+///
+/// ```ignore
+/// if store.name_taken(&body.name).await? {
+///     return Err(ValidationErrorResponse::new(vec![
+///         FieldError::new("name", "unique").message("is already taken"),
+///     ])
+///     .into_response());
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ValidationErrorResponse {
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// ID of the failed request, for log correlation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<Uuid>,
+    pub errors: Vec<FieldError>,
+}
+
+/// Former name of `ValidationErrorResponse`.
+#[deprecated(note = "renamed to `ValidationErrorResponse`")]
+pub type ValidationProblem = ValidationErrorResponse;
+
+impl ValidationErrorResponse {
+    /// Build a `422` problem from a list of field errors.
+    pub fn new(errors: Vec<FieldError>) -> Self {
+        let detail = match errors.len() {
+            1 => "1 invalid field".to_string(),
+            n => format!("{n} invalid fields"),
+        };
+        Self {
+            title: "Validation failed".to_string(),
+            status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+            detail,
+            request_id: eywa_errors::CURRENT_REQUEST_ID.try_with(|id| *id).ok(),
+            errors,
+        }
+    }
+
+    /// Use another status code (e.g. `400` for invalid path parameters).
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status.as_u16();
+        self
+    }
+}
+
+impl From<ValidationErrors> for ValidationErrorResponse {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        flatten_errors("", &errors, &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        Self::new(fields)
+    }
+}
+
+impl IntoResponse for ValidationErrorResponse {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.status).unwrap_or(StatusCode::UNPROCESSABLE_ENTITY);
+        let mut response = (status, Json(self)).into_response();
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        response
+    }
+}
+
+/// Flatten nested validator errors into dotted/indexed field paths.
+fn flatten_errors(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                out.extend(field_errors.iter().map(|error| FieldError {
+                    field: path.clone(),
+                    code: error.code.to_string(),
+                    message: error.message.as_ref().map(ToString::to_string),
+                    // The rejected value isn't echoed back (it may be a secret)
+                    params: error
+                        .params
+                        .iter()
+                        .filter(|(name, _)| *name != "value")
+                        .map(|(name, value)| (name.to_string(), value.clone()))
+                        .collect(),
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => flatten_errors(&path, nested, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    flatten_errors(&format!("{path}[{index}]"), nested, out);
+                }
+            }
+        }
+    }
+}
+
+/// JSON body extractor that runs `validator::Validate` after deserializing.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Deserialize, Validate, ToSchema)]
+/// struct CreateProject {
+///     #[validate(length(min = 1, max = 120))]
+///     name: String,
+/// }
+///
+/// #[route(POST "/")]
+/// async fn create(ValidatedJson(body): ValidatedJson<CreateProject>) -> Result<Json<Project>> {
+///     // `body` is valid here
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(rejection_response)?;
+        value
+            .validate()
+            .map_err(|errors| ValidationErrorResponse::from(errors).into_response())?;
+        Ok(Self(value))
+    }
+}
+
+/// Query string extractor that runs `validator::Validate` after deserializing.
+///
+/// Invalid parameters return `422 Unprocessable Entity`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Deserialize, Validate, IntoParams)]
+/// struct ListParams {
+///     #[validate(range(min = 1, max = 100))]
+///     per_page: Option<u32>,
+/// }
+///
+/// #[route(GET "/")]
+/// async fn list(ValidatedQuery(params): ValidatedQuery<ListParams>) -> Result<Json<Vec<Project>>> {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(rejection_response)?;
+        value
+            .validate()
+            .map_err(|errors| ValidationErrorResponse::from(errors).into_response())?;
+        Ok(Self(value))
+    }
+}
+
+/// Path parameter extractor that runs `validator::Validate` after deserializing.
+///
+/// Invalid parameters return `400 Bad Request`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Deserialize, Validate)]
+/// struct ProjectPath {
+///     #[validate(length(equal = 8))]
+///     code: String,
+/// }
+///
+/// #[route(GET "/by-code/{code}")]
+/// async fn by_code(ValidatedPath(path): ValidatedPath<ProjectPath>) -> Result<Json<Project>> {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedPath<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidatedPath<T>
+where
+    T: DeserializeOwned + Validate + Send,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(value) = Path::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(rejection_response)?;
+        value.validate().map_err(|errors| {
+            ValidationErrorResponse::from(errors)
+                .with_status(StatusCode::BAD_REQUEST)
+                .into_response()
+        })?;
+        Ok(Self(value))
+    }
+}
+
+/// Validation constraints of a query or path parameter, mirrored from its
+/// `#[validate(...)]` attributes into the OpenAPI parameter schema.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterConstraint {
+    /// Parameter name
+    pub name: String,
+    /// `range(min = ..)`
+    pub minimum: Option<f64>,
+    /// `range(max = ..)`
+    pub maximum: Option<f64>,
+    /// `length(min = ..)`
+    pub min_length: Option<usize>,
+    /// `length(max = ..)`
+    pub max_length: Option<usize>,
+    /// `regex(path = ..)`
+    pub pattern: Option<String>,
+}
+
+impl ParameterConstraint {
+    /// Write the constraints into an inline parameter schema.
+    fn apply(&self, schema: &mut RefOr<Schema>) {
+        let RefOr::T(Schema::Object(object)) = schema else {
+            return;
+        };
+        if let Some(minimum) = self.minimum {
+            object.minimum = Some(Number::Float(minimum));
+        }
+        if let Some(maximum) = self.maximum {
+            object.maximum = Some(Number::Float(maximum));
+        }
+        if self.min_length.is_some() {
+            object.min_length = self.min_length;
+        }
+        if self.max_length.is_some() {
+            object.max_length = self.max_length;
+        }
+        if self.pattern.is_some() {
+            object.pattern = self.pattern.clone();
+        }
+    }
+}
+
+/// Register the `ValidationErrorResponse` schemas and the `ValidationFailed`
+/// and `InvalidPathParameters` response components.
+///
+/// Controllers returning `ValidationErrorResponse` from handlers reference
+/// them with `response_ref(VALIDATION_FAILED_RESPONSE)`.
+pub fn register_components(components: &mut Components) {
+    components
+        .schemas
+        .insert("FieldError".to_string(), FieldError::schema());
+    components.schemas.insert(
+        "ValidationErrorResponse".to_string(),
+        ValidationErrorResponse::schema(),
+    );
+
+    let problem = |description: &str| {
+        RefOr::T(
+            ResponseBuilder::new()
+                .description(description)
+                .content(
+                    PROBLEM_JSON,
+                    ContentBuilder::new()
+                        .schema(Some(Ref::from_schema_name("ValidationErrorResponse")))
+                        .build(),
+                )
+                .build(),
+        )
+    };
+    components.responses.insert(
+        VALIDATION_FAILED_RESPONSE.to_string(),
+        problem("Validation failed"),
+    );
+    components.responses.insert(
+        INVALID_PATH_PARAMETERS_RESPONSE.to_string(),
+        problem("Invalid path parameters"),
+    );
+}
+
+/// Register the validation components, document the validation response and
+/// apply the parameter constraints on the operations of routes using a
+/// validating extractor.
+pub fn apply_to_openapi(openapi: &mut OpenApi, routes: &[RouteValidation]) {
+    if routes.is_empty() {
+        return;
+    }
+
+    register_components(openapi.components.get_or_insert_with(Components::new));
+
+    for route in routes {
+        let Some(item) = openapi.paths.paths.get_mut(&route.path) else {
+            continue;
+        };
+        let operation = match route.method.to_uppercase().as_str() {
+            "GET" => &mut item.get,
+            "POST" => &mut item.post,
+            "PUT" => &mut item.put,
+            "DELETE" => &mut item.delete,
+            "PATCH" => &mut item.patch,
+            _ => continue,
+        };
+        if let Some(operation) = operation.as_mut() {
+            document_route(operation, route);
+        }
+    }
+}
+
+fn document_route(operation: &mut Operation, route: &RouteValidation) {
+    let responses = &mut operation.responses.responses;
+    if route.body || route.query {
+        responses
+            .entry("422".to_string())
+            .or_insert_with(|| response_ref(VALIDATION_FAILED_RESPONSE));
+    }
+    if route.path_params {
+        responses
+            .entry("400".to_string())
+            .or_insert_with(|| response_ref(INVALID_PATH_PARAMETERS_RESPONSE));
+    }
+
+    for parameter in operation.parameters.iter_mut().flatten() {
+        let Some(schema) = parameter.schema.as_mut() else {
+            continue;
+        };
+        for constraint in route.constraints.iter().filter(|c| c.name == parameter.name) {
+            constraint.apply(schema);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestClient;
+    use axum::{body::Body, routing::get, Router};
+    use utoipa::openapi::path::{
+        HttpMethod, OperationBuilder, ParameterBuilder, ParameterIn, PathItem,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct Member {
+        #[validate(email)]
+        email: String,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct CreateProject {
+        #[validate(length(min = 1, message = "must not be empty"))]
+        name: String,
+        #[validate(nested)]
+        members: Vec<Member>,
+    }
+
+    async fn extract(body: &str) -> Result<ValidatedJson<CreateProject>, Response> {
+        let req = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        ValidatedJson::<CreateProject>::from_request(req, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_is_extracted() {
+        let ValidatedJson(project) = extract(r#"{"name":"Apollo","members":[]}"#).await.unwrap();
+        assert_eq!(project.name, "Apollo");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_body_returns_422() {
+        let response = extract(r#"{"name":"","members":[{"email":"nope"}]}"#)
+            .await
+            .unwrap_err();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], PROBLEM_JSON);
+    }
+
+    #[test]
+    fn test_nested_errors_are_flattened() {
+        let project: CreateProject =
+            serde_json::from_str(r#"{"name":"","members":[{"email":"nope"}]}"#).unwrap();
+        let problem = ValidationErrorResponse::from(project.validate().unwrap_err());
+
+        assert_eq!(
+            problem.errors,
+            vec![
+                FieldError::new("members[0].email", "email"),
+                FieldError::new("name", "length")
+                    .message("must not be empty")
+                    .param("min", 1),
+            ]
+        );
+        assert_eq!(problem.detail, "2 invalid fields");
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct ListParams {
+        #[validate(range(min = 1, max = 100))]
+        per_page: u32,
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    struct CodePath {
+        #[validate(length(equal = 4))]
+        code: String,
+    }
+
+    fn client() -> TestClient {
+        TestClient::new(
+            Router::new()
+                .route(
+                    "/projects",
+                    get(|ValidatedQuery(params): ValidatedQuery<ListParams>| async move {
+                        params.per_page.to_string()
+                    }),
+                )
+                .route(
+                    "/projects/{code}",
+                    get(|ValidatedPath(path): ValidatedPath<CodePath>| async move { path.code }),
+                ),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_query_validation() {
+        let client = client();
+        client
+            .get("/projects?per_page=10")
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        client
+            .get("/projects?per_page=500")
+            .send()
+            .await
+            .assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_path_validation_returns_400() {
+        let response = client().get("/projects/toolong").send().await;
+
+        response.assert_status(StatusCode::BAD_REQUEST);
+        let problem: ValidationErrorResponse = response.json();
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.errors[0].field, "code");
+    }
+
+    #[test]
+    fn test_parameter_constraints_in_openapi() {
+        let operation = OperationBuilder::new()
+            .parameter(
+                ParameterBuilder::new()
+                    .name("per_page")
+                    .parameter_in(ParameterIn::Query)
+                    .schema(Some(u32::schema())),
+            )
+            .build();
+        let mut openapi = OpenApi::default();
+        openapi
+            .paths
+            .paths
+            .insert("/projects".to_string(), PathItem::new(HttpMethod::Get, operation));
+
+        apply_to_openapi(
+            &mut openapi,
+            &[RouteValidation {
+                method: "GET".to_string(),
+                path: "/projects".to_string(),
+                query: true,
+                constraints: vec![ParameterConstraint {
+                    name: "per_page".to_string(),
+                    maximum: Some(100.0),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        );
+
+        let operation = openapi.paths.paths["/projects"].get.as_ref().unwrap();
+        assert!(operation.responses.responses.contains_key("422"));
+        let parameter = &operation.parameters.as_ref().unwrap()[0];
+        let Some(RefOr::T(Schema::Object(schema))) = &parameter.schema else {
+            panic!("expected inline schema");
+        };
+        assert_eq!(schema.maximum, Some(Number::Float(100.0)));
+    }
+
+    #[test]
+    fn test_422_documented_on_validated_routes() {
+        let mut openapi = OpenApi::default();
+        openapi.paths.paths.insert(
+            "/projects".to_string(),
+            PathItem::new(HttpMethod::Post, OperationBuilder::new().build()),
+        );
+
+        apply_to_openapi(
+            &mut openapi,
+            &[RouteValidation {
+                method: "POST".to_string(),
+                path: "/projects".to_string(),
+                body: true,
+                ..Default::default()
+            }],
+        );
+
+        let operation = openapi.paths.paths["/projects"].post.as_ref().unwrap();
+        assert_eq!(
+            operation.responses.responses["422"],
+            response_ref(VALIDATION_FAILED_RESPONSE)
+        );
+        let components = openapi.components.unwrap();
+        assert!(components.schemas.contains_key("ValidationErrorResponse"));
+        assert!(components.responses.contains_key(VALIDATION_FAILED_RESPONSE));
+    }
+}
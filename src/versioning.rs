@@ -0,0 +1,260 @@
+//! API version groups and header-based versioning.
+//!
+//! `#[controller(version = "v1")]` and `#[route(version = 2)]` prefix routes
+//! with a `v{n}` path segment, as does `EywaApp::version` for every mounted
+//! controller. This module builds on that segment to:
+//! - split the spec into one document per version (`split_by_version`)
+//! - route requests carrying a version header (`Api-Version: 2`) to the
+//!   matching versioned route, without the segment in the URL (`VersionRewriter`)
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::Request,
+    http::{uri::PathAndQuery, HeaderName, Uri},
+};
+use utoipa::openapi::{OpenApi, Paths};
+
+/// Default header carrying the requested API version.
+pub const DEFAULT_VERSION_HEADER: &str = "api-version";
+
+/// Returns the index and number of the `v{n}` segment of a route path.
+pub fn path_version(path: &str) -> Option<(usize, u32)> {
+    segments(path).enumerate().find_map(|(index, segment)| {
+        segment
+            .strip_prefix('v')
+            .and_then(|n| n.parse().ok())
+            .map(|version| (index, version))
+    })
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Normalize a version prefix: `v1`, `/v1` and `/v1/` are all `/v1`.
+pub(crate) fn version_prefix(version: &str) -> String {
+    format!("/{}", version.trim_matches('/'))
+}
+
+/// Prefix a route path with a version prefix (`/v1` + `/projects`).
+pub(crate) fn prefix_path(prefix: &str, path: &str) -> String {
+    match path {
+        "" | "/" => prefix.to_string(),
+        path => format!("{prefix}{path}"),
+    }
+}
+
+/// Prefix every path of a spec with a version prefix.
+pub(crate) fn prefix_paths(openapi: &mut OpenApi, prefix: &str) {
+    openapi.paths.paths = std::mem::take(&mut openapi.paths.paths)
+        .into_iter()
+        .map(|(path, item)| (prefix_path(prefix, &path), item))
+        .collect();
+}
+
+/// Split a spec into one document per API version.
+///
+/// Each document keeps the info, tags and components of the full spec, and
+/// only the paths with the matching `v{n}` segment. Unversioned paths (health,
+/// docs) are left out of every group.
+pub fn split_by_version(openapi: &OpenApi) -> BTreeMap<u32, OpenApi> {
+    let mut groups: BTreeMap<u32, OpenApi> = BTreeMap::new();
+
+    for (path, item) in &openapi.paths.paths {
+        let Some((_, version)) = path_version(path) else {
+            continue;
+        };
+        groups
+            .entry(version)
+            .or_insert_with(|| {
+                let mut group = openapi.clone();
+                group.paths = Paths::new();
+                group
+            })
+            .paths
+            .paths
+            .insert(path.clone(), item.clone());
+    }
+
+    groups
+}
+
+struct VersionedRoute {
+    version: u32,
+    /// Index of the `v{n}` segment in the route path
+    index: usize,
+    /// Route segments without the version segment
+    segments: Vec<String>,
+}
+
+impl VersionedRoute {
+    /// Match a request path (without version segment) against the route template.
+    fn matches(&self, path: &str) -> bool {
+        let mut request = segments(path);
+        self.segments.iter().all(|segment| match request.next() {
+            Some(actual) => {
+                (segment.starts_with('{') && segment.ends_with('}')) || segment == actual
+            }
+            None => false,
+        }) && request.next().is_none()
+    }
+}
+
+/// Rewrites requests carrying a version header to the matching versioned route.
+///
+/// `GET /api/projects` with `Api-Version: 2` is routed to `/api/v2/projects`
+/// when that route exists. Requests without the header, with an unknown
+/// version, or already containing a version segment are left unchanged.
+pub struct VersionRewriter {
+    header: HeaderName,
+    routes: Vec<VersionedRoute>,
+}
+
+impl VersionRewriter {
+    /// Create a rewriter for the versioned paths of the spec.
+    pub fn new(header: HeaderName, openapi: &OpenApi) -> Self {
+        let routes = openapi
+            .paths
+            .paths
+            .keys()
+            .filter_map(|path| {
+                let (index, version) = path_version(path)?;
+                let segments = segments(path)
+                    .enumerate()
+                    .filter(|(i, _)| *i != index)
+                    .map(|(_, segment)| segment.to_string())
+                    .collect();
+                Some(VersionedRoute {
+                    version,
+                    index,
+                    segments,
+                })
+            })
+            .collect();
+        Self { header, routes }
+    }
+
+    /// Returns the rewritten path for a request path and requested version.
+    fn versioned_path(&self, path: &str, version: u32) -> Option<String> {
+        if path_version(path).is_some() {
+            return None;
+        }
+        let route = self
+            .routes
+            .iter()
+            .find(|route| route.version == version && route.matches(path))?;
+
+        let mut segments: Vec<&str> = segments(path).collect();
+        let version_segment = format!("v{version}");
+        segments.insert(route.index, &version_segment);
+        Some(format!("/{}", segments.join("/")))
+    }
+
+    /// Rewrite the request URI in place if it targets a versioned route.
+    pub fn rewrite(&self, req: &mut Request) {
+        let Some(version) = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().trim_start_matches('v').parse::<u32>().ok())
+        else {
+            return;
+        };
+        let Some(path) = self.versioned_path(req.uri().path(), version) else {
+            return;
+        };
+
+        let path_and_query = match req.uri().query() {
+            Some(query) => format!("{path}?{query}"),
+            None => path,
+        };
+        let mut parts = req.uri().clone().into_parts();
+        parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *req.uri_mut() = uri;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    fn spec() -> OpenApi {
+        let mut openapi = OpenApi::default();
+        for path in ["/api/v1/projects/{id}", "/api/v2/projects/{id}", "/health"] {
+            openapi.paths.paths.insert(
+                path.to_string(),
+                PathItem::new(HttpMethod::Get, OperationBuilder::new().build()),
+            );
+        }
+        openapi
+    }
+
+    fn rewriter() -> VersionRewriter {
+        VersionRewriter::new(HeaderName::from_static(DEFAULT_VERSION_HEADER), &spec())
+    }
+
+    #[test]
+    fn test_path_version() {
+        assert_eq!(path_version("/api/v2/projects"), Some((1, 2)));
+        assert_eq!(path_version("/v1/projects"), Some((0, 1)));
+        assert_eq!(path_version("/health"), None);
+        assert_eq!(path_version("/videos"), None);
+    }
+
+    #[test]
+    fn test_prefix_paths() {
+        assert_eq!(version_prefix("v1/"), "/v1");
+        assert_eq!(prefix_path("/v1", "/"), "/v1");
+
+        let mut openapi = spec();
+        prefix_paths(&mut openapi, &version_prefix("v3"));
+        assert!(openapi.paths.paths.contains_key("/v3/health"));
+        assert_eq!(path_version("/v3/health"), Some((0, 3)));
+    }
+
+    #[test]
+    fn test_split_by_version() {
+        let groups = split_by_version(&spec());
+
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(groups[&2].paths.paths.contains_key("/api/v2/projects/{id}"));
+        assert_eq!(groups[&2].paths.paths.len(), 1);
+    }
+
+    #[test]
+    fn test_header_rewrites_to_versioned_route() {
+        let rewriter = rewriter();
+
+        let mut req = Request::builder()
+            .uri("/api/projects/42?expand=tasks")
+            .header(DEFAULT_VERSION_HEADER, "2")
+            .body(Body::empty())
+            .unwrap();
+        rewriter.rewrite(&mut req);
+        assert_eq!(req.uri(), "/api/v2/projects/42?expand=tasks");
+    }
+
+    #[test]
+    fn test_unknown_routes_and_versions_are_unchanged() {
+        let rewriter = rewriter();
+
+        assert_eq!(rewriter.versioned_path("/health", 2), None);
+        assert_eq!(rewriter.versioned_path("/api/projects/42", 3), None);
+        assert_eq!(rewriter.versioned_path("/api/v1/projects/42", 2), None);
+    }
+
+    #[test]
+    fn test_invalid_header_name_fails_the_build() {
+        let result = crate::EywaApp::new(()).header_versioning("api version").into_router();
+
+        let Err(eywa_errors::AppError::InternalServerError(message)) = result else {
+            panic!("expected the build to fail");
+        };
+        assert!(message.contains("'api version'"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Route visibility in the OpenAPI specs.
+//!
+//! - **Hidden** routes (`#[route(hidden)]`, `EywaApp::hide_path`) stay
+//!   registered in the router but are removed from every generated spec (and
+//!   therefore from the docs UIs, per-version specs, generated clients and
+//!   mock mode).
+//! - **Internal** routes (`#[route(internal)]`, `EywaApp::internal_path`) are
+//!   removed from the public spec served at `/scalar`, and only documented in
+//!   the internal spec served at `/scalar/internal` (see `EywaApp::internal_docs`).
+
+use std::collections::HashSet;
+
+use utoipa::openapi::OpenApi;
+
+/// Returns `true` if `path` matches the glob `pattern`.
+///
+/// `*` matches within a single path segment, `**` across segments:
+/// `/internal/**` matches every route below `/internal`, `/debug/*` only its
+/// direct children.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+            [b'*', rest @ ..] => (0..=text.len())
+                .take_while(|&i| i == 0 || text[i - 1] != b'/')
+                .any(|i| matches(rest, &text[i..])),
+            [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Operations and path globs selecting routes of the spec.
+#[derive(Debug, Clone, Default)]
+pub struct RouteSet {
+    routes: HashSet<(String, String)>,
+    patterns: Vec<String>,
+}
+
+impl RouteSet {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a single operation.
+    pub fn insert(&mut self, method: &str, path: impl Into<String>) {
+        self.routes.insert((method.to_uppercase(), path.into()));
+    }
+
+    /// Add every operation whose path matches the glob.
+    pub fn insert_pattern(&mut self, pattern: impl Into<String>) {
+        self.patterns.push(pattern.into());
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Returns `true` if `method` on the route template `path` is in the set.
+    pub fn contains(&self, method: &str, path: &str) -> bool {
+        self.routes
+            .contains(&(method.to_uppercase(), path.to_string()))
+            || self.patterns.iter().any(|pattern| glob_match(pattern, path))
+    }
+
+    /// Remove the operations in the set, and paths left without operations.
+    pub fn remove_from_openapi(&self, openapi: &mut OpenApi) {
+        if self.is_empty() {
+            return;
+        }
+
+        openapi.paths.paths.retain(|path, item| {
+            let operations = [
+                ("GET", &mut item.get),
+                ("POST", &mut item.post),
+                ("PUT", &mut item.put),
+                ("DELETE", &mut item.delete),
+                ("PATCH", &mut item.patch),
+                ("HEAD", &mut item.head),
+                ("OPTIONS", &mut item.options),
+                ("TRACE", &mut item.trace),
+            ];
+            let mut remaining = false;
+            for (method, operation) in operations {
+                if operation.is_some() && self.contains(method, path) {
+                    *operation = None;
+                }
+                remaining |= operation.is_some();
+            }
+            remaining
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{HttpMethod, OperationBuilder, PathItem};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("/internal/**", "/internal/cache/flush"));
+        assert!(glob_match("/debug/*", "/debug/pprof"));
+        assert!(!glob_match("/debug/*", "/debug/pprof/heap"));
+        assert!(glob_match("/api/*/debug", "/api/v1/debug"));
+        assert!(!glob_match("/internal/**", "/api/v1/projects"));
+    }
+
+    #[test]
+    fn test_operations_removed() {
+        let mut openapi = OpenApi::default();
+        let mut projects = PathItem::new(HttpMethod::Get, OperationBuilder::new().build());
+        projects.delete = Some(OperationBuilder::new().build());
+        openapi
+            .paths
+            .paths
+            .insert("/api/v1/projects".to_string(), projects);
+        openapi.paths.paths.insert(
+            "/internal/cache".to_string(),
+            PathItem::new(HttpMethod::Post, OperationBuilder::new().build()),
+        );
+
+        let mut hidden = RouteSet::new();
+        hidden.insert("delete", "/api/v1/projects");
+        hidden.insert_pattern("/internal/**");
+        hidden.remove_from_openapi(&mut openapi);
+
+        assert_eq!(openapi.paths.paths.len(), 1);
+        let projects = &openapi.paths.paths["/api/v1/projects"];
+        assert!(projects.get.is_some());
+        assert!(projects.delete.is_none());
+    }
+}
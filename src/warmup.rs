@@ -0,0 +1,133 @@
+//! Warmup tasks run before the service reports ready.
+//!
+//! Priming caches, opening connection pools or loading models on the first
+//! real request makes that request (and every one queued behind it) slow.
+//! Tasks registered with `EywaApp::warmup` run once the listener is bound;
+//! until they finish, `/health/ready` and `/health/startup` answer `503`, so
+//! Kubernetes doesn't route traffic to the pod yet.
+//!
+//! Tasks run concurrently. A failed task is logged and doesn't block
+//! readiness: a cold service is better than one that never becomes ready.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use futures_util::future::{join_all, BoxFuture};
+use futures_util::FutureExt;
+
+use crate::Result;
+
+type WarmupFn = Box<dyn FnOnce() -> BoxFuture<'static, Result<()>> + Send>;
+
+/// Whether the warmup tasks have finished, shared with the health probes.
+#[derive(Debug, Clone, Default)]
+pub struct WarmupProgress(Arc<AtomicBool>);
+
+impl WarmupProgress {
+    /// Returns `true` once every warmup task has finished.
+    pub fn is_complete(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn complete(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Warmup tasks of the application.
+#[derive(Default)]
+pub struct Warmup {
+    tasks: Vec<(String, WarmupFn)>,
+    progress: WarmupProgress,
+}
+
+impl Warmup {
+    /// Create an empty task list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a task.
+    pub fn add<F, Fut>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.tasks.push((name.into(), Box::new(move || task().boxed())));
+    }
+
+    /// Progress of the tasks.
+    pub fn progress(&self) -> WarmupProgress {
+        self.progress.clone()
+    }
+
+    /// Run every task concurrently, then mark the warmup complete.
+    pub async fn run(self) {
+        let started = Instant::now();
+        let count = self.tasks.len();
+        join_all(self.tasks.into_iter().map(|(name, task)| async move {
+            let started = Instant::now();
+            match task().await {
+                Ok(()) => tracing::info!(
+                    task = %name,
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "warmup task completed"
+                ),
+                Err(e) => tracing::warn!(task = %name, error = ?e, "warmup task failed"),
+            }
+        }))
+        .await;
+
+        if count > 0 {
+            tracing::info!(
+                tasks = count,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "warmup completed"
+            );
+        }
+        self.progress.complete();
+    }
+
+    /// Run the tasks in the background (immediately complete without tasks).
+    pub fn spawn(self) {
+        if self.tasks.is_empty() {
+            self.progress.complete();
+        } else {
+            tokio::spawn(self.run());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_complete_after_every_task() {
+        let mut warmup = Warmup::new();
+        warmup.add("cache", || async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        });
+        warmup.add("model", || async {
+            Err(eywa_errors::AppError::InternalServerError("model missing".to_string()))
+        });
+        let progress = warmup.progress();
+
+        warmup.spawn();
+        assert!(!progress.is_complete());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(progress.is_complete());
+    }
+
+    #[test]
+    fn test_no_tasks_complete_immediately() {
+        let warmup = Warmup::new();
+        let progress = warmup.progress();
+        warmup.spawn();
+        assert!(progress.is_complete());
+    }
+}
@@ -0,0 +1,854 @@
+//! Webhook subscriptions managed by API consumers.
+//!
+//! Consumers register the URLs their webhooks are sent to, and the events
+//! each URL receives. Subscriptions are stored in an
+//! `eywa_webhook_subscriptions` table and belong to the consumer that
+//! created them, identified by the `sub` claim of its verified token (see
+//! `WebhookSubscriptions::owner_claim`).
+//!
+//! A URL only receives events once its owner proved they control it: on
+//! creation, a challenge is `POST`ed to the URL,
+//!
+//! ```json
+//! {"type": "webhook.verification", "challenge": "3kf9a2xq..."}
+//! ```
+//!
+//! and the subscription becomes `active` when the endpoint answers `2xx`
+//! with the challenge, as plain text or as `{"challenge": "..."}`. Otherwise
+//! it stays `pending_verification` until verified again.
+//!
+//! The challenge is only sent to public addresses, without following
+//! redirects, so subscriptions can't probe the internal network. Why a
+//! verification failed is logged, not returned to the consumer.
+//!
+//! - `WebhookSubscriptions` - Create, list, verify and delete subscriptions;
+//!   `subscribers(event)` returns the active ones for delivery
+//! - `WebhookController` - Consumer endpoints, mounted with
+//!   `EywaApp::webhook_endpoints` and requiring a token verified by
+//!   `EywaApp::auth` with the `webhooks:manage` scope:
+//!   - `POST /webhooks` - Subscribe a URL to events
+//!   - `GET /webhooks` - List the caller's subscriptions
+//!   - `POST /webhooks/{id}/verify` - Send the challenge again
+//!   - `DELETE /webhooks/{id}` - Unsubscribe
+//!
+//! The signing secret is generated unless given, and only returned when the
+//! subscription is created.
+
+use std::collections::BTreeSet;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, post},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    ModelTrait, QueryFilter, QueryOrder, Schema, Set,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{PartialSchema, ToSchema};
+use uuid::Uuid;
+
+use eywa_errors::AppError;
+
+use crate::middleware::auth::VerifiedClaims;
+use crate::Result;
+
+/// Scope required to call the webhook subscription endpoints.
+pub const WEBHOOKS_SCOPE: &str = "webhooks:manage";
+
+/// Default claim identifying the consumer owning subscriptions.
+pub const DEFAULT_OWNER_CLAIM: &str = "sub";
+
+/// Event matching every event.
+pub const ALL_EVENTS: &str = "*";
+
+/// Shortest secret accepted from a consumer.
+const MIN_SECRET_LENGTH: usize = 16;
+
+/// Random bytes in generated secrets and challenges.
+const TOKEN_BYTES: usize = 24;
+
+/// How long the URL has to answer the challenge.
+const CHALLENGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Verification error returned to consumers, whatever went wrong.
+const VERIFICATION_FAILED: &str = "The URL didn't answer the verification challenge";
+
+/// sea_orm entity of the `eywa_webhook_subscriptions` table.
+pub mod entity {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "eywa_webhook_subscriptions")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        /// Consumer owning the subscription
+        #[sea_orm(indexed)]
+        pub owner: String,
+        pub url: String,
+        /// Space-separated event names
+        pub events: String,
+        /// Signing secret of the deliveries
+        pub secret: String,
+        /// `pending_verification` or `active`
+        pub status: String,
+        pub created_at: DateTimeUtc,
+        pub verified_at: Option<DateTimeUtc>,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+/// Verification status of a subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookStatus {
+    /// The URL didn't answer the challenge yet; no events are sent
+    PendingVerification,
+    /// The URL answered the challenge and receives events
+    Active,
+}
+
+impl WebhookStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::PendingVerification => "pending_verification",
+            Self::Active => "active",
+        }
+    }
+
+    fn parse(status: &str) -> Self {
+        match status {
+            "active" => Self::Active,
+            _ => Self::PendingVerification,
+        }
+    }
+}
+
+/// Webhook subscription request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateWebhookSubscription {
+    #[schema(example = "https://hooks.example.com/eywa")]
+    pub url: String,
+    /// Events sent to the URL, `*` for all
+    #[schema(example = json!(["project.created", "project.deleted"]))]
+    pub events: Vec<String>,
+    /// Signing secret (at least 16 characters), generated if missing
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Webhook subscription (never includes the secret)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WebhookSubscriptionInfo {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub status: WebhookStatus,
+    pub created_at: DateTime<Utc>,
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+impl From<&entity::Model> for WebhookSubscriptionInfo {
+    fn from(model: &entity::Model) -> Self {
+        Self {
+            id: model.id,
+            url: model.url.clone(),
+            events: model.events.split_whitespace().map(str::to_string).collect(),
+            status: WebhookStatus::parse(&model.status),
+            created_at: model.created_at,
+            verified_at: model.verified_at,
+        }
+    }
+}
+
+/// A new subscription; the only time its secret is returned
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreatedWebhookSubscription {
+    pub secret: String,
+    pub subscription: WebhookSubscriptionInfo,
+    /// Why the URL failed verification, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_error: Option<String>,
+}
+
+/// An active subscription, as needed to deliver an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookSubscriber {
+    pub id: Uuid,
+    pub owner: String,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Challenge sent to a URL to verify its ownership.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::rng().fill(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn store_error(e: DbErr) -> AppError {
+    AppError::InternalServerError(format!("Webhook subscription store failed: {e}"))
+}
+
+fn not_found(id: Uuid) -> AppError {
+    AppError::BadRequest(format!("Webhook subscription {id} not found"))
+}
+
+/// Whether the body of a challenge response echoes the challenge.
+fn echoes(body: &str, challenge: &str) -> bool {
+    if body.trim() == challenge {
+        return true;
+    }
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("challenge")?.as_str().map(|echo| echo == challenge))
+        .unwrap_or(false)
+}
+
+/// Whether `ip` is routable on the public internet.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                // Shared address space (RFC 6598)
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local (fc00::/7) and link-local (fe80::/10)
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Webhook subscriptions persisted in the `eywa_webhook_subscriptions` table.
+#[derive(Debug, Clone)]
+pub struct WebhookSubscriptions {
+    db: DatabaseConnection,
+    events: BTreeSet<String>,
+    owner_claim: String,
+    allow_http: bool,
+    allow_private_networks: bool,
+}
+
+impl WebhookSubscriptions {
+    /// Create a store on the given connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            events: BTreeSet::new(),
+            owner_claim: DEFAULT_OWNER_CLAIM.to_string(),
+            allow_http: false,
+            allow_private_networks: false,
+        }
+    }
+
+    /// Only accept subscriptions to these events (any event name by default).
+    pub fn events<I, T>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.events = events.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Identify the consumer by another claim of its token (e.g. `tenant_id`).
+    pub fn owner_claim(mut self, claim: impl Into<String>) -> Self {
+        self.owner_claim = claim.into();
+        self
+    }
+
+    /// Accept `http://` URLs, e.g. in development; only `https://` otherwise.
+    pub fn allow_http(mut self, allow: bool) -> Self {
+        self.allow_http = allow;
+        self
+    }
+
+    /// Send challenges to loopback and private addresses, e.g. in development.
+    pub fn allow_private_networks(mut self, allow: bool) -> Self {
+        self.allow_private_networks = allow;
+        self
+    }
+
+    /// Create the `eywa_webhook_subscriptions` table and its owner index, if missing.
+    ///
+    /// Services with their own migrations can use
+    /// `migrations::webhook_subscriptions()` instead.
+    pub async fn create_table(&self) -> Result<()> {
+        let backend = self.db.get_database_backend();
+        let schema = Schema::new(backend);
+
+        let mut table = schema.create_table_from_entity(entity::Entity);
+        table.if_not_exists();
+        self.db.execute(backend.build(&table)).await.map_err(store_error)?;
+        for mut index in schema.create_index_from_entity(entity::Entity) {
+            index.if_not_exists();
+            self.db.execute(backend.build(&index)).await.map_err(store_error)?;
+        }
+        Ok(())
+    }
+
+    /// The consumer making a request, from its verified token.
+    fn owner(&self, claims: Option<&VerifiedClaims>) -> Result<String> {
+        let owner = match claims.and_then(|claims| claims.get(&self.owner_claim)) {
+            Some(serde_json::Value::String(owner)) => owner.clone(),
+            Some(serde_json::Value::Number(owner)) => owner.to_string(),
+            _ => String::new(),
+        };
+        if owner.is_empty() {
+            return Err(AppError::Unauthorized(format!(
+                "Missing {} claim in the bearer token",
+                self.owner_claim
+            )));
+        }
+        Ok(owner)
+    }
+
+    fn validate(&self, request: &CreateWebhookSubscription) -> Result<()> {
+        let url = url::Url::parse(&request.url)
+            .map_err(|e| AppError::BadRequest(format!("Invalid webhook URL: {e}")))?;
+        match url.scheme() {
+            "https" => {}
+            "http" if self.allow_http => {}
+            _ => return Err(AppError::BadRequest("Webhook URLs must use https".to_string())),
+        }
+
+        if request.events.is_empty() {
+            return Err(AppError::BadRequest("At least one event is required".to_string()));
+        }
+        let unknown: Vec<&str> = request
+            .events
+            .iter()
+            .map(String::as_str)
+            .filter(|event| {
+                *event != ALL_EVENTS && !self.events.is_empty() && !self.events.contains(*event)
+            })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(AppError::BadRequest(format!("Unknown events: {}", unknown.join(", "))));
+        }
+
+        if request
+            .secret
+            .as_ref()
+            .is_some_and(|secret| secret.len() < MIN_SECRET_LENGTH)
+        {
+            return Err(AppError::BadRequest(format!(
+                "Webhook secrets must have at least {MIN_SECRET_LENGTH} characters"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Subscribe a URL of `owner` to events, then send it the challenge.
+    pub async fn create(
+        &self,
+        owner: &str,
+        request: CreateWebhookSubscription,
+    ) -> Result<CreatedWebhookSubscription> {
+        self.validate(&request)?;
+        let secret = request.secret.unwrap_or_else(random_token);
+
+        let model = entity::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            owner: Set(owner.to_string()),
+            url: Set(request.url),
+            events: Set(request.events.join(" ")),
+            secret: Set(secret.clone()),
+            status: Set(WebhookStatus::PendingVerification.as_str().to_string()),
+            created_at: Set(Utc::now()),
+            verified_at: Set(None),
+        }
+        .insert(&self.db)
+        .await
+        .map_err(store_error)?;
+        tracing::info!(owner, url = model.url, "🪝 Webhook subscription created");
+
+        let (model, verification_error) = self.challenge(model).await?;
+        Ok(CreatedWebhookSubscription {
+            secret,
+            subscription: (&model).into(),
+            verification_error,
+        })
+    }
+
+    /// Subscriptions of `owner`, most recent first.
+    pub async fn list(&self, owner: &str) -> Result<Vec<WebhookSubscriptionInfo>> {
+        let models = entity::Entity::find()
+            .filter(entity::Column::Owner.eq(owner))
+            .order_by_desc(entity::Column::CreatedAt)
+            .all(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(models.iter().map(WebhookSubscriptionInfo::from).collect())
+    }
+
+    async fn find(&self, owner: &str, id: Uuid) -> Result<entity::Model> {
+        entity::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(store_error)?
+            .filter(|model| model.owner == owner)
+            .ok_or_else(|| not_found(id))
+    }
+
+    /// Send the challenge of a subscription of `owner` again.
+    ///
+    /// Fails with `400` if the URL doesn't answer it.
+    pub async fn verify(&self, owner: &str, id: Uuid) -> Result<WebhookSubscriptionInfo> {
+        let model = self.find(owner, id).await?;
+        match self.challenge(model).await? {
+            (model, None) => Ok((&model).into()),
+            (_, Some(error)) => Err(AppError::BadRequest(error)),
+        }
+    }
+
+    /// Delete a subscription of `owner`.
+    pub async fn delete(&self, owner: &str, id: Uuid) -> Result<()> {
+        let model = self.find(owner, id).await?;
+        let url = model.url.clone();
+        model.delete(&self.db).await.map_err(store_error)?;
+        tracing::info!(owner, url, "🪝 Webhook subscription deleted");
+        Ok(())
+    }
+
+    /// Active subscriptions receiving `event`, for delivery.
+    pub async fn subscribers(&self, event: &str) -> Result<Vec<WebhookSubscriber>> {
+        let models = entity::Entity::find()
+            .filter(entity::Column::Status.eq(WebhookStatus::Active.as_str()))
+            .all(&self.db)
+            .await
+            .map_err(store_error)?;
+        Ok(models
+            .into_iter()
+            .filter(|model| {
+                model
+                    .events
+                    .split_whitespace()
+                    .any(|subscribed| subscribed == event || subscribed == ALL_EVENTS)
+            })
+            .map(|model| WebhookSubscriber {
+                id: model.id,
+                owner: model.owner,
+                url: model.url,
+                secret: model.secret,
+            })
+            .collect())
+    }
+
+    /// Resolve the host of `url`, refusing non-public addresses.
+    ///
+    /// Returns the domain to pin to the address, unless the host is an IP.
+    async fn resolve(
+        &self,
+        url: &str,
+    ) -> std::result::Result<(Option<String>, SocketAddr), String> {
+        let url = url::Url::parse(url).map_err(|e| format!("invalid URL: {e}"))?;
+        let port = url.port_or_known_default().ok_or("no port")?;
+        let (domain, addrs): (_, Vec<SocketAddr>) = match url.host() {
+            Some(url::Host::Ipv4(ip)) => (None, vec![SocketAddr::new(ip.into(), port)]),
+            Some(url::Host::Ipv6(ip)) => (None, vec![SocketAddr::new(ip.into(), port)]),
+            Some(url::Host::Domain(domain)) => {
+                let addrs = tokio::net::lookup_host((domain, port))
+                    .await
+                    .map_err(|e| format!("DNS lookup failed: {e}"))?;
+                (Some(domain.to_string()), addrs.collect())
+            }
+            None => return Err("no host".to_string()),
+        };
+
+        let addr = *addrs.first().ok_or("the host has no address")?;
+        if !self.allow_private_networks && addrs.iter().any(|addr| !is_public(addr.ip())) {
+            return Err(format!("the host resolves to the non-public address {}", addr.ip()));
+        }
+        Ok((domain, addr))
+    }
+
+    /// Send `challenge` to `url`; `Err` says why it wasn't echoed.
+    async fn send_challenge(&self, url: &str, challenge: &str) -> std::result::Result<(), String> {
+        let (domain, addr) = self.resolve(url).await?;
+        let mut client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .timeout(CHALLENGE_TIMEOUT);
+        // Connect to the checked address rather than to a second DNS answer
+        if let Some(domain) = &domain {
+            client = client.resolve(domain, addr);
+        }
+        let client = client.build().map_err(|e| format!("HTTP client failed: {e}"))?;
+
+        let response = client
+            .post(url)
+            .json(&Challenge {
+                kind: "webhook.verification".to_string(),
+                challenge: challenge.to_string(),
+            })
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("answered {}", response.status()));
+        }
+        let body = response.text().await.unwrap_or_default();
+        if !echoes(&body, challenge) {
+            return Err("the response didn't echo the challenge".to_string());
+        }
+        Ok(())
+    }
+
+    /// Send a challenge to the URL of a subscription, activating it if echoed.
+    ///
+    /// Returns the subscription and, if verification failed, a generic error.
+    async fn challenge(&self, model: entity::Model) -> Result<(entity::Model, Option<String>)> {
+        let challenge = random_token();
+        if let Err(error) = self.send_challenge(&model.url, &challenge).await {
+            // Only logged: the details would tell consumers about internal hosts
+            tracing::warn!(url = model.url, "🪝 Webhook URL verification failed: {}", error);
+            return Ok((model, Some(VERIFICATION_FAILED.to_string())));
+        }
+
+        let mut active: entity::ActiveModel = model.into();
+        active.status = Set(WebhookStatus::Active.as_str().to_string());
+        active.verified_at = Set(Some(Utc::now()));
+        let model = active.update(&self.db).await.map_err(store_error)?;
+        tracing::info!(url = model.url, "🪝 Webhook URL verified");
+        Ok((model, None))
+    }
+}
+
+/// Subscribe to webhooks
+///
+/// Sends a verification challenge to the URL: the subscription only
+/// receives events once the URL echoed it. Returns the signing secret; it
+/// can't be retrieved afterwards.
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "Webhooks",
+    request_body = CreateWebhookSubscription,
+    responses(
+        (status = 201, description = "Subscription created", body = CreatedWebhookSubscription)
+    )
+)]
+pub async fn create(
+    State(store): State<Arc<WebhookSubscriptions>>,
+    claims: Option<Extension<VerifiedClaims>>,
+    Json(request): Json<CreateWebhookSubscription>,
+) -> Result<(StatusCode, Json<CreatedWebhookSubscription>)> {
+    let owner = store.owner(claims.as_deref())?;
+    Ok((StatusCode::CREATED, Json(store.create(&owner, request).await?)))
+}
+
+/// List webhook subscriptions
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "Webhooks",
+    responses(
+        (
+            status = 200,
+            description = "The caller's subscriptions, most recent first",
+            body = Vec<WebhookSubscriptionInfo>
+        )
+    )
+)]
+pub async fn list(
+    State(store): State<Arc<WebhookSubscriptions>>,
+    claims: Option<Extension<VerifiedClaims>>,
+) -> Result<Json<Vec<WebhookSubscriptionInfo>>> {
+    let owner = store.owner(claims.as_deref())?;
+    Ok(Json(store.list(&owner).await?))
+}
+
+/// Verify a webhook URL
+///
+/// Sends the verification challenge again, e.g. once the endpoint is fixed.
+#[utoipa::path(
+    post,
+    path = "/webhooks/{id}/verify",
+    tag = "Webhooks",
+    params(("id" = Uuid, Path, description = "Subscription ID")),
+    responses(
+        (status = 200, description = "URL verified", body = WebhookSubscriptionInfo)
+    )
+)]
+pub async fn verify(
+    State(store): State<Arc<WebhookSubscriptions>>,
+    claims: Option<Extension<VerifiedClaims>>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookSubscriptionInfo>> {
+    let owner = store.owner(claims.as_deref())?;
+    Ok(Json(store.verify(&owner, id).await?))
+}
+
+/// Unsubscribe from webhooks
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "Webhooks",
+    params(("id" = Uuid, Path, description = "Subscription ID")),
+    responses(
+        (status = 204, description = "Subscription deleted")
+    )
+)]
+pub async fn unsubscribe(
+    State(store): State<Arc<WebhookSubscriptions>>,
+    claims: Option<Extension<VerifiedClaims>>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    let owner = store.owner(claims.as_deref())?;
+    store.delete(&owner, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub struct WebhookController;
+
+impl WebhookController {
+    /// Methods and paths of the subscription endpoints.
+    pub const ROUTES: [(&'static str, &'static str); 4] = [
+        ("POST", "/webhooks"),
+        ("GET", "/webhooks"),
+        ("POST", "/webhooks/{id}/verify"),
+        ("DELETE", "/webhooks/{id}"),
+    ];
+
+    /// Build the webhook subscription router for the given store.
+    pub fn router<S>(store: WebhookSubscriptions) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/webhooks", post(create).get(list))
+            .route("/webhooks/{id}/verify", post(verify))
+            .route("/webhooks/{id}", delete(unsubscribe))
+            .with_state(Arc::new(store))
+    }
+
+    /// Register paths in the OpenAPI spec.
+    pub fn register_paths(openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::Path;
+
+        let paths = &mut openapi.paths;
+        for (path, methods, operation) in [
+            (
+                <__path_create as Path>::path(),
+                <__path_create as Path>::methods(),
+                <__path_create as Path>::operation(),
+            ),
+            (
+                <__path_list as Path>::path(),
+                <__path_list as Path>::methods(),
+                <__path_list as Path>::operation(),
+            ),
+            (
+                <__path_verify as Path>::path(),
+                <__path_verify as Path>::methods(),
+                <__path_verify as Path>::operation(),
+            ),
+            (
+                <__path_unsubscribe as Path>::path(),
+                <__path_unsubscribe as Path>::methods(),
+                <__path_unsubscribe as Path>::operation(),
+            ),
+        ] {
+            paths.add_path_operation(path, methods, operation);
+        }
+    }
+
+    /// Register schemas used by this controller.
+    pub fn register_schemas(components: &mut utoipa::openapi::Components) {
+        for (name, schema) in [
+            ("WebhookStatus", WebhookStatus::schema()),
+            ("CreateWebhookSubscription", CreateWebhookSubscription::schema()),
+            ("WebhookSubscriptionInfo", WebhookSubscriptionInfo::schema()),
+            ("CreatedWebhookSubscription", CreatedWebhookSubscription::schema()),
+        ] {
+            components.schemas.insert(name.to_string(), schema);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, events: &[&str]) -> CreateWebhookSubscription {
+        CreateWebhookSubscription {
+            url: url.to_string(),
+            events: events.iter().map(|event| event.to_string()).collect(),
+            secret: None,
+        }
+    }
+
+    #[test]
+    fn test_challenge_echo() {
+        assert!(echoes("abc\n", "abc"));
+        assert!(echoes(r#"{"challenge":"abc"}"#, "abc"));
+        assert!(!echoes(r#"{"challenge":"abd"}"#, "abc"));
+        assert!(!echoes("ok", "abc"));
+    }
+
+    #[test]
+    fn test_owner_from_verified_claims() {
+        let config = crate::JwtConfig::new("s3cret");
+        let verifier = crate::JwtVerifier::new(&config);
+        let exp = Utc::now().timestamp() + 300;
+        let claims = |claims: serde_json::Value| {
+            verifier.verify(&config.sign(&claims).unwrap(), Utc::now()).unwrap()
+        };
+        let store = WebhookSubscriptions::new(DatabaseConnection::Disconnected);
+
+        let acme = claims(serde_json::json!({ "sub": "acme", "tenant_id": 7, "exp": exp }));
+        assert_eq!(store.owner(Some(&acme)).unwrap(), "acme");
+        assert_eq!(store.clone().owner_claim("tenant_id").owner(Some(&acme)).unwrap(), "7");
+
+        let anonymous = claims(serde_json::json!({ "exp": exp }));
+        assert!(store.owner(Some(&anonymous)).is_err());
+        assert!(store.owner(None).is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        let store = WebhookSubscriptions::new(DatabaseConnection::Disconnected)
+            .events(["project.created", "project.deleted"]);
+
+        assert!(store.validate(&request("https://hooks.example", &["*"])).is_ok());
+        assert!(store.validate(&request("http://hooks.example", &["*"])).is_err());
+        assert!(store.validate(&request("https://hooks.example", &[])).is_err());
+        assert!(store.validate(&request("https://hooks.example", &["project.moved"])).is_err());
+
+        let mut weak = request("https://hooks.example", &["project.created"]);
+        weak.secret = Some("short".to_string());
+        assert!(store.validate(&weak).is_err());
+    }
+
+    #[test]
+    fn test_public_addresses() {
+        for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.7",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_challenges_only_reach_public_addresses() {
+        let store = WebhookSubscriptions::new(DatabaseConnection::Disconnected);
+        for url in [
+            "https://127.0.0.1/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]:8443/hooks",
+            "https://localhost/hooks",
+        ] {
+            assert!(store.resolve(url).await.is_err(), "{url}");
+        }
+
+        let store = store.allow_private_networks(true);
+        let (domain, addr) = store.resolve("https://127.0.0.1:8443/hooks").await.unwrap();
+        assert_eq!((domain, addr), (None, "127.0.0.1:8443".parse().unwrap()));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_subscription_lifecycle() {
+        use axum::routing::post;
+
+        // A consumer endpoint echoing the challenge
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let consumer = Router::new()
+            .route(
+                "/hooks",
+                post(|Json(challenge): Json<Challenge>| async move { challenge.challenge }),
+            )
+            .route(
+                "/moved",
+                post(|| async { axum::response::Redirect::temporary("/hooks") }),
+            );
+        tokio::spawn(async move { axum::serve(listener, consumer).await });
+
+        let db = crate::DatabaseSettings::in_memory().connect().await.unwrap();
+        let store = WebhookSubscriptions::new(db).allow_http(true).allow_private_networks(true);
+        store.create_table().await.unwrap();
+
+        let created = store
+            .create("acme", request(&url, &["project.created"]))
+            .await
+            .unwrap();
+        assert_eq!(created.subscription.status, WebhookStatus::Active);
+        assert_eq!(created.verification_error, None);
+
+        let unreachable = store
+            .create("acme", request("http://127.0.0.1:9/hooks", &["*"]))
+            .await
+            .unwrap();
+        assert_eq!(unreachable.subscription.status, WebhookStatus::PendingVerification);
+        assert_eq!(unreachable.verification_error.as_deref(), Some(VERIFICATION_FAILED));
+
+        // Redirects aren't followed
+        let moved = url.replace("/hooks", "/moved");
+        let redirected = store.create("acme", request(&moved, &["*"])).await.unwrap();
+        assert_eq!(redirected.subscription.status, WebhookStatus::PendingVerification);
+
+        let subscribers = store.subscribers("project.created").await.unwrap();
+        assert_eq!(subscribers.len(), 1);
+        assert_eq!(subscribers[0].secret, created.secret);
+        assert!(store.subscribers("project.deleted").await.unwrap().is_empty());
+
+        assert_eq!(store.list("acme").await.unwrap().len(), 3);
+        assert!(store.list("globex").await.unwrap().is_empty());
+        let id = created.subscription.id;
+        assert!(store.delete("globex", id).await.is_err());
+        store.delete("acme", id).await.unwrap();
+        assert!(store.subscribers("project.created").await.unwrap().is_empty());
+    }
+}